@@ -41,6 +41,11 @@ mod ipc;
 mod state;
 mod webview;
 
+/// Counts heap allocations so RustKit frame stats can report them.
+#[cfg(all(target_os = "macos", feature = "rustkit", not(feature = "webview-fallback")))]
+#[global_allocator]
+static ALLOCATOR: rustkit_engine::CountingAllocator = rustkit_engine::CountingAllocator::system();
+
 #[cfg(all(target_os = "macos", feature = "rustkit", not(feature = "webview-fallback")))]
 mod webview_rustkit;
 #[cfg(all(target_os = "macos", feature = "rustkit", not(feature = "webview-fallback")))]
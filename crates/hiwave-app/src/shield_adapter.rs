@@ -285,6 +285,8 @@ mod tests {
             timeout: None,
             credentials: Default::default(),
             referrer: None,
+            mode: Default::default(),
+            origin: None,
         }
     }
 
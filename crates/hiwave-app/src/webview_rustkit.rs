@@ -69,7 +69,8 @@ impl RustKitView {
         let mut builder = EngineBuilder::new()
             .user_agent("HiWave/1.0 RustKit/1.0")
             .javascript_enabled(true)
-            .cookies_enabled(true);
+            .cookies_enabled(true)
+            .system_form_theme();
 
         // Add shield interceptor if counter is provided
         let counter_clone = blocked_counter.clone();
@@ -109,6 +110,18 @@ impl RustKitView {
     /// Process pending engine events (call this in the event loop).
     /// Note: This requires a tokio runtime to be available.
     pub fn process_events(&self) {
+        // Fire due page timers, blink carets, advance CSS transitions and
+        // hand over decoded images, then run queued engine work (relayouts
+        // etc.) in a slice short enough to keep input responsive; the rest
+        // runs on the next turn
+        let mut engine = self.engine.borrow_mut();
+        let now = std::time::Instant::now();
+        engine.pump_timers(now);
+        engine.pump_caret_blink(now);
+        engine.pump_animations(now);
+        engine.pump_decoded_images();
+        engine.run_scheduled_tasks(std::time::Duration::from_millis(8));
+
         // For now, event processing is handled by the engine's internal event loop
         // We'll implement proper event handling when we have a tokio runtime in the event loop
         // TODO: Integrate with main event loop's tokio runtime
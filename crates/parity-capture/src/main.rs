@@ -2,13 +2,14 @@
 //!
 //! This tool renders HTML files using RustKit's headless mode and exports:
 //! - PPM frame capture
+//! - PNG capture of the whole laid-out page
 //! - Layout tree JSON
 //! - Performance metrics
 //!
 //! Unlike hiwave-smoke, this does NOT require a display and can run in CI.
 
 use clap::Parser;
-use rustkit_engine::{EngineBuilder, EngineConfig};
+use rustkit_engine::{CaptureOptions, EngineBuilder, EngineConfig};
 use rustkit_viewhost::Bounds;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -34,6 +35,10 @@ struct Args {
     #[arg(long)]
     dump_frame: Option<String>,
 
+    /// Output path for a PNG of the whole laid-out page, beyond the viewport
+    #[arg(long)]
+    dump_full_page: Option<String>,
+
     /// Output path for layout JSON
     #[arg(long)]
     dump_layout: Option<String>,
@@ -50,6 +55,7 @@ struct CaptureResult {
     width: u32,
     height: u32,
     frame_path: Option<String>,
+    full_page_path: Option<String>,
     layout_path: Option<String>,
     layout_stats: Option<LayoutStats>,
     error: Option<String>,
@@ -100,6 +106,7 @@ fn run_capture(args: &Args) -> CaptureResult {
                 width: args.width,
                 height: args.height,
                 frame_path: None,
+                full_page_path: None,
                 layout_path: None,
                 layout_stats: None,
                 error: Some(format!("Failed to read HTML file: {}", e)),
@@ -123,6 +130,7 @@ fn run_capture(args: &Args) -> CaptureResult {
                 width: args.width,
                 height: args.height,
                 frame_path: None,
+                full_page_path: None,
                 layout_path: None,
                 layout_stats: None,
                 error: Some(format!("Failed to create engine: {:?}", e)),
@@ -147,6 +155,7 @@ fn run_capture(args: &Args) -> CaptureResult {
                 width: args.width,
                 height: args.height,
                 frame_path: None,
+                full_page_path: None,
                 layout_path: None,
                 layout_stats: None,
                 error: Some(format!("Failed to create headless view: {:?}", e)),
@@ -162,6 +171,7 @@ fn run_capture(args: &Args) -> CaptureResult {
             width: args.width,
             height: args.height,
             frame_path: None,
+            full_page_path: None,
             layout_path: None,
             layout_stats: None,
             error: Some(format!("Failed to load HTML: {:?}", e)),
@@ -176,6 +186,7 @@ fn run_capture(args: &Args) -> CaptureResult {
             width: args.width,
             height: args.height,
             frame_path: None,
+            full_page_path: None,
             layout_path: None,
             layout_stats: None,
             error: Some(format!("Failed to render: {:?}", e)),
@@ -194,6 +205,19 @@ fn run_capture(args: &Args) -> CaptureResult {
         None
     };
 
+    // Capture the whole page if requested
+    let full_page_path = if let Some(ref path) = args.dump_full_page {
+        let options = CaptureOptions { full_page: true, ..Default::default() };
+        if let Err(e) = engine.capture_frame_png(view_id, &options, Some(path)) {
+            error!("Failed to capture full page: {:?}", e);
+            None
+        } else {
+            Some(path.clone())
+        }
+    } else {
+        None
+    };
+
     // Export layout if requested
     let (layout_path, layout_stats) = if let Some(ref path) = args.dump_layout {
         match engine.export_layout_json(view_id, path) {
@@ -228,6 +252,7 @@ fn run_capture(args: &Args) -> CaptureResult {
         width: args.width,
         height: args.height,
         frame_path,
+        full_page_path,
         layout_path,
         layout_stats,
         error: None,
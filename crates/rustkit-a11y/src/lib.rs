@@ -97,6 +97,7 @@ pub enum Role {
     ListItem,
     Math,
     Note,
+    Paragraph,
     Presentation,
     Separator,
     Toolbar,
@@ -148,6 +149,9 @@ pub enum Role {
     // Generic
     Generic,
     None,
+    /// A run of text, which isn't an ARIA role but how screen readers
+    /// reach a page's text.
+    StaticText,
 }
 
 impl Role {
@@ -171,6 +175,7 @@ impl Role {
             "listitem" => Some(Self::ListItem),
             "math" => Some(Self::Math),
             "note" => Some(Self::Note),
+            "paragraph" => Some(Self::Paragraph),
             "presentation" | "none" => Some(Self::Presentation),
             "separator" => Some(Self::Separator),
             "toolbar" => Some(Self::Toolbar),
@@ -218,6 +223,155 @@ impl Role {
         }
     }
 
+    /// The role's ARIA name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Banner => "banner",
+            Self::Complementary => "complementary",
+            Self::ContentInfo => "contentinfo",
+            Self::Form => "form",
+            Self::Main => "main",
+            Self::Navigation => "navigation",
+            Self::Region => "region",
+            Self::Search => "search",
+            Self::Article => "article",
+            Self::Heading => "heading",
+            Self::Document => "document",
+            Self::Group => "group",
+            Self::Img => "img",
+            Self::List => "list",
+            Self::ListItem => "listitem",
+            Self::Math => "math",
+            Self::Note => "note",
+            Self::Paragraph => "paragraph",
+            Self::Presentation => "presentation",
+            Self::Separator => "separator",
+            Self::Toolbar => "toolbar",
+            Self::Alert => "alert",
+            Self::AlertDialog => "alertdialog",
+            Self::Button => "button",
+            Self::Checkbox => "checkbox",
+            Self::Dialog => "dialog",
+            Self::GridCell => "gridcell",
+            Self::Link => "link",
+            Self::Log => "log",
+            Self::Marquee => "marquee",
+            Self::Menu => "menu",
+            Self::MenuBar => "menubar",
+            Self::MenuItem => "menuitem",
+            Self::MenuItemCheckbox => "menuitemcheckbox",
+            Self::MenuItemRadio => "menuitemradio",
+            Self::Option => "option",
+            Self::ProgressBar => "progressbar",
+            Self::Radio => "radio",
+            Self::RadioGroup => "radiogroup",
+            Self::ScrollBar => "scrollbar",
+            Self::SearchBox => "searchbox",
+            Self::Slider => "slider",
+            Self::SpinButton => "spinbutton",
+            Self::Status => "status",
+            Self::Switch => "switch",
+            Self::Tab => "tab",
+            Self::TabList => "tablist",
+            Self::TabPanel => "tabpanel",
+            Self::TextBox => "textbox",
+            Self::Timer => "timer",
+            Self::Tooltip => "tooltip",
+            Self::Tree => "tree",
+            Self::TreeGrid => "treegrid",
+            Self::TreeItem => "treeitem",
+            Self::Cell => "cell",
+            Self::ColumnHeader => "columnheader",
+            Self::Grid => "grid",
+            Self::Row => "row",
+            Self::RowGroup => "rowgroup",
+            Self::RowHeader => "rowheader",
+            Self::Table => "table",
+            Self::Generic => "generic",
+            Self::None => "none",
+            Self::StaticText => "statictext",
+        }
+    }
+
+    /// The role an HTML element has without a `role` attribute.
+    pub fn implicit(node: &rustkit_dom::Node) -> Self {
+        let Some(tag) = node.tag_name() else {
+            return Self::Generic;
+        };
+        let has = |name: &str| node.get_attribute(name).is_some();
+        match tag.to_ascii_lowercase().as_str() {
+            "a" | "area" if has("href") => Self::Link,
+            "article" => Self::Article,
+            "aside" => Self::Complementary,
+            "button" | "summary" => Self::Button,
+            "dialog" => Self::Dialog,
+            "footer" => Self::ContentInfo,
+            "form" => Self::Form,
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Self::Heading,
+            "header" => Self::Banner,
+            "hr" => Self::Separator,
+            "img" if node.get_attribute("alt") == Some("") => Self::Presentation,
+            "img" | "svg" => Self::Img,
+            "input" => match node.input_type() {
+                rustkit_dom::InputType::Button
+                | rustkit_dom::InputType::Submit
+                | rustkit_dom::InputType::Reset
+                | rustkit_dom::InputType::Image
+                | rustkit_dom::InputType::File => Self::Button,
+                rustkit_dom::InputType::Checkbox => Self::Checkbox,
+                rustkit_dom::InputType::Radio => Self::Radio,
+                rustkit_dom::InputType::Range => Self::Slider,
+                rustkit_dom::InputType::Number => Self::SpinButton,
+                rustkit_dom::InputType::Search => Self::SearchBox,
+                rustkit_dom::InputType::Hidden => Self::None,
+                _ => Self::TextBox,
+            },
+            "li" => Self::ListItem,
+            "main" => Self::Main,
+            "math" => Self::Math,
+            "menu" | "ol" | "ul" => Self::List,
+            "nav" => Self::Navigation,
+            "option" => Self::Option,
+            "p" => Self::Paragraph,
+            "progress" => Self::ProgressBar,
+            "section" if has("aria-label") || has("aria-labelledby") => Self::Region,
+            "select" => Self::Menu,
+            "table" => Self::Table,
+            "tbody" | "thead" | "tfoot" => Self::RowGroup,
+            "td" => Self::Cell,
+            "textarea" => Self::TextBox,
+            "th" if node.get_attribute("scope") == Some("row") => Self::RowHeader,
+            "th" => Self::ColumnHeader,
+            "tr" => Self::Row,
+            _ => Self::Generic,
+        }
+    }
+
+    /// Whether an element with the role is named by its text content
+    /// when nothing else names it.
+    pub fn is_named_from_content(&self) -> bool {
+        matches!(
+            self,
+            Role::Button
+                | Role::Cell
+                | Role::Checkbox
+                | Role::ColumnHeader
+                | Role::GridCell
+                | Role::Heading
+                | Role::Link
+                | Role::MenuItem
+                | Role::MenuItemCheckbox
+                | Role::MenuItemRadio
+                | Role::Option
+                | Role::Radio
+                | Role::RowHeader
+                | Role::Switch
+                | Role::Tab
+                | Role::Tooltip
+                | Role::TreeItem
+        )
+    }
+
     /// Check if focusable by default.
     pub fn is_focusable(&self) -> bool {
         matches!(
@@ -306,6 +460,24 @@ impl State {
             _ => None,
         }
     }
+
+    /// The state's ARIA name, without the `aria-` prefix.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Busy => "busy",
+            Self::Checked => "checked",
+            Self::Disabled => "disabled",
+            Self::Expanded => "expanded",
+            Self::Grabbed => "grabbed",
+            Self::Hidden => "hidden",
+            Self::Invalid => "invalid",
+            Self::Pressed => "pressed",
+            Self::Selected => "selected",
+            Self::Required => "required",
+            Self::ReadOnly => "readonly",
+            Self::Multiselectable => "multiselectable",
+        }
+    }
 }
 
 // ==================== Live Region ====================
@@ -506,6 +678,11 @@ impl AccessibilityTree {
         self.root = Some(id);
     }
 
+    /// Get root node.
+    pub fn root(&self) -> Option<&AccessibleNode> {
+        self.root.and_then(|id| self.nodes.get(&id))
+    }
+
     /// Get node.
     pub fn get(&self, id: AccessibleId) -> Option<&AccessibleNode> {
         self.nodes.get(&id)
@@ -653,6 +830,50 @@ impl AccessibilityTree {
         }
     }
 
+    /// The tree from its root as nested JSON objects, for automated
+    /// accessibility testing. Each node has its `role`, `focusable` and
+    /// `children`, and whichever of `name`, `description`, `value`,
+    /// `states`, `level`, `focused`, `bounds` and `dom_node_id` apply.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.root.map_or(serde_json::Value::Null, |root| self.node_to_json(root))
+    }
+
+    fn node_to_json(&self, id: AccessibleId) -> serde_json::Value {
+        use serde_json::{json, Map, Value};
+
+        let Some(node) = self.nodes.get(&id) else {
+            return Value::Null;
+        };
+        let mut object = Map::new();
+        object.insert("role".into(), json!(node.role.as_str()));
+        for (key, value) in [("name", &node.name), ("description", &node.description), ("value", &node.value)] {
+            if let Some(value) = value {
+                object.insert(key.into(), json!(value));
+            }
+        }
+        if !node.states.is_empty() {
+            let mut states: Vec<&str> = node.states.iter().map(State::as_str).collect();
+            states.sort_unstable();
+            object.insert("states".into(), json!(states));
+        }
+        if let Some(level) = node.level {
+            object.insert("level".into(), json!(level));
+        }
+        object.insert("focusable".into(), json!(node.is_focusable()));
+        if self.focus == Some(id) {
+            object.insert("focused".into(), json!(true));
+        }
+        if let Some((x, y, width, height)) = node.bounds {
+            object.insert("bounds".into(), json!({ "x": x, "y": y, "width": width, "height": height }));
+        }
+        if let Some(dom_id) = node.dom_node_id {
+            object.insert("dom_node_id".into(), json!(dom_id.raw()));
+        }
+        let children: Vec<Value> = node.children.iter().map(|&child| self.node_to_json(child)).collect();
+        object.insert("children".into(), Value::Array(children));
+        Value::Object(object)
+    }
+
     /// Count nodes.
     pub fn len(&self) -> usize {
         self.nodes.len()
@@ -941,6 +1162,53 @@ mod tests {
         assert_eq!(Role::from_str("unknown"), None);
     }
 
+    #[test]
+    fn test_implicit_roles() {
+        let document = rustkit_dom::Document::parse_html(
+            r#"<a id="link" href="/">x</a><a id="anchor">x</a><img id="decoration" alt="">
+               <input id="check" type="checkbox"><input id="email" type="email"><th id="th" scope="row">x</th><p id="p">x</p>"#,
+        )
+        .unwrap();
+        let role = |id: &str| Role::implicit(&document.get_element_by_id(id).unwrap());
+        assert_eq!(role("link"), Role::Link);
+        assert_eq!(role("anchor"), Role::Generic);
+        assert_eq!(role("decoration"), Role::Presentation);
+        assert_eq!(role("check"), Role::Checkbox);
+        assert_eq!(role("email"), Role::TextBox);
+        assert_eq!(role("p"), Role::Paragraph);
+        assert_eq!(Role::implicit(&document.body().unwrap()), Role::Generic);
+        assert_eq!(Role::MenuItemCheckbox.as_str(), "menuitemcheckbox");
+        assert_eq!(Role::from_str(Role::SpinButton.as_str()), Some(Role::SpinButton));
+    }
+
+    #[test]
+    fn test_tree_to_json() {
+        let mut tree = AccessibilityTree::new();
+        let root_id = tree.add_node(AccessibleNode::new(Role::Document));
+        tree.set_root(root_id);
+        let mut heading = AccessibleNode::new(Role::Heading);
+        heading.name = Some("Title".into());
+        heading.level = Some(2);
+        let heading_id = tree.add_node(heading);
+        tree.add_child(root_id, heading_id);
+        let mut button = AccessibleNode::new(Role::Button);
+        button.add_state(State::Pressed);
+        button.add_state(State::Disabled);
+        button.bounds = Some((1.0, 2.0, 30.0, 20.0));
+        let button_id = tree.add_node(button);
+        tree.add_child(root_id, button_id);
+
+        let json = tree.to_json();
+        assert_eq!(json["role"], "document");
+        assert_eq!(json["children"][0]["name"], "Title");
+        assert_eq!(json["children"][0]["level"], 2);
+        assert_eq!(json["children"][0]["focusable"], false);
+        assert_eq!(json["children"][1]["states"], serde_json::json!(["disabled", "pressed"]));
+        assert_eq!(json["children"][1]["bounds"]["width"], 30.0);
+        assert_eq!(json["children"][1]["children"], serde_json::json!([]));
+        assert!(json["children"][1].get("name").is_none());
+    }
+
     #[test]
     fn test_role_focusable() {
         assert!(Role::Button.is_focusable());
@@ -937,6 +937,16 @@ impl PointerLockState {
         }
     }
 
+    /// Refuse the pending lock request.
+    pub fn deny_lock(&mut self) -> Option<NodeId> {
+        self.pending_lock.take()
+    }
+
+    /// Get the element waiting for pointer lock.
+    pub fn pending_lock(&self) -> Option<NodeId> {
+        self.pending_lock
+    }
+
     /// Exit pointer lock.
     pub fn exit_lock(&mut self) -> Option<NodeId> {
         let old = self.locked_element.take();
@@ -1231,6 +1241,13 @@ mod tests {
         let exited = lock.exit_lock();
         assert_eq!(exited, Some(element));
         assert!(!lock.is_locked());
+
+        // Deny
+        assert!(lock.request_lock(other));
+        assert_eq!(lock.pending_lock(), Some(other));
+        assert_eq!(lock.deny_lock(), Some(other));
+        assert_eq!(lock.confirm_lock(), None);
+        assert!(!lock.is_locked());
     }
 }
 
@@ -19,12 +19,16 @@ pub use events::{
     TransitionEventData, WheelDeltaMode, WheelEventData,
 };
 
+use rustkit_core::{DocumentReadyState, GamepadState, PermissionState, ScrollRestoration, VisibilityState};
 use rustkit_dom::{Document, Node, NodeId};
-use rustkit_js::{JsError, JsRuntime, JsValue};
-use std::cell::RefCell;
+use rustkit_js::{JsError, JsRuntime, JsValue, TimerQueue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 use tracing::{debug, trace};
 use url::Url;
@@ -119,6 +123,144 @@ pub enum EventData {
     Input(InputEventBindingData),
 }
 
+/// A pointer lock call a page made, for the engine to act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerLockRequest {
+    /// `element.requestPointerLock()`, with the element's `id`, which is
+    /// empty if it has none.
+    Lock { element_id: String },
+    /// `document.exitPointerLock()`.
+    Exit,
+}
+
+/// A Notifications API call a page made, for the engine to act on.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotificationRequest {
+    /// `Notification.requestPermission()`.
+    Permission,
+    /// `new Notification(title, options)`. Options the page left out are
+    /// empty.
+    Show {
+        id: u64,
+        title: String,
+        body: String,
+        icon: String,
+        tag: String,
+    },
+    /// `notification.close()`.
+    Close { id: u64 },
+}
+
+/// A `setTimeout`, `setInterval` or clear call a page made.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum TimerRequest {
+    Set { id: u64, delay: u64, repeat: bool },
+    Clear { id: u64 },
+}
+
+/// A change a page's script made to its document, for the engine to apply
+/// to the DOM. Nodes are [`NodeId`] raw values.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DomMutation {
+    /// `setAttribute()`, or setting `id` or `className`.
+    SetAttribute { node: usize, name: String, value: String },
+    /// `removeAttribute()`.
+    RemoveAttribute { node: usize, name: String },
+    /// `appendChild()` or `insertBefore()` of nodes made by script, as HTML
+    /// markup. An element inserted alone has a `key` for
+    /// [`DomBindings::bind_node`] to tie it to the node made for it.
+    Insert {
+        parent: usize,
+        before: Option<usize>,
+        markup: String,
+        key: Option<u64>,
+    },
+    /// `appendChild()` or `insertBefore()` of a node already in the DOM.
+    Move { node: usize, parent: usize, before: Option<usize> },
+    /// `removeChild()`.
+    Remove { node: usize },
+    /// Setting `innerHTML` or `textContent`: the children are replaced with
+    /// the markup's nodes.
+    ReplaceChildren { node: usize, markup: String },
+}
+
+/// A request a page made that must go out even if the page goes away:
+/// a `navigator.sendBeacon()`, for the engine to hand to the loader.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeepaliveRequest {
+    /// The URL as the page gave it, possibly relative.
+    pub url: String,
+    pub method: String,
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+/// A `fetch()` or `XMLHttpRequest` a page made, for the engine to load.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FetchRequest {
+    /// Identifies the request to [`DomBindings::resolve_fetch`].
+    pub id: u64,
+    /// The URL as the page gave it, possibly relative.
+    pub url: String,
+    pub method: String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    pub mode: FetchMode,
+    pub credentials: FetchCredentials,
+    /// Whether the request should outlive the page.
+    pub keepalive: bool,
+}
+
+/// A fetch's `mode`: which origins it may reach and read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FetchMode {
+    SameOrigin,
+    Cors,
+    NoCors,
+}
+
+/// A fetch's `credentials`: when it carries cookies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FetchCredentials {
+    Omit,
+    SameOrigin,
+    Include,
+}
+
+/// The response a page's fetch gets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchResponse {
+    pub url: String,
+    pub status: u16,
+    pub status_text: String,
+    /// The headers the page may read, with lowercase names.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub redirected: bool,
+    #[serde(rename = "type")]
+    pub response_type: FetchResponseType,
+}
+
+/// `Response.type`: how much of a response the page may read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchResponseType {
+    /// From the page's own origin.
+    Basic,
+    /// From another origin that allowed it.
+    Cors,
+    /// From another origin with `no-cors`: status 0, nothing to read.
+    Opaque,
+}
+
 /// Location object (window.location).
 #[derive(Debug, Clone)]
 pub struct Location {
@@ -300,6 +442,10 @@ pub struct DomBindings {
     node_map: RefCell<HashMap<u64, Rc<Node>>>,
     /// Queue of IPC messages from JavaScript
     _ipc_queue: RefCell<Vec<IpcMessage>>,
+    /// When the page's timers are due.
+    timers: RefCell<TimerQueue>,
+    /// Nesting level of the timer whose callback is running, or 0.
+    timer_nesting: Cell<u32>,
 }
 
 impl DomBindings {
@@ -316,6 +462,8 @@ impl DomBindings {
             event_listeners: RefCell::new(Vec::new()),
             node_map: RefCell::new(HashMap::new()),
             _ipc_queue: RefCell::new(Vec::new()),
+            timers: RefCell::new(TimerQueue::new()),
+            timer_nesting: Cell::new(0),
         })
     }
 
@@ -352,6 +500,12 @@ impl DomBindings {
                 },
                 history: {
                     length: 1,
+                    _scrollRestoration: 'auto',
+                    get scrollRestoration() { return this._scrollRestoration; },
+                    set scrollRestoration(mode) {
+                        // Values other than the two modes are ignored
+                        if (mode === 'auto' || mode === 'manual') this._scrollRestoration = mode;
+                    },
                     back: function() {},
                     forward: function() {},
                     go: function(delta) {},
@@ -390,8 +544,9 @@ impl DomBindings {
                 prompt: function(msg, def) { console.log('[prompt]', msg); return def || null; }
             };
 
-            // Alias
+            // Aliases
             var self = window;
+            var history = window.history;
         "#;
 
         runtime.evaluate_script(window_js)?;
@@ -435,6 +590,8 @@ impl DomBindings {
                 body: null,
                 title: '',
                 readyState: 'loading',
+                visibilityState: 'visible',
+                hidden: false,
                 cookie: '',
                 domain: '',
                 referrer: '',
@@ -770,155 +927,2568 @@ impl DomBindings {
 
         runtime.evaluate_script(input_element_js)?;
 
-        debug!("Global objects injected");
-        Ok(())
-    }
+        // Range, TreeWalker and NodeIterator over the stub node tree
+        let traversal_js = r#"
+            var NodeFilter = {
+                FILTER_ACCEPT: 1,
+                FILTER_REJECT: 2,
+                FILTER_SKIP: 3,
+                SHOW_ALL: 0xFFFFFFFF,
+                SHOW_ELEMENT: 0x1,
+                SHOW_TEXT: 0x4,
+                SHOW_COMMENT: 0x80,
+                SHOW_DOCUMENT: 0x100,
+                SHOW_DOCUMENT_FRAGMENT: 0x400
+            };
+            window.NodeFilter = NodeFilter;
 
-    /// Set the document.
-    pub fn set_document(&self, document: Rc<Document>) -> Result<(), BindingError> {
-        // Update state
-        self.window.borrow_mut().document = Some(document.clone());
+            function _nodeType(node) {
+                if (node.nodeType) return node.nodeType;
+                if (node === document) return 9;
+                return node.tagName ? 1 : 11;
+            }
+            function _isData(node) {
+                var type = _nodeType(node);
+                return type === 3 || type === 8;
+            }
+            function _kids(node) { return node.children || []; }
+            function _index(node) { return node.parentNode ? _kids(node.parentNode).indexOf(node) : 0; }
+            function _nodeLength(node) { return _isData(node) ? String(node.textContent).length : _kids(node).length; }
+            function _sibling(node, forward) {
+                if (!node.parentNode) return null;
+                return _kids(node.parentNode)[_index(node) + (forward ? 1 : -1)] || null;
+            }
+            function _child(node, forward) {
+                var kids = _kids(node);
+                return (forward ? kids[0] : kids[kids.length - 1]) || null;
+            }
+            function _isInclusiveAncestor(ancestor, node) {
+                for (; node; node = node.parentNode) {
+                    if (node === ancestor) return true;
+                }
+                return false;
+            }
+            function _remove(node) {
+                if (!node.parentNode) return;
+                _kids(node.parentNode).splice(_index(node), 1);
+                node.parentNode = null;
+            }
+            function _insert(parent, node, index) {
+                var nodes = _nodeType(node) === 11 ? _kids(node).slice() : [node];
+                nodes.forEach(function(n, i) {
+                    _remove(n);
+                    _kids(parent).splice(index + i, 0, n);
+                    n.parentNode = parent;
+                });
+                if (_nodeType(node) === 11) node.children = [];
+                return nodes.length;
+            }
+            function _shallowClone(node) {
+                if (_nodeType(node) === 3) return document.createTextNode(node.textContent);
+                if (_nodeType(node) === 11) return document.createDocumentFragment();
+                var copy = document.createElement(node.tagName);
+                for (var name in node.attributes) copy.setAttribute(name, node.attributes[name]);
+                return copy;
+            }
+            function _deepClone(node) {
+                var copy = _shallowClone(node);
+                _kids(node).forEach(function(child) { _insert(copy, _deepClone(child), _kids(copy).length); });
+                return copy;
+            }
+            // Compare boundary points: negative, zero or positive like a sort key
+            function _comparePoints(nodeA, offsetA, nodeB, offsetB) {
+                function path(node, offset) {
+                    var result = [offset];
+                    for (; node.parentNode; node = node.parentNode) result.unshift(_index(node));
+                    return result;
+                }
+                var a = path(nodeA, offsetA), b = path(nodeB, offsetB);
+                for (var i = 0; i < Math.min(a.length, b.length); i++) {
+                    if (a[i] !== b[i]) return a[i] - b[i];
+                }
+                return a.length - b.length;
+            }
+            function _root(node) {
+                while (node.parentNode) node = node.parentNode;
+                return node;
+            }
+            function _filter(walker, node) {
+                if (!(walker.whatToShow & (1 << (_nodeType(node) - 1)))) return NodeFilter.FILTER_SKIP;
+                var filter = walker.filter;
+                if (!filter) return NodeFilter.FILTER_ACCEPT;
+                return typeof filter === 'function' ? filter(node) : filter.acceptNode(node);
+            }
 
-        // Sync to JS
-        let title = document.title().unwrap_or_default();
-        let mut runtime = self.runtime.borrow_mut();
-        runtime.evaluate_script(&format!("document.title = {:?};", title))?;
-        runtime.evaluate_script("document.readyState = 'complete';")?;
+            function TreeWalker(root, whatToShow, filter) {
+                this.root = root;
+                this.whatToShow = whatToShow === undefined ? NodeFilter.SHOW_ALL : whatToShow;
+                this.filter = filter || null;
+                this.currentNode = root;
+            }
+            TreeWalker.prototype.parentNode = function() {
+                var node = this.currentNode;
+                while (node && node !== this.root) {
+                    node = node.parentNode;
+                    if (node && _filter(this, node) === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                }
+                return null;
+            };
+            TreeWalker.prototype._children = function(forward) {
+                var node = _child(this.currentNode, forward);
+                while (node) {
+                    var result = _filter(this, node);
+                    if (result === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                    if (result === NodeFilter.FILTER_SKIP && _child(node, forward)) {
+                        node = _child(node, forward);
+                        continue;
+                    }
+                    while (!_sibling(node, forward)) {
+                        node = node.parentNode;
+                        if (!node || node === this.root || node === this.currentNode) return null;
+                    }
+                    node = _sibling(node, forward);
+                }
+                return null;
+            };
+            TreeWalker.prototype.firstChild = function() { return this._children(true); };
+            TreeWalker.prototype.lastChild = function() { return this._children(false); };
+            TreeWalker.prototype._siblings = function(forward) {
+                var node = this.currentNode;
+                if (node === this.root) return null;
+                while (true) {
+                    var sibling = _sibling(node, forward);
+                    while (sibling) {
+                        node = sibling;
+                        var result = _filter(this, node);
+                        if (result === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                        sibling = _child(node, forward);
+                        if (result === NodeFilter.FILTER_REJECT || !sibling) sibling = _sibling(node, forward);
+                    }
+                    node = node.parentNode;
+                    if (!node || node === this.root || _filter(this, node) === NodeFilter.FILTER_ACCEPT) return null;
+                }
+            };
+            TreeWalker.prototype.nextSibling = function() { return this._siblings(true); };
+            TreeWalker.prototype.previousSibling = function() { return this._siblings(false); };
+            TreeWalker.prototype.previousNode = function() {
+                var node = this.currentNode;
+                while (node !== this.root) {
+                    var sibling = _sibling(node, false);
+                    while (sibling) {
+                        node = sibling;
+                        var result = _filter(this, node);
+                        while (result !== NodeFilter.FILTER_REJECT && _child(node, false)) {
+                            node = _child(node, false);
+                            result = _filter(this, node);
+                        }
+                        if (result === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                        sibling = _sibling(node, false);
+                    }
+                    if (node === this.root || !node.parentNode) return null;
+                    node = node.parentNode;
+                    if (_filter(this, node) === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                }
+                return null;
+            };
+            TreeWalker.prototype.nextNode = function() {
+                var node = this.currentNode;
+                var result = NodeFilter.FILTER_ACCEPT;
+                while (true) {
+                    while (result !== NodeFilter.FILTER_REJECT && _child(node, true)) {
+                        node = _child(node, true);
+                        result = _filter(this, node);
+                        if (result === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                    }
+                    while (node && node !== this.root && !_sibling(node, true)) node = node.parentNode;
+                    if (!node || node === this.root) return null;
+                    node = _sibling(node, true);
+                    result = _filter(this, node);
+                    if (result === NodeFilter.FILTER_ACCEPT) return this.currentNode = node;
+                }
+            };
 
-        // Index elements by ID
-        document.traverse(|node| {
-            if let Some(_id) = node.get_attribute("id") {
-                let node_id = node.id.raw();
-                self.node_map
-                    .borrow_mut()
-                    .insert(node_id as u64, node.clone());
+            function NodeIterator(root, whatToShow, filter) {
+                this.root = root;
+                this.whatToShow = whatToShow === undefined ? NodeFilter.SHOW_ALL : whatToShow;
+                this.filter = filter || null;
+                this.referenceNode = root;
+                this.pointerBeforeReferenceNode = true;
             }
-        });
+            NodeIterator.prototype._traverse = function(forward) {
+                var node = this.referenceNode;
+                var before = this.pointerBeforeReferenceNode;
+                while (true) {
+                    if (forward && before) {
+                        before = false;
+                    } else if (forward) {
+                        var next = _child(node, true);
+                        while (!next && node && node !== this.root) {
+                            next = _sibling(node, true);
+                            node = node.parentNode;
+                        }
+                        if (!next) return null;
+                        node = next;
+                    } else if (before) {
+                        if (node === this.root) return null;
+                        var previous = _sibling(node, false);
+                        if (previous) {
+                            while (_child(previous, false)) previous = _child(previous, false);
+                            node = previous;
+                        } else {
+                            node = node.parentNode;
+                        }
+                    } else {
+                        before = true;
+                    }
+                    if (_filter(this, node) === NodeFilter.FILTER_ACCEPT) break;
+                }
+                this.referenceNode = node;
+                this.pointerBeforeReferenceNode = before;
+                return node;
+            };
+            NodeIterator.prototype.nextNode = function() { return this._traverse(true); };
+            NodeIterator.prototype.previousNode = function() { return this._traverse(false); };
+            NodeIterator.prototype.detach = function() {};
 
-        debug!("Document bound to JS context");
-        Ok(())
-    }
+            function Range() {
+                this.startContainer = document;
+                this.startOffset = 0;
+                this.endContainer = document;
+                this.endOffset = 0;
+            }
+            Object.defineProperty(Range.prototype, 'collapsed', {
+                get: function() { return this.startContainer === this.endContainer && this.startOffset === this.endOffset; }
+            });
+            Object.defineProperty(Range.prototype, 'commonAncestorContainer', {
+                get: function() {
+                    var node = this.startContainer;
+                    while (!_isInclusiveAncestor(node, this.endContainer)) node = node.parentNode;
+                    return node;
+                }
+            });
+            Range.prototype._check = function(node, offset) {
+                if (offset > _nodeLength(node)) throw new Error('IndexSizeError: offset ' + offset + ' is out of range');
+            };
+            Range.prototype.setStart = function(node, offset) {
+                this._check(node, offset);
+                if (_root(node) !== _root(this.endContainer) ||
+                    _comparePoints(node, offset, this.endContainer, this.endOffset) > 0) {
+                    this.endContainer = node;
+                    this.endOffset = offset;
+                }
+                this.startContainer = node;
+                this.startOffset = offset;
+            };
+            Range.prototype.setEnd = function(node, offset) {
+                this._check(node, offset);
+                if (_root(node) !== _root(this.startContainer) ||
+                    _comparePoints(this.startContainer, this.startOffset, node, offset) > 0) {
+                    this.startContainer = node;
+                    this.startOffset = offset;
+                }
+                this.endContainer = node;
+                this.endOffset = offset;
+            };
+            Range.prototype.setStartBefore = function(node) { this.setStart(node.parentNode, _index(node)); };
+            Range.prototype.setStartAfter = function(node) { this.setStart(node.parentNode, _index(node) + 1); };
+            Range.prototype.setEndBefore = function(node) { this.setEnd(node.parentNode, _index(node)); };
+            Range.prototype.setEndAfter = function(node) { this.setEnd(node.parentNode, _index(node) + 1); };
+            Range.prototype.collapse = function(toStart) {
+                if (toStart) {
+                    this.endContainer = this.startContainer;
+                    this.endOffset = this.startOffset;
+                } else {
+                    this.startContainer = this.endContainer;
+                    this.startOffset = this.endOffset;
+                }
+            };
+            Range.prototype.selectNode = function(node) {
+                var index = _index(node);
+                this.startContainer = this.endContainer = node.parentNode;
+                this.startOffset = index;
+                this.endOffset = index + 1;
+            };
+            Range.prototype.selectNodeContents = function(node) {
+                this.startContainer = this.endContainer = node;
+                this.startOffset = 0;
+                this.endOffset = _nodeLength(node);
+            };
+            Range.prototype.cloneRange = function() {
+                var range = new Range();
+                range.startContainer = this.startContainer;
+                range.startOffset = this.startOffset;
+                range.endContainer = this.endContainer;
+                range.endOffset = this.endOffset;
+                return range;
+            };
+            Range.prototype._contains = function(node) {
+                return _root(node) === _root(this.startContainer) &&
+                    _comparePoints(node, 0, this.startContainer, this.startOffset) > 0 &&
+                    _comparePoints(node, _nodeLength(node), this.endContainer, this.endOffset) < 0;
+            };
+            Range.prototype.isPointInRange = function(node, offset) {
+                return _root(node) === _root(this.startContainer) &&
+                    _comparePoints(node, offset, this.startContainer, this.startOffset) >= 0 &&
+                    _comparePoints(node, offset, this.endContainer, this.endOffset) <= 0;
+            };
+            Range.prototype.intersectsNode = function(node) {
+                if (!node.parentNode) return _root(node) === _root(this.startContainer);
+                var index = _index(node);
+                return _root(node) === _root(this.startContainer) &&
+                    _comparePoints(node.parentNode, index, this.endContainer, this.endOffset) < 0 &&
+                    _comparePoints(node.parentNode, index + 1, this.startContainer, this.startOffset) > 0;
+            };
+            Range.prototype._process = function(extract) {
+                var fragment = document.createDocumentFragment();
+                if (this.collapsed) return fragment;
+                var start = this.startContainer, startOffset = this.startOffset;
+                var end = this.endContainer, endOffset = this.endOffset;
+                var data;
 
-    /// Set the current URL.
-    pub fn set_location(&self, url: &Url) -> Result<(), BindingError> {
-        let location = Location::from_url(url);
+                if (start === end && _isData(start)) {
+                    data = String(start.textContent);
+                    var piece = _shallowClone(start);
+                    piece.textContent = data.substring(startOffset, endOffset);
+                    _insert(fragment, piece, 0);
+                    if (extract) {
+                        start.textContent = data.substring(0, startOffset) + data.substring(endOffset);
+                        this.collapse(true);
+                    }
+                    return fragment;
+                }
 
-        // Update state
-        self.window.borrow_mut().location = location.clone();
+                var common = this.commonAncestorContainer;
+                var self = this;
+                function childContaining(node) {
+                    while (node.parentNode !== common) node = node.parentNode;
+                    return node;
+                }
+                var firstPartial = _isInclusiveAncestor(start, end) ? null : childContaining(start);
+                var lastPartial = _isInclusiveAncestor(end, start) ? null : childContaining(end);
+                var contained = _kids(common).filter(function(child) { return self._contains(child); });
 
-        // Sync to JS
-        let mut runtime = self.runtime.borrow_mut();
-        runtime.evaluate_script(&format!(
-            r#"
-            window.location.href = {:?};
-            window.location.protocol = {:?};
-            window.location.host = {:?};
-            window.location.hostname = {:?};
-            window.location.port = {:?};
-            window.location.pathname = {:?};
-            window.location.search = {:?};
-            window.location.hash = {:?};
-            window.location.origin = {:?};
-            document.URL = {:?};
-            "#,
-            location.href,
-            location.protocol,
-            location.host,
-            location.hostname,
-            location.port,
-            location.pathname,
-            location.search,
-            location.hash,
-            location.origin,
-            location.href
-        ))?;
+                var newNode = start, newOffset = startOffset;
+                if (!_isInclusiveAncestor(start, end)) {
+                    var reference = start;
+                    while (!_isInclusiveAncestor(reference.parentNode, end)) reference = reference.parentNode;
+                    newNode = reference.parentNode;
+                    newOffset = _index(reference) + 1;
+                }
 
-        Ok(())
-    }
+                function partial(child, subStart, subStartOffset, subEnd, subEndOffset, keepHead) {
+                    var copy = _shallowClone(child);
+                    if (_isData(child)) {
+                        data = String(child.textContent);
+                        copy.textContent = keepHead ? data.substring(subStartOffset) : data.substring(0, subEndOffset);
+                        if (extract) {
+                            child.textContent = keepHead ? data.substring(0, subStartOffset) : data.substring(subEndOffset);
+                        }
+                    } else {
+                        var subrange = new Range();
+                        subrange.startContainer = subStart;
+                        subrange.startOffset = subStartOffset;
+                        subrange.endContainer = subEnd;
+                        subrange.endOffset = subEndOffset;
+                        _insert(copy, subrange._process(extract), 0);
+                    }
+                    _insert(fragment, copy, _kids(fragment).length);
+                }
 
-    /// Set window dimensions.
-    pub fn set_dimensions(&self, width: f64, height: f64) -> Result<(), BindingError> {
-        let mut window = self.window.borrow_mut();
-        window.inner_width = width;
-        window.inner_height = height;
-        window.outer_width = width;
-        window.outer_height = height;
-        drop(window);
+                if (firstPartial) partial(firstPartial, start, startOffset, firstPartial, _nodeLength(firstPartial), true);
+                contained.forEach(function(child) {
+                    _insert(fragment, extract ? child : _deepClone(child), _kids(fragment).length);
+                });
+                if (lastPartial) partial(lastPartial, lastPartial, 0, end, endOffset, false);
 
-        let mut runtime = self.runtime.borrow_mut();
-        runtime.evaluate_script(&format!(
-            "window.innerWidth = {}; window.innerHeight = {}; \
-             window.outerWidth = {}; window.outerHeight = {};",
-            width, height, width, height
-        ))?;
+                if (extract) {
+                    this.startContainer = this.endContainer = newNode;
+                    this.startOffset = this.endOffset = newOffset;
+                }
+                return fragment;
+            };
+            Range.prototype.extractContents = function() { return this._process(true); };
+            Range.prototype.cloneContents = function() { return this._process(false); };
+            Range.prototype.deleteContents = function() { this._process(true); };
+            Range.prototype.insertNode = function(node) {
+                var parent = this.startContainer, index = this.startOffset;
+                if (_isData(parent)) {
+                    var data = String(parent.textContent);
+                    var tail = _shallowClone(parent);
+                    tail.textContent = data.substring(index);
+                    parent.textContent = data.substring(0, index);
+                    index = _index(parent) + 1;
+                    parent = parent.parentNode;
+                    _insert(parent, tail, index);
+                }
+                var collapsed = this.collapsed;
+                var inserted = _insert(parent, node, index);
+                if (collapsed) {
+                    this.endContainer = parent;
+                    this.endOffset = index + inserted;
+                }
+            };
+            Range.prototype.toString = function() {
+                var start = this.startContainer, end = this.endContainer;
+                if (start === end && _nodeType(start) === 3) {
+                    return String(start.textContent).substring(this.startOffset, this.endOffset);
+                }
+                var text = _nodeType(start) === 3 ? String(start.textContent).substring(this.startOffset) : '';
+                var self = this;
+                (function walk(node) {
+                    _kids(node).forEach(function(child) {
+                        if (_nodeType(child) === 3 && self._contains(child)) text += child.textContent;
+                        walk(child);
+                    });
+                })(this.commonAncestorContainer);
+                if (_nodeType(end) === 3) text += String(end.textContent).substring(0, this.endOffset);
+                return text;
+            };
+            // Rects come from the engine's layout tree once it installs
+            // `window.__rangeClientRects`
+            Range.prototype.getClientRects = function() {
+                return window.__rangeClientRects ? window.__rangeClientRects(this) : [];
+            };
+            Range.prototype.getBoundingClientRect = function() {
+                var rects = this.getClientRects();
+                if (!rects.length) return { x: 0, y: 0, width: 0, height: 0, top: 0, left: 0, right: 0, bottom: 0 };
+                var left = Infinity, top = Infinity, right = -Infinity, bottom = -Infinity;
+                rects.forEach(function(r) {
+                    left = Math.min(left, r.x);
+                    top = Math.min(top, r.y);
+                    right = Math.max(right, r.x + r.width);
+                    bottom = Math.max(bottom, r.y + r.height);
+                });
+                return { x: left, y: top, width: right - left, height: bottom - top, top: top, left: left, right: right, bottom: bottom };
+            };
+            Range.prototype.detach = function() {};
+            window.Range = Range;
 
-        Ok(())
-    }
+            document.createRange = function() { return new Range(); };
+            document.createTreeWalker = function(root, whatToShow, filter) {
+                return new TreeWalker(root, whatToShow, filter);
+            };
+            document.createNodeIterator = function(root, whatToShow, filter) {
+                return new NodeIterator(root, whatToShow, filter);
+            };
+        "#;
 
-    /// Evaluate a script in the bound context.
-    pub fn evaluate(&self, script: &str) -> Result<JsValue, BindingError> {
-        self.runtime
-            .borrow_mut()
-            .evaluate_script(script)
-            .map_err(Into::into)
-    }
+        runtime.evaluate_script(traversal_js)?;
 
-    /// Drain the IPC message queue.
-    ///
-    /// This method collects all IPC messages that were queued via
-    /// `window.ipc.postMessage()` since the last drain call.
-    ///
-    /// Returns a Vec of IpcMessage structs.
-    pub fn drain_ipc_queue(&self) -> Vec<IpcMessage> {
-        // Call JS to drain the queue and get JSON
-        let result = self.runtime
-            .borrow_mut()
-            .evaluate_script("window.__drainIpcQueue()");
+        // DOMParser and XMLSerializer over stub nodes. XML types parse
+        // strictly; text/html parses leniently
+        let dom_parser_js = r#"
+            var _XHTML_NS = 'http://www.w3.org/1999/xhtml';
+            var _VOID_ELEMENTS = ['area', 'base', 'br', 'col', 'embed', 'hr', 'img', 'input', 'link', 'meta', 'source', 'track', 'wbr'];
 
-        match result {
-            Ok(JsValue::String(json)) => {
-                // Parse the JSON array
-                match serde_json::from_str::<Vec<String>>(&json) {
-                    Ok(messages) => {
-                        messages
-                            .into_iter()
-                            .map(|payload| IpcMessage { payload })
-                            .collect()
+            function _markupNode(props) {
+                var node = { parentNode: null, children: [] };
+                for (var key in props) node[key] = props[key];
+                return node;
+            }
+            function _markupElement(name, namespaceURI, attributes, html) {
+                var colon = name.indexOf(':');
+                var element = _markupNode({
+                    tagName: html ? name.toUpperCase() : name,
+                    localName: colon >= 0 ? name.substring(colon + 1) : name,
+                    prefix: colon >= 0 ? name.substring(0, colon) : null,
+                    namespaceURI: namespaceURI,
+                    attributes: attributes,
+                    id: attributes.id || '',
+                    className: attributes['class'] || '',
+                    style: {},
+                    getAttribute: function(n) { return n in this.attributes ? this.attributes[n] : null; },
+                    setAttribute: function(n, v) { this.attributes[n] = String(v); },
+                    hasAttribute: function(n) { return n in this.attributes; },
+                    removeAttribute: function(n) { delete this.attributes[n]; },
+                    appendChild: function(child) { _insert(this, child, this.children.length); return child; },
+                    removeChild: function(child) { _remove(child); return child; },
+                    getElementsByTagName: function(n) { return _elementsByTagName(this, n); },
+                    addEventListener: function(type, callback, options) {},
+                    removeEventListener: function(type, callback, options) {}
+                });
+                Object.defineProperty(element, 'textContent', {
+                    get: function() {
+                        return this.children.map(function(c) { return _nodeType(c) === 8 ? '' : c.textContent; }).join('');
+                    },
+                    set: function(value) { this.children = [document.createTextNode(String(value))]; }
+                });
+                return element;
+            }
+            function _elementsByTagName(root, name) {
+                var found = [];
+                (function walk(node) {
+                    _kids(node).forEach(function(child) {
+                        if (_nodeType(child) === 1 && (name === '*' || child.tagName === name ||
+                            child.localName === name || (child.tagName === name.toUpperCase() && child.namespaceURI === _XHTML_NS))) {
+                            found.push(child);
+                        }
+                        walk(child);
+                    });
+                })(root);
+                return found;
+            }
+            function _decodeReferences(text, strict) {
+                return text.replace(/&(#[xX][0-9a-fA-F]+|#[0-9]+|[A-Za-z][A-Za-z0-9]*);|&/g, function(match, ref) {
+                    var named = { lt: '<', gt: '>', amp: '&', quot: '"', apos: "'", nbsp: '\u00a0' };
+                    if (ref === undefined) {
+                        if (strict) throw new Error('unescaped &');
+                        return match;
                     }
-                    Err(e) => {
-                        trace!(error = %e, "Failed to parse IPC queue JSON");
-                        Vec::new()
+                    if (ref.charAt(0) === '#') {
+                        var hex = ref.charAt(1) === 'x' || ref.charAt(1) === 'X';
+                        return String.fromCodePoint(parseInt(ref.substring(hex ? 2 : 1), hex ? 16 : 10));
+                    }
+                    if (ref in named && (!strict || ref !== 'nbsp')) return named[ref];
+                    if (strict) throw new Error('undefined entity &' + ref + ';');
+                    return match;
+                });
+            }
+            function _parseMarkup(source, contentType) {
+                var html = contentType === 'text/html';
+                var doc = _markupNode({
+                    nodeType: 9,
+                    contentType: contentType,
+                    getElementsByTagName: function(n) { return _elementsByTagName(this, n); },
+                    getElementById: function(id) {
+                        var all = _elementsByTagName(this, '*');
+                        for (var i = 0; i < all.length; i++) if (all[i].attributes.id === id) return all[i];
+                        return null;
+                    },
+                    querySelector: function(selector) { return this.getElementsByTagName(selector)[0] || null; }
+                });
+                Object.defineProperty(doc, 'documentElement', {
+                    get: function() { return this.children.filter(function(c) { return _nodeType(c) === 1; })[0] || null; }
+                });
+                var stack = [doc];
+                var scopes = [{ xml: 'http://www.w3.org/XML/1998/namespace' }];
+                var pos = 0;
+                function top() { return stack[stack.length - 1]; }
+                function add(node) { _insert(top(), node, top().children.length); }
+                function text(data) {
+                    if (top() === doc && !html && /\S/.test(data)) throw new Error('content outside the document element');
+                    if (!data || (top() === doc && !/\S/.test(data))) return;
+                    add(_markupNode({ nodeType: 3, textContent: data }));
+                }
+                function until(terminator) {
+                    var end = source.indexOf(terminator, pos);
+                    if (end < 0) throw new Error("expected '" + terminator + "'");
+                    var data = source.substring(pos, end);
+                    pos = end + terminator.length;
+                    return data;
+                }
+                function lookup(prefix) {
+                    for (var i = scopes.length - 1; i >= 0; i--) {
+                        if (prefix in scopes[i]) return scopes[i][prefix] || null;
+                    }
+                    return null;
+                }
+
+                while (pos < source.length) {
+                    if (source.startsWith('<!--', pos)) {
+                        pos += 4;
+                        add(_markupNode({ nodeType: 8, textContent: until('-->') }));
+                    } else if (source.startsWith('<![CDATA[', pos) && !html) {
+                        pos += 9;
+                        text(until(']]>'));
+                    } else if (source.startsWith('<?', pos)) {
+                        pos += 2;
+                        until('?>');
+                    } else if (source.startsWith('<!', pos)) {
+                        var internal = source.indexOf('[', pos), close = source.indexOf('>', pos);
+                        pos = internal >= 0 && internal < close ? source.indexOf(']', internal) : pos;
+                        pos = source.indexOf('>', pos) + 1;
+                        if (pos === 0) throw new Error('unterminated declaration');
+                    } else if (source.startsWith('</', pos)) {
+                        var end = /^<\/([^\s>]+)\s*>/.exec(source.substring(pos));
+                        if (!end) throw new Error('malformed end tag');
+                        pos += end[0].length;
+                        var name = html ? end[1].toLowerCase() : end[1];
+                        if (html) {
+                            for (var i = stack.length - 1; i > 0; i--) {
+                                if (stack[i].localName === name) {
+                                    stack.length = i;
+                                    scopes.length = i;
+                                    break;
+                                }
+                            }
+                        } else {
+                            if (stack.length < 2 || top().tagName !== name) throw new Error('</' + name + '> does not match');
+                            stack.pop();
+                            scopes.pop();
+                        }
+                    } else if (source.charAt(pos) === '<' && /[A-Za-z_:]/.test(source.charAt(pos + 1))) {
+                        var tag = /^<([^\s\/>]+)((?:\s+[^\s=\/>]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(\/?)>/.exec(source.substring(pos));
+                        if (!tag) throw new Error('malformed start tag');
+                        pos += tag[0].length;
+                        var tagName = html ? tag[1].toLowerCase() : tag[1];
+                        var attributes = {}, scope = {};
+                        var attrPattern = /([^\s=\/>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?/g, attr;
+                        while ((attr = attrPattern.exec(tag[2]))) {
+                            var quoted = attr[2] !== undefined ? attr[2] : attr[3];
+                            if (!html && quoted === undefined) throw new Error('attribute ' + attr[1] + ' needs a quoted value');
+                            var attrName = html ? attr[1].toLowerCase() : attr[1];
+                            if (!html && attrName in attributes) throw new Error('duplicate attribute ' + attrName);
+                            var value = _decodeReferences(quoted !== undefined ? quoted : (attr[4] || ''), !html);
+                            attributes[attrName] = value;
+                            if (attrName === 'xmlns') scope[''] = value;
+                            else if (attrName.indexOf('xmlns:') === 0) scope[attrName.substring(6)] = value;
+                        }
+                        if (!html && top() === doc && doc.documentElement) throw new Error('junk after document element');
+                        scopes.push(scope);
+                        var colon = tagName.indexOf(':');
+                        var namespaceURI = html ? _XHTML_NS : lookup(colon >= 0 ? tagName.substring(0, colon) : '');
+                        if (!html && colon >= 0 && !namespaceURI) throw new Error('unbound prefix in <' + tagName + '>');
+                        var element = _markupElement(tagName, namespaceURI, attributes, html);
+                        add(element);
+                        if (tag[3] || (html && _VOID_ELEMENTS.indexOf(tagName) >= 0)) {
+                            scopes.pop();
+                        } else {
+                            stack.push(element);
+                        }
+                    } else {
+                        if (!html && source.charAt(pos) === '<') throw new Error("unescaped '<'");
+                        var next = source.indexOf('<', pos + 1);
+                        if (next < 0) next = source.length;
+                        text(_decodeReferences(source.substring(pos, next), !html));
+                        pos = next;
                     }
                 }
+
+                if (!html) {
+                    if (stack.length > 1) throw new Error('unclosed element <' + top().tagName + '>');
+                    if (!doc.documentElement) throw new Error('no document element');
+                    return doc;
+                }
+
+                // Give HTML the html/head/body skeleton
+                var root = doc.documentElement;
+                if (!root || root.localName !== 'html') {
+                    var content = doc.children.slice();
+                    root = _markupElement('html', _XHTML_NS, {}, true);
+                    var head = _markupElement('head', _XHTML_NS, {}, true);
+                    var body = _markupElement('body', _XHTML_NS, {}, true);
+                    var inHead = true;
+                    content.forEach(function(node) {
+                        inHead = inHead && ['title', 'meta', 'link', 'style', 'base'].indexOf(node.localName) >= 0;
+                        var parent = inHead ? head : body;
+                        _insert(parent, node, parent.children.length);
+                    });
+                    _insert(root, head, 0);
+                    _insert(root, body, 1);
+                    _insert(doc, root, doc.children.length);
+                }
+                doc.head = _elementsByTagName(doc, 'head')[0] || null;
+                doc.body = _elementsByTagName(doc, 'body')[0] || null;
+                var title = _elementsByTagName(doc, 'title')[0];
+                doc.title = title ? title.textContent : '';
+                return doc;
             }
-            Ok(_) => {
-                trace!("IPC queue returned non-string value");
-                Vec::new()
+
+            function DOMParser() {}
+            DOMParser.prototype.parseFromString = function(source, type) {
+                var types = ['text/html', 'text/xml', 'application/xml', 'application/xhtml+xml', 'image/svg+xml'];
+                if (types.indexOf(type) < 0) throw new TypeError('Unsupported type: ' + type);
+                source = String(source);
+                try {
+                    return _parseMarkup(source, type);
+                } catch (e) {
+                    // Malformed XML yields a document holding a <parsererror>
+                    var doc = _parseMarkup('<parsererror/>', type);
+                    var error = doc.documentElement;
+                    error.namespaceURI = 'http://www.mozilla.org/newlayout/xml/parsererror.xml';
+                    error.appendChild(document.createTextNode('XML Parsing Error: ' + e.message));
+                    return doc;
+                }
+            };
+            window.DOMParser = DOMParser;
+
+            function _escapeMarkup(text, attribute) {
+                text = String(text).replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+                return attribute ? text.replace(/"/g, '&quot;') : text;
             }
-            Err(e) => {
-                trace!(error = %e, "Failed to drain IPC queue");
-                Vec::new()
+            function _serialize(node, parentNamespace) {
+                var type = _nodeType(node);
+                if (type === 3) return _escapeMarkup(node.textContent, false);
+                if (type === 8) return '<!--' + node.textContent + '-->';
+                if (type !== 1) {
+                    return _kids(node).map(function(c) { return _serialize(c, parentNamespace); }).join('');
+                }
+                var namespaceURI = node.namespaceURI === undefined ? _XHTML_NS : node.namespaceURI;
+                var name = namespaceURI === _XHTML_NS ? (node.localName || node.tagName.toLowerCase()) : node.tagName;
+                var markup = '<' + name;
+                var attributes = node.attributes || {};
+                if (!node.prefix && namespaceURI !== parentNamespace && !('xmlns' in attributes)) {
+                    markup += ' xmlns="' + _escapeMarkup(namespaceURI || '', true) + '"';
+                }
+                Object.keys(attributes).sort().forEach(function(attrName) {
+                    markup += ' ' + attrName + '="' + _escapeMarkup(attributes[attrName], true) + '"';
+                });
+                var kids = _kids(node);
+                if (!kids.length) return markup + '/>';
+                return markup + '>' + kids.map(function(c) { return _serialize(c, namespaceURI); }).join('') + '</' + name + '>';
             }
-        }
-    }
 
-    /// Check if there are pending IPC messages.
-    pub fn has_pending_ipc(&self) -> bool {
-        let result = self.runtime
-            .borrow_mut()
-            .evaluate_script("window.__ipcQueue.length > 0");
+            function XMLSerializer() {}
+            XMLSerializer.prototype.serializeToString = function(node) { return _serialize(node, null); };
+            window.XMLSerializer = XMLSerializer;
+        "#;
 
-        matches!(result, Ok(JsValue::Boolean(true)))
-    }
+        runtime.evaluate_script(dom_parser_js)?;
 
-    /// Add an event listener.
-    pub fn add_event_listener(
-        &self,
-        node_id: NodeId,
-        event_type: &str,
-        callback: &str,
-        capture: bool,
-    ) -> ListenerId {
+        // HTMLDialogElement over stub nodes. Modal dialogs join
+        // document._topLayer, topmost last
+        let dialog_js = r#"
+            document._topLayer = [];
+
+            function _makeDialog(dialog) {
+                dialog.open = false;
+                dialog.returnValue = '';
+                dialog._modal = false;
+
+                dialog.show = function() {
+                    if (this.open) {
+                        if (this._modal) throw new Error('InvalidStateError: dialog is already open as a modal');
+                        return;
+                    }
+                    this.open = true;
+                    this.setAttribute('open', '');
+                };
+                dialog.showModal = function() {
+                    if (this.open) {
+                        if (!this._modal) throw new Error('InvalidStateError: dialog is already open');
+                        return;
+                    }
+                    this.open = true;
+                    this._modal = true;
+                    this.setAttribute('open', '');
+                    document._topLayer.push(this);
+                };
+                dialog.close = function(returnValue) {
+                    if (!this.open) return;
+                    if (returnValue !== undefined) this.returnValue = String(returnValue);
+                    this.open = false;
+                    this.removeAttribute('open');
+                    if (this._modal) {
+                        this._modal = false;
+                        var index = document._topLayer.indexOf(this);
+                        if (index >= 0) document._topLayer.splice(index, 1);
+                    }
+                    if (typeof this.onclose === 'function') this.onclose({ type: 'close', target: this });
+                };
+                return dialog;
+            }
+
+            var _createElementBeforeDialog = document.createElement;
+            document.createElement = function(tagName) {
+                var element = _createElementBeforeDialog(tagName);
+                return element.tagName === 'DIALOG' ? _makeDialog(element) : element;
+            };
+        "#;
+
+        runtime.evaluate_script(dialog_js)?;
+
+        // Popover API: elements with a popover attribute join the top layer
+        let popover_js = r#"
+            function _popoverState(element) {
+                if (!element.attributes || !('popover' in element.attributes)) return null;
+                var value = String(element.attributes.popover).toLowerCase();
+                return value === '' || value === 'auto' ? 'auto' : 'manual';
+            }
+
+            function _containsNode(ancestor, node) {
+                for (; node; node = node.parentNode) {
+                    if (node === ancestor) return true;
+                }
+                return false;
+            }
+
+            function _firePopoverToggle(popover, newState) {
+                var oldState = newState === 'open' ? 'closed' : 'open';
+                var event = { type: 'toggle', oldState: oldState, newState: newState, target: popover };
+                if (typeof popover.ontoggle === 'function') popover.ontoggle(event);
+            }
+
+            function _makePopoverHost(element) {
+                element._popoverOpen = false;
+
+                Object.defineProperty(element, 'popover', {
+                    get: function() { return _popoverState(this); },
+                    set: function(value) {
+                        if (value === null) this.removeAttribute('popover');
+                        else this.setAttribute('popover', String(value));
+                    }
+                });
+                element.showPopover = function() {
+                    var state = _popoverState(this);
+                    if (state === null) throw new Error('NotSupportedError: element is not a popover');
+                    if (this._popoverOpen) return;
+                    if (state === 'auto') {
+                        var popover = this;
+                        for (var i = document._topLayer.length - 1; i >= 0; i--) {
+                            var open = document._topLayer[i];
+                            if (_popoverState(open) === 'auto' && !_containsNode(open, popover)) open.hidePopover();
+                        }
+                    }
+                    this._popoverOpen = true;
+                    document._topLayer.push(this);
+                    _firePopoverToggle(this, 'open');
+                };
+                element.hidePopover = function() {
+                    if (!this._popoverOpen) return;
+                    var index = document._topLayer.indexOf(this);
+                    if (_popoverState(this) === 'auto') {
+                        for (var i = document._topLayer.length - 1; i > index; i--) {
+                            var above = document._topLayer[i];
+                            if (_popoverState(above) === 'auto') above.hidePopover();
+                        }
+                    }
+                    this._popoverOpen = false;
+                    index = document._topLayer.indexOf(this);
+                    if (index >= 0) document._topLayer.splice(index, 1);
+                    _firePopoverToggle(this, 'closed');
+                };
+                element.togglePopover = function(force) {
+                    var show = force === undefined ? !this._popoverOpen : !!force;
+                    if (show) this.showPopover();
+                    else this.hidePopover();
+                    return this._popoverOpen;
+                };
+                return element;
+            }
+
+            var _createElementBeforePopover = document.createElement;
+            document.createElement = function(tagName) {
+                return _makePopoverHost(_createElementBeforePopover(tagName));
+            };
+        "#;
+
+        runtime.evaluate_script(popover_js)?;
+
+        // Window and document event listeners, for events the engine fires
+        // at them
+        let event_target_js = r#"
+            function _makeEventTarget(target) {
+                target._listeners = {};
+                target.addEventListener = function(type, callback, options) {
+                    var listeners = this._listeners[type] || (this._listeners[type] = []);
+                    if (listeners.indexOf(callback) < 0) listeners.push(callback);
+                };
+                target.removeEventListener = function(type, callback, options) {
+                    var listeners = this._listeners[type] || [];
+                    var index = listeners.indexOf(callback);
+                    if (index >= 0) listeners.splice(index, 1);
+                };
+                target.dispatchEvent = function(event) {
+                    event.target = this;
+                    event.currentTarget = this;
+                    var handler = this['on' + event.type.toLowerCase()];
+                    if (typeof handler === 'function') handler.call(this, event);
+                    var listeners = (this._listeners[event.type] || []).slice();
+                    for (var i = 0; i < listeners.length; i++) {
+                        var listener = listeners[i];
+                        if (typeof listener === 'function') listener.call(this, event);
+                        else if (listener && typeof listener.handleEvent === 'function') listener.handleEvent(event);
+                    }
+                    return !event.defaultPrevented;
+                };
+            }
+
+            _makeEventTarget(window);
+            _makeEventTarget(document);
+        "#;
+
+        runtime.evaluate_script(event_target_js)?;
+
+        // Pointer Lock API: requests queue for the engine, which asks the
+        // host and reports back with pointerlockchange or pointerlockerror
+        let pointer_lock_js = r#"
+            window.__pointerLockQueue = [];
+            document.pointerLockElement = null;
+            document._pointerLockPending = null;
+
+            function _makePointerLockTarget(element) {
+                element.requestPointerLock = function() {
+                    if (document.pointerLockElement === this || document._pointerLockPending) return;
+                    document._pointerLockPending = this;
+                    window.__pointerLockQueue.push(String(this.id || ''));
+                };
+                return element;
+            }
+
+            document.exitPointerLock = function() {
+                if (document.pointerLockElement) window.__pointerLockQueue.push(null);
+            };
+
+            window.__drainPointerLockQueue = function() {
+                var queue = window.__pointerLockQueue;
+                window.__pointerLockQueue = [];
+                return JSON.stringify(queue);
+            };
+
+            var _createElementBeforePointerLock = document.createElement;
+            document.createElement = function(tagName) {
+                return _makePointerLockTarget(_createElementBeforePointerLock(tagName));
+            };
+        "#;
+
+        runtime.evaluate_script(pointer_lock_js)?;
+
+        // Gamepad API over the state the engine pushes in
+        let gamepad_js = r#"
+            var navigator = window.navigator;
+            navigator._gamepads = [];
+            navigator.getGamepads = function() { return this._gamepads.slice(); };
+
+            window.__setGamepad = function(index, gamepad) {
+                var gamepads = navigator._gamepads;
+                var previous = gamepads[index] || null;
+                while (gamepads.length <= index) gamepads.push(null);
+                if (gamepad) {
+                    gamepad.index = index;
+                    gamepad.connected = true;
+                }
+                gamepads[index] = gamepad;
+                while (gamepads.length && !gamepads[gamepads.length - 1]) gamepads.pop();
+
+                if (!previous && gamepad) {
+                    window.dispatchEvent({ type: 'gamepadconnected', bubbles: false, cancelable: false,
+                        defaultPrevented: false, gamepad: gamepad });
+                } else if (previous && !gamepad) {
+                    previous.connected = false;
+                    window.dispatchEvent({ type: 'gamepaddisconnected', bubbles: false, cancelable: false,
+                        defaultPrevented: false, gamepad: previous });
+                }
+            };
+        "#;
+
+        runtime.evaluate_script(gamepad_js)?;
+
+        // Notifications API: the engine shows notifications through the host
+        // and routes the user's clicks back with __dispatchNotificationEvent
+        let notification_js = r#"
+            window.__notificationQueue = [];
+            window.__notifications = {};
+            window.__nextNotificationId = 1;
+
+            function Notification(title, options) {
+                options = options || {};
+                this.title = String(title);
+                this.body = options.body === undefined ? '' : String(options.body);
+                this.icon = options.icon === undefined ? '' : String(options.icon);
+                this.tag = options.tag === undefined ? '' : String(options.tag);
+                this.data = options.data === undefined ? null : options.data;
+                this._id = window.__nextNotificationId++;
+                _makeEventTarget(this);
+                window.__notifications[this._id] = this;
+                window.__notificationQueue.push({ type: 'show', id: this._id, title: this.title,
+                    body: this.body, icon: this.icon, tag: this.tag });
+            }
+
+            Notification.permission = 'default';
+            Notification._permissionCallbacks = [];
+
+            Notification.requestPermission = function(callback) {
+                return new Promise(function(resolve) {
+                    var settle = function(permission) {
+                        if (typeof callback === 'function') callback(permission);
+                        resolve(permission);
+                    };
+                    if (Notification.permission !== 'default') {
+                        settle(Notification.permission);
+                        return;
+                    }
+                    if (!Notification._permissionCallbacks.length) {
+                        window.__notificationQueue.push({ type: 'permission' });
+                    }
+                    Notification._permissionCallbacks.push(settle);
+                });
+            };
+
+            Notification.prototype.close = function() {
+                if (!window.__notifications[this._id]) return;
+                window.__notificationQueue.push({ type: 'close', id: this._id });
+                window.__dispatchNotificationEvent(this._id, 'close');
+            };
+
+            window.Notification = Notification;
+
+            window.__drainNotificationQueue = function() {
+                var queue = window.__notificationQueue;
+                window.__notificationQueue = [];
+                return JSON.stringify(queue);
+            };
+
+            window.__setNotificationPermission = function(permission, settle) {
+                Notification.permission = permission;
+                if (!settle) return;
+                var callbacks = Notification._permissionCallbacks;
+                Notification._permissionCallbacks = [];
+                for (var i = 0; i < callbacks.length; i++) callbacks[i](permission);
+            };
+
+            window.__dispatchNotificationEvent = function(id, type) {
+                var notification = window.__notifications[id];
+                if (!notification) return;
+                if (type === 'close' || type === 'error') delete window.__notifications[id];
+                notification.dispatchEvent({ type: type, bubbles: false, cancelable: type === 'click',
+                    defaultPrevented: false });
+            };
+        "#;
+
+        runtime.evaluate_script(notification_js)?;
+
+        // Beacons queue for the engine, which sends them from the loader so
+        // they outlive the page
+        let beacon_js = r#"
+            window.__keepaliveQueue = [];
+
+            function _utf8Length(text) {
+                var length = 0;
+                for (var i = 0; i < text.length; i++) {
+                    var code = text.charCodeAt(i);
+                    if (code < 0x80) length += 1;
+                    else if (code < 0x800) length += 2;
+                    else if (code >= 0xd800 && code < 0xdc00) { length += 4; i++; }
+                    else length += 3;
+                }
+                return length;
+            }
+
+            navigator.sendBeacon = function(url, data) {
+                var body = data === undefined || data === null ? '' : String(data);
+                if (_utf8Length(body) > 65536) return false;
+                window.__keepaliveQueue.push({ url: String(url), method: 'POST',
+                    contentType: typeof data === 'string' ? 'text/plain;charset=UTF-8' : null, body: body });
+                return true;
+            };
+
+            window.__drainKeepaliveQueue = function() {
+                var queue = window.__keepaliveQueue;
+                window.__keepaliveQueue = [];
+                return JSON.stringify(queue);
+            };
+        "#;
+
+        runtime.evaluate_script(beacon_js)?;
+
+        // fetch() and XMLHttpRequest queue requests for the engine, which
+        // loads them and settles them with __resolveFetch or __rejectFetch
+        let fetch_js = r#"
+            function Headers(init) {
+                this._list = [];
+                if (init instanceof Headers) init = init._list;
+                if (Array.isArray(init)) {
+                    for (var i = 0; i < init.length; i++) this.append(init[i][0], init[i][1]);
+                } else if (init) {
+                    for (var name in init) {
+                        if (Object.prototype.hasOwnProperty.call(init, name)) this.append(name, init[name]);
+                    }
+                }
+            }
+            Headers.prototype.append = function(name, value) {
+                this._list.push([String(name).toLowerCase(), String(value)]);
+            };
+            Headers.prototype.delete = function(name) {
+                name = String(name).toLowerCase();
+                this._list = this._list.filter(function(header) { return header[0] !== name; });
+            };
+            Headers.prototype.set = function(name, value) {
+                this.delete(name);
+                this.append(name, value);
+            };
+            Headers.prototype.get = function(name) {
+                name = String(name).toLowerCase();
+                var values = this._list
+                    .filter(function(header) { return header[0] === name; })
+                    .map(function(header) { return header[1]; });
+                return values.length ? values.join(', ') : null;
+            };
+            Headers.prototype.has = function(name) { return this.get(name) !== null; };
+            Headers.prototype.forEach = function(callback, thisArg) {
+                for (var i = 0; i < this._list.length; i++) {
+                    callback.call(thisArg, this._list[i][1], this._list[i][0], this);
+                }
+            };
+            window.Headers = Headers;
+
+            function Response(body, init) {
+                init = init || {};
+                this._body = body === undefined || body === null ? '' : String(body);
+                this.status = init.status === undefined ? 200 : init.status;
+                this.statusText = init.statusText || '';
+                this.headers = new Headers(init.headers);
+                this.ok = this.status >= 200 && this.status < 300;
+                this.url = init.url || '';
+                this.redirected = !!init.redirected;
+                this.type = init.type || 'default';
+                this.bodyUsed = false;
+            }
+            Response.prototype._consume = function() {
+                if (this.bodyUsed) return Promise.reject(new TypeError('Body has already been read'));
+                this.bodyUsed = true;
+                return Promise.resolve(this._body);
+            };
+            Response.prototype.text = function() { return this._consume(); };
+            Response.prototype.json = function() { return this._consume().then(JSON.parse); };
+            Response.prototype.clone = function() {
+                if (this.bodyUsed) throw new TypeError('Body has already been read');
+                return new Response(this._body, this);
+            };
+            window.Response = Response;
+
+            window.__fetchQueue = [];
+            window.__pendingFetches = {};
+            window.__nextFetchId = 1;
+
+            function _queueFetch(request, settle) {
+                request.id = window.__nextFetchId++;
+                window.__pendingFetches[request.id] = settle;
+                window.__fetchQueue.push(request);
+                return request.id;
+            }
+
+            function _oneOf(value, allowed, fallback) {
+                return allowed.indexOf(value) >= 0 ? value : fallback;
+            }
+
+            function fetch(input, init) {
+                init = init || {};
+                return new Promise(function(resolve, reject) {
+                    var url = input !== null && typeof input === 'object' && input.url !== undefined ? input.url : input;
+                    var method = String(init.method || 'GET').toUpperCase();
+                    var headers = new Headers(init.headers);
+                    var body = init.body === undefined || init.body === null ? null : String(init.body);
+                    if (body !== null && (method === 'GET' || method === 'HEAD')) {
+                        reject(new TypeError(method + " requests can't have a body"));
+                        return;
+                    }
+                    if (body !== null && !headers.has('content-type')) {
+                        headers.set('content-type', 'text/plain;charset=UTF-8');
+                    }
+                    if (init.keepalive && body !== null && _utf8Length(body) > 65536) {
+                        reject(new TypeError('Keepalive request body is too large'));
+                        return;
+                    }
+                    _queueFetch({
+                        url: String(url), method: method, headers: headers._list, body: body,
+                        mode: _oneOf(init.mode, ['same-origin', 'cors', 'no-cors'], 'cors'),
+                        credentials: _oneOf(init.credentials, ['omit', 'same-origin', 'include'], 'same-origin'),
+                        keepalive: !!init.keepalive
+                    }, function(response, error) {
+                        if (error !== undefined) reject(new TypeError(error));
+                        else resolve(new Response(response.body, response));
+                    });
+                });
+            }
+            window.fetch = fetch;
+
+            window.__resolveFetch = function(id, response) {
+                var settle = window.__pendingFetches[id];
+                if (!settle) return;
+                delete window.__pendingFetches[id];
+                settle(response);
+            };
+
+            window.__rejectFetch = function(id, message) {
+                var settle = window.__pendingFetches[id];
+                if (!settle) return;
+                delete window.__pendingFetches[id];
+                settle(null, message);
+            };
+
+            window.__drainFetchQueue = function() {
+                var queue = window.__fetchQueue;
+                window.__fetchQueue = [];
+                return JSON.stringify(queue);
+            };
+
+            function XMLHttpRequest() {
+                _makeEventTarget(this);
+                this.readyState = 0;
+                this.status = 0;
+                this.statusText = '';
+                this.responseText = '';
+                this.response = '';
+                this.responseURL = '';
+                this.responseType = '';
+                this.withCredentials = false;
+                this._requestHeaders = new Headers();
+                this._responseHeaders = new Headers();
+                this._id = null;
+            }
+            XMLHttpRequest.UNSENT = 0;
+            XMLHttpRequest.OPENED = 1;
+            XMLHttpRequest.HEADERS_RECEIVED = 2;
+            XMLHttpRequest.LOADING = 3;
+            XMLHttpRequest.DONE = 4;
+
+            XMLHttpRequest.prototype._fire = function(type) {
+                this.dispatchEvent({ type: type, bubbles: false, cancelable: false, defaultPrevented: false });
+            };
+            XMLHttpRequest.prototype._setState = function(state) {
+                this.readyState = state;
+                this._fire('readystatechange');
+            };
+            XMLHttpRequest.prototype.open = function(method, url) {
+                this.abort();
+                this._method = String(method).toUpperCase();
+                this._url = String(url);
+                this._requestHeaders = new Headers();
+                this._setState(1);
+            };
+            XMLHttpRequest.prototype.setRequestHeader = function(name, value) {
+                if (this.readyState !== 1) throw new Error('InvalidStateError');
+                this._requestHeaders.append(name, value);
+            };
+            XMLHttpRequest.prototype.send = function(body) {
+                if (this.readyState !== 1 || this._id !== null) throw new Error('InvalidStateError');
+                var xhr = this;
+                var bodyless = body === undefined || body === null || this._method === 'GET' || this._method === 'HEAD';
+                body = bodyless ? null : String(body);
+                if (body !== null && !this._requestHeaders.has('content-type')) {
+                    this._requestHeaders.set('content-type', 'text/plain;charset=UTF-8');
+                }
+                this._id = _queueFetch({
+                    url: this._url, method: this._method, headers: this._requestHeaders._list, body: body,
+                    mode: 'cors', credentials: this.withCredentials ? 'include' : 'same-origin', keepalive: false
+                }, function(response, error) {
+                    xhr._id = null;
+                    if (error !== undefined) {
+                        xhr._setState(4);
+                        xhr._fire('error');
+                        xhr._fire('loadend');
+                        return;
+                    }
+                    xhr.status = response.status;
+                    xhr.statusText = response.statusText;
+                    xhr.responseURL = response.url;
+                    xhr._responseHeaders = new Headers(response.headers);
+                    xhr._setState(2);
+                    xhr._setState(3);
+                    xhr.responseText = response.body;
+                    xhr.response = response.body;
+                    if (xhr.responseType === 'json') {
+                        try { xhr.response = JSON.parse(response.body); } catch (e) { xhr.response = null; }
+                    }
+                    xhr._setState(4);
+                    xhr._fire('load');
+                    xhr._fire('loadend');
+                });
+            };
+            XMLHttpRequest.prototype.abort = function() {
+                if (this._id === null) return;
+                delete window.__pendingFetches[this._id];
+                this._id = null;
+                this.readyState = 0;
+                this._fire('abort');
+                this._fire('loadend');
+            };
+            XMLHttpRequest.prototype.getResponseHeader = function(name) {
+                return this.readyState >= 2 ? this._responseHeaders.get(name) : null;
+            };
+            XMLHttpRequest.prototype.getAllResponseHeaders = function() {
+                return this._responseHeaders._list
+                    .map(function(header) { return header[0] + ': ' + header[1] + '\r\n'; })
+                    .join('');
+            };
+            window.XMLHttpRequest = XMLHttpRequest;
+        "#;
+
+        runtime.evaluate_script(fetch_js)?;
+
+        // Timers keep their callbacks here and queue their schedule for
+        // the engine, which fires them with __fireTimer when they're due
+        let timers_js = r#"
+            window.__timers = {};
+            window.__timerQueue = [];
+            window.__nextTimerId = 1;
+
+            function _setTimer(callback, delay, args, repeat) {
+                var id = window.__nextTimerId++;
+                window.__timers[id] = { callback: callback, args: args, repeat: repeat };
+                // Delays that aren't positive or don't fit in 32 bits are 0
+                delay = Math.floor(Number(delay));
+                if (!(delay > 0) || delay > 2147483647) delay = 0;
+                window.__timerQueue.push({ type: 'set', id: id, delay: delay, repeat: repeat });
+                return id;
+            }
+
+            function setTimeout(callback, delay) {
+                return _setTimer(callback, delay, Array.prototype.slice.call(arguments, 2), false);
+            }
+
+            function setInterval(callback, delay) {
+                return _setTimer(callback, delay, Array.prototype.slice.call(arguments, 2), true);
+            }
+
+            function clearTimeout(id) {
+                if (!window.__timers[id]) return;
+                delete window.__timers[id];
+                window.__timerQueue.push({ type: 'clear', id: Number(id) });
+            }
+            var clearInterval = clearTimeout;
+
+            function queueMicrotask(callback) {
+                if (typeof callback !== 'function') throw new TypeError('queueMicrotask needs a function');
+                Promise.resolve().then(function() { callback(); });
+            }
+
+            // Idle callbacks run as soon as the engine next runs timers,
+            // with the rest of a 50ms idle period to work in
+            function requestIdleCallback(callback) {
+                if (typeof callback !== 'function') throw new TypeError('requestIdleCallback needs a function');
+                return setTimeout(function() {
+                    var start = Date.now();
+                    callback({
+                        didTimeout: false,
+                        timeRemaining: function() { return Math.max(0, 50 - (Date.now() - start)); }
+                    });
+                }, 0);
+            }
+            var cancelIdleCallback = clearTimeout;
+
+            window.setTimeout = setTimeout;
+            window.setInterval = setInterval;
+            window.clearTimeout = clearTimeout;
+            window.clearInterval = clearInterval;
+            window.queueMicrotask = queueMicrotask;
+            window.requestIdleCallback = requestIdleCallback;
+            window.cancelIdleCallback = cancelIdleCallback;
+
+            window.__fireTimer = function(id) {
+                var timer = window.__timers[id];
+                if (!timer) return;
+                if (!timer.repeat) delete window.__timers[id];
+                if (typeof timer.callback === 'function') timer.callback.apply(window, timer.args);
+                else (0, eval)(String(timer.callback));
+            };
+
+            window.__drainTimerQueue = function() {
+                var queue = window.__timerQueue;
+                window.__timerQueue = [];
+                return JSON.stringify(queue);
+            };
+        "#;
+
+        runtime.evaluate_script(timers_js)?;
+
+        // A minimal Intl: number and date formatting for a few locales,
+        // with English month and weekday names. Locales without their own
+        // data format like their language, or else like English
+        let intl_js = r#"
+            var _NativeDate = Date;
+            var Intl = window.Intl = {
+                _defaultLocale: 'en-US',
+                _locales: {
+                    'en': { group: ',', decimal: '.', space: '', currencyAfter: false, order: 'mdy', separator: '/', hour12: true },
+                    'en-GB': { group: ',', decimal: '.', space: '', currencyAfter: false, order: 'dmy', separator: '/', hour12: false },
+                    'de': { group: '.', decimal: ',', space: '\u00a0', currencyAfter: true, order: 'dmy', separator: '.', hour12: false },
+                    'es': { group: '.', decimal: ',', space: '\u00a0', currencyAfter: true, order: 'dmy', separator: '/', hour12: false },
+                    'fr': { group: '\u202f', decimal: ',', space: '\u00a0', currencyAfter: true, order: 'dmy', separator: '/', hour12: false },
+                    'it': { group: '.', decimal: ',', space: '', currencyAfter: true, order: 'dmy', separator: '/', hour12: false },
+                    'ja': { group: ',', decimal: '.', space: '', currencyAfter: false, order: 'ymd', separator: '/', hour12: false },
+                    'zh': { group: ',', decimal: '.', space: '', currencyAfter: false, order: 'ymd', separator: '/', hour12: false }
+                },
+                _currencySymbols: { USD: '$', EUR: '€', GBP: '£', JPY: '¥', CNY: 'CN¥', INR: '₹' },
+                _months: ['January', 'February', 'March', 'April', 'May', 'June', 'July',
+                    'August', 'September', 'October', 'November', 'December'],
+                _weekdays: ['Sunday', 'Monday', 'Tuesday', 'Wednesday', 'Thursday', 'Friday', 'Saturday']
+            };
+
+            function _localeData(locale) {
+                return Intl._locales[locale] || Intl._locales[locale.split('-')[0]];
+            }
+
+            // The first requested locale there's data for, or the default
+            function _resolveLocale(locales) {
+                var requested = locales === undefined ? [] : [].concat(locales);
+                for (var i = 0; i < requested.length; i++) {
+                    if (_localeData(String(requested[i]))) return String(requested[i]);
+                }
+                return Intl._defaultLocale;
+            }
+
+            function _supportedLocalesOf(locales) {
+                var requested = locales === undefined ? [] : [].concat(locales);
+                return requested.map(String).filter(function(locale) { return !!_localeData(locale); });
+            }
+
+            Intl.getCanonicalLocales = function(locales) {
+                return locales === undefined ? [] : [].concat(locales).map(String);
+            };
+
+            function _pad(value, length) {
+                var text = String(value);
+                while (text.length < length) text = '0' + text;
+                return text;
+            }
+
+            function NumberFormat(locales, options) {
+                if (!(this instanceof NumberFormat)) return new NumberFormat(locales, options);
+                options = options || {};
+                this._locale = _resolveLocale(locales);
+                this._style = options.style || 'decimal';
+                if (['decimal', 'percent', 'currency'].indexOf(this._style) < 0) {
+                    throw new RangeError('Unsupported number format style: ' + this._style);
+                }
+                this._currency = options.currency === undefined ? undefined : String(options.currency).toUpperCase();
+                if (this._style === 'currency' && !this._currency) {
+                    throw new TypeError('Currency code is required with currency style.');
+                }
+                var currencyDigits = this._currency === 'JPY' ? 0 : 2;
+                var defaultMin = this._style === 'currency' ? currencyDigits : 0;
+                var defaultMax = this._style === 'currency' ? currencyDigits : this._style === 'percent' ? 0 : 3;
+                var min = options.minimumFractionDigits;
+                var max = options.maximumFractionDigits;
+                this._minimumFractionDigits = min !== undefined ? Number(min) : Math.min(defaultMin, max !== undefined ? Number(max) : defaultMin);
+                this._maximumFractionDigits = max !== undefined ? Number(max) : Math.max(defaultMax, this._minimumFractionDigits);
+                if (!(this._minimumFractionDigits >= 0 && this._maximumFractionDigits <= 100
+                      && this._minimumFractionDigits <= this._maximumFractionDigits)) {
+                    throw new RangeError('Fraction digits out of range');
+                }
+                this._minimumIntegerDigits = options.minimumIntegerDigits === undefined ? 1 : Number(options.minimumIntegerDigits);
+                this._useGrouping = options.useGrouping !== false;
+                var self = this;
+                // Bound, as pages pass it around on its own
+                this.format = function(value) { return self._format(value); };
+            }
+
+            NumberFormat.prototype._format = function(value) {
+                var number = Number(value);
+                if (isNaN(number)) return 'NaN';
+                var data = _localeData(this._locale) || Intl._locales.en;
+                var negative = number < 0 || (number === 0 && 1 / number < 0);
+                number = Math.abs(number);
+                if (this._style === 'percent') number *= 100;
+                var text;
+                if (!isFinite(number)) {
+                    text = '∞';
+                } else {
+                    var parts = number.toFixed(this._maximumFractionDigits).split('.');
+                    var integer = _pad(parts[0], this._minimumIntegerDigits);
+                    var fraction = parts[1] || '';
+                    while (fraction.length > this._minimumFractionDigits && fraction.charAt(fraction.length - 1) === '0') {
+                        fraction = fraction.slice(0, -1);
+                    }
+                    if (this._useGrouping) {
+                        var grouped = '';
+                        for (var i = 0; i < integer.length; i++) {
+                            if (i > 0 && (integer.length - i) % 3 === 0) grouped += data.group;
+                            grouped += integer.charAt(i);
+                        }
+                        integer = grouped;
+                    }
+                    text = fraction ? integer + data.decimal + fraction : integer;
+                }
+                if (this._style === 'percent') {
+                    text += data.space + '%';
+                } else if (this._style === 'currency') {
+                    var symbol = Intl._currencySymbols[this._currency];
+                    if (data.currencyAfter) text += '\u00a0' + (symbol || this._currency);
+                    else text = symbol ? symbol + text : this._currency + '\u00a0' + text;
+                }
+                return negative ? '-' + text : text;
+            };
+
+            NumberFormat.prototype.resolvedOptions = function() {
+                var options = {
+                    locale: this._locale,
+                    numberingSystem: 'latn',
+                    style: this._style,
+                    minimumIntegerDigits: this._minimumIntegerDigits,
+                    minimumFractionDigits: this._minimumFractionDigits,
+                    maximumFractionDigits: this._maximumFractionDigits,
+                    useGrouping: this._useGrouping
+                };
+                if (this._currency) options.currency = this._currency;
+                return options;
+            };
+
+            NumberFormat.supportedLocalesOf = _supportedLocalesOf;
+            Intl.NumberFormat = NumberFormat;
+
+            // Time zones pages can be put in: their standard offset in minutes
+            // east of UTC, and the daylight saving rules they follow
+            Intl._timeZones = {
+                'UTC': [0], 'Etc/UTC': [0], 'GMT': [0], 'Etc/GMT': [0],
+                'Europe/London': [0, 'eu'], 'Europe/Dublin': [0, 'eu'], 'Europe/Lisbon': [0, 'eu'],
+                'Europe/Paris': [60, 'eu'], 'Europe/Berlin': [60, 'eu'], 'Europe/Madrid': [60, 'eu'],
+                'Europe/Rome': [60, 'eu'], 'Europe/Amsterdam': [60, 'eu'], 'Europe/Brussels': [60, 'eu'],
+                'Europe/Vienna': [60, 'eu'], 'Europe/Zurich': [60, 'eu'], 'Europe/Stockholm': [60, 'eu'],
+                'Europe/Oslo': [60, 'eu'], 'Europe/Copenhagen': [60, 'eu'], 'Europe/Warsaw': [60, 'eu'],
+                'Europe/Prague': [60, 'eu'], 'Europe/Athens': [120, 'eu'], 'Europe/Helsinki': [120, 'eu'],
+                'Europe/Kyiv': [120, 'eu'], 'Europe/Istanbul': [180], 'Europe/Moscow': [180],
+                'America/New_York': [-300, 'us'], 'America/Toronto': [-300, 'us'], 'America/Chicago': [-360, 'us'],
+                'America/Mexico_City': [-360], 'America/Denver': [-420, 'us'], 'America/Phoenix': [-420],
+                'America/Los_Angeles': [-480, 'us'], 'America/Vancouver': [-480, 'us'],
+                'America/Anchorage': [-540, 'us'], 'Pacific/Honolulu': [-600], 'America/Sao_Paulo': [-180],
+                'America/Argentina/Buenos_Aires': [-180], 'Africa/Cairo': [120], 'Africa/Johannesburg': [120],
+                'Africa/Lagos': [60], 'Asia/Dubai': [240], 'Asia/Kolkata': [330], 'Asia/Bangkok': [420],
+                'Asia/Shanghai': [480], 'Asia/Hong_Kong': [480], 'Asia/Singapore': [480], 'Asia/Taipei': [480],
+                'Asia/Tokyo': [540], 'Asia/Seoul': [540], 'Australia/Brisbane': [600],
+                'Australia/Sydney': [600, 'au'], 'Australia/Melbourne': [600, 'au']
+            };
+            // Set by the host to put the page in a time zone; otherwise the
+            // host's own is used
+            Intl._timeZone = undefined;
+
+            // The zone named `name`, as its name and offset rules, or
+            // undefined if it isn't known
+            function _zoneData(name) {
+                var zone = Intl._timeZones[name];
+                if (zone) return { name: name, offset: zone[0], rule: zone[1] };
+                for (var known in Intl._timeZones) {
+                    if (known.toLowerCase() === name.toLowerCase()) return _zoneData(known);
+                }
+                // POSIX-style, so Etc/GMT+5 is five hours behind UTC
+                var fixed = /^Etc\/GMT([+-])(\d{1,2})$/i.exec(name);
+                if (fixed && Number(fixed[2]) <= 14) {
+                    var hours = Number(fixed[2]);
+                    return { name: 'Etc/GMT' + fixed[1] + hours, offset: (fixed[1] === '+' ? -60 : 60) * hours };
+                }
+                return undefined;
+            }
+
+            // The UTC time of the `n`th Sunday of a month, counting back from
+            // its end for negative `n`, at `hour` UTC
+            function _sunday(year, month, n, hour) {
+                if (n > 0) {
+                    var first = new _NativeDate(_NativeDate.UTC(year, month, 1)).getUTCDay();
+                    return _NativeDate.UTC(year, month, 1 + (7 - first) % 7 + (n - 1) * 7, hour);
+                }
+                var last = new _NativeDate(_NativeDate.UTC(year, month + 1, 0));
+                return _NativeDate.UTC(year, month, last.getUTCDate() - last.getUTCDay(), hour);
+            }
+
+            // Minutes east of UTC in `zone` at UTC time `time`
+            function _zoneOffset(zone, time) {
+                if (!zone.rule || isNaN(time)) return zone.offset;
+                var year = new _NativeDate(time).getUTCFullYear();
+                // Changes happen at 2am local standard time, or 1am UTC in the EU
+                var local = 2 - zone.offset / 60;
+                var summer;
+                if (zone.rule === 'us') {
+                    summer = time >= _sunday(year, 2, 2, local) && time < _sunday(year, 10, 1, local - 1);
+                } else if (zone.rule === 'eu') {
+                    summer = time >= _sunday(year, 2, -1, 1) && time < _sunday(year, 9, -1, 1);
+                } else {
+                    summer = !(time >= _sunday(year, 3, 1, local) && time < _sunday(year, 9, 1, local));
+                }
+                return zone.offset + (summer ? 60 : 0);
+            }
+
+            // The page's time zone, or the host's as far as its offset tells
+            function _localTimeZone() {
+                if (Intl._timeZone) return Intl._timeZone.name;
+                var offset = new Date().getTimezoneOffset();
+                if (offset === 0) return 'UTC';
+                if (offset % 60 !== 0) return 'UTC';
+                return 'Etc/GMT' + (offset > 0 ? '+' : '-') + Math.abs(offset / 60);
+            }
+
+            var _dateStyles = {
+                full: { weekday: 'long', year: 'numeric', month: 'long', day: 'numeric' },
+                long: { year: 'numeric', month: 'long', day: 'numeric' },
+                medium: { year: 'numeric', month: 'short', day: 'numeric' },
+                short: { year: '2-digit', month: 'numeric', day: 'numeric' }
+            };
+            var _timeStyles = {
+                full: { hour: 'numeric', minute: '2-digit', second: '2-digit' },
+                long: { hour: 'numeric', minute: '2-digit', second: '2-digit' },
+                medium: { hour: 'numeric', minute: '2-digit', second: '2-digit' },
+                short: { hour: 'numeric', minute: '2-digit' }
+            };
+            var _dateFields = ['weekday', 'year', 'month', 'day', 'hour', 'minute', 'second'];
+
+            function DateTimeFormat(locales, options, defaults) {
+                if (!(this instanceof DateTimeFormat)) return new DateTimeFormat(locales, options, defaults);
+                options = options || {};
+                this._locale = _resolveLocale(locales);
+                this._fields = {};
+                var fields = this._fields;
+                var any = false;
+                _dateFields.forEach(function(field) {
+                    if (options[field] !== undefined) {
+                        fields[field] = String(options[field]);
+                        any = true;
+                    }
+                });
+                [[options.dateStyle, _dateStyles], [options.timeStyle, _timeStyles]].forEach(function(style) {
+                    if (style[0] === undefined) return;
+                    var preset = style[1][style[0]];
+                    if (!preset) throw new RangeError('Unsupported style: ' + style[0]);
+                    for (var field in preset) fields[field] = preset[field];
+                    any = true;
+                });
+                if (!any) {
+                    defaults = defaults || { year: 'numeric', month: 'numeric', day: 'numeric' };
+                    for (var field in defaults) fields[field] = defaults[field];
+                }
+                var data = _localeData(this._locale) || Intl._locales.en;
+                this._hour12 = options.hour12 !== undefined ? !!options.hour12 : data.hour12;
+                if (options.timeZone === undefined) {
+                    this._zone = Intl._timeZone;
+                    this._timeZone = _localTimeZone();
+                } else {
+                    this._zone = _zoneData(String(options.timeZone));
+                    if (!this._zone) throw new RangeError('Invalid time zone specified: ' + options.timeZone);
+                    this._timeZone = this._zone.name;
+                }
+                var self = this;
+                this.format = function(date) { return self._format(date); };
+            }
+
+            DateTimeFormat.prototype._format = function(date) {
+                date = date === undefined ? new Date() : new Date(date instanceof Date ? date.getTime() : Number(date));
+                if (isNaN(date.getTime())) throw new RangeError('Invalid time value');
+                // Without a zone, the host's local time
+                var zone = this._zone;
+                var wall = zone && new _NativeDate(date.getTime() + _zoneOffset(zone, date.getTime()) * 60000);
+                var get = function(name) { return wall ? wall['getUTC' + name]() : date['get' + name](); };
+                var fields = this._fields;
+                var data = _localeData(this._locale) || Intl._locales.en;
+
+                var year = fields.year === '2-digit' ? _pad(get('FullYear') % 100, 2) : fields.year && String(get('FullYear'));
+                var day = fields.day && (fields.day === '2-digit' ? _pad(get('Date'), 2) : String(get('Date')));
+                var month;
+                var monthName = fields.month === 'long' || fields.month === 'short' || fields.month === 'narrow';
+                if (monthName) {
+                    var name = Intl._months[get('Month')];
+                    month = fields.month === 'long' ? name : fields.month === 'short' ? name.slice(0, 3) : name.charAt(0);
+                } else if (fields.month) {
+                    month = fields.month === '2-digit' ? _pad(get('Month') + 1, 2) : String(get('Month') + 1);
+                }
+
+                var dateText;
+                var pieces = { y: year, m: month, d: day };
+                if (monthName) {
+                    if (data.order === 'mdy') {
+                        dateText = [month, day].filter(Boolean).join(' ');
+                        if (year) dateText += (day ? ', ' : ' ') + year;
+                    } else {
+                        dateText = data.order.split('').map(function(piece) { return pieces[piece]; }).filter(Boolean).join(' ');
+                    }
+                } else {
+                    dateText = data.order.split('').map(function(piece) { return pieces[piece]; }).filter(Boolean).join(data.separator);
+                }
+                if (fields.weekday) {
+                    var weekday = Intl._weekdays[get('Day')];
+                    weekday = fields.weekday === 'long' ? weekday : fields.weekday === 'short' ? weekday.slice(0, 3) : weekday.charAt(0);
+                    dateText = dateText ? weekday + ', ' + dateText : weekday;
+                }
+
+                var timeText = '';
+                if (fields.hour || fields.minute || fields.second) {
+                    var hours = get('Hours');
+                    var time = [];
+                    if (fields.hour) {
+                        var hour = this._hour12 ? hours % 12 || 12 : hours;
+                        time.push(fields.hour === '2-digit' || !this._hour12 ? _pad(hour, 2) : String(hour));
+                    }
+                    if (fields.minute) time.push(fields.hour || fields.minute === '2-digit' ? _pad(get('Minutes'), 2) : String(get('Minutes')));
+                    if (fields.second) time.push(fields.minute || fields.second === '2-digit' ? _pad(get('Seconds'), 2) : String(get('Seconds')));
+                    timeText = time.join(':');
+                    if (fields.hour && this._hour12) timeText += hours < 12 ? ' AM' : ' PM';
+                }
+                return [dateText, timeText].filter(Boolean).join(', ');
+            };
+
+            DateTimeFormat.prototype.resolvedOptions = function() {
+                var options = {
+                    locale: this._locale,
+                    calendar: 'gregory',
+                    numberingSystem: 'latn',
+                    timeZone: this._timeZone
+                };
+                for (var field in this._fields) options[field] = this._fields[field];
+                if (this._fields.hour) options.hour12 = this._hour12;
+                return options;
+            };
+
+            Intl.DateTimeFormat = function(locales, options) { return new DateTimeFormat(locales, options); };
+            Intl.DateTimeFormat.prototype = DateTimeFormat.prototype;
+            Intl.DateTimeFormat.supportedLocalesOf = _supportedLocalesOf;
+
+            // Putting the page in a time zone replaces Date with one whose
+            // local time is that zone's. Dates are still native ones, so
+            // only the methods reading or writing local time change
+            var _localMethods = ['FullYear', 'Month', 'Date', 'Day', 'Hours', 'Minutes', 'Seconds', 'Milliseconds'];
+            var _nativeMethods = {};
+            ['getTimezoneOffset', 'toString', 'toDateString', 'toTimeString'].concat(
+                _localMethods.map(function(name) { return 'get' + name; }),
+                _localMethods.filter(function(name) { return name !== 'Day'; }).map(function(name) { return 'set' + name; })
+            ).forEach(function(name) { _nativeMethods[name] = _NativeDate.prototype[name]; });
+
+            // The wall clock time in the page's zone, as a date whose UTC
+            // fields read it, or undefined for an invalid date
+            function _wall(date) {
+                var time = _NativeDate.prototype.getTime.call(date);
+                if (isNaN(time)) return undefined;
+                return new _NativeDate(time + _zoneOffset(Intl._timeZone, time) * 60000);
+            }
+
+            // The UTC time at which the page's zone reads wall clock time `wall`
+            function _fromWall(wall) {
+                var guess = wall - _zoneOffset(Intl._timeZone, wall) * 60000;
+                return wall - _zoneOffset(Intl._timeZone, guess) * 60000;
+            }
+
+            function _ZonedDate(a, b, c, d, e, f, g) {
+                if (!(this instanceof _ZonedDate)) return new _ZonedDate().toString();
+                if (arguments.length === 0) return new _NativeDate(_NativeDate.now());
+                // Outside a zone it's only there to read the virtual clock
+                if (!Intl._timeZone) return Reflect.construct(_NativeDate, arguments);
+                if (arguments.length === 1) {
+                    return new _NativeDate(typeof a === 'string' ? _ZonedDate.parse(a) : a instanceof _NativeDate ? a.getTime() : a);
+                }
+                var wall = _NativeDate.UTC(a, b, c === undefined ? 1 : c, d || 0, e || 0, f || 0, g || 0);
+                return new _NativeDate(_fromWall(wall));
+            }
+            _ZonedDate.prototype = _NativeDate.prototype;
+            _ZonedDate.now = function() { return _NativeDate.now(); };
+            _ZonedDate.UTC = _NativeDate.UTC;
+            // Times without a zone are the page's local time, except ISO dates
+            _ZonedDate.parse = function(text) {
+                text = String(text);
+                var time = _NativeDate.parse(text);
+                if (isNaN(time) || !Intl._timeZone || /(Z|[+-]\d\d:?\d\d|GMT|UTC)\s*(\(.*\))?$/i.test(text) || /^\d{4}(-\d\d){0,2}$/.test(text)) {
+                    return time;
+                }
+                var offset = _nativeMethods.getTimezoneOffset.call(new _NativeDate(time));
+                return _fromWall(time - offset * 60000);
+            };
+
+            var _zonedMethods = {
+                getTimezoneOffset: function() {
+                    var time = this.getTime();
+                    return isNaN(time) ? NaN : -_zoneOffset(Intl._timeZone, time);
+                },
+                toDateString: function() {
+                    var wall = _wall(this);
+                    if (!wall) return 'Invalid Date';
+                    return Intl._weekdays[wall.getUTCDay()].slice(0, 3) + ' ' + Intl._months[wall.getUTCMonth()].slice(0, 3)
+                        + ' ' + _pad(wall.getUTCDate(), 2) + ' ' + _pad(wall.getUTCFullYear(), 4);
+                },
+                toTimeString: function() {
+                    var wall = _wall(this);
+                    if (!wall) return 'Invalid Date';
+                    var offset = -this.getTimezoneOffset();
+                    return _pad(wall.getUTCHours(), 2) + ':' + _pad(wall.getUTCMinutes(), 2) + ':' + _pad(wall.getUTCSeconds(), 2)
+                        + ' GMT' + (offset < 0 ? '-' : '+') + _pad(Math.floor(Math.abs(offset) / 60), 2) + _pad(Math.abs(offset) % 60, 2)
+                        + ' (' + Intl._timeZone.name + ')';
+                },
+                toString: function() {
+                    return _wall(this) ? this.toDateString() + ' ' + this.toTimeString() : 'Invalid Date';
+                }
+            };
+            _localMethods.forEach(function(name) {
+                _zonedMethods['get' + name] = function() {
+                    var wall = _wall(this);
+                    return wall ? wall['getUTC' + name]() : NaN;
+                };
+                if (name === 'Day') return;
+                _zonedMethods['set' + name] = function() {
+                    // Setting the year of an invalid date starts from time 0
+                    var wall = _wall(this) || new _NativeDate(name === 'FullYear' ? 0 : NaN);
+                    wall['setUTC' + name].apply(wall, arguments);
+                    return this.setTime(_fromWall(wall.getTime()));
+                };
+            });
+
+            // Put the page in time zone `name`, or back in the host's
+            Intl._setTimeZone = function(name) {
+                var zone = name === undefined ? undefined : _zoneData(String(name));
+                if (name !== undefined && !zone) throw new RangeError('Invalid time zone specified: ' + name);
+                Intl._timeZone = zone;
+                var methods = zone ? _zonedMethods : _nativeMethods;
+                for (var method in methods) _NativeDate.prototype[method] = methods[method];
+                Intl._installDate();
+            };
+
+            // Make Date the one for the page's zone and clock: dates made
+            // without a time read the engine's virtual clock once it set one
+            Intl._installDate = function() {
+                var date = Intl._timeZone || window.__clock ? _ZonedDate : _NativeDate;
+                _NativeDate.prototype.constructor = date;
+                Date = window.Date = date;
+            };
+
+            Number.prototype.toLocaleString = function(locales, options) {
+                return new NumberFormat(locales, options).format(this);
+            };
+            Date.prototype.toLocaleString = function(locales, options) {
+                return new DateTimeFormat(locales, options, {
+                    year: 'numeric', month: 'numeric', day: 'numeric', hour: 'numeric', minute: '2-digit', second: '2-digit'
+                }).format(this);
+            };
+            Date.prototype.toLocaleDateString = function(locales, options) {
+                return new DateTimeFormat(locales, options).format(this);
+            };
+            Date.prototype.toLocaleTimeString = function(locales, options) {
+                return new DateTimeFormat(locales, options, { hour: 'numeric', minute: '2-digit', second: '2-digit' }).format(this);
+            };
+        "#;
+
+        runtime.evaluate_script(intl_js)?;
+
+        // Time and randomness. Once the engine sets a virtual clock, as
+        // deterministic mode does, Date and performance.now() read it
+        // instead of the host's; seeding Math.random() makes it repeat
+        let clock_js = r#"
+            window.__clock = null;
+            var _hostNow = _NativeDate.now;
+            _NativeDate.now = function() { return window.__clock ? window.__clock.date : _hostNow(); };
+
+            var performance = window.performance = {
+                timeOrigin: _hostNow(),
+                now: function() {
+                    return window.__clock ? window.__clock.elapsed - window.__clock.origin : _hostNow() - this.timeOrigin;
+                },
+                toJSON: function() { return { timeOrigin: this.timeOrigin }; }
+            };
+
+            // The page's time origin is where the clock first stood
+            window.__setClock = function(date, elapsed) {
+                if (!window.__clock) {
+                    window.__clock = { origin: elapsed };
+                    performance.timeOrigin = date;
+                }
+                window.__clock.date = date;
+                window.__clock.elapsed = elapsed;
+                Intl._installDate();
+            };
+
+            // Mulberry32: small, fast, and the same sequence for a seed
+            // in every engine
+            window.__seedRandom = function(seed) {
+                var state = seed >>> 0;
+                Math.random = function() {
+                    state = (state + 0x6D2B79F5) >>> 0;
+                    var t = Math.imul(state ^ (state >>> 15), state | 1);
+                    t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+                    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+                };
+            };
+        "#;
+
+        runtime.evaluate_script(clock_js)?;
+
+        // Elements of the real document: getElementById(), document.body
+        // and the elements inserted into them. Their changes queue for
+        // the engine, which applies them to the DOM and relays out
+        let dom_mutation_js = r#"
+            window.__domMutationQueue = [];
+            window.__unboundNodes = {};
+            window.__nextNodeKey = 1;
+
+            function _queueDomMutation(mutation) {
+                window.__domMutationQueue.push(mutation);
+            }
+
+            function _htmlMarkup(node) {
+                var type = _nodeType(node);
+                if (type === 3) return _escapeMarkup(node.textContent, false);
+                if (type === 8) return '<!--' + node.textContent + '-->';
+                if (type !== 1) return _kids(node).map(_htmlMarkup).join('');
+                var name = String(node.tagName).toLowerCase();
+                var attributes = {};
+                for (var attrName in node.attributes || {}) attributes[attrName] = node.attributes[attrName];
+                if (node.id && !('id' in attributes)) attributes.id = node.id;
+                if (node.className && !('class' in attributes)) attributes['class'] = node.className;
+                var style = Object.keys(node.style || {}).filter(function(property) {
+                    return typeof node.style[property] === 'string' && node.style[property];
+                }).map(function(property) {
+                    return property.replace(/[A-Z]/g, function(c) { return '-' + c.toLowerCase(); }) + ': ' + node.style[property];
+                }).join('; ');
+                if (style && !('style' in attributes)) attributes.style = style;
+                var markup = '<' + name;
+                for (var attrName in attributes) {
+                    markup += ' ' + attrName + '="' + _escapeMarkup(attributes[attrName], true) + '"';
+                }
+                markup += '>';
+                if (_VOID_ELEMENTS.indexOf(name) >= 0) return markup;
+                var content = _kids(node).length ? _kids(node).map(_htmlMarkup).join('')
+                    : node.innerHTML ? String(node.innerHTML) : _escapeMarkup(node.textContent || '', false);
+                return markup + content + '</' + name + '>';
+            }
+
+            function _queueInsert(parent, child, reference) {
+                var before = reference && reference._nodeId !== undefined ? reference._nodeId : null;
+                if (child._nodeId !== undefined) {
+                    _queueDomMutation({ type: 'move', node: child._nodeId, parent: parent._nodeId, before: before });
+                    return;
+                }
+                var key = null;
+                if (_nodeType(child) === 1) {
+                    key = window.__nextNodeKey++;
+                    window.__unboundNodes[key] = child;
+                }
+                _queueDomMutation({ type: 'insert', parent: parent._nodeId, before: before, markup: _htmlMarkup(child), key: key });
+            }
+
+            function _bindElement(element, nodeId) {
+                element._nodeId = nodeId;
+                var setAttribute = element.setAttribute;
+                var removeAttribute = element.removeAttribute;
+                var id = element.id, className = element.className, markup = '';
+
+                element.setAttribute = function(name, value) {
+                    name = String(name);
+                    value = String(value);
+                    setAttribute.call(this, name, value);
+                    if (name === 'id') id = value;
+                    if (name === 'class') className = value;
+                    _queueDomMutation({ type: 'setAttribute', node: this._nodeId, name: name, value: value });
+                };
+                element.removeAttribute = function(name) {
+                    name = String(name);
+                    removeAttribute.call(this, name);
+                    _queueDomMutation({ type: 'removeAttribute', node: this._nodeId, name: name });
+                };
+                element.insertBefore = function(child, reference) {
+                    var index = reference ? _kids(this).indexOf(reference) : -1;
+                    _insert(this, child, index < 0 ? _kids(this).length : index);
+                    _queueInsert(this, child, reference);
+                    return child;
+                };
+                element.appendChild = function(child) { return this.insertBefore(child, null); };
+                element.removeChild = function(child) {
+                    _remove(child);
+                    if (child._nodeId !== undefined) _queueDomMutation({ type: 'remove', node: child._nodeId });
+                    return child;
+                };
+                Object.defineProperty(element, 'id', {
+                    get: function() { return id; },
+                    set: function(value) { this.setAttribute('id', value); }
+                });
+                Object.defineProperty(element, 'className', {
+                    get: function() { return className; },
+                    set: function(value) { this.setAttribute('class', value); }
+                });
+                Object.defineProperty(element, 'innerHTML', {
+                    get: function() { return markup; },
+                    set: function(value) {
+                        markup = String(value);
+                        this.children = [];
+                        _queueDomMutation({ type: 'replaceChildren', node: this._nodeId, markup: markup });
+                    }
+                });
+                Object.defineProperty(element, 'textContent', {
+                    get: function() { return _kids(this).map(function(c) { return c.textContent || ''; }).join(''); },
+                    set: function(value) {
+                        this.children = [document.createTextNode(String(value))];
+                        markup = _escapeMarkup(String(value), false);
+                        _queueDomMutation({ type: 'replaceChildren', node: this._nodeId, markup: markup });
+                    }
+                });
+                return element;
+            }
+
+            window.__bindDocument = function(elements) {
+                document._elements = {};
+                elements.forEach(function(entry) {
+                    var element = document.createElement(entry.tag);
+                    for (var name in entry.attributes) element.attributes[name] = entry.attributes[name];
+                    element.id = entry.attributes.id || '';
+                    element.className = entry.attributes['class'] || '';
+                    _bindElement(element, entry.node);
+                    if (element.id && !(element.id in document._elements)) document._elements[element.id] = element;
+                    if (entry.tag === 'html') document.documentElement = element;
+                    if (entry.tag === 'head') document.head = element;
+                    if (entry.tag === 'body') document.body = element;
+                });
+            };
+
+            window.__bindNode = function(key, nodeId) {
+                var element = window.__unboundNodes[key];
+                delete window.__unboundNodes[key];
+                if (!element) return;
+                _bindElement(element, nodeId);
+                if (element.id && !(element.id in document._elements)) document._elements[element.id] = element;
+            };
+
+            window.__drainDomMutationQueue = function() {
+                var queue = window.__domMutationQueue;
+                window.__domMutationQueue = [];
+                return JSON.stringify(queue);
+            };
+        "#;
+
+        runtime.evaluate_script(dom_mutation_js)?;
+
+        debug!("Global objects injected");
+        Ok(())
+    }
+
+    /// Set the document.
+    pub fn set_document(&self, document: Rc<Document>) -> Result<(), BindingError> {
+        // Update state
+        self.window.borrow_mut().document = Some(document.clone());
+
+        // Sync to JS
+        let title = document.title().unwrap_or_default();
+        let mut runtime = self.runtime.borrow_mut();
+        runtime.evaluate_script(&format!("document.title = {:?};", title))?;
+        runtime.evaluate_script("document.readyState = 'loading';")?;
+
+        // Index elements by ID, and hand them and the document's root
+        // elements to script
+        let mut elements = Vec::new();
+        document.traverse(|node| {
+            let Some(tag) = node.tag_name() else {
+                return;
+            };
+            let is_root_element = matches!(tag, "html" | "head" | "body");
+            if node.get_attribute("id").is_none() && !is_root_element {
+                return;
+            }
+            let node_id = node.id.raw();
+            self.node_map.borrow_mut().insert(node_id as u64, node.clone());
+            elements.push(serde_json::json!({
+                "node": node_id,
+                "tag": tag.to_ascii_lowercase(),
+                "attributes": node.attributes(),
+            }));
+        });
+        runtime.evaluate_script(&format!(
+            "window.__bindDocument({});",
+            serde_json::Value::Array(elements)
+        ))?;
+
+        debug!("Document bound to JS context");
+        Ok(())
+    }
+
+    /// Set the current URL.
+    pub fn set_location(&self, url: &Url) -> Result<(), BindingError> {
+        let location = Location::from_url(url);
+
+        // Update state
+        self.window.borrow_mut().location = location.clone();
+
+        // Sync to JS
+        let mut runtime = self.runtime.borrow_mut();
+        runtime.evaluate_script(&format!(
+            r#"
+            window.location.href = {:?};
+            window.location.protocol = {:?};
+            window.location.host = {:?};
+            window.location.hostname = {:?};
+            window.location.port = {:?};
+            window.location.pathname = {:?};
+            window.location.search = {:?};
+            window.location.hash = {:?};
+            window.location.origin = {:?};
+            document.URL = {:?};
+            "#,
+            location.href,
+            location.protocol,
+            location.host,
+            location.hostname,
+            location.port,
+            location.pathname,
+            location.search,
+            location.hash,
+            location.origin,
+            location.href
+        ))?;
+
+        Ok(())
+    }
+
+    /// Fire `hashchange` at the window after a navigation within the
+    /// document from `old_url` to `new_url`.
+    pub fn dispatch_hash_change(&self, old_url: &Url, new_url: &Url) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "window.dispatchEvent({{ type: 'hashchange', bubbles: false, cancelable: false, \
+             defaultPrevented: false, oldURL: {:?}, newURL: {:?} }});",
+            old_url.as_str(),
+            new_url.as_str()
+        ))?;
+        Ok(())
+    }
+
+    /// Move `document.readyState` to `state`, firing `readystatechange` at
+    /// the document, then `DOMContentLoaded` at the document on reaching
+    /// interactive or `load` at the window on reaching complete.
+    pub fn set_ready_state(&self, state: DocumentReadyState) -> Result<(), BindingError> {
+        let then = match state {
+            DocumentReadyState::Loading => "",
+            DocumentReadyState::Interactive => "document.dispatchEvent(_simpleEvent('DOMContentLoaded', true));",
+            DocumentReadyState::Complete => "window.dispatchEvent(_simpleEvent('load', false));",
+        };
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "(function() {{ \
+                 function _simpleEvent(type, bubbles) {{ \
+                     return {{ type: type, bubbles: bubbles, cancelable: false, defaultPrevented: false }}; \
+                 }} \
+                 if (document.readyState === {state:?}) return; \
+                 document.readyState = {state:?}; \
+                 document.dispatchEvent(_simpleEvent('readystatechange', false)); \
+                 {then} \
+             }})();",
+            state = state.as_str(),
+        ))?;
+        Ok(())
+    }
+
+    /// Set `document.visibilityState` and `document.hidden`, firing
+    /// `visibilitychange` at the document if they change.
+    pub fn set_visibility_state(&self, state: VisibilityState) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "(function() {{ \
+                 if (document.visibilityState === {state:?}) return; \
+                 document.visibilityState = {state:?}; \
+                 document.hidden = {hidden}; \
+                 document.dispatchEvent({{ type: 'visibilitychange', bubbles: true, cancelable: false, \
+                     defaultPrevented: false }}); \
+             }})();",
+            state = state.as_str(),
+            hidden = state == VisibilityState::Hidden,
+        ))?;
+        Ok(())
+    }
+
+    /// Take the pointer lock requests the page made since the last call,
+    /// in order.
+    pub fn drain_pointer_lock_requests(&self) -> Vec<PointerLockRequest> {
+        let json = match self.evaluate("window.__drainPointerLockQueue()") {
+            Ok(JsValue::String(json)) => json,
+            Ok(_) => return Vec::new(),
+            Err(e) => {
+                trace!(error = %e, "Failed to drain pointer lock queue");
+                return Vec::new();
+            }
+        };
+        match serde_json::from_str::<Vec<Option<String>>>(&json) {
+            Ok(requests) => requests
+                .into_iter()
+                .map(|request| match request {
+                    Some(element_id) => PointerLockRequest::Lock { element_id },
+                    None => PointerLockRequest::Exit,
+                })
+                .collect(),
+            Err(e) => {
+                trace!(error = %e, "Failed to parse pointer lock queue JSON");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Lock the pointer to the element that asked for it, or release it,
+    /// firing `pointerlockchange` at the document.
+    pub fn set_pointer_locked(&self, locked: bool) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "(function() {{ \
+                 var element = {locked} ? document._pointerLockPending : null; \
+                 document._pointerLockPending = null; \
+                 if (document.pointerLockElement === element) return; \
+                 document.pointerLockElement = element; \
+                 document.dispatchEvent({{ type: 'pointerlockchange', bubbles: false, cancelable: false, \
+                     defaultPrevented: false }}); \
+             }})();"
+        ))?;
+        Ok(())
+    }
+
+    /// Refuse the page's pending pointer lock request, firing
+    /// `pointerlockerror` at the document.
+    pub fn reject_pointer_lock(&self) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(
+            "document._pointerLockPending = null; \
+             document.dispatchEvent({ type: 'pointerlockerror', bubbles: false, cancelable: false, \
+                 defaultPrevented: false });",
+        )?;
+        Ok(())
+    }
+
+    /// Fire `mousemove` with raw relative motion at the pointer lock
+    /// element, then the document.
+    pub fn dispatch_pointer_movement(&self, movement_x: f64, movement_y: f64) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "(function() {{ \
+                 var target = document.pointerLockElement; \
+                 if (!target) return; \
+                 var event = {{ type: 'mousemove', bubbles: true, cancelable: true, defaultPrevented: false, \
+                     target: target, movementX: {movement_x}, movementY: {movement_y} }}; \
+                 if (typeof target.dispatchEvent === 'function' && target._listeners) target.dispatchEvent(event); \
+                 else if (typeof target.onmousemove === 'function') target.onmousemove(event); \
+                 document.dispatchEvent(event); \
+             }})();"
+        ))?;
+        Ok(())
+    }
+
+    /// Set the gamepad at `index` of `navigator.getGamepads()`, or with
+    /// `None` disconnect it, firing `gamepadconnected` or
+    /// `gamepaddisconnected` at the window as it comes and goes.
+    pub fn set_gamepad(&self, index: usize, gamepad: Option<&GamepadState>) -> Result<(), BindingError> {
+        let gamepad = match gamepad {
+            Some(gamepad) => {
+                let axes: Vec<String> = gamepad.axes.iter().map(|axis| axis.to_string()).collect();
+                let buttons: Vec<String> = gamepad
+                    .buttons
+                    .iter()
+                    .map(|button| {
+                        format!(
+                            "{{ pressed: {}, touched: {}, value: {} }}",
+                            button.pressed, button.touched, button.value
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{ id: {:?}, mapping: {:?}, axes: [{}], buttons: [{}], timestamp: {} }}",
+                    gamepad.id,
+                    gamepad.mapping.as_str(),
+                    axes.join(", "),
+                    buttons.join(", "),
+                    gamepad.timestamp
+                )
+            }
+            None => "null".to_string(),
+        };
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.__setGamepad({index}, {gamepad});"))?;
+        Ok(())
+    }
+
+    /// Take the Notifications API calls the page made since the last call,
+    /// in order.
+    pub fn drain_notification_requests(&self) -> Vec<NotificationRequest> {
+        self.drain_queue("window.__drainNotificationQueue()", "notification")
+    }
+
+    /// Take the changes the page's script made to its document since the
+    /// last call, in order.
+    pub fn drain_dom_mutations(&self) -> Vec<DomMutation> {
+        self.drain_queue("window.__drainDomMutationQueue()", "DOM mutation")
+    }
+
+    /// Tie the element script inserted as [`DomMutation::Insert`] `key` to
+    /// `node`, the DOM node made for it, so its own changes reach the DOM.
+    pub fn bind_node(&self, key: u64, node: &Rc<Node>) -> Result<(), BindingError> {
+        self.node_map.borrow_mut().insert(node.id.raw() as u64, node.clone());
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.__bindNode({key}, {});", node.id.raw()))?;
+        Ok(())
+    }
+
+    /// Schedule the timers the page set since the last call, counting their
+    /// delays from `now`, and cancel those it cleared.
+    pub fn take_timer_requests(&self, now: Instant) {
+        let requests: Vec<TimerRequest> = self.drain_queue("window.__drainTimerQueue()", "timer");
+        let nesting = self.timer_nesting.get() + 1;
+        let mut timers = self.timers.borrow_mut();
+        for request in requests {
+            match request {
+                TimerRequest::Set { id, delay, repeat } => {
+                    timers.schedule(id, Duration::from_millis(delay), repeat, nesting, now)
+                }
+                TimerRequest::Clear { id } => {
+                    timers.cancel(id);
+                }
+            }
+        }
+    }
+
+    /// Run the callbacks of the page's timers due at `now`, in the order
+    /// they fell due. Returns how many timers fired. A callback throwing
+    /// doesn't stop the others.
+    pub fn run_timers(&self, now: Instant) -> usize {
+        self.take_timer_requests(now);
+        let due = self.timers.borrow_mut().take_due(now);
+        let fired = due.len();
+        for (id, nesting) in due {
+            self.timer_nesting.set(nesting);
+            let result = self
+                .runtime
+                .borrow_mut()
+                .evaluate_script(&format!("window.__fireTimer({id});"));
+            if let Err(e) = result {
+                debug!(id, error = %e, "Timer callback threw");
+            }
+            self.take_timer_requests(now);
+        }
+        self.timer_nesting.set(0);
+        fired
+    }
+
+    /// When the page's next timer is due, if it has any.
+    pub fn next_timer_due(&self) -> Option<Instant> {
+        self.timers.borrow().next_due()
+    }
+
+    /// Take the beacons the page sent since the last call, in order.
+    pub fn drain_keepalive_requests(&self) -> Vec<KeepaliveRequest> {
+        self.drain_queue("window.__drainKeepaliveQueue()", "keepalive")
+    }
+
+    /// Take the `fetch()` and `XMLHttpRequest` requests the page made since
+    /// the last call, in order.
+    pub fn drain_fetch_requests(&self) -> Vec<FetchRequest> {
+        self.drain_queue("window.__drainFetchQueue()", "fetch")
+    }
+
+    /// Settle the page's request `id` with a response.
+    pub fn resolve_fetch(&self, id: u64, response: &FetchResponse) -> Result<(), BindingError> {
+        let response = serde_json::to_string(response).map_err(|e| BindingError::InvalidArgument(e.to_string()))?;
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.__resolveFetch({id}, {response});"))?;
+        Ok(())
+    }
+
+    /// Fail the page's request `id` with a network error: `fetch()`
+    /// rejects with a `TypeError` carrying `message`.
+    pub fn reject_fetch(&self, id: u64, message: &str) -> Result<(), BindingError> {
+        let message = serde_json::to_string(message).map_err(|e| BindingError::InvalidArgument(e.to_string()))?;
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.__rejectFetch({id}, {message});"))?;
+        Ok(())
+    }
+
+    /// Run a JS function that empties a queue into a JSON array, and parse
+    /// it.
+    fn drain_queue<T: DeserializeOwned>(&self, drain: &str, queue: &str) -> Vec<T> {
+        let json = match self.evaluate(drain) {
+            Ok(JsValue::String(json)) => json,
+            Ok(_) => return Vec::new(),
+            Err(e) => {
+                trace!(queue, error = %e, "Failed to drain queue");
+                return Vec::new();
+            }
+        };
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            trace!(queue, error = %e, "Failed to parse queue JSON");
+            Vec::new()
+        })
+    }
+
+    /// Set `Notification.permission`.
+    pub fn set_notification_permission(&self, state: PermissionState) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "window.__setNotificationPermission({:?}, false);",
+            notification_permission(state)
+        ))?;
+        Ok(())
+    }
+
+    /// Answer the page's pending `Notification.requestPermission()` calls
+    /// with `state`, which `Notification.permission` takes.
+    pub fn resolve_notification_permission(&self, state: PermissionState) -> Result<(), BindingError> {
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "window.__setNotificationPermission({:?}, true);",
+            notification_permission(state)
+        ))?;
+        Ok(())
+    }
+
+    /// Fire `event` (`show`, `click`, `close` or `error`) at the page's
+    /// notification `id`. After `close` or `error` the page hears no more
+    /// about it.
+    pub fn dispatch_notification_event(&self, id: u64, event: &str) -> Result<(), BindingError> {
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.__dispatchNotificationEvent({id}, {event:?});"))?;
+        Ok(())
+    }
+
+    /// The page's `history.scrollRestoration` mode.
+    pub fn scroll_restoration(&self) -> ScrollRestoration {
+        match self.evaluate("window.history.scrollRestoration") {
+            Ok(JsValue::String(mode)) if mode == "manual" => ScrollRestoration::Manual,
+            _ => ScrollRestoration::Auto,
+        }
+    }
+
+    /// Set `history.scrollRestoration`, as kept with the current history
+    /// entry.
+    pub fn set_scroll_restoration(&self, mode: ScrollRestoration) -> Result<(), BindingError> {
+        let mode = match mode {
+            ScrollRestoration::Auto => "auto",
+            ScrollRestoration::Manual => "manual",
+        };
+        self.window.borrow_mut().history.scroll_restoration = mode.to_string();
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.history.scrollRestoration = {mode:?};"))?;
+        Ok(())
+    }
+
+    /// Set window dimensions.
+    pub fn set_dimensions(&self, width: f64, height: f64) -> Result<(), BindingError> {
+        let mut window = self.window.borrow_mut();
+        window.inner_width = width;
+        window.inner_height = height;
+        window.outer_width = width;
+        window.outer_height = height;
+        drop(window);
+
+        let mut runtime = self.runtime.borrow_mut();
+        runtime.evaluate_script(&format!(
+            "window.innerWidth = {}; window.innerHeight = {}; \
+             window.outerWidth = {}; window.outerHeight = {};",
+            width, height, width, height
+        ))?;
+
+        Ok(())
+    }
+
+    /// Set the device pixel ratio.
+    pub fn set_device_pixel_ratio(&self, ratio: f64) -> Result<(), BindingError> {
+        self.window.borrow_mut().device_pixel_ratio = ratio;
+
+        let mut runtime = self.runtime.borrow_mut();
+        runtime.evaluate_script(&format!("window.devicePixelRatio = {};", ratio))?;
+
+        Ok(())
+    }
+
+    /// Set the user agent reported by `navigator.userAgent`.
+    pub fn set_user_agent(&self, user_agent: &str) -> Result<(), BindingError> {
+        self.window.borrow_mut().navigator.user_agent = user_agent.to_string();
+
+        let mut runtime = self.runtime.borrow_mut();
+        runtime.evaluate_script(&format!(
+            "window.navigator.userAgent = {:?};",
+            user_agent
+        ))?;
+
+        Ok(())
+    }
+
+    /// Set the locale reported by `navigator.language` and that `Intl`
+    /// and `toLocaleString()` format in by default, as a BCP 47 tag like
+    /// `de-DE`.
+    pub fn set_locale(&self, locale: &str) -> Result<(), BindingError> {
+        let mut languages = vec![locale.to_string()];
+        if let Some((language, _)) = locale.split_once('-') {
+            languages.push(language.to_string());
+        }
+        let mut window = self.window.borrow_mut();
+        window.navigator.language = locale.to_string();
+        window.navigator.languages = languages.clone();
+        drop(window);
+
+        let mut runtime = self.runtime.borrow_mut();
+        runtime.evaluate_script(&format!(
+            "window.navigator.language = {:?}; window.navigator.languages = {:?}; Intl._defaultLocale = {:?};",
+            locale, languages, locale
+        ))?;
+
+        Ok(())
+    }
+
+    /// Put the page in an IANA time zone like `Europe/Berlin`, in which
+    /// `Date` reads and writes local time and `Intl` formats by default, or
+    /// back in the host's with `None`. Zones with daylight saving time
+    /// follow their current rules. Fails for zones it doesn't know.
+    pub fn set_time_zone(&self, time_zone: Option<&str>) -> Result<(), BindingError> {
+        let known = self
+            .runtime
+            .borrow_mut()
+            .evaluate_script(&format!(
+                "(function(name) {{ try {{ Intl._setTimeZone(name); return true; }} catch (e) {{ return false; }} }})({})",
+                time_zone.map_or("undefined".to_string(), |name| format!("{:?}", name))
+            ))?;
+        if !matches!(known, JsValue::Boolean(true)) {
+            return Err(BindingError::InvalidArgument(format!("Unknown time zone: {}", time_zone.unwrap_or_default())));
+        }
+        Ok(())
+    }
+
+    /// Set the virtual clock `Date` and `performance.now()` read from now
+    /// on, in place of the host's: the wall clock time `date`, and the time
+    /// `elapsed` since the clock started. `performance.now()` counts from
+    /// where the clock stood the first time it was set.
+    pub fn set_clock(&self, date: SystemTime, elapsed: Duration) -> Result<(), BindingError> {
+        let date = date
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_millis() as f64);
+        self.runtime.borrow_mut().evaluate_script(&format!(
+            "window.__setClock({date}, {});",
+            elapsed.as_secs_f64() * 1000.0
+        ))?;
+        Ok(())
+    }
+
+    /// Make `Math.random()` a generator seeded with `seed`, which returns
+    /// the same sequence every time.
+    pub fn seed_random(&self, seed: u64) -> Result<(), BindingError> {
+        let seed = (seed ^ (seed >> 32)) as u32;
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(&format!("window.__seedRandom({seed});"))?;
+        Ok(())
+    }
+
+    /// Evaluate a script in the bound context.
+    pub fn evaluate(&self, script: &str) -> Result<JsValue, BindingError> {
+        self.runtime
+            .borrow_mut()
+            .evaluate_script(script)
+            .map_err(Into::into)
+    }
+
+    /// Drain the IPC message queue.
+    ///
+    /// This method collects all IPC messages that were queued via
+    /// `window.ipc.postMessage()` since the last drain call.
+    ///
+    /// Returns a Vec of IpcMessage structs.
+    pub fn drain_ipc_queue(&self) -> Vec<IpcMessage> {
+        // Call JS to drain the queue and get JSON
+        let result = self.runtime
+            .borrow_mut()
+            .evaluate_script("window.__drainIpcQueue()");
+
+        match result {
+            Ok(JsValue::String(json)) => {
+                // Parse the JSON array
+                match serde_json::from_str::<Vec<String>>(&json) {
+                    Ok(messages) => {
+                        messages
+                            .into_iter()
+                            .map(|payload| IpcMessage { payload })
+                            .collect()
+                    }
+                    Err(e) => {
+                        trace!(error = %e, "Failed to parse IPC queue JSON");
+                        Vec::new()
+                    }
+                }
+            }
+            Ok(_) => {
+                trace!("IPC queue returned non-string value");
+                Vec::new()
+            }
+            Err(e) => {
+                trace!(error = %e, "Failed to drain IPC queue");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Check if there are pending IPC messages.
+    pub fn has_pending_ipc(&self) -> bool {
+        let result = self.runtime
+            .borrow_mut()
+            .evaluate_script("window.__ipcQueue.length > 0");
+
+        matches!(result, Ok(JsValue::Boolean(true)))
+    }
+
+    /// Add an event listener.
+    pub fn add_event_listener(
+        &self,
+        node_id: NodeId,
+        event_type: &str,
+        callback: &str,
+        capture: bool,
+    ) -> ListenerId {
         let id = ListenerId::new();
         let listener = EventListener {
             id,
@@ -1069,6 +3639,15 @@ impl DomBindings {
     }
 }
 
+/// `Notification.permission` for a permission state, which calls the
+/// prompt state `default`.
+fn notification_permission(state: PermissionState) -> &'static str {
+    match state {
+        PermissionState::Prompt => "default",
+        state => state.as_str(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1157,6 +3736,553 @@ mod tests {
         assert!(matches!(input_type, JsValue::String(s) if s == "text"));
     }
 
+    #[test]
+    fn test_dialog_element() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        bindings
+            .evaluate(
+                "var dialog = document.createElement('dialog'); var closed = ''; \
+                 dialog.onclose = function() { closed = dialog.returnValue; }; \
+                 dialog.showModal();",
+            )
+            .unwrap();
+        let open = bindings.evaluate("dialog.open && document._topLayer[0] === dialog").unwrap();
+        assert!(matches!(open, JsValue::Boolean(true)));
+
+        let error = bindings
+            .evaluate("try { dialog.show(); '' } catch (e) { e.message }")
+            .unwrap();
+        assert!(matches!(error, JsValue::String(s) if s.starts_with("InvalidStateError")));
+
+        bindings.evaluate("dialog.close('ok')").unwrap();
+        let closed = bindings.evaluate("closed + document._topLayer.length").unwrap();
+        assert!(matches!(closed, JsValue::String(s) if s == "ok0"));
+    }
+
+    #[test]
+    fn test_popover_element() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        let error = bindings
+            .evaluate("var plain = document.createElement('div'); try { plain.showPopover(); '' } catch (e) { e.message }")
+            .unwrap();
+        assert!(matches!(error, JsValue::String(s) if s.starts_with("NotSupportedError")));
+
+        bindings
+            .evaluate(
+                "var menu = document.createElement('div'); menu.popover = 'auto'; \
+                 var other = document.createElement('div'); other.popover = ''; \
+                 var pinned = document.createElement('div'); pinned.popover = 'manual'; \
+                 var states = []; menu.ontoggle = function(e) { states.push(e.newState); }; \
+                 pinned.showPopover(); menu.showPopover(); other.showPopover();",
+            )
+            .unwrap();
+        let stack = bindings
+            .evaluate("states.join() + ' ' + document._topLayer.length + ' ' + menu.togglePopover()")
+            .unwrap();
+        assert!(matches!(stack, JsValue::String(s) if s == "open,closed 2 true"));
+
+        let state = bindings.evaluate("pinned.popover + other._popoverOpen").unwrap();
+        assert!(matches!(state, JsValue::String(s) if s == "manualfalse"));
+    }
+
+    #[test]
+    fn test_hash_change_event() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        bindings
+            .evaluate(
+                "var seen = []; window.onhashchange = function(e) { seen.push('on:' + e.newURL); }; \
+                 function listener(e) { seen.push(e.oldURL); } \
+                 window.addEventListener('hashchange', listener); \
+                 window.addEventListener('hashchange', listener);",
+            )
+            .unwrap();
+        let old_url = Url::parse("https://example.com/page").unwrap();
+        let new_url = Url::parse("https://example.com/page#intro").unwrap();
+        bindings.set_location(&new_url).unwrap();
+        bindings.dispatch_hash_change(&old_url, &new_url).unwrap();
+
+        let seen = bindings.evaluate("seen.join(' ') + ' ' + window.location.hash").unwrap();
+        assert!(
+            matches!(seen, JsValue::String(s) if s == "on:https://example.com/page#intro https://example.com/page #intro")
+        );
+
+        bindings.evaluate("window.removeEventListener('hashchange', listener); seen = [];").unwrap();
+        bindings.dispatch_hash_change(&new_url, &old_url).unwrap();
+        let seen = bindings.evaluate("seen.length").unwrap();
+        assert!(matches!(seen, JsValue::Number(n) if n == 1.0));
+    }
+
+    #[test]
+    fn test_ready_state_transitions() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        bindings
+            .evaluate(
+                "var seen = []; \
+                 document.onreadystatechange = function() { seen.push(document.readyState); }; \
+                 document.addEventListener('DOMContentLoaded', function(e) { seen.push(e.type); }); \
+                 window.addEventListener('load', function(e) { seen.push(e.type); });",
+            )
+            .unwrap();
+        bindings.set_ready_state(DocumentReadyState::Interactive).unwrap();
+        bindings.set_ready_state(DocumentReadyState::Complete).unwrap();
+        // Staying in a state fires nothing
+        bindings.set_ready_state(DocumentReadyState::Complete).unwrap();
+
+        let seen = bindings.evaluate("seen.join()").unwrap();
+        assert!(matches!(seen, JsValue::String(s) if s == "interactive,DOMContentLoaded,complete,load"));
+    }
+
+    #[test]
+    fn test_visibility_state() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        bindings
+            .evaluate(
+                "var seen = []; \
+                 document.addEventListener('visibilitychange', function() { \
+                     seen.push(document.visibilityState + ':' + document.hidden); \
+                 });",
+            )
+            .unwrap();
+        bindings.set_visibility_state(VisibilityState::Hidden).unwrap();
+        bindings.set_visibility_state(VisibilityState::Hidden).unwrap();
+        bindings.set_visibility_state(VisibilityState::Visible).unwrap();
+
+        let seen = bindings.evaluate("seen.join()").unwrap();
+        assert!(matches!(seen, JsValue::String(s) if s == "hidden:true,visible:false"));
+    }
+
+    #[test]
+    fn test_scroll_restoration_mode() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        assert_eq!(bindings.scroll_restoration(), ScrollRestoration::Auto);
+
+        bindings.evaluate("history.scrollRestoration = 'manual';").unwrap();
+        assert_eq!(bindings.scroll_restoration(), ScrollRestoration::Manual);
+        // Other values leave the mode alone
+        bindings.evaluate("history.scrollRestoration = 'smooth';").unwrap();
+        assert_eq!(bindings.scroll_restoration(), ScrollRestoration::Manual);
+
+        bindings.set_scroll_restoration(ScrollRestoration::Auto).unwrap();
+        let mode = bindings.evaluate("history.scrollRestoration").unwrap();
+        assert!(matches!(mode, JsValue::String(s) if s == "auto"));
+    }
+
+    #[test]
+    fn test_gamepads() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        bindings
+            .evaluate(
+                r#"
+            var log = [];
+            window.addEventListener('gamepadconnected', function(e) { log.push('connected ' + e.gamepad.index); });
+            window.addEventListener('gamepaddisconnected', function(e) { log.push('disconnected ' + e.gamepad.id); });
+        "#,
+            )
+            .unwrap();
+
+        let mut gamepad = GamepadState::standard("Pad");
+        bindings.set_gamepad(1, Some(&gamepad)).unwrap();
+        gamepad.buttons[0] = rustkit_core::GamepadButton::digital(true);
+        gamepad.axes[0] = -0.5;
+        bindings.set_gamepad(1, Some(&gamepad)).unwrap();
+        let polled = bindings
+            .evaluate(
+                "var pads = navigator.getGamepads(); \
+                 [pads.length, pads[0], pads[1].buttons[0].pressed, pads[1].axes[0], pads[1].mapping].join(',')",
+            )
+            .unwrap();
+        assert!(matches!(polled, JsValue::String(s) if s == "2,,true,-0.5,standard"));
+
+        bindings.set_gamepad(1, None).unwrap();
+        let log = bindings.evaluate("log.join('; ') + ' / ' + navigator.getGamepads().length").unwrap();
+        assert!(matches!(log, JsValue::String(s) if s == "connected 1; disconnected Pad / 0"));
+    }
+
+    #[test]
+    fn test_notifications() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        bindings
+            .evaluate(
+                r#"
+            var log = [];
+            Notification.requestPermission(function(p) { log.push('first ' + p); });
+            Notification.requestPermission(function(p) { log.push('second ' + p); });
+        "#,
+            )
+            .unwrap();
+        assert_eq!(bindings.drain_notification_requests(), [NotificationRequest::Permission]);
+
+        bindings.resolve_notification_permission(PermissionState::Granted).unwrap();
+        bindings
+            .evaluate(
+                r#"
+            var n = new Notification('Build done', { body: 'All green', tag: 'ci' });
+            n.onclick = function() { log.push('click'); };
+            n.addEventListener('close', function() { log.push('close'); });
+            n.close();
+        "#,
+            )
+            .unwrap();
+        assert_eq!(
+            bindings.drain_notification_requests(),
+            [
+                NotificationRequest::Show {
+                    id: 1,
+                    title: "Build done".to_string(),
+                    body: "All green".to_string(),
+                    icon: String::new(),
+                    tag: "ci".to_string(),
+                },
+                NotificationRequest::Close { id: 1 },
+            ]
+        );
+
+        // Closed notifications hear nothing more
+        bindings.dispatch_notification_event(1, "click").unwrap();
+        let log = bindings.evaluate("log.join('; ') + ' / ' + Notification.permission").unwrap();
+        assert!(matches!(log, JsValue::String(s) if s == "first granted; second granted; close / granted"));
+    }
+
+    #[test]
+    fn test_send_beacon() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        let sent = bindings
+            .evaluate(
+                "[navigator.sendBeacon('/collect', 'event=close'), \
+                  navigator.sendBeacon('/collect', new Array(70000).join('x'))].join()",
+            )
+            .unwrap();
+        assert!(matches!(sent, JsValue::String(s) if s == "true,false"));
+        assert_eq!(
+            bindings.drain_keepalive_requests(),
+            [KeepaliveRequest {
+                url: "/collect".to_string(),
+                method: "POST".to_string(),
+                content_type: Some("text/plain;charset=UTF-8".to_string()),
+                body: "event=close".to_string(),
+            }]
+        );
+        assert!(bindings.drain_keepalive_requests().is_empty());
+    }
+
+    #[test]
+    fn test_timers() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        let start = Instant::now();
+        bindings
+            .evaluate(
+                r#"
+            var log = [];
+            setTimeout(function(name) { log.push(name); }, 20, 'late');
+            var cancelled = setTimeout(function() { log.push('cancelled'); }, 5);
+            var ticks = 0;
+            var interval = setInterval(function() {
+                log.push('tick' + ++ticks);
+                if (ticks === 2) clearInterval(interval);
+            }, 10);
+            clearTimeout(cancelled);
+            queueMicrotask(function() { log.push('microtask'); });
+        "#,
+            )
+            .unwrap();
+        bindings.take_timer_requests(start);
+        assert_eq!(bindings.next_timer_due(), Some(start + Duration::from_millis(10)));
+
+        assert_eq!(bindings.run_timers(start + Duration::from_millis(10)), 1);
+        assert_eq!(bindings.run_timers(start + Duration::from_millis(20)), 2);
+        assert_eq!(bindings.run_timers(start + Duration::from_millis(40)), 0);
+        assert_eq!(bindings.next_timer_due(), None);
+
+        let log = bindings.evaluate("log.join()").unwrap();
+        assert!(matches!(log, JsValue::String(s) if s == "microtask,tick1,late,tick2"));
+    }
+
+    #[test]
+    fn test_idle_callbacks() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        let start = Instant::now();
+        bindings
+            .evaluate(
+                r#"
+            var idle = [];
+            requestIdleCallback(function(deadline) {
+                idle.push(deadline.didTimeout, deadline.timeRemaining() > 0);
+            });
+            cancelIdleCallback(requestIdleCallback(function() { idle.push('cancelled'); }));
+        "#,
+            )
+            .unwrap();
+        assert_eq!(bindings.run_timers(start), 1);
+
+        let idle = bindings.evaluate("idle.join()").unwrap();
+        assert!(matches!(idle, JsValue::String(s) if s == "false,true"));
+    }
+
+    #[test]
+    fn test_intl() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        let format = |script: &str| match bindings.evaluate(script).unwrap() {
+            JsValue::String(s) => s,
+            other => panic!("{script}: {other:?}"),
+        };
+
+        assert_eq!(format("new Intl.NumberFormat().format(1234567.891)"), "1,234,567.891");
+        assert_eq!(format("(0.256).toLocaleString('en-US', { style: 'percent' })"), "26%");
+        assert_eq!(
+            format("new Intl.NumberFormat('en-US', { style: 'currency', currency: 'USD' }).format(-1234.5)"),
+            "-$1,234.50"
+        );
+        assert_eq!(
+            format("Intl.NumberFormat('de-DE', { style: 'currency', currency: 'EUR' }).format(1234.5)"),
+            "1.234,50\u{a0}€"
+        );
+        assert_eq!(format("[1, 2.5].map(new Intl.NumberFormat('en', { minimumFractionDigits: 1 }).format).join(' ')"), "1.0 2.5");
+
+        let date = "new Date(Date.UTC(2024, 0, 5, 13, 5, 9))";
+        assert_eq!(format(&format!("new Intl.DateTimeFormat('en-US', {{ timeZone: 'UTC' }}).format({date})")), "1/5/2024");
+        assert_eq!(
+            format(&format!(
+                "{date}.toLocaleDateString('en-US', {{ weekday: 'long', year: 'numeric', month: 'long', day: 'numeric', timeZone: 'UTC' }})"
+            )),
+            "Friday, January 5, 2024"
+        );
+        assert_eq!(format(&format!("{date}.toLocaleString('en-US', {{ timeZone: 'UTC' }})")), "1/5/2024, 1:05:09 PM");
+        assert_eq!(format(&format!("{date}.toLocaleTimeString('de', {{ timeZone: 'UTC' }})")), "13:05:09");
+
+        // Formatting follows the page's locale unless asked for another
+        bindings.set_locale("de-DE").unwrap();
+        assert_eq!(format("navigator.languages.join()"), "de-DE,de");
+        assert_eq!(format("(1234.5).toLocaleString()"), "1.234,5");
+        assert_eq!(format(&format!("{date}.toLocaleDateString(undefined, {{ timeZone: 'UTC' }})")), "5.1.2024");
+        assert_eq!(format("Intl.DateTimeFormat().resolvedOptions().locale"), "de-DE");
+    }
+
+    #[test]
+    fn test_time_zone() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        let format = |script: &str| match bindings.evaluate(script).unwrap() {
+            JsValue::String(s) => s,
+            JsValue::Number(n) => n.to_string(),
+            other => panic!("{script}: {other:?}"),
+        };
+
+        bindings.set_time_zone(Some("America/New_York")).unwrap();
+        let winter = "new Date(Date.UTC(2024, 0, 5, 13, 5, 9))";
+        let summer = "new Date(Date.UTC(2024, 6, 5, 13, 5, 9))";
+        assert_eq!(format(&format!("{winter}.getHours()")), "8");
+        assert_eq!(format(&format!("{summer}.getTimezoneOffset()")), "240");
+        assert_eq!(format(&format!("{winter}.toString()")), "Fri Jan 05 2024 08:05:09 GMT-0500 (America/New_York)");
+        assert_eq!(format(&format!("{summer}.toLocaleTimeString('en-US')")), "9:05:09 AM");
+        assert_eq!(format("Intl.DateTimeFormat().resolvedOptions().timeZone"), "America/New_York");
+        // Local times are the zone's, across the change to daylight saving
+        assert_eq!(format("new Date(2024, 2, 10, 12).toISOString()"), "2024-03-10T16:00:00.000Z");
+        assert_eq!(format("new Date('2024-03-09T12:00:00').toISOString()"), "2024-03-09T17:00:00.000Z");
+        assert_eq!(format("var d = new Date(Date.UTC(2024, 0, 5)); d.setHours(23); d.toISOString()"), "2024-01-05T04:00:00.000Z");
+        assert_eq!(format("String(new Date(0) instanceof Date && Date.now() > 0)"), "true");
+
+        // Other zones can still be asked for
+        assert_eq!(
+            format(&format!("{summer}.toLocaleString('de-DE', {{ timeZone: 'Europe/Berlin' }})")),
+            "5.7.2024, 15:05:09"
+        );
+        assert_eq!(format(&format!("{winter}.toLocaleTimeString('en-GB', {{ timeZone: 'Etc/GMT-3' }})")), "16:05:09");
+        assert!(bindings.set_time_zone(Some("Mars/Olympus_Mons")).is_err());
+
+        bindings.set_time_zone(None).unwrap();
+        assert_eq!(format("String(Date.prototype.constructor === Date && Intl._timeZone === undefined)"), "true");
+    }
+
+    #[test]
+    fn test_virtual_clock_and_seeded_random() {
+        let new_bindings = || DomBindings::new(JsRuntime::new().unwrap()).unwrap();
+        let format = |bindings: &DomBindings, script: &str| match bindings.evaluate(script).unwrap() {
+            JsValue::String(s) => s,
+            JsValue::Number(n) => n.to_string(),
+            other => panic!("{script}: {other:?}"),
+        };
+        let bindings = new_bindings();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        bindings.set_clock(start, Duration::from_millis(500)).unwrap();
+        assert_eq!(format(&bindings, "Date.now()"), "1700000000000");
+        assert_eq!(format(&bindings, "new Date().toISOString()"), "2023-11-14T22:13:20.000Z");
+        assert_eq!(format(&bindings, "performance.now()"), "0");
+        // Explicit times and the Date API are untouched
+        assert_eq!(format(&bindings, "new Date(0).getTime()"), "0");
+        assert_eq!(format(&bindings, "new Date(2024, 0, 5).getFullYear()"), "2024");
+        assert_eq!(format(&bindings, "String(new Date() instanceof Date)"), "true");
+
+        // The clock only moves when it's set
+        bindings.set_clock(start + Duration::from_millis(16), Duration::from_millis(516)).unwrap();
+        assert_eq!(format(&bindings, "Date.now() - 1700000000000"), "16");
+        assert_eq!(format(&bindings, "performance.now()"), "16");
+        bindings.set_time_zone(Some("Europe/Berlin")).unwrap();
+        assert_eq!(format(&bindings, "new Date().getHours()"), "23");
+
+        // The same seed gives the same sequence in every page
+        let other = new_bindings();
+        bindings.seed_random(42).unwrap();
+        other.seed_random(42).unwrap();
+        let sequence = "[Math.random(), Math.random(), Math.random()].join()";
+        let first = format(&bindings, sequence);
+        assert_eq!(first, format(&other, sequence));
+        assert!(first.split(',').all(|n| (0.0..1.0).contains(&n.parse::<f64>().unwrap())));
+        other.seed_random(7).unwrap();
+        assert_ne!(format(&other, sequence), format(&bindings, sequence));
+    }
+
+    #[test]
+    fn test_dom_mutations() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        let document = Rc::new(Document::parse_html(r#"<div id="box" class="a"><p id="old">Old</p></div>"#).unwrap());
+        bindings.set_document(document.clone()).unwrap();
+        let node = |id: &str| document.get_element_by_id(id).unwrap().id.raw();
+
+        bindings
+            .evaluate(
+                r#"
+            var box = document.getElementById('box');
+            box.className = 'b';
+            box.removeChild(document.getElementById('old'));
+            var item = document.createElement('li');
+            item.textContent = 'a < b';
+            box.appendChild(item);
+            document.body.innerHTML = '<em>hi</em>';
+        "#,
+            )
+            .unwrap();
+        let body = document.body().unwrap().id.raw();
+        let mutations = bindings.drain_dom_mutations();
+        assert_eq!(
+            mutations,
+            [
+                DomMutation::SetAttribute { node: node("box"), name: "class".into(), value: "b".into() },
+                DomMutation::Remove { node: node("old") },
+                DomMutation::Insert {
+                    parent: node("box"),
+                    before: None,
+                    markup: "<li>a &lt; b</li>".into(),
+                    key: Some(1),
+                },
+                DomMutation::ReplaceChildren { node: body, markup: "<em>hi</em>".into() },
+            ]
+        );
+
+        // Once bound, the inserted element's own changes reach the DOM
+        let li = document.parse_fragment("<li></li>").unwrap().first_child().unwrap();
+        bindings.bind_node(1, &li).unwrap();
+        bindings.evaluate("item.setAttribute('title', 'x');").unwrap();
+        assert_eq!(
+            bindings.drain_dom_mutations(),
+            [DomMutation::SetAttribute { node: li.id.raw(), name: "title".into(), value: "x".into() }]
+        );
+    }
+
+    #[test]
+    fn test_fetch() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        bindings
+            .evaluate(
+                r#"
+            var log = [];
+            fetch('/api', { method: 'post', body: '{}', headers: { 'Content-Type': 'application/json' } })
+                .then(function(r) { log.push(r.status + ' ' + r.ok + ' ' + r.headers.get('x-count')); return r.json(); })
+                .then(function(data) { log.push('count=' + data.count); });
+            fetch('https://other.test/', { mode: 'same-origin' })
+                .catch(function(e) { log.push(e.name + ': ' + e.message); });
+            var xhr = new XMLHttpRequest();
+            xhr.onload = function() { log.push('xhr ' + xhr.status + ' ' + xhr.responseText); };
+            xhr.open('GET', '/text');
+            xhr.send();
+        "#,
+            )
+            .unwrap();
+
+        let requests = bindings.drain_fetch_requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].headers, [("content-type".to_string(), "application/json".to_string())]);
+        assert_eq!(requests[0].body.as_deref(), Some("{}"));
+        assert_eq!(requests[0].mode, FetchMode::Cors);
+        assert_eq!(requests[0].credentials, FetchCredentials::SameOrigin);
+        assert_eq!(requests[1].mode, FetchMode::SameOrigin);
+        assert_eq!(requests[2].url, "/text");
+
+        let response = |body: &str| FetchResponse {
+            url: "https://example.test/".to_string(),
+            status: 200,
+            status_text: "OK".to_string(),
+            headers: vec![("x-count".to_string(), "3".to_string())],
+            body: body.to_string(),
+            redirected: false,
+            response_type: FetchResponseType::Basic,
+        };
+        bindings.resolve_fetch(requests[0].id, &response(r#"{"count": 3}"#)).unwrap();
+        bindings.reject_fetch(requests[1].id, "Cross-origin request blocked").unwrap();
+        bindings.resolve_fetch(requests[2].id, &response("hello")).unwrap();
+
+        let log = bindings.evaluate("log.join('|')").unwrap();
+        assert!(matches!(log, JsValue::String(s) if s ==
+            "200 true 3|count=3|TypeError: Cross-origin request blocked|xhr 200 hello"));
+    }
+
+    #[test]
+    fn test_pointer_lock() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+        bindings
+            .evaluate(
+                r#"
+            var log = [];
+            var canvas = document.createElement('canvas');
+            canvas.id = 'game';
+            canvas.onmousemove = function(e) { log.push(e.movementX + ',' + e.movementY); };
+            document.addEventListener('pointerlockchange', function() {
+                log.push(document.pointerLockElement ? 'locked' : 'released');
+            });
+            document.addEventListener('pointerlockerror', function() { log.push('error'); });
+            canvas.requestPointerLock();
+            canvas.requestPointerLock();
+        "#,
+            )
+            .unwrap();
+        assert_eq!(
+            bindings.drain_pointer_lock_requests(),
+            [PointerLockRequest::Lock { element_id: "game".to_string() }]
+        );
+
+        bindings.set_pointer_locked(true).unwrap();
+        bindings.dispatch_pointer_movement(3.0, -2.0).unwrap();
+        bindings.evaluate("document.exitPointerLock();").unwrap();
+        assert_eq!(bindings.drain_pointer_lock_requests(), [PointerLockRequest::Exit]);
+        bindings.set_pointer_locked(false).unwrap();
+
+        bindings.evaluate("canvas.requestPointerLock();").unwrap();
+        bindings.reject_pointer_lock().unwrap();
+        let log = bindings.evaluate("log.join(' ')").unwrap();
+        assert!(matches!(log, JsValue::String(s) if s == "locked 3,-2 released error"));
+    }
+
     #[test]
     fn test_input_element_value() {
         let runtime = JsRuntime::new().unwrap();
@@ -1288,4 +4414,74 @@ mod tests {
         let method = bindings.evaluate("form.method").unwrap();
         assert!(matches!(method, JsValue::String(s) if s == "post"));
     }
+
+    #[test]
+    fn test_range_and_tree_walker() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        bindings
+            .evaluate(
+                r#"
+            var p = document.createElement('p');
+            var one = p.appendChild(document.createTextNode('one '));
+            var b = p.appendChild(document.createElement('b'));
+            var two = b.appendChild(document.createTextNode('two'));
+            p.appendChild(document.createTextNode(' three'));
+            var range = document.createRange();
+            range.setStart(one, 2);
+            range.setEnd(two, 1);
+        "#,
+            )
+            .unwrap();
+
+        let text = bindings.evaluate("range.toString()").unwrap();
+        assert!(matches!(text, JsValue::String(s) if s == "e t"));
+
+        bindings.evaluate("var fragment = range.extractContents()").unwrap();
+        let remaining = bindings.evaluate("one.textContent + two.textContent").unwrap();
+        assert!(matches!(remaining, JsValue::String(s) if s == "onwo"));
+        let collapsed = bindings.evaluate("range.collapsed").unwrap();
+        assert!(matches!(collapsed, JsValue::Boolean(true)));
+
+        let walked = bindings
+            .evaluate(
+                r#"
+            var walker = document.createTreeWalker(p, NodeFilter.SHOW_TEXT);
+            var texts = [];
+            while (walker.nextNode()) texts.push(walker.currentNode.textContent);
+            texts.join('|');
+        "#,
+            )
+            .unwrap();
+        assert!(matches!(walked, JsValue::String(s) if s == "on|wo| three"));
+    }
+
+    #[test]
+    fn test_dom_parser_and_serializer() {
+        let runtime = JsRuntime::new().unwrap();
+        let bindings = DomBindings::new(runtime).unwrap();
+
+        bindings
+            .evaluate(
+                r#"
+            var parser = new DOMParser();
+            var feed = parser.parseFromString(
+                '<feed xmlns="http://www.w3.org/2005/Atom"><title>A &amp; B</title><entry/></feed>',
+                'application/xml');
+            var broken = parser.parseFromString('<feed><title></feed>', 'application/xml');
+        "#,
+            )
+            .unwrap();
+
+        let title = bindings.evaluate("feed.getElementsByTagName('title')[0].textContent").unwrap();
+        assert!(matches!(title, JsValue::String(s) if s == "A & B"));
+
+        let error = bindings.evaluate("broken.documentElement.tagName").unwrap();
+        assert!(matches!(error, JsValue::String(s) if s == "parsererror"));
+
+        let markup = bindings.evaluate("new XMLSerializer().serializeToString(feed)").unwrap();
+        assert!(matches!(markup, JsValue::String(s)
+            if s == r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>A &amp; B</title><entry/></feed>"#));
+    }
 }
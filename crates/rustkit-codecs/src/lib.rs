@@ -3,7 +3,7 @@
 //! Minimal image codec layer used by RustKit to remove the large `image` crate dependency.
 //!
 //! Current support:
-//! - PNG (via `png` crate)
+//! - PNG decode and encode (via `png` crate)
 //! - JPEG (via `jpeg-decoder` crate)
 //! - GIF (static + animated via `gif` crate)
 //!
@@ -25,7 +25,7 @@ pub enum ImageFormat {
     Unknown,
 }
 
-/// Errors that can occur during decoding.
+/// Errors that can occur during decoding or encoding.
 #[derive(Error, Debug)]
 pub enum CodecError {
     #[error("Unsupported image format: {0:?}")]
@@ -36,6 +36,9 @@ pub enum CodecError {
 
     #[error("Decode error: {0}")]
     Decode(String),
+
+    #[error("Encode error: {0}")]
+    Encode(String),
 }
 
 /// A simple RGBA8 image buffer.
@@ -91,6 +94,51 @@ impl RgbaImage {
     pub fn pixels_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
         self.data.chunks_exact_mut(4)
     }
+
+    /// Shrink the image to `width`x`height`, averaging the pixels each
+    /// output pixel covers. Sizes no smaller than the image's return a copy.
+    pub fn downscale(&self, width: u32, height: u32) -> RgbaImage {
+        let (width, height) = (width.max(1), height.max(1));
+        if width >= self.width && height >= self.height {
+            return self.clone();
+        }
+        let (width, height) = (width.min(self.width), height.min(self.height));
+        let span = |out: u32, out_len: u32, len: u32| {
+            let start = (out as u64 * len as u64 / out_len as u64) as u32;
+            let end = ((out as u64 + 1) * len as u64 / out_len as u64) as u32;
+            start..end.max(start + 1)
+        };
+
+        let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+        for out_y in 0..height {
+            let rows = span(out_y, height, self.height);
+            for out_x in 0..width {
+                let columns = span(out_x, width, self.width);
+                // Colors are averaged premultiplied, so transparent pixels
+                // don't darken their neighbors
+                let mut sum = [0u64; 4];
+                for y in rows.clone() {
+                    let row = y as usize * self.width as usize;
+                    for x in columns.clone() {
+                        let pixel = &self.data[(row + x as usize) * 4..][..4];
+                        let alpha = pixel[3] as u64;
+                        sum[0] += pixel[0] as u64 * alpha;
+                        sum[1] += pixel[1] as u64 * alpha;
+                        sum[2] += pixel[2] as u64 * alpha;
+                        sum[3] += alpha;
+                    }
+                }
+                let count = (rows.len() * columns.len()) as u64;
+                if sum[3] == 0 {
+                    data.extend_from_slice(&[0, 0, 0, 0]);
+                } else {
+                    data.extend(sum[..3].iter().map(|c| ((c + sum[3] / 2) / sum[3]) as u8));
+                    data.push(((sum[3] + count / 2) / count) as u8);
+                }
+            }
+        }
+        RgbaImage { width, height, data }
+    }
 }
 
 /// One decoded animation frame.
@@ -143,6 +191,76 @@ pub fn decode_any(bytes: &[u8]) -> Result<Decoded, CodecError> {
     }
 }
 
+/// Read an image's size from its header, without decoding it.
+pub fn dimensions(bytes: &[u8]) -> Result<(u32, u32), CodecError> {
+    let fmt = detect_format(bytes).unwrap_or(ImageFormat::Unknown);
+    match fmt {
+        ImageFormat::Png => {
+            let reader = png::Decoder::new(bytes)
+                .read_info()
+                .map_err(|e| CodecError::Decode(e.to_string()))?;
+            let info = reader.info();
+            Ok((info.width, info.height))
+        }
+        ImageFormat::Jpeg => {
+            let mut decoder = jpeg_decoder::Decoder::new(std::io::Cursor::new(bytes));
+            decoder
+                .read_info()
+                .map_err(|e| CodecError::Decode(e.to_string()))?;
+            let info = decoder
+                .info()
+                .ok_or_else(|| CodecError::Decode("Missing JPEG info".into()))?;
+            Ok((info.width as u32, info.height as u32))
+        }
+        ImageFormat::Gif => {
+            let decoder = gif::DecodeOptions::new()
+                .read_info(std::io::Cursor::new(bytes))
+                .map_err(|e| CodecError::Decode(e.to_string()))?;
+            Ok((decoder.width() as u32, decoder.height() as u32))
+        }
+        ImageFormat::WebP | ImageFormat::Bmp | ImageFormat::Ico | ImageFormat::Unknown => {
+            Err(CodecError::Unsupported(fmt))
+        }
+    }
+}
+
+/// The size to decode an image of `natural` size at to draw it at
+/// `target` size: scaled down to just cover `target`, keeping its aspect
+/// ratio, and never scaled up.
+pub fn scaled_size(natural: (u32, u32), target: (u32, u32)) -> (u32, u32) {
+    let (width, height) = natural;
+    if width == 0 || height == 0 {
+        return natural;
+    }
+    let scale = (target.0 as f64 / width as f64)
+        .max(target.1 as f64 / height as f64)
+        .min(1.0);
+    (
+        ((width as f64 * scale).ceil() as u32).clamp(1, width),
+        ((height as f64 * scale).ceil() as u32).clamp(1, height),
+    )
+}
+
+/// Decode image bytes no larger than needed to draw them at `target` size
+/// (see [`scaled_size`]). JPEGs are scaled while they decode, so a huge
+/// photo never exists at full size; other formats are decoded, then
+/// shrunk. Animations are decoded at full size.
+pub fn decode_scaled(bytes: &[u8], target: (u32, u32)) -> Result<Decoded, CodecError> {
+    let size = scaled_size(dimensions(bytes)?, target);
+    match detect_format(bytes).unwrap_or(ImageFormat::Unknown) {
+        ImageFormat::Jpeg => {
+            let mut decoder = jpeg_decoder::Decoder::new(std::io::Cursor::new(bytes));
+            let clamp = |n: u32| n.min(u16::MAX as u32) as u16;
+            decoder
+                .scale(clamp(size.0), clamp(size.1))
+                .map_err(|e| CodecError::Decode(e.to_string()))?;
+            Ok(Decoded::Static(jpeg_to_rgba(decoder)?.downscale(size.0, size.1)))
+        }
+        ImageFormat::Png => Ok(Decoded::Static(decode_png(bytes)?.downscale(size.0, size.1))),
+        _ => decode_any(bytes),
+    }
+}
+
 pub fn decode_png(bytes: &[u8]) -> Result<RgbaImage, CodecError> {
     let mut decoder = png::Decoder::new(bytes);
     // Expand palette/gray to RGB, add alpha, strip 16-bit.
@@ -178,8 +296,30 @@ pub fn decode_png(bytes: &[u8]) -> Result<RgbaImage, CodecError> {
     RgbaImage::from_rgba8(width, height, rgba)
 }
 
+/// Encode an image as an 8-bit RGBA PNG.
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, CodecError> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| CodecError::Encode(e.to_string()))?;
+    writer
+        .write_image_data(&image.data)
+        .map_err(|e| CodecError::Encode(e.to_string()))?;
+    writer
+        .finish()
+        .map_err(|e| CodecError::Encode(e.to_string()))?;
+    Ok(bytes)
+}
+
 pub fn decode_jpeg(bytes: &[u8]) -> Result<RgbaImage, CodecError> {
-    let mut decoder = jpeg_decoder::Decoder::new(std::io::Cursor::new(bytes));
+    jpeg_to_rgba(jpeg_decoder::Decoder::new(std::io::Cursor::new(bytes)))
+}
+
+/// Decode a JPEG at the size its decoder is set up for.
+fn jpeg_to_rgba(mut decoder: jpeg_decoder::Decoder<std::io::Cursor<&[u8]>>) -> Result<RgbaImage, CodecError> {
     let pixels = decoder
         .decode()
         .map_err(|e| CodecError::Decode(e.to_string()))?;
@@ -289,6 +429,41 @@ mod tests {
         let bytes = b"GIF89a....";
         assert_eq!(detect_format(bytes), Some(ImageFormat::Gif));
     }
+
+    #[test]
+    fn test_png_round_trip() {
+        let data: Vec<u8> = (0..2 * 3 * 4).map(|i| (i * 10) as u8).collect();
+        let image = RgbaImage::from_rgba8(2, 3, data.clone()).unwrap();
+        let bytes = encode_png(&image).unwrap();
+        assert_eq!(detect_format(&bytes), Some(ImageFormat::Png));
+
+        let decoded = decode_png(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 3));
+        assert_eq!(decoded.data(), &data[..]);
+    }
+
+    #[test]
+    fn test_decode_scaled() {
+        // Opaque red on the left half, transparent on the right
+        let data: Vec<u8> = (0..8 * 4)
+            .flat_map(|i| if i % 8 < 4 { [255, 0, 0, 255] } else { [0, 0, 255, 0] })
+            .collect();
+        let bytes = encode_png(&RgbaImage::from_rgba8(8, 4, data).unwrap()).unwrap();
+        assert_eq!(dimensions(&bytes).unwrap(), (8, 4));
+
+        assert_eq!(scaled_size((8, 4), (2, 2)), (4, 2));
+        assert_eq!(scaled_size((8, 4), (100, 1)), (8, 4));
+        let Decoded::Static(small) = decode_scaled(&bytes, (2, 2)).unwrap() else {
+            panic!("expected a static image");
+        };
+        assert_eq!((small.width(), small.height()), (4, 2));
+        assert_eq!(&small.data()[..4], &[255, 0, 0, 255]);
+        assert_eq!(&small.data()[12..16], &[0, 0, 0, 0]);
+
+        // Pixels straddling the edge average their colors by coverage
+        let edge = RgbaImage::from_rgba8(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 0]).unwrap().downscale(1, 1);
+        assert_eq!(edge.data(), &[255, 0, 0, 128]);
+    }
 }
 
 
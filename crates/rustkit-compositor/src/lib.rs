@@ -10,14 +10,19 @@
 //! 4. **DirectComposition**: Smooth composition on Windows
 
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
 use tracing::{debug, info, trace};
 
 use rustkit_layout::DisplayCommand;
-use rustkit_renderer::Renderer;
+use rustkit_renderer::{GpuMemoryUsage, Renderer};
 use rustkit_viewhost::{Bounds, ViewId};
 
+mod mask;
+mod occlusion;
+
+pub use occlusion::SurfacePlacement;
+
 /// Errors that can occur in the compositor.
 #[derive(Error, Debug)]
 pub enum CompositorError {
@@ -44,8 +49,8 @@ pub struct CompositorConfig {
     pub vsync: bool,
     /// Preferred surface format.
     pub format: wgpu::TextureFormat,
-    /// Power preference for GPU selection.
-    pub power_preference: wgpu::PowerPreference,
+    /// How the GPU adapter is picked.
+    pub adapter: AdapterOptions,
 }
 
 impl Default for CompositorConfig {
@@ -56,11 +61,51 @@ impl Default for CompositorConfig {
             // CSS colors are already in sRGB space, so we don't want the GPU
             // to apply sRGB encoding when writing to the texture.
             format: wgpu::TextureFormat::Bgra8Unorm,
+            adapter: AdapterOptions::default(),
+        }
+    }
+}
+
+/// How the compositor picks a GPU adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdapterOptions {
+    /// Whether to favor a low-power (integrated) or a high-performance
+    /// (discrete) GPU where there are both.
+    pub power_preference: wgpu::PowerPreference,
+    /// Graphics APIs adapters may be driven through, such as Metal, Vulkan
+    /// or DX12.
+    pub backends: wgpu::Backends,
+    /// Use the software fallback adapter even when there's a GPU. Without
+    /// it, the fallback adapter is only used when there's no GPU.
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for AdapterOptions {
+    fn default() -> Self {
+        Self {
             power_preference: wgpu::PowerPreference::HighPerformance,
+            backends: wgpu::Backends::all(),
+            force_fallback_adapter: false,
         }
     }
 }
 
+/// GPU memory backing a view's frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceMemory {
+    /// Format of the frames.
+    pub format: wgpu::TextureFormat,
+    /// Width of each frame in pixels.
+    pub width: u32,
+    /// Height of each frame in pixels.
+    pub height: u32,
+    /// Frames held: a surface's swapchain images, or a headless view's
+    /// one texture.
+    pub textures: usize,
+    /// Bytes the frames take.
+    pub bytes: u64,
+}
+
 /// Per-view surface state.
 pub struct SurfaceState {
     view_id: ViewId,
@@ -114,6 +159,15 @@ pub struct Compositor {
     queue: Arc<wgpu::Queue>,
     surfaces: RwLock<HashMap<ViewId, SurfaceState>>,
     headless_textures: RwLock<HashMap<ViewId, HeadlessState>>,
+    /// Frames held back since [`hold_presents`](Self::hold_presents).
+    held_frames: Mutex<Option<Vec<wgpu::SurfaceTexture>>>,
+    /// Where surfaces sit in their window, for occlusion culling.
+    placements: RwLock<occlusion::Placements>,
+    /// Visible regions hosts set in place of those from placements.
+    visible_regions: RwLock<HashMap<ViewId, Vec<Bounds>>>,
+    /// Corner radii of views with rounded corners, in pixels.
+    corner_radii: RwLock<HashMap<ViewId, f32>>,
+    corner_mask: mask::CornerMask,
     config: CompositorConfig,
 }
 
@@ -127,31 +181,33 @@ impl Compositor {
     pub fn with_config(config: CompositorConfig) -> Result<Self, CompositorError> {
         info!("Initializing compositor");
 
-        // Create wgpu instance - use all backends to allow fallback options
+        // Create wgpu instance with the backends allowed; all of them by
+        // default, to maximize compatibility
+        let options = config.adapter;
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            // Use all available backends to maximize compatibility
-            // On macOS this includes Metal and potentially software fallback
-            backends: wgpu::Backends::all(),
+            backends: options.backends,
             ..Default::default()
         });
 
-        // Request adapter - try hardware first, then fall back to software
+        // Request adapter - try hardware first, unless software is forced,
+        // then fall back to software
         let adapter = pollster::block_on(async {
-            // First try hardware adapter
-            let hardware = instance
-                .request_adapter(&wgpu::RequestAdapterOptions {
-                    power_preference: config.power_preference,
-                    compatible_surface: None,
-                    force_fallback_adapter: false,
-                })
-                .await;
-            
-            if hardware.is_some() {
-                return hardware;
+            if !options.force_fallback_adapter {
+                let hardware = instance
+                    .request_adapter(&wgpu::RequestAdapterOptions {
+                        power_preference: options.power_preference,
+                        compatible_surface: None,
+                        force_fallback_adapter: false,
+                    })
+                    .await;
+
+                if hardware.is_some() {
+                    return hardware;
+                }
+
+                // Fall back to software adapter if hardware not available
+                info!("No hardware GPU adapter found, trying software fallback");
             }
-            
-            // Fall back to software adapter if hardware not available
-            info!("No hardware GPU adapter found, trying software fallback");
             instance
                 .request_adapter(&wgpu::RequestAdapterOptions {
                     power_preference: wgpu::PowerPreference::LowPower,
@@ -160,9 +216,22 @@ impl Compositor {
                 })
                 .await
         })
-        .ok_or_else(|| CompositorError::DeviceCreation("No suitable GPU adapter found (tried hardware and software fallback)".into()))?;
-
-        info!(adapter = ?adapter.get_info().name, "GPU adapter selected");
+        .ok_or_else(|| {
+            CompositorError::DeviceCreation(format!(
+                "No suitable GPU adapter found for backends {:?} (tried {})",
+                options.backends,
+                if options.force_fallback_adapter { "software fallback" } else { "hardware and software fallback" }
+            ))
+        })?;
+
+        let info = adapter.get_info();
+        info!(
+            adapter = ?info.name,
+            backend = ?info.backend,
+            device_type = ?info.device_type,
+            power_preference = ?options.power_preference,
+            "GPU adapter selected"
+        );
 
         // Create device and queue
         let (device, queue) = pollster::block_on(async {
@@ -180,6 +249,8 @@ impl Compositor {
         })
         .map_err(|e| CompositorError::DeviceCreation(e.to_string()))?;
 
+        let corner_mask = mask::CornerMask::new(&device);
+
         Ok(Self {
             instance,
             adapter,
@@ -187,6 +258,11 @@ impl Compositor {
             queue: Arc::new(queue),
             surfaces: RwLock::new(HashMap::new()),
             headless_textures: RwLock::new(HashMap::new()),
+            held_frames: Mutex::new(None),
+            placements: RwLock::new(occlusion::Placements::default()),
+            visible_regions: RwLock::new(HashMap::new()),
+            corner_radii: RwLock::new(HashMap::new()),
+            corner_mask,
             config,
         })
     }
@@ -251,7 +327,7 @@ impl Compositor {
             width: width.max(1),
             height: height.max(1),
             present_mode,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            alpha_mode: self.alpha_mode(&surface, self.corner_radius(view_id) > 0.0),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
@@ -321,7 +397,7 @@ impl Compositor {
             width: width.max(1),
             height: height.max(1),
             present_mode,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            alpha_mode: self.alpha_mode(&surface, self.corner_radius(view_id) > 0.0),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
@@ -528,6 +604,104 @@ impl Compositor {
         }
     }
 
+    /// Place a view's surface in its window, so the parts other surfaces
+    /// cover can be left unpainted, or take it out with `None`.
+    pub fn set_placement(&self, view_id: ViewId, placement: Option<SurfacePlacement>) {
+        trace!(?view_id, ?placement, "Surface placed");
+        self.placements.write().unwrap().set(view_id, placement);
+    }
+
+    /// Where a view's surface sits in its window, if it was placed.
+    pub fn placement(&self, view_id: ViewId) -> Option<SurfacePlacement> {
+        self.placements.read().unwrap().get(view_id)
+    }
+
+    /// Set the parts of a view's surface that can be seen, in its own
+    /// coordinates, for hosts that know better than the placements, e.g.
+    /// when the views covering it belong to other compositors. `None` goes
+    /// back to working it out from the placements.
+    pub fn set_visible_region(&self, view_id: ViewId, region: Option<Vec<Bounds>>) {
+        let mut regions = self.visible_regions.write().unwrap();
+        match region {
+            Some(region) => regions.insert(view_id, region),
+            None => regions.remove(&view_id),
+        };
+    }
+
+    /// The parts of a view's surface that can be seen, in its own
+    /// coordinates: those the host set, or those no opaque surface placed
+    /// above it covers. Empty if it's covered entirely; `None` if it isn't
+    /// known, and all of it may be seen.
+    pub fn visible_region(&self, view_id: ViewId) -> Option<Vec<Bounds>> {
+        if let Some(region) = self.visible_regions.read().unwrap().get(&view_id) {
+            return Some(region.clone());
+        }
+        // Rounded surfaces don't hide what's under their corners
+        let corner_radii = self.corner_radii.read().unwrap();
+        self.placements
+            .read()
+            .unwrap()
+            .visible_region(view_id, |other| corner_radii.contains_key(&other))
+    }
+
+    /// Round the corners of a view's frames to `radius` pixels, or square
+    /// them again with 0. Window surfaces are made transparent where the
+    /// platform allows it, so what's behind shows through the corners.
+    pub fn set_corner_radius(&self, view_id: ViewId, radius: f32) {
+        let rounded = radius > 0.0;
+        debug!(?view_id, radius, "Setting corner radius");
+        {
+            let mut corner_radii = self.corner_radii.write().unwrap();
+            if rounded {
+                corner_radii.insert(view_id, radius);
+            } else {
+                corner_radii.remove(&view_id);
+            }
+        }
+
+        if let Some(state) = self.surfaces.write().unwrap().get_mut(&view_id) {
+            let alpha_mode = self.alpha_mode(&state.surface, rounded);
+            if state.config.alpha_mode != alpha_mode {
+                state.config.alpha_mode = alpha_mode;
+                state.surface.configure(&self.device, &state.config);
+            }
+        }
+    }
+
+    /// The corner radius of a view's frames, in pixels; 0 if they're square.
+    pub fn corner_radius(&self, view_id: ViewId) -> f32 {
+        self.corner_radii.read().unwrap().get(&view_id).copied().unwrap_or(0.0)
+    }
+
+    /// Mask a finished frame of a view to its corner radius, before it's
+    /// presented. Frames of views with square corners are left alone.
+    pub fn mask_corners(&self, view_id: ViewId, target: &wgpu::TextureView) -> Result<(), CompositorError> {
+        let radius = self.corner_radius(view_id);
+        if radius <= 0.0 {
+            return Ok(());
+        }
+        let format = match self.surfaces.read().unwrap().get(&view_id) {
+            Some(state) => state.config.format,
+            None => self.config.format,
+        };
+        let size = self.get_surface_size(view_id)?;
+        self.corner_mask.apply(&self.device, &self.queue, target, format, size, radius);
+        Ok(())
+    }
+
+    /// How a surface's alpha is composited: premultiplied if it has
+    /// rounded corners and the platform can, else whatever suits it.
+    fn alpha_mode(&self, surface: &wgpu::Surface<'static>, rounded: bool) -> wgpu::CompositeAlphaMode {
+        if !rounded {
+            return wgpu::CompositeAlphaMode::Auto;
+        }
+        let capabilities = surface.get_capabilities(&self.adapter);
+        [wgpu::CompositeAlphaMode::PreMultiplied, wgpu::CompositeAlphaMode::PostMultiplied]
+            .into_iter()
+            .find(|mode| capabilities.alpha_modes.contains(mode))
+            .unwrap_or(wgpu::CompositeAlphaMode::Auto)
+    }
+
     /// Get the number of active surfaces.
     pub fn surface_count(&self) -> usize {
         self.surfaces.read().unwrap().len()
@@ -597,12 +771,33 @@ impl Compositor {
         Ok(view)
     }
 
-    /// Present a surface texture.
+    /// Present a surface texture, or hold it back if presents are held.
     pub fn present(&self, output: wgpu::SurfaceTexture) {
+        if let Some(held) = self.held_frames.lock().unwrap().as_mut() {
+            held.push(output);
+            return;
+        }
         trace!("Presenting surface texture");
         output.present();
     }
 
+    /// Hold back frames passed to [`present`](Self::present) until
+    /// [`present_held`](Self::present_held), so several surfaces show their
+    /// new frames together.
+    pub fn hold_presents(&self) {
+        self.held_frames.lock().unwrap().get_or_insert_with(Vec::new);
+    }
+
+    /// Present the frames held back since [`hold_presents`](Self::hold_presents)
+    /// and go back to presenting straight away.
+    pub fn present_held(&self) {
+        let held = self.held_frames.lock().unwrap().take().unwrap_or_default();
+        trace!(count = held.len(), "Presenting held surface textures");
+        for output in held {
+            output.present();
+        }
+    }
+
     /// Capture a frame to a PPM file.
     ///
     /// This creates a temporary render target, renders a solid color (or current state),
@@ -778,21 +973,7 @@ impl Compositor {
         commands: &[DisplayCommand],
     ) -> Result<(), CompositorError> {
         // Get dimensions from either headless texture or surface
-        let (width, height) = {
-            // Check headless textures first
-            let headless = self.headless_textures.read().unwrap();
-            if let Some(state) = headless.get(&view_id) {
-                (state.width, state.height)
-            } else {
-                drop(headless);
-                // Fall back to surfaces
-                let surfaces = self.surfaces.read().unwrap();
-                let state = surfaces
-                    .get(&view_id)
-                    .ok_or(CompositorError::SurfaceNotFound(view_id))?;
-                (state.width, state.height)
-            }
-        };
+        let (width, height) = self.get_surface_size(view_id)?;
 
         if width == 0 || height == 0 {
             return Err(CompositorError::Render(
@@ -802,6 +983,90 @@ impl Compositor {
 
         info!(?view_id, width, height, path, cmd_count = commands.len(), "Capturing frame with display list");
 
+        let pixels = self.render_pixels((width, height), renderer, commands, self.corner_radius(view_id))?;
+
+        // Write PPM file (simple portable format), dropping alpha
+        let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+        ppm.extend(pixels.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]));
+        std::fs::write(path, ppm)
+            .map_err(|e| CompositorError::Render(format!("Failed to write PPM file: {}", e)))?;
+
+        info!(?view_id, path, "Frame captured with display list successfully");
+        Ok(())
+    }
+
+    /// Render a display list to an offscreen texture `size` pixels large and
+    /// read it back as rows of RGBA8 pixels, top row first.
+    ///
+    /// The renderer's viewport should be set to `size` beforehand.
+    pub fn capture_pixels_with_renderer(
+        &self,
+        size: (u32, u32),
+        renderer: &mut Renderer,
+        commands: &[DisplayCommand],
+    ) -> Result<Vec<u8>, CompositorError> {
+        if size.0 == 0 || size.1 == 0 {
+            return Err(CompositorError::Render(
+                "Cannot capture zero-size frame".into(),
+            ));
+        }
+        self.render_pixels(size, renderer, commands, 0.0)
+    }
+
+    /// Largest width or height of a texture the device can create.
+    pub fn max_texture_dimension(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    /// The device's limits.
+    pub fn limits(&self) -> wgpu::Limits {
+        self.device.limits()
+    }
+
+    /// GPU memory backing a view's surface or headless texture.
+    ///
+    /// A surface's swapchain is counted as one image more than its frame
+    /// latency, which is what the platforms allocate; the swapchain itself
+    /// belongs to the platform and isn't visible to wgpu.
+    pub fn surface_memory(&self, view_id: ViewId) -> Option<SurfaceMemory> {
+        if let Some(state) = self.headless_textures.read().unwrap().get(&view_id) {
+            let mut usage = GpuMemoryUsage::default();
+            usage.add_texture(&state.texture);
+            return Some(SurfaceMemory {
+                format: state.texture.format(),
+                width: state.width,
+                height: state.height,
+                textures: 1,
+                bytes: usage.texture_bytes,
+            });
+        }
+
+        let surfaces = self.surfaces.read().unwrap();
+        let config = &surfaces.get(&view_id)?.config;
+        let textures = config.desired_maximum_frame_latency as usize + 1;
+        Some(SurfaceMemory {
+            format: config.format,
+            width: config.width,
+            height: config.height,
+            textures,
+            bytes: rustkit_renderer::texture_bytes(config.format, config.width, config.height) * textures as u64,
+        })
+    }
+
+    /// GPU memory the compositor holds apart from views' surfaces.
+    pub fn memory_usage(&self) -> GpuMemoryUsage {
+        self.corner_mask.memory_usage()
+    }
+
+    /// Render commands offscreen with the corners rounded by
+    /// `corner_radius`, returning RGBA8 rows.
+    fn render_pixels(
+        &self,
+        (width, height): (u32, u32),
+        renderer: &mut Renderer,
+        commands: &[DisplayCommand],
+        corner_radius: f32,
+    ) -> Result<Vec<u8>, CompositorError> {
         // Create an offscreen texture for capture (RENDER_ATTACHMENT + COPY_SRC)
         let texture = self.device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Capture Texture"),
@@ -823,6 +1088,16 @@ impl Compositor {
         // Render the display list to the offscreen texture
         renderer.execute(commands, &texture_view)
             .map_err(|e| CompositorError::Render(format!("Renderer error: {}", e)))?;
+        if corner_radius > 0.0 {
+            self.corner_mask.apply(
+                &self.device,
+                &self.queue,
+                &texture_view,
+                wgpu::TextureFormat::Bgra8Unorm,
+                (width, height),
+                corner_radius,
+            );
+        }
 
         // Create staging buffer for readback
         let bytes_per_pixel = 4u32; // BGRA8
@@ -882,39 +1157,18 @@ impl Compositor {
 
         let data = buffer_slice.get_mapped_range();
 
-        // Write PPM file (simple portable format)
-        let mut file = std::fs::File::create(path)
-            .map_err(|e| CompositorError::Render(format!("Failed to create file: {}", e)))?;
-
-        use std::io::Write;
-        writeln!(file, "P6")
-            .map_err(|e| CompositorError::Render(format!("Failed to write PPM header: {}", e)))?;
-        writeln!(file, "{} {}", width, height)
-            .map_err(|e| CompositorError::Render(format!("Failed to write PPM dimensions: {}", e)))?;
-        writeln!(file, "255")
-            .map_err(|e| CompositorError::Render(format!("Failed to write PPM max value: {}", e)))?;
-
-        // Convert BGRA to RGB and handle row padding
-        let mut rgb_data = Vec::with_capacity((width * height * 3) as usize);
+        // Convert BGRA to RGBA and drop the row padding
+        let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
         for y in 0..height {
             let row_start = (y * padded_bytes_per_row) as usize;
-            for x in 0..width {
-                let pixel_start = row_start + (x * bytes_per_pixel) as usize;
-                // BGRA -> RGB
-                rgb_data.push(data[pixel_start + 2]); // R (from B position in BGRA)
-                rgb_data.push(data[pixel_start + 1]); // G
-                rgb_data.push(data[pixel_start]);     // B (from R position in BGRA)
+            for pixel in data[row_start..row_start + (width * bytes_per_pixel) as usize].chunks_exact(4) {
+                rgba_data.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
             }
         }
 
-        file.write_all(&rgb_data)
-            .map_err(|e| CompositorError::Render(format!("Failed to write PPM data: {}", e)))?;
-
         drop(data);
         staging_buffer.unmap();
-
-        info!(?view_id, path, "Frame captured with display list successfully");
-        Ok(())
+        Ok(rgba_data)
     }
 
     /// Get the surface dimensions for a view (supports both surfaces and headless textures).
@@ -952,6 +1206,8 @@ mod tests {
         let config = CompositorConfig::default();
         assert!(config.vsync);
         assert_eq!(config.format, wgpu::TextureFormat::Bgra8Unorm);
+        assert_eq!(config.adapter.backends, wgpu::Backends::all());
+        assert!(!config.adapter.force_fallback_adapter);
     }
 
     // Note: GPU tests require a display and are typically run manually
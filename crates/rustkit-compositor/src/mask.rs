@@ -0,0 +1,160 @@
+//! Rounded corners for views.
+//!
+//! Floating views, like HiWave's sidebar and popovers, have rounded
+//! corners. Rather than pages faking them with CSS over a transparent
+//! background, the compositor masks each finished frame of a view to its
+//! corner radius before the frame is presented, antialiasing the edge.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The pipeline that masks frames to a corner radius.
+pub(crate) struct CornerMask {
+    shader: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Pipelines by the format of the frames they mask.
+    pipelines: Mutex<HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>>,
+}
+
+impl CornerMask {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Corner Mask Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/corner_mask.wgsl").into()),
+        });
+
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Corner Mask Uniforms"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Corner Mask Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Corner Mask Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Corner Mask Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            shader,
+            layout,
+            uniforms,
+            bind_group,
+            pipelines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// GPU memory the mask holds apart from its pipelines.
+    pub fn memory_usage(&self) -> rustkit_renderer::GpuMemoryUsage {
+        let mut usage = rustkit_renderer::GpuMemoryUsage::default();
+        usage.add_buffer(&self.uniforms);
+        usage
+    }
+
+    /// Round the corners of `target`, a `size` frame in `format`, to
+    /// `radius` pixels.
+    pub fn apply(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: &wgpu::TextureView,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+        radius: f32,
+    ) {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let pipeline = pipelines.entry(format).or_insert_with(|| self.create_pipeline(device, format));
+
+        let params = [size.0 as f32, size.1 as f32, radius, 0.0];
+        let bytes: Vec<u8> = params.iter().flat_map(|value| value.to_ne_bytes()).collect();
+        queue.write_buffer(&self.uniforms, 0, &bytes);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Corner Mask Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Corner Mask Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn create_pipeline(&self, device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        // What's in the frame, times the coverage
+        let multiply = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Zero,
+            dst_factor: wgpu::BlendFactor::Src,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Corner Mask Pipeline"),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: multiply,
+                        alpha: multiply,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
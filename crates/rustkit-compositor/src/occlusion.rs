@@ -0,0 +1,151 @@
+//! Which parts of each surface can be seen.
+//!
+//! Views stacked in one window cover each other: the chrome and a shelf
+//! sit over the content. The parts of a surface under opaque surfaces
+//! stacked above it needn't be painted, and a surface covered entirely
+//! needn't be painted at all.
+
+use rustkit_viewhost::{Bounds, ViewId};
+
+/// Where a surface sits in its window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfacePlacement {
+    /// Position and size, in window coordinates.
+    pub bounds: Bounds,
+    /// Stacking order. Higher surfaces cover lower ones; of two at the same
+    /// z-index, the one placed later is on top.
+    pub z_index: i32,
+    /// Whether the surface hides what's below it. Surfaces that are
+    /// partly transparent cover nothing.
+    pub opaque: bool,
+}
+
+impl SurfacePlacement {
+    /// An opaque surface at `bounds`, stacked at z-index 0.
+    pub fn new(bounds: Bounds) -> Self {
+        Self { bounds, z_index: 0, opaque: true }
+    }
+}
+
+/// The placed surfaces, in the order they were first placed.
+#[derive(Default)]
+pub(crate) struct Placements {
+    surfaces: Vec<(ViewId, SurfacePlacement)>,
+}
+
+impl Placements {
+    /// Place a surface, keeping its place among surfaces at the same
+    /// z-index if it was placed before, or take it out.
+    pub fn set(&mut self, view_id: ViewId, placement: Option<SurfacePlacement>) {
+        let existing = self.surfaces.iter_mut().find(|(id, _)| *id == view_id);
+        match (existing, placement) {
+            (Some(entry), Some(placement)) => entry.1 = placement,
+            (None, Some(placement)) => self.surfaces.push((view_id, placement)),
+            (_, None) => self.surfaces.retain(|(id, _)| *id != view_id),
+        }
+    }
+
+    pub fn get(&self, view_id: ViewId) -> Option<SurfacePlacement> {
+        self.surfaces.iter().find(|(id, _)| *id == view_id).map(|(_, placement)| *placement)
+    }
+
+    /// The parts of a surface no opaque surface above it covers, in the
+    /// surface's own coordinates, or `None` if it isn't placed. Surfaces
+    /// `see_through` accepts cover nothing, whether opaque or not.
+    pub fn visible_region(&self, view_id: ViewId, see_through: impl Fn(ViewId) -> bool) -> Option<Vec<Bounds>> {
+        let index = self.surfaces.iter().position(|(id, _)| *id == view_id)?;
+        let placement = self.surfaces[index].1;
+
+        let mut region = vec![placement.bounds];
+        for (other_index, (other_id, other)) in self.surfaces.iter().enumerate() {
+            let above = other.z_index > placement.z_index
+                || (other.z_index == placement.z_index && other_index > index);
+            if above && other.opaque && !see_through(*other_id) {
+                region = region.into_iter().flat_map(|rect| subtract(rect, other.bounds)).collect();
+            }
+        }
+
+        let (x, y) = (placement.bounds.x, placement.bounds.y);
+        Some(
+            region
+                .into_iter()
+                .map(|rect| Bounds::new(rect.x - x, rect.y - y, rect.width, rect.height))
+                .collect(),
+        )
+    }
+}
+
+/// The parts of `rect` outside `hole`: the bands above and below it, and
+/// those either side of it.
+fn subtract(rect: Bounds, hole: Bounds) -> Vec<Bounds> {
+    let edges = |b: Bounds| {
+        let (x, y) = (i64::from(b.x), i64::from(b.y));
+        (x, y, x + i64::from(b.width), y + i64::from(b.height))
+    };
+    let (left, top, right, bottom) = edges(rect);
+    let (hole_left, hole_top, hole_right, hole_bottom) = edges(hole);
+    if hole_left >= right || hole_right <= left || hole_top >= bottom || hole_bottom <= top {
+        return vec![rect];
+    }
+    let (hole_left, hole_right) = (hole_left.max(left), hole_right.min(right));
+    let (hole_top, hole_bottom) = (hole_top.max(top), hole_bottom.min(bottom));
+
+    let band = |left: i64, top: i64, right: i64, bottom: i64| {
+        (right > left && bottom > top)
+            .then(|| Bounds::new(left as i32, top as i32, (right - left) as u32, (bottom - top) as u32))
+    };
+    [
+        band(left, top, right, hole_top),
+        band(left, hole_bottom, right, bottom),
+        band(left, hole_top, hole_left, hole_bottom),
+        band(hole_right, hole_top, right, hole_bottom),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(region: &[Bounds]) -> u32 {
+        region.iter().map(|rect| rect.width * rect.height).sum()
+    }
+
+    #[test]
+    fn test_visible_region() {
+        let (content, chrome, shelf) = (ViewId::new(), ViewId::new(), ViewId::new());
+        let mut placements = Placements::default();
+        placements.set(content, Some(SurfacePlacement::new(Bounds::new(0, 0, 800, 600))));
+        placements.set(chrome, Some(SurfacePlacement::new(Bounds::new(0, 0, 800, 80))));
+        placements.set(
+            shelf,
+            Some(SurfacePlacement { z_index: -1, ..SurfacePlacement::new(Bounds::new(0, 500, 800, 100)) }),
+        );
+
+        // The chrome, placed later, covers the top of the content
+        let region = placements.visible_region(content, |_| false).unwrap();
+        assert_eq!(region, vec![Bounds::new(0, 80, 800, 520)]);
+
+        // The shelf is stacked below the content and can't be seen
+        assert_eq!(placements.visible_region(shelf, |_| false), Some(Vec::new()));
+
+        // A transparent chrome covers nothing
+        let mut chrome_placement = placements.get(chrome).unwrap();
+        chrome_placement.opaque = false;
+        placements.set(chrome, Some(chrome_placement));
+        assert_eq!(area(&placements.visible_region(content, |_| false).unwrap()), 800 * 600);
+
+        // Regions are in the surface's own coordinates
+        placements.set(shelf, Some(SurfacePlacement { z_index: 1, ..SurfacePlacement::new(Bounds::new(100, 100, 50, 50)) }));
+        let region = placements.visible_region(content, |_| false).unwrap();
+        assert_eq!(area(&region), 800 * 600 - 50 * 50);
+        placements.set(content, Some(SurfacePlacement::new(Bounds::new(100, 100, 100, 100))));
+        let region = placements.visible_region(content, |_| false).unwrap();
+        assert_eq!(region, vec![Bounds::new(0, 50, 100, 50), Bounds::new(50, 0, 50, 50)]);
+
+        placements.set(content, None);
+        assert_eq!(placements.visible_region(content, |_| false), None);
+    }
+}
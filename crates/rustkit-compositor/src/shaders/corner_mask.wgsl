@@ -0,0 +1,34 @@
+// Rounds the corners of a finished frame. Drawn over the whole frame with
+// blending that multiplies what's there by the coverage of the rounded
+// rect, so the corners turn transparent. Frames are premultiplied.
+
+struct Mask {
+    // Frame width and height in pixels
+    size: vec2<f32>,
+    radius: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> mask: Mask;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle covering the frame
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let half_size = mask.size * 0.5;
+    let radius = min(mask.radius, min(half_size.x, half_size.y));
+
+    // Signed distance to the rounded rect (negative inside)
+    let d = abs(position.xy - half_size) - half_size + vec2<f32>(radius);
+    let distance = length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0) - radius;
+
+    // Antialias over one pixel across the edge
+    let coverage = 1.0 - smoothstep(-0.5, 0.5, distance);
+    return vec4<f32>(coverage);
+}
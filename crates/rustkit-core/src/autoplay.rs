@@ -0,0 +1,25 @@
+//! Media autoplay policy.
+
+/// Which media may start playing on its own, through the `autoplay`
+/// attribute, rather than on a user's request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AutoplayPolicy {
+    /// No media autoplays.
+    Block,
+    /// Only muted media autoplays.
+    #[default]
+    AllowMuted,
+    /// All media autoplays.
+    Allow,
+}
+
+impl AutoplayPolicy {
+    /// Whether media, muted or not, may autoplay.
+    pub fn allows(self, muted: bool) -> bool {
+        match self {
+            AutoplayPolicy::Block => false,
+            AutoplayPolicy::AllowMuted => muted,
+            AutoplayPolicy::Allow => true,
+        }
+    }
+}
@@ -31,6 +31,9 @@ pub struct HistoryEntry {
     /// The scroll position to restore.
     pub scroll_position: ScrollPosition,
 
+    /// Whether the scroll position is restored on returning to this entry.
+    pub scroll_restoration: ScrollRestoration,
+
     /// Timestamp when this entry was created.
     pub created_at: Instant,
 
@@ -48,6 +51,7 @@ impl HistoryEntry {
             state: None,
             title,
             scroll_position: ScrollPosition::default(),
+            scroll_restoration: ScrollRestoration::Auto,
             created_at: Instant::now(),
             navigation_type: NavigationType::Navigate,
         }
@@ -1051,6 +1051,79 @@ impl MouseState {
     }
 }
 
+/// Number of buttons of a gamepad in the standard layout.
+pub const STANDARD_GAMEPAD_BUTTONS: usize = 17;
+
+/// Number of axes of a gamepad in the standard layout.
+pub const STANDARD_GAMEPAD_AXES: usize = 4;
+
+/// How a gamepad's buttons and axes are laid out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GamepadMapping {
+    /// The W3C standard gamepad layout.
+    Standard,
+    /// A layout the host doesn't know.
+    #[default]
+    Unknown,
+}
+
+impl GamepadMapping {
+    /// Get the mapping string (for JavaScript Gamepad.mapping).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GamepadMapping::Standard => "standard",
+            GamepadMapping::Unknown => "",
+        }
+    }
+}
+
+/// A gamepad button.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GamepadButton {
+    pub pressed: bool,
+    pub touched: bool,
+    /// How far the button is pressed, from 0.0 to 1.0.
+    pub value: f64,
+}
+
+impl GamepadButton {
+    /// A button that is either pressed or not.
+    pub fn digital(pressed: bool) -> Self {
+        Self {
+            pressed,
+            touched: pressed,
+            value: if pressed { 1.0 } else { 0.0 },
+        }
+    }
+}
+
+/// State of a connected gamepad, as the host polls it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GamepadState {
+    /// Identifies the device, e.g. its product name and vendor and
+    /// product IDs.
+    pub id: String,
+    pub mapping: GamepadMapping,
+    /// Axis positions, from -1.0 to 1.0.
+    pub axes: Vec<f64>,
+    pub buttons: Vec<GamepadButton>,
+    /// When the state last changed, in milliseconds.
+    pub timestamp: f64,
+}
+
+impl GamepadState {
+    /// A gamepad in the standard layout, at rest.
+    pub fn standard(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            mapping: GamepadMapping::Standard,
+            axes: vec![0.0; STANDARD_GAMEPAD_AXES],
+            buttons: vec![GamepadButton::default(); STANDARD_GAMEPAD_BUTTONS],
+            timestamp: 0.0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1113,4 +1186,20 @@ mod tests {
         state.button_up(MouseButton::Primary);
         assert!(!state.is_pressed(MouseButton::Primary));
     }
+
+    #[test]
+    fn test_standard_gamepad() {
+        let mut gamepad = GamepadState::standard("Xbox Wireless Controller");
+        assert_eq!(gamepad.mapping.as_str(), "standard");
+        assert_eq!(gamepad.axes.len(), 4);
+        assert_eq!(gamepad.buttons.len(), 17);
+        assert!(gamepad.buttons.iter().all(|button| !button.pressed && button.value == 0.0));
+
+        gamepad.buttons[0] = GamepadButton::digital(true);
+        assert_eq!(
+            gamepad.buttons[0],
+            GamepadButton { pressed: true, touched: true, value: 1.0 }
+        );
+        assert_eq!(GamepadMapping::default().as_str(), "");
+    }
 }
@@ -11,13 +11,17 @@
 //! 4. **Structured logging**: Full tracing support
 //! 5. **Platform-agnostic input**: Unified input event types
 
+pub mod autoplay;
 pub mod history;
 pub mod input;
 pub mod lifecycle;
+pub mod permissions;
 
+pub use autoplay::*;
 pub use history::*;
 pub use input::*;
 pub use lifecycle::*;
+pub use permissions::*;
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -116,6 +120,12 @@ pub enum LoadEvent {
         navigation_id: NavigationId,
         url: Url,
     },
+    /// The server redirected the navigation to another URL.
+    DidReceiveServerRedirect {
+        navigation_id: NavigationId,
+        from: Url,
+        to: Url,
+    },
     /// First bytes received.
     DidCommitLoad {
         navigation_id: NavigationId,
@@ -212,6 +222,32 @@ impl NavigationStateMachine {
         Ok(nav_id)
     }
 
+    /// Move the provisional navigation to `url`, where the server
+    /// redirected it.
+    pub fn redirect_navigation(&mut self, url: Url) -> Result<(), CoreError> {
+        if self.state != NavigationState::Provisional {
+            return Err(CoreError::NavigationFailed(
+                "Cannot redirect: not in provisional state".into(),
+            ));
+        }
+
+        let nav = self
+            .current_navigation
+            .as_mut()
+            .ok_or_else(|| CoreError::NavigationFailed("No current navigation".into()))?;
+
+        debug!(navigation_id = ?nav.id, from = %nav.url, to = %url, "Navigation redirected");
+
+        let from = std::mem::replace(&mut nav.url, url.clone());
+        let _ = self.event_sender.send(LoadEvent::DidReceiveServerRedirect {
+            navigation_id: nav.id,
+            from,
+            to: url,
+        });
+
+        Ok(())
+    }
+
     /// Mark navigation as committed (first bytes received).
     pub fn commit_navigation(&mut self) -> Result<(), CoreError> {
         if self.state != NavigationState::Provisional {
@@ -333,6 +369,11 @@ impl NavigationStateMachine {
         self.history.get(self.history_index)
     }
 
+    /// URL of the navigation in progress, if any.
+    pub fn pending_url(&self) -> Option<&Url> {
+        self.current_navigation.as_ref().map(|nav| &nav.url)
+    }
+
     /// Check if can go back.
     pub fn can_go_back(&self) -> bool {
         self.history_index > 0
@@ -524,8 +565,14 @@ mod tests {
         assert_eq!(nav.state(), NavigationState::Provisional);
         assert!(nav.is_loading());
 
+        // Redirect
+        let redirected = Url::parse("https://www.example.com/").unwrap();
+        nav.redirect_navigation(redirected.clone()).unwrap();
+        assert_eq!(nav.pending_url(), Some(&redirected));
+
         // Commit
         nav.commit_navigation().unwrap();
+        assert!(nav.redirect_navigation(url.clone()).is_err());
         assert_eq!(nav.state(), NavigationState::Committed);
         assert!(nav.is_loading());
 
@@ -0,0 +1,41 @@
+//! Permissions for powerful web features.
+
+/// A feature pages need the user's permission to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Showing system notifications.
+    Notifications,
+}
+
+impl Permission {
+    /// The name the Permissions API uses.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Permission::Notifications => "notifications",
+        }
+    }
+}
+
+/// Whether an origin may use a [`Permission`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PermissionState {
+    /// The user hasn't decided; the page may ask.
+    #[default]
+    Prompt,
+    /// The user allowed it.
+    Granted,
+    /// The user refused it; the page can't ask again.
+    Denied,
+}
+
+impl PermissionState {
+    /// Convert to JavaScript string representation, as the Permissions API
+    /// reports it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionState::Prompt => "prompt",
+            PermissionState::Granted => "granted",
+            PermissionState::Denied => "denied",
+        }
+    }
+}
@@ -0,0 +1,520 @@
+//! CSS color parsing.
+//!
+//! Handles named colors, hex notation and the color functions from CSS Color
+//! Level 4: `rgb()`/`rgba()` and `hsl()`/`hsla()` in both the legacy comma
+//! syntax and the modern space syntax with `/ alpha`, plus `hwb()`, `lab()`,
+//! `lch()`, `oklab()` and `oklch()`. Colors outside the sRGB gamut are clipped.
+
+use crate::{Color, ColorF32};
+
+/// Parse a color value.
+///
+/// Returns `None` for invalid colors and for keywords that need context to
+/// resolve, such as `currentcolor`.
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_ascii_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+
+    if let Some((name, rest)) = value.split_once('(') {
+        let args = rest.strip_suffix(')')?;
+        return match name.trim() {
+            "rgb" | "rgba" => parse_rgb(args),
+            "hsl" | "hsla" => parse_hsl(args),
+            "hwb" => parse_hwb(args),
+            "lab" => parse_lab(args),
+            "lch" => parse_lch(args),
+            "oklab" => parse_oklab(args),
+            "oklch" => parse_oklch(args),
+            _ => None,
+        };
+    }
+
+    if value == "transparent" {
+        return Some(Color::TRANSPARENT);
+    }
+    NAMED_COLORS
+        .binary_search_by(|(name, _)| name.cmp(&value.as_str()))
+        .ok()
+        .map(|i| {
+            let [r, g, b] = NAMED_COLORS[i].1;
+            Color::from_rgb(r, g, b)
+        })
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let nibble = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| v * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let (r, g, b, a) = match hex.len() {
+        3 => (nibble(0)?, nibble(1)?, nibble(2)?, 255),
+        4 => (nibble(0)?, nibble(1)?, nibble(2)?, nibble(3)?),
+        6 => (byte(0)?, byte(2)?, byte(4)?, 255),
+        8 => (byte(0)?, byte(2)?, byte(4)?, byte(6)?),
+        _ => return None,
+    };
+    Some(Color::new(r, g, b, a as f32 / 255.0))
+}
+
+// ==================== Arguments ====================
+
+/// Split color function arguments into exactly three channel components and
+/// an optional alpha.
+///
+/// Accepts the legacy `a, b, c[, alpha]` form when `allow_legacy` is set, and
+/// always accepts the modern `a b c [/ alpha]` form.
+fn split_args(args: &str, allow_legacy: bool) -> Option<([&str; 3], Option<&str>)> {
+    if args.contains(',') {
+        if !allow_legacy {
+            return None;
+        }
+        let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+        return match parts.as_slice() {
+            [a, b, c] => Some(([a, b, c], None)),
+            [a, b, c, alpha] => Some(([a, b, c], Some(alpha))),
+            _ => None,
+        };
+    }
+
+    let (channels, alpha) = match args.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (args, None),
+    };
+    let parts: Vec<&str> = channels.split_whitespace().collect();
+    match parts.as_slice() {
+        [a, b, c] => Some(([a, b, c], alpha)),
+        _ => None,
+    }
+}
+
+/// Parse a number or percentage, mapping `100%` to `percent_scale`.
+/// `none` is treated as zero.
+fn parse_component(value: &str, percent_scale: f32) -> Option<f32> {
+    if value == "none" {
+        return Some(0.0);
+    }
+    match value.strip_suffix('%') {
+        Some(pct) => Some(pct.parse::<f32>().ok()? / 100.0 * percent_scale),
+        None => value.parse::<f32>().ok(),
+    }
+}
+
+/// Parse an angle in degrees; bare numbers are degrees.
+fn parse_hue(value: &str) -> Option<f32> {
+    if value == "none" {
+        return Some(0.0);
+    }
+    let (number, scale) = if let Some(v) = value.strip_suffix("deg") {
+        (v, 1.0)
+    } else if let Some(v) = value.strip_suffix("grad") {
+        (v, 0.9)
+    } else if let Some(v) = value.strip_suffix("rad") {
+        (v, 180.0 / std::f32::consts::PI)
+    } else if let Some(v) = value.strip_suffix("turn") {
+        (v, 360.0)
+    } else {
+        (value, 1.0)
+    };
+    Some(number.parse::<f32>().ok()? * scale)
+}
+
+fn parse_alpha(value: Option<&str>) -> Option<f32> {
+    match value {
+        Some(v) => Some(parse_component(v, 1.0)?.clamp(0.0, 1.0)),
+        None => Some(1.0),
+    }
+}
+
+// ==================== sRGB-based functions ====================
+
+fn parse_rgb(args: &str) -> Option<Color> {
+    let ([r, g, b], alpha) = split_args(args, true)?;
+    let channel = |v: &str| parse_component(v, 255.0).map(|c| c / 255.0);
+    Some(ColorF32::new(channel(r)?, channel(g)?, channel(b)?, parse_alpha(alpha)?).to_color())
+}
+
+fn parse_hsl(args: &str) -> Option<Color> {
+    let ([h, s, l], alpha) = split_args(args, true)?;
+    let (r, g, b) = hsl_to_srgb(
+        parse_hue(h)?,
+        parse_component(s, 100.0)? / 100.0,
+        parse_component(l, 100.0)? / 100.0,
+    );
+    Some(ColorF32::new(r, g, b, parse_alpha(alpha)?).to_color())
+}
+
+fn parse_hwb(args: &str) -> Option<Color> {
+    let ([h, w, b], alpha) = split_args(args, false)?;
+    let white = parse_component(w, 100.0)? / 100.0;
+    let black = parse_component(b, 100.0)? / 100.0;
+    let alpha = parse_alpha(alpha)?;
+
+    if white + black >= 1.0 {
+        let gray = white / (white + black);
+        return Some(ColorF32::new(gray, gray, gray, alpha).to_color());
+    }
+    let (r, g, b) = hsl_to_srgb(parse_hue(h)?, 1.0, 0.5);
+    let scale = 1.0 - white - black;
+    Some(ColorF32::new(r * scale + white, g * scale + white, b * scale + white, alpha).to_color())
+}
+
+/// Convert HSL (hue in degrees, saturation and lightness in 0-1) to sRGB 0-1.
+fn hsl_to_srgb(h: f32, s: f32, l: f32) -> (f32, f32, f32) {
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+    let h = h.rem_euclid(360.0);
+
+    let f = |n: f32| {
+        let k = (n + h / 30.0) % 12.0;
+        let a = s * l.min(1.0 - l);
+        l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    (f(0.0), f(8.0), f(4.0))
+}
+
+// ==================== Lab-based functions ====================
+
+fn parse_lab(args: &str) -> Option<Color> {
+    let ([l, a, b], alpha) = split_args(args, false)?;
+    let (r, g, b) = lab_to_srgb(
+        parse_component(l, 100.0)?.clamp(0.0, 100.0),
+        parse_component(a, 125.0)?,
+        parse_component(b, 125.0)?,
+    );
+    Some(ColorF32::new(r, g, b, parse_alpha(alpha)?).to_color())
+}
+
+fn parse_lch(args: &str) -> Option<Color> {
+    let ([l, c, h], alpha) = split_args(args, false)?;
+    let (a, b) = polar_to_cartesian(parse_component(c, 150.0)?.max(0.0), parse_hue(h)?);
+    let (r, g, b) = lab_to_srgb(parse_component(l, 100.0)?.clamp(0.0, 100.0), a, b);
+    Some(ColorF32::new(r, g, b, parse_alpha(alpha)?).to_color())
+}
+
+fn parse_oklab(args: &str) -> Option<Color> {
+    let ([l, a, b], alpha) = split_args(args, false)?;
+    let (r, g, b) = oklab_to_srgb(
+        parse_component(l, 1.0)?.clamp(0.0, 1.0),
+        parse_component(a, 0.4)?,
+        parse_component(b, 0.4)?,
+    );
+    Some(ColorF32::new(r, g, b, parse_alpha(alpha)?).to_color())
+}
+
+fn parse_oklch(args: &str) -> Option<Color> {
+    let ([l, c, h], alpha) = split_args(args, false)?;
+    let (a, b) = polar_to_cartesian(parse_component(c, 0.4)?.max(0.0), parse_hue(h)?);
+    let (r, g, b) = oklab_to_srgb(parse_component(l, 1.0)?.clamp(0.0, 1.0), a, b);
+    Some(ColorF32::new(r, g, b, parse_alpha(alpha)?).to_color())
+}
+
+fn polar_to_cartesian(chroma: f32, hue_degrees: f32) -> (f32, f32) {
+    let hue = hue_degrees.to_radians();
+    (chroma * hue.cos(), chroma * hue.sin())
+}
+
+/// Convert CIE Lab (D50 white point) to gamma-encoded sRGB.
+fn lab_to_srgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    const KAPPA: f32 = 24389.0 / 27.0;
+    const EPSILON: f32 = 216.0 / 24389.0;
+    const D50: [f32; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    let finv = |f: f32| {
+        if f.powi(3) > EPSILON {
+            f.powi(3)
+        } else {
+            (116.0 * f - 16.0) / KAPPA
+        }
+    };
+    let y = if l > KAPPA * EPSILON { fy.powi(3) } else { l / KAPPA };
+    let xyz_d50 = [finv(fx) * D50[0], y * D50[1], finv(fz) * D50[2]];
+
+    // Bradford chromatic adaptation from D50 to D65
+    let xyz = mat3_mul(
+        [
+            [0.955_473_4, -0.023_098_54, 0.063_259_31],
+            [-0.028_369_7, 1.009_995_5, 0.021_041_4],
+            [0.012_314_01, -0.020_507_7, 1.330_365_9],
+        ],
+        xyz_d50,
+    );
+    let linear = mat3_mul(
+        [
+            [3.240_97, -1.537_383_2, -0.498_610_76],
+            [-0.969_243_6, 1.875_967_5, 0.041_555_06],
+            [0.055_630_08, -0.203_976_96, 1.056_971_5],
+        ],
+        xyz,
+    );
+    (
+        linear_to_srgb(linear[0]),
+        linear_to_srgb(linear[1]),
+        linear_to_srgb(linear[2]),
+    )
+}
+
+/// Convert Oklab to gamma-encoded sRGB.
+fn oklab_to_srgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+    let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+    let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+    let lms = [l_.powi(3), m_.powi(3), s_.powi(3)];
+
+    let linear = mat3_mul(
+        [
+            [4.076_741_7, -3.307_711_6, 0.230_969_94],
+            [-1.268_438, 2.609_757_4, -0.341_319_38],
+            [-0.004_196_086_3, -0.703_418_6, 1.707_614_7],
+        ],
+        lms,
+    );
+    (
+        linear_to_srgb(linear[0]),
+        linear_to_srgb(linear[1]),
+        linear_to_srgb(linear[2]),
+    )
+}
+
+fn mat3_mul(m: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+/// Apply the sRGB transfer function, clipping to the sRGB gamut.
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// ==================== Named colors ====================
+
+/// CSS named colors, sorted by name for binary search.
+static NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("aliceblue", [240, 248, 255]),
+    ("antiquewhite", [250, 235, 215]),
+    ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]),
+    ("blueviolet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadetblue", [95, 158, 160]),
+    ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]),
+    ("darkcyan", [0, 139, 139]),
+    ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]),
+    ("darkgreen", [0, 100, 0]),
+    ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]),
+    ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]),
+    ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]),
+    ("darkseagreen", [143, 188, 143]),
+    ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]),
+    ("darkslategrey", [47, 79, 79]),
+    ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]),
+    ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]),
+    ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floralwhite", [255, 250, 240]),
+    ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]),
+    ("hotpink", [255, 105, 180]),
+    ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]),
+    ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]),
+    ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]),
+    ("lightgoldenrodyellow", [250, 250, 210]),
+    ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]),
+    ("lightgrey", [211, 211, 211]),
+    ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]),
+    ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]),
+    ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]),
+    ("lime", [0, 255, 0]),
+    ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]),
+    ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]),
+    ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]),
+    ("mediumturquoise", [72, 209, 204]),
+    ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]),
+    ("mintcream", [245, 255, 250]),
+    ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orangered", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]),
+    ("palegreen", [152, 251, 152]),
+    ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]),
+    ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]),
+    ("purple", [128, 0, 128]),
+    ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]),
+    ("rosybrown", [188, 143, 143]),
+    ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("skyblue", [135, 206, 235]),
+    ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]),
+    ("slategrey", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]),
+    ("whitesmoke", [245, 245, 245]),
+    ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(value: &str) -> (u8, u8, u8) {
+        let c = parse_color(value).unwrap_or_else(|| panic!("{value} should parse"));
+        (c.r, c.g, c.b)
+    }
+
+    fn close(actual: (u8, u8, u8), expected: (u8, u8, u8)) -> bool {
+        let d = |a: u8, b: u8| (a as i16 - b as i16).abs() <= 1;
+        d(actual.0, expected.0) && d(actual.1, expected.1) && d(actual.2, expected.2)
+    }
+
+    #[test]
+    fn test_named_color_table_is_sorted() {
+        assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(rgb("RebeccaPurple"), (102, 51, 153));
+        assert_eq!(rgb("lightgoldenrodyellow"), (250, 250, 210));
+        assert!(parse_color("notacolor").is_none());
+        assert!(parse_color("currentcolor").is_none());
+    }
+
+    #[test]
+    fn test_hex_with_alpha() {
+        let c = parse_color("#f008").unwrap();
+        assert_eq!((c.r, c.g, c.b), (255, 0, 0));
+        assert!((c.a - 136.0 / 255.0).abs() < 1e-4);
+        assert!(parse_color("#ggg").is_none());
+    }
+
+    #[test]
+    fn test_rgb_modern_syntax() {
+        assert_eq!(rgb("rgb(255 128 0)"), (255, 128, 0));
+        assert_eq!(rgb("rgb(100% 50% 0%)"), (255, 128, 0));
+        let c = parse_color("rgb(0 0 255 / 50%)").unwrap();
+        assert_eq!((c.b, c.a), (255, 0.5));
+        let c = parse_color("rgba(10, 20, 30, 0.25)").unwrap();
+        assert_eq!((c.r, c.g, c.b, c.a), (10, 20, 30, 0.25));
+        // Mixing comma and slash syntax is invalid
+        assert!(parse_color("rgb(1, 2, 3 / 0.5)").is_none());
+    }
+
+    #[test]
+    fn test_hsl_and_hwb() {
+        assert_eq!(rgb("hsl(120deg 100% 50%)"), (0, 255, 0));
+        assert_eq!(rgb("hsl(0.5turn, 100%, 50%)"), (0, 255, 255));
+        assert_eq!(parse_color("hsla(240, 100%, 50%, 0.5)").unwrap().a, 0.5);
+        assert_eq!(rgb("hwb(0 0% 0%)"), (255, 0, 0));
+        assert_eq!(rgb("hwb(200 60% 60%)"), (128, 128, 128));
+        assert!(parse_color("hwb(0, 0%, 0%)").is_none());
+    }
+
+    #[test]
+    fn test_lab_and_oklch() {
+        assert!(close(rgb("lab(54.29 80.8 69.89)"), (255, 0, 0)));
+        assert!(close(rgb("lch(54.29 106.84 40.85)"), (255, 0, 0)));
+        assert!(close(rgb("oklab(0.628 0.2249 0.1258)"), (255, 0, 0)));
+        assert!(close(rgb("oklch(62.8% 0.2577 29.23)"), (255, 0, 0)));
+        assert_eq!(rgb("oklch(100% 0 0)"), (255, 255, 255));
+        assert_eq!(rgb("lab(0 0 0)"), (0, 0, 0));
+        assert_eq!(parse_color("oklch(0.5 0.1 200 / 0.3)").unwrap().a, 0.3);
+    }
+}
@@ -13,6 +13,14 @@ use thiserror::Error;
 use tracing::debug;
 use rustkit_cssparser::parse_stylesheet;
 
+mod color;
+mod media;
+mod shorthand;
+
+pub use color::parse_color;
+pub use media::{MediaFeatures, MediaQueryList};
+pub use shorthand::{shorthand, Shorthand};
+
 /// Errors that can occur in CSS operations.
 #[derive(Error, Debug)]
 pub enum CssError {
@@ -313,9 +321,28 @@ pub enum Length {
     Max(Box<(Length, Length)>),
     /// clamp(min, preferred, max) - clamps preferred between min and max.
     Clamp(Box<(Length, Length, Length)>),
+    /// min-content - the narrowest size that doesn't overflow.
+    MinContent,
+    /// max-content - the size the content takes without wrapping.
+    MaxContent,
+    /// fit-content - max-content, limited by the available space but never
+    /// below min-content.
+    FitContent,
 }
 
 impl Length {
+    /// Whether this is an intrinsic sizing keyword (`min-content`,
+    /// `max-content` or `fit-content`), sized from the box's content.
+    pub fn is_intrinsic(&self) -> bool {
+        matches!(self, Length::MinContent | Length::MaxContent | Length::FitContent)
+    }
+
+    /// Whether the size comes from content or context rather than the value:
+    /// `auto` or an intrinsic sizing keyword.
+    pub fn is_content_sized(&self) -> bool {
+        matches!(self, Length::Auto) || self.is_intrinsic()
+    }
+
     /// Compute the absolute pixel value.
     /// 
     /// For viewport units, pass viewport dimensions via `viewport_width` and `viewport_height`.
@@ -341,7 +368,8 @@ impl Length {
             Length::Vh(vh) => vh / 100.0 * viewport_height,
             Length::Vmin(vmin) => vmin / 100.0 * viewport_width.min(viewport_height),
             Length::Vmax(vmax) => vmax / 100.0 * viewport_width.max(viewport_height),
-            Length::Auto => 0.0, // Context-dependent
+            // Context-dependent
+            Length::Auto | Length::MinContent | Length::MaxContent | Length::FitContent => 0.0,
             Length::Zero => 0.0,
             Length::Min(pair) => {
                 let a = pair.0.to_px_with_viewport(font_size, root_font_size, container_size, viewport_width, viewport_height);
@@ -484,6 +512,8 @@ pub struct ColorStop {
     pub color: Color,
     /// Position along the gradient (percentage 0.0-1.0, pixels, or None for auto).
     pub position: Option<StopPosition>,
+    /// Transition hint between this stop and the next one, if any.
+    pub hint: Option<StopPosition>,
 }
 
 impl ColorStop {
@@ -491,6 +521,7 @@ impl ColorStop {
         Self {
             color,
             position: position.map(StopPosition::Percent),
+            hint: None,
         }
     }
 
@@ -499,6 +530,7 @@ impl ColorStop {
         Self {
             color,
             position: Some(StopPosition::Pixels(pixels)),
+            hint: None,
         }
     }
 
@@ -507,10 +539,119 @@ impl ColorStop {
         Self {
             color,
             position: Some(StopPosition::Percent(percent)),
+            hint: None,
         }
     }
 }
 
+/// Resolve color stops to ascending positions on the 0-1 gradient line.
+///
+/// `normalize` maps an explicit position onto the gradient line. Missing
+/// positions are fixed up per CSS Images 3: the first and last stops default
+/// to 0 and 1, a position is never less than any before it, and runs of
+/// unpositioned stops are spaced evenly between their neighbours.
+///
+/// Transition hints are replaced with extra stops that approximate the hint's
+/// exponential curve, the same way Blink does, so renderers only ever need to
+/// interpolate linearly between stops.
+pub fn resolve_color_stops(
+    stops: &[ColorStop],
+    normalize: impl Fn(&StopPosition) -> f32,
+) -> Vec<(f32, ColorF32)> {
+    let n = stops.len();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut positions: Vec<Option<f32>> = stops.iter().map(|s| s.position.as_ref().map(&normalize)).collect();
+    positions[0].get_or_insert(0.0);
+    positions[n - 1].get_or_insert(1.0);
+
+    let mut max = f32::MIN;
+    for pos in positions.iter_mut().flatten() {
+        *pos = pos.max(max);
+        max = *pos;
+    }
+
+    let mut i = 1;
+    while i < n {
+        if positions[i].is_some() {
+            i += 1;
+            continue;
+        }
+        let start = i - 1;
+        let end = (i..n).find(|&j| positions[j].is_some()).unwrap_or(n - 1);
+        let (from, to) = (positions[start].unwrap_or(0.0), positions[end].unwrap_or(1.0));
+        let span = (end - start) as f32;
+        for (k, slot) in positions[start + 1..end].iter_mut().enumerate() {
+            *slot = Some(from + (to - from) * (k + 1) as f32 / span);
+        }
+        i = end + 1;
+    }
+
+    let mut resolved = Vec::with_capacity(n);
+    for (i, stop) in stops.iter().enumerate() {
+        let pos = positions[i].unwrap_or(0.0);
+        let color = ColorF32::from_color(stop.color);
+        resolved.push((pos, color));
+
+        let (Some(hint), Some(next)) = (stop.hint.as_ref(), stops.get(i + 1)) else {
+            continue;
+        };
+        let next_pos = positions[i + 1].unwrap_or(pos);
+        let next_color = ColorF32::from_color(next.color);
+        push_hint_stops(&mut resolved, (pos, color), (next_pos, next_color), normalize(hint));
+    }
+    resolved
+}
+
+/// Append the stops that approximate a transition hint between two stops.
+fn push_hint_stops(out: &mut Vec<(f32, ColorF32)>, left: (f32, ColorF32), right: (f32, ColorF32), hint: f32) {
+    const EPSILON: f32 = 1e-4;
+    let (left_pos, left_color) = left;
+    let (right_pos, right_color) = right;
+    let hint = hint.clamp(left_pos, right_pos);
+    let left_dist = hint - left_pos;
+    let right_dist = right_pos - hint;
+
+    // A hint at the midpoint is plain linear interpolation
+    if (left_dist - right_dist).abs() < EPSILON {
+        return;
+    }
+    // A hint on either stop turns the segment into a hard edge
+    if left_dist < EPSILON {
+        out.push((hint, right_color));
+        return;
+    }
+    if right_dist < EPSILON {
+        out.push((hint, left_color));
+        return;
+    }
+
+    // Nine stops, denser on the side of the hint where the curve is steeper
+    let mut offsets = [0.0f32; 9];
+    if left_dist > right_dist {
+        for (y, offset) in offsets.iter_mut().take(7).enumerate() {
+            *offset = left_pos + left_dist * (7 + y) as f32 / 13.0;
+        }
+        offsets[7] = hint + right_dist / 3.0;
+        offsets[8] = hint + right_dist * 2.0 / 3.0;
+    } else {
+        offsets[0] = left_pos + left_dist / 3.0;
+        offsets[1] = left_pos + left_dist * 2.0 / 3.0;
+        for (y, offset) in offsets.iter_mut().skip(2).enumerate() {
+            *offset = hint + right_dist * y as f32 / 13.0;
+        }
+    }
+
+    let span = right_pos - left_pos;
+    let exponent = 0.5f32.ln() / ((hint - left_pos) / span).ln();
+    for offset in offsets {
+        let t = ((offset - left_pos) / span).powf(exponent);
+        out.push((offset, left_color.lerp(&right_color, t)));
+    }
+}
+
 /// Direction for linear gradients.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum GradientDirection {
@@ -839,6 +980,8 @@ pub enum Display {
     InlineFlex,
     Grid,
     InlineGrid,
+    /// A block box that also generates a `::marker`.
+    ListItem,
     None,
 }
 
@@ -963,6 +1106,12 @@ pub enum FlexBasis {
     Length(f32),
     /// Percentage of container.
     Percent(f32),
+    /// The item's min-content size.
+    MinContent,
+    /// The item's max-content size.
+    MaxContent,
+    /// The item's max-content size, limited by the container.
+    FitContent,
 }
 
 // ==================== Grid Types ====================
@@ -1184,32 +1333,38 @@ pub struct GridTemplateAreas {
 
 impl GridTemplateAreas {
     /// Parse grid-template-areas value.
+    ///
+    /// Each quoted string is one row; several strings may share a line.
+    /// Returns `None` for `none` and for invalid values: rows of unequal
+    /// length or areas that aren't rectangles.
     pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value == "none" {
+            return None;
+        }
+
+        let row_strings = Self::row_strings(value);
         let mut rows = Vec::new();
-        
-        for line in value.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            // Remove quotes if present
-            let line = line.trim_matches('"').trim_matches('\'');
-            
+        for line in row_strings {
             let cells: Vec<Option<String>> = line
                 .split_whitespace()
                 .map(|s| {
-                    if s == "." {
+                    // Any run of periods is a null cell token
+                    if s.chars().all(|c| c == '.') {
                         None
                     } else {
                         Some(s.to_string())
                     }
                 })
                 .collect();
-            
+
+            if cells.is_empty() {
+                return None;
+            }
             rows.push(cells);
         }
 
-        if rows.is_empty() {
+        if rows.is_empty() || rows.iter().any(|r| r.len() != rows[0].len()) {
             return None;
         }
 
@@ -1236,9 +1391,44 @@ impl GridTemplateAreas {
             }
         }
 
+        // Every cell carrying an area's name must lie inside its rectangle, and
+        // the rectangle must be filled
+        for area in &areas {
+            let cells = rows
+                .iter()
+                .flatten()
+                .filter(|cell| cell.as_deref() == Some(area.name.as_str()))
+                .count();
+            let rect = (area.row_end - area.row_start) * (area.column_end - area.column_start);
+            if cells as i32 != rect {
+                return None;
+            }
+        }
+
         Some(Self { rows, areas })
     }
 
+    /// Split a value into its row strings: the contents of each quoted string,
+    /// or each line when the value isn't quoted.
+    fn row_strings(value: &str) -> Vec<&str> {
+        if !value.contains(['"', '\'']) {
+            return value.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+        }
+
+        let mut strings = Vec::new();
+        let mut rest = value;
+        while let Some(open) = rest.find(['"', '\'']) {
+            let quote = rest[open..].chars().next().unwrap_or('"');
+            let after = &rest[open + 1..];
+            let Some(close) = after.find(quote) else {
+                break;
+            };
+            strings.push(&after[..close]);
+            rest = &after[close + 1..];
+        }
+        strings
+    }
+
     fn find_area_extent(rows: &[Vec<Option<String>>], start_row: usize, start_col: usize, name: &str) -> (usize, usize) {
         let mut row_end = start_row;
         let mut col_end = start_col;
@@ -1460,11 +1650,26 @@ impl LineHeight {
 /// Text alignment.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextAlign {
-    #[default]
     Left,
     Right,
     Center,
     Justify,
+    /// The start edge of the line: left in LTR, right in RTL.
+    #[default]
+    Start,
+    /// The end edge of the line: right in LTR, left in RTL.
+    End,
+}
+
+impl TextAlign {
+    /// Resolve `start`/`end` to a physical alignment for the given direction.
+    pub fn resolve(self, direction: Direction) -> TextAlign {
+        match (self, direction) {
+            (TextAlign::Start, Direction::Ltr) | (TextAlign::End, Direction::Rtl) => TextAlign::Left,
+            (TextAlign::Start, Direction::Rtl) | (TextAlign::End, Direction::Ltr) => TextAlign::Right,
+            (other, _) => other,
+        }
+    }
 }
 
 /// Overflow behavior.
@@ -1525,6 +1730,178 @@ pub enum ScrollbarGutter {
     BothEdges,
 }
 
+/// How a multi-column container fills its columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnFill {
+    /// Balance content equally between columns.
+    #[default]
+    Balance,
+    /// Fill columns one after another.
+    Auto,
+}
+
+/// Whether a box may be broken across columns or pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakInside {
+    #[default]
+    Auto,
+    /// Avoid breaks of any kind inside the box.
+    Avoid,
+    /// Avoid column breaks inside the box.
+    AvoidColumn,
+    /// Avoid page breaks inside the box.
+    AvoidPage,
+}
+
+impl BreakInside {
+    /// Whether the box should be kept in one column.
+    pub fn avoids_column_break(self) -> bool {
+        matches!(self, BreakInside::Avoid | BreakInside::AvoidColumn)
+    }
+
+    /// Whether the box should be kept on one page.
+    pub fn avoids_page_break(self) -> bool {
+        matches!(self, BreakInside::Avoid | BreakInside::AvoidPage)
+    }
+}
+
+/// Whether a break is forced or avoided before or after a box
+/// (`break-before`, `break-after`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakBetween {
+    #[default]
+    Auto,
+    /// Avoid breaks of any kind.
+    Avoid,
+    /// Avoid page breaks.
+    AvoidPage,
+    /// Force a page break, as `page`, `left`, `right`, `recto` and `verso`
+    /// (or the legacy `always`) all do.
+    Page,
+    /// Avoid column breaks.
+    AvoidColumn,
+    /// Force a column break.
+    Column,
+}
+
+impl BreakBetween {
+    /// Whether a page break must happen here.
+    pub fn forces_page_break(self) -> bool {
+        self == BreakBetween::Page
+    }
+
+    /// Whether a page break should not happen here.
+    pub fn avoids_page_break(self) -> bool {
+        matches!(self, BreakBetween::Avoid | BreakBetween::AvoidPage)
+    }
+}
+
+/// List marker style (`list-style-type`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListStyleType {
+    None,
+    #[default]
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    DecimalLeadingZero,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+    /// Triangle of a closed `<details>` summary.
+    DisclosureClosed,
+    /// Triangle of an open `<details>` summary.
+    DisclosureOpen,
+}
+
+impl ListStyleType {
+    /// Parse a `list-style-type` keyword.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "none" => Some(ListStyleType::None),
+            "disc" => Some(ListStyleType::Disc),
+            "circle" => Some(ListStyleType::Circle),
+            "square" => Some(ListStyleType::Square),
+            "decimal" => Some(ListStyleType::Decimal),
+            "decimal-leading-zero" => Some(ListStyleType::DecimalLeadingZero),
+            "lower-alpha" | "lower-latin" => Some(ListStyleType::LowerAlpha),
+            "upper-alpha" | "upper-latin" => Some(ListStyleType::UpperAlpha),
+            "lower-roman" => Some(ListStyleType::LowerRoman),
+            "upper-roman" => Some(ListStyleType::UpperRoman),
+            "disclosure-closed" => Some(ListStyleType::DisclosureClosed),
+            "disclosure-open" => Some(ListStyleType::DisclosureOpen),
+            _ => None,
+        }
+    }
+
+    /// Marker text for the list item numbered `ordinal`, including the
+    /// suffix, or None for `list-style-type: none`.
+    ///
+    /// Alphabetic and roman styles fall back to decimal outside their range.
+    pub fn marker_text(self, ordinal: i32) -> Option<String> {
+        let symbol = match self {
+            ListStyleType::None => return None,
+            ListStyleType::Disc => "\u{2022}",
+            ListStyleType::Circle => "\u{25E6}",
+            ListStyleType::Square => "\u{25AA}",
+            ListStyleType::DisclosureClosed => "\u{25B8}",
+            ListStyleType::DisclosureOpen => "\u{25BE}",
+            _ => return Some(format!("{}. ", self.counter_representation(ordinal))),
+        };
+        Some(format!("{} ", symbol))
+    }
+
+    /// The counter value written in this style, without a suffix.
+    fn counter_representation(self, ordinal: i32) -> String {
+        match self {
+            ListStyleType::DecimalLeadingZero if (0..10).contains(&ordinal) => format!("0{}", ordinal),
+            ListStyleType::LowerAlpha if ordinal >= 1 => alphabetic(ordinal as u32),
+            ListStyleType::UpperAlpha if ordinal >= 1 => alphabetic(ordinal as u32).to_uppercase(),
+            ListStyleType::LowerRoman if (1..4000).contains(&ordinal) => roman(ordinal as u32).to_lowercase(),
+            ListStyleType::UpperRoman if (1..4000).contains(&ordinal) => roman(ordinal as u32),
+            _ => ordinal.to_string(),
+        }
+    }
+}
+
+/// Bijective base-26: 1 → a, 26 → z, 27 → aa.
+fn alphabetic(mut n: u32) -> String {
+    let mut letters = Vec::new();
+    while n > 0 {
+        n -= 1;
+        letters.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+fn roman(mut n: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            out.push_str(numeral);
+            n -= value;
+        }
+    }
+    out
+}
+
+/// Where a list marker sits relative to the list item (`list-style-position`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListStylePosition {
+    /// In the margin, beside the item's content.
+    #[default]
+    Outside,
+    /// As the first inline box of the item's content.
+    Inside,
+}
+
 /// Text decoration line values.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct TextDecorationLine {
@@ -1666,6 +2043,37 @@ pub enum Direction {
     Rtl,
 }
 
+impl Direction {
+    /// Check if this direction is right-to-left.
+    pub fn is_rtl(self) -> bool {
+        self == Direction::Rtl
+    }
+}
+
+/// How an element's text takes part in the bidi algorithm (`unicode-bidi`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnicodeBidi {
+    #[default]
+    Normal,
+    /// Open an embedding level in the element's direction.
+    Embed,
+    /// Lay the element's text out as a separate bidi paragraph.
+    Isolate,
+    /// Force every character into the element's direction.
+    BidiOverride,
+    /// Isolate and override combined.
+    IsolateOverride,
+    /// Pick the paragraph direction from the text itself, ignoring `direction`.
+    Plaintext,
+}
+
+impl UnicodeBidi {
+    /// Whether characters are forced into the element's direction.
+    pub fn is_override(self) -> bool {
+        matches!(self, UnicodeBidi::BidiOverride | UnicodeBidi::IsolateOverride)
+    }
+}
+
 // ==================== Transform Types ====================
 
 /// A single 2D transform operation.
@@ -1780,6 +2188,38 @@ fn multiply_matrices(a: [f32; 6], b: [f32; 6]) -> [f32; 6] {
     ]
 }
 
+/// Properties named by `will-change`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WillChange {
+    pub transform: bool,
+    pub opacity: bool,
+    pub scroll_position: bool,
+    pub contents: bool,
+}
+
+impl WillChange {
+    /// Parse a `will-change` value. Unknown identifiers are ignored; `auto`
+    /// names nothing.
+    pub fn parse(value: &str) -> Self {
+        let mut will_change = Self::default();
+        for part in value.split(',') {
+            match part.trim().to_ascii_lowercase().as_str() {
+                "transform" | "translate" | "rotate" | "scale" => will_change.transform = true,
+                "opacity" => will_change.opacity = true,
+                "scroll-position" => will_change.scroll_position = true,
+                "contents" => will_change.contents = true,
+                _ => {}
+            }
+        }
+        will_change
+    }
+
+    /// Whether the hint asks for a compositor layer.
+    pub fn wants_layer(&self) -> bool {
+        self.transform || self.opacity
+    }
+}
+
 /// Transform origin (default: 50% 50%).
 #[derive(Debug, Clone, PartialEq)]
 pub struct TransformOrigin {
@@ -1813,6 +2253,73 @@ pub enum TimingFunction {
     CubicBezier(f32, f32, f32, f32),
 }
 
+impl TimingFunction {
+    /// Eased output progress for input progress `t`, clamped to 0..=1.
+    pub fn progress(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            TimingFunction::Linear => t,
+            TimingFunction::Ease => cubic_bezier(0.25, 0.1, 0.25, 1.0, t),
+            TimingFunction::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            TimingFunction::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            TimingFunction::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            TimingFunction::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+            TimingFunction::StepStart => steps(1, true, t),
+            TimingFunction::StepEnd => steps(1, false, t),
+            TimingFunction::Steps(count, jump_start) => steps(count, jump_start, t),
+        }
+    }
+}
+
+/// `steps(count, start|end)` at progress `t`.
+fn steps(count: u32, jump_start: bool, t: f32) -> f32 {
+    let count = count.max(1) as f32;
+    let step = if jump_start { (t * count).ceil() } else { (t * count).floor() };
+    (step / count).min(1.0)
+}
+
+/// `cubic-bezier(x1, y1, x2, y2)` at progress `t`: the curve's y where its
+/// x is `t`, found by Newton's method with bisection as the fallback.
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, t: f32) -> f32 {
+    fn sample(p1: f32, p2: f32, s: f32) -> f32 {
+        let u = 1.0 - s;
+        3.0 * u * u * s * p1 + 3.0 * u * s * s * p2 + s * s * s
+    }
+    fn slope(p1: f32, p2: f32, s: f32) -> f32 {
+        let u = 1.0 - s;
+        3.0 * u * u * p1 + 6.0 * u * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+    }
+
+    let mut s = t;
+    for _ in 0..8 {
+        let error = sample(x1, x2, s) - t;
+        if error.abs() < 1e-5 {
+            return sample(y1, y2, s);
+        }
+        let d = slope(x1, x2, s);
+        if d.abs() < 1e-6 {
+            break;
+        }
+        s -= error / d;
+    }
+
+    let (mut low, mut high) = (0.0f32, 1.0f32);
+    s = t;
+    for _ in 0..32 {
+        let x = sample(x1, x2, s);
+        if (x - t).abs() < 1e-5 {
+            break;
+        }
+        if x < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    sample(y1, y2, s)
+}
+
 /// Animation fill mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AnimationFillMode {
@@ -1944,6 +2451,7 @@ pub struct ComputedStyle {
     pub vertical_align: VerticalAlign,
     pub writing_mode: WritingMode,
     pub direction: Direction,
+    pub unicode_bidi: UnicodeBidi,
 
     // Positioning offsets
     pub top: Option<Length>,
@@ -1955,14 +2463,15 @@ pub struct ComputedStyle {
     // Transforms
     pub transform: TransformList,
     pub transform_origin: TransformOrigin,
+    pub will_change: WillChange,
 
-    // Transitions (parsed but not executed during parity capture)
+    // Transitions
     pub transition_property: String,
     pub transition_duration: f32, // seconds
     pub transition_timing_function: TimingFunction,
     pub transition_delay: f32, // seconds
 
-    // Animations (parsed but not executed during parity capture)
+    // Animations
     pub animation_name: String,
     pub animation_duration: f32, // seconds
     pub animation_timing_function: TimingFunction,
@@ -2015,6 +2524,25 @@ pub struct ComputedStyle {
     pub scrollbar_gutter: ScrollbarGutter,
     pub scrollbar_color: Option<(Color, Color)>, // (thumb, track)
 
+    // Multi-column layout (column-gap is shared with flex and grid)
+    /// None is `auto`.
+    pub column_count: Option<u32>,
+    /// None is `auto`.
+    pub column_width: Option<Length>,
+    pub column_fill: ColumnFill,
+    pub column_rule_width: Length,
+    /// None is `currentcolor`.
+    pub column_rule_color: Option<Color>,
+
+    // Fragmentation
+    pub break_inside: BreakInside,
+    pub break_before: BreakBetween,
+    pub break_after: BreakBetween,
+
+    // Lists
+    pub list_style_type: ListStyleType,
+    pub list_style_position: ListStylePosition,
+
     // Grid Container
     pub grid_template_columns: GridTemplate,
     pub grid_template_rows: GridTemplate,
@@ -2063,10 +2591,12 @@ impl ComputedStyle {
             // Width/height defaults to auto (fill available space)
             width: Length::Auto,
             height: Length::Auto,
-            min_width: Length::Zero,
-            min_height: Length::Zero,
+            min_width: Length::Auto,
+            min_height: Length::Auto,
             max_width: Length::Auto, // No max constraint
             max_height: Length::Auto,
+            // `normal`: no gap in flex and grid, 1em between columns
+            column_gap: Length::Auto,
             // Image/replaced element defaults
             image_url: None,
             object_fit: "contain".to_string(),
@@ -2095,6 +2625,8 @@ impl ComputedStyle {
             word_break: parent.word_break,
             direction: parent.direction,
             writing_mode: parent.writing_mode,
+            list_style_type: parent.list_style_type,
+            list_style_position: parent.list_style_position,
 
             // Text decoration is NOT inherited (each element sets its own)
             text_decoration_line: TextDecorationLine::NONE,
@@ -2109,7 +2641,7 @@ impl ComputedStyle {
 }
 
 /// CSS property value (unparsed or parsed).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PropertyValue {
     /// Inherit from parent.
     Inherit,
@@ -2120,7 +2652,7 @@ pub enum PropertyValue {
 }
 
 /// A CSS declaration (property: value).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Declaration {
     pub property: String,
     pub value: PropertyValue,
@@ -2128,29 +2660,120 @@ pub struct Declaration {
 }
 
 /// A CSS rule (selector + declarations).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Rule {
     pub selector: String,
     pub declarations: Vec<Declaration>,
 }
 
+/// One keyframe of a `@keyframes` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    /// Where in an iteration the keyframe is, from 0.0 (`from`) to 1.0
+    /// (`to`).
+    pub offset: f32,
+    pub declarations: Vec<Declaration>,
+}
+
+/// A `@keyframes` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes {
+    pub name: String,
+    /// Keyframes in offset order.
+    pub frames: Vec<Keyframe>,
+}
+
+impl Keyframes {
+    /// Build the `@keyframes` rule named `prelude` from the rules in its
+    /// block. A rule listing several offsets gives a keyframe at each;
+    /// invalid offsets are dropped.
+    fn from_rules(prelude: &str, rules: Vec<rustkit_cssparser::RuleAst>) -> Self {
+        let mut frames = Vec::new();
+        for rule in rules {
+            let declarations: Vec<Declaration> = rule
+                .declarations
+                .into_iter()
+                .flat_map(|d| expand_declaration(d.property, d.value, d.important))
+                // !important declarations are ignored in keyframes
+                .filter(|d| !d.important)
+                .collect();
+            for selector in rule.selector.split(',') {
+                let offset = match selector.trim().to_ascii_lowercase().as_str() {
+                    "from" => Some(0.0),
+                    "to" => Some(1.0),
+                    percent => percent
+                        .strip_suffix('%')
+                        .and_then(|n| n.trim().parse::<f32>().ok())
+                        .filter(|n| (0.0..=100.0).contains(n))
+                        .map(|n| n / 100.0),
+                };
+                if let Some(offset) = offset {
+                    frames.push(Keyframe {
+                        offset,
+                        declarations: declarations.clone(),
+                    });
+                }
+            }
+        }
+        frames.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        Keyframes {
+            name: prelude.trim().trim_matches(|c| c == '"' || c == '\'').to_string(),
+            frames,
+        }
+    }
+}
+
 /// A complete stylesheet.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
+    /// `@keyframes` rules, in source order.
+    pub keyframes: Vec<Keyframes>,
+    /// `@media` rules, in source order.
+    pub media: Vec<MediaRule>,
+}
+
+/// An `@media` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRule {
+    pub query: MediaQueryList,
+    /// How many of the enclosing stylesheet's rules come before this one.
+    pub index: usize,
+    /// The rules that apply while the query matches.
+    pub stylesheet: Stylesheet,
 }
 
 impl Stylesheet {
     /// Create an empty stylesheet.
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self::default()
     }
 
     /// Parse a CSS string into a stylesheet.
     pub fn parse(css: &str) -> Result<Self, CssError> {
         debug!(len = css.len(), "Parsing CSS");
         let ast = parse_stylesheet(css).map_err(|e| CssError::ParseError(e.to_string()))?;
-
+        let stylesheet = Self::from_ast(ast);
+        debug!(rule_count = stylesheet.rules.len(), media_rules = stylesheet.media.len(), "CSS parsed");
+        Ok(stylesheet)
+    }
+
+    fn from_ast(ast: rustkit_cssparser::StylesheetAst) -> Self {
+        let mut keyframes = Vec::new();
+        let mut media = Vec::new();
+        for at_rule in ast.at_rules {
+            match at_rule.name.as_str() {
+                "keyframes" | "-webkit-keyframes" => {
+                    keyframes.push(Keyframes::from_rules(&at_rule.prelude, at_rule.block.rules))
+                }
+                "media" => media.push(MediaRule {
+                    query: MediaQueryList::parse(&at_rule.prelude),
+                    index: at_rule.index,
+                    stylesheet: Self::from_ast(at_rule.block),
+                }),
+                _ => {}
+            }
+        }
         let rules = ast
             .rules
             .into_iter()
@@ -2159,288 +2782,83 @@ impl Stylesheet {
                 declarations: r
                     .declarations
                     .into_iter()
-                    .map(|d| Declaration {
-                        property: d.property,
-                        value: PropertyValue::Specified(d.value),
-                        important: d.important,
-                    })
+                    .flat_map(|d| expand_declaration(d.property, d.value, d.important))
                     .collect(),
             })
             .collect::<Vec<_>>();
 
-        debug!(rule_count = rules.len(), "CSS parsed");
-        Ok(Stylesheet { rules })
-    }
-
-    /// Get the number of rules in this stylesheet.
-    pub fn rule_count(&self) -> usize {
-        self.rules.len()
+        Stylesheet { rules, keyframes, media }
     }
-}
-
-/// Parse a color value.
-pub fn parse_color(value: &str) -> Option<Color> {
-    let value = value.trim();
 
-    // Named colors (CSS Color Level 4)
-    match value.to_lowercase().as_str() {
-        "transparent" => return Some(Color::TRANSPARENT),
-        "black" => return Some(Color::BLACK),
-        "white" => return Some(Color::WHITE),
-        "red" => return Some(Color::from_rgb(255, 0, 0)),
-        "green" => return Some(Color::from_rgb(0, 128, 0)),
-        "blue" => return Some(Color::from_rgb(0, 0, 255)),
-        "yellow" => return Some(Color::from_rgb(255, 255, 0)),
-        "gray" | "grey" => return Some(Color::from_rgb(128, 128, 128)),
-        // Extended named colors
-        "coral" => return Some(Color::from_rgb(255, 127, 80)),
-        "orange" => return Some(Color::from_rgb(255, 165, 0)),
-        "pink" => return Some(Color::from_rgb(255, 192, 203)),
-        "purple" => return Some(Color::from_rgb(128, 0, 128)),
-        "cyan" => return Some(Color::from_rgb(0, 255, 255)),
-        "magenta" | "fuchsia" => return Some(Color::from_rgb(255, 0, 255)),
-        "lime" => return Some(Color::from_rgb(0, 255, 0)),
-        "navy" => return Some(Color::from_rgb(0, 0, 128)),
-        "teal" => return Some(Color::from_rgb(0, 128, 128)),
-        "olive" => return Some(Color::from_rgb(128, 128, 0)),
-        "maroon" => return Some(Color::from_rgb(128, 0, 0)),
-        "aqua" => return Some(Color::from_rgb(0, 255, 255)),
-        "silver" => return Some(Color::from_rgb(192, 192, 192)),
-        "lightgray" | "lightgrey" => return Some(Color::from_rgb(211, 211, 211)),
-        "darkgray" | "darkgrey" => return Some(Color::from_rgb(169, 169, 169)),
-        "dimgray" | "dimgrey" => return Some(Color::from_rgb(105, 105, 105)),
-        "lightblue" => return Some(Color::from_rgb(173, 216, 230)),
-        "lightgreen" => return Some(Color::from_rgb(144, 238, 144)),
-        "lightyellow" => return Some(Color::from_rgb(255, 255, 224)),
-        "lightpink" => return Some(Color::from_rgb(255, 182, 193)),
-        "lightcoral" => return Some(Color::from_rgb(240, 128, 128)),
-        "darkblue" => return Some(Color::from_rgb(0, 0, 139)),
-        "darkgreen" => return Some(Color::from_rgb(0, 100, 0)),
-        "darkred" => return Some(Color::from_rgb(139, 0, 0)),
-        "gold" => return Some(Color::from_rgb(255, 215, 0)),
-        "brown" => return Some(Color::from_rgb(165, 42, 42)),
-        "beige" => return Some(Color::from_rgb(245, 245, 220)),
-        "ivory" => return Some(Color::from_rgb(255, 255, 240)),
-        "wheat" => return Some(Color::from_rgb(245, 222, 179)),
-        "tan" => return Some(Color::from_rgb(210, 180, 140)),
-        "khaki" => return Some(Color::from_rgb(240, 230, 140)),
-        "salmon" => return Some(Color::from_rgb(250, 128, 114)),
-        "tomato" => return Some(Color::from_rgb(255, 99, 71)),
-        "crimson" => return Some(Color::from_rgb(220, 20, 60)),
-        "indianred" => return Some(Color::from_rgb(205, 92, 92)),
-        "firebrick" => return Some(Color::from_rgb(178, 34, 34)),
-        "orangered" => return Some(Color::from_rgb(255, 69, 0)),
-        "chocolate" => return Some(Color::from_rgb(210, 105, 30)),
-        "sienna" => return Some(Color::from_rgb(160, 82, 45)),
-        "peru" => return Some(Color::from_rgb(205, 133, 63)),
-        "sandybrown" => return Some(Color::from_rgb(244, 164, 96)),
-        "goldenrod" => return Some(Color::from_rgb(218, 165, 32)),
-        "darkgoldenrod" => return Some(Color::from_rgb(184, 134, 11)),
-        "lemonchiffon" => return Some(Color::from_rgb(255, 250, 205)),
-        "palegoldenrod" => return Some(Color::from_rgb(238, 232, 170)),
-        "greenyellow" => return Some(Color::from_rgb(173, 255, 47)),
-        "chartreuse" => return Some(Color::from_rgb(127, 255, 0)),
-        "lawngreen" => return Some(Color::from_rgb(124, 252, 0)),
-        "springgreen" => return Some(Color::from_rgb(0, 255, 127)),
-        "mediumspringgreen" => return Some(Color::from_rgb(0, 250, 154)),
-        "seagreen" => return Some(Color::from_rgb(46, 139, 87)),
-        "forestgreen" => return Some(Color::from_rgb(34, 139, 34)),
-        "limegreen" => return Some(Color::from_rgb(50, 205, 50)),
-        "palegreen" => return Some(Color::from_rgb(152, 251, 152)),
-        "mediumseagreen" => return Some(Color::from_rgb(60, 179, 113)),
-        "aquamarine" => return Some(Color::from_rgb(127, 255, 212)),
-        "turquoise" => return Some(Color::from_rgb(64, 224, 208)),
-        "mediumturquoise" => return Some(Color::from_rgb(72, 209, 204)),
-        "darkturquoise" => return Some(Color::from_rgb(0, 206, 209)),
-        "cadetblue" => return Some(Color::from_rgb(95, 158, 160)),
-        "steelblue" => return Some(Color::from_rgb(70, 130, 180)),
-        "lightsteelblue" => return Some(Color::from_rgb(176, 196, 222)),
-        "powderblue" => return Some(Color::from_rgb(176, 224, 230)),
-        "skyblue" => return Some(Color::from_rgb(135, 206, 235)),
-        "lightskyblue" => return Some(Color::from_rgb(135, 206, 250)),
-        "deepskyblue" => return Some(Color::from_rgb(0, 191, 255)),
-        "dodgerblue" => return Some(Color::from_rgb(30, 144, 255)),
-        "cornflowerblue" => return Some(Color::from_rgb(100, 149, 237)),
-        "royalblue" => return Some(Color::from_rgb(65, 105, 225)),
-        "mediumblue" => return Some(Color::from_rgb(0, 0, 205)),
-        "midnightblue" => return Some(Color::from_rgb(25, 25, 112)),
-        "slateblue" => return Some(Color::from_rgb(106, 90, 205)),
-        "darkslateblue" => return Some(Color::from_rgb(72, 61, 139)),
-        "mediumslateblue" => return Some(Color::from_rgb(123, 104, 238)),
-        "mediumpurple" => return Some(Color::from_rgb(147, 112, 219)),
-        "blueviolet" => return Some(Color::from_rgb(138, 43, 226)),
-        "darkorchid" => return Some(Color::from_rgb(153, 50, 204)),
-        "darkviolet" => return Some(Color::from_rgb(148, 0, 211)),
-        "mediumorchid" => return Some(Color::from_rgb(186, 85, 211)),
-        "orchid" => return Some(Color::from_rgb(218, 112, 214)),
-        "plum" => return Some(Color::from_rgb(221, 160, 221)),
-        "violet" => return Some(Color::from_rgb(238, 130, 238)),
-        "thistle" => return Some(Color::from_rgb(216, 191, 216)),
-        "lavender" => return Some(Color::from_rgb(230, 230, 250)),
-        "mistyrose" => return Some(Color::from_rgb(255, 228, 225)),
-        "antiquewhite" => return Some(Color::from_rgb(250, 235, 215)),
-        "linen" => return Some(Color::from_rgb(250, 240, 230)),
-        "oldlace" => return Some(Color::from_rgb(253, 245, 230)),
-        "papayawhip" => return Some(Color::from_rgb(255, 239, 213)),
-        "seashell" => return Some(Color::from_rgb(255, 245, 238)),
-        "mintcream" => return Some(Color::from_rgb(245, 255, 250)),
-        "slategray" | "slategrey" => return Some(Color::from_rgb(112, 128, 144)),
-        "lightslategray" | "lightslategrey" => return Some(Color::from_rgb(119, 136, 153)),
-        "gainsboro" => return Some(Color::from_rgb(220, 220, 220)),
-        "whitesmoke" => return Some(Color::from_rgb(245, 245, 245)),
-        "floralwhite" => return Some(Color::from_rgb(255, 250, 240)),
-        "ghostwhite" => return Some(Color::from_rgb(248, 248, 255)),
-        "honeydew" => return Some(Color::from_rgb(240, 255, 240)),
-        "azure" => return Some(Color::from_rgb(240, 255, 255)),
-        "aliceblue" => return Some(Color::from_rgb(240, 248, 255)),
-        "snow" => return Some(Color::from_rgb(255, 250, 250)),
-        "darkcyan" => return Some(Color::from_rgb(0, 139, 139)),
-        "darkmagenta" => return Some(Color::from_rgb(139, 0, 139)),
-        "darkorange" => return Some(Color::from_rgb(255, 140, 0)),
-        "darksalmon" => return Some(Color::from_rgb(233, 150, 122)),
-        "darkseagreen" => return Some(Color::from_rgb(143, 188, 143)),
-        "darkslategray" | "darkslategrey" => return Some(Color::from_rgb(47, 79, 79)),
-        "deeppink" => return Some(Color::from_rgb(255, 20, 147)),
-        "hotpink" => return Some(Color::from_rgb(255, 105, 180)),
-        "mediumvioletred" => return Some(Color::from_rgb(199, 21, 133)),
-        "palevioletred" => return Some(Color::from_rgb(219, 112, 147)),
-        "rosybrown" => return Some(Color::from_rgb(188, 143, 143)),
-        "saddlebrown" => return Some(Color::from_rgb(139, 69, 19)),
-        "yellowgreen" => return Some(Color::from_rgb(154, 205, 50)),
-        "olivedrab" => return Some(Color::from_rgb(107, 142, 35)),
-        "darkolivegreen" => return Some(Color::from_rgb(85, 107, 47)),
-        "mediumaquamarine" => return Some(Color::from_rgb(102, 205, 170)),
-        "lightcyan" => return Some(Color::from_rgb(224, 255, 255)),
-        "paleturquoise" => return Some(Color::from_rgb(175, 238, 238)),
-        "lightseagreen" => return Some(Color::from_rgb(32, 178, 170)),
-        "cornsilk" => return Some(Color::from_rgb(255, 248, 220)),
-        "blanchedalmond" => return Some(Color::from_rgb(255, 235, 205)),
-        "bisque" => return Some(Color::from_rgb(255, 228, 196)),
-        "navajowhite" => return Some(Color::from_rgb(255, 222, 173)),
-        "moccasin" => return Some(Color::from_rgb(255, 228, 181)),
-        "peachpuff" => return Some(Color::from_rgb(255, 218, 185)),
-        "burlywood" => return Some(Color::from_rgb(222, 184, 135)),
-        "lavenderblush" => return Some(Color::from_rgb(255, 240, 245)),
-        "currentcolor" => return None, // Special case - needs context
-        "inherit" => return None, // Special case - needs context
-        _ => {}
+    /// Apply the whole stylesheet only while `query` matches, as for
+    /// `<style media>` and `<link media>`.
+    pub fn with_media(self, query: MediaQueryList) -> Self {
+        Stylesheet {
+            media: vec![MediaRule { query, index: 0, stylesheet: self }],
+            ..Default::default()
+        }
     }
 
-    // Hex colors
-    if let Some(hex) = value.strip_prefix('#') {
-        let (r, g, b, a) = match hex.len() {
-            3 => {
-                let r = u8::from_str_radix(&hex[0..1], 16).ok()? * 17;
-                let g = u8::from_str_radix(&hex[1..2], 16).ok()? * 17;
-                let b = u8::from_str_radix(&hex[2..3], 16).ok()? * 17;
-                (r, g, b, 1.0)
-            }
-            6 => {
-                let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-                let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-                let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-                (r, g, b, 1.0)
-            }
-            8 => {
-                let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-                let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-                let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-                let a = u8::from_str_radix(&hex[6..8], 16).ok()? as f32 / 255.0;
-                (r, g, b, a)
+    /// The stylesheet as it applies to `features`: the rules of matching
+    /// `@media` rules spliced in where the `@media` rule was, and the rest
+    /// dropped.
+    pub fn for_media(&self, features: &MediaFeatures) -> Stylesheet {
+        if self.media.is_empty() {
+            return self.clone();
+        }
+        let mut out = Stylesheet { keyframes: self.keyframes.clone(), ..Default::default() };
+        let mut next = 0;
+        for media in &self.media {
+            let index = media.index.min(self.rules.len());
+            out.rules.extend_from_slice(&self.rules[next.min(index)..index]);
+            next = next.max(index);
+            if media.query.matches(features) {
+                let applied = media.stylesheet.for_media(features);
+                out.rules.extend(applied.rules);
+                out.keyframes.extend(applied.keyframes);
             }
-            _ => return None,
-        };
-        return Some(Color::new(r, g, b, a));
-    }
-
-    // rgb() / rgba()
-    if value.starts_with("rgb") {
-        // Simplified parsing
-        let inner = value
-            .trim_start_matches("rgba(")
-            .trim_start_matches("rgb(")
-            .trim_end_matches(')');
-        let parts: Vec<&str> = inner.split(',').collect();
-        if parts.len() >= 3 {
-            let r = parts[0].trim().parse::<u8>().ok()?;
-            let g = parts[1].trim().parse::<u8>().ok()?;
-            let b = parts[2].trim().parse::<u8>().ok()?;
-            let a = if parts.len() >= 4 {
-                parts[3].trim().parse::<f32>().ok()?
-            } else {
-                1.0
-            };
-            return Some(Color::new(r, g, b, a));
-        }
-    }
-
-    // hsl() / hsla()
-    if value.starts_with("hsl") {
-        let inner = value
-            .trim_start_matches("hsla(")
-            .trim_start_matches("hsl(")
-            .trim_end_matches(')');
-        let parts: Vec<&str> = inner.split(',').collect();
-        if parts.len() >= 3 {
-            let h = parts[0].trim().trim_end_matches("deg").parse::<f32>().ok()?;
-            let s = parts[1].trim().trim_end_matches('%').parse::<f32>().ok()? / 100.0;
-            let l = parts[2].trim().trim_end_matches('%').parse::<f32>().ok()? / 100.0;
-            let a = if parts.len() >= 4 {
-                parts[3].trim().parse::<f32>().ok()?
-            } else {
-                1.0
-            };
-            
-            // HSL to RGB conversion
-            let (r, g, b) = hsl_to_rgb(h, s, l);
-            return Some(Color::new(r, g, b, a));
         }
+        out.rules.extend_from_slice(&self.rules[next..]);
+        out
     }
 
-    None
-}
-
-/// Convert HSL to RGB
-fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
-    if s == 0.0 {
-        // Achromatic (gray)
-        let v = (l * 255.0).round() as u8;
-        return (v, v, v);
+    /// Get the number of rules in this stylesheet.
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
     }
+}
 
-    let h = h / 360.0;
-    let q = if l < 0.5 {
-        l * (1.0 + s)
-    } else {
-        l + s - l * s
+/// Expand a parsed declaration into longhand declarations.
+///
+/// Values containing `var()` can't be split until the variables are
+/// substituted, so they are kept as the shorthand for the style computer to
+/// expand later. Invalid shorthand values drop the declaration.
+fn expand_declaration(property: String, value: String, important: bool) -> Vec<Declaration> {
+    let expanded = match shorthand(&property) {
+        Some(shorthand) if !value.contains("var(") => shorthand.expand(&value),
+        _ => {
+            return vec![Declaration {
+                property,
+                value: PropertyValue::Specified(value),
+                important,
+            }]
+        }
     };
-    let p = 2.0 * l - q;
 
-    let r = hue_to_rgb(p, q, h + 1.0 / 3.0);
-    let g = hue_to_rgb(p, q, h);
-    let b = hue_to_rgb(p, q, h - 1.0 / 3.0);
-
-    (
-        (r * 255.0).round() as u8,
-        (g * 255.0).round() as u8,
-        (b * 255.0).round() as u8,
-    )
-}
-
-fn hue_to_rgb(p: f32, q: f32, mut t: f32) -> f32 {
-    if t < 0.0 { t += 1.0; }
-    if t > 1.0 { t -= 1.0; }
-    
-    if t < 1.0 / 6.0 {
-        return p + (q - p) * 6.0 * t;
-    }
-    if t < 1.0 / 2.0 {
-        return q;
-    }
-    if t < 2.0 / 3.0 {
-        return p + (q - p) * (2.0 / 3.0 - t) * 6.0;
+    match expanded {
+        Some(longhands) => longhands
+            .into_iter()
+            .map(|(longhand, value)| Declaration {
+                property: longhand.to_string(),
+                value: PropertyValue::Specified(value),
+                important,
+            })
+            .collect(),
+        None => {
+            debug!(property = property.as_str(), value = value.as_str(), "Dropping invalid shorthand");
+            Vec::new()
+        }
     }
-    p
 }
 
 /// Parse a length value.
@@ -2453,6 +2871,12 @@ pub fn parse_length(value: &str) -> Option<Length> {
     if value == "0" {
         return Some(Length::Zero);
     }
+    match value {
+        "min-content" => return Some(Length::MinContent),
+        "max-content" => return Some(Length::MaxContent),
+        "fit-content" | "-webkit-fit-content" | "-moz-fit-content" => return Some(Length::FitContent),
+        _ => {}
+    }
 
     // Handle min(), max(), clamp() CSS math functions
     if value.starts_with("min(") && value.ends_with(')') {
@@ -2579,6 +3003,7 @@ pub fn parse_display(value: &str) -> Option<Display> {
         "inline-flex" => Some(Display::InlineFlex),
         "grid" => Some(Display::Grid),
         "inline-grid" => Some(Display::InlineGrid),
+        "list-item" => Some(Display::ListItem),
         "none" => Some(Display::None),
         _ => None,
     }
@@ -2635,6 +3060,10 @@ mod tests {
         assert_eq!(parse_length("1.5em"), Some(Length::Em(1.5)));
         assert_eq!(parse_length("50%"), Some(Length::Percent(50.0)));
         assert_eq!(parse_length("auto"), Some(Length::Auto));
+        assert_eq!(parse_length("min-content"), Some(Length::MinContent));
+        assert_eq!(parse_length("-webkit-fit-content"), Some(Length::FitContent));
+        assert!(Length::MaxContent.is_intrinsic());
+        assert!(Length::Auto.is_content_sized() && !Length::Auto.is_intrinsic());
     }
 
     #[test]
@@ -2679,6 +3108,55 @@ mod tests {
         assert_eq!(parse_length("20vmax"), Some(Length::Vmax(20.0)));
     }
 
+    #[test]
+    fn test_resolve_color_stops_fixup() {
+        let red = Color::from_rgb(255, 0, 0);
+        let blue = Color::from_rgb(0, 0, 255);
+        let percent = |p: &StopPosition| p.to_normalized(100.0);
+
+        // Auto positions spread between neighbours; positions never decrease
+        let stops = [
+            ColorStop::new(red, None),
+            ColorStop::new(red, Some(0.6)),
+            ColorStop::new(blue, Some(0.2)),
+            ColorStop::new(blue, None),
+            ColorStop::new(red, None),
+        ];
+        let positions: Vec<f32> = resolve_color_stops(&stops, percent).iter().map(|(p, _)| *p).collect();
+        assert_eq!(positions, [0.0, 0.6, 0.6, 0.8, 1.0]);
+
+        // Pixel positions go through the caller's mapping
+        let stops = [ColorStop::with_pixels(red, 25.0), ColorStop::new(blue, None)];
+        assert_eq!(resolve_color_stops(&stops, percent)[0].0, 0.25);
+    }
+
+    #[test]
+    fn test_resolve_color_stops_hints() {
+        let black = Color::BLACK;
+        let white = Color::WHITE;
+        let percent = |p: &StopPosition| p.to_normalized(100.0);
+        let hinted = |hint: f32| {
+            let mut first = ColorStop::with_percent(black, 0.0);
+            first.hint = Some(StopPosition::Percent(hint));
+            resolve_color_stops(&[first, ColorStop::with_percent(white, 1.0)], percent)
+        };
+
+        // Midpoint hint is linear, so no extra stops
+        assert_eq!(hinted(0.5).len(), 2);
+
+        // Off-centre hint expands to nine stops, ascending, with the
+        // colour halfway between the stops landing on the hint
+        let stops = hinted(0.2);
+        assert_eq!(stops.len(), 11);
+        assert!(stops.windows(2).all(|w| w[0].0 <= w[1].0));
+        let at_hint = stops.iter().find(|(p, _)| (*p - 0.2).abs() < 1e-4).unwrap();
+        assert!((at_hint.1.r - 0.5).abs() < 1e-3);
+
+        // Hint on a stop makes a hard edge
+        let stops = hinted(0.0);
+        assert_eq!(stops[1], (0.0, ColorF32::WHITE));
+    }
+
     #[test]
     fn test_parse_stylesheet() {
         let css = r#"
@@ -2694,6 +3172,76 @@ mod tests {
         assert!(stylesheet.rules.len() >= 2);
     }
 
+    #[test]
+    fn test_parse_stylesheet_expands_shorthands() {
+        let css = r#"
+            .a { border: 1px solid red !important; margin: 4px; }
+            .b { border: 1px 2px; }
+            .c { border: var(--edge); }
+        "#;
+
+        let stylesheet = Stylesheet::parse(css).unwrap();
+        let props = |i: usize| -> Vec<&str> {
+            stylesheet.rules[i].declarations.iter().map(|d| d.property.as_str()).collect()
+        };
+
+        let a = &stylesheet.rules[0].declarations;
+        assert_eq!(a.len(), 13);
+        assert!(a[..12].iter().all(|d| d.important));
+        assert!(props(0).contains(&"border-top-style"));
+        assert!(props(0).contains(&"margin"));
+
+        // Invalid shorthand values are dropped, var() is left for later
+        assert!(stylesheet.rules[1].declarations.is_empty());
+        assert_eq!(props(2), ["border"]);
+    }
+
+    #[test]
+    fn test_parse_keyframes() {
+        let css = r#"
+            @keyframes "pulse" {
+                to { opacity: 1; }
+                from, 50% { opacity: 0.5; color: red !important; }
+                150% { opacity: 0; }
+            }
+            .a { animation-name: pulse; }
+        "#;
+
+        let stylesheet = Stylesheet::parse(css).unwrap();
+        assert_eq!(stylesheet.rules.len(), 1);
+        let keyframes = &stylesheet.keyframes[0];
+        assert_eq!(keyframes.name, "pulse");
+        let offsets: Vec<f32> = keyframes.frames.iter().map(|frame| frame.offset).collect();
+        assert_eq!(offsets, [0.0, 0.5, 1.0]);
+        // !important is ignored in keyframes
+        assert_eq!(keyframes.frames[0].declarations.len(), 1);
+    }
+
+    #[test]
+    fn test_stylesheet_for_media() {
+        let css = r#"
+            .a { width: 1px; }
+            @media (max-width: 600px) {
+                .b { width: 2px; }
+                @media (prefers-color-scheme: dark) { .c { width: 3px; } }
+            }
+            .d { width: 4px; }
+            @media print { .e { width: 5px; } }
+        "#;
+        let stylesheet = Stylesheet::parse(css).unwrap();
+        let selectors = |features: MediaFeatures| -> Vec<String> {
+            stylesheet.for_media(&features).rules.into_iter().map(|rule| rule.selector).collect()
+        };
+
+        let narrow = MediaFeatures { width: 400.0, ..Default::default() };
+        assert_eq!(selectors(narrow), [".a", ".b", ".d"]);
+        assert_eq!(selectors(MediaFeatures { prefers_dark: true, ..narrow }), [".a", ".b", ".c", ".d"]);
+        assert_eq!(selectors(MediaFeatures::default()), [".a", ".d"]);
+
+        let print_only = Stylesheet::parse(".f { width: 6px; }").unwrap().with_media(MediaQueryList::parse("print"));
+        assert!(print_only.for_media(&narrow).rules.is_empty());
+    }
+
     #[test]
     fn test_computed_style_inherit() {
         let parent = ComputedStyle {
@@ -2857,4 +3405,82 @@ mod tests {
         assert_eq!(expanded[0].line_names, vec!["col-start".to_string()]);
         assert_eq!(expanded[1].line_names, vec!["col-start".to_string()]);
     }
+
+    #[test]
+    fn test_template_areas_strings_on_one_line() {
+        let areas = GridTemplateAreas::parse("\"head head\" \"nav main\" '... main'").unwrap();
+
+        assert_eq!(areas.rows.len(), 3);
+        assert_eq!(areas.rows[2][0], None);
+        let main = areas.get_area("main").unwrap();
+        assert_eq!((main.row_start, main.row_end), (2, 4));
+        assert_eq!((main.column_start, main.column_end), (2, 3));
+    }
+
+    #[test]
+    fn test_template_areas_invalid() {
+        assert!(GridTemplateAreas::parse("none").is_none());
+        // Rows of different lengths
+        assert!(GridTemplateAreas::parse("\"a a\" \"b\"").is_none());
+        // Non-rectangular area
+        assert!(GridTemplateAreas::parse("\"a a\" \"a b\"").is_none());
+        // Disconnected area
+        assert!(GridTemplateAreas::parse("\"a b a\"").is_none());
+    }
+
+    #[test]
+    fn test_text_align_resolves_against_direction() {
+        assert_eq!(TextAlign::default(), TextAlign::Start);
+        assert_eq!(TextAlign::Start.resolve(Direction::Ltr), TextAlign::Left);
+        assert_eq!(TextAlign::Start.resolve(Direction::Rtl), TextAlign::Right);
+        assert_eq!(TextAlign::End.resolve(Direction::Ltr), TextAlign::Right);
+        assert_eq!(TextAlign::End.resolve(Direction::Rtl), TextAlign::Left);
+        assert_eq!(TextAlign::Center.resolve(Direction::Rtl), TextAlign::Center);
+        assert_eq!(TextAlign::Left.resolve(Direction::Rtl), TextAlign::Left);
+    }
+
+    #[test]
+    fn test_list_marker_text() {
+        assert_eq!(ListStyleType::Disc.marker_text(1).as_deref(), Some("\u{2022} "));
+        assert_eq!(ListStyleType::Decimal.marker_text(12).as_deref(), Some("12. "));
+        assert_eq!(ListStyleType::DecimalLeadingZero.marker_text(7).as_deref(), Some("07. "));
+        assert_eq!(ListStyleType::LowerAlpha.marker_text(28).as_deref(), Some("ab. "));
+        assert_eq!(ListStyleType::UpperAlpha.marker_text(26).as_deref(), Some("Z. "));
+        assert_eq!(ListStyleType::UpperRoman.marker_text(1994).as_deref(), Some("MCMXCIV. "));
+        assert_eq!(ListStyleType::LowerRoman.marker_text(4).as_deref(), Some("iv. "));
+        // Out of range falls back to decimal
+        assert_eq!(ListStyleType::LowerAlpha.marker_text(0).as_deref(), Some("0. "));
+        assert_eq!(ListStyleType::None.marker_text(1), None);
+        assert_eq!(ListStyleType::DisclosureOpen.marker_text(0).as_deref(), Some("\u{25BE} "));
+        assert_eq!(ListStyleType::parse("lower-latin"), Some(ListStyleType::LowerAlpha));
+    }
+
+    #[test]
+    fn test_will_change_parse() {
+        let will_change = WillChange::parse("transform, Opacity");
+        assert!(will_change.transform && will_change.opacity && will_change.wants_layer());
+        assert!(!WillChange::parse("auto").wants_layer());
+        assert!(!WillChange::parse("scroll-position, left").wants_layer());
+        assert!(WillChange::parse("scroll-position").scroll_position);
+    }
+
+    #[test]
+    fn test_timing_function_progress() {
+        assert_eq!(TimingFunction::Linear.progress(0.25), 0.25);
+        assert_eq!(TimingFunction::Linear.progress(1.5), 1.0);
+        for timing in [TimingFunction::Ease, TimingFunction::EaseIn, TimingFunction::EaseInOut] {
+            assert!(timing.progress(0.0).abs() < 1e-3);
+            assert!((timing.progress(1.0) - 1.0).abs() < 1e-3);
+        }
+        // ease-in starts slow and ease-out fast; ease-in-out is symmetric
+        assert!(TimingFunction::EaseIn.progress(0.25) < 0.25);
+        assert!(TimingFunction::EaseOut.progress(0.25) > 0.25);
+        assert!((TimingFunction::EaseInOut.progress(0.5) - 0.5).abs() < 1e-3);
+        assert!((TimingFunction::CubicBezier(0.0, 0.0, 1.0, 1.0).progress(0.3) - 0.3).abs() < 1e-3);
+
+        assert_eq!(TimingFunction::StepEnd.progress(0.99), 0.0);
+        assert_eq!(TimingFunction::StepStart.progress(0.01), 1.0);
+        assert_eq!(TimingFunction::Steps(4, false).progress(0.6), 0.5);
+        assert_eq!(TimingFunction::Steps(4, true).progress(0.6), 0.75);
+    }
 }
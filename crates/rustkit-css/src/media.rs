@@ -0,0 +1,310 @@
+//! Media queries.
+//!
+//! `@media` blocks, `<style media>` and `<link media>` only apply when their
+//! query matches the viewport. Queries are parsed once, when the stylesheet
+//! is, and matched against [`MediaFeatures`] each time the page is styled,
+//! so a resize re-evaluates them.
+
+use crate::{parse_length, Length};
+
+/// What media queries are matched against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaFeatures {
+    /// Viewport width, in CSS pixels.
+    pub width: f32,
+    /// Viewport height, in CSS pixels.
+    pub height: f32,
+    /// Device pixels per CSS pixel.
+    pub resolution: f32,
+    /// Whether the user prefers a dark color scheme.
+    pub prefers_dark: bool,
+}
+
+impl Default for MediaFeatures {
+    fn default() -> Self {
+        Self { width: 800.0, height: 600.0, resolution: 1.0, prefers_dark: false }
+    }
+}
+
+/// A comma-separated list of media queries, which matches if any of them
+/// does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryList {
+    queries: Vec<MediaQuery>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MediaQuery {
+    negated: bool,
+    /// Whether the media type matches a screen: `all` and `screen` do,
+    /// `print` and unknown types don't.
+    screen: bool,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Width(Comparison, f32),
+    Height(Comparison, f32),
+    AspectRatio(Comparison, f32),
+    Resolution(Comparison, f32),
+    Orientation { portrait: bool },
+    PrefersDark(bool),
+    /// A feature with a fixed value on a desktop screen, e.g. `hover`.
+    Fixed(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Min,
+    Max,
+    Exact,
+    Less,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, actual: f32, wanted: f32) -> bool {
+        match self {
+            Comparison::Min => actual >= wanted,
+            Comparison::Max => actual <= wanted,
+            Comparison::Exact => (actual - wanted).abs() < 0.01,
+            Comparison::Less => actual < wanted,
+            Comparison::Greater => actual > wanted,
+        }
+    }
+
+    /// The comparison as seen from the other side, for `600px < width`.
+    fn flipped(self) -> Self {
+        match self {
+            Comparison::Min => Comparison::Max,
+            Comparison::Max => Comparison::Min,
+            Comparison::Less => Comparison::Greater,
+            Comparison::Greater => Comparison::Less,
+            Comparison::Exact => Comparison::Exact,
+        }
+    }
+}
+
+impl MediaQueryList {
+    /// Parse a media query list, such as an `@media` prelude or a `media`
+    /// attribute. An empty list matches everything; a query that can't be
+    /// parsed matches nothing.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        if text.is_empty() {
+            return Self { queries: vec![MediaQuery { negated: false, screen: true, conditions: Vec::new() }] };
+        }
+        let queries = text.split(',').filter_map(|query| MediaQuery::parse(&query.to_ascii_lowercase())).collect();
+        Self { queries }
+    }
+
+    /// Whether any query in the list matches `features`.
+    pub fn matches(&self, features: &MediaFeatures) -> bool {
+        self.queries.iter().any(|query| query.matches(features))
+    }
+}
+
+impl MediaQuery {
+    fn parse(query: &str) -> Option<Self> {
+        let mut rest = query.trim();
+        let mut negated = false;
+        let mut screen = true;
+
+        if !rest.starts_with('(') {
+            let (first, after) = split_word(rest);
+            rest = after;
+            let media_type = match first {
+                "not" => {
+                    negated = true;
+                    let (media_type, after) = split_word(rest);
+                    rest = after;
+                    media_type
+                }
+                "only" => {
+                    let (media_type, after) = split_word(rest);
+                    rest = after;
+                    media_type
+                }
+                media_type => media_type,
+            };
+            screen = matches!(media_type, "all" | "screen");
+            if !rest.is_empty() {
+                rest = rest.strip_prefix("and")?.trim_start();
+            }
+        }
+
+        let mut conditions = Vec::new();
+        while !rest.is_empty() {
+            let inner = rest.strip_prefix('(')?;
+            let close = inner.find(')')?;
+            conditions.push(Condition::parse(&inner[..close])?);
+            rest = inner[close + 1..].trim_start();
+            if !rest.is_empty() {
+                rest = rest.strip_prefix("and")?.trim_start();
+            }
+        }
+
+        Some(Self { negated, screen, conditions })
+    }
+
+    fn matches(&self, features: &MediaFeatures) -> bool {
+        let matched = self.screen && self.conditions.iter().all(|condition| condition.matches(features));
+        matched != self.negated
+    }
+}
+
+impl Condition {
+    fn parse(feature: &str) -> Option<Self> {
+        if let Some((name, value)) = feature.split_once(':') {
+            let name = name.trim();
+            let name = name.strip_prefix("-webkit-").unwrap_or(name);
+            let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+                (Comparison::Min, name)
+            } else if let Some(name) = name.strip_prefix("max-") {
+                (Comparison::Max, name)
+            } else {
+                (Comparison::Exact, name)
+            };
+            return Self::compare(name, comparison, value.trim());
+        }
+
+        for (operator, comparison) in [
+            (">=", Comparison::Min),
+            ("<=", Comparison::Max),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Exact),
+        ] {
+            if let Some((left, right)) = feature.split_once(operator) {
+                let (left, right) = (left.trim(), right.trim());
+                return if is_feature_name(left) {
+                    Self::compare(left, comparison, right)
+                } else {
+                    Self::compare(right, comparison.flipped(), left)
+                };
+            }
+        }
+
+        // A bare feature matches when its value isn't zero or `none`
+        match feature.trim() {
+            "width" | "height" | "orientation" | "color" => Some(Condition::Fixed(true)),
+            "hover" | "any-hover" | "pointer" | "any-pointer" => Some(Condition::Fixed(true)),
+            "prefers-color-scheme" => Some(Condition::Fixed(true)),
+            _ => Some(Condition::Fixed(false)),
+        }
+    }
+
+    fn compare(name: &str, comparison: Comparison, value: &str) -> Option<Self> {
+        Some(match name {
+            "width" | "device-width" => Condition::Width(comparison, parse_media_length(value)?),
+            "height" | "device-height" => Condition::Height(comparison, parse_media_length(value)?),
+            "aspect-ratio" | "device-aspect-ratio" => Condition::AspectRatio(comparison, parse_ratio(value)?),
+            "resolution" => Condition::Resolution(comparison, parse_resolution(value)?),
+            "device-pixel-ratio" => Condition::Resolution(comparison, value.parse().ok()?),
+            "orientation" => Condition::Orientation { portrait: value == "portrait" },
+            "prefers-color-scheme" => Condition::PrefersDark(value == "dark"),
+            "hover" | "any-hover" => Condition::Fixed(value == "hover"),
+            "pointer" | "any-pointer" => Condition::Fixed(value == "fine"),
+            "prefers-reduced-motion" | "prefers-contrast" | "forced-colors" => {
+                Condition::Fixed(matches!(value, "no-preference" | "none"))
+            }
+            "color" => Condition::Fixed(comparison != Comparison::Exact || value == "8"),
+            _ => Condition::Fixed(false),
+        })
+    }
+
+    fn matches(&self, features: &MediaFeatures) -> bool {
+        match *self {
+            Condition::Width(comparison, value) => comparison.holds(features.width, value),
+            Condition::Height(comparison, value) => comparison.holds(features.height, value),
+            Condition::AspectRatio(comparison, value) => {
+                features.height > 0.0 && comparison.holds(features.width / features.height, value)
+            }
+            Condition::Resolution(comparison, value) => comparison.holds(features.resolution, value),
+            Condition::Orientation { portrait } => (features.height >= features.width) == portrait,
+            Condition::PrefersDark(dark) => features.prefers_dark == dark,
+            Condition::Fixed(matches) => matches,
+        }
+    }
+}
+
+fn is_feature_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+/// Split off the first word of `text`.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text.find(|c: char| c.is_whitespace() || c == '(').unwrap_or(text.len());
+    (&text[..end], text[end..].trim_start())
+}
+
+/// A length in a media query, in CSS pixels. Relative units are relative
+/// to the initial font size, 16px.
+fn parse_media_length(value: &str) -> Option<f32> {
+    match parse_length(value)? {
+        Length::Px(px) => Some(px),
+        Length::Em(em) | Length::Rem(em) => Some(em * 16.0),
+        Length::Zero => Some(0.0),
+        _ => None,
+    }
+}
+
+fn parse_ratio(value: &str) -> Option<f32> {
+    match value.split_once('/') {
+        Some((width, height)) => {
+            let (width, height): (f32, f32) = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+            (height > 0.0).then(|| width / height)
+        }
+        None => value.parse().ok(),
+    }
+}
+
+/// A resolution, in device pixels per CSS pixel.
+fn parse_resolution(value: &str) -> Option<f32> {
+    if let Some(dpi) = value.strip_suffix("dpi") {
+        return Some(dpi.parse::<f32>().ok()? / 96.0);
+    }
+    if let Some(dpcm) = value.strip_suffix("dpcm") {
+        return Some(dpcm.parse::<f32>().ok()? * 2.54 / 96.0);
+    }
+    value.strip_suffix("dppx").or_else(|| value.strip_suffix('x'))?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_queries() {
+        let narrow = MediaFeatures { width: 400.0, height: 800.0, ..Default::default() };
+        let wide = MediaFeatures { width: 1200.0, height: 800.0, resolution: 2.0, prefers_dark: true };
+        let matches = |query: &str, features: &MediaFeatures| MediaQueryList::parse(query).matches(features);
+
+        assert!(matches("", &narrow));
+        assert!(matches("screen", &narrow));
+        assert!(!matches("print", &narrow));
+        assert!(matches("not print", &narrow));
+        assert!(matches("only screen and (max-width: 600px)", &narrow));
+        assert!(!matches("only screen and (max-width: 600px)", &wide));
+        assert!(matches("(min-width: 40em) and (min-resolution: 2dppx)", &wide));
+        assert!(!matches("(min-width: 40em) and (min-resolution: 2dppx)", &narrow));
+        assert!(matches("print, (orientation: portrait)", &narrow));
+        assert!(matches("(-webkit-min-device-pixel-ratio: 1.5)", &wide));
+
+        // Range syntax, either way round
+        assert!(matches("(width >= 600px)", &wide));
+        assert!(matches("(600px < width)", &wide));
+        assert!(!matches("(width < 600px)", &wide));
+        assert!(matches("(min-aspect-ratio: 16/9)", &MediaFeatures { width: 1920.0, height: 1080.0, ..wide }));
+
+        assert!(matches("(prefers-color-scheme: dark)", &wide));
+        assert!(!matches("(prefers-color-scheme: dark)", &narrow));
+        assert!(matches("(hover: hover) and (pointer: fine)", &narrow));
+
+        // Unknown features and broken queries match nothing
+        assert!(!matches("(scan: interlace)", &narrow));
+        assert!(!matches("screen and (min-width: 1px", &narrow));
+    }
+}
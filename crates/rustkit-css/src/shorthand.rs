@@ -0,0 +1,791 @@
+//! Shorthand property expansion.
+//!
+//! Shorthands are expanded into their longhands before the cascade, so a later
+//! longhand (e.g. `border-top-color`) only overrides its own part of an earlier
+//! shorthand and the style computer only ever sees longhands. Every longhand of
+//! a shorthand is always emitted; components the author omitted are reset to
+//! their initial value, as the spec requires.
+
+use crate::{parse_color, parse_length};
+
+/// A shorthand property and the longhands it sets.
+pub struct Shorthand {
+    /// Shorthand property name.
+    pub name: &'static str,
+    /// Longhands set by this shorthand, in the order they are emitted.
+    pub longhands: &'static [&'static str],
+    /// Parses the shorthand value into one value per longhand.
+    parse: fn(&str) -> Option<Vec<String>>,
+}
+
+impl Shorthand {
+    /// Expand a value into `(longhand, value)` pairs.
+    ///
+    /// Returns `None` if the value is not valid for this shorthand, in which
+    /// case the whole declaration should be dropped.
+    pub fn expand(&self, value: &str) -> Option<Vec<(&'static str, String)>> {
+        let value = value.trim();
+        let values = if is_css_wide_keyword(value) {
+            vec![value.to_string(); self.longhands.len()]
+        } else {
+            (self.parse)(value)?
+        };
+        debug_assert_eq!(values.len(), self.longhands.len(), "{}", self.name);
+        Some(self.longhands.iter().copied().zip(values).collect())
+    }
+}
+
+const BORDER_LONGHANDS: &[&str] = &[
+    "border-top-width",
+    "border-right-width",
+    "border-bottom-width",
+    "border-left-width",
+    "border-top-style",
+    "border-right-style",
+    "border-bottom-style",
+    "border-left-style",
+    "border-top-color",
+    "border-right-color",
+    "border-bottom-color",
+    "border-left-color",
+];
+
+static SHORTHANDS: &[Shorthand] = &[
+    Shorthand {
+        name: "border",
+        longhands: BORDER_LONGHANDS,
+        parse: parse_border,
+    },
+    Shorthand {
+        name: "border-top",
+        longhands: &["border-top-width", "border-top-style", "border-top-color"],
+        parse: parse_border_side,
+    },
+    Shorthand {
+        name: "border-right",
+        longhands: &["border-right-width", "border-right-style", "border-right-color"],
+        parse: parse_border_side,
+    },
+    Shorthand {
+        name: "border-bottom",
+        longhands: &["border-bottom-width", "border-bottom-style", "border-bottom-color"],
+        parse: parse_border_side,
+    },
+    Shorthand {
+        name: "border-left",
+        longhands: &["border-left-width", "border-left-style", "border-left-color"],
+        parse: parse_border_side,
+    },
+    Shorthand {
+        name: "border-width",
+        longhands: &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+        ],
+        parse: |v| parse_box_sides(v, is_border_width),
+    },
+    Shorthand {
+        name: "border-style",
+        longhands: &[
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+        ],
+        parse: |v| parse_box_sides(v, is_border_style),
+    },
+    Shorthand {
+        name: "border-color",
+        longhands: &[
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        parse: |v| parse_box_sides(v, is_color),
+    },
+    Shorthand {
+        name: "background",
+        longhands: &[
+            "background-image",
+            "background-position",
+            "background-size",
+            "background-repeat",
+            "background-origin",
+            "background-clip",
+            "background-attachment",
+            "background-color",
+        ],
+        parse: parse_background,
+    },
+    Shorthand {
+        name: "font",
+        longhands: &[
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-stretch",
+            "font-size",
+            "line-height",
+            "font-family",
+        ],
+        parse: parse_font,
+    },
+    Shorthand {
+        name: "list-style",
+        longhands: &["list-style-position", "list-style-image", "list-style-type"],
+        parse: parse_list_style,
+    },
+    Shorthand {
+        name: "flex",
+        longhands: &["flex-grow", "flex-shrink", "flex-basis"],
+        parse: parse_flex,
+    },
+    Shorthand {
+        name: "columns",
+        longhands: &["column-width", "column-count"],
+        parse: parse_columns,
+    },
+    Shorthand {
+        name: "column-rule",
+        longhands: &["column-rule-width", "column-rule-style", "column-rule-color"],
+        parse: parse_border_side,
+    },
+    Shorthand {
+        name: "flex-flow",
+        longhands: &["flex-direction", "flex-wrap"],
+        parse: parse_flex_flow,
+    },
+];
+
+/// Look up a shorthand property by name.
+pub fn shorthand(name: &str) -> Option<&'static Shorthand> {
+    SHORTHANDS.iter().find(|s| s.name == name)
+}
+
+fn is_css_wide_keyword(value: &str) -> bool {
+    matches!(
+        value.to_ascii_lowercase().as_str(),
+        "inherit" | "initial" | "unset" | "revert"
+    )
+}
+
+/// Split a value on top-level whitespace, keeping function arguments and
+/// quoted strings intact. A top-level `/` becomes its own token.
+fn tokenize(value: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start: Option<usize> = None;
+
+    for (i, c) in value.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                start.get_or_insert(i);
+            }
+            '(' => {
+                depth += 1;
+                start.get_or_insert(i);
+            }
+            ')' => depth -= 1,
+            '/' if depth == 0 => {
+                if let Some(s) = start.take() {
+                    tokens.push(&value[s..i]);
+                }
+                tokens.push("/");
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = start.take() {
+                    tokens.push(&value[s..i]);
+                }
+            }
+            _ => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&value[s..]);
+    }
+    tokens
+}
+
+/// Split a value on top-level commas.
+fn split_layers(value: &str) -> Vec<&str> {
+    let mut layers = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                layers.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    layers.push(value[start..].trim());
+    layers
+}
+
+// ==================== Borders ====================
+
+fn is_border_width(token: &str) -> bool {
+    matches!(token, "thin" | "medium" | "thick")
+        || matches!(parse_length(token), Some(l) if !matches!(l, crate::Length::Auto))
+}
+
+fn is_border_style(token: &str) -> bool {
+    matches!(
+        token,
+        "none" | "hidden" | "dotted" | "dashed" | "solid" | "double" | "groove" | "ridge" | "inset"
+            | "outset"
+    )
+}
+
+fn is_color(token: &str) -> bool {
+    token.eq_ignore_ascii_case("currentcolor") || parse_color(token).is_some()
+}
+
+/// Parse `<width> || <style> || <color>` for one side.
+fn parse_border_side(value: &str) -> Option<Vec<String>> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+    for token in tokenize(value) {
+        if style.is_none() && is_border_style(token) {
+            style = Some(token);
+        } else if width.is_none() && is_border_width(token) {
+            width = Some(token);
+        } else if color.is_none() && is_color(token) {
+            color = Some(token);
+        } else {
+            return None;
+        }
+    }
+    Some(vec![
+        width.unwrap_or("medium").to_string(),
+        style.unwrap_or("none").to_string(),
+        color.unwrap_or("currentcolor").to_string(),
+    ])
+}
+
+fn parse_border(value: &str) -> Option<Vec<String>> {
+    let side = parse_border_side(value)?;
+    Some(
+        side.iter()
+            .flat_map(|v| std::iter::repeat_n(v.clone(), 4))
+            .collect(),
+    )
+}
+
+/// Parse the 1-4 value `top right bottom left` box syntax.
+fn parse_box_sides(value: &str, valid: fn(&str) -> bool) -> Option<Vec<String>> {
+    let tokens = tokenize(value);
+    if !tokens.iter().all(|t| valid(t)) {
+        return None;
+    }
+    let [top, right, bottom, left] = match tokens.as_slice() {
+        [a] => [a, a, a, a],
+        [a, b] => [a, b, a, b],
+        [a, b, c] => [a, b, c, b],
+        [a, b, c, d] => [a, b, c, d],
+        _ => return None,
+    };
+    Some(vec![top.to_string(), right.to_string(), bottom.to_string(), left.to_string()])
+}
+
+// ==================== Background ====================
+
+fn is_image(token: &str) -> bool {
+    token == "none"
+        || token.starts_with("url(")
+        || token.starts_with("image-set(")
+        || token.starts_with("-webkit-image-set(")
+        || token.contains("gradient(")
+}
+
+fn is_position_component(token: &str) -> bool {
+    matches!(token, "left" | "right" | "top" | "bottom" | "center")
+        || matches!(parse_length(token), Some(l) if !matches!(l, crate::Length::Auto))
+}
+
+fn is_size_component(token: &str) -> bool {
+    matches!(token, "cover" | "contain" | "auto") || parse_length(token).is_some()
+}
+
+fn is_repeat(token: &str) -> bool {
+    matches!(token, "repeat" | "repeat-x" | "repeat-y" | "no-repeat" | "space" | "round")
+}
+
+fn is_box(token: &str) -> bool {
+    matches!(token, "border-box" | "padding-box" | "content-box")
+}
+
+/// Parsed components of one background layer.
+#[derive(Default)]
+struct BackgroundLayerParts {
+    image: Option<String>,
+    position: Vec<String>,
+    size: Vec<String>,
+    repeat: Vec<String>,
+    origin: Option<String>,
+    clip: Option<String>,
+    attachment: Option<String>,
+    color: Option<String>,
+}
+
+fn parse_background_layer_parts(layer: &str, is_final: bool) -> Option<BackgroundLayerParts> {
+    let tokens = tokenize(layer);
+    let mut parts = BackgroundLayerParts::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        if parts.image.is_none() && is_image(token) {
+            parts.image = Some(token.to_string());
+        } else if parts.position.is_empty() && is_position_component(token) {
+            while i < tokens.len() && parts.position.len() < 4 && is_position_component(tokens[i]) {
+                parts.position.push(tokens[i].to_string());
+                i += 1;
+            }
+            if tokens.get(i) == Some(&"/") {
+                i += 1;
+                while i < tokens.len() && parts.size.len() < 2 && is_size_component(tokens[i]) {
+                    parts.size.push(tokens[i].to_string());
+                    i += 1;
+                }
+                if parts.size.is_empty() {
+                    return None;
+                }
+            }
+            continue;
+        } else if parts.repeat.is_empty() && is_repeat(token) {
+            while i < tokens.len() && parts.repeat.len() < 2 && is_repeat(tokens[i]) {
+                parts.repeat.push(tokens[i].to_string());
+                i += 1;
+            }
+            continue;
+        } else if parts.attachment.is_none() && matches!(token, "scroll" | "fixed" | "local") {
+            parts.attachment = Some(token.to_string());
+        } else if parts.origin.is_none() && is_box(token) {
+            parts.origin = Some(token.to_string());
+        } else if parts.clip.is_none() && (is_box(token) || token == "text") {
+            parts.clip = Some(token.to_string());
+        } else if is_final && parts.color.is_none() && is_color(token) {
+            parts.color = Some(token.to_string());
+        } else {
+            return None;
+        }
+        i += 1;
+    }
+    // A single box keyword sets both origin and clip
+    if parts.clip.is_none() {
+        parts.clip = parts.origin.clone();
+    }
+    Some(parts)
+}
+
+fn parse_background(value: &str) -> Option<Vec<String>> {
+    let layers = split_layers(value);
+    let last = layers.len() - 1;
+    let mut parsed = Vec::with_capacity(layers.len());
+    for (i, layer) in layers.iter().enumerate() {
+        if layer.is_empty() {
+            return None;
+        }
+        parsed.push(parse_background_layer_parts(layer, i == last)?);
+    }
+
+    let join = |f: &dyn Fn(&BackgroundLayerParts) -> String| {
+        parsed.iter().map(f).collect::<Vec<_>>().join(", ")
+    };
+    let or = |v: &Option<String>, default: &str| v.clone().unwrap_or_else(|| default.to_string());
+    let or_list = |v: &Vec<String>, default: &str| {
+        if v.is_empty() {
+            default.to_string()
+        } else {
+            v.join(" ")
+        }
+    };
+
+    Some(vec![
+        join(&|p| or(&p.image, "none")),
+        join(&|p| or_list(&p.position, "0% 0%")),
+        join(&|p| or_list(&p.size, "auto")),
+        join(&|p| or_list(&p.repeat, "repeat")),
+        join(&|p| or(&p.origin, "padding-box")),
+        join(&|p| or(&p.clip, "border-box")),
+        join(&|p| or(&p.attachment, "scroll")),
+        or(&parsed[last].color, "transparent"),
+    ])
+}
+
+// ==================== Font ====================
+
+fn is_font_size(token: &str) -> bool {
+    matches!(
+        token,
+        "xx-small" | "x-small" | "small" | "medium" | "large" | "x-large" | "xx-large"
+            | "xxx-large" | "smaller" | "larger"
+    ) || matches!(parse_length(token), Some(l) if !matches!(l, crate::Length::Auto))
+}
+
+fn is_font_weight(token: &str) -> bool {
+    matches!(token, "bold" | "bolder" | "lighter")
+        || matches!(token.parse::<f32>(), Ok(w) if (1.0..=1000.0).contains(&w))
+}
+
+fn is_font_stretch(token: &str) -> bool {
+    matches!(
+        token,
+        "ultra-condensed" | "extra-condensed" | "condensed" | "semi-condensed" | "semi-expanded"
+            | "expanded" | "extra-expanded" | "ultra-expanded"
+    )
+}
+
+/// Parse `[style || variant || weight || stretch]? size[/line-height]? family`.
+fn parse_font(value: &str) -> Option<Vec<String>> {
+    let tokens = tokenize(value);
+    let mut style = None;
+    let mut variant = None;
+    let mut weight = None;
+    let mut stretch = None;
+    let mut normals = 0;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        if token == "normal" {
+            normals += 1;
+        } else if style.is_none() && matches!(token, "italic" | "oblique") {
+            style = Some(token);
+        } else if variant.is_none() && token == "small-caps" {
+            variant = Some(token);
+        } else if weight.is_none() && is_font_weight(token) {
+            weight = Some(token);
+        } else if stretch.is_none() && is_font_stretch(token) {
+            stretch = Some(token);
+        } else {
+            break;
+        }
+        i += 1;
+    }
+    if normals > 4 {
+        return None;
+    }
+
+    let size = *tokens.get(i).filter(|t| is_font_size(t))?;
+    i += 1;
+    let mut line_height = "normal";
+    if tokens.get(i) == Some(&"/") {
+        line_height = tokens.get(i + 1)?;
+        i += 2;
+    }
+
+    let family = tokens.get(i..).filter(|rest| !rest.is_empty())?.join(" ");
+
+    Some(vec![
+        style.unwrap_or("normal").to_string(),
+        variant.unwrap_or("normal").to_string(),
+        weight.unwrap_or("normal").to_string(),
+        stretch.unwrap_or("normal").to_string(),
+        size.to_string(),
+        line_height.to_string(),
+        family,
+    ])
+}
+
+// ==================== Lists ====================
+
+fn parse_list_style(value: &str) -> Option<Vec<String>> {
+    let mut position = None;
+    let mut image = None;
+    let mut kind = None;
+    let mut nones = 0;
+    for token in tokenize(value) {
+        if token == "none" {
+            nones += 1;
+        } else if position.is_none() && matches!(token, "inside" | "outside") {
+            position = Some(token);
+        } else if image.is_none() && is_image(token) {
+            image = Some(token);
+        } else if kind.is_none() {
+            kind = Some(token);
+        } else {
+            return None;
+        }
+    }
+
+    // `none` fills whichever of image/type the author left unset
+    match nones {
+        0 => {}
+        1 | 2 if image.is_none() && kind.is_none() => {
+            image = Some("none");
+            kind = Some("none");
+        }
+        1 if image.is_none() => image = Some("none"),
+        1 if kind.is_none() => kind = Some("none"),
+        _ => return None,
+    }
+
+    Some(vec![
+        position.unwrap_or("outside").to_string(),
+        image.unwrap_or("none").to_string(),
+        kind.unwrap_or("disc").to_string(),
+    ])
+}
+
+// ==================== Flexbox ====================
+
+/// Parse `none | auto | <grow> <shrink>? || <basis>`.
+fn parse_flex(value: &str) -> Option<Vec<String>> {
+    match value {
+        "none" => return Some(vec!["0".into(), "0".into(), "auto".into()]),
+        "auto" => return Some(vec!["1".into(), "1".into(), "auto".into()]),
+        _ => {}
+    }
+
+    let tokens = tokenize(value);
+    let mut grow = None;
+    let mut shrink = None;
+    let mut basis = None;
+    let mut grow_index = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let is_number = token.parse::<f32>().map(|n| n >= 0.0).unwrap_or(false);
+        if is_number && grow.is_none() {
+            grow = Some(*token);
+            grow_index = Some(i);
+        } else if is_number && shrink.is_none() && grow_index.map(|g| g + 1) == Some(i) {
+            // Shrink must directly follow grow
+            shrink = Some(*token);
+        } else if basis.is_none()
+            && (matches!(*token, "auto" | "content") || parse_length(token).is_some())
+        {
+            basis = Some(*token);
+        } else {
+            return None;
+        }
+    }
+    if tokens.is_empty() || tokens.len() > 3 {
+        return None;
+    }
+
+    // Omitted grow/shrink default to 1; an omitted basis becomes 0%
+    Some(vec![
+        grow.unwrap_or("1").to_string(),
+        shrink.unwrap_or("1").to_string(),
+        basis.unwrap_or("0%").to_string(),
+    ])
+}
+
+fn parse_flex_flow(value: &str) -> Option<Vec<String>> {
+    let mut direction = None;
+    let mut wrap = None;
+    for token in tokenize(value) {
+        if direction.is_none() && matches!(token, "row" | "row-reverse" | "column" | "column-reverse") {
+            direction = Some(token);
+        } else if wrap.is_none() && matches!(token, "nowrap" | "wrap" | "wrap-reverse") {
+            wrap = Some(token);
+        } else {
+            return None;
+        }
+    }
+    Some(vec![
+        direction.unwrap_or("row").to_string(),
+        wrap.unwrap_or("nowrap").to_string(),
+    ])
+}
+
+// ==================== Multi-column ====================
+
+/// Parse `<'column-width'> || <'column-count'>`, where either may be `auto`.
+fn parse_columns(value: &str) -> Option<Vec<String>> {
+    let tokens = tokenize(value);
+    if tokens.is_empty() || tokens.len() > 2 {
+        return None;
+    }
+    let mut width = None;
+    let mut count = None;
+    let mut autos = 0;
+    for token in tokens {
+        if token == "auto" {
+            autos += 1;
+        } else if count.is_none() && token.parse::<u32>().is_ok_and(|n| n > 0) {
+            count = Some(token);
+        } else if width.is_none()
+            && matches!(parse_length(token), Some(l) if !matches!(l, crate::Length::Auto | crate::Length::Zero))
+        {
+            width = Some(token);
+        } else {
+            return None;
+        }
+    }
+    if autos == 2 && (width.is_some() || count.is_some()) {
+        return None;
+    }
+    Some(vec![
+        width.unwrap_or("auto").to_string(),
+        count.unwrap_or("auto").to_string(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(property: &str, value: &str) -> Option<Vec<(&'static str, String)>> {
+        shorthand(property)?.expand(value)
+    }
+
+    fn get<'a>(expanded: &'a [(&'static str, String)], longhand: &str) -> &'a str {
+        expanded
+            .iter()
+            .find(|(name, _)| *name == longhand)
+            .map(|(_, v)| v.as_str())
+            .unwrap_or_else(|| panic!("missing longhand {longhand}"))
+    }
+
+    #[test]
+    fn test_border_expansion_any_order() {
+        for value in ["1px solid red", "red 1px solid", "solid red 1px"] {
+            let e = expand("border", value).unwrap();
+            assert_eq!(e.len(), 12);
+            assert_eq!(get(&e, "border-left-width"), "1px");
+            assert_eq!(get(&e, "border-top-style"), "solid");
+            assert_eq!(get(&e, "border-bottom-color"), "red");
+        }
+
+        let e = expand("border-top", "dashed").unwrap();
+        assert_eq!(get(&e, "border-top-width"), "medium");
+        assert_eq!(get(&e, "border-top-color"), "currentcolor");
+
+        assert!(expand("border", "1px 2px solid").is_none());
+    }
+
+    #[test]
+    fn test_box_side_expansion() {
+        let e = expand("border-width", "1px 2px 3px").unwrap();
+        let widths: Vec<&str> = e.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(widths, ["1px", "2px", "3px", "2px"]);
+
+        let e = expand("border-color", "red blue").unwrap();
+        assert_eq!(get(&e, "border-left-color"), "blue");
+
+        assert!(expand("border-style", "solid wavy").is_none());
+    }
+
+    #[test]
+    fn test_background_expansion() {
+        let e = expand("background", "url(a.png) no-repeat center / cover #fff").unwrap();
+        assert_eq!(get(&e, "background-image"), "url(a.png)");
+        assert_eq!(get(&e, "background-repeat"), "no-repeat");
+        assert_eq!(get(&e, "background-position"), "center");
+        assert_eq!(get(&e, "background-size"), "cover");
+        assert_eq!(get(&e, "background-color"), "#fff");
+
+        let e = expand("background", "red").unwrap();
+        assert_eq!(get(&e, "background-image"), "none");
+        assert_eq!(get(&e, "background-color"), "red");
+
+        let e = expand(
+            "background",
+            "linear-gradient(red, blue) padding-box, url(b.png) 10px 20px/50% repeat-x",
+        )
+        .unwrap();
+        assert_eq!(get(&e, "background-image"), "linear-gradient(red, blue), url(b.png)");
+        assert_eq!(get(&e, "background-position"), "0% 0%, 10px 20px");
+        assert_eq!(get(&e, "background-size"), "auto, 50%");
+        assert_eq!(get(&e, "background-clip"), "padding-box, border-box");
+
+        // Color is only allowed in the final layer
+        assert!(expand("background", "red, url(a.png)").is_none());
+    }
+
+    #[test]
+    fn test_font_expansion() {
+        let e = expand("font", "italic bold 12px/1.5 \"Helvetica Neue\", sans-serif").unwrap();
+        assert_eq!(get(&e, "font-style"), "italic");
+        assert_eq!(get(&e, "font-weight"), "bold");
+        assert_eq!(get(&e, "font-variant"), "normal");
+        assert_eq!(get(&e, "font-size"), "12px");
+        assert_eq!(get(&e, "line-height"), "1.5");
+        assert_eq!(get(&e, "font-family"), "\"Helvetica Neue\", sans-serif");
+
+        let e = expand("font", "16px serif").unwrap();
+        assert_eq!(get(&e, "font-weight"), "normal");
+        assert_eq!(get(&e, "line-height"), "normal");
+
+        // Size and family are required
+        assert!(expand("font", "bold serif").is_none());
+        assert!(expand("font", "bold 12px").is_none());
+    }
+
+    #[test]
+    fn test_list_style_expansion() {
+        let e = expand("list-style", "square inside").unwrap();
+        assert_eq!(get(&e, "list-style-type"), "square");
+        assert_eq!(get(&e, "list-style-position"), "inside");
+        assert_eq!(get(&e, "list-style-image"), "none");
+
+        let e = expand("list-style", "none").unwrap();
+        assert_eq!(get(&e, "list-style-type"), "none");
+        assert_eq!(get(&e, "list-style-image"), "none");
+
+        let e = expand("list-style", "url(dot.png) none").unwrap();
+        assert_eq!(get(&e, "list-style-image"), "url(dot.png)");
+        assert_eq!(get(&e, "list-style-type"), "none");
+    }
+
+    #[test]
+    fn test_flex_expansion() {
+        let values = |v: &str| -> Vec<String> {
+            expand("flex", v).unwrap().into_iter().map(|(_, v)| v).collect()
+        };
+        assert_eq!(values("1"), ["1", "1", "0%"]);
+        assert_eq!(values("2 3"), ["2", "3", "0%"]);
+        assert_eq!(values("1 0 200px"), ["1", "0", "200px"]);
+        assert_eq!(values("200px"), ["1", "1", "200px"]);
+        assert_eq!(values("none"), ["0", "0", "auto"]);
+        assert_eq!(values("auto"), ["1", "1", "auto"]);
+
+        let e = expand("flex-flow", "column wrap").unwrap();
+        assert_eq!(get(&e, "flex-direction"), "column");
+        assert_eq!(get(&e, "flex-wrap"), "wrap");
+    }
+
+    #[test]
+    fn test_column_shorthands() {
+        let values = |v: &str| -> Vec<String> {
+            expand("columns", v).unwrap().into_iter().map(|(_, v)| v).collect()
+        };
+        assert_eq!(values("3"), ["auto", "3"]);
+        assert_eq!(values("12em"), ["12em", "auto"]);
+        assert_eq!(values("2 200px"), ["200px", "2"]);
+        assert_eq!(values("auto 4"), ["auto", "4"]);
+        assert!(expand("columns", "0").is_none());
+        assert!(expand("columns", "1 2 3").is_none());
+
+        let e = expand("column-rule", "dotted 2px blue").unwrap();
+        assert_eq!(get(&e, "column-rule-width"), "2px");
+        assert_eq!(get(&e, "column-rule-style"), "dotted");
+        assert_eq!(get(&e, "column-rule-color"), "blue");
+    }
+
+    #[test]
+    fn test_css_wide_keywords_apply_to_every_longhand() {
+        let e = expand("border", "inherit").unwrap();
+        assert_eq!(e.len(), 12);
+        assert!(e.iter().all(|(_, v)| v == "inherit"));
+    }
+}
@@ -6,6 +6,9 @@
 //! Current implementation is a **minimal** stylesheet parser suitable for RustKit's current
 //! needs: parse basic rules `selector { prop: value; }` into an AST.
 
+use std::iter::Peekable;
+use std::str::Chars;
+
 use thiserror::Error;
 
 /// Errors that can occur while parsing CSS.
@@ -22,6 +25,21 @@ pub enum ParseError {
 #[derive(Debug, Default, Clone)]
 pub struct StylesheetAst {
     pub rules: Vec<RuleAst>,
+    /// At-rules holding rules of their own, like `@keyframes` or `@media`.
+    pub at_rules: Vec<AtRuleAst>,
+}
+
+/// A parsed at-rule whose block holds rules.
+#[derive(Debug, Clone)]
+pub struct AtRuleAst {
+    /// Name, without the `@`, lowercased.
+    pub name: String,
+    /// What comes between the name and the block.
+    pub prelude: String,
+    /// How many of the stylesheet's rules come before this one.
+    pub index: usize,
+    /// The rules in the block.
+    pub block: StylesheetAst,
 }
 
 /// A parsed rule AST.
@@ -43,7 +61,9 @@ pub struct DeclarationAst {
 ///
 /// Notes:
 /// - This is not a full CSS parser.
-/// - It does not currently support nested rules (`@media`, `@supports`) or complex tokenization.
+/// - At-rules whose block holds rules (`@keyframes`, `@media`, `@supports`)
+///   are parsed into [`AtRuleAst`]s; other nesting and complex tokenization
+///   are not supported.
 /// - It attempts to be robust for common author CSS and RustKit test inputs.
 pub fn parse_stylesheet(css: &str) -> Result<StylesheetAst, ParseError> {
     let mut out = StylesheetAst::default();
@@ -60,20 +80,26 @@ pub fn parse_stylesheet(css: &str) -> Result<StylesheetAst, ParseError> {
     while let Some(c) = chars.next() {
         // Very small comment skipper: /* ... */
         if c == '/' && chars.peek() == Some(&'*') {
-            // consume '*'
-            chars.next();
-            // consume until */
-            while let Some(cc) = chars.next() {
-                if cc == '*' && chars.peek() == Some(&'/') {
-                    chars.next();
-                    break;
-                }
-            }
+            skip_comment(&mut chars);
             continue;
         }
 
         if !in_block {
             if c == '{' {
+                if let Some(at_rule) = current_selector.trim().strip_prefix('@') {
+                    if has_nested_rules(chars.clone()) {
+                        let (name, prelude) = at_rule.split_once(|c: char| c.is_whitespace()).unwrap_or((at_rule, ""));
+                        let block = read_block(&mut chars).ok_or(ParseError::UnexpectedEof)?;
+                        out.at_rules.push(AtRuleAst {
+                            name: name.to_ascii_lowercase(),
+                            prelude: prelude.trim().to_string(),
+                            index: out.rules.len(),
+                            block: parse_stylesheet(&block)?,
+                        });
+                        current_selector.clear();
+                        continue;
+                    }
+                }
                 in_block = true;
                 current_selector = current_selector.trim().to_string();
                 current_property.clear();
@@ -134,6 +160,52 @@ pub fn parse_stylesheet(css: &str) -> Result<StylesheetAst, ParseError> {
     Ok(out)
 }
 
+/// Whether the block `chars` starts inside holds a nested block before it
+/// closes.
+fn has_nested_rules(mut chars: Peekable<Chars<'_>>) -> bool {
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => skip_comment(&mut chars),
+            '{' => return true,
+            '}' => return false,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Read the rest of the block `chars` starts inside, up to its closing
+/// brace, or `None` if it isn't closed.
+fn read_block(chars: &mut Peekable<Chars<'_>>) -> Option<String> {
+    let mut block = String::new();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                skip_comment(chars);
+                continue;
+            }
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(block),
+            '}' => depth -= 1,
+            _ => {}
+        }
+        block.push(c);
+    }
+    None
+}
+
+/// Skip a comment whose `/` was just read.
+fn skip_comment(chars: &mut Peekable<Chars<'_>>) {
+    chars.next();
+    while let Some(c) = chars.next() {
+        if c == '*' && chars.peek() == Some(&'/') {
+            chars.next();
+            return;
+        }
+    }
+}
+
 fn flush_decl(
     current_property: &mut String,
     current_value: &mut String,
@@ -216,6 +288,30 @@ mod tests {
         assert_eq!(ast.rules[0].declarations[0].value, "hsl(0, 100%, 50%)");
         assert_eq!(ast.rules[1].declarations[0].value, "hsl(120, 100%, 50%)");
     }
-}
 
+    #[test]
+    fn parse_nested_at_rules() {
+        let css = r#"
+            a { color: red; }
+            @keyframes fade { from { opacity: 0; } /* } */ 50%, to { opacity: 1; } }
+            @font-face { font-family: X; }
+            @media (min-width: 100px) { @supports (display: grid) { b { width: 1px; } } }
+        "#;
+        let ast = parse_stylesheet(css).unwrap();
+        assert_eq!(ast.rules.len(), 2);
+        assert_eq!(ast.rules[1].selector, "@font-face");
+        assert_eq!(ast.at_rules.len(), 2);
+
+        let keyframes = &ast.at_rules[0];
+        assert_eq!((keyframes.name.as_str(), keyframes.prelude.as_str()), ("keyframes", "fade"));
+        assert_eq!(keyframes.index, 1);
+        assert_eq!(keyframes.block.rules.len(), 2);
+        assert_eq!(keyframes.block.rules[1].selector, "50%, to");
 
+        let media = &ast.at_rules[1];
+        assert_eq!((media.name.as_str(), media.prelude.as_str()), ("media", "(min-width: 100px)"));
+        assert_eq!(media.index, 2);
+        assert_eq!(media.block.at_rules[0].block.rules[0].selector, "b");
+        assert!(parse_stylesheet("@media x { a { color: red; }").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! # Dialogs and the top layer
+//!
+//! `<dialog>` elements open with `show()`, which renders them in place, or
+//! `showModal()`, which also adds them to the document's top layer. Top
+//! layer elements render above every stacking context, in the order they
+//! were added, each over a `::backdrop`; the topmost modal dialog makes the
+//! rest of the document inert.
+
+use std::rc::Rc;
+
+use crate::{Document, DomError, Node};
+
+impl Node {
+    /// Whether this element is in its document's top layer.
+    pub fn in_top_layer(&self) -> bool {
+        self.in_top_layer.get()
+    }
+
+    /// Whether this is a `<dialog>` opened with `showModal()` (`:modal`).
+    pub fn is_modal(&self) -> bool {
+        self.in_top_layer() && self.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("dialog"))
+    }
+
+    /// Whether this is a `<dialog>` with the `open` attribute.
+    pub fn is_open_dialog(&self) -> bool {
+        self.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("dialog")) && self.get_attribute("open").is_some()
+    }
+}
+
+impl Document {
+    /// Elements in the top layer, bottommost first.
+    pub fn top_layer(&self) -> Vec<Rc<Node>> {
+        self.top_layer.borrow().clone()
+    }
+
+    /// Add `element` to the top of the top layer, moving it there if it's
+    /// already in it.
+    pub fn add_to_top_layer(&self, element: &Rc<Node>) {
+        self.remove_from_top_layer(element);
+        element.in_top_layer.set(true);
+        self.top_layer.borrow_mut().push(element.clone());
+        element.mark_needs_layout();
+    }
+
+    /// Take `element` out of the top layer. Returns whether it was in it.
+    pub fn remove_from_top_layer(&self, element: &Node) -> bool {
+        let mut top_layer = self.top_layer.borrow_mut();
+        let before = top_layer.len();
+        top_layer.retain(|node| node.id != element.id);
+        element.in_top_layer.set(false);
+        let removed = top_layer.len() != before;
+        if removed {
+            element.mark_needs_layout();
+        }
+        removed
+    }
+
+    /// The topmost modal dialog, which makes everything outside it inert.
+    pub fn modal_dialog(&self) -> Option<Rc<Node>> {
+        self.top_layer.borrow().iter().rev().find(|node| node.is_modal()).cloned()
+    }
+
+    /// Open `dialog` in place (`show()`). Opening an open non-modal dialog
+    /// does nothing.
+    pub fn show_dialog(&self, dialog: &Rc<Node>) -> Result<(), DomError> {
+        Self::check_dialog(dialog)?;
+        if dialog.is_open_dialog() {
+            if dialog.is_modal() {
+                return Err(DomError::InvalidOperation("dialog is already open as a modal".into()));
+            }
+            return Ok(());
+        }
+        dialog.set_boolean_attribute("open", true);
+        Ok(())
+    }
+
+    /// Open `dialog` as a modal in the top layer (`showModal()`). Opening
+    /// an open modal dialog does nothing.
+    pub fn show_modal_dialog(&self, dialog: &Rc<Node>) -> Result<(), DomError> {
+        Self::check_dialog(dialog)?;
+        if dialog.is_open_dialog() {
+            if !dialog.is_modal() {
+                return Err(DomError::InvalidOperation("dialog is already open".into()));
+            }
+            return Ok(());
+        }
+        dialog.set_boolean_attribute("open", true);
+        self.add_to_top_layer(dialog);
+        Ok(())
+    }
+
+    /// Close `dialog` (`close()`), taking it out of the top layer. Returns
+    /// whether it was open.
+    pub fn close_dialog(&self, dialog: &Rc<Node>) -> bool {
+        if !dialog.is_open_dialog() {
+            return false;
+        }
+        dialog.set_boolean_attribute("open", false);
+        self.remove_from_top_layer(dialog);
+        true
+    }
+
+    fn check_dialog(node: &Node) -> Result<(), DomError> {
+        match node.tag_name() {
+            Some(tag) if tag.eq_ignore_ascii_case("dialog") => Ok(()),
+            _ => Err(DomError::InvalidOperation("not a dialog element".into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modal_dialogs_stack_in_the_top_layer() {
+        let doc = Document::parse_html(
+            r#"<dialog id="a"></dialog><dialog id="b"></dialog><dialog id="c" open></dialog><p id="p"></p>"#,
+        )
+        .unwrap();
+        let dialog = |id: &str| doc.get_element_by_id(id).unwrap();
+        let (a, b, c) = (dialog("a"), dialog("b"), dialog("c"));
+
+        assert!(doc.show_dialog(&dialog("p")).is_err());
+        assert!(doc.show_dialog(&c).is_ok());
+        assert!(doc.show_modal_dialog(&c).is_err());
+        assert!(!c.in_top_layer());
+
+        doc.show_modal_dialog(&a).unwrap();
+        doc.show_modal_dialog(&b).unwrap();
+        assert!(doc.show_dialog(&a).is_err());
+        // Already open as a modal
+        doc.show_modal_dialog(&a).unwrap();
+        let ids: Vec<_> = doc.top_layer().iter().map(|node| node.id).collect();
+        assert_eq!(ids, [a.id, b.id]);
+        assert!(Rc::ptr_eq(&doc.modal_dialog().unwrap(), &b));
+
+        assert!(doc.close_dialog(&b));
+        assert!(!doc.close_dialog(&b));
+        assert!(!b.is_modal() && b.get_attribute("open").is_none());
+        assert!(Rc::ptr_eq(&doc.modal_dialog().unwrap(), &a));
+    }
+}
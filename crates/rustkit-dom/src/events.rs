@@ -290,6 +290,12 @@ pub enum DomEvent {
 }
 
 impl DomEvent {
+    /// UI events from the browser cross shadow boundaries.
+    fn composed(mut event: Event) -> Event {
+        event.composed = true;
+        event
+    }
+
     /// Get the base event.
     pub fn event(&self) -> &Event {
         match self {
@@ -314,13 +320,13 @@ impl DomEvent {
 
     /// Create a mouse event.
     pub fn mouse(event_type: &str, bubbles: bool, data: MouseEventData) -> Self {
-        let event = Event::new_trusted(event_type, bubbles, true);
+        let event = Self::composed(Event::new_trusted(event_type, bubbles, true));
         DomEvent::Mouse(event, data)
     }
 
     /// Create a keyboard event.
     pub fn keyboard(event_type: &str, data: KeyboardEventData) -> Self {
-        let event = Event::new_trusted(event_type, true, true);
+        let event = Self::composed(Event::new_trusted(event_type, true, true));
         DomEvent::Keyboard(event, data)
     }
 
@@ -328,13 +334,13 @@ impl DomEvent {
     pub fn focus(event_type: &str, data: FocusEventData) -> Self {
         // focus/blur don't bubble, focusin/focusout do
         let bubbles = event_type == "focusin" || event_type == "focusout";
-        let event = Event::new_trusted(event_type, bubbles, false);
+        let event = Self::composed(Event::new_trusted(event_type, bubbles, false));
         DomEvent::Focus(event, data)
     }
 
     /// Create an input event.
     pub fn input(data: InputEventData) -> Self {
-        let event = Event::new_trusted("input", true, false);
+        let event = Self::composed(Event::new_trusted("input", true, false));
         DomEvent::Input(event, data)
     }
 }
@@ -465,8 +471,49 @@ impl std::fmt::Debug for EventTarget {
 pub struct EventDispatcher;
 
 impl EventDispatcher {
+    /// The ancestors an event at `target` propagates through, ordered from
+    /// the root to the parent.
+    ///
+    /// Slotted nodes pass through their slot, and shadow roots through their
+    /// host, so the path follows the rendered (flat) tree.
+    pub fn event_path(target: &Rc<Node>) -> Vec<Rc<Node>> {
+        let mut path = Vec::new();
+        let mut node = target.clone();
+        loop {
+            let parent = node
+                .assigned_slot()
+                .or_else(|| node.parent())
+                .or_else(|| node.host());
+            let Some(parent) = parent else {
+                break;
+            };
+            path.push(parent.clone());
+            node = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Retarget `target` against `node`: while `target` is in a shadow tree
+    /// that `node` is outside of, replace it with that tree's host.
+    fn retarget(target: &Rc<Node>, node: &Rc<Node>) -> NodeId {
+        let mut target = target.clone();
+        while let Some(host) = target
+            .containing_shadow_root()
+            .filter(|root| !root.is_shadow_including_inclusive_ancestor_of(node))
+            .and_then(|root| root.host())
+        {
+            target = host;
+        }
+        target.id
+    }
+
     /// Dispatch an event to a target node.
     /// Returns true if the event was not prevented.
+    ///
+    /// Listeners outside the target's shadow tree see the event retargeted
+    /// to the shadow host, and events that aren't `composed` stop at the
+    /// target's shadow root.
     pub fn dispatch(event: &mut DomEvent, target: &Rc<Node>, ancestors: &[Rc<Node>]) -> bool {
         // Get event info we need upfront
         let event_type = event.event().event_type.clone();
@@ -478,6 +525,13 @@ impl EventDispatcher {
         // Build the propagation path (ancestors + target)
         // Ancestors should be ordered from root to parent
         let mut path: Vec<&Rc<Node>> = ancestors.iter().collect();
+        if !event.event().composed {
+            if let Some(shadow_root) = target.containing_shadow_root() {
+                if let Some(start) = path.iter().position(|node| Rc::ptr_eq(node, &shadow_root)) {
+                    path.drain(..start);
+                }
+            }
+        }
         path.push(target);
 
         // Capture phase (root to target, excluding target)
@@ -486,6 +540,7 @@ impl EventDispatcher {
             if event.event().propagation_stopped() {
                 break;
             }
+            event.event().set_target(Self::retarget(target, node));
             event.event().set_current_target(Some(node.id));
             let to_remove = node
                 .event_target
@@ -496,6 +551,7 @@ impl EventDispatcher {
         // At target phase
         if !event.event().propagation_stopped() {
             event.event().set_phase(EventPhase::AtTarget);
+            event.event().set_target(target.id);
             event.event().set_current_target(Some(target.id));
             let to_remove = target
                 .event_target
@@ -510,6 +566,7 @@ impl EventDispatcher {
                 if event.event().propagation_stopped() {
                     break;
                 }
+                event.event().set_target(Self::retarget(target, node));
                 event.event().set_current_target(Some(node.id));
                 let to_remove = node
                     .event_target
@@ -518,7 +575,8 @@ impl EventDispatcher {
             }
         }
 
-        // Reset state
+        // Reset state; afterwards the target is as seen from outside the path
+        event.event().set_target(Self::retarget(target, path[0]));
         event.event().set_phase(EventPhase::None);
         event.event().set_current_target(None);
 
@@ -607,4 +665,42 @@ mod tests {
         let focusin = DomEvent::focus("focusin", FocusEventData::default());
         assert!(focusin.event().bubbles); // focusin does bubble
     }
+
+    #[test]
+    fn test_dispatch_retargets_across_shadow_boundary() {
+        let mut doc = crate::Document::new();
+        let body = doc.create_element("body", vec![]);
+        let host = doc.create_element("div", vec![]);
+        body.append_child(host.clone());
+        let shadow_root = doc.attach_shadow(&host, crate::ShadowRootMode::Open).unwrap();
+        let button = doc.create_element("button", vec![]);
+        shadow_root.append_child(button.clone());
+
+        let path = EventDispatcher::event_path(&button);
+        let ids: Vec<NodeId> = path.iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![body.id, host.id, shadow_root.id]);
+
+        // Record the target each listener sees
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        for node in [&body, &shadow_root] {
+            let seen = seen.clone();
+            node.event_target.add_event_listener(
+                "click",
+                Box::new(move |e| seen.borrow_mut().push(e.event().target())),
+                AddEventListenerOptions::default(),
+            );
+        }
+
+        let mut composed = DomEvent::mouse("click", true, MouseEventData::default());
+        EventDispatcher::dispatch(&mut composed, &button, &path);
+        // Inside the shadow tree the real target shows; outside it's the host
+        assert_eq!(*seen.borrow(), vec![Some(button.id), Some(host.id)]);
+        assert_eq!(composed.event().target(), Some(host.id));
+
+        // Non-composed events never leave the shadow tree
+        seen.borrow_mut().clear();
+        let mut scoped = DomEvent::Generic(Event::new("click", true, true));
+        EventDispatcher::dispatch(&mut scoped, &button, &path);
+        assert_eq!(*seen.borrow(), vec![Some(button.id)]);
+    }
 }
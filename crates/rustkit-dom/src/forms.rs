@@ -4,6 +4,9 @@
 //! Provides text editing, selection, and form submission support.
 
 use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::Node;
 
 /// Text selection range.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -561,6 +564,255 @@ impl CheckableState {
     }
 }
 
+/// `<select>` and `<option>` state.
+///
+/// An option's selectedness starts out as its `selected` attribute, which
+/// stays the option's default (`defaultSelected`) once the selection is
+/// changed.
+impl Node {
+    fn is_element_named(&self, name: &str) -> bool {
+        self.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+    }
+
+    /// The `<option>`s of a `<select>` in tree order, including those in
+    /// an `<optgroup>`.
+    pub fn select_options(&self) -> Vec<Rc<Node>> {
+        let mut options = Vec::new();
+        for child in self.children() {
+            if child.is_element_named("option") {
+                options.push(child);
+            } else if child.is_element_named("optgroup") {
+                options.extend(child.children().into_iter().filter(|c| c.is_element_named("option")));
+            }
+        }
+        options
+    }
+
+    /// Whether an `<option>` is selected.
+    pub fn is_option_selected(&self) -> bool {
+        self.selectedness
+            .get()
+            .unwrap_or_else(|| self.get_attribute("selected").is_some())
+    }
+
+    /// Select or deselect an `<option>`, leaving the other options of its
+    /// `<select>` alone.
+    pub fn set_option_selected(&self, selected: bool) {
+        self.selectedness.set(Some(selected));
+        self.mark_needs_layout();
+    }
+
+    /// Whether an `<option>` is disabled, itself or through its `<optgroup>`.
+    pub fn is_option_disabled(&self) -> bool {
+        self.get_attribute("disabled").is_some()
+            || self
+                .parent()
+                .is_some_and(|parent| parent.is_element_named("optgroup") && parent.get_attribute("disabled").is_some())
+    }
+
+    /// The text an `<option>` shows: its `label` attribute, or its text with
+    /// whitespace collapsed.
+    pub fn option_label(&self) -> String {
+        match self.get_attribute("label") {
+            Some(label) if !label.is_empty() => label.to_string(),
+            _ => self.text_content().split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    /// The value an `<option>` submits: its `value` attribute, or its text
+    /// with whitespace collapsed.
+    pub fn option_value(&self) -> String {
+        match self.get_attribute("value") {
+            Some(value) => value.to_string(),
+            None => self.text_content().split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    /// Index of a `<select>`'s selected option (`selectedIndex`).
+    ///
+    /// A single-choice select shows its last selected option, or its first
+    /// enabled one if none is selected.
+    pub fn selected_index(&self) -> Option<usize> {
+        let options = self.select_options();
+        if self.get_attribute("multiple").is_some() {
+            return options.iter().position(|option| option.is_option_selected());
+        }
+        options
+            .iter()
+            .rposition(|option| option.is_option_selected())
+            .or_else(|| options.iter().position(|option| !option.is_option_disabled()))
+    }
+
+    /// Select the option of a `<select>` at `index`, deselecting the rest,
+    /// or deselect all of them.
+    pub fn set_selected_index(&self, index: Option<usize>) {
+        for (i, option) in self.select_options().iter().enumerate() {
+            option.set_option_selected(Some(i) == index);
+        }
+    }
+}
+
+/// The bounds and step of an `<input type=range>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeBounds {
+    pub min: f64,
+    pub max: f64,
+    /// Allowed step between values, or `None` for `step="any"`.
+    pub step: Option<f64>,
+}
+
+impl Default for RangeBounds {
+    fn default() -> Self {
+        Self {
+            min: 0.0,
+            max: 100.0,
+            step: Some(1.0),
+        }
+    }
+}
+
+impl RangeBounds {
+    /// Bounds from the `min`, `max` and `step` attributes, falling back to
+    /// the defaults for missing or invalid ones. A `max` below `min` is
+    /// raised to it.
+    pub fn from_attributes(min: Option<&str>, max: Option<&str>, step: Option<&str>) -> Self {
+        let defaults = Self::default();
+        let min = min.and_then(parse_number).unwrap_or(defaults.min);
+        let max = max.and_then(parse_number).unwrap_or(defaults.max).max(min);
+        let step = match step.map(str::trim) {
+            Some(step) if step.eq_ignore_ascii_case("any") => None,
+            Some(step) => parse_number(step).filter(|&s| s > 0.0).or(defaults.step),
+            None => defaults.step,
+        };
+        Self { min, max, step }
+    }
+
+    /// The value a range shows without one: halfway between the bounds.
+    pub fn default_value(&self) -> f64 {
+        self.clamp(self.min + (self.max - self.min) / 2.0)
+    }
+
+    /// `value` limited to the bounds and snapped to the nearest step.
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = value.clamp(self.min, self.max);
+        let Some(step) = self.step else {
+            return value;
+        };
+        let snapped = self.min + ((value - self.min) / step).round() * step;
+        if snapped > self.max {
+            snapped - step
+        } else {
+            snapped
+        }
+    }
+
+    /// The value `fraction` of the way from `min` to `max`, snapped.
+    pub fn value_at(&self, fraction: f64) -> f64 {
+        self.clamp(self.min + fraction.clamp(0.0, 1.0) * (self.max - self.min))
+    }
+
+    /// How far `value` is from `min` towards `max`, from 0 to 1.
+    pub fn fraction(&self, value: f64) -> f64 {
+        if self.max > self.min {
+            ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Parse a number value (the rules for parsing floating-point numbers).
+fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+/// Serialize a number value as script would (`50`, `0.5`).
+fn serialize_number(value: f64) -> String {
+    // Avoid "-0"
+    format!("{}", value + 0.0)
+}
+
+/// Whether `value` is a valid date string: `YYYY-MM-DD` with a year of at
+/// least four digits and a day that exists in its month.
+pub fn is_valid_date_string(value: &str) -> bool {
+    let mut parts = value.splitn(3, '-');
+    let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    let digits = |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    if year.len() < 4 || !digits(year, year.len()) || !digits(month, 2) || !digits(day, 2) {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    year > 0 && (1..=days).contains(&day)
+}
+
+/// Whether `value` is a valid simple color: `#` and six hex digits.
+fn is_valid_simple_color(value: &str) -> bool {
+    value.len() == 7 && value.starts_with('#') && value[1..].bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// `<input>` values.
+///
+/// Like selectedness, an input's value starts out as its `value` attribute
+/// and is tracked on the node once it changes. Values are sanitized for the
+/// input's type as they are read and set.
+impl Node {
+    /// Type of an `<input>`, from its `type` attribute.
+    pub fn input_type(&self) -> InputType {
+        InputType::from_str(self.get_attribute("type").unwrap_or("text"))
+    }
+
+    /// Bounds of an `<input type=range>`.
+    pub fn range_bounds(&self) -> RangeBounds {
+        RangeBounds::from_attributes(
+            self.get_attribute("min"),
+            self.get_attribute("max"),
+            self.get_attribute("step"),
+        )
+    }
+
+    /// Current value of an `<input>`.
+    pub fn input_value(&self) -> String {
+        let value = self.dirty_value.borrow();
+        let value = value.as_deref().or_else(|| self.get_attribute("value")).unwrap_or("");
+        self.sanitize_value(value)
+    }
+
+    /// Set the value of an `<input>`, sanitizing it for the input's type.
+    pub fn set_input_value(&self, value: &str) {
+        let value = self.sanitize_value(value);
+        *self.dirty_value.borrow_mut() = Some(value);
+        self.mark_needs_layout();
+    }
+
+    /// The value sanitization algorithm, for the types that have one.
+    fn sanitize_value(&self, value: &str) -> String {
+        match self.input_type() {
+            InputType::Range => {
+                let bounds = self.range_bounds();
+                let number = parse_number(value).map_or_else(|| bounds.default_value(), |n| bounds.clamp(n));
+                serialize_number(number)
+            }
+            InputType::Color if is_valid_simple_color(value) => value.to_ascii_lowercase(),
+            InputType::Color => "#000000".to_string(),
+            InputType::Date if is_valid_date_string(value) => value.to_string(),
+            InputType::Date => String::new(),
+            _ => value.to_string(),
+        }
+    }
+}
+
 /// Form data entry for submission.
 #[derive(Debug, Clone)]
 pub struct FormDataEntry {
@@ -602,8 +854,20 @@ impl FormEnctype {
             FormEnctype::TextPlain => "text/plain",
         }
     }
+
+    /// The `Content-Type` of a body encoded with
+    /// [`FormState::encode_form_data`], with the multipart boundary.
+    pub fn body_content_type(&self) -> String {
+        match self {
+            FormEnctype::MultipartFormData => format!("{}; boundary={}", self.content_type(), MULTIPART_BOUNDARY),
+            _ => self.content_type().to_string(),
+        }
+    }
 }
 
+/// Boundary between the parts of a `multipart/form-data` body.
+const MULTIPART_BOUNDARY: &str = "----RustKitFormBoundary";
+
 /// Form submission method.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum FormMethod {
@@ -1019,8 +1283,9 @@ impl FormState {
     }
 
     fn encode_multipart(entries: &[FormDataEntry]) -> Vec<u8> {
-        // Simplified multipart - real implementation would use proper boundary
-        let boundary = "----RustKitFormBoundary";
+        let boundary = MULTIPART_BOUNDARY;
+        // Quotes and line breaks would end the header value
+        let escape = |name: &str| name.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
         let mut result = Vec::new();
 
         for entry in entries {
@@ -1031,7 +1296,7 @@ impl FormState {
                     result.extend_from_slice(
                         format!(
                             "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
-                            entry.name, v
+                            escape(&entry.name), v
                         )
                         .as_bytes(),
                     );
@@ -1041,7 +1306,7 @@ impl FormState {
                         format!(
                             "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
                              Content-Type: application/octet-stream\r\n\r\n",
-                            entry.name, name
+                            escape(&entry.name), escape(name)
                         )
                         .as_bytes(),
                     );
@@ -1103,13 +1368,7 @@ impl FormState {
             }
             FormMethod::Post => {
                 let body = FormState::encode_form_data(entries, enctype);
-                let content_type = match enctype {
-                    FormEnctype::UrlEncoded => "application/x-www-form-urlencoded".to_string(),
-                    FormEnctype::MultipartFormData => {
-                        "multipart/form-data; boundary=----RustKitFormBoundary".to_string()
-                    }
-                    FormEnctype::TextPlain => "text/plain".to_string(),
-                };
+                let content_type = enctype.body_content_type();
 
                 FormSubmission {
                     url,
@@ -1600,6 +1859,78 @@ pub mod keyboard {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_range_bounds_snap_to_steps() {
+        let bounds = RangeBounds::from_attributes(Some("0"), Some("10"), Some("3"));
+        assert_eq!(bounds.clamp(4.0), 3.0);
+        assert_eq!(bounds.clamp(5.0), 6.0);
+        // 12 would pass max, so the last step below it is used
+        assert_eq!(bounds.clamp(10.0), 9.0);
+        assert_eq!(bounds.value_at(0.5), 6.0);
+        assert_eq!(bounds.default_value(), 6.0);
+
+        let any = RangeBounds::from_attributes(Some("1"), Some("0"), Some("any"));
+        assert_eq!((any.min, any.max, any.step), (1.0, 1.0, None));
+        assert_eq!(any.fraction(1.0), 0.0);
+    }
+
+    #[test]
+    fn test_input_value_sanitization() {
+        let doc = crate::Document::parse_html(
+            r##"<input id="r" type="range" min="0" max="1" step="0.25" value="2">
+                <input id="c" type="color" value="#FF8800">
+                <input id="d" type="date" value="2023-02-29">"##,
+        )
+        .unwrap();
+        let range = doc.get_element_by_id("r").unwrap();
+        assert_eq!(range.input_value(), "1");
+        range.set_input_value("0.3");
+        assert_eq!(range.input_value(), "0.25");
+        range.set_input_value("abc");
+        assert_eq!(range.input_value(), "0.5");
+
+        let color = doc.get_element_by_id("c").unwrap();
+        assert_eq!(color.input_value(), "#ff8800");
+        color.set_input_value("red");
+        assert_eq!(color.input_value(), "#000000");
+
+        let date = doc.get_element_by_id("d").unwrap();
+        assert_eq!(date.input_value(), "");
+        date.set_input_value("2024-02-29");
+        assert_eq!(date.input_value(), "2024-02-29");
+        assert!(!is_valid_date_string("2024-13-01"));
+        assert!(!is_valid_date_string("24-01-01"));
+        assert!(is_valid_date_string("12345-06-30"));
+    }
+
+    #[test]
+    fn test_select_selectedness() {
+        let doc = crate::Document::parse_html(
+            r#"<select id="s">
+                <option disabled>Pick one</option>
+                <option>  Red  </option>
+                <optgroup label="More" disabled><option>Green</option></optgroup>
+                <option label="Blue!">Blue</option>
+            </select>"#,
+        )
+        .unwrap();
+        let select = doc.get_element_by_id("s").unwrap();
+        let options = select.select_options();
+        assert_eq!(options.len(), 4);
+        assert_eq!(options[1].option_label(), "Red");
+        assert_eq!(options[3].option_label(), "Blue!");
+        assert!(options[2].is_option_disabled());
+
+        // Nothing selected: the first enabled option shows
+        assert_eq!(select.selected_index(), Some(1));
+
+        select.set_selected_index(Some(3));
+        assert_eq!(select.selected_index(), Some(3));
+        assert!(!options[1].is_option_selected());
+        // The attribute is still the default
+        assert_eq!(options[3].get_attribute("selected"), None);
+    }
+
     #[test]
     fn test_selection_range() {
         let sel = SelectionRange::new(5, 10);
@@ -1759,6 +2090,23 @@ mod tests {
         assert!(encoded_str.contains("email=john%40example.com"));
     }
 
+    #[test]
+    fn test_multipart_encoding() {
+        let entries = vec![FormDataEntry {
+            name: "say \"hi\"".to_string(),
+            value: FormDataValue::String("Hello".to_string()),
+        }];
+
+        let encoded = FormState::encode_form_data(&entries, FormEnctype::MultipartFormData);
+        let encoded_str = String::from_utf8(encoded).unwrap();
+        let content_type = FormEnctype::MultipartFormData.body_content_type();
+        let boundary = content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+        assert!(encoded_str.starts_with(&format!("--{boundary}\r\n")));
+        assert!(encoded_str.contains("name=\"say %22hi%22\"\r\n\r\nHello\r\n"));
+        assert!(encoded_str.ends_with(&format!("--{boundary}--\r\n")));
+        assert_eq!(FormEnctype::UrlEncoded.body_content_type(), "application/x-www-form-urlencoded");
+    }
+
     #[test]
     fn test_validation() {
         let state = TextEditState::new();
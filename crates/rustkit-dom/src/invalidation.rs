@@ -0,0 +1,105 @@
+//! # Layout invalidation
+//!
+//! Mutations mark the nodes they change as needing layout: a node whose
+//! children changed, an element whose boolean attributes, form state or
+//! top layer membership changed. Their ancestors, through shadow hosts,
+//! are marked as having a descendant that needs layout.
+//!
+//! The engine's next layout pass keeps the boxes of unmarked subtrees from
+//! the previous tree, rebuilds the rest, and then clears the marks with
+//! [`Document::clear_needs_layout`].
+
+use crate::{Document, Node};
+
+impl Node {
+    /// Mark this node as needing layout, and its ancestors as having a
+    /// descendant that does.
+    pub fn mark_needs_layout(&self) {
+        self.needs_layout.set(true);
+        let mut current = self.parent().or_else(|| self.host());
+        while let Some(ancestor) = current {
+            // Its ancestors are already marked
+            if ancestor.child_needs_layout.replace(true) {
+                break;
+            }
+            current = ancestor.parent().or_else(|| ancestor.host());
+        }
+    }
+
+    /// Whether this node changed since the last layout pass.
+    pub fn needs_layout(&self) -> bool {
+        self.needs_layout.get()
+    }
+
+    /// Whether this node or anything under it changed since the last
+    /// layout pass.
+    pub fn subtree_needs_layout(&self) -> bool {
+        self.needs_layout.get() || self.child_needs_layout.get()
+    }
+
+    fn clear_needs_layout(&self) {
+        if !self.subtree_needs_layout() {
+            return;
+        }
+        self.needs_layout.set(false);
+        self.child_needs_layout.set(false);
+        for child in self.children() {
+            child.clear_needs_layout();
+        }
+        if let Some(shadow_root) = self.shadow_root() {
+            shadow_root.clear_needs_layout();
+        }
+    }
+}
+
+impl Document {
+    /// Whether anything in the document changed since the last layout
+    /// pass.
+    pub fn needs_layout(&self) -> bool {
+        self.root().subtree_needs_layout()
+    }
+
+    /// Clear the marks, once a layout pass has caught up with them.
+    pub fn clear_needs_layout(&self) {
+        self.root().clear_needs_layout();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutations_mark_nodes_and_ancestors() {
+        let mut doc = Document::parse_html(
+            r#"<div id="list"><p id="first">One</p></div><div id="other"><input id="name"></div>"#,
+        )
+        .unwrap();
+        let node = |doc: &Document, id: &str| doc.get_element_by_id(id).unwrap();
+        doc.clear_needs_layout();
+        assert!(!doc.needs_layout());
+
+        let item = doc.create_element("p", Vec::new());
+        node(&doc, "list").append_child(item);
+        assert!(node(&doc, "list").needs_layout());
+        assert!(doc.body().unwrap().subtree_needs_layout() && !doc.body().unwrap().needs_layout());
+        assert!(!node(&doc, "first").subtree_needs_layout());
+        assert!(!node(&doc, "other").subtree_needs_layout());
+
+        doc.clear_needs_layout();
+        node(&doc, "name").set_input_value("Ada");
+        assert!(node(&doc, "name").needs_layout() && node(&doc, "other").subtree_needs_layout());
+        assert!(!node(&doc, "list").subtree_needs_layout());
+
+        // Changes inside a shadow tree reach the host
+        doc.clear_needs_layout();
+        let host = node(&doc, "first");
+        let shadow_root = doc.attach_shadow(&host, crate::ShadowRootMode::Open).unwrap();
+        doc.clear_needs_layout();
+        let span = doc.create_element("span", Vec::new());
+        shadow_root.append_child(span);
+        assert!(host.subtree_needs_layout() && node(&doc, "list").subtree_needs_layout());
+        doc.clear_needs_layout();
+        assert!(!shadow_root.subtree_needs_layout());
+    }
+}
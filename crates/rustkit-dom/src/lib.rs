@@ -11,23 +11,37 @@
 //! 4. **Mutation support**: Node insertion, removal, attribute modification
 //! 5. **Event dispatch**: DOM Events with capture/bubble phases
 
+pub mod dialog;
 pub mod events;
 pub mod forms;
 pub mod images;
+pub mod invalidation;
+pub mod mutation;
+pub mod popover;
+pub mod range;
+pub mod text_fragment;
+pub mod traversal;
+pub mod xml;
 
 pub use events::{
     AddEventListenerOptions, DomEvent, Event, EventDispatcher, EventId, EventListenerCallback,
     EventPhase, EventTarget, FocusEventData, InputEventData, KeyboardEventData, MouseEventData,
 };
 pub use forms::{
-    CheckableState, FormDataEntry, FormDataValue, FormEnctype, FormMethod, FormState, InputType,
-    SelectionDirection, SelectionRange, TextEditState,
+    is_valid_date_string, CheckableState, FormDataEntry, FormDataValue, FormEnctype, FormMethod, FormState,
+    InputType, RangeBounds, SelectionDirection, SelectionRange, TextEditState,
 };
 pub use images::{
     CrossOrigin, FaviconLink, ImageDecoding, ImageElement, ImageElementManager, ImageLoading,
     ImageLoadingState, PictureElement, PictureSource,
 };
+pub use popover::{PopoverState, PopoverTargetAction};
+pub use range::{BoundaryPoint, Range};
+pub use text_fragment::TextDirective;
+pub use traversal::{FilterCallback, FilterResult, NodeFilter, NodeIterator, TreeWalker};
+pub use xml::{is_xml_mime_type, serialize_xml};
 
+use std::borrow::Cow;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::{Rc, Weak};
@@ -83,8 +97,40 @@ pub enum NodeType {
         target: String,
         data: String,
     },
+    /// Root of a shadow tree, attached to a host element.
+    ShadowRoot {
+        mode: ShadowRootMode,
+    },
+    /// Parentless container, such as a `<template>`'s content.
+    DocumentFragment,
+}
+
+/// Whether a shadow root is reachable from script through its host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowRootMode {
+    /// `element.shadowRoot` returns the root.
+    Open,
+    /// `element.shadowRoot` returns null; only the creator holds the root.
+    Closed,
+}
+
+impl ShadowRootMode {
+    /// Parse an `attachShadow({ mode })` or `shadowrootmode` value.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "open" => Some(ShadowRootMode::Open),
+            "closed" => Some(ShadowRootMode::Closed),
+            _ => None,
+        }
+    }
 }
 
+/// Elements that may host a shadow root, besides custom elements.
+const SHADOW_HOST_TAGS: &[&str] = &[
+    "article", "aside", "blockquote", "body", "div", "footer", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "main", "nav", "p", "section", "span",
+];
+
 /// A DOM node.
 #[derive(Debug)]
 pub struct Node {
@@ -100,6 +146,25 @@ pub struct Node {
     prev_sibling: RefCell<Option<Weak<Node>>>,
     /// Next sibling.
     next_sibling: RefCell<Option<Weak<Node>>>,
+    /// Shadow root attached to this element.
+    shadow_root: RefCell<Option<Rc<Node>>>,
+    /// Host element, for shadow roots.
+    host: RefCell<Option<Weak<Node>>>,
+    /// Inert contents, for `<template>` elements.
+    template_content: RefCell<Option<Rc<Node>>>,
+    /// Selectedness of an `<option>`, once changed from its `selected`
+    /// attribute.
+    selectedness: Cell<Option<bool>>,
+    /// Value of an `<input>`, once changed from its `value` attribute.
+    dirty_value: RefCell<Option<String>>,
+    /// Attributes set (`Some`) or removed (`None`) since parsing.
+    changed_attributes: RefCell<HashMap<String, Option<String>>>,
+    /// Whether the element is in its document's top layer.
+    in_top_layer: Cell<bool>,
+    /// Whether the node changed since the last layout pass.
+    needs_layout: Cell<bool>,
+    /// Whether a descendant changed since the last layout pass.
+    child_needs_layout: Cell<bool>,
     /// Event target mixin for event handling.
     pub event_target: EventTarget,
 }
@@ -114,6 +179,15 @@ impl Node {
             children: RefCell::new(Vec::new()),
             prev_sibling: RefCell::new(None),
             next_sibling: RefCell::new(None),
+            shadow_root: RefCell::new(None),
+            host: RefCell::new(None),
+            template_content: RefCell::new(None),
+            selectedness: Cell::new(None),
+            dirty_value: RefCell::new(None),
+            changed_attributes: RefCell::new(HashMap::new()),
+            in_top_layer: Cell::new(false),
+            needs_layout: Cell::new(false),
+            child_needs_layout: Cell::new(false),
             event_target: EventTarget::new(),
         })
     }
@@ -127,9 +201,61 @@ impl Node {
     }
 
     /// Get an attribute value.
+    ///
+    /// This sees attributes added or removed since parsing, but an
+    /// attribute whose value was changed keeps its parsed value here, or
+    /// reads as empty if it had none: [`attributes`](Self::attributes) has
+    /// the current values.
     pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        let NodeType::Element { attributes, .. } = &self.node_type else {
+            return None;
+        };
+        match self.changed_attributes.borrow().get(name) {
+            Some(None) => None,
+            Some(Some(_)) => Some(attributes.get(name).map_or("", String::as_str)),
+            None => attributes.get(name).map(String::as_str),
+        }
+    }
+
+    /// All attributes of an element, with attributes set or removed since
+    /// parsing applied.
+    pub fn attributes(&self) -> Cow<'_, HashMap<String, String>> {
+        let NodeType::Element { attributes, .. } = &self.node_type else {
+            return Cow::Owned(HashMap::new());
+        };
+        let changes = self.changed_attributes.borrow();
+        if changes.is_empty() {
+            return Cow::Borrowed(attributes);
+        }
+        let mut attributes = attributes.clone();
+        for (name, value) in changes.iter() {
+            match value {
+                Some(value) => attributes.insert(name.clone(), value.clone()),
+                None => attributes.remove(name),
+            };
+        }
+        Cow::Owned(attributes)
+    }
+
+    /// Add or remove a boolean attribute such as `open` or `hidden`.
+    ///
+    /// An attribute that's already present keeps its value.
+    pub fn set_boolean_attribute(&self, name: &str, present: bool) {
+        if !self.is_element() {
+            return;
+        }
+        if present == self.get_attribute(name).is_some() {
+            return;
+        }
+        let value = present.then(|| self.parsed_attribute(name).unwrap_or_default());
+        self.changed_attributes.borrow_mut().insert(name.to_string(), value);
+        self.mark_needs_layout();
+    }
+
+    /// An attribute's value as parsed.
+    fn parsed_attribute(&self, name: &str) -> Option<String> {
         match &self.node_type {
-            NodeType::Element { attributes, .. } => attributes.get(name).map(|s| s.as_str()),
+            NodeType::Element { attributes, .. } => attributes.get(name).cloned(),
             _ => None,
         }
     }
@@ -198,8 +324,137 @@ impl Node {
         matches!(self.node_type, NodeType::Text(_))
     }
 
-    /// Append a child node.
+    /// The numeric `nodeType` (1 for elements, 3 for text, ...). Shadow
+    /// roots report as document fragments.
+    pub fn node_type_code(&self) -> u16 {
+        match self.node_type {
+            NodeType::Element { .. } => 1,
+            NodeType::Text(_) => 3,
+            NodeType::ProcessingInstruction { .. } => 7,
+            NodeType::Comment(_) => 8,
+            NodeType::Document => 9,
+            NodeType::DocumentType { .. } => 10,
+            NodeType::ShadowRoot { .. } | NodeType::DocumentFragment => 11,
+        }
+    }
+
+    /// Check if this is a shadow root.
+    pub fn is_shadow_root(&self) -> bool {
+        matches!(self.node_type, NodeType::ShadowRoot { .. })
+    }
+
+    /// Get the shadow root attached to this element, whatever its mode.
+    ///
+    /// Script-facing `element.shadowRoot` must hide closed roots.
+    pub fn shadow_root(&self) -> Option<Rc<Node>> {
+        self.shadow_root.borrow().clone()
+    }
+
+    /// Get the host element of a shadow root.
+    pub fn host(&self) -> Option<Rc<Node>> {
+        self.host.borrow().as_ref().and_then(|w| w.upgrade())
+    }
+
+    /// Get the root of the tree containing this node: the document, a
+    /// shadow root, or the topmost node of a detached subtree.
+    pub fn root_node(self: &Rc<Self>) -> Rc<Node> {
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            node = parent;
+        }
+        node
+    }
+
+    /// Whether this node is in the document tree (not detached, inside a
+    /// template's content, or in a shadow tree).
+    pub fn in_document_tree(self: &Rc<Self>) -> bool {
+        matches!(self.root_node().node_type, NodeType::Document)
+    }
+
+    /// Get a `<template>`'s content fragment.
+    ///
+    /// Template children live here instead of in the tree, so they are
+    /// neither rendered nor found by document lookups.
+    pub fn template_content(&self) -> Option<Rc<Node>> {
+        self.template_content.borrow().clone()
+    }
+
+    /// Get the shadow root whose tree contains this node, if any.
+    pub fn containing_shadow_root(self: &Rc<Self>) -> Option<Rc<Node>> {
+        Some(self.root_node()).filter(|root| root.is_shadow_root())
+    }
+
+    /// Whether this node is `other` or one of its ancestors, counting
+    /// shadow hosts as the parents of their shadow roots.
+    pub fn is_shadow_including_inclusive_ancestor_of(self: &Rc<Self>, other: &Rc<Node>) -> bool {
+        let mut node = Some(other.clone());
+        while let Some(current) = node {
+            if Rc::ptr_eq(self, &current) {
+                return true;
+            }
+            node = current.parent().or_else(|| current.host());
+        }
+        false
+    }
+
+    /// The slot name this node is assigned by: its `slot` attribute, or the
+    /// default slot.
+    pub fn slot_name(&self) -> &str {
+        self.get_attribute("slot").unwrap_or("")
+    }
+
+    /// Get the `<slot>` in the parent's shadow tree that this node is
+    /// rendered in, if any.
+    pub fn assigned_slot(self: &Rc<Self>) -> Option<Rc<Node>> {
+        if !self.is_element() && !self.is_text() {
+            return None;
+        }
+        let shadow_root = self.parent()?.shadow_root()?;
+        find_slot(&shadow_root, self.slot_name())
+    }
+
+    /// Get the host children assigned to this `<slot>`, in tree order.
+    pub fn assigned_nodes(self: &Rc<Self>) -> Vec<Rc<Node>> {
+        let Some(host) = self.containing_shadow_root().and_then(|root| root.host()) else {
+            return Vec::new();
+        };
+        host.children()
+            .into_iter()
+            .filter(|child| child.assigned_slot().is_some_and(|slot| Rc::ptr_eq(&slot, self)))
+            .collect()
+    }
+
+    /// `assignedNodes({ flatten: true })`: assigned nodes with nested slots
+    /// replaced by what they show, falling back to this slot's own children
+    /// when nothing is assigned.
+    pub fn flattened_assigned_nodes(self: &Rc<Self>) -> Vec<Rc<Node>> {
+        let mut nodes = self.assigned_nodes();
+        if nodes.is_empty() {
+            nodes = self.children();
+        }
+        nodes
+            .into_iter()
+            .flat_map(|node| {
+                let is_slot = node.tag_name().is_some_and(|t| t.eq_ignore_ascii_case("slot"));
+                if is_slot && node.containing_shadow_root().is_some() {
+                    node.flattened_assigned_nodes()
+                } else {
+                    vec![node]
+                }
+            })
+            .collect()
+    }
+
+    /// Append a child node. Appending a document fragment moves its children.
     pub fn append_child(self: &Rc<Self>, child: Rc<Node>) {
+        if matches!(child.node_type, NodeType::DocumentFragment) {
+            for grandchild in child.children() {
+                grandchild.remove_from_parent();
+                self.append_child(grandchild);
+            }
+            return;
+        }
+
         // Update child's parent
         *child.parent.borrow_mut() = Some(Rc::downgrade(self));
 
@@ -211,6 +466,7 @@ impl Node {
 
         // Add to children
         self.children.borrow_mut().push(child);
+        self.mark_needs_layout();
     }
 
     /// Remove this node from its parent.
@@ -226,6 +482,7 @@ impl Node {
 
             // Remove from parent's children
             parent.children.borrow_mut().retain(|c| !Rc::ptr_eq(c, self));
+            parent.mark_needs_layout();
 
             // Clear our parent reference
             *self.parent.borrow_mut() = None;
@@ -234,8 +491,17 @@ impl Node {
         }
     }
 
-    /// Insert a child node before a reference node.
+    /// Insert a child node before a reference node. Inserting a document
+    /// fragment inserts its children.
     pub fn insert_before(self: &Rc<Self>, new_child: Rc<Node>, reference: Rc<Node>) {
+        if matches!(new_child.node_type, NodeType::DocumentFragment) {
+            for grandchild in new_child.children() {
+                grandchild.remove_from_parent();
+                self.insert_before(grandchild, reference.clone());
+            }
+            return;
+        }
+
         // Find the index of the reference node
         let mut children = self.children.borrow_mut();
         let ref_idx = children.iter().position(|c| Rc::ptr_eq(c, &reference));
@@ -255,6 +521,8 @@ impl Node {
 
             // Insert into children
             children.insert(idx, new_child);
+            drop(children);
+            self.mark_needs_layout();
         } else {
             // Reference not found, append at end
             drop(children);
@@ -263,14 +531,31 @@ impl Node {
     }
 }
 
+/// The first `<slot>` named `name` under `root`, in tree order.
+fn find_slot(root: &Rc<Node>, name: &str) -> Option<Rc<Node>> {
+    for child in root.children() {
+        if child.tag_name().is_some_and(|t| t.eq_ignore_ascii_case("slot"))
+            && child.get_attribute("name").unwrap_or("") == name
+        {
+            return Some(child);
+        }
+        if let Some(slot) = find_slot(&child, name) {
+            return Some(slot);
+        }
+    }
+    None
+}
+
 /// A complete DOM document.
 pub struct Document {
     /// Root node of the document.
     root: Rc<Node>,
     /// All nodes indexed by ID.
-    nodes: HashMap<NodeId, Rc<Node>>,
-    /// Elements indexed by ID attribute.
-    elements_by_id: HashMap<String, Rc<Node>>,
+    nodes: RefCell<HashMap<NodeId, Rc<Node>>>,
+    /// Elements indexed by ID attribute, in creation order.
+    elements_by_id: RefCell<HashMap<String, Vec<Rc<Node>>>>,
+    /// Elements rendered above everything else, topmost last.
+    top_layer: RefCell<Vec<Rc<Node>>>,
     /// Next node ID.
     next_id: Cell<usize>,
 }
@@ -298,12 +583,7 @@ impl DocumentSink {
     }
 
     fn create_node(&mut self, node_type: NodeType) -> Rc<Node> {
-        let id = NodeId::new(self.doc.next_id.get());
-        self.doc.next_id.set(self.doc.next_id.get() + 1);
-
-        let node = Node::new(id, node_type);
-        self.doc.nodes.insert(id, node.clone());
-        node
+        self.doc.create_node(node_type)
     }
 }
 
@@ -336,11 +616,6 @@ impl rustkit_html::TreeSink for DocumentSink {
             attributes,
         });
 
-        // Index by ID attribute
-        if let Some(id) = node.get_attribute("id") {
-            self.doc.elements_by_id.insert(id.to_string(), node.clone());
-        }
-
         let parent = self.current_parent();
         parent.append_child(node.clone());
 
@@ -468,8 +743,9 @@ impl Document {
 
         Self {
             root,
-            nodes,
-            elements_by_id: HashMap::new(),
+            nodes: RefCell::new(nodes),
+            elements_by_id: RefCell::new(HashMap::new()),
+            top_layer: RefCell::new(Vec::new()),
             next_id: Cell::new(1),
         }
     }
@@ -479,24 +755,151 @@ impl Document {
         debug!(len = html.len(), "Parsing HTML (rustkit-html)");
 
         let sink = DocumentSink::new();
-        let sink = rustkit_html::parse(html, sink).map_err(|e| DomError::ParseError(e.to_string()))?;
+        let mut sink = rustkit_html::parse(html, sink).map_err(|e| DomError::ParseError(e.to_string()))?;
+
+        let root = sink.doc.root.clone();
+        sink.doc.move_template_contents(&root);
 
-        debug!(node_count = sink.doc.nodes.len(), "HTML parsed");
+        debug!(node_count = sink.doc.nodes.borrow().len(), "HTML parsed");
         Ok(sink.doc)
     }
 
+    fn create_node(&self, node_type: NodeType) -> Rc<Node> {
+        let id = NodeId::new(self.next_id.get());
+        self.next_id.set(self.next_id.get() + 1);
+
+        let node = Node::new(id, node_type);
+        self.nodes.borrow_mut().insert(id, node.clone());
+
+        // Index by ID attribute
+        if let Some(id) = node.get_attribute("id") {
+            self.index_element_id(id, &node);
+        }
+        node
+    }
+
+    /// Make `node` findable by [`get_element_by_id`](Self::get_element_by_id)
+    /// under `id`.
+    fn index_element_id(&self, id: &str, node: &Rc<Node>) {
+        let mut elements_by_id = self.elements_by_id.borrow_mut();
+        let elements = elements_by_id.entry(id.to_string()).or_default();
+        if !elements.iter().any(|element| Rc::ptr_eq(element, node)) {
+            elements.push(node.clone());
+        }
+    }
+
+    /// Create a detached document fragment.
+    pub fn create_document_fragment(&mut self) -> Rc<Node> {
+        self.create_node(NodeType::DocumentFragment)
+    }
+
+    /// Copy `node` (`cloneNode()` / `importNode()`), with its descendants if
+    /// `deep`. Templates always copy their content when deep.
+    ///
+    /// Shadow roots and event listeners are not copied.
+    pub fn clone_node(&mut self, node: &Rc<Node>, deep: bool) -> Rc<Node> {
+        let node_type = match &node.node_type {
+            // A copied shadow root is just a fragment of its children
+            NodeType::ShadowRoot { .. } => NodeType::DocumentFragment,
+            other => other.clone(),
+        };
+        let copy = self.create_node(node_type);
+        copy.selectedness.set(node.selectedness.get());
+        *copy.dirty_value.borrow_mut() = node.dirty_value.borrow().clone();
+        *copy.changed_attributes.borrow_mut() = node.changed_attributes.borrow().clone();
+        if let Some(content) = node.template_content() {
+            let content_copy = if deep {
+                self.clone_node(&content, true)
+            } else {
+                self.create_document_fragment()
+            };
+            *copy.template_content.borrow_mut() = Some(content_copy);
+        }
+        if deep {
+            for child in node.children() {
+                let child_copy = self.clone_node(&child, true);
+                copy.append_child(child_copy);
+            }
+        }
+        copy
+    }
+
+    /// Create a detached element.
+    pub fn create_element(&mut self, tag_name: &str, attributes: Vec<(String, String)>) -> Rc<Node> {
+        self.create_node(NodeType::Element {
+            tag_name: tag_name.to_string(),
+            namespace: String::from("http://www.w3.org/1999/xhtml"),
+            attributes: attributes.into_iter().collect(),
+        })
+    }
+
+    /// Create a detached text node.
+    pub fn create_text_node(&mut self, text: &str) -> Rc<Node> {
+        self.create_node(NodeType::Text(text.to_string()))
+    }
+
+    /// Attach a shadow root to `host` (`element.attachShadow()`).
+    ///
+    /// Fails for non-elements, elements that can't host a shadow tree, and
+    /// elements that already have one.
+    pub fn attach_shadow(&mut self, host: &Rc<Node>, mode: ShadowRootMode) -> Result<Rc<Node>, DomError> {
+        let tag = host
+            .tag_name()
+            .ok_or_else(|| DomError::InvalidOperation("attachShadow on a non-element".into()))?
+            .to_ascii_lowercase();
+        if !tag.contains('-') && !SHADOW_HOST_TAGS.contains(&tag.as_str()) {
+            return Err(DomError::InvalidOperation(format!("<{}> cannot host a shadow root", tag)));
+        }
+        if host.shadow_root().is_some() {
+            return Err(DomError::InvalidOperation(format!("<{}> already hosts a shadow root", tag)));
+        }
+
+        let shadow_root = self.create_node(NodeType::ShadowRoot { mode });
+        *shadow_root.host.borrow_mut() = Some(Rc::downgrade(host));
+        *host.shadow_root.borrow_mut() = Some(shadow_root.clone());
+        host.mark_needs_layout();
+        Ok(shadow_root)
+    }
+
+    /// Move parsed `<template>` children out of the tree: into a shadow root
+    /// of the parent for `<template shadowrootmode>`, or else into the
+    /// template's content. Innermost templates go first so nesting works.
+    fn move_template_contents(&mut self, node: &Rc<Node>) {
+        for child in node.children() {
+            self.move_template_contents(&child);
+        }
+
+        if !node.tag_name().is_some_and(|t| t.eq_ignore_ascii_case("template")) {
+            return;
+        }
+        let declarative_root = node
+            .get_attribute("shadowrootmode")
+            .and_then(ShadowRootMode::parse)
+            .zip(node.parent())
+            // Templates that can't attach stay in the tree as ordinary templates
+            .and_then(|(mode, host)| self.attach_shadow(&host, mode).ok());
+        let destination = declarative_root.clone().unwrap_or_else(|| self.create_document_fragment());
+        for content in node.children() {
+            content.remove_from_parent();
+            destination.append_child(content);
+        }
+
+        if declarative_root.is_some() {
+            node.remove_from_parent();
+        } else {
+            *node.template_content.borrow_mut() = Some(destination);
+        }
+    }
+
 
     /// Get the document root.
     pub fn root(&self) -> &Rc<Node> {
         &self.root
     }
 
-    /// Get the document element (<html>).
+    /// Get the document element: `<html>`, or an XML document's root element.
     pub fn document_element(&self) -> Option<Rc<Node>> {
-        self.root
-            .children()
-            .into_iter()
-            .find(|n| n.tag_name() == Some("html"))
+        self.root.children().into_iter().find(|n| n.is_element())
     }
 
     /// Get the <head> element.
@@ -528,20 +931,31 @@ impl Document {
     }
 
     /// Get element by ID.
+    ///
+    /// Like the other document-wide lookups, this only sees connected nodes,
+    /// not shadow trees, template content or detached nodes.
     pub fn get_element_by_id(&self, id: &str) -> Option<Rc<Node>> {
-        self.elements_by_id.get(id).cloned()
+        self.elements_by_id
+            .borrow()
+            .get(id)?
+            .iter()
+            // Elements whose `id` changed stay indexed under the old one
+            .find(|n| n.in_document_tree() && n.attributes().get("id").is_some_and(|current| current == id))
+            .cloned()
     }
 
     /// Get elements by tag name.
     pub fn get_elements_by_tag_name(&self, tag_name: &str) -> Vec<Rc<Node>> {
         let tag_name_lower = tag_name.to_lowercase();
         self.nodes
+            .borrow()
             .values()
             .filter(|n| {
                 n.tag_name()
                     .map(|t| t.to_lowercase() == tag_name_lower)
                     .unwrap_or(false)
             })
+            .filter(|n| n.in_document_tree())
             .cloned()
             .collect()
     }
@@ -549,19 +963,21 @@ impl Document {
     /// Get elements by class name.
     pub fn get_elements_by_class_name(&self, class_name: &str) -> Vec<Rc<Node>> {
         self.nodes
+            .borrow()
             .values()
             .filter(|n| {
                 n.get_attribute("class")
                     .map(|c| c.split_whitespace().any(|cls| cls == class_name))
                     .unwrap_or(false)
             })
+            .filter(|n| n.in_document_tree())
             .cloned()
             .collect()
     }
 
     /// Get node by ID.
     pub fn get_node(&self, id: NodeId) -> Option<Rc<Node>> {
-        self.nodes.get(&id).cloned()
+        self.nodes.borrow().get(&id).cloned()
     }
 
     /// Get the title of the document.
@@ -796,4 +1212,142 @@ mod tests {
 
         assert!(doc.body().is_some(), "should have body with meta and title");
     }
+
+    #[test]
+    fn test_declarative_shadow_root() {
+        let html = r#"<html><body>
+<my-card id="card"><template shadowrootmode="open"><style>p { color: red; }</style><p id="inner"><slot name="title"></slot></p><slot></slot></template><span slot="title">Title</span>Body text</my-card>
+</body></html>"#;
+        let doc = Document::parse_html(html).unwrap();
+
+        let card = doc.get_element_by_id("card").unwrap();
+        let shadow_root = card.shadow_root().expect("template should attach a shadow root");
+        assert_eq!(shadow_root.node_type, NodeType::ShadowRoot { mode: ShadowRootMode::Open });
+        assert!(Rc::ptr_eq(&shadow_root.host().unwrap(), &card));
+        // The template itself is gone from the light tree
+        assert!(card.children().iter().all(|c| c.tag_name() != Some("template")));
+
+        // Shadow content is hidden from document-wide lookups
+        assert!(doc.get_element_by_id("inner").is_none());
+        assert!(doc.get_elements_by_tag_name("style").is_empty());
+
+        let title = card.children().into_iter().find(|c| c.is_element()).unwrap();
+        let title_slot = title.assigned_slot().unwrap();
+        assert_eq!(title_slot.get_attribute("name"), Some("title"));
+        assert_eq!(title_slot.assigned_nodes().len(), 1);
+
+        let text = card.children().into_iter().find(|c| c.is_text()).unwrap();
+        let default_slot = text.assigned_slot().unwrap();
+        assert_eq!(default_slot.get_attribute("name"), None);
+        assert!(Rc::ptr_eq(&default_slot.containing_shadow_root().unwrap(), &shadow_root));
+    }
+
+    #[test]
+    fn test_attach_shadow_validation() {
+        let mut doc = Document::new();
+        let div = doc.create_element("div", vec![]);
+        let root = doc.attach_shadow(&div, ShadowRootMode::Closed).unwrap();
+        assert!(Rc::ptr_eq(&div.shadow_root().unwrap(), &root));
+        assert!(doc.attach_shadow(&div, ShadowRootMode::Open).is_err());
+
+        let img = doc.create_element("img", vec![]);
+        assert!(doc.attach_shadow(&img, ShadowRootMode::Open).is_err());
+        let text = doc.create_text_node("hi");
+        assert!(doc.attach_shadow(&text, ShadowRootMode::Open).is_err());
+        let custom = doc.create_element("x-widget", vec![]);
+        assert!(doc.attach_shadow(&custom, ShadowRootMode::Open).is_ok());
+
+        // Slots match by name; unnamed content falls to the default slot
+        let slot = doc.create_element("slot", vec![("name".into(), "a".into())]);
+        root.append_child(slot.clone());
+        let named = doc.create_element("span", vec![("slot".into(), "a".into())]);
+        let unnamed = doc.create_element("span", vec![]);
+        div.append_child(named.clone());
+        div.append_child(unnamed.clone());
+        assert!(Rc::ptr_eq(&named.assigned_slot().unwrap(), &slot));
+        assert!(unnamed.assigned_slot().is_none());
+    }
+
+    #[test]
+    fn test_template_content_is_inert() {
+        let html = r#"<html><body><template id="row"><li class="item"><span id="label">Item</span></li></template><ul id="list"></ul></body></html>"#;
+        let mut doc = Document::parse_html(html).unwrap();
+
+        let template = doc.get_element_by_id("row").unwrap();
+        assert!(template.children().is_empty());
+        let content = template.template_content().unwrap();
+        assert_eq!(content.node_type, NodeType::DocumentFragment);
+        assert_eq!(content.children().len(), 1);
+        // Nothing inside the content is part of the document
+        assert!(doc.get_element_by_id("label").is_none());
+        assert!(doc.get_elements_by_class_name("item").is_empty());
+
+        // Stamp the template into the list twice
+        let list = doc.get_element_by_id("list").unwrap();
+        for _ in 0..2 {
+            let copy = doc.clone_node(&content, true);
+            list.append_child(copy);
+        }
+        assert_eq!(list.children().len(), 2);
+        assert_eq!(doc.get_elements_by_class_name("item").len(), 2);
+        let label = doc.get_element_by_id("label").unwrap();
+        assert!(Rc::ptr_eq(&label.parent().unwrap(), &list.children()[0]));
+        // The template keeps its own content
+        assert_eq!(content.children().len(), 1);
+
+        // Cloning a template copies its content too
+        let template_copy = doc.clone_node(&template, true);
+        assert_eq!(template_copy.template_content().unwrap().children().len(), 1);
+        let shallow = doc.clone_node(&template, false);
+        assert!(shallow.template_content().unwrap().children().is_empty());
+    }
+
+    #[test]
+    fn test_flattened_slot_assignment() {
+        let mut doc = Document::new();
+        let outer_host = doc.create_element("div", vec![]);
+        let outer_root = doc.attach_shadow(&outer_host, ShadowRootMode::Open).unwrap();
+
+        // An inner host in the outer shadow tree forwards the outer slot
+        let inner_host = doc.create_element("span", vec![]);
+        outer_root.append_child(inner_host.clone());
+        let outer_slot = doc.create_element("slot", vec![]);
+        inner_host.append_child(outer_slot);
+        let inner_root = doc.attach_shadow(&inner_host, ShadowRootMode::Open).unwrap();
+        let inner_slot = doc.create_element("slot", vec![]);
+        inner_root.append_child(inner_slot.clone());
+
+        // With nothing assigned to the outer slot, its fallback (none) shows
+        assert_eq!(inner_slot.assigned_nodes().len(), 1);
+        assert!(inner_slot.flattened_assigned_nodes().is_empty());
+
+        let text = doc.create_text_node("light");
+        outer_host.append_child(text.clone());
+        let flattened = inner_slot.flattened_assigned_nodes();
+        assert_eq!(flattened.len(), 1);
+        assert!(Rc::ptr_eq(&flattened[0], &text));
+
+        // A slot with nothing assigned falls back to its children
+        let lone_slot = doc.create_element("slot", vec![("name".into(), "none".into())]);
+        inner_root.append_child(lone_slot.clone());
+        lone_slot.append_child(doc.create_text_node("default"));
+        assert_eq!(lone_slot.flattened_assigned_nodes().len(), 1);
+    }
+
+    #[test]
+    fn test_boolean_attributes() {
+        let doc = Document::parse_html(r#"<details id="d" class="faq"><summary>Q</summary></details>"#).unwrap();
+        let details = doc.get_element_by_id("d").unwrap();
+        assert_eq!(details.get_attribute("open"), None);
+
+        details.set_boolean_attribute("open", true);
+        assert_eq!(details.get_attribute("open"), Some(""));
+        assert_eq!(details.attributes().get("open").map(String::as_str), Some(""));
+        assert_eq!(details.attributes().get("class").map(String::as_str), Some("faq"));
+        assert!(serialize_xml(&details).contains(r#"open="""#));
+
+        details.set_boolean_attribute("open", false);
+        assert_eq!(details.get_attribute("open"), None);
+        assert!(!details.attributes().contains_key("open"));
+    }
 }
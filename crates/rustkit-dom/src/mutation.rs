@@ -0,0 +1,118 @@
+//! # Script mutations
+//!
+//! Pages change their document from script: `setAttribute()`,
+//! `removeAttribute()`, inserting nodes and markup, `innerHTML`. These
+//! operations work through a shared `&Document`, so the engine can apply a
+//! page's changes to the document it lays out, and they mark what they
+//! touch as needing layout like every other mutation.
+
+use std::rc::Rc;
+
+use crate::{Document, DomError, Node, NodeType};
+
+impl Node {
+    /// Set an attribute (`setAttribute()`).
+    ///
+    /// Use [`Document::set_attribute`] to keep an element findable when its
+    /// `id` changes.
+    pub fn set_attribute(&self, name: &str, value: &str) {
+        if !self.is_element() || self.attributes().get(name).is_some_and(|current| current == value) {
+            return;
+        }
+        self.changed_attributes
+            .borrow_mut()
+            .insert(name.to_string(), Some(value.to_string()));
+        self.mark_needs_layout();
+    }
+
+    /// Remove an attribute (`removeAttribute()`).
+    pub fn remove_attribute(&self, name: &str) {
+        if self.get_attribute(name).is_none() {
+            return;
+        }
+        self.changed_attributes.borrow_mut().insert(name.to_string(), None);
+        self.mark_needs_layout();
+    }
+
+    /// Remove all of this node's children.
+    pub fn remove_children(&self) {
+        for child in self.children() {
+            child.remove_from_parent();
+        }
+    }
+}
+
+impl Document {
+    /// Set an attribute of `element`, indexing it under its new `id`.
+    pub fn set_attribute(&self, element: &Rc<Node>, name: &str, value: &str) {
+        element.set_attribute(name, value);
+        if name == "id" {
+            self.index_element_id(value, element);
+        }
+    }
+
+    /// Parse `markup` as the contents of `<body>` into a new fragment of
+    /// this document (`innerHTML`).
+    pub fn parse_fragment(&self, markup: &str) -> Result<Rc<Node>, DomError> {
+        let parsed = Document::parse_html(markup)?;
+        let fragment = self.create_node(NodeType::DocumentFragment);
+        // The parser moves some leading elements, such as `<style>`, into
+        // the head
+        for container in parsed.head().into_iter().chain(parsed.body()) {
+            for child in container.children() {
+                fragment.append_child(self.import_node(&child));
+            }
+        }
+        Ok(fragment)
+    }
+
+    /// Copy a node of another document, with its descendants, into this
+    /// one.
+    fn import_node(&self, node: &Rc<Node>) -> Rc<Node> {
+        let copy = self.create_node(node.node_type.clone());
+        *copy.changed_attributes.borrow_mut() = node.changed_attributes.borrow().clone();
+        if let Some(content) = node.template_content() {
+            *copy.template_content.borrow_mut() = Some(self.import_node(&content));
+        }
+        for child in node.children() {
+            copy.append_child(self.import_node(&child));
+        }
+        copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_mutations() {
+        let doc = Document::parse_html(r#"<div id="box" class="a" title="t"><p>Old</p></div>"#).unwrap();
+        let container = doc.get_element_by_id("box").unwrap();
+        doc.clear_needs_layout();
+
+        doc.set_attribute(&container, "class", "b");
+        assert_eq!(container.attributes().get("class").map(String::as_str), Some("b"));
+        assert!(container.needs_layout());
+
+        doc.clear_needs_layout();
+        container.set_attribute("class", "b");
+        assert!(!doc.needs_layout());
+
+        container.remove_attribute("title");
+        assert_eq!(container.get_attribute("title"), None);
+        assert!(container.needs_layout());
+
+        doc.set_attribute(&container, "id", "moved");
+        assert!(Rc::ptr_eq(&doc.get_element_by_id("moved").unwrap(), &container));
+        assert!(doc.get_element_by_id("box").is_none());
+
+        container.remove_children();
+        let fragment = doc.parse_fragment(r#"<span id="new">New <b>text</b></span>tail"#).unwrap();
+        container.append_child(fragment);
+        assert_eq!(container.text_content(), "New texttail");
+        let span = doc.get_element_by_id("new").unwrap();
+        assert!(Rc::ptr_eq(&span.parent().unwrap(), &container));
+        assert!(doc.get_node(span.id).is_some());
+    }
+}
@@ -0,0 +1,191 @@
+//! # Popovers
+//!
+//! Elements with a `popover` attribute are hidden until shown with
+//! `showPopover()` or a `popovertarget` button, which puts them in the top
+//! layer with the modal dialogs. Auto popovers (`popover` or
+//! `popover="auto"`) form a stack: showing one hides the open auto popovers
+//! that don't contain it, and they are light-dismissed by clicking outside
+//! them or pressing Escape. Manual popovers (`popover="manual"`, or any
+//! other value) only close when asked to.
+
+use std::rc::Rc;
+
+use crate::{Document, DomError, Node};
+
+/// The `popover` attribute's state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopoverState {
+    /// Light-dismissed, and closes other auto popovers when shown.
+    Auto,
+    /// Stays open until hidden explicitly.
+    Manual,
+}
+
+/// What a `popovertarget` button does to its popover
+/// (`popovertargetaction`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopoverTargetAction {
+    Toggle,
+    Show,
+    Hide,
+}
+
+impl Node {
+    /// The element's popover state, if it's a popover.
+    pub fn popover_state(&self) -> Option<PopoverState> {
+        let value = self.get_attribute("popover")?;
+        if value.is_empty() || value.eq_ignore_ascii_case("auto") {
+            Some(PopoverState::Auto)
+        } else {
+            Some(PopoverState::Manual)
+        }
+    }
+
+    /// Whether this is a popover that's showing (`:popover-open`).
+    pub fn is_popover_open(&self) -> bool {
+        self.popover_state().is_some() && self.in_top_layer()
+    }
+
+    /// The popover a `popovertarget` button controls, found by ID in
+    /// `document`, and what clicking the button does to it.
+    pub fn popover_target(&self, document: &Document) -> Option<(Rc<Node>, PopoverTargetAction)> {
+        let is_button = match self.tag_name()?.to_ascii_lowercase().as_str() {
+            "button" => true,
+            "input" => matches!(self.get_attribute("type"), Some("button" | "submit" | "reset" | "image")),
+            _ => false,
+        };
+        if !is_button || self.get_attribute("disabled").is_some() {
+            return None;
+        }
+        let target = document.get_element_by_id(self.get_attribute("popovertarget")?)?;
+        target.popover_state()?;
+        let action = match self.get_attribute("popovertargetaction") {
+            Some(action) if action.eq_ignore_ascii_case("show") => PopoverTargetAction::Show,
+            Some(action) if action.eq_ignore_ascii_case("hide") => PopoverTargetAction::Hide,
+            _ => PopoverTargetAction::Toggle,
+        };
+        Some((target, action))
+    }
+}
+
+impl Document {
+    /// Open auto popovers, bottommost first.
+    pub fn auto_popovers(&self) -> Vec<Rc<Node>> {
+        self.top_layer
+            .borrow()
+            .iter()
+            .filter(|node| node.popover_state() == Some(PopoverState::Auto))
+            .cloned()
+            .collect()
+    }
+
+    /// Show `popover` in the top layer (`showPopover()`). Returns the auto
+    /// popovers hidden to make way for it; showing an open popover does
+    /// nothing.
+    pub fn show_popover(&self, popover: &Rc<Node>) -> Result<Vec<Rc<Node>>, DomError> {
+        let Some(state) = popover.popover_state() else {
+            return Err(DomError::InvalidOperation("element is not a popover".into()));
+        };
+        if popover.is_popover_open() {
+            return Ok(Vec::new());
+        }
+        let hidden = match state {
+            PopoverState::Auto => self.hide_auto_popovers_except(|open| open.is_shadow_including_inclusive_ancestor_of(popover)),
+            PopoverState::Manual => Vec::new(),
+        };
+        self.add_to_top_layer(popover);
+        Ok(hidden)
+    }
+
+    /// Hide `popover` (`hidePopover()`), and for an auto popover the auto
+    /// popovers opened after it. Returns the popovers hidden.
+    pub fn hide_popover(&self, popover: &Rc<Node>) -> Vec<Rc<Node>> {
+        if !popover.is_popover_open() {
+            return Vec::new();
+        }
+        if popover.popover_state() == Some(PopoverState::Auto) {
+            let stack = self.auto_popovers();
+            let position = stack.iter().position(|open| Rc::ptr_eq(open, popover)).unwrap_or(0);
+            let above: Vec<_> = stack[position..].to_vec();
+            return self.hide_auto_popovers_except(|open| !above.iter().any(|node| Rc::ptr_eq(node, open)));
+        }
+        self.remove_from_top_layer(popover);
+        vec![popover.clone()]
+    }
+
+    /// Light dismiss: hide the open auto popovers that don't contain
+    /// `target`, or with no target (Escape) just the topmost one. Returns
+    /// the popovers hidden.
+    pub fn light_dismiss_popovers(&self, target: Option<&Rc<Node>>) -> Vec<Rc<Node>> {
+        match target {
+            Some(target) => self.hide_auto_popovers_except(|open| open.is_shadow_including_inclusive_ancestor_of(target)),
+            None => match self.auto_popovers().pop() {
+                Some(topmost) => self.hide_popover(&topmost),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    /// Hide every open auto popover, as opening a modal dialog does.
+    /// Returns the popovers hidden.
+    pub fn hide_all_popovers(&self) -> Vec<Rc<Node>> {
+        self.hide_auto_popovers_except(|_| false)
+    }
+
+    /// Hide the open auto popovers `keep` rejects, topmost first.
+    fn hide_auto_popovers_except(&self, keep: impl Fn(&Rc<Node>) -> bool) -> Vec<Rc<Node>> {
+        let mut hidden = Vec::new();
+        for open in self.auto_popovers().into_iter().rev() {
+            if !keep(&open) {
+                self.remove_from_top_layer(&open);
+                hidden.push(open);
+            }
+        }
+        hidden
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_popovers_stack_and_light_dismiss() {
+        let doc = Document::parse_html(
+            r#"<button id="b" popovertarget="menu">Menu</button>
+               <div id="menu" popover><div id="sub" popover="auto"><span id="item">Item</span></div></div>
+               <div id="other" popover></div><div id="pinned" popover="manual"></div><p id="p">Text</p>"#,
+        )
+        .unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+        let ids = |nodes: Vec<Rc<Node>>| nodes.iter().map(|node| node.id).collect::<Vec<_>>();
+
+        let (target, action) = node("b").popover_target(&doc).unwrap();
+        assert!(Rc::ptr_eq(&target, &node("menu")));
+        assert_eq!(action, PopoverTargetAction::Toggle);
+        assert!(node("p").popover_target(&doc).is_none());
+        assert!(doc.show_popover(&node("p")).is_err());
+
+        doc.show_popover(&node("pinned")).unwrap();
+        doc.show_popover(&node("menu")).unwrap();
+        // A nested popover keeps its ancestor open
+        assert!(doc.show_popover(&node("sub")).unwrap().is_empty());
+        assert_eq!(ids(doc.auto_popovers()), [node("menu").id, node("sub").id]);
+
+        // Clicking inside the nested popover keeps both open
+        assert!(doc.light_dismiss_popovers(Some(&node("item"))).is_empty());
+        // An unrelated auto popover replaces the stack
+        assert_eq!(ids(doc.show_popover(&node("other")).unwrap()), [node("sub").id, node("menu").id]);
+        assert_eq!(ids(doc.light_dismiss_popovers(Some(&node("p")))), [node("other").id]);
+        assert!(node("pinned").is_popover_open());
+
+        doc.show_popover(&node("menu")).unwrap();
+        doc.show_popover(&node("sub")).unwrap();
+        assert_eq!(ids(doc.light_dismiss_popovers(None)), [node("sub").id]);
+        assert_eq!(ids(doc.hide_popover(&node("menu"))), [node("menu").id]);
+        doc.show_popover(&node("other")).unwrap();
+        assert_eq!(ids(doc.hide_all_popovers()), [node("other").id]);
+        assert_eq!(ids(doc.hide_popover(&node("pinned"))), [node("pinned").id]);
+        assert!(doc.top_layer().is_empty());
+    }
+}
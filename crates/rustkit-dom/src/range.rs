@@ -0,0 +1,597 @@
+//! # Ranges
+//!
+//! DOM `Range`: a span of the tree between two boundary points, used by
+//! selection and editing. Offsets into character data count characters
+//! rather than UTF-16 code units.
+//!
+//! Text nodes are immutable, so operations that change a partially selected
+//! text node replace it with a new node holding the remaining data.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{Document, DomError, Node, NodeType};
+
+/// A position in the tree: before the `offset`th child of `node`, or before
+/// its `offset`th character for character data.
+#[derive(Debug, Clone)]
+pub struct BoundaryPoint {
+    pub node: Rc<Node>,
+    pub offset: usize,
+}
+
+impl BoundaryPoint {
+    /// Create a boundary point.
+    pub fn new(node: Rc<Node>, offset: usize) -> Self {
+        Self { node, offset }
+    }
+
+    /// Order two points in tree order, or `None` if they are in different trees.
+    pub fn compare(&self, other: &BoundaryPoint) -> Option<Ordering> {
+        let (root, mut path) = tree_path(&self.node);
+        let (other_root, mut other_path) = tree_path(&other.node);
+        if !Rc::ptr_eq(&root, &other_root) {
+            return None;
+        }
+        // A point sits between children, so it sorts before the child at its
+        // offset and everything inside it
+        path.push(self.offset);
+        other_path.push(other.offset);
+        Some(path.cmp(&other_path))
+    }
+}
+
+/// A DOM range (`document.createRange()`).
+#[derive(Debug, Clone)]
+pub struct Range {
+    start: BoundaryPoint,
+    end: BoundaryPoint,
+}
+
+impl Range {
+    /// Create a range collapsed at the start of `document`.
+    pub fn new(document: &Document) -> Self {
+        let point = BoundaryPoint::new(document.root().clone(), 0);
+        Self {
+            start: point.clone(),
+            end: point,
+        }
+    }
+
+    /// The start boundary point.
+    pub fn start(&self) -> &BoundaryPoint {
+        &self.start
+    }
+
+    /// The end boundary point.
+    pub fn end(&self) -> &BoundaryPoint {
+        &self.end
+    }
+
+    /// Whether the start and end are the same point.
+    pub fn collapsed(&self) -> bool {
+        Rc::ptr_eq(&self.start.node, &self.end.node) && self.start.offset == self.end.offset
+    }
+
+    /// The deepest node containing both boundary points.
+    pub fn common_ancestor_container(&self) -> Rc<Node> {
+        let mut container = self.start.node.clone();
+        while !is_inclusive_ancestor(&container, &self.end.node) {
+            match container.parent() {
+                Some(parent) => container = parent,
+                None => break,
+            }
+        }
+        container
+    }
+
+    /// Move the start. An end before the new start, or in another tree,
+    /// collapses onto it.
+    pub fn set_start(&mut self, node: &Rc<Node>, offset: usize) -> Result<(), DomError> {
+        let point = checked_point(node, offset)?;
+        if !matches!(point.compare(&self.end), Some(Ordering::Less | Ordering::Equal)) {
+            self.end = point.clone();
+        }
+        self.start = point;
+        Ok(())
+    }
+
+    /// Move the end. A start after the new end, or in another tree,
+    /// collapses onto it.
+    pub fn set_end(&mut self, node: &Rc<Node>, offset: usize) -> Result<(), DomError> {
+        let point = checked_point(node, offset)?;
+        if !matches!(self.start.compare(&point), Some(Ordering::Less | Ordering::Equal)) {
+            self.start = point.clone();
+        }
+        self.end = point;
+        Ok(())
+    }
+
+    /// Start the range just before `node`.
+    pub fn set_start_before(&mut self, node: &Rc<Node>) -> Result<(), DomError> {
+        let (parent, index) = parent_and_index(node)?;
+        self.set_start(&parent, index)
+    }
+
+    /// Start the range just after `node`.
+    pub fn set_start_after(&mut self, node: &Rc<Node>) -> Result<(), DomError> {
+        let (parent, index) = parent_and_index(node)?;
+        self.set_start(&parent, index + 1)
+    }
+
+    /// End the range just before `node`.
+    pub fn set_end_before(&mut self, node: &Rc<Node>) -> Result<(), DomError> {
+        let (parent, index) = parent_and_index(node)?;
+        self.set_end(&parent, index)
+    }
+
+    /// End the range just after `node`.
+    pub fn set_end_after(&mut self, node: &Rc<Node>) -> Result<(), DomError> {
+        let (parent, index) = parent_and_index(node)?;
+        self.set_end(&parent, index + 1)
+    }
+
+    /// Collapse onto the start, or onto the end.
+    pub fn collapse(&mut self, to_start: bool) {
+        if to_start {
+            self.end = self.start.clone();
+        } else {
+            self.start = self.end.clone();
+        }
+    }
+
+    /// Select `node` itself.
+    pub fn select_node(&mut self, node: &Rc<Node>) -> Result<(), DomError> {
+        let (parent, index) = parent_and_index(node)?;
+        self.start = BoundaryPoint::new(parent.clone(), index);
+        self.end = BoundaryPoint::new(parent, index + 1);
+        Ok(())
+    }
+
+    /// Select everything inside `node`.
+    pub fn select_node_contents(&mut self, node: &Rc<Node>) -> Result<(), DomError> {
+        if matches!(node.node_type, NodeType::DocumentType { .. }) {
+            return Err(DomError::InvalidOperation("cannot select the contents of a doctype".into()));
+        }
+        self.start = BoundaryPoint::new(node.clone(), 0);
+        self.end = BoundaryPoint::new(node.clone(), node_length(node));
+        Ok(())
+    }
+
+    /// Whether the point (`node`, `offset`) lies within the range.
+    pub fn is_point_in_range(&self, node: &Rc<Node>, offset: usize) -> bool {
+        if offset > node_length(node) {
+            return false;
+        }
+        let point = BoundaryPoint::new(node.clone(), offset);
+        matches!(point.compare(&self.start), Some(Ordering::Greater | Ordering::Equal))
+            && matches!(point.compare(&self.end), Some(Ordering::Less | Ordering::Equal))
+    }
+
+    /// Whether any part of `node` lies within the range.
+    pub fn intersects_node(&self, node: &Rc<Node>) -> bool {
+        let Ok((parent, index)) = parent_and_index(node) else {
+            return Rc::ptr_eq(&node.root_node(), &self.start.node.root_node());
+        };
+        BoundaryPoint::new(parent.clone(), index).compare(&self.end) == Some(Ordering::Less)
+            && BoundaryPoint::new(parent, index + 1).compare(&self.start) == Some(Ordering::Greater)
+    }
+
+    /// Whether all of `node` lies within the range.
+    pub fn contains_node(&self, node: &Rc<Node>) -> bool {
+        BoundaryPoint::new(node.clone(), 0).compare(&self.start) == Some(Ordering::Greater)
+            && BoundaryPoint::new(node.clone(), node_length(node)).compare(&self.end) == Some(Ordering::Less)
+    }
+
+    /// Every node wholly inside the range, in tree order.
+    pub fn contained_nodes(&self) -> Vec<Rc<Node>> {
+        let mut nodes = Vec::new();
+        self.collect_contained(&self.common_ancestor_container(), &mut nodes);
+        nodes
+    }
+
+    fn collect_contained(&self, node: &Rc<Node>, nodes: &mut Vec<Rc<Node>>) {
+        for child in node.children() {
+            if self.contains_node(&child) {
+                nodes.push(child.clone());
+            }
+            self.collect_contained(&child, nodes);
+        }
+    }
+
+    /// Copy the range's contents into a new fragment.
+    pub fn clone_contents(&self, document: &mut Document) -> Rc<Node> {
+        self.clone().process_contents(document, false)
+    }
+
+    /// Move the range's contents into a new fragment, collapsing the range
+    /// where they were.
+    pub fn extract_contents(&mut self, document: &mut Document) -> Rc<Node> {
+        self.process_contents(document, true)
+    }
+
+    /// Remove the range's contents, collapsing the range where they were.
+    pub fn delete_contents(&mut self, document: &mut Document) {
+        self.process_contents(document, true);
+    }
+
+    /// Insert `node` at the start of the range, splitting a text node if
+    /// the range starts inside one.
+    pub fn insert_node(&mut self, document: &mut Document, node: Rc<Node>) -> Result<(), DomError> {
+        if is_inclusive_ancestor(&node, &self.start.node) {
+            return Err(DomError::InvalidOperation("cannot insert a node into itself".into()));
+        }
+
+        let was_collapsed = self.collapsed();
+        let (parent, reference) = match character_data(&self.start.node) {
+            Some(data) => {
+                let (parent, _) = parent_and_index(&self.start.node)?;
+                let offset = self.start.offset;
+                let split = self.start.node.clone();
+                let tail = with_data(document, &split, &char_slice(data, offset, usize::MAX));
+                let head = replace_data(document, &split, &char_slice(data, 0, offset));
+                insert_after(&parent, tail.clone(), &head);
+                self.start = BoundaryPoint::new(head, offset);
+                if Rc::ptr_eq(&self.end.node, &split) {
+                    self.end = BoundaryPoint::new(tail.clone(), self.end.offset - offset);
+                }
+                (parent, Some(tail))
+            }
+            None => (self.start.node.clone(), self.start.node.children().get(self.start.offset).cloned()),
+        };
+        let reference = match reference {
+            Some(reference) if Rc::ptr_eq(&reference, &node) => node.next_sibling(),
+            other => other,
+        };
+
+        let inserted = match &node.node_type {
+            NodeType::DocumentFragment => node_length(&node),
+            _ => 1,
+        };
+        node.remove_from_parent();
+        let offset = match &reference {
+            Some(reference) => child_index(reference),
+            None => node_length(&parent),
+        };
+        match reference {
+            Some(reference) => parent.insert_before(node, reference),
+            None => parent.append_child(node),
+        }
+        if was_collapsed {
+            self.end = BoundaryPoint::new(parent, offset + inserted);
+        }
+        Ok(())
+    }
+
+    /// The shared walk behind `cloneContents()` and `extractContents()`.
+    fn process_contents(&mut self, document: &mut Document, extract: bool) -> Rc<Node> {
+        let fragment = document.create_document_fragment();
+        if self.collapsed() {
+            return fragment;
+        }
+        let start = self.start.clone();
+        let end = self.end.clone();
+
+        // A range within a single text node
+        if Rc::ptr_eq(&start.node, &end.node) {
+            if let Some(data) = character_data(&start.node) {
+                fragment.append_child(with_data(document, &start.node, &char_slice(data, start.offset, end.offset)));
+                if extract {
+                    let remaining = char_slice(data, 0, start.offset) + &char_slice(data, end.offset, usize::MAX);
+                    let node = replace_data(document, &start.node, &remaining);
+                    self.start = BoundaryPoint::new(node, start.offset);
+                    self.end = self.start.clone();
+                }
+                return fragment;
+            }
+        }
+
+        let common = self.common_ancestor_container();
+        let first_partial = (!is_inclusive_ancestor(&start.node, &end.node))
+            .then(|| child_containing(&common, &start.node))
+            .flatten();
+        let last_partial = (!is_inclusive_ancestor(&end.node, &start.node))
+            .then(|| child_containing(&common, &end.node))
+            .flatten();
+        let contained: Vec<Rc<Node>> = common.children().into_iter().filter(|c| self.contains_node(c)).collect();
+
+        // Where the range collapses once its contents are gone: just after
+        // the highest ancestor of the start that doesn't hold the end
+        let collapse_point = if is_inclusive_ancestor(&start.node, &end.node) {
+            start.clone()
+        } else {
+            let mut reference = start.node.clone();
+            loop {
+                let Some(parent) = reference.parent() else {
+                    break start.clone();
+                };
+                if is_inclusive_ancestor(&parent, &end.node) {
+                    break BoundaryPoint::new(parent, child_index(&reference) + 1);
+                }
+                reference = parent;
+            }
+        };
+
+        if let Some(child) = first_partial {
+            if let Some(data) = character_data(&child) {
+                fragment.append_child(with_data(document, &child, &char_slice(data, start.offset, usize::MAX)));
+                if extract {
+                    replace_data(document, &child, &char_slice(data, 0, start.offset));
+                }
+            } else {
+                let clone = document.clone_node(&child, false);
+                fragment.append_child(clone.clone());
+                let mut subrange = Range {
+                    start: start.clone(),
+                    end: BoundaryPoint::new(child.clone(), node_length(&child)),
+                };
+                clone.append_child(subrange.process_contents(document, extract));
+            }
+        }
+
+        for child in contained {
+            if extract {
+                child.remove_from_parent();
+                fragment.append_child(child);
+            } else {
+                fragment.append_child(document.clone_node(&child, true));
+            }
+        }
+
+        if let Some(child) = last_partial {
+            if let Some(data) = character_data(&child) {
+                fragment.append_child(with_data(document, &child, &char_slice(data, 0, end.offset)));
+                if extract {
+                    replace_data(document, &child, &char_slice(data, end.offset, usize::MAX));
+                }
+            } else {
+                let clone = document.clone_node(&child, false);
+                fragment.append_child(clone.clone());
+                let mut subrange = Range {
+                    start: BoundaryPoint::new(child.clone(), 0),
+                    end: end.clone(),
+                };
+                clone.append_child(subrange.process_contents(document, extract));
+            }
+        }
+
+        if extract {
+            self.start = collapse_point;
+            self.end = self.start.clone();
+        }
+        fragment
+    }
+}
+
+/// The selected text, as `Range.toString()` returns it.
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if Rc::ptr_eq(&self.start.node, &self.end.node) && self.start.node.is_text() {
+            let data = character_data(&self.start.node).unwrap_or_default();
+            return f.write_str(&char_slice(data, self.start.offset, self.end.offset));
+        }
+        if self.start.node.is_text() {
+            let data = character_data(&self.start.node).unwrap_or_default();
+            f.write_str(&char_slice(data, self.start.offset, usize::MAX))?;
+        }
+        for node in self.contained_nodes() {
+            if let NodeType::Text(text) = &node.node_type {
+                f.write_str(text)?;
+            }
+        }
+        if self.end.node.is_text() {
+            let data = character_data(&self.end.node).unwrap_or_default();
+            f.write_str(&char_slice(data, 0, self.end.offset))?;
+        }
+        Ok(())
+    }
+}
+
+/// The data of text, comment, and processing instruction nodes.
+fn character_data(node: &Node) -> Option<&str> {
+    match &node.node_type {
+        NodeType::Text(data) | NodeType::Comment(data) | NodeType::ProcessingInstruction { data, .. } => Some(data),
+        _ => None,
+    }
+}
+
+/// A node's length: characters for character data, children otherwise.
+pub(crate) fn node_length(node: &Node) -> usize {
+    match &node.node_type {
+        NodeType::DocumentType { .. } => 0,
+        _ => match character_data(node) {
+            Some(data) => data.chars().count(),
+            None => node.children.borrow().len(),
+        },
+    }
+}
+
+fn char_slice(data: &str, start: usize, end: usize) -> String {
+    data.chars().skip(start).take(end.saturating_sub(start)).collect()
+}
+
+/// A detached copy of character data node `node` holding `data`.
+fn with_data(document: &mut Document, node: &Node, data: &str) -> Rc<Node> {
+    let node_type = match &node.node_type {
+        NodeType::Comment(_) => NodeType::Comment(data.to_string()),
+        NodeType::ProcessingInstruction { target, .. } => NodeType::ProcessingInstruction {
+            target: target.clone(),
+            data: data.to_string(),
+        },
+        _ => NodeType::Text(data.to_string()),
+    };
+    document.create_node(node_type)
+}
+
+/// Swap character data node `node` for a copy holding `data`.
+fn replace_data(document: &mut Document, node: &Rc<Node>, data: &str) -> Rc<Node> {
+    let replacement = with_data(document, node, data);
+    if let Some(parent) = node.parent() {
+        insert_after(&parent, replacement.clone(), node);
+        node.remove_from_parent();
+    }
+    replacement
+}
+
+fn insert_after(parent: &Rc<Node>, node: Rc<Node>, reference: &Rc<Node>) {
+    match reference.next_sibling() {
+        Some(next) => parent.insert_before(node, next),
+        None => parent.append_child(node),
+    }
+}
+
+fn checked_point(node: &Rc<Node>, offset: usize) -> Result<BoundaryPoint, DomError> {
+    if matches!(node.node_type, NodeType::DocumentType { .. }) {
+        return Err(DomError::InvalidOperation("a range cannot start or end in a doctype".into()));
+    }
+    if offset > node_length(node) {
+        return Err(DomError::InvalidOperation(format!("offset {offset} is past the end of the node")));
+    }
+    Ok(BoundaryPoint::new(node.clone(), offset))
+}
+
+fn parent_and_index(node: &Rc<Node>) -> Result<(Rc<Node>, usize), DomError> {
+    let parent = node
+        .parent()
+        .ok_or_else(|| DomError::InvalidOperation("node has no parent".into()))?;
+    Ok((parent, child_index(node)))
+}
+
+fn child_index(node: &Rc<Node>) -> usize {
+    node.parent()
+        .and_then(|parent| parent.children.borrow().iter().position(|c| Rc::ptr_eq(c, node)))
+        .unwrap_or(0)
+}
+
+fn is_inclusive_ancestor(ancestor: &Rc<Node>, node: &Rc<Node>) -> bool {
+    let mut current = Some(node.clone());
+    while let Some(n) = current {
+        if Rc::ptr_eq(&n, ancestor) {
+            return true;
+        }
+        current = n.parent();
+    }
+    false
+}
+
+/// The child of `ancestor` that contains `node`.
+fn child_containing(ancestor: &Rc<Node>, node: &Rc<Node>) -> Option<Rc<Node>> {
+    let mut current = node.clone();
+    loop {
+        let parent = current.parent()?;
+        if Rc::ptr_eq(&parent, ancestor) {
+            return Some(current);
+        }
+        current = parent;
+    }
+}
+
+/// The root of `node`'s tree and the child indices leading down to it.
+fn tree_path(node: &Rc<Node>) -> (Rc<Node>, Vec<usize>) {
+    let mut path = Vec::new();
+    let mut current = node.clone();
+    while let Some(parent) = current.parent() {
+        path.push(child_index(&current));
+        current = parent;
+    }
+    path.reverse();
+    (current, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paragraph(doc: &Document) -> Rc<Node> {
+        doc.get_elements_by_tag_name("p").remove(0)
+    }
+
+    #[test]
+    fn test_set_start_and_end_keep_order() {
+        let doc = Document::parse_html("<p>one <b>two</b> three</p>").unwrap();
+        let p = paragraph(&doc);
+        let mut range = Range::new(&doc);
+
+        range.set_start(&p, 1).unwrap();
+        assert!(range.collapsed(), "an end before the new start collapses onto it");
+
+        range.set_end(&p, 3).unwrap();
+        assert!(!range.collapsed());
+        assert!(Rc::ptr_eq(&range.common_ancestor_container(), &p));
+
+        range.set_end(&p, 0).unwrap();
+        assert!(range.collapsed(), "a start after the new end collapses onto it");
+
+        assert!(range.set_start(&p, 4).is_err());
+    }
+
+    #[test]
+    fn test_range_text_and_containment() {
+        let doc = Document::parse_html("<p>one <b>two</b> three</p>").unwrap();
+        let p = paragraph(&doc);
+        let children = p.children();
+        let bold = children[1].clone();
+
+        let mut range = Range::new(&doc);
+        range.set_start(&children[0], 2).unwrap();
+        range.set_end(&children[2], 3).unwrap();
+
+        assert_eq!(range.to_string(), "e two th");
+        assert!(range.contains_node(&bold));
+        assert!(!range.contains_node(&children[0]));
+        assert!(range.intersects_node(&children[0]));
+        assert!(range.is_point_in_range(&bold, 0));
+        assert!(!range.is_point_in_range(&children[0], 1));
+
+        range.select_node_contents(&bold).unwrap();
+        assert_eq!(range.to_string(), "two");
+    }
+
+    #[test]
+    fn test_extract_contents_splits_partial_nodes() {
+        let mut doc = Document::parse_html("<p>one <b>two</b> three</p>").unwrap();
+        let p = paragraph(&doc);
+        let children = p.children();
+
+        let mut range = Range::new(&doc);
+        range.set_start(&children[0], 2).unwrap();
+        range.set_end(&children[1].first_child().unwrap(), 1).unwrap();
+
+        let fragment = range.extract_contents(&mut doc);
+        let extracted: Vec<String> = fragment.children().iter().map(|c| c.text_content()).collect();
+        assert_eq!(extracted, vec!["e ", "t"]);
+        assert_eq!(fragment.children()[1].tag_name(), Some("b"));
+
+        assert_eq!(p.text_content(), "onwo three");
+        assert!(range.collapsed());
+        assert!(Rc::ptr_eq(&range.start().node, &p));
+        assert_eq!(range.start().offset, 1);
+
+        // Cloning leaves the tree alone
+        range.select_node_contents(&p).unwrap();
+        let copy = range.clone_contents(&mut doc);
+        assert_eq!(copy.text_content(), "onwo three");
+        assert_eq!(p.text_content(), "onwo three");
+    }
+
+    #[test]
+    fn test_insert_node_splits_text() {
+        let mut doc = Document::parse_html("<p>hello world</p>").unwrap();
+        let p = paragraph(&doc);
+
+        let mut range = Range::new(&doc);
+        range.set_start(&p.first_child().unwrap(), 5).unwrap();
+        range.collapse(true);
+
+        let em = doc.create_element("em", vec![]);
+        em.append_child(doc.create_text_node("!"));
+        range.insert_node(&mut doc, em.clone()).unwrap();
+
+        let children = p.children();
+        assert_eq!(children.len(), 3);
+        assert!(Rc::ptr_eq(&children[1], &em));
+        assert_eq!(p.text_content(), "hello! world");
+        assert_eq!(range.to_string(), "!");
+    }
+}
@@ -0,0 +1,252 @@
+//! # Text fragments
+//!
+//! A URL fragment can carry text directives after `:~:`, as in
+//! `#intro:~:text=quick,-brown&text=lazy%20dog`, naming text for the
+//! browser to scroll to and highlight. Each directive is
+//! `[prefix-,]start[,end][,-suffix]`: it matches `start` (through `end`, if
+//! given) where the text around it matches the prefix and suffix.
+//!
+//! Matching is case-insensitive, collapses runs of whitespace, only starts
+//! and ends at word boundaries and never crosses into another block.
+
+use std::rc::Rc;
+
+use crate::{Document, Node, NodeType, Range};
+
+/// Elements whose text never matches.
+const SKIPPED: &[&str] = &["head", "script", "style", "noscript", "template", "select", "textarea"];
+
+/// Elements that start a new block of text.
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "details", "dialog", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header",
+    "hr", "li", "main", "nav", "ol", "p", "pre", "section", "summary", "table", "td", "th", "tr", "ul",
+];
+
+/// One `text=` directive of a URL fragment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextDirective {
+    pub prefix: Option<String>,
+    pub start: String,
+    pub end: Option<String>,
+    pub suffix: Option<String>,
+}
+
+impl TextDirective {
+    /// The text directives of a URL fragment (without the `#`). Malformed
+    /// directives are skipped.
+    pub fn parse_fragment(fragment: &str) -> Vec<TextDirective> {
+        let Some((_, directives)) = fragment.split_once(":~:") else {
+            return Vec::new();
+        };
+        directives
+            .split('&')
+            .filter_map(|directive| directive.strip_prefix("text="))
+            .filter_map(Self::parse)
+            .collect()
+    }
+
+    /// Parse the value of a `text=` directive. Terms are percent-encoded,
+    /// so a literal comma or dash in one is `%2C` or `%2D`.
+    pub fn parse(value: &str) -> Option<TextDirective> {
+        let mut terms: Vec<&str> = value.split(',').collect();
+        let mut prefix = None;
+        if terms.len() > 1 {
+            if let Some(term) = terms[0].strip_suffix('-') {
+                prefix = Some(decode(term)?);
+                terms.remove(0);
+            }
+        }
+        let mut suffix = None;
+        if terms.len() > 1 {
+            if let Some(term) = terms[terms.len() - 1].strip_prefix('-') {
+                suffix = Some(decode(term)?);
+                terms.pop();
+            }
+        }
+        let (start, end) = match terms[..] {
+            [start] => (decode(start)?, None),
+            [start, end] => (decode(start)?, Some(decode(end)?)),
+            _ => return None,
+        };
+        Some(TextDirective { prefix, start, end, suffix })
+    }
+}
+
+fn decode(term: &str) -> Option<String> {
+    let term = urlencoding::decode(term).ok()?;
+    (!term.trim().is_empty()).then(|| term.into_owned())
+}
+
+/// A character of a block's text and where it comes from.
+struct TextChar {
+    ch: char,
+    node: Rc<Node>,
+    offset: usize,
+}
+
+impl Document {
+    /// The first text in the document `directive` matches.
+    pub fn find_text_directive(&self, directive: &TextDirective) -> Option<Range> {
+        let start = normalize(&directive.start);
+        let end = directive.end.as_deref().map(normalize);
+        let prefix = directive.prefix.as_deref().map(normalize);
+        let suffix = directive.suffix.as_deref().map(normalize);
+
+        let mut blocks = vec![Vec::new()];
+        collect_text(self.root(), &mut blocks);
+        for block in &blocks {
+            let mut from = 0;
+            while let Some(found) = find_term(block, &start, from) {
+                from = found + 1;
+                if prefix.as_ref().is_some_and(|prefix| !precedes(block, prefix, found)) {
+                    continue;
+                }
+                let last = match &end {
+                    Some(end) => match find_term(block, end, found + start.len()) {
+                        Some(end_found) => end_found + end.len(),
+                        // No later start can have an end either
+                        None => break,
+                    },
+                    None => found + start.len(),
+                };
+                if suffix.as_ref().is_some_and(|suffix| !follows(block, suffix, last)) {
+                    continue;
+                }
+
+                let (first, last) = (&block[found], &block[last - 1]);
+                let mut range = Range::new(self);
+                range.set_start(&first.node, first.offset).ok()?;
+                range.set_end(&last.node, last.offset + 1).ok()?;
+                return Some(range);
+            }
+        }
+        None
+    }
+}
+
+/// Lowercase `term` and collapse its whitespace.
+fn normalize(term: &str) -> Vec<char> {
+    term.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Append the text under `node` to `blocks`, starting a new block at each
+/// block-level element.
+fn collect_text(node: &Rc<Node>, blocks: &mut Vec<Vec<TextChar>>) {
+    match &node.node_type {
+        NodeType::Text(text) => {
+            let block = blocks.last_mut().expect("there is always a block");
+            for (offset, ch) in text.chars().enumerate() {
+                if ch.is_whitespace() {
+                    if block.last().is_some_and(|last| last.ch != ' ') {
+                        block.push(TextChar { ch: ' ', node: node.clone(), offset });
+                    }
+                    continue;
+                }
+                for ch in ch.to_lowercase() {
+                    block.push(TextChar { ch, node: node.clone(), offset });
+                }
+            }
+        }
+        NodeType::Element { .. } => {
+            let tag = node.tag_name().unwrap_or_default().to_ascii_lowercase();
+            if SKIPPED.contains(&tag.as_str()) {
+                return;
+            }
+            let is_block = BLOCKS.contains(&tag.as_str());
+            if is_block {
+                blocks.push(Vec::new());
+            }
+            for child in node.children() {
+                collect_text(&child, blocks);
+            }
+            if is_block {
+                blocks.push(Vec::new());
+            }
+        }
+        _ => {
+            for child in node.children() {
+                collect_text(&child, blocks);
+            }
+        }
+    }
+}
+
+fn is_word_char(block: &[TextChar], index: usize) -> bool {
+    block.get(index).is_some_and(|c| c.ch.is_alphanumeric())
+}
+
+/// Index of the first match of `term` in `block` at or after `from` that
+/// starts and ends at word boundaries.
+fn find_term(block: &[TextChar], term: &[char], from: usize) -> Option<usize> {
+    if term.is_empty() || block.len() < term.len() {
+        return None;
+    }
+    (from..=block.len() - term.len()).find(|&index| {
+        block[index..index + term.len()].iter().zip(term).all(|(c, ch)| c.ch == *ch)
+            && (index == 0 || !is_word_char(block, index - 1))
+            && !is_word_char(block, index + term.len())
+    })
+}
+
+/// Whether `term` ends right before `index`, give or take a space.
+fn precedes(block: &[TextChar], term: &[char], index: usize) -> bool {
+    let end = if index > 0 && block[index - 1].ch == ' ' { index - 1 } else { index };
+    end.checked_sub(term.len())
+        .is_some_and(|start| find_term(&block[..end], term, start) == Some(start))
+}
+
+/// Whether `term` starts right at `index`, give or take a space.
+fn follows(block: &[TextChar], term: &[char], index: usize) -> bool {
+    let start = if block.get(index).is_some_and(|c| c.ch == ' ') { index + 1 } else { index };
+    find_term(block, term, start) == Some(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_directives() {
+        let directives = TextDirective::parse_fragment("intro:~:text=the-,quick%2C%20brown,fox,-jumps&text=lazy&other=1");
+        assert_eq!(
+            directives,
+            [
+                TextDirective {
+                    prefix: Some("the".into()),
+                    start: "quick, brown".into(),
+                    end: Some("fox".into()),
+                    suffix: Some("jumps".into()),
+                },
+                TextDirective { prefix: None, start: "lazy".into(), end: None, suffix: None },
+            ]
+        );
+        assert!(TextDirective::parse_fragment("intro").is_empty());
+        assert!(TextDirective::parse("a,b,c").is_none());
+        assert!(TextDirective::parse("").is_none());
+    }
+
+    #[test]
+    fn test_find_text_directive() {
+        let doc = Document::parse_html(
+            r#"<p id="one">A cat sat. The  <b>Cat</b> sat down.</p><p id="two">cat sat on the mat</p>
+               <script>cat sat</script>"#,
+        )
+        .unwrap();
+        let found = |value: &str| doc.find_text_directive(&TextDirective::parse(value).unwrap()).map(|r| r.to_string());
+
+        assert_eq!(found("cat%20sat").as_deref(), Some("cat sat"));
+        // The prefix skips the first match, and the match crosses elements
+        assert_eq!(found("the-,cat%20sat").as_deref(), Some("Cat sat"));
+        assert_eq!(found("cat,mat").as_deref(), Some("cat sat on the mat"));
+        assert_eq!(found("sat,-on").as_deref(), Some("sat"));
+        // Neither within words nor across blocks
+        assert_eq!(found("ca"), None);
+        assert_eq!(found("down%20cat"), None);
+    }
+}
@@ -0,0 +1,469 @@
+//! # Traversal
+//!
+//! `TreeWalker` and `NodeIterator`: filtered walks over a subtree, as created
+//! by `document.createTreeWalker()` and `document.createNodeIterator()`.
+
+use std::fmt;
+use std::rc::Rc;
+
+use crate::Node;
+
+/// A filter callback's verdict on a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterResult {
+    /// Visit the node (`FILTER_ACCEPT`).
+    Accept,
+    /// Skip the node and, for tree walkers, its descendants (`FILTER_REJECT`).
+    Reject,
+    /// Skip the node but not its descendants (`FILTER_SKIP`).
+    Skip,
+}
+
+/// A filter's `acceptNode` callback.
+pub type FilterCallback = Box<dyn Fn(&Rc<Node>) -> FilterResult>;
+
+/// Which nodes a traversal visits: a `whatToShow` mask, and an optional
+/// callback applied to nodes the mask lets through.
+pub struct NodeFilter {
+    what_to_show: u32,
+    callback: Option<FilterCallback>,
+}
+
+impl NodeFilter {
+    pub const SHOW_ALL: u32 = 0xFFFF_FFFF;
+    pub const SHOW_ELEMENT: u32 = 0x1;
+    pub const SHOW_TEXT: u32 = 0x4;
+    pub const SHOW_PROCESSING_INSTRUCTION: u32 = 0x40;
+    pub const SHOW_COMMENT: u32 = 0x80;
+    pub const SHOW_DOCUMENT: u32 = 0x100;
+    pub const SHOW_DOCUMENT_TYPE: u32 = 0x200;
+    pub const SHOW_DOCUMENT_FRAGMENT: u32 = 0x400;
+
+    /// Create a filter showing the node types in `what_to_show`.
+    pub fn new(what_to_show: u32) -> Self {
+        Self {
+            what_to_show,
+            callback: None,
+        }
+    }
+
+    /// Also run `callback` on nodes of a shown type.
+    pub fn with_callback(mut self, callback: impl Fn(&Rc<Node>) -> FilterResult + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Filter a node.
+    pub fn filter(&self, node: &Rc<Node>) -> FilterResult {
+        if self.what_to_show & (1 << (node.node_type_code() - 1)) == 0 {
+            return FilterResult::Skip;
+        }
+        match &self.callback {
+            Some(callback) => callback(node),
+            None => FilterResult::Accept,
+        }
+    }
+}
+
+impl Default for NodeFilter {
+    fn default() -> Self {
+        Self::new(Self::SHOW_ALL)
+    }
+}
+
+impl fmt::Debug for NodeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeFilter")
+            .field("what_to_show", &format_args!("{:#x}", self.what_to_show))
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+/// Which way a sibling or child step moves.
+#[derive(Clone, Copy)]
+enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    fn child(self, node: &Rc<Node>) -> Option<Rc<Node>> {
+        match self {
+            Direction::Forward => node.first_child(),
+            Direction::Backward => node.last_child(),
+        }
+    }
+
+    fn sibling(self, node: &Rc<Node>) -> Option<Rc<Node>> {
+        match self {
+            Direction::Forward => node.next_sibling(),
+            Direction::Backward => node.previous_sibling(),
+        }
+    }
+}
+
+/// A cursor over the filtered view of a subtree (`TreeWalker`).
+///
+/// Rejected nodes hide their whole subtree; skipped nodes only themselves.
+#[derive(Debug)]
+pub struct TreeWalker {
+    root: Rc<Node>,
+    current: Rc<Node>,
+    filter: NodeFilter,
+}
+
+impl TreeWalker {
+    /// Create a walker over `root`'s subtree, starting at `root`.
+    pub fn new(root: Rc<Node>, filter: NodeFilter) -> Self {
+        Self {
+            current: root.clone(),
+            root,
+            filter,
+        }
+    }
+
+    /// The root of the walked subtree.
+    pub fn root(&self) -> &Rc<Node> {
+        &self.root
+    }
+
+    /// The node the walker is on.
+    pub fn current_node(&self) -> &Rc<Node> {
+        &self.current
+    }
+
+    /// Move the walker to `node`, which need not pass the filter.
+    pub fn set_current_node(&mut self, node: Rc<Node>) {
+        self.current = node;
+    }
+
+    /// The filter the walker applies.
+    pub fn filter(&self) -> &NodeFilter {
+        &self.filter
+    }
+
+    /// Move to the closest accepted ancestor within the root.
+    pub fn parent_node(&mut self) -> Option<Rc<Node>> {
+        let mut node = self.current.clone();
+        while !Rc::ptr_eq(&node, &self.root) {
+            node = node.parent()?;
+            if self.filter.filter(&node) == FilterResult::Accept {
+                self.current = node.clone();
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// Move to the first visible child.
+    pub fn first_child(&mut self) -> Option<Rc<Node>> {
+        self.traverse_children(Direction::Forward)
+    }
+
+    /// Move to the last visible child.
+    pub fn last_child(&mut self) -> Option<Rc<Node>> {
+        self.traverse_children(Direction::Backward)
+    }
+
+    /// Move to the previous visible sibling.
+    pub fn previous_sibling(&mut self) -> Option<Rc<Node>> {
+        self.traverse_siblings(Direction::Backward)
+    }
+
+    /// Move to the next visible sibling.
+    pub fn next_sibling(&mut self) -> Option<Rc<Node>> {
+        self.traverse_siblings(Direction::Forward)
+    }
+
+    /// Move to the previous visible node in tree order.
+    pub fn previous_node(&mut self) -> Option<Rc<Node>> {
+        let mut node = self.current.clone();
+        while !Rc::ptr_eq(&node, &self.root) {
+            let mut sibling = node.previous_sibling();
+            while let Some(previous) = sibling {
+                node = previous;
+                // Descend to the deepest last visible descendant
+                let mut result = self.filter.filter(&node);
+                while result != FilterResult::Reject {
+                    let Some(child) = node.last_child() else {
+                        break;
+                    };
+                    node = child;
+                    result = self.filter.filter(&node);
+                }
+                if result == FilterResult::Accept {
+                    self.current = node.clone();
+                    return Some(node);
+                }
+                sibling = node.previous_sibling();
+            }
+            if Rc::ptr_eq(&node, &self.root) {
+                return None;
+            }
+            node = node.parent()?;
+            if self.filter.filter(&node) == FilterResult::Accept {
+                self.current = node.clone();
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// Move to the next visible node in tree order.
+    pub fn next_node(&mut self) -> Option<Rc<Node>> {
+        let mut node = self.current.clone();
+        let mut result = FilterResult::Accept;
+        loop {
+            while result != FilterResult::Reject {
+                let Some(child) = node.first_child() else {
+                    break;
+                };
+                node = child;
+                result = self.filter.filter(&node);
+                if result == FilterResult::Accept {
+                    self.current = node.clone();
+                    return Some(node);
+                }
+            }
+            node = following_skipping_children(&node, &self.root)?;
+            result = self.filter.filter(&node);
+            if result == FilterResult::Accept {
+                self.current = node.clone();
+                return Some(node);
+            }
+        }
+    }
+
+    fn traverse_children(&mut self, direction: Direction) -> Option<Rc<Node>> {
+        let mut node = direction.child(&self.current);
+        while let Some(mut current) = node {
+            match self.filter.filter(&current) {
+                FilterResult::Accept => {
+                    self.current = current.clone();
+                    return Some(current);
+                }
+                FilterResult::Skip => {
+                    if let Some(child) = direction.child(&current) {
+                        node = Some(child);
+                        continue;
+                    }
+                }
+                FilterResult::Reject => {}
+            }
+            // Climb until a sibling turns up, without leaving the start node
+            node = loop {
+                if let Some(sibling) = direction.sibling(&current) {
+                    break Some(sibling);
+                }
+                let parent = current.parent()?;
+                if Rc::ptr_eq(&parent, &self.root) || Rc::ptr_eq(&parent, &self.current) {
+                    return None;
+                }
+                current = parent;
+            };
+        }
+        None
+    }
+
+    fn traverse_siblings(&mut self, direction: Direction) -> Option<Rc<Node>> {
+        let mut node = self.current.clone();
+        if Rc::ptr_eq(&node, &self.root) {
+            return None;
+        }
+        loop {
+            let mut sibling = direction.sibling(&node);
+            while let Some(current) = sibling {
+                node = current;
+                let result = self.filter.filter(&node);
+                if result == FilterResult::Accept {
+                    self.current = node.clone();
+                    return Some(node);
+                }
+                // Skipped nodes are transparent: look inside them first
+                sibling = direction.child(&node);
+                if result == FilterResult::Reject || sibling.is_none() {
+                    sibling = direction.sibling(&node);
+                }
+            }
+            node = node.parent()?;
+            if Rc::ptr_eq(&node, &self.root) || self.filter.filter(&node) == FilterResult::Accept {
+                return None;
+            }
+        }
+    }
+}
+
+/// A flat, bidirectional iterator over the filtered nodes of a subtree in
+/// tree order (`NodeIterator`).
+///
+/// Rejecting a node skips only that node. The iterator isn't told about
+/// mutations, so it shouldn't outlive changes to the nodes around it.
+#[derive(Debug)]
+pub struct NodeIterator {
+    root: Rc<Node>,
+    reference: Rc<Node>,
+    pointer_before_reference: bool,
+    filter: NodeFilter,
+}
+
+impl NodeIterator {
+    /// Create an iterator over `root`'s subtree, positioned before `root`.
+    pub fn new(root: Rc<Node>, filter: NodeFilter) -> Self {
+        Self {
+            reference: root.clone(),
+            root,
+            pointer_before_reference: true,
+            filter,
+        }
+    }
+
+    /// The root of the iterated subtree.
+    pub fn root(&self) -> &Rc<Node> {
+        &self.root
+    }
+
+    /// The node the iterator is anchored to.
+    pub fn reference_node(&self) -> &Rc<Node> {
+        &self.reference
+    }
+
+    /// Whether the iterator sits before its reference node.
+    pub fn pointer_before_reference_node(&self) -> bool {
+        self.pointer_before_reference
+    }
+
+    /// The filter the iterator applies.
+    pub fn filter(&self) -> &NodeFilter {
+        &self.filter
+    }
+
+    /// Step forward to the next accepted node.
+    pub fn next_node(&mut self) -> Option<Rc<Node>> {
+        self.traverse(Direction::Forward)
+    }
+
+    /// Step back to the previous accepted node.
+    pub fn previous_node(&mut self) -> Option<Rc<Node>> {
+        self.traverse(Direction::Backward)
+    }
+
+    fn traverse(&mut self, direction: Direction) -> Option<Rc<Node>> {
+        let mut node = self.reference.clone();
+        let mut before = self.pointer_before_reference;
+        loop {
+            match direction {
+                Direction::Forward if before => before = false,
+                Direction::Forward => node = following(&node, &self.root)?,
+                Direction::Backward if before => node = preceding(&node, &self.root)?,
+                Direction::Backward => before = true,
+            }
+            if self.filter.filter(&node) == FilterResult::Accept {
+                break;
+            }
+        }
+        self.reference = node.clone();
+        self.pointer_before_reference = before;
+        Some(node)
+    }
+}
+
+/// The node after `node` in tree order, within `root`.
+fn following(node: &Rc<Node>, root: &Rc<Node>) -> Option<Rc<Node>> {
+    node.first_child().or_else(|| following_skipping_children(node, root))
+}
+
+/// The node after `node` and its descendants in tree order, within `root`.
+fn following_skipping_children(node: &Rc<Node>, root: &Rc<Node>) -> Option<Rc<Node>> {
+    let mut current = node.clone();
+    loop {
+        if Rc::ptr_eq(&current, root) {
+            return None;
+        }
+        if let Some(sibling) = current.next_sibling() {
+            return Some(sibling);
+        }
+        current = current.parent()?;
+    }
+}
+
+/// The node before `node` in tree order, within `root`.
+fn preceding(node: &Rc<Node>, root: &Rc<Node>) -> Option<Rc<Node>> {
+    if Rc::ptr_eq(node, root) {
+        return None;
+    }
+    match node.previous_sibling() {
+        Some(mut previous) => {
+            while let Some(child) = previous.last_child() {
+                previous = child;
+            }
+            Some(previous)
+        }
+        None => node.parent(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+
+    fn names(nodes: &[Rc<Node>]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|n| n.tag_name().map(str::to_string).unwrap_or_else(|| n.text_content()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_walker_skip_and_reject() {
+        let doc = Document::parse_html("<div><p>a<i>b</i></p><ul><li>c</li></ul><span>d</span></div>").unwrap();
+        let div = doc.get_elements_by_tag_name("div").remove(0);
+
+        // Skipping <p> surfaces its children; rejecting <ul> hides <li>
+        let filter = NodeFilter::new(NodeFilter::SHOW_ELEMENT).with_callback(|node| match node.tag_name() {
+            Some("p") => FilterResult::Skip,
+            Some("ul") => FilterResult::Reject,
+            _ => FilterResult::Accept,
+        });
+        let mut walker = TreeWalker::new(div.clone(), filter);
+
+        let mut visited = Vec::new();
+        while let Some(node) = walker.next_node() {
+            visited.push(node);
+        }
+        assert_eq!(names(&visited), vec!["i", "span"]);
+
+        let mut visited = Vec::new();
+        while let Some(node) = walker.previous_node() {
+            visited.push(node);
+        }
+        assert_eq!(names(&visited), vec!["i", "div"]);
+
+        let first = walker.first_child().unwrap();
+        assert_eq!(first.tag_name(), Some("i"));
+        assert_eq!(walker.next_sibling().map(|n| names(&[n])), Some(vec!["span".to_string()]));
+        assert!(walker.next_sibling().is_none());
+        assert!(Rc::ptr_eq(&walker.parent_node().unwrap(), &div));
+        assert!(walker.parent_node().is_none());
+    }
+
+    #[test]
+    fn test_node_iterator_text_nodes() {
+        let doc = Document::parse_html("<div>one<b>two</b><!--x-->three</div>").unwrap();
+        let div = doc.get_elements_by_tag_name("div").remove(0);
+        let mut iterator = NodeIterator::new(div, NodeFilter::new(NodeFilter::SHOW_TEXT));
+
+        let mut visited = Vec::new();
+        while let Some(node) = iterator.next_node() {
+            visited.push(node);
+        }
+        assert_eq!(names(&visited), vec!["one", "two", "three"]);
+        assert!(!iterator.pointer_before_reference_node());
+
+        // Turning around revisits the reference node first
+        assert_eq!(iterator.previous_node().map(|n| n.text_content()), Some("three".to_string()));
+        assert_eq!(iterator.previous_node().map(|n| n.text_content()), Some("two".to_string()));
+        assert!(iterator.pointer_before_reference_node());
+    }
+}
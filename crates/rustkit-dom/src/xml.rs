@@ -0,0 +1,620 @@
+//! # XML
+//!
+//! Strict XML parsing, for `application/xhtml+xml` and other XML responses
+//! and `DOMParser`, and XML serialization for `XMLSerializer`.
+//!
+//! There is no error recovery: the first well-formedness error fails the
+//! parse. Document type declarations are skipped, so only the predefined
+//! and numeric character references are known.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{Document, DomError, Node, NodeType};
+
+/// Namespace bound to the `xml` prefix.
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Namespace bound to the `xmlns` prefix.
+const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// Whether a MIME type names an XML document (`text/xml`, `application/xml`,
+/// or any `+xml` type).
+pub fn is_xml_mime_type(mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    matches!(essence.as_str(), "text/xml" | "application/xml") || essence.ends_with("+xml")
+}
+
+impl Document {
+    /// Parse a well-formed XML document.
+    ///
+    /// Elements get the namespace their `xmlns` declarations put them in,
+    /// so an XHTML document's elements are in the HTML namespace.
+    pub fn parse_xml(xml: &str) -> Result<Self, DomError> {
+        let mut parser = XmlParser {
+            input: xml.strip_prefix('\u{feff}').unwrap_or(xml),
+            pos: 0,
+            document: Document::new(),
+            open: Vec::new(),
+            scopes: vec![HashMap::from([
+                ("xml".to_string(), XML_NAMESPACE.to_string()),
+                ("xmlns".to_string(), XMLNS_NAMESPACE.to_string()),
+            ])],
+            text: String::new(),
+        };
+        parser.parse_document()?;
+
+        let mut document = parser.document;
+        let root = document.root.clone();
+        document.move_template_contents(&root);
+        Ok(document)
+    }
+}
+
+struct XmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+    document: Document,
+    /// Elements whose end tag hasn't been seen yet.
+    open: Vec<Rc<Node>>,
+    /// Prefix bindings in scope, innermost last. The default namespace is
+    /// bound to the empty prefix.
+    scopes: Vec<HashMap<String, String>>,
+    /// Character data not yet added to the tree.
+    text: String,
+}
+
+impl<'a> XmlParser<'a> {
+    fn parse_document(&mut self) -> Result<(), DomError> {
+        if self.rest().starts_with("<?xml") && self.rest()[5..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            self.expect_until("?>")?;
+        }
+
+        let mut seen_root = false;
+        let mut seen_doctype = false;
+        while !self.at_end() {
+            self.skip_whitespace();
+            if self.at_end() {
+                break;
+            }
+            if self.rest().starts_with("<!--") {
+                self.parse_comment()?;
+            } else if self.rest().starts_with("<?") {
+                self.parse_processing_instruction()?;
+            } else if self.rest().starts_with("<!DOCTYPE") {
+                if seen_doctype || seen_root {
+                    return Err(self.error("unexpected DOCTYPE"));
+                }
+                seen_doctype = true;
+                self.parse_doctype()?;
+            } else if self.rest().starts_with('<') && !self.rest().starts_with("</") {
+                if seen_root {
+                    return Err(self.error("junk after document element"));
+                }
+                seen_root = true;
+                self.parse_element_content()?;
+            } else {
+                return Err(self.error("content outside the document element"));
+            }
+        }
+
+        if !seen_root {
+            return Err(self.error("no document element"));
+        }
+        Ok(())
+    }
+
+    /// Parse the root element and everything inside it.
+    fn parse_element_content(&mut self) -> Result<(), DomError> {
+        self.parse_start_tag()?;
+        while !self.open.is_empty() {
+            if self.at_end() {
+                let name = self.open.last().and_then(|n| n.tag_name().map(str::to_string)).unwrap_or_default();
+                return Err(self.error(&format!("unclosed element <{name}>")));
+            }
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.parse_end_tag()?;
+            } else if rest.starts_with("<!--") {
+                self.parse_comment()?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let data = self.expect_until("]]>")?;
+                self.text.push_str(data);
+            } else if rest.starts_with("<?") {
+                self.parse_processing_instruction()?;
+            } else if rest.starts_with("<!") {
+                return Err(self.error("unexpected markup declaration"));
+            } else if rest.starts_with('<') {
+                self.parse_start_tag()?;
+            } else {
+                self.parse_char_data()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_start_tag(&mut self) -> Result<(), DomError> {
+        self.pos += 1;
+        let name = self.parse_name()?;
+
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        let self_closing = loop {
+            let had_space = self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                break true;
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break false;
+            }
+            if !had_space {
+                return Err(self.error("expected whitespace between attributes"));
+            }
+            let attr_name = self.parse_name()?;
+            self.skip_whitespace();
+            if !self.eat('=') {
+                return Err(self.error(&format!("attribute {attr_name} has no value")));
+            }
+            self.skip_whitespace();
+            let value = self.parse_attribute_value()?;
+            if attributes.iter().any(|(n, _)| *n == attr_name) {
+                return Err(self.error(&format!("duplicate attribute {attr_name}")));
+            }
+            attributes.push((attr_name, value));
+        };
+
+        // Namespace declarations apply to the element declaring them
+        let mut scope = HashMap::new();
+        for (attr_name, value) in &attributes {
+            if attr_name == "xmlns" {
+                scope.insert(String::new(), value.clone());
+            } else if let Some(prefix) = attr_name.strip_prefix("xmlns:") {
+                if value.is_empty() {
+                    return Err(self.error(&format!("prefix {prefix} bound to the empty namespace")));
+                }
+                scope.insert(prefix.to_string(), value.clone());
+            }
+        }
+        self.scopes.push(scope);
+
+        let namespace = match name.split_once(':') {
+            Some((prefix, _)) => self
+                .lookup_namespace(prefix)
+                .ok_or_else(|| self.error(&format!("unbound prefix {prefix}")))?,
+            None => self.lookup_namespace("").unwrap_or_default(),
+        };
+        for (attr_name, _) in &attributes {
+            if let Some((prefix, _)) = attr_name.split_once(':') {
+                if prefix != "xmlns" && self.lookup_namespace(prefix).is_none() {
+                    return Err(self.error(&format!("unbound prefix {prefix}")));
+                }
+            }
+        }
+
+        self.flush_text();
+        let element = self.document.create_node(NodeType::Element {
+            tag_name: name,
+            namespace,
+            attributes: attributes.into_iter().collect(),
+        });
+        self.current_parent().append_child(element.clone());
+
+        if self_closing {
+            self.scopes.pop();
+        } else {
+            self.open.push(element);
+        }
+        Ok(())
+    }
+
+    fn parse_end_tag(&mut self) -> Result<(), DomError> {
+        self.pos += 2;
+        let name = self.parse_name()?;
+        self.skip_whitespace();
+        if !self.eat('>') {
+            return Err(self.error("expected '>'"));
+        }
+        let open = self.open.last().and_then(|n| n.tag_name().map(str::to_string)).unwrap_or_default();
+        if name != open {
+            return Err(self.error(&format!("</{name}> does not close <{open}>")));
+        }
+        self.flush_text();
+        self.open.pop();
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn parse_char_data(&mut self) -> Result<(), DomError> {
+        let end = self.rest().find('<').unwrap_or(self.rest().len());
+        let raw = &self.input[self.pos..self.pos + end];
+        if raw.contains("]]>") {
+            return Err(self.error("']]>' in character data"));
+        }
+        let text = self.decode_references(raw)?;
+        self.text.push_str(&text);
+        self.pos += end;
+        Ok(())
+    }
+
+    fn parse_comment(&mut self) -> Result<(), DomError> {
+        self.pos += "<!--".len();
+        let data = self.expect_until("-->")?;
+        if data.contains("--") || data.ends_with('-') {
+            return Err(self.error("'--' in comment"));
+        }
+        let data = data.to_string();
+        self.append_leaf(NodeType::Comment(data));
+        Ok(())
+    }
+
+    fn parse_processing_instruction(&mut self) -> Result<(), DomError> {
+        self.pos += 2;
+        let target = self.parse_name()?;
+        if target.eq_ignore_ascii_case("xml") {
+            return Err(self.error("XML declaration not at the start of the document"));
+        }
+        let data = self.expect_until("?>")?.trim_start().to_string();
+        self.append_leaf(NodeType::ProcessingInstruction { target, data });
+        Ok(())
+    }
+
+    fn parse_doctype(&mut self) -> Result<(), DomError> {
+        self.pos += "<!DOCTYPE".len();
+        if !self.skip_whitespace() {
+            return Err(self.error("expected whitespace after DOCTYPE"));
+        }
+        let name = self.parse_name()?;
+        self.skip_whitespace();
+
+        let mut public_id = String::new();
+        let mut system_id = String::new();
+        if self.rest().starts_with("PUBLIC") {
+            self.pos += "PUBLIC".len();
+            self.skip_whitespace();
+            public_id = self.parse_quoted()?;
+            self.skip_whitespace();
+            system_id = self.parse_quoted()?;
+        } else if self.rest().starts_with("SYSTEM") {
+            self.pos += "SYSTEM".len();
+            self.skip_whitespace();
+            system_id = self.parse_quoted()?;
+        }
+        self.skip_whitespace();
+
+        // The internal subset is skipped, not interpreted
+        if self.eat('[') {
+            self.expect_until("]")?;
+            self.skip_whitespace();
+        }
+        if !self.eat('>') {
+            return Err(self.error("expected '>' to end DOCTYPE"));
+        }
+        self.append_leaf(NodeType::DocumentType {
+            name,
+            public_id,
+            system_id,
+        });
+        Ok(())
+    }
+
+    fn parse_attribute_value(&mut self) -> Result<String, DomError> {
+        let raw = self.parse_quoted()?;
+        if raw.contains('<') {
+            return Err(self.error("'<' in attribute value"));
+        }
+        // Attribute-value normalization
+        let raw = raw.replace(['\t', '\n', '\r'], " ");
+        self.decode_references(&raw)
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, DomError> {
+        let quote = match self.rest().chars().next() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => return Err(self.error("expected a quoted value")),
+        };
+        self.pos += 1;
+        let end = self
+            .rest()
+            .find(quote)
+            .ok_or_else(|| self.error("unterminated quoted value"))?;
+        let value = self.input[self.pos..self.pos + end].to_string();
+        self.pos += end + 1;
+        Ok(value)
+    }
+
+    fn parse_name(&mut self) -> Result<String, DomError> {
+        let rest = self.rest();
+        let mut chars = rest.char_indices();
+        match chars.next() {
+            Some((_, c)) if c.is_alphabetic() || c == '_' || c == ':' || !c.is_ascii() => {}
+            _ => return Err(self.error("expected a name")),
+        }
+        let end = chars
+            .find(|&(_, c)| !(c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.') || !c.is_ascii()))
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let name = rest[..end].to_string();
+        self.pos += end;
+        Ok(name)
+    }
+
+    /// Replace character and predefined entity references.
+    fn decode_references(&self, raw: &str) -> Result<String, DomError> {
+        let mut result = String::with_capacity(raw.len());
+        let mut rest = raw;
+        while let Some(amp) = rest.find('&') {
+            result.push_str(&rest[..amp]);
+            let semicolon = rest[amp..]
+                .find(';')
+                .ok_or_else(|| self.error("unterminated reference"))?;
+            let reference = &rest[amp + 1..amp + semicolon];
+            let decoded = match reference {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match reference.strip_prefix("#x").or_else(|| reference.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => reference
+                        .strip_prefix('#')
+                        .and_then(|dec| dec.parse::<u32>().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            let decoded = decoded.ok_or_else(|| self.error(&format!("undefined reference &{reference};")))?;
+            result.push(decoded);
+            rest = &rest[amp + semicolon + 1..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn lookup_namespace(&self, prefix: &str) -> Option<String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(prefix))
+            .filter(|ns| !ns.is_empty())
+            .cloned()
+    }
+
+    fn current_parent(&self) -> Rc<Node> {
+        self.open.last().cloned().unwrap_or_else(|| self.document.root.clone())
+    }
+
+    fn append_leaf(&mut self, node_type: NodeType) {
+        self.flush_text();
+        let node = self.document.create_node(node_type);
+        self.current_parent().append_child(node);
+    }
+
+    fn flush_text(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.text);
+        let node = self.document.create_node(NodeType::Text(text));
+        self.current_parent().append_child(node);
+    }
+
+    /// Consume everything up to and including `terminator`, returning what
+    /// came before it.
+    fn expect_until(&mut self, terminator: &str) -> Result<&'a str, DomError> {
+        let end = self
+            .rest()
+            .find(terminator)
+            .ok_or_else(|| self.error(&format!("expected '{terminator}'")))?;
+        let start = self.pos;
+        self.pos += end + terminator.len();
+        Ok(&self.input[start..start + end])
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip whitespace, returning whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+        self.pos += rest.len() - trimmed.len();
+        trimmed.len() != rest.len()
+    }
+
+    fn error(&self, message: &str) -> DomError {
+        let before = &self.input[..self.pos.min(self.input.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+        DomError::ParseError(format!("XML {line}:{column}: {message}"))
+    }
+}
+
+/// Serialize `node` and its descendants as XML
+/// (`XMLSerializer.serializeToString()`).
+///
+/// Elements whose namespace differs from their parent's get an `xmlns`
+/// declaration; attributes are written in name order.
+pub fn serialize_xml(node: &Node) -> String {
+    let mut out = String::new();
+    serialize_into(node, "", &mut out);
+    out
+}
+
+fn serialize_into(node: &Node, parent_namespace: &str, out: &mut String) {
+    match &node.node_type {
+        NodeType::Element { tag_name, namespace, .. } => {
+            let attributes = node.attributes();
+            out.push('<');
+            out.push_str(tag_name);
+
+            let mut names: Vec<&String> = attributes.keys().collect();
+            names.sort();
+            let declares_default = attributes.contains_key("xmlns");
+            if !tag_name.contains(':') && namespace != parent_namespace && !declares_default {
+                out.push_str(" xmlns=\"");
+                escape_into(namespace, true, out);
+                out.push('"');
+            }
+            for name in names {
+                out.push(' ');
+                out.push_str(name);
+                out.push_str("=\"");
+                escape_into(&attributes[name], true, out);
+                out.push('"');
+            }
+
+            let children = node.children();
+            if children.is_empty() {
+                out.push_str("/>");
+                return;
+            }
+            out.push('>');
+            for child in &children {
+                serialize_into(child, namespace, out);
+            }
+            out.push_str("</");
+            out.push_str(tag_name);
+            out.push('>');
+        }
+        NodeType::Text(text) => escape_into(text, false, out),
+        NodeType::Comment(data) => {
+            out.push_str("<!--");
+            out.push_str(data);
+            out.push_str("-->");
+        }
+        NodeType::ProcessingInstruction { target, data } => {
+            out.push_str("<?");
+            out.push_str(target);
+            if !data.is_empty() {
+                out.push(' ');
+                out.push_str(data);
+            }
+            out.push_str("?>");
+        }
+        NodeType::DocumentType { name, public_id, system_id } => {
+            out.push_str("<!DOCTYPE ");
+            out.push_str(name);
+            if !public_id.is_empty() {
+                out.push_str(&format!(" PUBLIC \"{public_id}\" \"{system_id}\""));
+            } else if !system_id.is_empty() {
+                out.push_str(&format!(" SYSTEM \"{system_id}\""));
+            }
+            out.push('>');
+        }
+        NodeType::Document | NodeType::DocumentFragment | NodeType::ShadowRoot { .. } => {
+            for child in node.children() {
+                serialize_into(&child, parent_namespace, out);
+            }
+        }
+    }
+}
+
+fn escape_into(text: &str, attribute: bool, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            '\t' if attribute => out.push_str("&#9;"),
+            '\n' if attribute => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xml_namespaces_and_text() {
+        let xml = "<?xml version=\"1.0\"?>\n\
+            <!DOCTYPE feed>\n\
+            <feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:x=\"urn:x\">\
+            <title type='text'>A &amp; B &#x263A;</title>\
+            <x:meta x:id=\"1\"/>\
+            <content><![CDATA[<p>raw</p>]]></content>\
+            <!-- note -->\
+            </feed>";
+        let doc = Document::parse_xml(xml).unwrap();
+
+        let feed = doc.document_element().unwrap();
+        assert_eq!(feed.tag_name(), Some("feed"));
+        let children = feed.children();
+        let namespace = |node: &Rc<Node>| match &node.node_type {
+            NodeType::Element { namespace, .. } => namespace.clone(),
+            _ => String::new(),
+        };
+        assert_eq!(namespace(&feed), "http://www.w3.org/2005/Atom");
+        assert_eq!(namespace(&children[1]), "urn:x");
+        assert_eq!(children[0].text_content(), "A & B \u{263A}");
+        assert_eq!(children[0].get_attribute("type"), Some("text"));
+        assert_eq!(children[2].text_content(), "<p>raw</p>");
+        assert!(matches!(children[3].node_type, NodeType::Comment(_)));
+        assert!(matches!(doc.root().children()[0].node_type, NodeType::DocumentType { .. }));
+    }
+
+    #[test]
+    fn test_parse_xml_rejects_malformed_documents() {
+        for xml in [
+            "<a><b></a></b>",
+            "<a>",
+            "<a/><b/>",
+            "<a>&nbsp;</a>",
+            "<x:a/>",
+            "<a b=1/>",
+            "<a b='1' b='2'/>",
+            "text<a/>",
+            "<A></a>",
+        ] {
+            assert!(
+                matches!(Document::parse_xml(xml), Err(DomError::ParseError(_))),
+                "{xml} should not parse"
+            );
+        }
+
+        let Err(DomError::ParseError(message)) = Document::parse_xml("<a>\n  <b></c>\n</a>") else {
+            panic!("mismatched tags should fail");
+        };
+        assert!(message.contains("2:"), "{message}");
+    }
+
+    #[test]
+    fn test_xhtml_document_and_serialization() {
+        let xhtml = "<html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title>T</title></head>\
+            <body><p class=\"a\">x &lt; y<br/></p><svg xmlns=\"http://www.w3.org/2000/svg\"><g/></svg></body></html>";
+        let doc = Document::parse_xml(xhtml).unwrap();
+        assert_eq!(doc.title().as_deref(), Some("T"));
+        assert!(doc.body().is_some());
+
+        let body = doc.body().unwrap();
+        assert_eq!(
+            serialize_xml(&body),
+            "<body xmlns=\"http://www.w3.org/1999/xhtml\"><p class=\"a\">x &lt; y<br/></p>\
+             <svg xmlns=\"http://www.w3.org/2000/svg\"><g/></svg></body>"
+        );
+
+        // Serializing and reparsing gives the same markup
+        let markup = serialize_xml(doc.root());
+        let reparsed = Document::parse_xml(&markup).unwrap();
+        assert_eq!(serialize_xml(reparsed.root()), markup);
+    }
+}
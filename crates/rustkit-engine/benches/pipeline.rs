@@ -0,0 +1,117 @@
+//! Rendering pipeline benchmarks.
+//!
+//! Loads each WebSuite case page (`websuite/manifest.json`) into a headless
+//! view at its manifest viewport and times the pipeline stages separately:
+//! style resolution (including layout tree construction), layout, display
+//! list building and rendering.
+//!
+//! ```bash
+//! cargo bench -p rustkit-engine --features headless,bench --bench pipeline
+//!
+//! # One stage or one page
+//! cargo bench -p rustkit-engine --features headless,bench --bench pipeline -- layout/card-grid
+//! ```
+//!
+//! Skips itself when no GPU adapter is available.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rustkit_engine::{bench, Engine, EngineBuilder, EngineViewId};
+use rustkit_viewhost::Bounds;
+
+/// A WebSuite case page.
+struct Page {
+    id: String,
+    html: String,
+    width: u32,
+    height: u32,
+}
+
+fn websuite_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../websuite")
+}
+
+fn load_pages() -> Vec<Page> {
+    let dir = websuite_dir();
+    let manifest = fs::read_to_string(dir.join("manifest.json")).expect("read websuite manifest");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).expect("parse websuite manifest");
+    manifest["cases"]
+        .as_array()
+        .expect("manifest has a cases array")
+        .iter()
+        .filter_map(|case| {
+            let html = fs::read_to_string(dir.join(case["path"].as_str()?)).ok()?;
+            Some(Page {
+                id: case["id"].as_str()?.to_string(),
+                html,
+                width: case["viewport"]["width"].as_u64().unwrap_or(1280) as u32,
+                height: case["viewport"]["height"].as_u64().unwrap_or(800) as u32,
+            })
+        })
+        .collect()
+}
+
+/// Load a page into a fresh headless view.
+fn open(engine: &mut Engine, page: &Page) -> EngineViewId {
+    let bounds = Bounds {
+        x: 0,
+        y: 0,
+        width: page.width,
+        height: page.height,
+    };
+    let view = engine.create_headless_view(bounds).expect("create headless view");
+    engine.load_html(view, &page.html).expect("load page");
+    view
+}
+
+fn pipeline(c: &mut Criterion) {
+    let mut engine = match EngineBuilder::new().javascript_enabled(false).build() {
+        Ok(engine) => engine,
+        Err(_) => {
+            eprintln!("Skipping pipeline benchmarks: GPU not available");
+            return;
+        }
+    };
+    let pages = load_pages();
+    let views: Vec<(&Page, EngineViewId)> = pages.iter().map(|page| (page, open(&mut engine, page))).collect();
+
+    let mut group = c.benchmark_group("style");
+    for &(page, view) in &views {
+        group.bench_function(&page.id, |b| b.iter(|| bench::resolve_styles(&engine, view).unwrap()));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("layout");
+    for &(page, view) in &views {
+        group.bench_function(&page.id, |b| {
+            b.iter_batched(
+                || bench::resolve_styles(&engine, view).unwrap(),
+                |mut root| {
+                    bench::layout(&engine, view, &mut root).unwrap();
+                    root
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("display_list");
+    for &(page, view) in &views {
+        let mut root = bench::resolve_styles(&engine, view).unwrap();
+        bench::layout(&engine, view, &mut root).unwrap();
+        group.bench_function(&page.id, |b| b.iter(|| bench::build_display_list(&root)));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("render");
+    for &(page, view) in &views {
+        group.bench_function(&page.id, |b| b.iter(|| engine.render_view(view).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
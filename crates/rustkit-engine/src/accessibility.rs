@@ -0,0 +1,450 @@
+//! Accessibility trees.
+//!
+//! A page's accessibility tree is built from its DOM and layout the way
+//! assistive technology sees it: elements that aren't rendered, `hidden`
+//! or `aria-hidden` are left out, and elements with nothing to tell (no
+//! name, no role of their own, not focusable) are flattened away so their
+//! children take their place, while text gets a static text node of its
+//! own. Roles come from the `role` attribute or the element's implicit
+//! ARIA role, names follow the accessible name computation's order
+//! (`aria-labelledby`, `aria-label`, `alt`, labels, text content,
+//! `title`), and bounds are where elements, and the boxes of text, appear
+//! in the view, in view pixels.
+
+use std::collections::HashMap;
+
+use rustkit_a11y::{AccessibilityTree, AccessibleId, AccessibleNode, Role, State};
+use rustkit_dom::{Document, InputType, Node, NodeId};
+use rustkit_layout::{LayoutBox, Rect};
+
+use crate::dialog;
+
+/// Elements that never render.
+const UNRENDERED_TAGS: &[&str] = &["head", "link", "meta", "noscript", "script", "style", "template", "title"];
+
+/// Build the accessibility tree of `document`. `layout` is the page's
+/// layout tree, if it has been laid out, and `to_view` maps layout rects
+/// to the view's coordinates.
+pub(crate) fn build_tree(
+    document: &Document,
+    layout: Option<&LayoutBox>,
+    to_view: impl Fn(Rect) -> Rect,
+    focused: Option<NodeId>,
+) -> AccessibilityTree {
+    let mut bounds = HashMap::new();
+    if let Some(layout) = layout {
+        collect_bounds(layout, &mut bounds);
+    }
+    let mut builder = Builder {
+        document,
+        laid_out: layout.is_some(),
+        bounds,
+        to_view,
+        tree: AccessibilityTree::new(),
+    };
+
+    let mut root = AccessibleNode::new(Role::Document);
+    root.name = document.title().filter(|title| !title.is_empty());
+    root.tab_index = Some(-1);
+    if let Some(element) = document.document_element() {
+        root.dom_node_id = Some(element.id);
+        root.bounds = builder.bounds_of(&element);
+    }
+    let root_id = builder.tree.add_node(root);
+    builder.tree.set_root(root_id);
+    if let Some(body) = document.body() {
+        for child in builder.build(&body) {
+            builder.tree.add_child(root_id, child);
+        }
+    }
+
+    let mut tree = builder.tree;
+    let focus = focused.and_then(|node_id| tree.get_by_dom(node_id)).map(|node| node.id);
+    tree.set_focus(focus);
+    tree
+}
+
+/// The union of each element's and text node's border boxes, in layout
+/// coordinates.
+fn collect_bounds(layout_box: &LayoutBox, bounds: &mut HashMap<usize, Rect>) {
+    if let Some(element_id) = layout_box.element_id() {
+        let rect = layout_box.dimensions.border_box();
+        bounds
+            .entry(element_id)
+            .and_modify(|union: &mut Rect| {
+                let right = (union.x + union.width).max(rect.x + rect.width);
+                let bottom = (union.y + union.height).max(rect.y + rect.height);
+                union.x = union.x.min(rect.x);
+                union.y = union.y.min(rect.y);
+                union.width = right - union.x;
+                union.height = bottom - union.y;
+            })
+            .or_insert(rect);
+    }
+    for child in &layout_box.children {
+        collect_bounds(child, bounds);
+    }
+}
+
+struct Builder<'a, F> {
+    document: &'a Document,
+    laid_out: bool,
+    bounds: HashMap<usize, Rect>,
+    to_view: F,
+    tree: AccessibilityTree,
+}
+
+impl<F: Fn(Rect) -> Rect> Builder<'_, F> {
+    fn bounds_of(&self, node: &Node) -> Option<(f32, f32, f32, f32)> {
+        let rect = (self.to_view)(*self.bounds.get(&node.id.raw())?);
+        Some((rect.x, rect.y, rect.width, rect.height))
+    }
+
+    /// The accessible nodes `node` contributes to its parent's children:
+    /// its own, or its children's when it's flattened away.
+    fn build(&mut self, node: &Node) -> Vec<AccessibleId> {
+        if node.is_text() {
+            return self.build_text(node).into_iter().collect();
+        }
+        let Some(tag) = node.tag_name().map(str::to_ascii_lowercase) else {
+            return Vec::new();
+        };
+        if UNRENDERED_TAGS.contains(&tag.as_str())
+            || node.get_attribute("hidden").is_some()
+            || node.get_attribute("aria-hidden") == Some("true")
+        {
+            return Vec::new();
+        }
+
+        let children: Vec<AccessibleId> = node.children().iter().flat_map(|child| self.build(child)).collect();
+        let bounds = self.bounds_of(node);
+        // Unrendered elements have no boxes and neither do their
+        // descendants; `display: contents` ones only lack their own
+        if self.laid_out && bounds.is_none() && children.is_empty() {
+            return Vec::new();
+        }
+
+        let role = explicit_role(node).unwrap_or_else(|| Role::implicit(node));
+        let mut accessible = AccessibleNode::new(role);
+        accessible.dom_node_id = Some(node.id);
+        accessible.bounds = bounds;
+        accessible.name = self.name(node, &tag, role);
+        accessible.description = self.description(node, accessible.name.is_some());
+        accessible.value = value(node, &tag, role);
+        accessible.level = level(node, &tag);
+        accessible.tab_index = Some(tab_index(node));
+        for state in states(node, &tag, role) {
+            accessible.add_state(state);
+        }
+        for (name, value) in node.attributes().iter() {
+            if name.starts_with("aria-") {
+                accessible.set_property(name, value);
+            }
+        }
+
+        let flatten = match role {
+            Role::Presentation | Role::None => !accessible.is_focusable(),
+            Role::Generic => accessible.name.is_none() && !accessible.is_focusable(),
+            _ => false,
+        };
+        if flatten {
+            return children;
+        }
+        let id = self.tree.add_node(accessible);
+        for child in children {
+            self.tree.add_child(id, child);
+        }
+        vec![id]
+    }
+
+    /// The static text node of a text node that isn't all whitespace.
+    fn build_text(&mut self, node: &Node) -> Option<AccessibleId> {
+        let name = collapse(&node.text_content())?;
+        let bounds = self.bounds_of(node);
+        if self.laid_out && bounds.is_none() {
+            return None;
+        }
+        let mut accessible = AccessibleNode::new(Role::StaticText);
+        accessible.dom_node_id = Some(node.id);
+        accessible.bounds = bounds;
+        accessible.name = Some(name);
+        Some(self.tree.add_node(accessible))
+    }
+
+    /// The element's accessible name.
+    fn name(&self, node: &Node, tag: &str, role: Role) -> Option<String> {
+        if let Some(ids) = node.get_attribute("aria-labelledby") {
+            let name = collapse(
+                &ids.split_whitespace()
+                    .filter_map(|id| self.document.get_element_by_id(id))
+                    .map(|label| label.text_content())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+            if name.is_some() {
+                return name;
+            }
+        }
+        if let Some(label) = node.get_attribute("aria-label").and_then(collapse) {
+            return Some(label);
+        }
+        if matches!(tag, "img" | "area") || (tag == "input" && node.input_type() == InputType::Image) {
+            if let Some(alt) = node.get_attribute("alt").and_then(collapse) {
+                return Some(alt);
+            }
+        }
+        if matches!(tag, "input" | "select" | "textarea" | "meter" | "progress") {
+            if let Some(label) = self.label(node) {
+                return Some(label);
+            }
+        }
+        if tag == "input" {
+            let default = match node.input_type() {
+                InputType::Submit => Some("Submit"),
+                InputType::Reset => Some("Reset"),
+                InputType::Button => Some(""),
+                _ => None,
+            };
+            if let Some(value) = default.and_then(|default| collapse(node.get_attribute("value").unwrap_or(default))) {
+                return Some(value);
+            }
+        }
+        if matches!(tag, "input" | "textarea") {
+            if let Some(placeholder) = node.get_attribute("placeholder").and_then(collapse) {
+                return Some(placeholder);
+            }
+        }
+        if role.is_named_from_content() {
+            if let Some(text) = collapse(&node.text_content()) {
+                return Some(text);
+            }
+        }
+        node.get_attribute("title").and_then(collapse)
+    }
+
+    /// The text of the `<label>`s of a form control: those whose `for`
+    /// names it, or else the one it's in.
+    fn label(&self, node: &Node) -> Option<String> {
+        if let Some(id) = node.get_attribute("id").filter(|id| !id.is_empty()) {
+            let text = self
+                .document
+                .get_elements_by_tag_name("label")
+                .iter()
+                .filter(|label| label.get_attribute("for") == Some(id))
+                .map(|label| label.text_content())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if let Some(text) = collapse(&text) {
+                return Some(text);
+            }
+        }
+        let mut ancestor = node.parent();
+        while let Some(element) = ancestor {
+            if element.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("label")) {
+                return collapse(&element.text_content());
+            }
+            ancestor = element.parent();
+        }
+        None
+    }
+
+    /// The element's accessible description: the text of the elements its
+    /// `aria-describedby` names, or its `title` when that didn't name it.
+    fn description(&self, node: &Node, named: bool) -> Option<String> {
+        if let Some(ids) = node.get_attribute("aria-describedby") {
+            let text = ids
+                .split_whitespace()
+                .filter_map(|id| self.document.get_element_by_id(id))
+                .map(|element| element.text_content())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if let Some(text) = collapse(&text) {
+                return Some(text);
+            }
+        }
+        let title = node.get_attribute("title").and_then(collapse)?;
+        (named && Some(title.as_str()) != node.get_attribute("aria-label")).then_some(title)
+    }
+}
+
+/// The first role in the element's `role` attribute this tree knows.
+fn explicit_role(node: &Node) -> Option<Role> {
+    node.get_attribute("role")?.split_whitespace().find_map(Role::from_str)
+}
+
+/// The value of a control that has one.
+fn value(node: &Node, tag: &str, role: Role) -> Option<String> {
+    if let Some(text) = node.get_attribute("aria-valuetext").or_else(|| node.get_attribute("aria-valuenow")) {
+        return Some(text.to_string());
+    }
+    match (tag, role) {
+        ("input", Role::TextBox | Role::SearchBox | Role::Slider | Role::SpinButton) => Some(node.input_value()),
+        ("textarea", _) => Some(node.text_content()),
+        ("select", _) => {
+            let options = node.select_options();
+            Some(node.selected_index().and_then(|index| options.get(index)).map(|option| option.option_label()).unwrap_or_default())
+        }
+        ("progress", _) => node.get_attribute("value").map(str::to_string),
+        _ => None,
+    }
+}
+
+/// A heading's level, from its tag or `aria-level`.
+fn level(node: &Node, tag: &str) -> Option<u32> {
+    if let Some(level) = node.get_attribute("aria-level").and_then(|level| level.trim().parse().ok()) {
+        return Some(level);
+    }
+    match tag {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => tag[1..].parse().ok(),
+        _ => None,
+    }
+}
+
+/// The element's tab index as focus sees it: its `tabindex`, else 0 if
+/// Tab can reach it and -1 if not.
+fn tab_index(node: &Node) -> i32 {
+    node.get_attribute("tabindex")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(if dialog::is_focusable(node) { 0 } else { -1 })
+}
+
+/// The ARIA states that apply to the element, from its attributes and
+/// their `aria-*` counterparts.
+fn states(node: &Node, tag: &str, role: Role) -> Vec<State> {
+    let aria = |name: &str| node.get_attribute(name) == Some("true");
+    let has = |name: &str| node.get_attribute(name).is_some();
+    let control = matches!(tag, "button" | "input" | "select" | "textarea" | "option" | "fieldset");
+    let mut states = Vec::new();
+
+    if (control && has("disabled")) || aria("aria-disabled") {
+        states.push(State::Disabled);
+    }
+    let checked = match (tag, role) {
+        ("input", Role::Checkbox | Role::Radio) => has("checked"),
+        _ => matches!(node.get_attribute("aria-checked"), Some("true" | "mixed")),
+    };
+    if checked {
+        states.push(State::Checked);
+    }
+    let expanded = match tag {
+        "details" => has("open"),
+        "summary" => node.parent().is_some_and(|details| details.get_attribute("open").is_some()),
+        _ => aria("aria-expanded"),
+    };
+    if expanded {
+        states.push(State::Expanded);
+    }
+    if (control && has("required")) || aria("aria-required") {
+        states.push(State::Required);
+    }
+    if (matches!(tag, "input" | "textarea") && has("readonly")) || aria("aria-readonly") {
+        states.push(State::ReadOnly);
+    }
+    if (tag == "option" && node.is_option_selected()) || aria("aria-selected") {
+        states.push(State::Selected);
+    }
+    if matches!(node.get_attribute("aria-pressed"), Some("true" | "mixed")) {
+        states.push(State::Pressed);
+    }
+    if (tag == "select" && has("multiple")) || aria("aria-multiselectable") {
+        states.push(State::Multiselectable);
+    }
+    if aria("aria-invalid") {
+        states.push(State::Invalid);
+    }
+    if aria("aria-busy") {
+        states.push(State::Busy);
+    }
+    states
+}
+
+/// `text` with whitespace collapsed, if there's any left.
+fn collapse(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree_of(html: &str) -> (Document, AccessibilityTree) {
+        let document = Document::parse_html(html).unwrap();
+        let tree = build_tree(&document, None, |rect| rect, None);
+        (document, tree)
+    }
+
+    #[test]
+    fn test_names_and_roles() {
+        let (document, tree) = tree_of(
+            r#"<nav aria-label="Primary"><a id="home" href="/"> Home  page </a></nav>
+               <img id="logo" alt="Logo"><img id="spacer" alt="">
+               <label for="email">Email</label><input id="email" type="email" required>
+               <label><input id="terms" type="checkbox" checked> I agree</label>
+               <input id="send" type="submit"><input id="query" placeholder="Search">
+               <span id="labelled" role="button" aria-labelledby="caption">x</span><p id="caption">Close</p>
+               <h3 id="title" title="Section">Results</h3><div id="plain"><span>Text</span></div>"#,
+        );
+        let node = |id: &str| tree.get_by_dom(document.get_element_by_id(id).unwrap().id);
+
+        assert_eq!(tree.root().unwrap().role, Role::Document);
+        assert_eq!(node("home").unwrap().role, Role::Link);
+        assert_eq!(node("home").unwrap().name.as_deref(), Some("Home page"));
+        assert!(node("home").unwrap().is_focusable());
+        assert_eq!(node("logo").unwrap().name.as_deref(), Some("Logo"));
+        assert!(node("spacer").is_none());
+        let email = node("email").unwrap();
+        assert_eq!((email.role, email.name.as_deref()), (Role::TextBox, Some("Email")));
+        assert!(email.has_state(State::Required));
+        let terms = node("terms").unwrap();
+        assert_eq!(terms.name.as_deref(), Some("I agree"));
+        assert!(terms.has_state(State::Checked));
+        assert_eq!(node("send").unwrap().name.as_deref(), Some("Submit"));
+        assert_eq!(node("query").unwrap().name.as_deref(), Some("Search"));
+        let labelled = node("labelled").unwrap();
+        assert_eq!((labelled.role, labelled.name.as_deref()), (Role::Button, Some("Close")));
+        assert!(!labelled.is_focusable());
+        let title = node("title").unwrap();
+        assert_eq!((title.level, title.description.as_deref()), (Some(3), Some("Section")));
+        assert!(node("plain").is_none());
+        assert_eq!(node("caption").unwrap().role, Role::Paragraph);
+    }
+
+    #[test]
+    fn test_text_is_static_text() {
+        let (document, tree) = tree_of(r#"<div id="plain"><span>Some <b>bold</b> text</span></div> <p id="para">More</p>"#);
+        let root = tree.root().unwrap();
+        let text: Vec<_> = root
+            .children
+            .iter()
+            .map(|&id| tree.get(id).unwrap())
+            .map(|node| (node.role, node.name.as_deref()))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                (Role::StaticText, Some("Some")),
+                (Role::StaticText, Some("bold")),
+                (Role::StaticText, Some("text")),
+                (Role::Paragraph, None),
+            ]
+        );
+        let para = tree.get_by_dom(document.get_element_by_id("para").unwrap().id).unwrap();
+        assert_eq!(tree.get(para.children[0]).unwrap().name.as_deref(), Some("More"));
+    }
+
+    #[test]
+    fn test_hidden_elements_are_left_out() {
+        let (document, tree) = tree_of(
+            r#"<button id="shown">Shown</button><button id="hidden" hidden>Hidden</button>
+               <div aria-hidden="true"><button id="inside">Inside</button></div>
+               <script>var x;</script>"#,
+        );
+        let node = |id: &str| tree.get_by_dom(document.get_element_by_id(id).unwrap().id);
+
+        assert!(node("shown").is_some());
+        assert!(node("hidden").is_none());
+        assert!(node("inside").is_none());
+        assert_eq!(tree.root().unwrap().children.len(), 1);
+    }
+}
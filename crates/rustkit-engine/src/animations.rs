@@ -0,0 +1,384 @@
+//! CSS animations.
+//!
+//! An element whose `animation-name` names a `@keyframes` rule runs it
+//! from the relayout that first sees the name, for
+//! `animation-iteration-count` iterations of `animation-duration` after
+//! `animation-delay`, in `animation-direction`. Between keyframes the
+//! properties that can transition are interpolated, eased by the earlier
+//! keyframe's `animation-timing-function`; keyframes may leave properties
+//! out, the element's own value standing in at 0% and 100%.
+//! `animation-fill-mode` decides whether the first keyframe shows during
+//! the delay and the last one stays after the end, and
+//! `animation-play-state: paused` holds the animation where it is.
+//!
+//! As with transitions, each relayout writes the animated values into the
+//! new layout tree and puts the element's own ones back before the tree is
+//! reused; [`Engine::pump_animations`] drives them.
+//!
+//! [`Engine::pump_animations`]: crate::Engine::pump_animations
+
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use rustkit_css::{
+    AnimationDirection, AnimationFillMode, AnimationIterationCount, AnimationPlayState, ComputedStyle, Declaration,
+    Keyframes, TimingFunction,
+};
+use rustkit_layout::LayoutBox;
+
+use crate::transitions::{for_each_element, Property, Value, ANIMATION_FRAME_INTERVAL};
+
+/// An element's running `@keyframes` animation.
+#[derive(Debug, Clone)]
+struct Animation {
+    /// The `@keyframes` rule's name.
+    name: String,
+    /// When the animation started, delay included.
+    start: Instant,
+    /// When it was paused, if it is.
+    paused_at: Option<Instant>,
+    /// Whether its last iteration ended.
+    finished: bool,
+    /// Properties written into the layout tree, with the element's own
+    /// value and the animated one.
+    applied: Vec<(Property, Value, Value)>,
+}
+
+impl Animation {
+    /// The animation `name`, starting at `now`.
+    fn new(name: &str, now: Instant) -> Self {
+        Self {
+            name: name.to_string(),
+            start: now,
+            paused_at: None,
+            finished: false,
+            applied: Vec::new(),
+        }
+    }
+
+    /// Seconds of animation time at `now`, which stands still while paused.
+    fn elapsed(&self, now: Instant) -> f32 {
+        self.paused_at.unwrap_or(now).saturating_duration_since(self.start).as_secs_f32()
+    }
+}
+
+/// Where an animation of an element with `style` is `elapsed` seconds
+/// after it started: the progress through the current iteration, already
+/// reversed for the direction, or `None` when it has no effect. Also
+/// returns whether it's over.
+fn iteration_progress(style: &ComputedStyle, elapsed: f32) -> (Option<f32>, bool) {
+    let duration = style.animation_duration;
+    let iterations = match style.animation_iteration_count {
+        AnimationIterationCount::One => 1.0,
+        AnimationIterationCount::Infinite => f32::INFINITY,
+        AnimationIterationCount::Count(count) => count.max(0.0),
+    };
+    let fills_backwards = matches!(style.animation_fill_mode, AnimationFillMode::Backwards | AnimationFillMode::Both);
+    let fills_forwards = matches!(style.animation_fill_mode, AnimationFillMode::Forwards | AnimationFillMode::Both);
+
+    let time = elapsed - style.animation_delay;
+    let active = duration * iterations;
+    let (iteration, progress) = if time < 0.0 {
+        if !fills_backwards {
+            return (None, false);
+        }
+        (0.0, 0.0)
+    } else if time >= active {
+        if !fills_forwards {
+            return (None, true);
+        }
+        // The end of the last iteration, which may be part way through one
+        let last = (iterations.ceil() - 1.0).max(0.0);
+        let progress = if iterations.fract() == 0.0 && iterations > 0.0 { 1.0 } else { iterations.fract() };
+        return (Some(directed(style.animation_direction, last, progress)), true);
+    } else {
+        let iterations_done = time / duration;
+        (iterations_done.floor(), iterations_done.fract())
+    };
+    (Some(directed(style.animation_direction, iteration, progress)), false)
+}
+
+/// `progress` through iteration number `iteration`, run in `direction`.
+fn directed(direction: AnimationDirection, iteration: f32, progress: f32) -> f32 {
+    let odd = iteration % 2.0 == 1.0;
+    let reversed = match direction {
+        AnimationDirection::Normal => false,
+        AnimationDirection::Reverse => true,
+        AnimationDirection::Alternate => odd,
+        AnimationDirection::AlternateReverse => !odd,
+    };
+    if reversed {
+        1.0 - progress
+    } else {
+        progress
+    }
+}
+
+/// Properties a keyframe declaration sets, shorthands expanded.
+fn declared_properties(declaration: &Declaration) -> Vec<Property> {
+    let rustkit_css::PropertyValue::Specified(value) = &declaration.value else {
+        return Vec::new();
+    };
+    match rustkit_css::shorthand(&declaration.property) {
+        Some(shorthand) => shorthand
+            .expand(value)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(longhand, _)| Property::from_name(longhand))
+            .collect(),
+        None => Property::from_name(&declaration.property).into_iter().collect(),
+    }
+}
+
+/// Value of `property` at `progress` through an iteration of `keyframes`,
+/// each keyframe's values computed from the element's `style` by `apply`.
+fn keyframe_value(
+    keyframes: &Keyframes,
+    property: Property,
+    style: &ComputedStyle,
+    apply: &impl Fn(&mut ComputedStyle, &Declaration),
+    progress: f32,
+) -> Value {
+    // The keyframes that set the property, with the element's own value
+    // at the ends they leave open
+    let base = property.get(style);
+    let mut frames: Vec<(f32, Value, TimingFunction)> = keyframes
+        .frames
+        .iter()
+        .filter(|frame| frame.declarations.iter().any(|d| declared_properties(d).contains(&property)))
+        .map(|frame| {
+            let mut frame_style = style.clone();
+            for declaration in &frame.declarations {
+                apply(&mut frame_style, declaration);
+            }
+            (frame.offset, property.get(&frame_style), frame_style.animation_timing_function)
+        })
+        .collect();
+    if frames.first().is_none_or(|(offset, ..)| *offset > 0.0) {
+        frames.insert(0, (0.0, base.clone(), style.animation_timing_function.clone()));
+    }
+    if frames.last().is_none_or(|(offset, ..)| *offset < 1.0) {
+        frames.push((1.0, base, style.animation_timing_function.clone()));
+    }
+
+    let next = frames.iter().position(|(offset, ..)| *offset > progress).unwrap_or(frames.len() - 1).max(1);
+    let (from_offset, from, timing) = &frames[next - 1];
+    let (to_offset, to, _) = &frames[next];
+    if to_offset <= from_offset {
+        return to.clone();
+    }
+    let eased = timing.progress((progress - from_offset) / (to_offset - from_offset));
+    from.interpolate(to, eased)
+        .unwrap_or_else(|| if eased < 0.5 { from.clone() } else { to.clone() })
+}
+
+/// `@keyframes` animations of a view's elements.
+#[derive(Debug, Default)]
+pub(crate) struct Animations {
+    /// Animations by DOM node ID.
+    running: HashMap<usize, Animation>,
+    /// When the animations last advanced.
+    last_frame: Option<Instant>,
+}
+
+impl Animations {
+    /// When the running animations next advance, if any are running.
+    pub fn next_frame(&self) -> Option<Instant> {
+        let next = self.last_frame? + ANIMATION_FRAME_INTERVAL;
+        self.running
+            .values()
+            .any(|animation| !animation.finished && animation.paused_at.is_none())
+            .then_some(next)
+    }
+
+    /// Put the elements' own values back in place of the animated ones in
+    /// `root`, the last relayout's tree.
+    pub fn restore(&mut self, root: &mut LayoutBox) {
+        if self.running.is_empty() {
+            return;
+        }
+        for_each_element(root, &mut |element_id, layout_box| {
+            let Some(animation) = self.running.get_mut(&element_id) else {
+                return;
+            };
+            for (property, base, shown) in animation.applied.drain(..) {
+                if property.get(&layout_box.style) == shown {
+                    property.set_in_tree(layout_box, &base, &shown);
+                }
+            }
+        });
+    }
+
+    /// Start, restart or stop the animations the elements in `root` name,
+    /// then write the values they've reached at `now` into it. `keyframes`
+    /// finds a `@keyframes` rule by name, and `apply` applies a keyframe's
+    /// declaration to a style.
+    pub fn update<'a>(
+        &mut self,
+        root: &mut LayoutBox,
+        keyframes: impl Fn(&str) -> Option<&'a Keyframes>,
+        apply: impl Fn(&mut ComputedStyle, &Declaration),
+        now: Instant,
+    ) {
+        let mut seen = HashSet::new();
+        let running = &mut self.running;
+        for_each_element(root, &mut |element_id, layout_box| {
+            if !seen.insert(element_id) {
+                return;
+            }
+            let style = &layout_box.style;
+            let name = style.animation_name.split(',').next().unwrap_or_default().trim();
+            let Some(rule) = keyframes(name).filter(|_| name != "none" && style.animation_duration > 0.0) else {
+                running.remove(&element_id);
+                return;
+            };
+
+            // A new name starts the animation over
+            let animation = running
+                .entry(element_id)
+                .and_modify(|animation| {
+                    if animation.name != name {
+                        *animation = Animation::new(name, now);
+                    }
+                })
+                .or_insert_with(|| Animation::new(name, now));
+            match (style.animation_play_state, animation.paused_at) {
+                (AnimationPlayState::Paused, None) => animation.paused_at = Some(now),
+                (AnimationPlayState::Running, Some(paused_at)) => {
+                    animation.start += now.saturating_duration_since(paused_at);
+                    animation.paused_at = None;
+                }
+                _ => {}
+            }
+
+            let (progress, finished) = iteration_progress(style, animation.elapsed(now));
+            animation.finished = finished;
+            let Some(progress) = progress else {
+                return;
+            };
+            let properties: Vec<Property> = rule
+                .frames
+                .iter()
+                .flat_map(|frame| frame.declarations.iter().flat_map(declared_properties))
+                .collect();
+            let base_style = layout_box.style.clone();
+            for property in properties {
+                if animation.applied.iter().any(|(applied, ..)| *applied == property) {
+                    continue;
+                }
+                let base = property.get(&base_style);
+                let value = keyframe_value(rule, property, &base_style, &apply, progress);
+                property.set_in_tree(layout_box, &value, &base);
+                animation.applied.push((property, base, value));
+            }
+        });
+        running.retain(|element_id, _| seen.contains(element_id));
+        self.last_frame = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_css::{Color, Stylesheet};
+    use std::time::Duration;
+    use rustkit_layout::BoxType;
+
+    fn animated(stylesheet: &Stylesheet, configure: impl Fn(&mut ComputedStyle)) -> (LayoutBox, &Keyframes) {
+        let mut style = ComputedStyle::new();
+        style.animation_name = "pulse".to_string();
+        style.animation_duration = 1.0;
+        style.animation_timing_function = TimingFunction::Linear;
+        configure(&mut style);
+        let mut layout_box = LayoutBox::new(BoxType::Block, style);
+        layout_box.set_element_id(1);
+        (layout_box, &stylesheet.keyframes[0])
+    }
+
+    /// Apply the few declarations the tests use.
+    fn apply(style: &mut ComputedStyle, declaration: &Declaration) {
+        let rustkit_css::PropertyValue::Specified(value) = &declaration.value else {
+            return;
+        };
+        match declaration.property.as_str() {
+            "opacity" => style.opacity = value.parse().unwrap(),
+            "color" => style.color = Color::from_rgb(value.parse().unwrap(), 0, 0),
+            _ => {}
+        }
+    }
+
+    fn run(animations: &mut Animations, layout_box: &mut LayoutBox, keyframes: &Keyframes, now: Instant) {
+        animations.restore(layout_box);
+        animations.update(layout_box, |name| (name == keyframes.name).then_some(keyframes), apply, now);
+    }
+
+    #[test]
+    fn test_keyframes_interpolate_over_iterations() {
+        let stylesheet =
+            Stylesheet::parse("@keyframes pulse { 50% { opacity: 0; } to { opacity: 0.5; color: 200; } }").unwrap();
+        let (mut layout_box, keyframes) = animated(&stylesheet, |style| {
+            style.animation_iteration_count = AnimationIterationCount::Count(2.0);
+            style.animation_direction = AnimationDirection::Alternate;
+        });
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut animations = Animations::default();
+
+        run(&mut animations, &mut layout_box, keyframes, start);
+        assert_eq!(layout_box.style.opacity, 1.0);
+        assert_eq!(animations.next_frame(), Some(start + ANIMATION_FRAME_INTERVAL));
+
+        // The element's own opacity stands in at 0%
+        run(&mut animations, &mut layout_box, keyframes, at(250));
+        assert!((layout_box.style.opacity - 0.5).abs() < 1e-3);
+        assert_eq!(layout_box.style.color.r, 50);
+        run(&mut animations, &mut layout_box, keyframes, at(750));
+        assert!((layout_box.style.opacity - 0.25).abs() < 1e-3);
+
+        // The second iteration runs backwards
+        run(&mut animations, &mut layout_box, keyframes, at(1250));
+        assert!((layout_box.style.opacity - 0.25).abs() < 1e-3);
+
+        // Without a fill mode the element's own values come back at the end
+        run(&mut animations, &mut layout_box, keyframes, at(2000));
+        assert_eq!(layout_box.style.opacity, 1.0);
+        assert_eq!(layout_box.style.color, Color::BLACK);
+        assert_eq!(animations.next_frame(), None);
+    }
+
+    #[test]
+    fn test_fill_mode_and_pausing() {
+        let stylesheet = Stylesheet::parse("@keyframes pulse { from { opacity: 0.2; } to { opacity: 0.6; } }").unwrap();
+        let (mut layout_box, keyframes) = animated(&stylesheet, |style| {
+            style.animation_delay = 1.0;
+            style.animation_fill_mode = AnimationFillMode::Both;
+        });
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut animations = Animations::default();
+
+        // The first keyframe shows during the delay
+        run(&mut animations, &mut layout_box, keyframes, start);
+        assert!((layout_box.style.opacity - 0.2).abs() < 1e-3);
+
+        // Paused half way, the animation holds there
+        layout_box.style.animation_play_state = AnimationPlayState::Paused;
+        run(&mut animations, &mut layout_box, keyframes, at(1500));
+        assert!((layout_box.style.opacity - 0.4).abs() < 1e-3);
+        assert_eq!(animations.next_frame(), None);
+        run(&mut animations, &mut layout_box, keyframes, at(5000));
+        assert!((layout_box.style.opacity - 0.4).abs() < 1e-3);
+
+        // Resumed, it carries on from there and the last keyframe stays
+        layout_box.style.animation_play_state = AnimationPlayState::Running;
+        run(&mut animations, &mut layout_box, keyframes, at(5000));
+        run(&mut animations, &mut layout_box, keyframes, at(5600));
+        assert!((layout_box.style.opacity - 0.6).abs() < 1e-3);
+
+        // Another name starts over
+        layout_box.style.animation_name = "other".to_string();
+        run(&mut animations, &mut layout_box, keyframes, at(6000));
+        assert_eq!(layout_box.style.opacity, 1.0);
+        assert_eq!(animations.next_frame(), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! Media autoplay.
+//!
+//! Whether `<audio autoplay>` and `<video autoplay>` may start playing on
+//! their own is up to the engine's [`AutoplayPolicy`], which hosts can
+//! override per origin, for instance once the user unblocks a site. When a
+//! page loads with media the policy holds back, the engine reports it with
+//! [`EngineEvent::AutoplayBlocked`] so the host can offer to unblock it.
+//! Overrides last for the engine's lifetime; hosts that keep them across
+//! sessions save them and set them again on startup.
+//!
+//! [`AutoplayPolicy`]: crate::AutoplayPolicy
+//! [`EngineEvent::AutoplayBlocked`]: crate::EngineEvent::AutoplayBlocked
+
+use std::rc::Rc;
+
+use rustkit_dom::{Document, Node};
+
+/// The document's media elements set to autoplay, with whether each
+/// starts muted.
+pub(crate) fn autoplaying_media(document: &Document) -> Vec<(Rc<Node>, bool)> {
+    let mut media = Vec::new();
+    document.traverse(|node| {
+        let is_media = node
+            .tag_name()
+            .is_some_and(|tag| tag.eq_ignore_ascii_case("audio") || tag.eq_ignore_ascii_case("video"));
+        if is_media && node.get_attribute("autoplay").is_some() {
+            media.push((node.clone(), node.get_attribute("muted").is_some()));
+        }
+    });
+    media
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AutoplayPolicy;
+
+    #[test]
+    fn test_autoplaying_media() {
+        let document = Document::parse_html(
+            r#"<video id="intro" autoplay muted></video><audio id="theme" autoplay></audio>
+               <video id="manual" controls></video>"#,
+        )
+        .unwrap();
+        let media: Vec<_> = autoplaying_media(&document)
+            .into_iter()
+            .map(|(node, muted)| (node.get_attribute("id").unwrap().to_string(), muted))
+            .collect();
+        assert_eq!(media, [("intro".to_string(), true), ("theme".to_string(), false)]);
+
+        let blocked = |policy: AutoplayPolicy| media.iter().filter(|(_, muted)| !policy.allows(*muted)).count();
+        assert_eq!(blocked(AutoplayPolicy::Allow), 0);
+        assert_eq!(blocked(AutoplayPolicy::AllowMuted), 1);
+        assert_eq!(blocked(AutoplayPolicy::Block), 2);
+    }
+}
@@ -0,0 +1,48 @@
+//! Pipeline stages for benchmarks.
+//!
+//! `relayout` runs style resolution, layout and display list building in
+//! one call; these functions run each stage on its own against a loaded
+//! view, so benchmarks can time them separately. Rendering is timed with
+//! `Engine::render_view`, which paints the display list already stored on
+//! the view. Enabled by the `bench` feature; the criterion benchmarks are
+//! in `benches/pipeline.rs`.
+
+use rustkit_layout::{Dimensions, DisplayList, LayoutBox, Rect};
+
+use crate::{Engine, EngineError, EngineViewId};
+
+/// Resolve styles for a view's document and build its layout tree.
+pub fn resolve_styles(engine: &Engine, id: EngineViewId) -> Result<LayoutBox, EngineError> {
+    let view = engine.views.get(&id).ok_or(EngineError::ViewNotFound(id))?;
+    let document = view
+        .document
+        .as_ref()
+        .ok_or(EngineError::RenderError("No document".into()))?;
+    Ok(engine.build_layout_from_document(document, &view.external_stylesheets))
+}
+
+/// Lay out a tree built by [`resolve_styles`] at the view's size.
+pub fn layout(engine: &Engine, id: EngineViewId, root: &mut LayoutBox) -> Result<(), EngineError> {
+    let view = engine.views.get(&id).ok_or(EngineError::ViewNotFound(id))?;
+    let bounds = match view.headless_bounds {
+        Some(bounds) => bounds,
+        None => engine
+            .viewhost
+            .get_bounds(view.viewhost_id)
+            .map_err(|e| EngineError::ViewError(e.to_string()))?,
+    };
+    let containing_block = Dimensions {
+        content: Rect::new(0.0, 0.0, bounds.width as f32, 0.0),
+        ..Default::default()
+    };
+    root.set_viewport(bounds.width as f32, bounds.height as f32);
+    root.layout(&containing_block);
+    Ok(())
+}
+
+/// Build and compact the display list for a laid-out tree.
+pub fn build_display_list(root: &LayoutBox) -> DisplayList {
+    let mut display_list = DisplayList::build(root);
+    display_list.compact();
+    display_list
+}
@@ -0,0 +1,90 @@
+//! PNG screenshots.
+//!
+//! [`Engine::capture_frame_png`](crate::Engine::capture_frame_png) renders a
+//! view's display list offscreen and encodes it as PNG. Captures of the
+//! whole scrollable page can be larger than the GPU's largest texture; those
+//! are rendered a tile at a time, each as large as the GPU allows, and the
+//! tiles are composited into one image.
+
+use rustkit_codecs::RgbaImage;
+use rustkit_layout::Rect;
+use rustkit_viewhost::Bounds;
+
+/// What [`Engine::capture_frame_png`](crate::Engine::capture_frame_png)
+/// captures.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CaptureOptions {
+    /// Capture only this part of the viewport, or of the page when
+    /// `full_height` is set, in view pixels.
+    pub clip: Option<Bounds>,
+    /// Capture the page's whole scrollable height, not just the viewport.
+    pub full_height: bool,
+    /// Capture all of the page's laid-out content: its whole scrollable
+    /// width as well as its height. Implies `full_height`.
+    pub full_page: bool,
+    /// Image pixels per view pixel. Defaults to the pixel ratio of the
+    /// emulated device, or 1.
+    pub device_pixel_ratio: Option<f32>,
+}
+
+/// The part of `area` that `clip`, relative to its top left corner,
+/// covers, if any.
+pub(crate) fn clip_area(area: Rect, clip: Bounds) -> Option<Rect> {
+    let left = area.x.max(area.x + clip.x as f32);
+    let top = area.y.max(area.y + clip.y as f32);
+    let right = (area.x + area.width).min(area.x + clip.x as f32 + clip.width as f32);
+    let bottom = (area.y + area.height).min(area.y + clip.y as f32 + clip.height as f32);
+    (right > left && bottom > top).then(|| Rect::new(left, top, right - left, bottom - top))
+}
+
+/// The tiles, as their left column, top row, width and height, that an
+/// image `width` by `height` pixels is rendered in, row by row, each at
+/// most `tile_size` pixels on a side.
+pub(crate) fn tiles(width: u32, height: u32, tile_size: u32) -> impl Iterator<Item = (u32, u32, u32, u32)> {
+    let tile_size = tile_size.max(1);
+    (0..height).step_by(tile_size as usize).flat_map(move |top| {
+        (0..width)
+            .step_by(tile_size as usize)
+            .map(move |left| (left, top, tile_size.min(width - left), tile_size.min(height - top)))
+    })
+}
+
+/// Copy the RGBA8 rows of a tile into an image `width` pixels wide.
+pub(crate) fn paste_tile(image: &mut [u8], width: u32, tile: (u32, u32, u32, u32), pixels: &[u8]) {
+    let (left, top, tile_width, tile_height) = tile;
+    let row_bytes = tile_width as usize * 4;
+    for row in 0..tile_height as usize {
+        let start = ((top as usize + row) * width as usize + left as usize) * 4;
+        image[start..start + row_bytes].copy_from_slice(&pixels[row * row_bytes..][..row_bytes]);
+    }
+}
+
+/// Encode RGBA8 rows as a PNG.
+pub(crate) fn encode_png(width: u32, height: u32, pixels: Vec<u8>) -> Result<Vec<u8>, String> {
+    let image = RgbaImage::from_rgba8(width, height, pixels).map_err(|e| e.to_string())?;
+    rustkit_codecs::encode_png(&image).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_regions() {
+        assert_eq!(tiles(100, 250, 100).collect::<Vec<_>>(), [(0, 0, 100, 100), (0, 100, 100, 100), (0, 200, 100, 50)]);
+        assert_eq!(tiles(150, 80, 100).collect::<Vec<_>>(), [(0, 0, 100, 80), (100, 0, 50, 80)]);
+        assert_eq!(tiles(100, 100, 100).collect::<Vec<_>>(), [(0, 0, 100, 100)]);
+
+        // Tiles land at their place in the image
+        let mut image = vec![0; 3 * 2 * 4];
+        paste_tile(&mut image, 3, (1, 1, 2, 1), &[1, 1, 1, 1, 2, 2, 2, 2]);
+        assert_eq!(&image[..16], &[0; 16]);
+        assert_eq!(&image[16..], &[1, 1, 1, 1, 2, 2, 2, 2]);
+
+        let area = Rect::new(0.0, 40.0, 200.0, 100.0);
+        assert_eq!(clip_area(area, Bounds::new(10, 20, 50, 30)), Some(Rect::new(10.0, 60.0, 50.0, 30.0)));
+        // Clips reaching past the area are cut to it
+        assert_eq!(clip_area(area, Bounds::new(-10, 90, 50, 30)), Some(Rect::new(0.0, 130.0, 40.0, 10.0)));
+        assert_eq!(clip_area(area, Bounds::new(0, 100, 50, 30)), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! The cascade.
+//!
+//! The declarations that apply to an element come from the user-agent
+//! stylesheet, the page's stylesheets and its `style` attribute. They apply
+//! in order of precedence, lowest first, so later ones win: user-agent
+//! rules, author rules, then the `style` attribute; then `!important`
+//! declarations the other way round, author rules, the `style` attribute,
+//! and last the user agent's. Rules from the same origin apply by
+//! specificity, then in source order.
+
+use std::sync::OnceLock;
+
+use rustkit_css::{PropertyValue, Rule, Stylesheet};
+use tracing::warn;
+
+/// The user-agent stylesheet's source.
+const USER_AGENT_CSS: &str = include_str!("user_agent.css");
+
+/// The default styles of HTML elements, beneath every page's own.
+pub(crate) fn user_agent_stylesheet() -> &'static Stylesheet {
+    static STYLESHEET: OnceLock<Stylesheet> = OnceLock::new();
+    STYLESHEET.get_or_init(|| {
+        Stylesheet::parse(USER_AGENT_CSS).unwrap_or_else(|e| {
+            warn!(?e, "Failed to parse the user-agent stylesheet");
+            Stylesheet::new()
+        })
+    })
+}
+
+/// Where a rule comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Origin {
+    UserAgent,
+    Author,
+}
+
+/// The declarations applying to one element, collected to be applied in
+/// cascade order.
+#[derive(Default)]
+pub(crate) struct Cascade<'a> {
+    /// Matching rules, in source order.
+    rules: Vec<(Origin, (usize, usize, usize), &'a Rule)>,
+    /// Declarations of the `style` attribute: property, value and whether
+    /// it's important.
+    inline: Vec<(String, &'a str, bool)>,
+}
+
+impl<'a> Cascade<'a> {
+    /// Add a rule that matches the element. Rules must be added in source
+    /// order.
+    pub fn add_rule(&mut self, origin: Origin, specificity: (usize, usize, usize), rule: &'a Rule) {
+        self.rules.push((origin, specificity, rule));
+    }
+
+    /// Add the declarations of the element's `style` attribute.
+    pub fn add_inline_style(&mut self, style_attr: &'a str) {
+        for declaration in split_declarations(style_attr) {
+            if let Some((property, value)) = declaration.split_once(':') {
+                let (value, important) = strip_important(value.trim());
+                self.inline.push((property.trim().to_lowercase(), value, important));
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.inline.is_empty()
+    }
+
+    /// Call `apply` with each declaration's property and value, in cascade
+    /// order.
+    pub fn apply(mut self, mut apply: impl FnMut(&str, &str)) {
+        // Stable, so rules of equal specificity keep their source order
+        self.rules.sort_by_key(|(origin, specificity, _)| (*origin, *specificity));
+
+        let declarations = self
+            .rule_declarations(None, false)
+            .chain(self.inline_declarations(false))
+            .chain(self.rule_declarations(Some(Origin::Author), true))
+            .chain(self.inline_declarations(true))
+            .chain(self.rule_declarations(Some(Origin::UserAgent), true));
+        for (property, value) in declarations {
+            apply(property, value);
+        }
+    }
+
+    /// The declarations of the rules from `origin`, or from every origin,
+    /// that are or aren't important.
+    fn rule_declarations(&self, origin: Option<Origin>, important: bool) -> impl Iterator<Item = (&str, &str)> {
+        self.rules
+            .iter()
+            .filter(move |(rule_origin, _, _)| origin.is_none_or(|origin| *rule_origin == origin))
+            .flat_map(|(_, _, rule)| &rule.declarations)
+            .filter(move |declaration| declaration.important == important)
+            .filter_map(|declaration| match &declaration.value {
+                PropertyValue::Specified(value) => Some((declaration.property.as_str(), value.as_str())),
+                PropertyValue::Inherit | PropertyValue::Initial => None,
+            })
+    }
+
+    fn inline_declarations(&self, important: bool) -> impl Iterator<Item = (&str, &str)> {
+        self.inline
+            .iter()
+            .filter(move |(_, _, is_important)| *is_important == important)
+            .map(|(property, value, _)| (property.as_str(), *value))
+    }
+}
+
+/// Split off a trailing `!important`.
+fn strip_important(value: &str) -> (&str, bool) {
+    match value.rfind('!') {
+        Some(bang) if value[bang + 1..].trim().eq_ignore_ascii_case("important") => (value[..bang].trim_end(), true),
+        _ => (value, false),
+    }
+}
+
+/// Split a style attribute into declarations at the semicolons outside
+/// parentheses and quotes, so `url(data:image/png;base64,...)` stays whole.
+fn split_declarations(style: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut paren_depth = 0;
+    let mut quote = None;
+
+    for (i, ch) in style.char_indices() {
+        match (ch, quote) {
+            ('"' | '\'', None) => quote = Some(ch),
+            (_, Some(open)) if ch == open => quote = None,
+            (_, Some(_)) => {}
+            ('(', None) => paren_depth += 1,
+            (')', None) => paren_depth -= 1,
+            (';', None) if paren_depth <= 0 => {
+                parts.push(&style[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&style[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_cascade_order() {
+        let user_agent = Stylesheet::parse("p { color: black; margin-top: 16px !important; }").unwrap();
+        let author = Stylesheet::parse(
+            "#a { color: red !important; width: 1px; height: 1px; }
+             p { color: blue; width: 2px !important; height: 2px; margin-top: 0; }",
+        )
+        .unwrap();
+        let mut cascade = Cascade::default();
+        cascade.add_rule(Origin::UserAgent, (0, 0, 1), &user_agent.rules[0]);
+        cascade.add_rule(Origin::Author, (1, 0, 0), &author.rules[0]);
+        cascade.add_rule(Origin::Author, (0, 0, 1), &author.rules[1]);
+        cascade.add_inline_style("color: green ! IMPORTANT; width: 3px; margin-top: 4px");
+
+        let mut applied = HashMap::new();
+        cascade.apply(|property, value| {
+            applied.insert(property.to_string(), value.to_string());
+        });
+        // The more specific author rule wins
+        assert_eq!(applied["height"], "1px");
+        // Important author declarations beat the style attribute, and
+        // important ones in it beat theirs
+        assert_eq!(applied["width"], "2px");
+        assert_eq!(applied["color"], "green");
+        // Important user-agent declarations beat everything
+        assert_eq!(applied["margin-top"], "16px");
+
+        assert!(!user_agent_stylesheet().rules.is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+//! `<details>` disclosure widgets.
+//!
+//! A closed `<details>` shows only its summary. Clicking the summary, or
+//! pressing Enter or Space while it has focus, toggles the `open` attribute
+//! and fires `toggle` at the details. A details without a `<summary>` child
+//! gets a default one.
+
+use std::rc::Rc;
+
+use rustkit_dom::Node;
+
+/// Localization key of the label of the summary generated for a details
+/// without one.
+pub(crate) const DEFAULT_SUMMARY: &str = "details.summary";
+
+fn is_element(node: &Node, name: &str) -> bool {
+    node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+}
+
+/// The summary of `details`: its first `<summary>` child.
+pub(crate) fn summary_of(details: &Node) -> Option<Rc<Node>> {
+    details.children().into_iter().find(|child| is_element(child, "summary"))
+}
+
+/// The `<details>` that activating `node` toggles, if `node` is or is inside
+/// a details' summary. Activating a link or control inside the summary
+/// doesn't toggle it.
+pub(crate) fn toggled_details(node: Rc<Node>) -> Option<Rc<Node>> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if ["a", "button", "input", "select", "textarea"].iter().any(|name| is_element(&node, name)) {
+            return None;
+        }
+        if is_element(&node, "summary") {
+            let details = node.parent().filter(|parent| is_element(parent, "details"))?;
+            let is_summary = summary_of(&details).is_some_and(|summary| Rc::ptr_eq(&summary, &node));
+            return is_summary.then_some(details);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// The children of `details` that are rendered: all of them when it's open,
+/// and just its summary when it's closed.
+pub(crate) fn rendered_children(details: &Node, children: Vec<Rc<Node>>) -> Vec<Rc<Node>> {
+    if details.get_attribute("open").is_some() {
+        return children;
+    }
+    let summary = summary_of(details);
+    children
+        .into_iter()
+        .filter(|child| summary.as_ref().is_some_and(|summary| Rc::ptr_eq(summary, child)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_summary_toggles_its_details() {
+        let doc = Document::parse_html(
+            r#"<details id="d"><summary id="s"><b id="b">More</b> <a id="a" href="/">link</a></summary>
+               <summary id="second">Not the summary</summary><p>Body</p></details>"#,
+        )
+        .unwrap();
+        let details = doc.get_element_by_id("d").unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+
+        let toggled = toggled_details(node("b")).unwrap();
+        assert!(Rc::ptr_eq(&toggled, &details));
+        assert!(toggled_details(node("a")).is_none());
+        assert!(toggled_details(node("second")).is_none());
+
+        let rendered = rendered_children(&details, details.children());
+        assert_eq!(rendered.len(), 1);
+        assert!(Rc::ptr_eq(&rendered[0], &node("s")));
+
+        details.set_boolean_attribute("open", true);
+        assert_eq!(rendered_children(&details, details.children()).len(), details.children().len());
+    }
+}
@@ -0,0 +1,95 @@
+//! Deterministic mode.
+//!
+//! For parity captures and reproducible tests, pages can be made to run the
+//! same way every time: `Math.random()` is seeded, time stands still except
+//! when [`Engine::tick`](crate::Engine::tick) advances a virtual clock, so
+//! `Date`, `performance.now()`, timers and CSS animations all step by the
+//! same amounts, and responses can be recorded to disk and replayed from
+//! there byte-exact.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use rustkit_net::NetworkReplay;
+
+/// Settings of deterministic mode (see
+/// [`EngineConfig::determinism`](crate::EngineConfig::determinism)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Determinism {
+    /// Seed of `Math.random()` in every page.
+    pub seed: u64,
+    /// Wall clock time the virtual clock starts at.
+    pub start_time: SystemTime,
+    /// Record responses to, or replay them from, a directory.
+    pub network: Option<NetworkReplay>,
+}
+
+impl Default for Determinism {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            // 2000-01-01T00:00:00Z, far from daylight saving changes
+            start_time: SystemTime::UNIX_EPOCH + Duration::from_secs(946_684_800),
+            network: None,
+        }
+    }
+}
+
+/// Time as deterministic mode sees it: it only moves when advanced.
+#[derive(Debug, Clone)]
+pub(crate) struct VirtualClock {
+    /// The instant the clock started at.
+    origin: Instant,
+    start_time: SystemTime,
+    elapsed: Duration,
+}
+
+impl VirtualClock {
+    pub(crate) fn new(start_time: SystemTime) -> Self {
+        Self {
+            origin: Instant::now(),
+            start_time,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// The clock's current time, as an instant timers and animations are
+    /// due against.
+    pub(crate) fn now(&self) -> Instant {
+        self.origin + self.elapsed
+    }
+
+    /// The clock's current wall clock time.
+    pub(crate) fn date(&self) -> SystemTime {
+        self.start_time + self.elapsed
+    }
+
+    /// Time since the clock started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub(crate) fn advance(&mut self, delta: Duration) {
+        self.elapsed += delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_only_moves_when_advanced() {
+        let start = Determinism::default().start_time;
+        let mut clock = VirtualClock::new(start);
+        let now = clock.now();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(clock.now(), now);
+        assert_eq!(clock.date(), start);
+
+        clock.advance(Duration::from_millis(16));
+        clock.advance(Duration::from_millis(16));
+        assert_eq!(clock.now(), now + Duration::from_millis(32));
+        assert_eq!(clock.date(), start + Duration::from_millis(32));
+        assert_eq!(clock.elapsed(), Duration::from_millis(32));
+    }
+}
@@ -0,0 +1,106 @@
+//! Modal dialog focus.
+//!
+//! Opening a modal dialog moves focus into it, and while it's the topmost
+//! modal, Tab cycles through the focusable elements inside it instead of
+//! the document's. Everything outside is inert: its `::backdrop` covers the
+//! viewport, so clicks never reach it. Escape fires `cancel` at the dialog
+//! and closes it unless the event is cancelled. Closing a dialog returns
+//! focus to the element that had it when the dialog opened.
+
+use std::rc::Rc;
+
+use rustkit_dom::{Node, NodeId};
+
+/// Whether Tab can move focus to `node`.
+pub(crate) fn is_focusable(node: &Node) -> bool {
+    let Some(tag) = node.tag_name() else {
+        return false;
+    };
+    if let Some(tabindex) = node.get_attribute("tabindex").and_then(|value| value.trim().parse::<i32>().ok()) {
+        return tabindex >= 0;
+    }
+    match tag.to_ascii_lowercase().as_str() {
+        "a" => node.get_attribute("href").is_some(),
+        "button" | "select" | "textarea" => node.get_attribute("disabled").is_none(),
+        "input" => node.get_attribute("disabled").is_none() && node.get_attribute("type") != Some("hidden"),
+        "summary" => node.parent().is_some_and(|parent| parent.tag_name() == Some("details")),
+        _ => false,
+    }
+}
+
+/// Focusable elements inside `scope`, in tree order. Hidden popovers and
+/// contents of closed dialogs and details other than their summary are
+/// skipped.
+pub(crate) fn focusable_descendants(scope: &Node) -> Vec<Rc<Node>> {
+    let mut found = Vec::new();
+    collect_focusable(scope, &mut found);
+    found
+}
+
+fn collect_focusable(node: &Node, found: &mut Vec<Rc<Node>>) {
+    let closed = |tag: &str| node.tag_name() == Some(tag) && node.get_attribute("open").is_none();
+    for child in node.children() {
+        if closed("dialog") || (closed("details") && child.tag_name() != Some("summary")) {
+            continue;
+        }
+        if child.popover_state().is_some() && !child.in_top_layer() {
+            continue;
+        }
+        if is_focusable(&child) {
+            found.push(child.clone());
+        }
+        collect_focusable(&child, found);
+    }
+}
+
+/// Where focus goes when `dialog` opens: its first `autofocus` descendant,
+/// else its first focusable one, else the dialog itself.
+pub(crate) fn initial_focus(dialog: &Rc<Node>) -> Rc<Node> {
+    let focusable = focusable_descendants(dialog);
+    focusable
+        .iter()
+        .find(|node| node.get_attribute("autofocus").is_some())
+        .or(focusable.first())
+        .cloned()
+        .unwrap_or_else(|| dialog.clone())
+}
+
+/// The element after `current` in the tab order of `scope`, wrapping
+/// around at the end.
+pub(crate) fn next_focus(scope: &Node, current: Option<NodeId>) -> Option<Rc<Node>> {
+    let focusable = focusable_descendants(scope);
+    let next = current
+        .and_then(|current| focusable.iter().position(|node| node.id == current))
+        .map_or(0, |index| index + 1);
+    focusable.get(next).or(focusable.first()).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_tab_cycles_inside_the_dialog() {
+        let doc = Document::parse_html(
+            r#"<button id="outside">Out</button>
+               <dialog id="d"><p>Sure?</p><a>no href</a><button id="cancel">Cancel</button>
+               <input id="hidden" type="hidden"><button id="ok" autofocus>OK</button></dialog>
+               <dialog id="empty"><p>Nothing to focus</p></dialog>"#,
+        )
+        .unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+        let dialog = node("d");
+
+        // Closed dialogs hide their contents
+        assert_eq!(focusable_descendants(doc.root()).len(), 1);
+        doc.show_modal_dialog(&dialog).unwrap();
+
+        assert_eq!(initial_focus(&dialog).id, node("ok").id);
+        assert_eq!(initial_focus(&node("empty")).id, node("empty").id);
+        assert_eq!(next_focus(&dialog, Some(node("cancel").id)).unwrap().id, node("ok").id);
+        assert_eq!(next_focus(&dialog, Some(node("ok").id)).unwrap().id, node("cancel").id);
+        // Focus left outside moves in
+        assert_eq!(next_focus(&dialog, Some(node("outside").id)).unwrap().id, node("cancel").id);
+    }
+}
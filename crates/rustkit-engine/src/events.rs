@@ -0,0 +1,194 @@
+//! Event delivery to the host.
+//!
+//! Every [`EngineEvent`] goes to the single receiver
+//! [`Engine::take_event_receiver`] hands out, and is broadcast to each
+//! [`EventStream`] from [`Engine::subscribe`], so the UI, a session
+//! manager and metrics can all follow the engine. Streams filter on their
+//! own side, by view or by any test on the event. The receiver only gets
+//! events sent after it's taken, so they don't pile up for hosts that
+//! only subscribe.
+//!
+//! [`Engine::take_event_receiver`]: crate::Engine::take_event_receiver
+//! [`Engine::subscribe`]: crate::Engine::subscribe
+
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{EngineEvent, EngineViewId};
+
+/// Events a subscriber may fall behind by before it misses the oldest.
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// Sends events to the receiver, once taken, and to every subscriber.
+#[derive(Clone)]
+pub(crate) struct EventSender {
+    receiver: Arc<OnceLock<mpsc::UnboundedSender<EngineEvent>>>,
+    subscribers: broadcast::Sender<EngineEvent>,
+}
+
+impl EventSender {
+    /// Send `event`, returning whether the event receiver is still open
+    /// or yet to be taken.
+    pub fn send(&self, event: EngineEvent) -> bool {
+        if self.subscribers.receiver_count() > 0 {
+            let _ = self.subscribers.send(event.clone());
+        }
+        self.receiver.get().is_none_or(|receiver| receiver.send(event).is_ok())
+    }
+
+    /// The event receiver, the first time it's taken.
+    pub fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<EngineEvent>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.receiver.set(sender).ok()?;
+        Some(receiver)
+    }
+
+    pub fn subscribe(&self) -> EventStream {
+        EventStream {
+            events: self.subscribers.subscribe(),
+            view: None,
+            filter: None,
+            missed: 0,
+        }
+    }
+}
+
+/// An event sender, with no receiver or subscribers yet.
+pub(crate) fn channel() -> EventSender {
+    let (subscribers, _) = broadcast::channel(SUBSCRIBER_CAPACITY);
+    EventSender {
+        receiver: Arc::default(),
+        subscribers,
+    }
+}
+
+type EventFilter = Box<dyn Fn(&EngineEvent) -> bool + Send + Sync>;
+
+/// A subscription to engine events, from [`Engine::subscribe`].
+///
+/// A stream that falls more than 1024 events behind skips the oldest;
+/// [`missed`](Self::missed) counts them.
+///
+/// [`Engine::subscribe`]: crate::Engine::subscribe
+pub struct EventStream {
+    events: broadcast::Receiver<EngineEvent>,
+    view: Option<EngineViewId>,
+    filter: Option<EventFilter>,
+    missed: u64,
+}
+
+impl EventStream {
+    /// Only deliver events about `view`, leaving out engine-wide ones
+    /// such as downloads.
+    pub fn for_view(mut self, view: EngineViewId) -> Self {
+        self.view = Some(view);
+        self
+    }
+
+    /// Only deliver events `filter` accepts, e.g.
+    /// `|event| matches!(event, EngineEvent::PageLoaded { .. })`.
+    pub fn filter(mut self, filter: impl Fn(&EngineEvent) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Wait for the next event, or `None` once the engine is gone.
+    pub async fn recv(&mut self) -> Option<EngineEvent> {
+        loop {
+            match self.events.recv().await {
+                Ok(event) if self.accepts(&event) => return Some(event),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => self.missed += missed,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// The next event if one is waiting.
+    pub fn try_recv(&mut self) -> Option<EngineEvent> {
+        loop {
+            match self.events.try_recv() {
+                Ok(event) if self.accepts(&event) => return Some(event),
+                Ok(_) => {}
+                Err(broadcast::error::TryRecvError::Lagged(missed)) => self.missed += missed,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// How many events the stream skipped for falling behind.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    fn accepts(&self, event: &EngineEvent) -> bool {
+        self.view.is_none_or(|view| event.view_id() == Some(view))
+            && self.filter.as_ref().is_none_or(|filter| filter(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn focused(view: EngineViewId) -> EngineEvent {
+        EngineEvent::ViewFocused { view_id: view }
+    }
+
+    #[test]
+    fn test_subscribers_filter_events() {
+        let sender = channel();
+        let mut receiver = sender.take_receiver().unwrap();
+        let (first, second) = (EngineViewId::new(), EngineViewId::new());
+        let mut all = sender.subscribe();
+        let mut second_only = sender.subscribe().for_view(second);
+        let mut zooms = sender
+            .subscribe()
+            .filter(|event| matches!(event, EngineEvent::ZoomChanged { .. }));
+
+        sender.send(focused(first));
+        sender.send(EngineEvent::ZoomChanged { view_id: second, zoom: 2.0 });
+
+        assert!(matches!(receiver.try_recv(), Ok(EngineEvent::ViewFocused { .. })));
+        assert!(matches!(all.try_recv(), Some(EngineEvent::ViewFocused { .. })));
+        assert!(matches!(all.try_recv(), Some(EngineEvent::ZoomChanged { .. })));
+        assert!(all.try_recv().is_none());
+        assert!(matches!(second_only.try_recv(), Some(EngineEvent::ZoomChanged { view_id, .. }) if view_id == second));
+        assert!(second_only.try_recv().is_none());
+        assert!(matches!(zooms.try_recv(), Some(EngineEvent::ZoomChanged { .. })));
+        assert!(zooms.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_receiver_gets_events_once_taken() {
+        let sender = channel();
+        let view = EngineViewId::new();
+        assert!(sender.send(focused(view)));
+
+        let mut receiver = sender.take_receiver().unwrap();
+        assert!(sender.take_receiver().is_none());
+        assert!(receiver.try_recv().is_err());
+        assert!(sender.send(focused(view)));
+        assert!(matches!(receiver.try_recv(), Ok(EngineEvent::ViewFocused { .. })));
+
+        drop(receiver);
+        assert!(!sender.send(focused(view)));
+    }
+
+    #[test]
+    fn test_lagging_subscriber_skips_oldest() {
+        let sender = channel();
+        let view = EngineViewId::new();
+        let mut stream = sender.subscribe();
+        for _ in 0..SUBSCRIBER_CAPACITY + 10 {
+            sender.send(focused(view));
+        }
+        let mut received = 0;
+        while stream.try_recv().is_some() {
+            received += 1;
+        }
+        assert_eq!(received, SUBSCRIBER_CAPACITY);
+        assert_eq!(stream.missed(), 10);
+    }
+}
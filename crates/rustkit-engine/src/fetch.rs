@@ -0,0 +1,384 @@
+//! Page fetches.
+//!
+//! `fetch()` and `XMLHttpRequest` calls queue up in a view's bindings. The
+//! engine turns each into a loader request from the page's origin, so the
+//! loader's interceptor, cookies and origin checks apply, and runs it on
+//! the async runtime. Results come back through [`Engine::pump_fetches`],
+//! which settles them in the page that made them, unless it has navigated
+//! away since. Keepalive fetches outlive their page; others are aborted
+//! with it.
+//!
+//! [`Engine::pump_fetches`]: crate::Engine::pump_fetches
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use rustkit_bindings::{FetchCredentials, FetchMode, FetchRequest, FetchResponse, FetchResponseType};
+use rustkit_net::{CredentialsMode, Origin, RedirectChain, Request, RequestMode, ResourceLoader, Response};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::network_log::Transfer;
+use crate::EngineViewId;
+
+/// Response headers a page may read from another origin without the
+/// server listing them in `Access-Control-Expose-Headers`.
+const SAFELISTED_RESPONSE_HEADERS: &[&str] = &[
+    "cache-control",
+    "content-language",
+    "content-length",
+    "content-type",
+    "expires",
+    "last-modified",
+    "pragma",
+];
+
+/// Request headers pages can't set, as the engine controls them; those
+/// starting with `proxy-` or `sec-` neither.
+const FORBIDDEN_REQUEST_HEADERS: &[&str] = &[
+    "accept-charset",
+    "accept-encoding",
+    "access-control-request-headers",
+    "access-control-request-method",
+    "connection",
+    "content-length",
+    "cookie",
+    "cookie2",
+    "date",
+    "dnt",
+    "expect",
+    "host",
+    "keep-alive",
+    "origin",
+    "referer",
+    "set-cookie",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "via",
+];
+
+/// A finished page fetch.
+pub(crate) struct FetchOutcome {
+    pub view_id: EngineViewId,
+    /// The view's page generation when the fetch started.
+    pub page: u64,
+    pub id: u64,
+    /// The loader request's id, which the view's network log knows it by.
+    pub request_id: u64,
+    /// The response, or why the fetch failed.
+    pub result: Result<FetchResponse, String>,
+    /// What the loader got back, or why the request failed.
+    pub transfer: Result<Transfer, String>,
+    /// When the response came, before the engine got to it.
+    pub finished_at: Instant,
+}
+
+/// Page fetches in flight.
+pub(crate) struct PageFetches {
+    tx: mpsc::UnboundedSender<FetchOutcome>,
+    rx: mpsc::UnboundedReceiver<FetchOutcome>,
+    /// Running fetches, with the view that made them and whether they're
+    /// keepalive.
+    tasks: Vec<(EngineViewId, bool, JoinHandle<()>)>,
+}
+
+impl PageFetches {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            rx,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Start a fetch for the page `page` of a view. Without an async
+    /// runtime to run it on, it fails straight away.
+    pub fn start(
+        &mut self,
+        loader: &Arc<ResourceLoader>,
+        view_id: EngineViewId,
+        page: u64,
+        id: u64,
+        request: Request,
+        keepalive: bool,
+    ) {
+        let tx = self.tx.clone();
+        let request_id = request.id.raw();
+        if tokio::runtime::Handle::try_current().is_err() {
+            let message = "No runtime to fetch on".to_string();
+            let _ = tx.send(FetchOutcome {
+                view_id,
+                page,
+                id,
+                request_id,
+                result: Err(message.clone()),
+                transfer: Err(message),
+                finished_at: Instant::now(),
+            });
+            return;
+        }
+        let loader = Arc::clone(loader);
+        let task = tokio::spawn(async move {
+            let mode = request.mode;
+            let origin = request.origin.clone();
+            let response = if keepalive {
+                loader.fetch_keepalive(request).await
+            } else {
+                loader.fetch(request).await
+            };
+            let finished_at = Instant::now();
+            let (result, transfer) = match response {
+                Ok(response) => {
+                    let transfer = Transfer::of(&response);
+                    let cross_origin = origin.is_some_and(|origin| crossed_origins(&origin, &response.url, &response.redirects));
+                    (read_response(response, mode, cross_origin).await, Ok(transfer))
+                }
+                Err(e) => (Err(e.to_string()), Err(e.to_string())),
+            };
+            let _ = tx.send(FetchOutcome {
+                view_id,
+                page,
+                id,
+                request_id,
+                result,
+                transfer,
+                finished_at,
+            });
+        });
+        self.tasks.retain(|(_, _, task)| !task.is_finished());
+        self.tasks.push((view_id, keepalive, task));
+    }
+
+    /// Take the fetches that finished since the last call.
+    pub fn take_finished(&mut self) -> Vec<FetchOutcome> {
+        let mut outcomes = Vec::new();
+        while let Ok(outcome) = self.rx.try_recv() {
+            outcomes.push(outcome);
+        }
+        self.tasks.retain(|(_, _, task)| !task.is_finished());
+        outcomes
+    }
+
+    /// Whether fetches are running or finished ones await
+    /// [`take_finished`](Self::take_finished).
+    pub fn has_pending(&self) -> bool {
+        !self.rx.is_empty() || self.tasks.iter().any(|(_, _, task)| !task.is_finished())
+    }
+
+    /// Abort a view's fetches, except keepalive ones.
+    pub fn cancel_view(&mut self, view_id: EngineViewId) {
+        self.tasks.retain(|(owner, keepalive, task)| {
+            let cancel = *owner == view_id && !keepalive;
+            if cancel {
+                task.abort();
+            }
+            !cancel
+        });
+    }
+
+    /// Abort fetches, except keepalive ones, which are waited for.
+    pub async fn finish_keepalive(&mut self) {
+        for (_, keepalive, task) in std::mem::take(&mut self.tasks) {
+            if keepalive {
+                let _ = task.await;
+            } else {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// Build the loader request for a page's fetch, resolving its URL against
+/// the page's.
+pub(crate) fn page_request(fetch: &FetchRequest, page_url: &Url) -> Result<Request, String> {
+    let url = page_url.join(&fetch.url).map_err(|_| format!("Invalid URL: {}", fetch.url))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Can't fetch {} URLs", url.scheme()));
+    }
+    let method: http::Method = fetch
+        .method
+        .parse()
+        .map_err(|_| format!("Invalid method: {}", fetch.method))?;
+    if matches!(method.as_str(), "CONNECT" | "TRACE" | "TRACK") {
+        return Err(format!("Forbidden method: {method}"));
+    }
+
+    let mut request = Request::get(url);
+    request.method = method;
+    for (name, value) in &fetch.headers {
+        let name = http::HeaderName::try_from(name.as_str()).map_err(|_| format!("Invalid header name: {name}"))?;
+        let value = http::HeaderValue::try_from(value.as_str()).map_err(|_| format!("Invalid header value for {name}"))?;
+        // Left out without failing the request, as browsers do
+        if is_forbidden_header(name.as_str()) {
+            continue;
+        }
+        request.headers.append(name, value);
+    }
+    request.body = fetch.body.clone().map(Into::into);
+    request.mode = match fetch.mode {
+        FetchMode::SameOrigin => RequestMode::SameOrigin,
+        FetchMode::Cors => RequestMode::Cors,
+        FetchMode::NoCors => RequestMode::NoCors,
+    };
+    request.credentials = match fetch.credentials {
+        FetchCredentials::Omit => CredentialsMode::Omit,
+        FetchCredentials::SameOrigin => CredentialsMode::SameOrigin,
+        FetchCredentials::Include => CredentialsMode::Include,
+    };
+    request.origin = Some(Origin::from_url(page_url));
+    request.referrer = Some(page_url.clone());
+    Ok(request)
+}
+
+/// Whether a page may not set a request header (see
+/// [`FORBIDDEN_REQUEST_HEADERS`]). Header names are lowercase.
+fn is_forbidden_header(name: &str) -> bool {
+    FORBIDDEN_REQUEST_HEADERS.contains(&name) || name.starts_with("proxy-") || name.starts_with("sec-")
+}
+
+/// Whether a response from `url`, or any request of the `redirects` on
+/// the way there, came from another origin than `origin`.
+fn crossed_origins(origin: &Origin, url: &Url, redirects: &RedirectChain) -> bool {
+    redirects
+        .redirects
+        .iter()
+        .map(|redirect| &redirect.from_url)
+        .chain([url])
+        .any(|url| !origin.same_origin(&Origin::from_url(url)))
+}
+
+/// Read a response into what the page may see of it: all of a
+/// same-origin one, the body and safelisted or exposed headers of a
+/// cross-origin one, and nothing of a `no-cors` cross-origin one.
+async fn read_response(response: Response, mode: RequestMode, cross_origin: bool) -> Result<FetchResponse, String> {
+    if cross_origin && mode == RequestMode::NoCors {
+        return Ok(FetchResponse {
+            url: String::new(),
+            status: 0,
+            status_text: String::new(),
+            headers: Vec::new(),
+            body: String::new(),
+            redirected: false,
+            response_type: FetchResponseType::Opaque,
+        });
+    }
+
+    let exposed: Vec<String> = response
+        .headers
+        .get_all(http::header::ACCESS_CONTROL_EXPOSE_HEADERS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    let readable = |name: &str| {
+        name != "set-cookie"
+            && (!cross_origin
+                || SAFELISTED_RESPONSE_HEADERS.contains(&name)
+                || exposed.iter().any(|exposed| exposed == name || exposed == "*"))
+    };
+    let headers = response
+        .headers
+        .iter()
+        .filter(|(name, _)| readable(name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    let url = response.url.to_string();
+    let status = response.status;
+    let redirected = !response.redirects.redirects.is_empty();
+    let body = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(FetchResponse {
+        url,
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        redirected,
+        response_type: if cross_origin {
+            FetchResponseType::Cors
+        } else {
+            FetchResponseType::Basic
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch(url: &str, mode: FetchMode) -> FetchRequest {
+        FetchRequest {
+            id: 1,
+            url: url.to_string(),
+            method: "PUT".to_string(),
+            headers: vec![("x-token".to_string(), "abc".to_string())],
+            body: Some("data".to_string()),
+            mode,
+            credentials: FetchCredentials::Include,
+            keepalive: false,
+        }
+    }
+
+    #[test]
+    fn test_page_request() {
+        let page = Url::parse("https://example.test/app/index.html").unwrap();
+
+        let request = page_request(&fetch("api?q=1", FetchMode::Cors), &page).unwrap();
+        assert_eq!(request.url.as_str(), "https://example.test/app/api?q=1");
+        assert_eq!(request.method, http::Method::PUT);
+        assert_eq!(request.headers["x-token"], "abc");
+        assert_eq!(request.body.as_deref(), Some(&b"data"[..]));
+        assert_eq!(request.mode, RequestMode::Cors);
+        assert_eq!(request.credentials, CredentialsMode::Include);
+        assert_eq!(request.referrer.as_ref(), Some(&page));
+        assert!(!request.is_cross_origin());
+
+        let request = page_request(&fetch("https://other.test/", FetchMode::SameOrigin), &page).unwrap();
+        assert_eq!(request.mode, RequestMode::SameOrigin);
+        assert!(request.is_cross_origin());
+
+        assert!(page_request(&fetch("file:///etc/passwd", FetchMode::Cors), &page).is_err());
+        let mut trace = fetch("/", FetchMode::Cors);
+        trace.method = "TRACE".to_string();
+        assert!(page_request(&trace, &page).is_err());
+
+        // Headers the engine controls are left out
+        let mut spoofed = fetch("/", FetchMode::Cors);
+        for name in ["Cookie", "Host", "Origin", "Referer", "Content-Length", "Connection", "Sec-Fetch-Site", "Proxy-Authorization"] {
+            spoofed.headers.push((name.to_string(), "x".to_string()));
+        }
+        let request = page_request(&spoofed, &page).unwrap();
+        assert_eq!(request.headers.keys().map(|name| name.as_str()).collect::<Vec<_>>(), ["x-token"]);
+    }
+
+    #[test]
+    fn test_crossed_origins() {
+        let page = Url::parse("https://example.test/").unwrap();
+        let origin = Origin::from_url(&page);
+        let crossed = |url: &str, via: &[&str]| {
+            let url = Url::parse(url).unwrap();
+            let mut redirects = RedirectChain::new();
+            let mut from = page.join("/start").unwrap();
+            for to in via.iter().map(|to| Url::parse(to).unwrap()).chain([url.clone()]) {
+                redirects.redirects.push(rustkit_net::RedirectInfo {
+                    from_url: from,
+                    to_url: to.clone(),
+                    redirect_type: rustkit_net::RedirectType::Found,
+                    method_changed: false,
+                });
+                from = to;
+            }
+            crossed_origins(&origin, &url, &redirects)
+        };
+
+        assert!(!crossed("https://example.test/end", &[]));
+        assert!(crossed("https://other.test/end", &[]));
+        // Back to the page's origin, by way of another
+        assert!(crossed("https://example.test/end", &["https://other.test/bounce"]));
+    }
+}
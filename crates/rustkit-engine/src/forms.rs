@@ -0,0 +1,371 @@
+//! Form submission.
+//!
+//! Clicking a submit button, pressing Enter or Space while one has focus,
+//! or pressing Enter in a text field submits the field's form. `submit`
+//! fires at the form and, unless a listener cancels it, the names and
+//! values of the form's controls are encoded as its `enctype` says: into
+//! the query of the `action` URL for `method=get`, or as the request body
+//! for `method=post`. The view loads the response when
+//! [`Engine::pump_navigations`] next runs. A `method=dialog` form closes
+//! the dialog it's in instead. The submit button's `formaction`,
+//! `formmethod` and `formenctype` override the form's attributes.
+//!
+//! [`Engine::pump_navigations`]: crate::Engine::pump_navigations
+
+use std::rc::Rc;
+
+use rustkit_dom::{Document, FormDataEntry, FormDataValue, FormEnctype, FormMethod, FormState, InputType, Node};
+use url::Url;
+
+use crate::links;
+
+/// Elements that submit a name and value with their form.
+const CONTROLS: [&str; 4] = ["button", "input", "select", "textarea"];
+
+fn is_element(node: &Node, name: &str) -> bool {
+    node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+}
+
+/// The body of a `method=post` submission.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FormBody {
+    /// The `Content-Type` the body is sent with.
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// What submitting a form does.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Submission {
+    /// Load `url`, posting `body` if there is one.
+    Navigate { url: Url, body: Option<FormBody> },
+    /// Close the `<dialog>` the form is in.
+    CloseDialog,
+}
+
+/// Whether a control is disabled, itself or by a disabled `<fieldset>`
+/// around it.
+fn is_disabled(control: &Node) -> bool {
+    if control.get_attribute("disabled").is_some() {
+        return true;
+    }
+    let mut current = control.parent();
+    while let Some(node) = current {
+        if is_element(&node, "fieldset") && node.get_attribute("disabled").is_some() {
+            return true;
+        }
+        current = node.parent();
+    }
+    false
+}
+
+fn is_submit_button(node: &Node) -> bool {
+    if is_element(node, "button") {
+        // Buttons with a missing or unknown type submit
+        return !node
+            .get_attribute("type")
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("button") || kind.eq_ignore_ascii_case("reset"));
+    }
+    is_element(node, "input") && matches!(node.input_type(), InputType::Submit | InputType::Image)
+}
+
+/// The enabled submit button `node` is or is in.
+pub(crate) fn submit_button(node: Rc<Node>) -> Option<Rc<Node>> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if is_element(&node, "button") || is_element(&node, "input") {
+            return (is_submit_button(&node) && !is_disabled(&node)).then_some(node);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// The form `control` belongs to: the one its `form` attribute names, or
+/// else the nearest `<form>` around it.
+pub(crate) fn form_owner(control: &Node, document: &Document) -> Option<Rc<Node>> {
+    if let Some(form_id) = control.get_attribute("form") {
+        return document.get_element_by_id(form_id).filter(|form| is_element(form, "form"));
+    }
+    let mut current = control.parent();
+    while let Some(node) = current {
+        if is_element(&node, "form") {
+            return Some(node);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// The controls of `form`, in tree order.
+fn controls(form: &Rc<Node>, document: &Document) -> Vec<Rc<Node>> {
+    let mut controls = Vec::new();
+    document.traverse(|node| {
+        if CONTROLS.iter().any(|name| is_element(node, name))
+            && form_owner(node, document).is_some_and(|owner| Rc::ptr_eq(&owner, form))
+        {
+            controls.push(node.clone());
+        }
+    });
+    controls
+}
+
+/// The form pressing Enter in `field` submits, with the button that
+/// submits it: the form's first submit button. Only text fields submit
+/// this way, and a form without a submit button only when the field is its
+/// one text field.
+pub(crate) fn implicit_submission(field: &Node, document: &Document) -> Option<(Rc<Node>, Option<Rc<Node>>)> {
+    if !is_element(field, "input") || !field.input_type().is_text_input() || is_disabled(field) {
+        return None;
+    }
+    let form = form_owner(field, document)?;
+    let controls = controls(&form, document);
+    match controls.iter().find(|control| is_submit_button(control)) {
+        Some(button) if is_disabled(button) => None,
+        Some(button) => Some((form.clone(), Some(button.clone()))),
+        None => {
+            let fields = controls
+                .iter()
+                .filter(|control| is_element(control, "input") && control.input_type().is_text_input())
+                .count();
+            (fields == 1).then_some((form, None))
+        }
+    }
+}
+
+/// The names and values `form` submits when `submitter` submits it.
+pub(crate) fn form_data(form: &Rc<Node>, submitter: Option<&Rc<Node>>, document: &Document) -> Vec<FormDataEntry> {
+    let mut entries = Vec::new();
+    let mut push = |name: &str, value: FormDataValue| {
+        entries.push(FormDataEntry {
+            name: name.to_string(),
+            value,
+        })
+    };
+    for control in controls(form, document) {
+        if is_disabled(&control) {
+            continue;
+        }
+        let is_submitter = submitter.is_some_and(|submitter| Rc::ptr_eq(submitter, &control));
+        let input_type = is_element(&control, "input").then(|| control.input_type());
+        let name = control.get_attribute("name").unwrap_or_default();
+
+        // An image button submits where it was pressed, which keyboard and
+        // script submissions don't have
+        if input_type == Some(InputType::Image) {
+            if is_submitter {
+                let prefix = if name.is_empty() { String::new() } else { format!("{name}.") };
+                push(&format!("{prefix}x"), FormDataValue::String("0".to_string()));
+                push(&format!("{prefix}y"), FormDataValue::String("0".to_string()));
+            }
+            continue;
+        }
+        if name.is_empty() {
+            continue;
+        }
+
+        if is_element(&control, "select") {
+            let options = control.select_options();
+            let multiple = control.get_attribute("multiple").is_some();
+            let selected = control.selected_index();
+            for (index, option) in options.iter().enumerate() {
+                let chosen = if multiple { option.is_option_selected() } else { selected == Some(index) };
+                if chosen && !option.is_option_disabled() {
+                    push(name, FormDataValue::String(option.option_value()));
+                }
+            }
+        } else if is_element(&control, "textarea") {
+            push(name, FormDataValue::String(control.text_content()));
+        } else if is_element(&control, "button") || input_type.is_some_and(|kind| kind.is_button()) {
+            // Only the button that submitted the form is sent
+            if is_submitter {
+                let value = control.get_attribute("value").unwrap_or_default();
+                push(name, FormDataValue::String(value.to_string()));
+            }
+        } else if input_type.is_some_and(|kind| kind.is_checkable()) {
+            if control.get_attribute("checked").is_some() {
+                let value = control.get_attribute("value").unwrap_or("on");
+                push(name, FormDataValue::String(value.to_string()));
+            }
+        } else if input_type == Some(InputType::File) {
+            // No file can be chosen yet, so an empty one is sent
+            let file = FormDataValue::File {
+                name: String::new(),
+                content: Vec::new(),
+            };
+            push(name, file);
+        } else {
+            push(name, FormDataValue::String(control.input_value()));
+        }
+    }
+    entries
+}
+
+/// An attribute of the submission: the submitter's `form`-prefixed
+/// override, else the form's own.
+fn attribute<'a>(form: &'a Node, submitter: Option<&'a Rc<Node>>, name: &str) -> Option<&'a str> {
+    submitter
+        .and_then(|submitter| submitter.get_attribute(&format!("form{name}")))
+        .or_else(|| form.get_attribute(name))
+}
+
+/// What submitting `form` with `submitter` does, for a document at
+/// `base_url`. `None` if the action isn't a URL the view can load.
+pub(crate) fn submission(
+    form: &Rc<Node>,
+    submitter: Option<&Rc<Node>>,
+    document: &Document,
+    base_url: Option<&Url>,
+) -> Option<Submission> {
+    let method = attribute(form, submitter, "method").map_or(FormMethod::Get, FormMethod::from_str);
+    if method == FormMethod::Dialog {
+        return Some(Submission::CloseDialog);
+    }
+
+    // A missing or empty action submits to the document's own URL
+    let action = attribute(form, submitter, "action").map(str::trim).filter(|action| !action.is_empty());
+    let mut url = match (action, base_url) {
+        (Some(action), Some(base)) => base.join(action).ok()?,
+        (Some(action), None) => Url::parse(action).ok()?,
+        (None, base) => base?.clone(),
+    };
+    if !links::page_may_navigate_to(&url) {
+        return None;
+    }
+
+    let entries = form_data(form, submitter, document);
+    let body = match method {
+        FormMethod::Post if matches!(url.scheme(), "http" | "https") => {
+            let enctype = attribute(form, submitter, "enctype").map_or(FormEnctype::UrlEncoded, FormEnctype::from_str);
+            Some(FormBody {
+                content_type: enctype.body_content_type(),
+                data: FormState::encode_form_data(&entries, enctype),
+            })
+        }
+        FormMethod::Post => None,
+        _ => {
+            let query = FormState::encode_form_data(&entries, FormEnctype::UrlEncoded);
+            url.set_query(Some(&String::from_utf8_lossy(&query)));
+            None
+        }
+    };
+    Some(Submission::Navigate { url, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_data() {
+        let doc = Document::parse_html(
+            r#"<form id="f">
+                <input name="q" value="rust kit">
+                <input name="disabled" disabled value="x">
+                <input value="no name">
+                <input type="checkbox" name="c1" checked>
+                <input type="checkbox" name="c2" value="no">
+                <input type="radio" name="r" value="a">
+                <input type="radio" name="r" value="b" checked>
+                <fieldset disabled><input name="off" value="1"></fieldset>
+                <select name="s"><option>One</option><option value="2" selected>Two</option></select>
+                <select name="m" multiple><option selected> A  a </option><option>B</option><option selected disabled>C</option></select>
+                <textarea name="t">Line</textarea>
+                <button id="go" name="go" value="yes">Go</button>
+                <button name="other" value="no">Other</button>
+            </form>
+            <input name="outside" value="1">
+            <input name="linked" value="1" form="f">"#,
+        )
+        .unwrap();
+        let form = doc.get_element_by_id("f").unwrap();
+        let go = doc.get_element_by_id("go").unwrap();
+
+        let entries: Vec<(String, String)> = form_data(&form, Some(&go), &doc)
+            .into_iter()
+            .map(|entry| match entry.value {
+                FormDataValue::String(value) => (entry.name, value),
+                FormDataValue::File { .. } => panic!("unexpected file"),
+            })
+            .collect();
+        let expected = [
+            ("q", "rust kit"),
+            ("c1", "on"),
+            ("r", "b"),
+            ("s", "2"),
+            ("m", "A a"),
+            ("t", "Line"),
+            ("go", "yes"),
+            ("linked", "1"),
+        ];
+        assert_eq!(
+            entries,
+            expected.map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_submission() {
+        let doc = Document::parse_html(
+            r#"<form id="get" action="search?old=1#results">
+                <input id="q" name="q" value="a b&c">
+                <button id="b" type="button">Not a submitter</button>
+                <span id="label"><input type="submit" id="go"></span>
+            </form>
+            <form id="post" method="post" action="/login">
+                <input name="user" value="me">
+                <button id="upload" name="upload" formenctype="multipart/form-data" formaction="/upload">Upload</button>
+            </form>
+            <form id="two"><input id="first"><input id="second"></form>
+            <form id="dialog" method="dialog"></form>
+            <form id="internal" action="about:settings"></form>"#,
+        )
+        .unwrap();
+        let base = Url::parse("https://example.com/dir/page.html").unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+
+        assert!(submit_button(node("b")).is_none());
+        let go = submit_button(node("go")).unwrap();
+        assert!(Rc::ptr_eq(&go, &node("go")));
+        assert!(Rc::ptr_eq(&form_owner(&go, &doc).unwrap(), &node("get")));
+
+        let get = submission(&node("get"), Some(&go), &doc, Some(&base)).unwrap();
+        assert_eq!(
+            get,
+            Submission::Navigate {
+                url: Url::parse("https://example.com/dir/search?q=a%20b%26c#results").unwrap(),
+                body: None,
+            }
+        );
+
+        // Enter submits through the first submit button, or without one
+        // when the field is the form's only text field
+        let (form, submitter) = implicit_submission(&node("q"), &doc).unwrap();
+        assert!(Rc::ptr_eq(&form, &node("get")));
+        assert!(submitter.is_some_and(|submitter| Rc::ptr_eq(&submitter, &go)));
+        assert!(implicit_submission(&node("first"), &doc).is_none());
+
+        let post = node("post");
+        let Some(Submission::Navigate { url, body: Some(body) }) = submission(&post, None, &doc, Some(&base)) else {
+            panic!("expected a post");
+        };
+        assert_eq!(url.as_str(), "https://example.com/login");
+        assert_eq!(body.content_type, "application/x-www-form-urlencoded");
+        assert_eq!(body.data, b"user=me");
+
+        let upload = node("upload");
+        let Some(Submission::Navigate { url, body: Some(body) }) = submission(&post, Some(&upload), &doc, Some(&base))
+        else {
+            panic!("expected a post");
+        };
+        assert_eq!(url.as_str(), "https://example.com/upload");
+        assert!(body.content_type.starts_with("multipart/form-data; boundary="));
+        assert!(String::from_utf8(body.data).unwrap().contains("name=\"upload\"\r\n\r\n\r\n"));
+
+        assert_eq!(
+            submission(&node("dialog"), None, &doc, Some(&base)),
+            Some(Submission::CloseDialog)
+        );
+        assert!(submission(&node("internal"), None, &doc, Some(&base)).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+//! Fuzzing entry points for the engine's parsers.
+//!
+//! Each function takes arbitrary bytes, runs them through one family of
+//! parsers, and discards the result; the only failure is a panic or hang.
+//! They need no GPU or view, so fuzz targets can call them in a loop.
+//! Enabled by the `fuzzing` feature; the cargo-fuzz targets live in
+//! `fuzz/` at the repository root.
+
+use std::collections::HashMap;
+
+use rustkit_css::{ComputedStyle, PropertyValue, Stylesheet};
+use rustkit_dom::Document;
+
+use crate::cascade::Cascade;
+use crate::{parse_gradient, SelectorElement, SiblingPosition, StyleResolver};
+
+/// Parse a stylesheet, then match its selectors and apply its
+/// declarations to a style, covering the engine's value parsers.
+pub fn fuzz_parse_css(data: &[u8]) {
+    let css = String::from_utf8_lossy(data);
+    let Ok(stylesheet) = Stylesheet::parse(&css) else {
+        return;
+    };
+
+    let mut style = ComputedStyle::new();
+    let mut css_vars = HashMap::new();
+    for rule in &stylesheet.rules {
+        fuzz_selector(&rule.selector);
+        for declaration in &rule.declarations {
+            if let PropertyValue::Specified(value) = &declaration.value {
+                if declaration.property.starts_with("--") {
+                    css_vars.insert(declaration.property.clone(), value.clone());
+                }
+                let value = StyleResolver.resolve_css_variables(value, &css_vars);
+                StyleResolver.apply_style_property(&mut style, &declaration.property, &value);
+            }
+        }
+    }
+
+    // The same text as a style attribute
+    let mut cascade = Cascade::default();
+    cascade.add_inline_style(&css);
+    cascade.apply(|property, value| {
+        let value = StyleResolver.resolve_css_variables(value, &css_vars);
+        StyleResolver.apply_style_property(&mut style, property, &value);
+    });
+}
+
+/// Compute a selector's specificity and match it against a small tree.
+pub fn fuzz_parse_selector(data: &[u8]) {
+    fuzz_selector(&String::from_utf8_lossy(data));
+}
+
+/// Parse a `*-gradient()` value.
+pub fn fuzz_parse_gradient(data: &[u8]) {
+    let _ = parse_gradient(&String::from_utf8_lossy(data));
+}
+
+/// Parse an HTML document and walk the result.
+pub fn fuzz_parse_html(data: &[u8]) {
+    if let Ok(document) = Document::parse_html(&String::from_utf8_lossy(data)) {
+        let _ = document.title();
+        document.traverse(|node| {
+            let _ = node.text_content();
+        });
+    }
+}
+
+fn fuzz_selector(selector: &str) {
+    let _ = StyleResolver.selector_specificity(selector);
+
+    let element = |tag: &str, attributes: &[(&str, &str)]| SelectorElement {
+        tag_name: tag.to_string(),
+        attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        position: SiblingPosition::ONLY,
+    };
+    let ancestors = [
+        element("section", &[("class", "outer"), ("lang", "en-US")]),
+        element("body", &[]),
+        element("html", &[]),
+    ];
+    let siblings = [element("h1", &[("id", "title")])];
+    let attributes: HashMap<String, String> = [("id", "x"), ("class", "a b"), ("data-state", "open")]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let position = SiblingPosition {
+        index: 1,
+        count: 3,
+        type_index: 0,
+        type_count: 2,
+    };
+    let _ = StyleResolver.selector_matches(selector, "div", &attributes, &ancestors, &siblings, position);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz_entry_points_survive_malformed_input() {
+        let inputs: &[&[u8]] = &[
+            b"",
+            b"\xff\xfe\x00",
+            b"a{color:rgb(1,2,3)}",
+            b"div > p ~ :nth-child(2n+1 of .a):not(:is(,)) [x|=\"",
+            b"linear-gradient(to left top, red 10%, , blue calc(100% - ",
+            b"radial-gradient(circle at 50% 50%",
+            b"conic-gradient(from 90deg",
+            b"@media (min-width: 1px) { a { width: calc(1px + (2 * )); grid-template: repeat(999999, 1fr) / ; } }",
+            b"<table><td><svg><style>a{</style><template><p>",
+            b":root{--a:var(--a)} b{margin:var(--a, var(--b,)) !important}",
+        ];
+        for input in inputs {
+            fuzz_parse_css(input);
+            fuzz_parse_selector(input);
+            fuzz_parse_gradient(input);
+            fuzz_parse_html(input);
+        }
+    }
+}
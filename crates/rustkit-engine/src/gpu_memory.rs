@@ -0,0 +1,215 @@
+//! GPU memory reports.
+//!
+//! To find what grows when many tabs are open, the engine can report the
+//! GPU memory it holds: what all views share (the renderer's image and
+//! glyph caches, offscreen targets and uniform buffers) apart from what
+//! each view holds (its surface or headless texture and its page's raster
+//! tiles), with the vertex and index data each view's last frame streamed
+//! to the GPU. Sizes are worked out from the resources' formats and
+//! dimensions, as wgpu doesn't track them.
+//!
+//! Reports warn when a view comes close to the adapter's limits, which
+//! fail rendering outright rather than slowing it down.
+
+use rustkit_compositor::SurfaceMemory;
+use rustkit_renderer::GpuMemoryUsage;
+
+use crate::EngineViewId;
+
+/// How close to an adapter limit a view gets before it's warned about.
+const WARNING_FRACTION: f64 = 0.9;
+
+/// GPU memory the engine holds (see
+/// [`Engine::gpu_memory_report`](crate::Engine::gpu_memory_report)).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuMemoryReport {
+    /// What all views share: the renderer's image and glyph caches,
+    /// offscreen targets and uniform buffers, and the compositor's.
+    pub shared: GpuMemoryUsage,
+    /// What each view holds, in the order views were created.
+    pub views: Vec<ViewGpuMemory>,
+    /// Formats of the views' frames, each once.
+    pub surface_formats: Vec<wgpu::TextureFormat>,
+    /// Adapter limits views are close to.
+    pub warnings: Vec<GpuMemoryWarning>,
+}
+
+impl GpuMemoryReport {
+    /// Textures held, shared and by every view.
+    pub fn texture_count(&self) -> usize {
+        self.shared.textures + self.views.iter().map(ViewGpuMemory::texture_count).sum::<usize>()
+    }
+
+    /// Bytes of textures held, shared and by every view.
+    pub fn texture_bytes(&self) -> u64 {
+        self.shared.texture_bytes + self.views.iter().map(ViewGpuMemory::texture_bytes).sum::<u64>()
+    }
+
+    /// Bytes of buffers held between frames.
+    pub fn buffer_bytes(&self) -> u64 {
+        self.shared.buffer_bytes + self.views.iter().map(|view| view.tiles.buffer_bytes).sum::<u64>()
+    }
+
+    /// Bytes of textures and buffers held between frames.
+    pub fn total_bytes(&self) -> u64 {
+        self.texture_bytes() + self.buffer_bytes()
+    }
+}
+
+/// GPU memory one view holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewGpuMemory {
+    /// The view.
+    pub view_id: EngineViewId,
+    /// The view's surface or headless texture, if it has one.
+    pub surface: Option<SurfaceMemory>,
+    /// Raster tiles of the view's page.
+    pub tiles: GpuMemoryUsage,
+    /// Vertex and index buffers the view's last frame uploaded. They're
+    /// dropped once the frame is submitted, so they aren't counted in the
+    /// view's total.
+    pub frame_uploads: GpuMemoryUsage,
+}
+
+impl ViewGpuMemory {
+    /// Textures the view holds.
+    pub fn texture_count(&self) -> usize {
+        self.surface.map_or(0, |surface| surface.textures) + self.tiles.textures
+    }
+
+    /// Bytes of textures the view holds.
+    pub fn texture_bytes(&self) -> u64 {
+        self.surface.map_or(0, |surface| surface.bytes) + self.tiles.texture_bytes
+    }
+
+    /// Bytes the view holds between frames.
+    pub fn total_bytes(&self) -> u64 {
+        self.texture_bytes() + self.tiles.buffer_bytes
+    }
+}
+
+/// An adapter limit a view is close to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuMemoryWarning {
+    /// The view's frames are nearly as wide or tall as the adapter's
+    /// largest texture; resizing it further will fail.
+    SurfaceSize {
+        view_id: EngineViewId,
+        size: (u32, u32),
+        max_texture_dimension: u32,
+    },
+    /// The vertex and index data the view's last frame uploaded is nearly
+    /// as large as the adapter's largest buffer, which a single batch of
+    /// draws can't exceed.
+    FrameUploads {
+        view_id: EngineViewId,
+        bytes: u64,
+        max_buffer_size: u64,
+    },
+}
+
+impl std::fmt::Display for GpuMemoryWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SurfaceSize {
+                view_id,
+                size: (width, height),
+                max_texture_dimension,
+            } => write!(
+                f,
+                "View {view_id:?} is {width}x{height}, near the largest texture of {max_texture_dimension}px"
+            ),
+            Self::FrameUploads {
+                view_id,
+                bytes,
+                max_buffer_size,
+            } => write!(
+                f,
+                "View {view_id:?} uploaded {bytes} bytes of vertices last frame, near the largest buffer of {max_buffer_size} bytes"
+            ),
+        }
+    }
+}
+
+/// The adapter limits `view` is close to.
+pub(crate) fn warnings(view: &ViewGpuMemory, limits: &wgpu::Limits) -> Vec<GpuMemoryWarning> {
+    let near = |value: u64, limit: u64| value as f64 >= limit as f64 * WARNING_FRACTION;
+    let mut warnings = Vec::new();
+    if let Some(surface) = view.surface {
+        let max = limits.max_texture_dimension_2d;
+        if near(surface.width.max(surface.height).into(), max.into()) {
+            warnings.push(GpuMemoryWarning::SurfaceSize {
+                view_id: view.view_id,
+                size: (surface.width, surface.height),
+                max_texture_dimension: max,
+            });
+        }
+    }
+    if near(view.frame_uploads.buffer_bytes, limits.max_buffer_size) {
+        warnings.push(GpuMemoryWarning::FrameUploads {
+            view_id: view.view_id,
+            bytes: view.frame_uploads.buffer_bytes,
+            max_buffer_size: limits.max_buffer_size,
+        });
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(width: u32, height: u32, uploaded: u64) -> ViewGpuMemory {
+        ViewGpuMemory {
+            view_id: EngineViewId::new(),
+            surface: Some(SurfaceMemory {
+                format: wgpu::TextureFormat::Bgra8Unorm,
+                width,
+                height,
+                textures: 3,
+                bytes: rustkit_renderer::texture_bytes(wgpu::TextureFormat::Bgra8Unorm, width, height) * 3,
+            }),
+            tiles: GpuMemoryUsage { textures: 2, texture_bytes: 2 * 512 * 512 * 4, ..Default::default() },
+            frame_uploads: GpuMemoryUsage { buffers: 2, buffer_bytes: uploaded, ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn test_totals() {
+        let report = GpuMemoryReport {
+            shared: GpuMemoryUsage { textures: 1, texture_bytes: 1000, buffers: 4, buffer_bytes: 256 },
+            views: vec![view(100, 50, 4096), view(10, 10, 0)],
+            surface_formats: vec![wgpu::TextureFormat::Bgra8Unorm],
+            warnings: Vec::new(),
+        };
+        assert_eq!(report.views[0].total_bytes(), 100 * 50 * 4 * 3 + 2 * 512 * 512 * 4);
+        assert_eq!(report.texture_count(), 1 + 5 + 5);
+        assert_eq!(report.buffer_bytes(), 256);
+        assert_eq!(
+            report.total_bytes(),
+            1000 + 256 + report.views[0].texture_bytes() + report.views[1].texture_bytes()
+        );
+    }
+
+    #[test]
+    fn test_warnings_near_limits() {
+        let limits = wgpu::Limits { max_texture_dimension_2d: 8192, max_buffer_size: 1 << 20, ..Default::default() };
+        assert!(warnings(&view(1920, 1080, 4096), &limits).is_empty());
+
+        let tall = view(800, 7500, 4096);
+        assert_eq!(
+            warnings(&tall, &limits),
+            [GpuMemoryWarning::SurfaceSize {
+                view_id: tall.view_id,
+                size: (800, 7500),
+                max_texture_dimension: 8192,
+            }]
+        );
+
+        let busy = view(800, 600, 1 << 20);
+        assert!(matches!(
+            warnings(&busy, &limits)[..],
+            [GpuMemoryWarning::FrameUploads { bytes, .. }] if bytes == 1 << 20
+        ));
+    }
+}
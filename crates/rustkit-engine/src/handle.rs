@@ -0,0 +1,433 @@
+//! Thread-safe engine front-end.
+//!
+//! `Engine` holds `Rc` DOM state and must stay on the thread that created
+//! it. [`EngineHandle::spawn`] moves an engine onto its own thread and
+//! returns a cloneable `Send + Sync` handle; each call becomes a command
+//! that runs on the engine thread, with the result sent back. Commands run
+//! one at a time in the order they were sent, and the engine's scheduled
+//! tasks and page timers run, carets blink, its pages' fetches are
+//! settled, their decoded images handed over and the links followed and
+//! forms submitted in them are loaded whenever no command is waiting.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info};
+use url::Url;
+
+use rustkit_viewhost::Bounds;
+
+use crate::{
+    AccessibilityTree, CaptureOptions, Engine, EngineBuilder, EngineError, EngineEvent, EngineViewId, EventStream, FontDescriptor, GpuMemoryReport, NetworkRequest,
+    PrintOptions, TextMetrics, ViewMetadata,
+};
+
+/// Future returned by an async command, borrowing the engine.
+pub type EngineFuture<'a, R> = Pin<Box<dyn Future<Output = R> + 'a>>;
+
+type Command = Box<dyn for<'a> FnOnce(&'a mut Engine) -> EngineFuture<'a, ()> + Send>;
+
+/// Time slice for scheduled tasks between commands.
+const TASK_BUDGET: Duration = Duration::from_millis(8);
+
+/// How often pages' fetches are checked on while nothing else is to do.
+const FETCH_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Cloneable, thread-safe handle to an engine running on its own thread.
+///
+/// The engine thread exits once every handle has been dropped.
+#[derive(Clone)]
+pub struct EngineHandle {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl EngineHandle {
+    /// Build an engine on a new thread and return a handle to it.
+    pub fn spawn(builder: EngineBuilder) -> Result<Self, EngineError> {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+
+        thread::Builder::new()
+            .name("rustkit-engine".into())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(EngineError::ViewError(e.to_string())));
+                        return;
+                    }
+                };
+                let engine = match builder.build() {
+                    Ok(engine) => engine,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+                runtime.block_on(run_engine(engine, receiver));
+            })
+            .map_err(|e| EngineError::ViewError(e.to_string()))?;
+
+        ready_rx.recv().map_err(|_| EngineError::EngineStopped)??;
+        info!("Engine thread started");
+        Ok(Self { commands })
+    }
+
+    /// Run a closure on the engine thread and return its result.
+    pub async fn call<R, F>(&self, f: F) -> Result<R, EngineError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Engine) -> R + Send + 'static,
+    {
+        self.call_async(move |engine| {
+            let result = f(engine);
+            Box::pin(async move { result })
+        })
+        .await
+    }
+
+    /// Run an async closure on the engine thread and return its result.
+    ///
+    /// No other command runs until the returned future completes.
+    pub async fn call_async<R, F>(&self, f: F) -> Result<R, EngineError>
+    where
+        R: Send + 'static,
+        F: for<'a> FnOnce(&'a mut Engine) -> EngineFuture<'a, R> + Send + 'static,
+    {
+        self.send(f)?.await.map_err(|_| EngineError::EngineStopped)
+    }
+
+    /// Like [`call`](Self::call), blocking the current thread for the result.
+    ///
+    /// Must not be called from within an async runtime.
+    pub fn call_blocking<R, F>(&self, f: F) -> Result<R, EngineError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Engine) -> R + Send + 'static,
+    {
+        self.send(move |engine| {
+            let result = f(engine);
+            Box::pin(async move { result })
+        })?
+        .blocking_recv()
+        .map_err(|_| EngineError::EngineStopped)
+    }
+
+    /// Queue a command, returning the receiver for its result.
+    fn send<R, F>(&self, f: F) -> Result<oneshot::Receiver<R>, EngineError>
+    where
+        R: Send + 'static,
+        F: for<'a> FnOnce(&'a mut Engine) -> EngineFuture<'a, R> + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let command: Command = Box::new(move |engine| {
+            Box::pin(async move {
+                let _ = reply.send(f(engine).await);
+            })
+        });
+        self.commands.send(command).map_err(|_| EngineError::EngineStopped)?;
+        Ok(result)
+    }
+
+    /// Whether the engine thread is still running.
+    pub fn is_running(&self) -> bool {
+        !self.commands.is_closed()
+    }
+
+    /// Shut the engine down (see [`Engine::shutdown`]). The engine thread
+    /// keeps running until every handle has been dropped.
+    pub async fn shutdown(&self) -> Result<(), EngineError> {
+        self.call_async(|engine| Box::pin(engine.shutdown())).await
+    }
+
+    /// Take the engine's event receiver.
+    pub async fn take_event_receiver(&self) -> Result<Option<mpsc::UnboundedReceiver<EngineEvent>>, EngineError> {
+        self.call(|engine| engine.take_event_receiver()).await
+    }
+
+    /// Subscribe to the engine's events (see [`Engine::subscribe`]).
+    pub async fn subscribe(&self) -> Result<EventStream, EngineError> {
+        self.call(|engine| engine.subscribe()).await
+    }
+
+    /// Create a headless view.
+    #[cfg(feature = "headless")]
+    pub async fn create_headless_view(&self, bounds: Bounds) -> Result<EngineViewId, EngineError> {
+        self.call(move |engine| engine.create_headless_view(bounds)).await?
+    }
+
+    /// Destroy a view.
+    pub async fn destroy_view(&self, id: EngineViewId) -> Result<(), EngineError> {
+        self.call(move |engine| engine.destroy_view(id)).await?
+    }
+
+    /// Resize a view.
+    pub async fn resize_view(&self, id: EngineViewId, bounds: Bounds) -> Result<(), EngineError> {
+        self.call(move |engine| engine.resize_view(id, bounds)).await?
+    }
+
+    /// Resize several views, laying them out and painting them together.
+    pub async fn resize_views(&self, views: Vec<(EngineViewId, Bounds)>) -> Result<(), EngineError> {
+        self.call(move |engine| engine.resize_views(&views)).await?
+    }
+
+    /// Start a batch of view operations; see [`Engine::begin_batch`].
+    pub async fn begin_batch(&self) -> Result<(), EngineError> {
+        self.call(|engine| engine.begin_batch()).await
+    }
+
+    /// End a batch of view operations, laying out and painting the views
+    /// it changed.
+    pub async fn end_batch(&self) -> Result<(), EngineError> {
+        self.call(|engine| engine.end_batch()).await?
+    }
+
+    /// Stack a view among the others in its window.
+    pub async fn set_view_stacking(&self, id: EngineViewId, z_index: i32, opaque: bool) -> Result<(), EngineError> {
+        self.call(move |engine| engine.set_view_stacking(id, z_index, opaque)).await?
+    }
+
+    /// Set the parts of a view that can be seen, or `None` to follow the
+    /// engine's stacking.
+    pub async fn set_view_visible_region(&self, id: EngineViewId, region: Option<Vec<Bounds>>) -> Result<(), EngineError> {
+        self.call(move |engine| engine.set_view_visible_region(id, region)).await?
+    }
+
+    /// Round a view's corners to `radius` view pixels, or square them with 0.
+    pub async fn set_view_corner_radius(&self, id: EngineViewId, radius: f32) -> Result<(), EngineError> {
+        self.call(move |engine| engine.set_view_corner_radius(id, radius)).await?
+    }
+
+    /// Scroll a view, returning whether the offset changed.
+    pub async fn scroll_view(&self, id: EngineViewId, delta_x: f32, delta_y: f32) -> Result<bool, EngineError> {
+        self.call(move |engine| engine.scroll_view(id, delta_x, delta_y)).await?
+    }
+
+    /// Load a URL in a view.
+    pub async fn load_url(&self, id: EngineViewId, url: Url) -> Result<(), EngineError> {
+        self.call_async(move |engine| Box::pin(engine.load_url(id, url))).await?
+    }
+
+    /// Load HTML content into a view.
+    pub async fn load_html(&self, id: EngineViewId, html: impl Into<String>) -> Result<(), EngineError> {
+        let html = html.into();
+        self.call(move |engine| engine.load_html(id, &html)).await?
+    }
+
+    /// Execute a script in a view.
+    pub async fn execute_script(&self, id: EngineViewId, script: impl Into<String>) -> Result<String, EngineError> {
+        let script = script.into();
+        self.call(move |engine| engine.execute_script(id, &script)).await?
+    }
+
+    /// Render a view.
+    pub async fn render_view(&self, id: EngineViewId) -> Result<(), EngineError> {
+        self.call(move |engine| engine.render_view(id)).await?
+    }
+
+    /// Capture a view's frame to a PPM file.
+    pub async fn capture_frame(&self, id: EngineViewId, path: impl Into<String>) -> Result<(), EngineError> {
+        let path = path.into();
+        self.call(move |engine| engine.capture_frame(id, &path)).await?
+    }
+
+    /// Capture a view as a PNG image (see [`Engine::capture_frame_png`]).
+    pub async fn capture_frame_png(
+        &self,
+        id: EngineViewId,
+        options: CaptureOptions,
+        path: Option<String>,
+    ) -> Result<Vec<u8>, EngineError> {
+        self.call(move |engine| engine.capture_frame_png(id, &options, path.as_deref())).await?
+    }
+
+    /// A view's accessibility tree (see [`Engine::get_accessibility_tree`]).
+    pub async fn get_accessibility_tree(&self, id: EngineViewId) -> Result<AccessibilityTree, EngineError> {
+        self.call(move |engine| engine.get_accessibility_tree(id)).await?
+    }
+
+    /// Export a view's display list to an SVG file.
+    pub async fn export_vector(&self, id: EngineViewId, path: impl Into<String>) -> Result<(), EngineError> {
+        let path = path.into();
+        self.call(move |engine| engine.export_vector(id, &path)).await?
+    }
+
+    /// Print a view's document to a PDF file (see [`Engine::print_to_pdf`]).
+    pub async fn print_to_pdf(
+        &self,
+        id: EngineViewId,
+        path: impl Into<String>,
+        options: PrintOptions,
+    ) -> Result<usize, EngineError> {
+        let path = path.into();
+        self.call(move |engine| engine.print_to_pdf(id, &path, &options)).await?
+    }
+
+    /// Measure text in a font (see [`Engine::measure_text`]).
+    pub async fn measure_text(&self, text: impl Into<String>, font: FontDescriptor) -> Result<TextMetrics, EngineError> {
+        let text = text.into();
+        self.call(move |engine| engine.measure_text(&text, &font)).await
+    }
+
+    /// A view's network log (see [`Engine::get_network_log`]).
+    pub async fn get_network_log(&self, id: EngineViewId) -> Result<Option<Vec<NetworkRequest>>, EngineError> {
+        self.call(move |engine| engine.get_network_log(id)).await
+    }
+
+    /// The GPU memory the engine holds (see [`Engine::gpu_memory_report`]).
+    pub async fn gpu_memory_report(&self) -> Result<GpuMemoryReport, EngineError> {
+        self.call(move |engine| engine.gpu_memory_report()).await
+    }
+
+    /// Advance deterministic mode's virtual clock (see [`Engine::tick`]).
+    pub async fn tick(&self, delta: Duration) -> Result<usize, EngineError> {
+        self.call(move |engine| engine.tick(delta)).await
+    }
+
+    /// Override a view's locale and time zone (see [`Engine::set_locale`]).
+    pub async fn set_locale(
+        &self,
+        id: EngineViewId,
+        locale: Option<String>,
+        time_zone: Option<String>,
+    ) -> Result<(), EngineError> {
+        self.call(move |engine| engine.set_locale(id, locale.as_deref(), time_zone.as_deref())).await?
+    }
+
+    /// Get a view's URL.
+    pub async fn get_url(&self, id: EngineViewId) -> Result<Option<Url>, EngineError> {
+        self.call(move |engine| engine.get_url(id)).await
+    }
+
+    /// Get a view's title.
+    pub async fn get_title(&self, id: EngineViewId) -> Result<Option<String>, EngineError> {
+        self.call(move |engine| engine.get_title(id)).await
+    }
+
+    /// A view's metadata (see [`Engine::view_metadata`]).
+    pub async fn view_metadata(&self, id: EngineViewId) -> Result<Option<ViewMetadata>, EngineError> {
+        self.call(move |engine| engine.view_metadata(id)).await
+    }
+
+    /// Attach a value to a view (see [`Engine::set_view_data`]).
+    pub async fn set_view_data<T: Send + 'static>(&self, id: EngineViewId, value: T) -> Result<Option<T>, EngineError> {
+        self.call(move |engine| engine.set_view_data(id, value)).await?
+    }
+
+    /// A copy of the value of type `T` attached to a view.
+    pub async fn get_view_data<T: Clone + Send + 'static>(&self, id: EngineViewId) -> Result<Option<T>, EngineError> {
+        self.call(move |engine| engine.get_view_data::<T>(id).cloned()).await
+    }
+}
+
+/// Engine thread loop: run commands as they arrive, and scheduled tasks,
+/// due page timers, caret blinks, animation frames, finished page fetches
+/// and decoded images while the command queue is empty.
+async fn run_engine(mut engine: Engine, mut commands: mpsc::UnboundedReceiver<Command>) {
+    loop {
+        let command = if engine.has_scheduled_tasks()
+            || engine.has_pending_fetches()
+            || engine.has_pending_images()
+            || engine.has_pending_navigations()
+        {
+            match commands.try_recv() {
+                Ok(command) => command,
+                Err(mpsc::error::TryRecvError::Empty) => {
+                    let now = Instant::now();
+                    engine.pump_timers(now);
+                    engine.pump_caret_blink(now);
+                    engine.pump_animations(now);
+                    engine.pump_fetches();
+                    engine.pump_decoded_images();
+                    if engine.has_pending_navigations() {
+                        engine.pump_navigations().await;
+                    } else if engine.has_scheduled_tasks() {
+                        engine.run_scheduled_tasks(TASK_BUDGET);
+                        tokio::task::yield_now().await;
+                    } else {
+                        tokio::time::sleep(FETCH_POLL_INTERVAL).await;
+                    }
+                    continue;
+                }
+                Err(mpsc::error::TryRecvError::Disconnected) => break,
+            }
+        } else if let Some(due) = engine
+            .next_timer_due()
+            .into_iter()
+            .chain(engine.next_caret_blink())
+            .chain(engine.next_animation_frame())
+            .min()
+            // In deterministic mode nothing falls due until the host ticks
+            .filter(|_| !engine.is_deterministic())
+        {
+            // Wait for a command, or until the next timer is due, caret
+            // blinks or animation frame comes
+            match tokio::time::timeout_at(due.into(), commands.recv()).await {
+                Ok(Some(command)) => command,
+                Ok(None) => break,
+                Err(_) => {
+                    let now = Instant::now();
+                    engine.pump_timers(now);
+                    engine.pump_caret_blink(now);
+                    engine.pump_animations(now);
+                    continue;
+                }
+            }
+        } else {
+            match commands.recv().await {
+                Some(command) => command,
+                None => break,
+            }
+        };
+        command(&mut engine).await;
+    }
+    debug!("All engine handles dropped, stopping engine thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+
+    #[test]
+    fn test_handle_is_send_sync() {
+        assert_send_sync::<EngineHandle>();
+    }
+
+    #[tokio::test]
+    async fn test_handle_proxies_calls() {
+        let handle = match EngineHandle::spawn(EngineBuilder::new()) {
+            Ok(handle) => handle,
+            Err(_) => {
+                eprintln!("Skipping test: GPU not available");
+                return;
+            }
+        };
+
+        // Calls from other threads reach the same engine
+        let other = handle.clone();
+        let count = std::thread::spawn(move || other.call_blocking(|engine| engine.view_count()))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let missing = EngineViewId::new();
+        assert!(matches!(
+            handle.load_html(missing, "<p>hi</p>").await,
+            Err(EngineError::ViewNotFound(id)) if id == missing
+        ));
+        let title = handle.get_title(missing).await.unwrap();
+        assert_eq!(title, None);
+        assert!(handle.is_running());
+
+        handle.shutdown().await.unwrap();
+        assert!(handle.call(|engine| engine.is_shut_down()).await.unwrap());
+        drop(handle);
+    }
+}
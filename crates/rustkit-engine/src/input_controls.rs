@@ -0,0 +1,75 @@
+//! Range sliders and color wells.
+//!
+//! A range takes the value under the pointer when it's clicked or dragged,
+//! and steps with the arrow, Page Up/Down, Home and End keys while focused.
+//! A color well asks the host for a color picker with
+//! [`EngineEvent::ColorPickerRequested`] and takes the color the host
+//! reports with [`Engine::choose_color`].
+//!
+//! [`EngineEvent::ColorPickerRequested`]: crate::EngineEvent::ColorPickerRequested
+//! [`Engine::choose_color`]: crate::Engine::choose_color
+
+use rustkit_core::KeyCode;
+use rustkit_dom::{InputType, Node, NodeId};
+
+/// A range thumb being dragged.
+#[derive(Debug, Clone)]
+pub(crate) struct RangeDrag {
+    /// The `<input type=range>`.
+    pub node_id: NodeId,
+    /// Value when the drag started, to tell whether it changed.
+    pub initial_value: String,
+}
+
+/// Type of an enabled `<input>` the engine runs interaction for.
+pub(crate) fn interactive_input(node: &Node) -> Option<InputType> {
+    if !node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("input")) || node.get_attribute("disabled").is_some()
+    {
+        return None;
+    }
+    let input_type = node.input_type();
+    matches!(input_type, InputType::Range | InputType::Color).then_some(input_type)
+}
+
+/// The value a key press moves a range to, if the key moves it.
+pub(crate) fn range_key_value(range: &Node, key: KeyCode) -> Option<f64> {
+    let bounds = range.range_bounds();
+    let value: f64 = range.input_value().parse().ok()?;
+    let span = bounds.max - bounds.min;
+    let step = bounds.step.unwrap_or(span / 100.0);
+    let page = (span / 10.0).max(step);
+    let value = match key {
+        KeyCode::ArrowRight | KeyCode::ArrowUp => value + step,
+        KeyCode::ArrowLeft | KeyCode::ArrowDown => value - step,
+        KeyCode::PageUp => value + page,
+        KeyCode::PageDown => value - page,
+        KeyCode::Home => bounds.min,
+        KeyCode::End => bounds.max,
+        _ => return None,
+    };
+    Some(bounds.clamp(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_range_keys_step_within_bounds() {
+        let doc = Document::parse_html(
+            r#"<input id="r" type="range" min="0" max="50" step="5" value="45">
+               <input id="off" type="color" disabled>"#,
+        )
+        .unwrap();
+        let range = doc.get_element_by_id("r").unwrap();
+        assert_eq!(interactive_input(&range), Some(InputType::Range));
+        assert_eq!(interactive_input(&doc.get_element_by_id("off").unwrap()), None);
+
+        assert_eq!(range_key_value(&range, KeyCode::ArrowRight), Some(50.0));
+        assert_eq!(range_key_value(&range, KeyCode::PageUp), Some(50.0));
+        assert_eq!(range_key_value(&range, KeyCode::ArrowDown), Some(40.0));
+        assert_eq!(range_key_value(&range, KeyCode::Home), Some(0.0));
+        assert_eq!(range_key_value(&range, KeyCode::KeyA), None);
+    }
+}
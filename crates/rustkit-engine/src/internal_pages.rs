@@ -0,0 +1,311 @@
+//! Internal `about:` and `hiwave:` pages.
+//!
+//! Each page is a generator producing HTML from the engine's state. The
+//! engine loads the result like inline HTML, with the elevated IPC access
+//! the host grants its own UI.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use url::Url;
+
+use crate::{escape_html, EngineConfig, EngineError};
+
+/// Engine state available to page generators.
+pub struct InternalPageContext<'a> {
+    /// The URL being loaded.
+    pub url: &'a Url,
+    /// The engine configuration.
+    pub config: &'a EngineConfig,
+    /// GPU adapter description, as returned by `Engine::gpu_info()`.
+    pub gpu_info: String,
+    /// Why the page at `url` couldn't be loaded, for the `neterror` page
+    /// shown in its place.
+    pub error: Option<&'a EngineError>,
+}
+
+/// Produces the HTML of an internal page.
+pub type InternalPageGenerator = Arc<dyn Fn(&InternalPageContext<'_>) -> String + Send + Sync>;
+
+/// Registry of internal pages by name (`version` serves `about:version`
+/// and `hiwave:version`).
+#[derive(Clone)]
+pub struct InternalPages {
+    pages: BTreeMap<String, InternalPageGenerator>,
+}
+
+impl InternalPages {
+    /// Create a registry with the built-in pages: `blank`, `version`,
+    /// `gpu`, `flags` and `neterror`.
+    pub fn new() -> Self {
+        let mut pages = Self {
+            pages: BTreeMap::new(),
+        };
+        pages.register("blank", |_| String::new());
+        pages.register("version", version_page);
+        pages.register("gpu", gpu_page);
+        pages.register("flags", flags_page);
+        pages.register("neterror", error_page);
+        pages
+    }
+
+    /// Register a page, replacing any page with the same name.
+    pub fn register<F>(&mut self, name: &str, generator: F)
+    where
+        F: Fn(&InternalPageContext<'_>) -> String + Send + Sync + 'static,
+    {
+        self.pages.insert(name.to_ascii_lowercase(), Arc::new(generator));
+    }
+
+    /// Names of the registered pages, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.pages.keys().map(String::as_str)
+    }
+
+    /// Whether a URL uses an internal scheme.
+    pub fn is_internal_url(url: &Url) -> bool {
+        matches!(url.scheme(), "about" | "hiwave")
+    }
+
+    /// The page name an internal URL refers to, e.g. `gpu` for both
+    /// `about:gpu` and `hiwave://gpu/`.
+    pub fn page_name(url: &Url) -> Option<String> {
+        if !Self::is_internal_url(url) {
+            return None;
+        }
+        let name = match url.host_str() {
+            Some(host) => host,
+            None => url.path().trim_matches('/'),
+        };
+        Some(name.to_ascii_lowercase())
+    }
+
+    /// Generate the HTML for an internal URL, or `None` if no page is
+    /// registered for it.
+    pub fn generate(&self, context: &InternalPageContext<'_>) -> Option<String> {
+        let name = Self::page_name(context.url)?;
+        self.generate_page(&name, context)
+    }
+
+    /// Generate the HTML of the page registered as `name`.
+    pub fn generate_page(&self, name: &str, context: &InternalPageContext<'_>) -> Option<String> {
+        self.pages.get(name).map(|generator| generator(context))
+    }
+}
+
+impl Default for InternalPages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wrap page content in the shared internal page layout.
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><title>{title}</title><style>\
+         body {{ font-family: sans-serif; font-size: 14px; margin: 24px; color: #202124; }}\
+         h1 {{ font-size: 20px; font-weight: normal; }}\
+         table {{ border-collapse: collapse; }}\
+         td {{ padding: 4px 16px 4px 0; vertical-align: top; }}\
+         td:first-child {{ color: #5f6368; }}\
+         pre {{ background: #f1f3f4; padding: 8px; white-space: pre-wrap; }}\
+         </style></head><body><h1>{title}</h1>{body}</body></html>",
+        title = escape_html(title),
+    )
+}
+
+/// Render name/value pairs as a two-column table.
+fn table(rows: &[(&str, String)]) -> String {
+    let rows: String = rows
+        .iter()
+        .map(|(name, value)| format!("<tr><td>{}</td><td>{}</td></tr>", escape_html(name), escape_html(value)))
+        .collect();
+    format!("<table>{rows}</table>")
+}
+
+fn version_page(context: &InternalPageContext<'_>) -> String {
+    let config = context.config;
+    page(
+        &config.localize("version.title", &[]),
+        &table(&[
+            (&config.localize("version.engine", &[]), format!("RustKit {}", env!("CARGO_PKG_VERSION"))),
+            (&config.localize("version.user-agent", &[]), config.user_agent.clone()),
+            (
+                &config.localize("version.os", &[]),
+                format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+            ),
+        ]),
+    )
+}
+
+fn gpu_page(context: &InternalPageContext<'_>) -> String {
+    let config = context.config;
+    page(
+        &config.localize("gpu.title", &[]),
+        &format!(
+            "<p>{}</p><pre>{}</pre>",
+            escape_html(&config.localize("gpu.adapter", &[])),
+            escape_html(&context.gpu_info)
+        ),
+    )
+}
+
+fn flags_page(context: &InternalPageContext<'_>) -> String {
+    let config = context.config;
+    let [r, g, b, a] = config.background_color;
+    page(
+        &config.localize("flags.title", &[]),
+        &table(&[
+            ("javascript_enabled", config.javascript_enabled.to_string()),
+            ("cookies_enabled", config.cookies_enabled.to_string()),
+            ("disable_animations", config.disable_animations.to_string()),
+            ("background_color", format!("rgba({r}, {g}, {b}, {a})")),
+        ]),
+    )
+}
+
+/// The page shown in place of one that couldn't be loaded, in the words of
+/// the engine's localizer. Hosts can also replace it outright, keyed on the
+/// error's code.
+fn error_page(context: &InternalPageContext<'_>) -> String {
+    let config = context.config;
+    let Some(error) = context.error else {
+        return page(&config.localize("error.not-loaded.title", &[]), "");
+    };
+    let kind = match error {
+        EngineError::DnsFailure(_) => "dns",
+        EngineError::TlsError(_) => "tls",
+        EngineError::HttpStatus(_) => "http",
+        EngineError::NetworkError(_) => "network",
+        _ => "other",
+    };
+    let title = config.localize(&format!("error.{kind}.title"), &[]);
+    let message = config.localize(&format!("error.{kind}.message"), &[]);
+    page(
+        &title,
+        &format!(
+            "<p>{}</p>{}",
+            escape_html(&message),
+            table(&[
+                (&config.localize("error.address", &[]), context.url.to_string()),
+                (&config.localize("error.error", &[]), error.to_string()),
+                (&config.localize("error.code", &[]), error.code().to_string()),
+            ]),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    fn generate(pages: &InternalPages, url: &str) -> Option<String> {
+        let url = Url::parse(url).unwrap();
+        let config = EngineConfig::default();
+        pages.generate(&InternalPageContext {
+            url: &url,
+            config: &config,
+            gpu_info: "AdapterInfo { name: \"Test <GPU>\" }".to_string(),
+            error: None,
+        })
+    }
+
+    #[test]
+    fn test_page_names() {
+        let name = |url: &str| InternalPages::page_name(&Url::parse(url).unwrap());
+        assert_eq!(name("about:version").as_deref(), Some("version"));
+        assert_eq!(name("hiwave:GPU").as_deref(), Some("gpu"));
+        assert_eq!(name("hiwave://flags/").as_deref(), Some("flags"));
+        assert_eq!(name("https://example.com/"), None);
+    }
+
+    #[test]
+    fn test_builtin_pages() {
+        let pages = InternalPages::new();
+        assert_eq!(pages.names().collect::<Vec<_>>(), vec!["blank", "flags", "gpu", "neterror", "version"]);
+
+        assert_eq!(generate(&pages, "about:blank").as_deref(), Some(""));
+        assert!(generate(&pages, "about:missing").is_none());
+
+        let gpu = Document::parse_html(&generate(&pages, "hiwave://gpu").unwrap()).unwrap();
+        assert_eq!(gpu.title().as_deref(), Some("GPU"));
+        let pre = gpu.get_elements_by_tag_name("pre");
+        assert_eq!(pre[0].text_content(), "AdapterInfo { name: \"Test <GPU>\" }");
+
+        let version = generate(&pages, "about:version").unwrap();
+        assert!(version.contains(env!("CARGO_PKG_VERSION")));
+        assert!(version.contains("RustKit/1.0 HiWave/1.0"));
+    }
+
+    #[test]
+    fn test_error_page() {
+        let pages = InternalPages::new();
+        let url = Url::parse("https://unreachable.invalid/").unwrap();
+        let config = EngineConfig::default();
+        let error = EngineError::DnsFailure("unreachable.invalid".into());
+        let html = pages
+            .generate_page(
+                "neterror",
+                &InternalPageContext {
+                    url: &url,
+                    config: &config,
+                    gpu_info: String::new(),
+                    error: Some(&error),
+                },
+            )
+            .unwrap();
+
+        let document = Document::parse_html(&html).unwrap();
+        assert_eq!(document.title().as_deref(), Some("This site can't be reached"));
+        let cells: Vec<String> = document
+            .get_elements_by_tag_name("td")
+            .iter()
+            .map(|cell| cell.text_content())
+            .collect();
+        assert!(cells.iter().any(|cell| cell == "https://unreachable.invalid/"));
+        assert!(cells.iter().any(|cell| cell == "21"));
+    }
+
+    #[test]
+    fn test_localized_error_page() {
+        struct French;
+        impl crate::Localizer for French {
+            fn localize(&self, key: &str, args: &[(&str, &str)]) -> String {
+                match key {
+                    "error.dns.title" => "Ce site est inaccessible".into(),
+                    "error.address" => "Adresse".into(),
+                    _ => crate::EnglishLocalizer.localize(key, args),
+                }
+            }
+        }
+
+        let url = Url::parse("https://unreachable.invalid/").unwrap();
+        let config = EngineConfig { localizer: Arc::new(French), ..Default::default() };
+        let error = EngineError::DnsFailure("unreachable.invalid".into());
+        let html = InternalPages::new()
+            .generate_page(
+                "neterror",
+                &InternalPageContext {
+                    url: &url,
+                    config: &config,
+                    gpu_info: String::new(),
+                    error: Some(&error),
+                },
+            )
+            .unwrap();
+
+        let document = Document::parse_html(&html).unwrap();
+        assert_eq!(document.title().as_deref(), Some("Ce site est inaccessible"));
+        assert!(html.contains("<td>Adresse</td>"));
+        // Untranslated strings fall back to English
+        assert!(html.contains("The server's address couldn't be found."));
+    }
+
+    #[test]
+    fn test_register_page() {
+        let mut pages = InternalPages::new();
+        pages.register("Hello", |context| format!("<p>{}</p>", context.url));
+        assert_eq!(generate(&pages, "about:hello").as_deref(), Some("<p>about:hello</p>"));
+    }
+}
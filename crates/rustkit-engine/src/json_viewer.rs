@@ -0,0 +1,398 @@
+//! JSON viewer for `application/json` navigations.
+//!
+//! The response is parsed here and rendered into `json_viewer.html` as a
+//! tree of `<details>` elements, so collapsing works without script; the
+//! template's script adds the raw-text toggle and the filter box. Objects
+//! keep their keys in document order, and numbers are shown as written.
+
+use crate::{escape_html, Localizer};
+
+/// Page template; `{{title}}`, `{{tree}}` and `{{raw}}` are filled in, as
+/// are the toolbar's labels.
+const TEMPLATE: &str = include_str!("json_viewer.html");
+
+/// Whether a MIME type names JSON (`application/json`, `text/json`, or any
+/// `+json` type).
+pub(crate) fn is_json_mime_type(essence: &str) -> bool {
+    let essence = essence.to_ascii_lowercase();
+    matches!(essence.as_str(), "application/json" | "text/json") || essence.ends_with("+json")
+}
+
+/// Build the viewer page for a JSON response body, labelled by `localizer`.
+pub(crate) fn render_page(title: &str, source: &str, localizer: &dyn Localizer) -> String {
+    let tree = match JsonParser::new(source).parse() {
+        Ok(value) => {
+            let mut tree = String::new();
+            render_value(None, &value, localizer, &mut tree);
+            format!("<ul class=\"json-root\">{tree}</ul>")
+        }
+        Err(error) => format!(
+            "<p class=\"json-error\">{}</p>",
+            escape_html(&localizer.localize("json.invalid", &[("error", &error)]))
+        ),
+    };
+    fill_template(
+        TEMPLATE,
+        &[
+            ("title", &escape_html(title)),
+            ("tree", &tree),
+            ("raw", &escape_html(source)),
+            ("raw_label", &escape_html(&localizer.localize("json.raw", &[]))),
+            ("tree_label", &escape_html(&localizer.localize("json.tree", &[]))),
+            ("filter_label", &escape_html(&localizer.localize("json.filter", &[]))),
+        ],
+    )
+}
+
+/// Fill the `{{name}}` placeholders of `template` with `values` in one
+/// pass, so placeholders in the values themselves stay as they are.
+fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut page = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        page.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let value = after
+            .find("}}")
+            .and_then(|close| values.iter().find(|(name, _)| *name == &after[..close]).map(|(_, value)| (close, value)));
+        match value {
+            Some((close, value)) => {
+                page.push_str(value);
+                rest = &after[close + 2..];
+            }
+            None => {
+                page.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    page.push_str(rest);
+    page
+}
+
+/// A parsed JSON value.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    /// The number's source text.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in document order.
+    Object(Vec<(String, Json)>),
+}
+
+/// Render one tree row: a key (absent for array items and the root) and
+/// its value, with item and key counts from `localizer`.
+fn render_value(key: Option<&str>, value: &Json, localizer: &dyn Localizer, out: &mut String) {
+    let count = |len: usize, one: &str, other: &str| {
+        localizer.localize(if len == 1 { one } else { other }, &[("count", &len.to_string())])
+    };
+    let label = key
+        .map(|k| format!("<span class=\"json-key\">{}</span>: ", escape_html(k)))
+        .unwrap_or_default();
+
+    let (open, close, count, children): (_, _, _, Vec<(Option<String>, &Json)>) = match value {
+        Json::Array(items) => (
+            "[",
+            "]",
+            count(items.len(), "json.item", "json.items"),
+            items.iter().enumerate().map(|(i, item)| (Some(i.to_string()), item)).collect(),
+        ),
+        Json::Object(members) => (
+            "{",
+            "}",
+            count(members.len(), "json.key", "json.keys"),
+            members.iter().map(|(k, v)| (Some(k.clone()), v)).collect(),
+        ),
+        scalar => {
+            let (class, text) = match scalar {
+                Json::Null => ("json-null", "null".to_string()),
+                Json::Bool(b) => ("json-bool", b.to_string()),
+                Json::Number(n) => ("json-number", n.clone()),
+                Json::String(s) => ("json-string", format!("\"{}\"", escape_html(s))),
+                Json::Array(_) | Json::Object(_) => unreachable!(),
+            };
+            out.push_str(&format!("<li>{label}<span class=\"{class}\">{text}</span></li>"));
+            return;
+        }
+    };
+
+    if children.is_empty() {
+        out.push_str(&format!("<li>{label}<span class=\"json-brace\">{open}{close}</span></li>"));
+        return;
+    }
+    out.push_str(&format!(
+        "<li><details open><summary>{label}<span class=\"json-brace\">{open}</span> \
+         <span class=\"json-count\">{}</span></summary><ul>",
+        escape_html(&count)
+    ));
+    for (child_key, child) in children {
+        render_value(child_key.as_deref(), child, localizer, out);
+    }
+    out.push_str(&format!("</ul><span class=\"json-brace\">{close}</span></details></li>"));
+}
+
+/// Recursive-descent JSON parser (RFC 8259).
+struct JsonParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: input.strip_prefix('\u{feff}').unwrap_or(input),
+            pos: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<Json, String> {
+        let value = self.parse_value(0)?;
+        self.skip_whitespace();
+        if self.pos < self.input.len() {
+            return Err(self.error("unexpected data after the value"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Json, String> {
+        if depth > 512 {
+            return Err(self.error("nesting too deep"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                if self.eat_after_whitespace('}') {
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expected a string key"));
+                    }
+                    let key = self.parse_string()?;
+                    if !self.eat_after_whitespace(':') {
+                        return Err(self.error("expected ':'"));
+                    }
+                    members.push((key, self.parse_value(depth + 1)?));
+                    if self.eat_after_whitespace('}') {
+                        return Ok(Json::Object(members));
+                    }
+                    if !self.eat_after_whitespace(',') {
+                        return Err(self.error("expected ',' or '}'"));
+                    }
+                }
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if self.eat_after_whitespace(']') {
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.parse_value(depth + 1)?);
+                    if self.eat_after_whitespace(']') {
+                        return Ok(Json::Array(items));
+                    }
+                    if !self.eat_after_whitespace(',') {
+                        return Err(self.error("expected ',' or ']'"));
+                    }
+                }
+            }
+            Some('"') => self.parse_string().map(Json::String),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some(_) => {
+                for (word, value) in [("true", Json::Bool(true)), ("false", Json::Bool(false)), ("null", Json::Null)] {
+                    if self.input[self.pos..].starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("unexpected character"))
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        let rest = &self.input[start..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+            .unwrap_or(rest.len());
+        let text = &rest[..end];
+
+        // JSON numbers: no leading zeros, '+', or bare '.'
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        let mantissa = digits.split(['e', 'E']).next().unwrap_or("");
+        let integer = mantissa.split('.').next().unwrap_or("");
+        let invalid = integer.is_empty()
+            || (integer.len() > 1 && integer.starts_with('0'))
+            || mantissa.ends_with('.')
+            || !digits.starts_with(|c: char| c.is_ascii_digit())
+            || text.parse::<f64>().is_err();
+        if invalid {
+            return Err(self.error("invalid number"));
+        }
+        self.pos += end;
+        Ok(Json::Number(text.to_string()))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => result.push(escaped),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'u' => {
+                            let high = self.parse_hex4()?;
+                            let code = if (0xD800..0xDC00).contains(&high) && self.input[self.pos..].starts_with("\\u") {
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                            } else {
+                                high
+                            };
+                            result.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let hex = self.input.get(self.pos..self.pos + 4).ok_or_else(|| self.error("invalid \\u escape"))?;
+        let code = u32::from_str_radix(hex, 16).map_err(|_| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn eat_after_whitespace(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn error(&self, message: &str) -> String {
+        let before = &self.input[..self.pos.min(self.input.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+        format!("{message} at line {line}, column {column}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+    use crate::EnglishLocalizer;
+
+    #[test]
+    fn test_parse_json() {
+        let value = JsonParser::new(r#" {"b": [1, -2.5e3, true, null], "a": "xé\n\"", "c": {}} "#).parse();
+        assert_eq!(
+            value,
+            Ok(Json::Object(vec![
+                (
+                    "b".to_string(),
+                    Json::Array(vec![
+                        Json::Number("1".into()),
+                        Json::Number("-2.5e3".into()),
+                        Json::Bool(true),
+                        Json::Null
+                    ])
+                ),
+                ("a".to_string(), Json::String("x\u{e9}\n\"".into())),
+                ("c".to_string(), Json::Object(vec![])),
+            ]))
+        );
+
+        for invalid in ["", "{", "[1,]", "{'a': 1}", "01", "1.", "+1", "[1] 2", "\"a\tb\"", "tru"] {
+            assert!(JsonParser::new(invalid).parse().is_err(), "{invalid:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_render_page() {
+        let page = render_page("data.json", r#"{"name": "<b>", "tags": ["x", "y"], "empty": []}"#, &EnglishLocalizer);
+        let document = Document::parse_html(&page).unwrap();
+
+        assert_eq!(document.title().as_deref(), Some("data.json"));
+        // Only the root object and the non-empty array collapse
+        let details = document.get_elements_by_tag_name("details");
+        assert_eq!(details.len(), 2);
+        let keys: Vec<String> = document
+            .get_elements_by_class_name("json-key")
+            .iter()
+            .map(|k| k.text_content())
+            .collect();
+        let mut sorted_keys = keys.clone();
+        sorted_keys.sort();
+        assert_eq!(sorted_keys, vec!["0", "1", "empty", "name", "tags"]);
+        assert!(page.find("name").unwrap() < page.find("tags").unwrap());
+        assert!(page.contains("&quot;&lt;b&gt;&quot;"));
+        let mut counts: Vec<String> = document
+            .get_elements_by_class_name("json-count")
+            .iter()
+            .map(|count| count.text_content())
+            .collect();
+        counts.sort();
+        assert_eq!(counts, ["2 items", "3 keys"]);
+        assert!(render_page("one.json", "[1]", &EnglishLocalizer).contains(">1 item<"));
+
+        // Placeholders in the data stay literal
+        let source = r#"{"{{raw}}": "{{tree_label}}"}"#;
+        let literal = render_page("data.json?q={{raw}}", source, &EnglishLocalizer);
+        assert_eq!(literal.matches("{{raw}}").count(), 3);
+        let literal = Document::parse_html(&literal).unwrap();
+        assert_eq!(literal.title().as_deref(), Some("data.json?q={{raw}}"));
+        assert_eq!(literal.get_element_by_id("raw").unwrap().text_content(), source);
+
+        let raw = document.get_element_by_id("raw").unwrap();
+        assert_eq!(raw.text_content(), r#"{"name": "<b>", "tags": ["x", "y"], "empty": []}"#);
+
+        let page = render_page("bad.json", "{\"a\": }", &EnglishLocalizer);
+        assert!(page.contains("Invalid JSON: unexpected character at line 1, column 7"));
+        assert!(page.contains(r#"placeholder="Filter JSON""#));
+    }
+
+    #[test]
+    fn test_json_mime_types() {
+        assert!(is_json_mime_type("application/json"));
+        assert!(is_json_mime_type("application/ld+json"));
+        assert!(!is_json_mime_type("text/html"));
+    }
+}
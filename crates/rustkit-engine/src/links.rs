@@ -0,0 +1,130 @@
+//! Links.
+//!
+//! While the pointer is over a link, hosts show where it goes in a status
+//! bubble: the engine reports the resolved destination with
+//! [`EngineEvent::TargetUrlChanged`] when the pointer moves onto a
+//! different link, and reports `None` when it leaves.
+//!
+//! Clicking a link goes to its destination. Going to a URL that only adds
+//! or changes the `#fragment` of the page's own URL stays in the document:
+//! the view scrolls to the element the fragment names, reported with
+//! [`EngineEvent::SameDocumentNavigation`]. Other links load their page
+//! when [`Engine::pump_navigations`] next runs. Links to internal pages,
+//! such as `about:settings`, aren't followed: only the host opens those.
+//!
+//! [`EngineEvent::TargetUrlChanged`]: crate::EngineEvent::TargetUrlChanged
+//! [`EngineEvent::SameDocumentNavigation`]: crate::EngineEvent::SameDocumentNavigation
+//! [`Engine::pump_navigations`]: crate::Engine::pump_navigations
+
+use std::rc::Rc;
+
+use rustkit_dom::{Document, Node};
+use url::Url;
+
+use crate::internal_pages::InternalPages;
+
+/// The link `node` is in: the nearest `<a>` or `<area>` with an `href`,
+/// itself included.
+pub(crate) fn enclosing_link(node: Rc<Node>) -> Option<Rc<Node>> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        let is_link = node
+            .tag_name()
+            .is_some_and(|tag| tag.eq_ignore_ascii_case("a") || tag.eq_ignore_ascii_case("area"));
+        if is_link && node.get_attribute("href").is_some() {
+            return Some(node);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Where `link` goes, its `href` resolved against the document's URL.
+pub(crate) fn link_url(link: &Node, base_url: Option<&Url>) -> Option<Url> {
+    let href = link.get_attribute("href")?.trim();
+    match base_url {
+        Some(base) => base.join(href).ok(),
+        None => Url::parse(href).ok(),
+    }
+}
+
+/// Whether a page may start a navigation to `url`. Script URLs run script
+/// rather than navigate, which isn't supported, and internal pages are
+/// only for the host to open, as they get elevated IPC.
+pub(crate) fn page_may_navigate_to(url: &Url) -> bool {
+    url.scheme() != "javascript" && !InternalPages::is_internal_url(url)
+}
+
+/// Whether going from `from` to `to` stays in the document: `to` has a
+/// fragment and otherwise matches `from`.
+pub(crate) fn is_fragment_navigation(from: &Url, to: &Url) -> bool {
+    to.fragment().is_some() && from[..url::Position::AfterQuery] == to[..url::Position::AfterQuery]
+}
+
+/// The element name in a URL fragment: percent-decoded, without any text
+/// directives.
+pub(crate) fn fragment_name(fragment: &str) -> String {
+    let name = fragment.split(":~:").next().unwrap_or_default();
+    urlencoding::decode(name).map_or_else(|_| name.to_string(), |name| name.into_owned())
+}
+
+/// The element a fragment `name` indicates: the one with that ID, else
+/// the first `<a>` with that `name`.
+pub(crate) fn fragment_target(document: &Document, name: &str) -> Option<Rc<Node>> {
+    document.get_element_by_id(name).or_else(|| {
+        document
+            .get_elements_by_tag_name("a")
+            .into_iter()
+            .find(|anchor| anchor.get_attribute("name") == Some(name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_hovered_link_destination() {
+        let doc = Document::parse_html(
+            r#"<a id="a" href=" ../docs/intro.html#start "><b id="b">Intro</b></a>
+               <a id="anchor">No href</a><p id="p">Text</p>"#,
+        )
+        .unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+        let base = Url::parse("https://example.com/guide/index.html").unwrap();
+
+        let link = enclosing_link(node("b")).unwrap();
+        assert!(Rc::ptr_eq(&link, &node("a")));
+        assert_eq!(
+            link_url(&link, Some(&base)).unwrap().as_str(),
+            "https://example.com/docs/intro.html#start"
+        );
+        assert!(link_url(&link, None).is_none());
+        assert!(enclosing_link(node("anchor")).is_none());
+        assert!(enclosing_link(node("p")).is_none());
+    }
+
+    #[test]
+    fn test_fragment_navigation_target() {
+        let doc = Document::parse_html(r#"<h2 id="café">Café</h2><a name="legacy">Old</a>"#).unwrap();
+        let page = Url::parse("https://example.com/menu?lang=fr").unwrap();
+        let url = |s: &str| Url::parse(s).unwrap();
+
+        assert!(is_fragment_navigation(&page, &url("https://example.com/menu?lang=fr#caf%C3%A9")));
+        assert!(is_fragment_navigation(&url("https://example.com/menu?lang=fr#a"), &url("https://example.com/menu?lang=fr#")));
+        // Without a fragment it's a reload; another query is another page
+        assert!(!is_fragment_navigation(&url("https://example.com/menu?lang=fr#a"), &page));
+        assert!(!is_fragment_navigation(&page, &url("https://example.com/menu?lang=en#caf%C3%A9")));
+
+        assert!(page_may_navigate_to(&page));
+        assert!(!page_may_navigate_to(&url("javascript:alert(1)")));
+        assert!(!page_may_navigate_to(&url("about:settings")));
+        assert!(!page_may_navigate_to(&url("hiwave://gpu/")));
+
+        assert_eq!(fragment_name("caf%C3%A9:~:text=menu"), "café");
+        assert_eq!(fragment_target(&doc, &fragment_name("caf%C3%A9")).unwrap().tag_name(), Some("h2"));
+        assert_eq!(fragment_target(&doc, "legacy").unwrap().tag_name(), Some("a"));
+        assert!(fragment_target(&doc, "missing").is_none());
+    }
+}
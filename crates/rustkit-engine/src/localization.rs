@@ -0,0 +1,111 @@
+//! Localization of the text the engine generates itself.
+//!
+//! Error pages, internal pages, the default `<details>` summary and the
+//! JSON viewer's controls are written by the engine rather than a page. Each
+//! string is looked up by key through the [`Localizer`] in
+//! [`EngineConfig`](crate::EngineConfig), which defaults to English.
+//! Messages name their arguments in braces, e.g. `Invalid JSON: {error}`.
+
+use std::fmt;
+
+/// Provides the text of engine-generated strings in the user's language.
+pub trait Localizer: Send + Sync {
+    /// The text for `key`, with `args` (name and value pairs) filled in.
+    fn localize(&self, key: &str, args: &[(&str, &str)]) -> String;
+}
+
+impl fmt::Debug for dyn Localizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Localizer")
+    }
+}
+
+/// The built-in English strings. Localizers can fall back to it for keys
+/// they don't translate. Unknown keys come back as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnglishLocalizer;
+
+impl Localizer for EnglishLocalizer {
+    fn localize(&self, key: &str, args: &[(&str, &str)]) -> String {
+        format_message(english(key).unwrap_or(key), args)
+    }
+}
+
+/// The English text for `key`.
+fn english(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "error.not-loaded.title" => "Page not loaded",
+        "error.dns.title" => "This site can't be reached",
+        "error.dns.message" => "The server's address couldn't be found.",
+        "error.tls.title" => "Your connection isn't secure",
+        "error.tls.message" => "A secure connection to the server couldn't be established.",
+        "error.http.title" => "This page isn't working",
+        "error.http.message" => "The server couldn't return the page.",
+        "error.network.title" => "This site can't be reached",
+        "error.network.message" => "The connection to the server failed.",
+        "error.other.title" => "This page couldn't be loaded",
+        "error.other.message" => "Something went wrong loading the page.",
+        "error.address" => "Address",
+        "error.error" => "Error",
+        "error.code" => "Code",
+        "version.title" => "About HiWave",
+        "version.engine" => "Engine",
+        "version.user-agent" => "User agent",
+        "version.os" => "OS",
+        "gpu.title" => "GPU",
+        "gpu.adapter" => "Adapter",
+        "flags.title" => "Flags",
+        "details.summary" => "Details",
+        "json.raw" => "Raw Data",
+        "json.tree" => "JSON",
+        "json.filter" => "Filter JSON",
+        "json.invalid" => "Invalid JSON: {error}",
+        "json.item" => "{count} item",
+        "json.items" => "{count} items",
+        "json.key" => "{count} key",
+        "json.keys" => "{count} keys",
+        _ => return None,
+    })
+}
+
+/// Fill `{name}` placeholders in `message` with `args`. Placeholders
+/// without an argument are left in place.
+pub fn format_message(message: &str, args: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| args.iter().find(|(name, _)| *name == &after[..close]).map(|arg| (close, arg.1)));
+        match value {
+            Some((close, value)) => {
+                text.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_localizer() {
+        let english = EnglishLocalizer;
+        assert_eq!(english.localize("details.summary", &[]), "Details");
+        assert_eq!(english.localize("json.invalid", &[("error", "unexpected end")]), "Invalid JSON: unexpected end");
+        assert_eq!(english.localize("no.such.key", &[]), "no.such.key");
+
+        assert_eq!(format_message("{a} and {b}", &[("b", "2"), ("a", "1")]), "1 and 2");
+        assert_eq!(format_message("{missing} {", &[]), "{missing} {");
+    }
+}
@@ -0,0 +1,126 @@
+//! Page DOM mutations.
+//!
+//! Script changes to a page's document queue up in its bindings. After
+//! script runs, the engine applies them to the view's DOM, which marks the
+//! nodes they touch as needing layout, and a view whose document needs
+//! layout gets a relayout queued, which rebuilds only the changed
+//! subtrees.
+
+use std::rc::Rc;
+
+use rustkit_bindings::DomMutation;
+use rustkit_dom::{Document, Node, NodeId};
+
+/// Apply `mutation` to `document`. Returns the element made for an
+/// inserted element script holds, with its key, for the bindings to tie
+/// the two together.
+///
+/// Mutations of nodes that are gone are dropped.
+pub(crate) fn apply(document: &Document, mutation: &DomMutation) -> Result<Option<(u64, Rc<Node>)>, String> {
+    let node = |raw: usize| document.get_node(NodeId::new(raw));
+    match mutation {
+        DomMutation::SetAttribute { node: target, name, value } => {
+            if let Some(element) = node(*target) {
+                document.set_attribute(&element, name, value);
+            }
+        }
+        DomMutation::RemoveAttribute { node: target, name } => {
+            if let Some(element) = node(*target) {
+                element.remove_attribute(name);
+            }
+        }
+        DomMutation::Insert { parent, before, markup, key } => {
+            let Some(parent) = node(*parent) else {
+                return Ok(None);
+            };
+            let fragment = document.parse_fragment(markup).map_err(|e| e.to_string())?;
+            let children = fragment.children();
+            let bound = match (key, children.as_slice()) {
+                (Some(key), [element]) if element.is_element() => Some((*key, element.clone())),
+                _ => None,
+            };
+            insert(&parent, fragment, before.and_then(node));
+            return Ok(bound);
+        }
+        DomMutation::Move { node: target, parent, before } => {
+            let (Some(target), Some(parent)) = (node(*target), node(*parent)) else {
+                return Ok(None);
+            };
+            // A node can't go inside itself
+            if target.is_shadow_including_inclusive_ancestor_of(&parent) {
+                return Err("Can't move a node into itself".to_string());
+            }
+            target.remove_from_parent();
+            insert(&parent, target, before.and_then(node));
+        }
+        DomMutation::Remove { node: target } => {
+            if let Some(target) = node(*target) {
+                target.remove_from_parent();
+            }
+        }
+        DomMutation::ReplaceChildren { node: target, markup } => {
+            let Some(target) = node(*target) else {
+                return Ok(None);
+            };
+            let fragment = document.parse_fragment(markup).map_err(|e| e.to_string())?;
+            target.remove_children();
+            target.append_child(fragment);
+        }
+    }
+    Ok(None)
+}
+
+/// Insert `child` into `parent` before `reference`, or last if there's no
+/// reference or it isn't a child of `parent`.
+fn insert(parent: &Rc<Node>, child: Rc<Node>, reference: Option<Rc<Node>>) {
+    match reference {
+        Some(reference) if reference.parent().is_some_and(|p| Rc::ptr_eq(&p, parent)) => {
+            parent.insert_before(child, reference)
+        }
+        _ => parent.append_child(child),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let document = Document::parse_html(r#"<ul id="list"><li id="first">One</li></ul><p id="note">Note</p>"#).unwrap();
+        let raw = |id: &str| document.get_element_by_id(id).unwrap().id.raw();
+        let list = document.get_element_by_id("list").unwrap();
+        document.clear_needs_layout();
+
+        let inserted = apply(
+            &document,
+            &DomMutation::Insert {
+                parent: raw("list"),
+                before: Some(raw("first")),
+                markup: "<li class=\"new\">Zero</li>".to_string(),
+                key: Some(7),
+            },
+        )
+        .unwrap();
+        let (key, zero) = inserted.unwrap();
+        assert_eq!(key, 7);
+        assert!(Rc::ptr_eq(&list.first_child().unwrap(), &zero));
+        assert!(list.needs_layout() && document.needs_layout());
+
+        let set = DomMutation::SetAttribute { node: zero.id.raw(), name: "class".into(), value: "old".into() };
+        apply(&document, &set).unwrap();
+        assert_eq!(zero.attributes().get("class").map(String::as_str), Some("old"));
+
+        let note = raw("note");
+        apply(&document, &DomMutation::Move { node: note, parent: raw("list"), before: None }).unwrap();
+        assert_eq!(list.text_content(), "ZeroOneNote");
+        assert!(apply(&document, &DomMutation::Move { node: raw("list"), parent: note, before: None }).is_err());
+
+        apply(&document, &DomMutation::ReplaceChildren { node: raw("first"), markup: "<b>1</b>".into() }).unwrap();
+        apply(&document, &DomMutation::Remove { node: note }).unwrap();
+        assert_eq!(list.text_content(), "Zero1");
+
+        // Mutations of nodes that are gone do nothing
+        assert!(apply(&document, &DomMutation::Remove { node: 10_000 }).unwrap().is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! Per-view network request log.
+//!
+//! Every request a view's page makes, for its document, stylesheets,
+//! scripts, images and `fetch()` calls, is logged with its timing, status
+//! and size, for debugging and for devtools. The log starts afresh with
+//! each navigation, and keeps only the latest [`MAX_ENTRIES`] requests of
+//! pages that go on making them.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+use rustkit_net::Response;
+use url::Url;
+
+/// Most requests kept per view.
+pub const MAX_ENTRIES: usize = 1000;
+
+/// What a request was made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    Document,
+    Stylesheet,
+    Script,
+    Image,
+    /// A page's `fetch()` or `XMLHttpRequest`.
+    Fetch,
+}
+
+/// A request in a view's network log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkRequest {
+    /// Identifies the request within the engine.
+    pub id: u64,
+    pub url: Url,
+    pub method: String,
+    pub kind: RequestKind,
+    /// When the request was made.
+    pub started_at: SystemTime,
+    /// How long until it finished; `None` while it's in flight.
+    pub duration: Option<Duration>,
+    /// The response's HTTP status. `None` while the request is in flight,
+    /// if it failed, and for images, whose loader only tells whether they
+    /// loaded.
+    pub status: Option<u16>,
+    /// Body bytes received over the network.
+    pub encoded_size: u64,
+    /// Body bytes after decoding.
+    pub decoded_size: u64,
+    /// Whether the response came from the HTTP cache.
+    pub from_cache: bool,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
+
+impl NetworkRequest {
+    /// Whether the request has finished, with a response or an error.
+    pub fn is_finished(&self) -> bool {
+        self.duration.is_some()
+    }
+}
+
+/// What a finished request got back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Transfer {
+    pub status: Option<u16>,
+    pub encoded_size: u64,
+    pub decoded_size: u64,
+    pub from_cache: bool,
+}
+
+impl Transfer {
+    pub fn of(response: &Response) -> Self {
+        Self {
+            status: Some(response.status.as_u16()),
+            encoded_size: response.encoded_size,
+            decoded_size: response.decoded_size,
+            from_cache: response.from_cache,
+        }
+    }
+}
+
+/// The requests of a view's current page, oldest first.
+#[derive(Debug, Default)]
+pub(crate) struct NetworkLog {
+    entries: VecDeque<(NetworkRequest, Instant)>,
+}
+
+impl NetworkLog {
+    /// Log a request as it starts.
+    pub fn start(&mut self, id: u64, kind: RequestKind, method: &str, url: &Url) -> &NetworkRequest {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        let request = NetworkRequest {
+            id,
+            url: url.clone(),
+            method: method.to_string(),
+            kind,
+            started_at: SystemTime::now(),
+            duration: None,
+            status: None,
+            encoded_size: 0,
+            decoded_size: 0,
+            from_cache: false,
+            error: None,
+        };
+        self.entries.push_back((request, Instant::now()));
+        &self.entries.back().unwrap().0
+    }
+
+    /// Record how request `id` ended at `finished_at`. Returns the entry,
+    /// unless the request isn't in the log or has already finished.
+    pub fn finish(
+        &mut self,
+        id: u64,
+        result: Result<Transfer, String>,
+        finished_at: Instant,
+    ) -> Option<&NetworkRequest> {
+        let (request, started) = self.entries.iter_mut().rev().find(|(request, _)| request.id == id)?;
+        if request.is_finished() {
+            return None;
+        }
+        request.duration = Some(finished_at.saturating_duration_since(*started));
+        match result {
+            Ok(transfer) => {
+                request.status = transfer.status;
+                request.encoded_size = transfer.encoded_size;
+                request.decoded_size = transfer.decoded_size;
+                request.from_cache = transfer.from_cache;
+            }
+            Err(error) => request.error = Some(error),
+        }
+        Some(request)
+    }
+
+    pub fn entries(&self) -> Vec<NetworkRequest> {
+        self.entries.iter().map(|(request, _)| request.clone()).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_log() {
+        let url = Url::parse("https://example.com/app.js").unwrap();
+        let mut log = NetworkLog::default();
+        let started = Instant::now();
+        log.start(1, RequestKind::Script, "GET", &url);
+        log.start(2, RequestKind::Fetch, "POST", &url);
+
+        let transfer = Transfer { status: Some(200), encoded_size: 10, decoded_size: 30, from_cache: false };
+        let finished = log.finish(1, Ok(transfer), started + Duration::from_secs(1)).unwrap();
+        assert_eq!(finished.status, Some(200));
+        assert_eq!(finished.decoded_size, 30);
+        assert!(finished.duration.unwrap() <= Duration::from_secs(1));
+        // Finishing twice, or a request that isn't logged, does nothing
+        assert!(log.finish(1, Err("late".into()), Instant::now()).is_none());
+        assert!(log.finish(3, Err("unknown".into()), Instant::now()).is_none());
+
+        log.finish(2, Err("Connection refused".into()), Instant::now());
+        let entries = log.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].method, "POST");
+        assert_eq!(entries[1].status, None);
+        assert_eq!(entries[1].error.as_deref(), Some("Connection refused"));
+
+        for id in 10..10 + MAX_ENTRIES as u64 {
+            log.start(id, RequestKind::Image, "GET", &url);
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].id, 10);
+    }
+}
@@ -0,0 +1,45 @@
+//! Popover invokers.
+//!
+//! Clicking a `popovertarget` button, or anything inside one, toggles,
+//! shows or hides its popover, which is then anchored to the button.
+//! Clicking outside the open auto popovers light-dismisses them, except
+//! that clicking the invoker of an open popover leaves it to the invoker.
+
+use std::rc::Rc;
+
+use rustkit_dom::{Document, Node, PopoverTargetAction};
+
+/// The `popovertarget` button that clicking `node` activates, with its
+/// popover and action.
+pub(crate) fn popover_invoker(node: Rc<Node>, document: &Document) -> Option<(Rc<Node>, Rc<Node>, PopoverTargetAction)> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if let Some((popover, action)) = node.popover_target(document) {
+            return Some((node, popover, action));
+        }
+        current = node.parent();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_click_inside_button_invokes_popover() {
+        let doc = Document::parse_html(
+            r#"<button id="b" popovertarget="tip" popovertargetaction="show"><b id="label">Help</b></button>
+               <button id="broken" popovertarget="missing">?</button><div id="tip" popover>Tip</div>"#,
+        )
+        .unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+
+        let (invoker, popover, action) = popover_invoker(node("label"), &doc).unwrap();
+        assert!(Rc::ptr_eq(&invoker, &node("b")));
+        assert!(Rc::ptr_eq(&popover, &node("tip")));
+        assert_eq!(action, PopoverTargetAction::Show);
+        assert!(popover_invoker(node("broken"), &doc).is_none());
+        assert!(popover_invoker(node("tip"), &doc).is_none());
+    }
+}
@@ -0,0 +1,961 @@
+//! Printing to PDF.
+//!
+//! [`Engine::print_to_pdf`](crate::Engine::print_to_pdf) lays a view's
+//! document out at the width of the paper's content area, cuts the flow
+//! into pages with [`rustkit_layout::page_breaks`], and writes each page's
+//! share of the display list as PDF drawing operators. Text is set in the
+//! standard PDF font nearest the page's, so it stays sharp and selectable.
+//! Gradients become PDF shadings; blurs are drawn sharp, and what PDF has
+//! no equivalent for, like backdrop filters, is left out.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustkit_css::{Color, ColorF32, ColorStop, Gradient, GradientDirection, RadialShape, RadialSize};
+use rustkit_image::LoadedImage;
+use rustkit_layout::{BorderRadius, DisplayCommand, Rect};
+
+use crate::vector_export::{color_at, num, radial_radii, repeat_stops, ASCENT};
+
+/// PDF points per CSS pixel.
+const POINTS_PER_PX: f32 = 0.75;
+
+/// How far a quarter circle's Bézier control points sit from its ends, as
+/// a fraction of the radius.
+const KAPPA: f32 = 0.552_284_8;
+
+/// Degrees per wedge when drawing conic gradients, which PDF lacks.
+const CONIC_STEP: f32 = 2.0;
+
+/// The paper a view is printed on.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    /// 210 × 297 mm.
+    #[default]
+    A4,
+    /// 8.5 × 11 in.
+    Letter,
+    /// Width and height in CSS pixels, 96 to the inch.
+    Custom { width: f32, height: f32 },
+}
+
+impl PageSize {
+    /// Width and height in CSS pixels, upright.
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (793.7, 1122.52),
+            PageSize::Letter => (816.0, 1056.0),
+            PageSize::Custom { width, height } => (width, height),
+        }
+    }
+}
+
+/// Space between the paper's edges and what's printed, in CSS pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageMargins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl PageMargins {
+    /// The same margin on every side.
+    pub fn uniform(margin: f32) -> Self {
+        Self {
+            top: margin,
+            right: margin,
+            bottom: margin,
+            left: margin,
+        }
+    }
+}
+
+impl Default for PageMargins {
+    /// Half an inch all round.
+    fn default() -> Self {
+        Self::uniform(48.0)
+    }
+}
+
+/// How [`Engine::print_to_pdf`](crate::Engine::print_to_pdf) lays out pages.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PrintOptions {
+    pub page_size: PageSize,
+    /// Turn the paper sideways.
+    pub landscape: bool,
+    pub margins: PageMargins,
+}
+
+impl PrintOptions {
+    /// The paper's width and height in CSS pixels, as it's turned.
+    pub fn paper_size(&self) -> (f32, f32) {
+        let (width, height) = self.page_size.size();
+        if self.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// The size of the area within the margins that pages are laid out in.
+    pub fn content_size(&self) -> (f32, f32) {
+        let (width, height) = self.paper_size();
+        let margins = &self.margins;
+        (
+            (width - margins.left - margins.right).max(1.0),
+            (height - margins.top - margins.bottom).max(1.0),
+        )
+    }
+}
+
+/// The PDF for `commands`, drawn for a flow laid out at the content width
+/// of `options`, with a page starting at each of `page_starts`. `images`
+/// are the decoded images the commands draw, by their URL.
+pub(crate) fn to_pdf(
+    commands: &[DisplayCommand],
+    page_starts: &[f32],
+    options: &PrintOptions,
+    images: &HashMap<String, Arc<LoadedImage>>,
+) -> Vec<u8> {
+    let (paper_width, paper_height) = options.paper_size();
+    let (content_width, content_height) = options.content_size();
+    let margins = options.margins;
+    let mut pdf = PdfWriter::default();
+
+    for (index, &start) in page_starts.iter().enumerate() {
+        let end = page_starts.get(index + 1).map_or(start + content_height, |next| next.min(start + content_height));
+        // Whatever overflows sideways is cut off at the paper's edge anyway
+        let region = Rect::new(-paper_width, start, paper_width * 3.0, end - start);
+
+        // CSS pixels down from the paper's top left corner, then the page's
+        // part of the flow in the content area
+        let _ = write!(
+            pdf.ops,
+            "{} 0 0 {} 0 {} cm\n{} re W n\n1 0 0 1 {} {} cm\n",
+            num(POINTS_PER_PX),
+            num(-POINTS_PER_PX),
+            num(paper_height * POINTS_PER_PX),
+            rect_path(Rect::new(margins.left, margins.top, content_width, end - start)),
+            num(margins.left),
+            num(margins.top - start)
+        );
+        for command in rustkit_renderer::commands_within(commands, &[region]) {
+            pdf.command(&command, images);
+        }
+        pdf.end_page();
+    }
+    pdf.finish(paper_width * POINTS_PER_PX, paper_height * POINTS_PER_PX)
+}
+
+#[derive(Default)]
+struct PdfWriter {
+    /// Bodies of the objects after the catalog, page tree and resources.
+    objects: Vec<Vec<u8>>,
+    /// Content streams of the finished pages, by object number.
+    pages: Vec<usize>,
+    /// Operators of the page being drawn.
+    ops: String,
+    /// Graphics states saved by push commands and not yet restored.
+    depth: usize,
+    /// Opacity of each open opacity group, with the depth it was opened at.
+    opacity: Vec<(usize, f32)>,
+    /// Standard fonts used, named `F1`, `F2`, ...
+    fonts: Vec<&'static str>,
+    /// Alphas in thousandths, named `GS1`, `GS2`, ...
+    alphas: Vec<u16>,
+    /// Image objects, named `Im1`, `Im2`, ..., and what URL each shows.
+    images: Vec<(String, usize)>,
+    /// Shading objects, named `Sh1`, `Sh2`, ...
+    shadings: Vec<usize>,
+}
+
+/// Object numbers of the objects every PDF has.
+const CATALOG: usize = 1;
+const PAGE_TREE: usize = 2;
+const RESOURCES: usize = 3;
+
+impl PdfWriter {
+    fn command(&mut self, command: &DisplayCommand, images: &HashMap<String, Arc<LoadedImage>>) {
+        match command {
+            DisplayCommand::SolidColor(color, rect) | DisplayCommand::FillRect { rect, color } => {
+                self.fill(&rect_path(*rect), *color);
+            }
+            DisplayCommand::RoundedRect { color, rect, radius } => self.fill(&rounded_path(*rect, *radius), *color),
+            DisplayCommand::Border { color, rect, top, right, bottom, left } => {
+                let sides = [
+                    Rect::new(rect.x, rect.y, rect.width, *top),
+                    Rect::new(rect.x + rect.width - right, rect.y + top, *right, rect.height - top - bottom),
+                    Rect::new(rect.x, rect.y + rect.height - bottom, rect.width, *bottom),
+                    Rect::new(rect.x, rect.y + top, *left, rect.height - top - bottom),
+                ];
+                for side in sides.into_iter().filter(|side| side.width > 0.0 && side.height > 0.0) {
+                    self.fill(&rect_path(side), *color);
+                }
+            }
+            DisplayCommand::Text { text, x, y, color, font_size, font_family, font_weight, font_style } => {
+                let font = standard_font(font_family, *font_weight, *font_style);
+                self.text(text, *x, *y, *font_size, font, *color);
+            }
+            DisplayCommand::TextRun { segments, color, font_size, font_family, font_weight, font_style } => {
+                let font = standard_font(font_family, *font_weight, *font_style);
+                for segment in segments {
+                    self.text(&segment.text, segment.x, segment.y, *font_size, font, *color);
+                }
+            }
+            // Drawn solid whatever the style, as the renderer does
+            DisplayCommand::TextDecoration { x, y, width, thickness, color, style: _ } => {
+                self.fill(&rect_path(Rect::new(*x, *y, *width, *thickness)), *color);
+            }
+            DisplayCommand::Image { url, src_rect: _, dest_rect, object_fit: _, opacity } => {
+                if let Some(image) = images.get(url) {
+                    self.image(url, image, *dest_rect, *opacity);
+                }
+            }
+            DisplayCommand::BackgroundImage { url, rect, size, position, offset, repeat } => {
+                let Some(image) = images.get(url) else {
+                    return;
+                };
+                let natural = image.current_frame(Duration::ZERO);
+                let (width, height) = size.compute_size(*rect, natural.width() as f32, natural.height() as f32);
+                if width <= 0.0 || height <= 0.0 {
+                    return;
+                }
+                let x = rect.x + (rect.width - width) * position.0 + offset.0;
+                let y = rect.y + (rect.height - height) * position.1 + offset.1;
+                // Tiles back to the box's edge, then on past its end
+                let tiles = |start: f32, size: f32, from: f32, extent: f32, repeats: bool| {
+                    if !repeats {
+                        return vec![start];
+                    }
+                    let first = start - ((start - from) / size).ceil() * size;
+                    let count = ((from + extent - first) / size).ceil().max(1.0) as usize;
+                    (0..count).map(|i| first + i as f32 * size).collect()
+                };
+                self.save(&format!("{} W n", rect_path(*rect)));
+                for tile_y in tiles(y, height, rect.y, rect.height, repeat.repeats_y()) {
+                    for tile_x in tiles(x, width, rect.x, rect.width, repeat.repeats_x()) {
+                        self.image(url, image, Rect::new(tile_x, tile_y, width, height), 1.0);
+                    }
+                }
+                self.restore();
+            }
+            DisplayCommand::BoxShadow { offset_x, offset_y, blur_radius: _, spread_radius, color, rect, inset } => {
+                let spread = *spread_radius;
+                if *inset {
+                    // Everything around the shrunken box, seen through the box
+                    let hole = Rect::new(
+                        rect.x + offset_x + spread,
+                        rect.y + offset_y + spread,
+                        rect.width - spread * 2.0,
+                        rect.height - spread * 2.0,
+                    );
+                    self.save(&format!("{} W n", rect_path(*rect)));
+                    let path = format!("{} {}", rect_path(*rect), rect_path(hole));
+                    self.paint(&path, *color, "f*");
+                    self.restore();
+                } else {
+                    let shadow = Rect::new(
+                        rect.x + offset_x - spread,
+                        rect.y + offset_y - spread,
+                        rect.width + spread * 2.0,
+                        rect.height + spread * 2.0,
+                    );
+                    self.fill(&rect_path(shadow), *color);
+                }
+            }
+            // PDF can't filter what's behind an element
+            DisplayCommand::BackdropFilter { .. } => {}
+            DisplayCommand::LinearGradient { rect, direction, stops, repeating, border_radius } => {
+                let shading = self.linear_gradient(*rect, *direction, stops, *repeating);
+                self.shade(&rounded_path(*rect, *border_radius), shading);
+            }
+            DisplayCommand::RadialGradient { rect, shape, size, center, stops, repeating, border_radius } => {
+                let shading = self.radial_gradient(*rect, *shape, *size, *center, stops, *repeating);
+                self.shade(&rounded_path(*rect, *border_radius), shading);
+            }
+            DisplayCommand::ConicGradient { rect, from_angle, center, stops, repeating, border_radius } => {
+                self.conic_gradient(*rect, *from_angle, *center, stops, *repeating, *border_radius);
+            }
+            DisplayCommand::TextInput {
+                rect,
+                value,
+                placeholder,
+                font_size,
+                text_color,
+                placeholder_color,
+                background_color,
+                border_color,
+                border_width,
+                focused: _,
+                caret_position: _,
+            } => {
+                self.bordered(*rect, 0.0, *background_color, *border_color, *border_width);
+                let (text, color) = if value.is_empty() { (placeholder, placeholder_color) } else { (value, text_color) };
+                let top = rect.y + (rect.height - font_size) / 2.0;
+                self.text(text, rect.x + 6.0, top, *font_size, standard_font("sans-serif", 400, 0), *color);
+            }
+            DisplayCommand::Button {
+                rect,
+                label,
+                font_size,
+                text_color,
+                background_color,
+                border_color,
+                border_width,
+                border_radius,
+                pressed: _,
+                focused: _,
+            } => {
+                self.bordered(*rect, *border_radius, *background_color, *border_color, *border_width);
+                // Centered by a rough width, without the font's metrics
+                let width = label.chars().count() as f32 * font_size * 0.5;
+                let top = rect.y + (rect.height - font_size) / 2.0;
+                let x = rect.x + (rect.width - width) / 2.0;
+                self.text(label, x, top, *font_size, standard_font("sans-serif", 400, 0), *text_color);
+            }
+            DisplayCommand::FocusRing { rect, color, width, offset } => {
+                let grow = offset + width / 2.0;
+                let ring = Rect::new(rect.x - grow, rect.y - grow, rect.width + grow * 2.0, rect.height + grow * 2.0);
+                self.stroke(&rect_path(ring), *color, *width);
+            }
+            DisplayCommand::Caret { x, y, height, color } => {
+                self.fill(&rect_path(Rect::new(*x, *y, 2.0, *height)), *color);
+            }
+            DisplayCommand::PushClip(rect) => self.save(&format!("{} W n", rect_path(*rect))),
+            DisplayCommand::PushRoundedClip { rect, radius } => {
+                self.save(&format!("{} W n", rounded_path(*rect, *radius)));
+            }
+            // Printed pages don't composite layers
+            DisplayCommand::PushStackingContext { .. } | DisplayCommand::PushLayer { .. } => self.save(""),
+            DisplayCommand::PushTransform { matrix, origin } => {
+                let m = rustkit_layout::transform::around_origin(*matrix, *origin);
+                let ops = format!("{} {} {} {} {} {} cm", num(m[0]), num(m[1]), num(m[2]), num(m[3]), num(m[4]), num(m[5]));
+                self.save(&ops);
+            }
+            DisplayCommand::PushOpacity(opacity) => {
+                self.save("");
+                let opacity = opacity.clamp(0.0, 1.0) * self.opacity();
+                self.opacity.push((self.depth, opacity));
+            }
+            DisplayCommand::PopClip
+            | DisplayCommand::PopRoundedClip
+            | DisplayCommand::PopStackingContext
+            | DisplayCommand::PopTransform
+            | DisplayCommand::PopLayer
+            | DisplayCommand::PopOpacity => self.restore(),
+            // Text can't be filled with a shading; use the average color
+            DisplayCommand::GradientText {
+                text,
+                x,
+                y,
+                font_size,
+                font_family,
+                font_weight,
+                font_style,
+                gradient,
+                rect: _,
+            } => {
+                let stops = match gradient {
+                    Gradient::Linear(linear) => &linear.stops,
+                    Gradient::Radial(radial) => &radial.stops,
+                    Gradient::Conic(conic) => &conic.stops,
+                };
+                let stops = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(1.0));
+                let count = stops.len().max(1) as f32;
+                let sum = stops
+                    .iter()
+                    .fold([0.0; 4], |sum, (_, c)| [sum[0] + c.r, sum[1] + c.g, sum[2] + c.b, sum[3] + c.a]);
+                let color = to_color(ColorF32 { r: sum[0] / count, g: sum[1] / count, b: sum[2] / count, a: sum[3] / count });
+                let font = standard_font(font_family, *font_weight, *font_style);
+                self.text(text, *x, *y, *font_size, font, color);
+            }
+            DisplayCommand::StrokeRect { rect, color, width } => self.stroke(&rect_path(*rect), *color, *width),
+            DisplayCommand::FillCircle { cx, cy, radius, color } => {
+                self.fill(&ellipse_path(*cx, *cy, *radius, *radius), *color);
+            }
+            DisplayCommand::StrokeCircle { cx, cy, radius, color, width } => {
+                self.stroke(&ellipse_path(*cx, *cy, *radius, *radius), *color, *width);
+            }
+            DisplayCommand::FillEllipse { rect, color } => {
+                let path = ellipse_path(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0, rect.width / 2.0, rect.height / 2.0);
+                self.fill(&path, *color);
+            }
+            DisplayCommand::Line { x1, y1, x2, y2, color, width } => {
+                self.stroke(&polygon_path(&[(*x1, *y1), (*x2, *y2)], false), *color, *width);
+            }
+            DisplayCommand::Polyline { points, color, width } => self.stroke(&polygon_path(points, false), *color, *width),
+            DisplayCommand::FillPolygon { points, color } => self.fill(&polygon_path(points, true), *color),
+            DisplayCommand::StrokePolygon { points, color, width } => {
+                self.stroke(&polygon_path(points, true), *color, *width);
+            }
+        }
+    }
+
+    /// Save the graphics state, then apply `ops` until the matching restore.
+    fn save(&mut self, ops: &str) {
+        self.ops.push_str("q\n");
+        if !ops.is_empty() {
+            self.ops.push_str(ops);
+            self.ops.push('\n');
+        }
+        self.depth += 1;
+    }
+
+    /// Restore the state of the innermost save. Unbalanced pops are ignored.
+    fn restore(&mut self) {
+        if self.depth == 0 {
+            return;
+        }
+        self.opacity.retain(|&(depth, _)| depth < self.depth);
+        self.depth -= 1;
+        self.ops.push_str("Q\n");
+    }
+
+    /// Opacity of the innermost open opacity group.
+    fn opacity(&self) -> f32 {
+        self.opacity.last().map_or(1.0, |&(_, opacity)| opacity)
+    }
+
+    fn end_page(&mut self) {
+        while self.depth > 0 {
+            self.restore();
+        }
+        let ops = std::mem::take(&mut self.ops);
+        let id = self.stream("", ops.as_bytes());
+        self.pages.push(id);
+    }
+
+    /// Add an object, returning its number.
+    fn object(&mut self, body: Vec<u8>) -> usize {
+        self.objects.push(body);
+        RESOURCES + self.objects.len()
+    }
+
+    /// Add a stream object with extra dictionary `entries`.
+    fn stream(&mut self, entries: &str, data: &[u8]) -> usize {
+        let mut body = format!("<< /Length {}{entries} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(body)
+    }
+
+    /// Set the fill alpha to `alpha` times the group opacity, if it isn't 1,
+    /// returning whether the state needs restoring afterwards.
+    fn alpha(&mut self, alpha: f32) -> bool {
+        let alpha = ((alpha * self.opacity()).clamp(0.0, 1.0) * 1000.0).round() as u16;
+        if alpha >= 1000 {
+            return false;
+        }
+        let index = match self.alphas.iter().position(|&a| a == alpha) {
+            Some(index) => index,
+            None => {
+                self.alphas.push(alpha);
+                self.alphas.len() - 1
+            }
+        };
+        let _ = writeln!(self.ops, "q /GS{} gs", index + 1);
+        true
+    }
+
+    /// Paint `path` in `color` with the painting operator `op`.
+    fn paint(&mut self, path: &str, color: Color, op: &str) {
+        if color.a <= 0.0 {
+            return;
+        }
+        let translucent = self.alpha(color.a);
+        let operator = if op == "S" { "RG" } else { "rg" };
+        let _ = writeln!(self.ops, "{} {operator} {path} {op}", rgb(color));
+        if translucent {
+            self.ops.push_str("Q\n");
+        }
+    }
+
+    fn fill(&mut self, path: &str, color: Color) {
+        self.paint(path, color, "f");
+    }
+
+    fn stroke(&mut self, path: &str, color: Color, width: f32) {
+        self.paint(&format!("{} w {path}", num(width)), color, "S");
+    }
+
+    /// Text whose box's top is at `top`.
+    fn text(&mut self, text: &str, x: f32, top: f32, font_size: f32, font: &'static str, color: Color) {
+        if text.is_empty() || color.a <= 0.0 {
+            return;
+        }
+        let index = match self.fonts.iter().position(|&f| f == font) {
+            Some(index) => index,
+            None => {
+                self.fonts.push(font);
+                self.fonts.len() - 1
+            }
+        };
+        let translucent = self.alpha(color.a);
+        // The text matrix flips glyphs back upright in the page's y-down space
+        let _ = writeln!(
+            self.ops,
+            "BT {} rg /F{} {} Tf 1 0 0 -1 {} {} Tm ({}) Tj ET",
+            rgb(color),
+            index + 1,
+            num(font_size),
+            num(x),
+            num(top + font_size * ASCENT),
+            win_ansi(text)
+        );
+        if translucent {
+            self.ops.push_str("Q\n");
+        }
+    }
+
+    /// A box with a background and a border drawn inside its edge.
+    fn bordered(&mut self, rect: Rect, radius: f32, background: Color, border: Color, border_width: f32) {
+        self.fill(&rounded_path(rect, BorderRadius::uniform(radius)), background);
+        if border_width > 0.0 {
+            let inset = border_width / 2.0;
+            let edge = Rect::new(rect.x + inset, rect.y + inset, rect.width - border_width, rect.height - border_width);
+            let radius = BorderRadius::uniform((radius - inset).max(0.0));
+            self.stroke(&rounded_path(edge, radius), border, border_width);
+        }
+    }
+
+    /// Draw `url`'s image stretched over `rect`.
+    fn image(&mut self, url: &str, image: &LoadedImage, rect: Rect, opacity: f32) {
+        let index = match self.images.iter().position(|(u, _)| u == url) {
+            Some(index) => index,
+            None => {
+                let id = self.image_object(image);
+                self.images.push((url.to_string(), id));
+                self.images.len() - 1
+            }
+        };
+        let translucent = self.alpha(opacity);
+        // Image space is a unit square with its first row at the top
+        let _ = writeln!(
+            self.ops,
+            "q {} 0 0 {} {} {} cm /Im{} Do Q",
+            num(rect.width),
+            num(-rect.height),
+            num(rect.x),
+            num(rect.y + rect.height),
+            index + 1
+        );
+        if translucent {
+            self.ops.push_str("Q\n");
+        }
+    }
+
+    /// An image object for the first frame of `image`, with its alpha as a
+    /// soft mask if it has any.
+    fn image_object(&mut self, image: &LoadedImage) -> usize {
+        let frame = image.current_frame(Duration::ZERO);
+        let (width, height) = (frame.width(), frame.height());
+        let pixels = frame.data();
+        let rgb: Vec<u8> = pixels.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+        let alpha: Vec<u8> = pixels.chunks_exact(4).map(|pixel| pixel[3]).collect();
+        let common = format!(" /Type /XObject /Subtype /Image /Width {width} /Height {height} /BitsPerComponent 8");
+        let mask = if alpha.iter().any(|&a| a < 255) {
+            let mask = self.stream(&format!("{common} /ColorSpace /DeviceGray"), &alpha);
+            format!(" /SMask {mask} 0 R")
+        } else {
+            String::new()
+        };
+        self.stream(&format!("{common} /ColorSpace /DeviceRGB{mask}"), &rgb)
+    }
+
+    /// Fill `path` with a shading, in the group's opacity.
+    fn shade(&mut self, path: &str, shading: Option<usize>) {
+        let Some(shading) = shading else {
+            return;
+        };
+        self.save(&format!("{path} W n"));
+        self.alpha(1.0);
+        let _ = writeln!(self.ops, "/Sh{} sh", shading + 1);
+        self.restore();
+    }
+
+    /// An axial shading for a linear gradient over `rect`.
+    fn linear_gradient(
+        &mut self,
+        rect: Rect,
+        direction: GradientDirection,
+        stops: &[ColorStop],
+        repeating: bool,
+    ) -> Option<usize> {
+        let angle = direction.to_degrees().to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let half_length = (rect.width / 2.0 * sin.abs() + rect.height / 2.0 * cos.abs()).max(0.001);
+        let resolved = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(half_length * 2.0));
+        let stops = repeated(resolved, repeating);
+
+        // The gradient line runs through the center, pointing at `angle`
+        let (cx, cy) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+        let (x1, y1) = (cx - sin * half_length, cy + cos * half_length);
+        let (x2, y2) = (cx + sin * half_length, cy - cos * half_length);
+        let coords = format!("{} {} {} {}", num(x1), num(y1), num(x2), num(y2));
+        self.shading(2, &coords, &stops)
+    }
+
+    /// A radial shading for a radial gradient over `rect`. Elliptical
+    /// gradients are drawn as circles, of the larger radius.
+    fn radial_gradient(
+        &mut self,
+        rect: Rect,
+        shape: RadialShape,
+        size: RadialSize,
+        center: (f32, f32),
+        stops: &[ColorStop],
+        repeating: bool,
+    ) -> Option<usize> {
+        let (cx, cy) = (rect.x + rect.width * center.0, rect.y + rect.height * center.1);
+        let (rx, ry) = radial_radii(rect, shape, size, center);
+        let radius = rx.max(ry).max(0.001);
+        let resolved = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(radius));
+        let stops = repeated(resolved, repeating);
+        let coords = format!("{} {} 0 {} {} {}", num(cx), num(cy), num(cx), num(cy), num(radius));
+        self.shading(3, &coords, &stops)
+    }
+
+    /// A shading of `kind` (2 axial, 3 radial) at `coords` through `stops`,
+    /// which span 0 to 1 and are drawn opaque. Returns its index.
+    fn shading(&mut self, kind: u8, coords: &str, stops: &[(f32, ColorF32)]) -> Option<usize> {
+        if stops.len() < 2 {
+            return None;
+        }
+        let functions: Vec<String> = stops.windows(2).map(|pair| interpolation(pair[0].1, pair[1].1)).collect();
+        let bounds: Vec<String> = stops[1..stops.len() - 1].iter().map(|(offset, _)| fraction(*offset)).collect();
+        let body = format!(
+            "<< /ShadingType {kind} /ColorSpace /DeviceRGB /Coords [{coords}] /Function << /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >> /Extend [true true] >>",
+            functions.join(" "),
+            bounds.join(" "),
+            vec!["0 1"; functions.len()].join(" ")
+        );
+        let id = self.object(body.into_bytes());
+        self.shadings.push(id);
+        Some(self.shadings.len() - 1)
+    }
+
+    /// PDF has no conic gradients, so draw one as thin wedges around its
+    /// center, clipped to the box.
+    fn conic_gradient(
+        &mut self,
+        rect: Rect,
+        from_angle: f32,
+        center: (f32, f32),
+        stops: &[ColorStop],
+        repeating: bool,
+        radius: BorderRadius,
+    ) {
+        let resolved = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(360.0));
+        if resolved.is_empty() {
+            return;
+        }
+        let (resolved, _) = repeat_stops(resolved, repeating);
+        let (cx, cy) = (rect.x + rect.width * center.0, rect.y + rect.height * center.1);
+        let reach = rect.width.hypot(rect.height);
+
+        self.save(&format!("{} W n", rounded_path(rect, radius)));
+        let steps = (360.0 / CONIC_STEP) as usize;
+        for step in 0..steps {
+            let t = (step as f32 + 0.5) / steps as f32;
+            let color = color_at(&resolved, if repeating { t.fract() } else { t });
+            // A hair wider than the step, so no seams show between wedges
+            let start = (from_angle + step as f32 * CONIC_STEP - 90.0).to_radians();
+            let end = (from_angle + (step + 1) as f32 * CONIC_STEP - 90.0 + 0.5).to_radians();
+            let wedge = [
+                (cx, cy),
+                (cx + reach * start.cos(), cy + reach * start.sin()),
+                (cx + reach * end.cos(), cy + reach * end.sin()),
+            ];
+            self.fill(&polygon_path(&wedge, true), to_color(color));
+        }
+        self.restore();
+    }
+
+    fn finish(mut self, width: f32, height: f32) -> Vec<u8> {
+        let page_ids: Vec<usize> = self
+            .pages
+            .clone()
+            .into_iter()
+            .map(|contents| {
+                let page = format!(
+                    "<< /Type /Page /Parent {PAGE_TREE} 0 R /MediaBox [0 0 {} {}] /Resources {RESOURCES} 0 R /Contents {contents} 0 R >>",
+                    num(width),
+                    num(height)
+                );
+                self.object(page.into_bytes())
+            })
+            .collect();
+        let fonts: Vec<String> = self
+            .fonts
+            .iter()
+            .map(|font| format!("<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>"))
+            .collect();
+        let font_ids: Vec<usize> = fonts.into_iter().map(|font| self.object(font.into_bytes())).collect();
+
+        let named = |prefix: &str, ids: &mut dyn Iterator<Item = String>| {
+            ids.enumerate().map(|(i, value)| format!("/{prefix}{} {value}", i + 1)).collect::<Vec<_>>().join(" ")
+        };
+        let resources = format!(
+            "<< /Font << {} >> /ExtGState << {} >> /XObject << {} >> /Shading << {} >> >>",
+            named("F", &mut font_ids.iter().map(|id| format!("{id} 0 R"))),
+            named("GS", &mut self.alphas.iter().map(|a| format!("<< /ca {} /CA {} >>", fraction(*a as f32 / 1000.0), fraction(*a as f32 / 1000.0)))),
+            named("Im", &mut self.images.iter().map(|(_, id)| format!("{id} 0 R"))),
+            named("Sh", &mut self.shadings.iter().map(|id| format!("{id} 0 R")))
+        );
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{id} 0 R")).collect();
+        let mut objects = vec![
+            format!("<< /Type /Catalog /Pages {PAGE_TREE} 0 R >>").into_bytes(),
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len()).into_bytes(),
+            resources.into_bytes(),
+        ];
+        objects.append(&mut self.objects);
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, body) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(body);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {CATALOG} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        pdf.extend_from_slice(table.as_bytes());
+        pdf
+    }
+}
+
+/// Resolved stops spanning the whole gradient line, from 0 to 1. PDF
+/// shadings can't repeat, so a repeating gradient's stops are laid out
+/// again for each repetition.
+fn repeated(stops: Vec<(f32, ColorF32)>, repeating: bool) -> Vec<(f32, ColorF32)> {
+    let (stops, period) = repeat_stops(stops, repeating);
+    let count = (1.0 / period).ceil() as usize;
+    let tiled: Vec<(f32, ColorF32)> = (0..count)
+        .flat_map(|i| stops.iter().map(move |(offset, color)| ((i as f32 + offset) * period, *color)))
+        .collect();
+    let Some((first, last)) = tiled.first().zip(tiled.last()) else {
+        return tiled;
+    };
+    let mut spanning = vec![(0.0, first.1)];
+    spanning.extend(tiled.iter().copied().filter(|(offset, _)| *offset > 0.0 && *offset < 1.0));
+    spanning.push((1.0, if last.0 > 1.0 { color_at(&tiled, 1.0) } else { last.1 }));
+    spanning
+}
+
+/// A function blending from one color to another.
+fn interpolation(from: ColorF32, to: ColorF32) -> String {
+    let channels = |c: ColorF32| format!("{} {} {}", fraction(c.r), fraction(c.g), fraction(c.b));
+    format!(
+        "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+        channels(from),
+        channels(to)
+    )
+}
+
+/// The standard PDF font nearest a CSS font.
+fn standard_font(family: &str, weight: u16, style: u8) -> &'static str {
+    let family = family.to_ascii_lowercase();
+    let bold = weight >= 600;
+    let italic = style != 0;
+    let fonts = if family.contains("mono") || family.contains("courier") {
+        ["Courier", "Courier-Bold", "Courier-Oblique", "Courier-BoldOblique"]
+    } else if (family.contains("serif") && !family.contains("sans")) || family.contains("times") || family.contains("georgia")
+    {
+        ["Times-Roman", "Times-Bold", "Times-Italic", "Times-BoldItalic"]
+    } else {
+        ["Helvetica", "Helvetica-Bold", "Helvetica-Oblique", "Helvetica-BoldOblique"]
+    };
+    fonts[bold as usize + 2 * italic as usize]
+}
+
+/// `text` as the body of a PDF string in WinAnsi encoding. Characters it
+/// lacks become `?`.
+fn win_ansi(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for ch in text.chars() {
+        let byte = match ch {
+            ' '..='~' => ch as u8,
+            '\u{a0}'..='\u{ff}' => ch as u32 as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            _ => b'?',
+        };
+        match byte {
+            b'(' | b')' | b'\\' => {
+                encoded.push('\\');
+                encoded.push(byte as char);
+            }
+            0x20..=0x7e => encoded.push(byte as char),
+            _ => {
+                let _ = write!(encoded, "\\{byte:03o}");
+            }
+        }
+    }
+    encoded
+}
+
+fn rect_path(rect: Rect) -> String {
+    format!("{} {} {} {} re", num(rect.x), num(rect.y), num(rect.width.max(0.0)), num(rect.height.max(0.0)))
+}
+
+/// A rectangle with its corners rounded by `radius`, as Bézier curves.
+fn rounded_path(rect: Rect, radius: BorderRadius) -> String {
+    let max = rect.width.min(rect.height).max(0.0) / 2.0;
+    let [tl, tr, br, bl] =
+        [radius.top_left, radius.top_right, radius.bottom_right, radius.bottom_left].map(|r| r.clamp(0.0, max));
+    if tl == 0.0 && tr == 0.0 && br == 0.0 && bl == 0.0 {
+        return rect_path(rect);
+    }
+
+    let (x, y, right, bottom) = (rect.x, rect.y, rect.x + rect.width, rect.y + rect.height);
+    let point = |x: f32, y: f32| format!("{} {}", num(x), num(y));
+    let mut path = format!("{} m ", point(x + tl, y));
+    let _ = write!(path, "{} l ", point(right - tr, y));
+    let _ = write!(path, "{} {} {} c ", point(right - tr * (1.0 - KAPPA), y), point(right, y + tr * (1.0 - KAPPA)), point(right, y + tr));
+    let _ = write!(path, "{} l ", point(right, bottom - br));
+    let _ = write!(
+        path,
+        "{} {} {} c ",
+        point(right, bottom - br * (1.0 - KAPPA)),
+        point(right - br * (1.0 - KAPPA), bottom),
+        point(right - br, bottom)
+    );
+    let _ = write!(path, "{} l ", point(x + bl, bottom));
+    let _ = write!(path, "{} {} {} c ", point(x + bl * (1.0 - KAPPA), bottom), point(x, bottom - bl * (1.0 - KAPPA)), point(x, bottom - bl));
+    let _ = write!(path, "{} l ", point(x, y + tl));
+    let _ = write!(path, "{} {} {} c h", point(x, y + tl * (1.0 - KAPPA)), point(x + tl * (1.0 - KAPPA), y), point(x + tl, y));
+    path
+}
+
+/// An ellipse, as four Bézier curves.
+fn ellipse_path(cx: f32, cy: f32, rx: f32, ry: f32) -> String {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let point = |x: f32, y: f32| format!("{} {}", num(x), num(y));
+    format!(
+        "{} m {} {} {} c {} {} {} c {} {} {} c {} {} {} c h",
+        point(cx + rx, cy),
+        point(cx + rx, cy + ky),
+        point(cx + kx, cy + ry),
+        point(cx, cy + ry),
+        point(cx - kx, cy + ry),
+        point(cx - rx, cy + ky),
+        point(cx - rx, cy),
+        point(cx - rx, cy - ky),
+        point(cx - kx, cy - ry),
+        point(cx, cy - ry),
+        point(cx + kx, cy - ry),
+        point(cx + rx, cy - ky),
+        point(cx + rx, cy)
+    )
+}
+
+fn polygon_path(points: &[(f32, f32)], close: bool) -> String {
+    let mut path = String::new();
+    for (i, (x, y)) in points.iter().enumerate() {
+        let _ = write!(path, "{} {} {} ", num(*x), num(*y), if i == 0 { "m" } else { "l" });
+    }
+    if close {
+        path.push('h');
+    }
+    path.trim_end().to_string()
+}
+
+/// A color's channels as PDF color operands.
+fn rgb(color: Color) -> String {
+    let channel = |value: u8| fraction(value as f32 / 255.0);
+    format!("{} {} {}", channel(color.r), channel(color.g), channel(color.b))
+}
+
+fn to_color(color: ColorF32) -> Color {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Color::new(channel(color.r), channel(color.g), channel(color.b), color.a)
+}
+
+/// A number between 0 and 1, to a thousandth.
+fn fraction(value: f32) -> String {
+    let text = format!("{:.3}", value.clamp(0.0, 1.0));
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_list_to_pdf() {
+        let red = Color::from_rgb(255, 0, 0);
+        let commands = vec![
+            DisplayCommand::SolidColor(red, Rect::new(0.0, 0.0, 100.0, 50.0)),
+            DisplayCommand::Text {
+                text: "Total (net): 5 €".into(),
+                x: 10.0,
+                y: 20.0,
+                color: Color::new(0, 0, 0, 0.5),
+                font_size: 10.0,
+                font_family: "Georgia, serif".into(),
+                font_weight: 700,
+                font_style: 0,
+            },
+            DisplayCommand::LinearGradient {
+                rect: Rect::new(0.0, 900.0, 100.0, 40.0),
+                direction: GradientDirection::ToRight,
+                stops: vec![ColorStop::new(red, Some(0.0)), ColorStop::new(Color::BLACK, Some(1.0))],
+                repeating: false,
+                border_radius: BorderRadius::default(),
+            },
+            // Left open; closed at the end of each page
+            DisplayCommand::PushOpacity(0.5),
+        ];
+        let options = PrintOptions { page_size: PageSize::Letter, ..Default::default() };
+        let pdf = to_pdf(&commands, &[0.0, 800.0], &options, &HashMap::new());
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Type /Pages /Kids [") && text.contains("/Count 2 >>"));
+        assert!(text.contains("/MediaBox [0 0 612 792]"));
+        // The first page draws the fill and the text, half transparent
+        assert!(text.contains("1 0 0 rg 0 0 100 50 re f"));
+        assert!(text.contains("q /GS1 gs\nBT 0 0 0 rg /F1 10 Tf 1 0 0 -1 10 28 Tm (Total \\(net\\): 5 \\200) Tj ET\nQ"));
+        assert!(text.contains("/BaseFont /Times-Bold /Encoding /WinAnsiEncoding"));
+        assert!(text.contains("<< /ca 0.5 /CA 0.5 >>"));
+        // The second page is moved up to its part of the flow
+        assert!(text.contains("1 0 0 1 48 -752 cm"));
+        assert!(text.contains("/ShadingType 2 /ColorSpace /DeviceRGB /Coords [0 920 100 920]"));
+        assert_eq!(text.matches("\nq\n").count(), text.matches("\nQ\n").count() - text.matches("gs\n").count());
+    }
+
+    #[test]
+    fn test_print_page_sizes() {
+        let options = PrintOptions {
+            page_size: PageSize::A4,
+            landscape: true,
+            margins: PageMargins { top: 10.0, right: 20.0, bottom: 30.0, left: 40.0 },
+        };
+        assert_eq!(options.paper_size(), (1122.52, 793.7));
+        assert_eq!(options.content_size(), (1122.52 - 60.0, 793.7 - 40.0));
+        assert_eq!(win_ansi("a\\b “c”"), "a\\\\b \\223c\\224");
+    }
+}
@@ -0,0 +1,223 @@
+//! Engine task scheduler.
+//!
+//! Work that doesn't have to finish before the current call returns
+//! (relayout after a resize or image load, host-posted work) is queued here
+//! by priority and run from `Engine::run_scheduled_tasks`, one task at a
+//! time within a time budget. Hosts call it from their event loop between
+//! input events, so a burst of background work yields to input instead of
+//! running to completion.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Task priority, highest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    /// Work the user is waiting on, such as responding to input.
+    UserBlocking,
+    /// Work with visible results, such as relayout and repaint.
+    UserVisible,
+    /// Work nobody is waiting on, such as prefetching and cache upkeep.
+    Background,
+}
+
+impl TaskPriority {
+    /// All priorities, highest first.
+    pub const ALL: [TaskPriority; 3] = [
+        TaskPriority::UserBlocking,
+        TaskPriority::UserVisible,
+        TaskPriority::Background,
+    ];
+
+    /// How long a task may wait before it runs ahead of higher-priority
+    /// tasks (never ahead of user-blocking ones).
+    fn max_delay(self) -> Option<Duration> {
+        match self {
+            TaskPriority::UserBlocking => None,
+            TaskPriority::UserVisible => Some(Duration::from_millis(100)),
+            TaskPriority::Background => Some(Duration::from_secs(1)),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A queued task, run with mutable access to its context (the engine).
+pub struct Task<C> {
+    /// Name for tracing.
+    pub name: &'static str,
+    /// The task's priority.
+    pub priority: TaskPriority,
+    queued_at: Instant,
+    run: Box<dyn FnOnce(&mut C)>,
+}
+
+impl<C> Task<C> {
+    /// Run the task.
+    pub fn run(self, context: &mut C) {
+        (self.run)(context)
+    }
+
+    /// How long the task has been queued.
+    pub fn waited(&self) -> Duration {
+        self.queued_at.elapsed()
+    }
+}
+
+impl<C> fmt::Debug for Task<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("name", &self.name)
+            .field("priority", &self.priority)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Priority queues of tasks.
+pub struct TaskScheduler<C> {
+    queues: [VecDeque<Task<C>>; 3],
+}
+
+impl<C> TaskScheduler<C> {
+    /// Create an empty scheduler.
+    pub fn new() -> Self {
+        Self {
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+        }
+    }
+
+    /// Queue a task.
+    pub fn post(&mut self, priority: TaskPriority, name: &'static str, task: impl FnOnce(&mut C) + 'static) {
+        self.queues[priority.index()].push_back(Task {
+            name,
+            priority,
+            queued_at: Instant::now(),
+            run: Box::new(task),
+        });
+    }
+
+    /// Take the next task to run.
+    ///
+    /// User-blocking tasks always come first. Otherwise a task that has
+    /// waited past its priority's limit goes next, so background work
+    /// can't be starved indefinitely; then tasks run in priority order.
+    pub fn next_task(&mut self) -> Option<Task<C>> {
+        if let Some(task) = self.queues[TaskPriority::UserBlocking.index()].pop_front() {
+            return Some(task);
+        }
+
+        let overdue = [TaskPriority::Background, TaskPriority::UserVisible]
+            .into_iter()
+            .find(|priority| {
+                let limit = priority.max_delay();
+                self.queues[priority.index()]
+                    .front()
+                    .is_some_and(|task| limit.is_some_and(|limit| task.waited() >= limit))
+            });
+        if let Some(priority) = overdue {
+            return self.queues[priority.index()].pop_front();
+        }
+
+        self.queues.iter_mut().find_map(VecDeque::pop_front)
+    }
+
+    /// Number of queued tasks at a priority.
+    pub fn pending(&self, priority: TaskPriority) -> usize {
+        self.queues[priority.index()].len()
+    }
+
+    /// Whether any task is queued.
+    pub fn has_pending(&self) -> bool {
+        self.queues.iter().any(|queue| !queue.is_empty())
+    }
+
+    /// Run tasks until the queues are empty or `budget` has elapsed,
+    /// returning how many ran. At least one task runs if any is queued.
+    ///
+    /// `take` gets the scheduler out of the context, so that tasks can
+    /// queue more work while they run.
+    pub fn run(context: &mut C, budget: Duration, take: impl Fn(&mut C) -> &mut Self) -> usize {
+        let deadline = Instant::now() + budget;
+        let mut ran = 0;
+        while let Some(task) = take(context).next_task() {
+            tracing::trace!(name = task.name, priority = ?task.priority, waited = ?task.waited(), "Running task");
+            task.run(context);
+            ran += 1;
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+        ran
+    }
+}
+
+impl<C> Default for TaskScheduler<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Context {
+        scheduler: TaskScheduler<Context>,
+        log: Vec<&'static str>,
+    }
+
+    fn run(context: &mut Context, budget: Duration) -> usize {
+        TaskScheduler::run(context, budget, |c| &mut c.scheduler)
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut context = Context::default();
+        context.scheduler.post(TaskPriority::Background, "background", |c| c.log.push("background"));
+        context.scheduler.post(TaskPriority::UserVisible, "visible", |c| c.log.push("visible"));
+        context.scheduler.post(TaskPriority::UserBlocking, "input", |c| {
+            c.log.push("input");
+            // Work queued by a task runs in the same turn, by priority
+            c.scheduler.post(TaskPriority::UserBlocking, "follow-up", |c| c.log.push("follow-up"));
+        });
+
+        assert_eq!(context.scheduler.pending(TaskPriority::Background), 1);
+        assert_eq!(run(&mut context, Duration::from_secs(10)), 4);
+        assert_eq!(context.log, vec!["input", "follow-up", "visible", "background"]);
+        assert!(!context.scheduler.has_pending());
+    }
+
+    #[test]
+    fn test_budget_yields() {
+        let mut context = Context::default();
+        for _ in 0..3 {
+            context.scheduler.post(TaskPriority::Background, "slow", |c| {
+                std::thread::sleep(Duration::from_millis(5));
+                c.log.push("slow");
+            });
+        }
+
+        // A zero budget still makes progress
+        assert_eq!(run(&mut context, Duration::ZERO), 1);
+        assert_eq!(context.scheduler.pending(TaskPriority::Background), 2);
+    }
+
+    #[test]
+    fn test_overdue_tasks_run_ahead() {
+        let mut scheduler: TaskScheduler<Context> = TaskScheduler::new();
+        scheduler.post(TaskPriority::Background, "old", |_| {});
+        scheduler.queues[TaskPriority::Background.index()][0].queued_at -= Duration::from_secs(2);
+        scheduler.post(TaskPriority::UserVisible, "new", |_| {});
+        assert_eq!(scheduler.next_task().unwrap().name, "old");
+
+        // ...but never ahead of user-blocking work
+        scheduler.post(TaskPriority::Background, "old", |_| {});
+        scheduler.queues[TaskPriority::Background.index()][0].queued_at -= Duration::from_secs(2);
+        scheduler.post(TaskPriority::UserBlocking, "input", |_| {});
+        assert_eq!(scheduler.next_task().unwrap().name, "input");
+    }
+}
@@ -0,0 +1,112 @@
+//! External scripts.
+//!
+//! `<script src>` elements are fetched and run through the view's
+//! bindings once the document is parsed. Classic scripts run in document
+//! order before `DOMContentLoaded`, those marked `defer` after the rest;
+//! `async` scripts run as they arrive, while the page's other subresources
+//! load. Scripts of other types, such as modules and data blocks, don't
+//! run.
+
+use rustkit_dom::Document;
+use url::Url;
+
+/// When an external script runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScriptTiming {
+    /// In document order, before the page is interactive.
+    Blocking,
+    /// In document order after the blocking scripts.
+    Deferred,
+    /// As soon as it arrives.
+    Async,
+}
+
+/// A `<script src>` of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExternalScript {
+    pub(crate) url: Url,
+    pub(crate) timing: ScriptTiming,
+}
+
+/// The document's external classic scripts, in the order they run:
+/// blocking, then deferred, then async.
+pub(crate) fn discover_scripts(document: &Document, base_url: Option<&Url>) -> Vec<ExternalScript> {
+    // In tree order, which get_elements_by_tag_name doesn't keep
+    let mut elements = Vec::new();
+    document.traverse(|node| {
+        if node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("script")) {
+            elements.push(node.clone());
+        }
+    });
+    let mut scripts: Vec<ExternalScript> = elements
+        .into_iter()
+        .filter(|script| script.get_attribute("type").is_none_or(is_javascript_type))
+        .filter_map(|script| {
+            let src = script.get_attribute("src")?;
+            let url = match base_url {
+                Some(base) => base.join(src.trim()).ok()?,
+                None => Url::parse(src.trim()).ok()?,
+            };
+            let timing = if script.get_attribute("async").is_some() {
+                ScriptTiming::Async
+            } else if script.get_attribute("defer").is_some() {
+                ScriptTiming::Deferred
+            } else {
+                ScriptTiming::Blocking
+            };
+            Some(ExternalScript { url, timing })
+        })
+        .collect();
+    // Stable, so document order holds within each timing
+    scripts.sort_by_key(|script| script.timing as u8);
+    scripts
+}
+
+/// Whether a `<script type>` names a classic script.
+fn is_javascript_type(script_type: &str) -> bool {
+    let essence = script_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    matches!(
+        essence.as_str(),
+        "" | "text/javascript"
+            | "application/javascript"
+            | "application/ecmascript"
+            | "application/x-javascript"
+            | "text/ecmascript"
+            | "text/jscript"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_scripts_in_run_order() {
+        let document = Document::parse_html(
+            r#"<head>
+                 <script src="late.js" defer></script>
+                 <script src="whenever.js" async defer></script>
+                 <script src="/first.js"></script>
+                 <script>inline()</script>
+                 <script type="module" src="module.js"></script>
+                 <script type="application/json" src="data.json"></script>
+                 <script type="text/javascript; charset=utf-8" src="second.js"></script>
+               </head>"#,
+        )
+        .unwrap();
+        let base = Url::parse("https://example.com/app/").unwrap();
+        let scripts: Vec<_> = discover_scripts(&document, Some(&base))
+            .into_iter()
+            .map(|script| (script.url.to_string(), script.timing))
+            .collect();
+        assert_eq!(
+            scripts,
+            [
+                ("https://example.com/first.js".to_string(), ScriptTiming::Blocking),
+                ("https://example.com/app/second.js".to_string(), ScriptTiming::Blocking),
+                ("https://example.com/app/late.js".to_string(), ScriptTiming::Deferred),
+                ("https://example.com/app/whenever.js".to_string(), ScriptTiming::Async),
+            ]
+        );
+    }
+}
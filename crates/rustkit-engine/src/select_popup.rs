@@ -0,0 +1,172 @@
+//! `<select>` dropdown popups.
+//!
+//! Clicking a `<select>`, or pressing Space or an arrow key while it has
+//! focus, opens a popup listing its options. The engine doesn't draw the
+//! popup itself: the host gets [`EngineEvent::SelectPopupOpened`] and shows
+//! a native menu at the anchor, then reports the user's pick with
+//! [`Engine::choose_select_option`]. Hosts that draw their own overlay can
+//! instead forward key presses to [`Engine::key_down`], which moves the
+//! popup's highlight and commits or dismisses it.
+//!
+//! [`EngineEvent::SelectPopupOpened`]: crate::EngineEvent::SelectPopupOpened
+//! [`Engine::choose_select_option`]: crate::Engine::choose_select_option
+//! [`Engine::key_down`]: crate::Engine::key_down
+
+use std::rc::Rc;
+
+use rustkit_core::KeyCode;
+use rustkit_dom::{Node, NodeId};
+use rustkit_layout::Rect;
+
+/// An option listed in a select popup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectPopupOption {
+    /// Text shown for the option.
+    pub label: String,
+    /// Whether the option can't be chosen.
+    pub disabled: bool,
+}
+
+/// An open select popup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectPopup {
+    /// The `<select>` element.
+    pub node_id: NodeId,
+    /// Border box of the select, relative to the viewport.
+    pub anchor: Rect,
+    /// The select's options, in order.
+    pub options: Vec<SelectPopupOption>,
+    /// Option selected when the popup opened.
+    pub selected_index: Option<usize>,
+    /// Option the keyboard highlight is on.
+    pub highlighted: Option<usize>,
+}
+
+/// What a key press did to an open popup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PopupKey {
+    /// The highlight moved, or stayed at the end of the list.
+    Moved,
+    /// The highlighted option was chosen.
+    Choose(usize),
+    /// The popup was dismissed without choosing.
+    Dismiss,
+    /// The key does nothing in a popup.
+    Ignored,
+}
+
+/// The `<select>` a node is or is inside of, if it opens a popup.
+///
+/// Disabled selects don't open, and neither do list boxes (`multiple`).
+pub(crate) fn popup_select(node: Rc<Node>) -> Option<Rc<Node>> {
+    let mut current = Some(node);
+    while let Some(node) = current {
+        if node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("select")) {
+            let opens = node.get_attribute("disabled").is_none() && node.get_attribute("multiple").is_none();
+            return opens.then_some(node);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+impl SelectPopup {
+    /// A popup for `select`, anchored at `anchor`, with the selected option
+    /// highlighted.
+    pub(crate) fn new(select: &Node, anchor: Rect) -> Self {
+        let options = select
+            .select_options()
+            .iter()
+            .map(|option| SelectPopupOption {
+                label: option.option_label(),
+                disabled: option.is_option_disabled(),
+            })
+            .collect();
+        let selected_index = select.selected_index();
+        Self {
+            node_id: select.id,
+            anchor,
+            options,
+            selected_index,
+            highlighted: selected_index,
+        }
+    }
+
+    /// Handle a key press while the popup is open.
+    pub(crate) fn handle_key(&mut self, key: KeyCode) -> PopupKey {
+        match key {
+            KeyCode::ArrowDown => self.step(true),
+            KeyCode::ArrowUp => self.step(false),
+            KeyCode::Home => {
+                let first = self.enabled().next();
+                self.highlighted = first.or(self.highlighted);
+            }
+            KeyCode::End => {
+                let last = self.enabled().next_back();
+                self.highlighted = last.or(self.highlighted);
+            }
+            KeyCode::Enter | KeyCode::Space => {
+                return self.highlighted.map_or(PopupKey::Dismiss, PopupKey::Choose)
+            }
+            KeyCode::Escape | KeyCode::Tab => return PopupKey::Dismiss,
+            _ => return PopupKey::Ignored,
+        }
+        PopupKey::Moved
+    }
+
+    /// Move the highlight to the next or previous enabled option.
+    fn step(&mut self, forward: bool) {
+        let next = match (self.highlighted, forward) {
+            (None, true) => self.enabled().next(),
+            (None, false) => self.enabled().next_back(),
+            (Some(current), true) => self.enabled().find(|&i| i > current),
+            (Some(current), false) => self.enabled().rev().find(|&i| i < current),
+        };
+        if next.is_some() {
+            self.highlighted = next;
+        }
+    }
+
+    /// Indices of the options that can be chosen.
+    fn enabled(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.options
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| !option.disabled)
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_keyboard_skips_disabled_options() {
+        let doc = Document::parse_html(
+            r#"<select id="s"><option>One</option><option disabled>Two</option>
+               <option selected>Three</option><option>Four</option></select>"#,
+        )
+        .unwrap();
+        let select = doc.get_element_by_id("s").unwrap();
+        let option = select.select_options()[0].clone();
+        assert!(Rc::ptr_eq(&popup_select(option).unwrap(), &select));
+
+        let mut popup = SelectPopup::new(&select, Rect::new(0.0, 0.0, 100.0, 20.0));
+        assert_eq!(popup.selected_index, Some(2));
+        assert_eq!(popup.highlighted, Some(2));
+
+        assert_eq!(popup.handle_key(KeyCode::ArrowUp), PopupKey::Moved);
+        assert_eq!(popup.highlighted, Some(0));
+        // Already on the first enabled option
+        popup.handle_key(KeyCode::ArrowUp);
+        assert_eq!(popup.highlighted, Some(0));
+        popup.handle_key(KeyCode::ArrowDown);
+        assert_eq!(popup.highlighted, Some(2));
+        popup.handle_key(KeyCode::End);
+        assert_eq!(popup.handle_key(KeyCode::Enter), PopupKey::Choose(3));
+        assert_eq!(popup.handle_key(KeyCode::Escape), PopupKey::Dismiss);
+        assert_eq!(popup.handle_key(KeyCode::KeyA), PopupKey::Ignored);
+    }
+}
@@ -0,0 +1,204 @@
+//! Per-view session history.
+//!
+//! Each view keeps a back/forward list of the pages it showed. Going back
+//! or forward to an entry shows the document it had when the view left it,
+//! with its DOM and form state, instead of fetching the page again; only
+//! entries near the current one keep their documents. Reloading fetches
+//! the current entry's URL again.
+//!
+//! Entries also keep where the view was scrolled when it left them, which
+//! going back or forward and reloading restore once the page has laid out,
+//! unless the page set `history.scrollRestoration` to `manual`.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use rustkit_core::{HistoryEntry, NavigationRequest, ScrollPosition, ScrollRestoration, SessionHistory};
+use rustkit_dom::Document;
+use url::Url;
+
+/// Entries on each side of the current one whose documents are kept.
+const KEPT_DOCUMENTS: usize = 4;
+
+/// How a navigation changes the session history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryUpdate {
+    /// A new entry after the current one, dropping any forward entries.
+    Push,
+    /// The current entry, loaded again.
+    Reload,
+    /// The entry this many steps from the current one.
+    Traverse(i32),
+}
+
+impl HistoryUpdate {
+    /// The navigation request for `url`. Only pushes add to the navigation
+    /// state machine's own list.
+    pub(crate) fn request(self, url: Url) -> NavigationRequest {
+        match self {
+            HistoryUpdate::Push => NavigationRequest::new(url),
+            HistoryUpdate::Reload | HistoryUpdate::Traverse(_) => NavigationRequest::new(url).with_replace(),
+        }
+    }
+}
+
+/// A view's back/forward list and the documents kept for it.
+#[derive(Default)]
+pub(crate) struct ViewHistory {
+    entries: SessionHistory,
+    documents: HashMap<u64, Rc<Document>>,
+}
+
+impl ViewHistory {
+    pub(crate) fn can_go_back(&self) -> bool {
+        self.entries.can_go_back()
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        self.entries.can_go_forward()
+    }
+
+    /// The URL of the entry `delta` steps from the current one, taking its
+    /// kept document if it has one.
+    pub(crate) fn take_entry(&mut self, delta: i32) -> Option<(Url, Option<Rc<Document>>)> {
+        let index = self.entries.current_index().checked_add_signed(delta as isize)?;
+        let entry = self.entries.entries().get(index)?;
+        Some((entry.url.clone(), self.documents.remove(&entry.id)))
+    }
+
+    /// The current entry's URL.
+    pub(crate) fn current_url(&self) -> Option<&Url> {
+        self.entries.current_url()
+    }
+
+    /// The current entry's scroll restoration mode.
+    pub(crate) fn scroll_restoration(&self) -> ScrollRestoration {
+        self.entries.current_entry().map(|entry| entry.scroll_restoration).unwrap_or_default()
+    }
+
+    /// Set the current entry's scroll restoration mode.
+    pub(crate) fn set_scroll_restoration(&mut self, restoration: ScrollRestoration) {
+        if let Some(entry) = self.entries.current_entry_mut() {
+            entry.scroll_restoration = restoration;
+        }
+    }
+
+    /// Record the view's scroll offset on the current entry as it leaves
+    /// it, with the page's scroll restoration mode if it has scripts.
+    pub(crate) fn save_scroll_state(&mut self, (x, y): (f32, f32), restoration: Option<ScrollRestoration>) {
+        if let Some(entry) = self.entries.current_entry_mut() {
+            entry.scroll_position = ScrollPosition { x, y };
+            if let Some(restoration) = restoration {
+                entry.scroll_restoration = restoration;
+            }
+        }
+    }
+
+    /// The scroll offset to return the view to on the current entry, unless
+    /// its page restores scrolling itself.
+    pub(crate) fn restored_scroll_offset(&self) -> Option<(f32, f32)> {
+        let entry = self.entries.current_entry()?;
+        let ScrollPosition { x, y } = entry.scroll_position;
+        (entry.scroll_restoration == ScrollRestoration::Auto).then_some((x, y))
+    }
+
+    /// Record that the view now shows `url`, titled `title`, having left
+    /// `previous`.
+    pub(crate) fn commit(&mut self, update: HistoryUpdate, url: Url, title: Option<String>, previous: Option<Rc<Document>>) {
+        if update != HistoryUpdate::Reload {
+            if let (Some(entry), Some(previous)) = (self.entries.current_entry(), previous) {
+                self.documents.insert(entry.id, previous);
+            }
+        }
+
+        let title = title.unwrap_or_default();
+        match update {
+            HistoryUpdate::Push => self.entries.push(HistoryEntry::new(url, title)),
+            HistoryUpdate::Reload | HistoryUpdate::Traverse(_) => {
+                if let HistoryUpdate::Traverse(delta) = update {
+                    self.entries.go(delta);
+                }
+                if let Some(entry) = self.entries.current_entry_mut() {
+                    entry.url = url;
+                    entry.title = title;
+                }
+            }
+        }
+
+        // Forget documents of dropped and distant entries
+        let current = self.entries.current_index();
+        let kept: HashSet<u64> = self
+            .entries
+            .entries()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| index.abs_diff(current) <= KEPT_DOCUMENTS)
+            .map(|(_, entry)| entry.id)
+            .collect();
+        self.documents.retain(|id, _| kept.contains(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_forward_keeps_documents() {
+        let page = |n: u32| {
+            let url = Url::parse(&format!("https://example.com/{n}")).unwrap();
+            let document = Rc::new(Document::parse_html(&format!("<title>Page {n}</title>")).unwrap());
+            (url, document)
+        };
+        let mut history = ViewHistory::default();
+        let (one, two, three) = (page(1), page(2), page(3));
+
+        history.commit(HistoryUpdate::Push, one.0.clone(), None, None);
+        history.commit(HistoryUpdate::Push, two.0.clone(), None, Some(one.1.clone()));
+        history.commit(HistoryUpdate::Push, three.0.clone(), None, Some(two.1.clone()));
+        assert!(history.can_go_back() && !history.can_go_forward());
+
+        // Going back finds the document the view left
+        let (url, document) = history.take_entry(-1).unwrap();
+        assert_eq!(url, two.0);
+        assert!(Rc::ptr_eq(&document.unwrap(), &two.1));
+        history.commit(HistoryUpdate::Traverse(-1), url, Some("Page 2".into()), Some(three.1.clone()));
+        assert_eq!(history.current_url(), Some(&two.0));
+        assert!(history.can_go_forward());
+
+        // Reloading keeps the entry; a taken document isn't there twice
+        history.commit(HistoryUpdate::Reload, two.0.clone(), None, Some(two.1.clone()));
+        assert!(history.take_entry(0).unwrap().1.is_none());
+        assert!(history.take_entry(2).is_none());
+        assert!(history.take_entry(-2).is_none());
+
+        // A new page drops the forward entries and their documents
+        history.commit(HistoryUpdate::Push, page(4).0, None, Some(two.1.clone()));
+        assert!(!history.can_go_forward());
+        assert_eq!(history.documents.len(), 2);
+    }
+
+    #[test]
+    fn test_entries_keep_scroll_offsets() {
+        let url = |n: u32| Url::parse(&format!("https://example.com/{n}")).unwrap();
+        let mut history = ViewHistory::default();
+
+        history.commit(HistoryUpdate::Push, url(1), None, None);
+        history.save_scroll_state((0.0, 300.0), None);
+        history.commit(HistoryUpdate::Push, url(2), None, None);
+        // A new entry starts at the top
+        assert_eq!(history.restored_scroll_offset(), Some((0.0, 0.0)));
+        history.save_scroll_state((10.0, 50.0), Some(ScrollRestoration::Manual));
+
+        history.commit(HistoryUpdate::Traverse(-1), url(1), None, None);
+        assert_eq!(history.restored_scroll_offset(), Some((0.0, 300.0)));
+        history.save_scroll_state((0.0, 120.0), Some(ScrollRestoration::Auto));
+        history.commit(HistoryUpdate::Reload, url(1), None, None);
+        assert_eq!(history.restored_scroll_offset(), Some((0.0, 120.0)));
+
+        // A page that restores scrolling itself is left alone
+        history.commit(HistoryUpdate::Traverse(1), url(2), None, None);
+        assert_eq!(history.scroll_restoration(), ScrollRestoration::Manual);
+        assert_eq!(history.restored_scroll_offset(), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Editable text fields.
+//!
+//! Clicking a text `<input>` focuses it with the caret at the click, and
+//! focusing it otherwise puts the caret at the end. While it has focus,
+//! text the host reports with [`Engine::insert_text`] goes in at the
+//! caret, Backspace and Delete remove the character before or after it,
+//! and the left and right arrows, Home and End move it. Each edit fires
+//! `input` at the field, and `change` fires when it loses focus with a
+//! different value than it had when it got it.
+//!
+//! The caret blinks, lit for [`CARET_BLINK_INTERVAL`] and then dark for as
+//! long, starting lit again whenever it moves. Hosts repaint the views
+//! [`Engine::pump_caret_blink`] marks as needing it.
+//!
+//! [`Engine::insert_text`]: crate::Engine::insert_text
+//! [`Engine::pump_caret_blink`]: crate::Engine::pump_caret_blink
+
+use std::time::{Duration, Instant};
+
+use rustkit_core::KeyCode;
+use rustkit_dom::{Node, NodeId};
+use rustkit_layout::DisplayCommand;
+
+/// How long the caret stays lit, and then dark, as it blinks.
+pub(crate) const CARET_BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+/// Left padding of the text in a field, as the renderer draws it.
+const TEXT_PADDING: f32 = 6.0;
+
+/// Whether `node` is a text `<input>` the user can type in.
+pub(crate) fn is_editable(node: &Node) -> bool {
+    node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("input"))
+        && node.input_type().is_text_input()
+        && node.get_attribute("disabled").is_none()
+        && node.get_attribute("readonly").is_none()
+}
+
+/// The caret position nearest to `x` in a field whose content box starts
+/// at `content_x`, measured the way the renderer lays out the value.
+pub(crate) fn caret_at(value: &str, font_size: f32, content_x: f32, x: f32) -> usize {
+    let advance = font_size * 0.5;
+    let index = ((x - content_x - TEXT_PADDING) / advance).round().max(0.0) as usize;
+    index.min(value.chars().count())
+}
+
+/// Show the caret of the focused field in `commands` at `position`, or
+/// hide it for `None`.
+pub(crate) fn show_caret(commands: &mut [DisplayCommand], position: Option<usize>) {
+    for command in commands {
+        if let DisplayCommand::TextInput {
+            focused: true,
+            caret_position,
+            ..
+        } = command
+        {
+            *caret_position = position;
+        }
+    }
+}
+
+/// Byte offset of the character at `index` in `value`.
+fn byte_offset(value: &str, index: usize) -> usize {
+    value.char_indices().nth(index).map_or(value.len(), |(offset, _)| offset)
+}
+
+/// What a key did to a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum KeyEdit {
+    /// The value changed to this.
+    Value(String),
+    /// The caret moved.
+    Moved,
+    /// The key doesn't edit text.
+    Ignored,
+}
+
+/// Editing state of a view's focused text field.
+#[derive(Debug, Clone)]
+pub(crate) struct InputState {
+    /// The `<input>`.
+    pub node_id: NodeId,
+    /// Value when the field got focus, to tell whether it changed.
+    pub initial_value: String,
+    /// Caret position, in characters from the start of the value.
+    caret: usize,
+    /// When the caret last moved; it blinks from then.
+    blink_start: Instant,
+    /// Whether the view's display list shows the caret.
+    pub caret_shown: bool,
+}
+
+impl InputState {
+    /// Editing state for `field`, just focused, with the caret at the end.
+    pub fn new(field: &Node, now: Instant) -> Self {
+        let value = field.input_value();
+        Self {
+            node_id: field.id,
+            caret: value.chars().count(),
+            initial_value: value,
+            blink_start: now,
+            caret_shown: true,
+        }
+    }
+
+    /// Caret position in `value`, which script may have shortened.
+    pub fn caret(&self, value: &str) -> usize {
+        self.caret.min(value.chars().count())
+    }
+
+    /// Move the caret to `position`, lighting it.
+    pub fn move_caret(&mut self, position: usize, now: Instant) {
+        self.caret = position;
+        self.blink_start = now;
+    }
+
+    /// `value` with `text` inserted at the caret, which moves past it.
+    /// Line breaks are dropped, as single-line fields can't hold them.
+    pub fn insert(&mut self, value: &str, text: &str, now: Instant) -> String {
+        let text: String = text.chars().filter(|c| !matches!(c, '\r' | '\n')).collect();
+        let caret = self.caret(value);
+        let offset = byte_offset(value, caret);
+        self.move_caret(caret + text.chars().count(), now);
+        format!("{}{}{}", &value[..offset], text, &value[offset..])
+    }
+
+    /// Run the editing action of `key` on `value`.
+    pub fn key(&mut self, value: &str, key: KeyCode, now: Instant) -> KeyEdit {
+        let caret = self.caret(value);
+        let len = value.chars().count();
+        let removed = match key {
+            KeyCode::Backspace if caret > 0 => caret - 1..caret,
+            KeyCode::Delete if caret < len => caret..caret + 1,
+            _ => {
+                let position = match key {
+                    // Nothing to delete
+                    KeyCode::Backspace | KeyCode::Delete => caret,
+                    KeyCode::ArrowLeft => caret.saturating_sub(1),
+                    KeyCode::ArrowRight => (caret + 1).min(len),
+                    KeyCode::Home | KeyCode::ArrowUp => 0,
+                    KeyCode::End | KeyCode::ArrowDown => len,
+                    _ => return KeyEdit::Ignored,
+                };
+                self.move_caret(position, now);
+                return KeyEdit::Moved;
+            }
+        };
+        self.move_caret(removed.start, now);
+        let (start, end) = (byte_offset(value, removed.start), byte_offset(value, removed.end));
+        KeyEdit::Value(format!("{}{}", &value[..start], &value[end..]))
+    }
+
+    /// Whether the caret is lit at `now`.
+    pub fn caret_visible(&self, now: Instant) -> bool {
+        let phases = now.saturating_duration_since(self.blink_start).as_millis() / CARET_BLINK_INTERVAL.as_millis();
+        phases.is_multiple_of(2)
+    }
+
+    /// When the caret next turns on or off after `now`.
+    pub fn next_blink(&self, now: Instant) -> Instant {
+        let interval = CARET_BLINK_INTERVAL.as_millis();
+        let elapsed = now.saturating_duration_since(self.blink_start).as_millis();
+        let phases = (elapsed / interval + 1) as u32;
+        self.blink_start + CARET_BLINK_INTERVAL * phases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_editing_keys() {
+        let doc = Document::parse_html(
+            r#"<input id="f" value="héllo"><input id="ro" readonly><input id="c" type="checkbox">"#,
+        )
+        .unwrap();
+        let field = doc.get_element_by_id("f").unwrap();
+        assert!(is_editable(&field));
+        assert!(!is_editable(&doc.get_element_by_id("ro").unwrap()));
+        assert!(!is_editable(&doc.get_element_by_id("c").unwrap()));
+
+        let now = Instant::now();
+        let mut state = InputState::new(&field, now);
+        let value = field.input_value();
+        assert_eq!(state.caret(&value), 5);
+
+        assert_eq!(state.key(&value, KeyCode::Home, now), KeyEdit::Moved);
+        assert_eq!(state.key(&value, KeyCode::ArrowRight, now), KeyEdit::Moved);
+        assert_eq!(state.key(&value, KeyCode::Delete, now), KeyEdit::Value("hllo".to_string()));
+        let value = state.insert("hllo", "e\n", now);
+        assert_eq!(value, "hello");
+        assert_eq!(state.caret(&value), 2);
+        assert_eq!(state.key(&value, KeyCode::Backspace, now), KeyEdit::Value("hllo".to_string()));
+        assert_eq!(state.key("hllo", KeyCode::KeyA, now), KeyEdit::Ignored);
+        assert_eq!(state.key("hllo", KeyCode::End, now), KeyEdit::Moved);
+        assert_eq!(state.key("hllo", KeyCode::Delete, now), KeyEdit::Moved);
+
+        // Script shortening the value pulls the caret in
+        assert_eq!(state.caret("hi"), 2);
+        assert_eq!(caret_at("hello", 16.0, 10.0, 10.0 + TEXT_PADDING + 17.0), 2);
+        assert_eq!(caret_at("hello", 16.0, 10.0, 500.0), 5);
+    }
+
+    #[test]
+    fn test_caret_blinks_from_last_move() {
+        let doc = Document::parse_html(r#"<input id="f">"#).unwrap();
+        let start = Instant::now();
+        let mut state = InputState::new(&doc.get_element_by_id("f").unwrap(), start);
+
+        assert!(state.caret_visible(start));
+        assert_eq!(state.next_blink(start), start + CARET_BLINK_INTERVAL);
+        let dark = start + CARET_BLINK_INTERVAL + Duration::from_millis(10);
+        assert!(!state.caret_visible(dark));
+        assert_eq!(state.next_blink(dark), start + CARET_BLINK_INTERVAL * 2);
+
+        // Moving the caret lights it again
+        state.move_caret(0, dark);
+        assert!(state.caret_visible(dark));
+    }
+}
@@ -0,0 +1,60 @@
+//! Title tooltips.
+//!
+//! Hovering an element shows the `title` of the nearest element with one,
+//! or an image's `alt` text when nothing sets a title. An empty `title`
+//! hides its ancestors' tooltips. The engine doesn't draw tooltips: it asks
+//! the host for a native one with [`EngineEvent::TooltipRequested`] when
+//! the pointer moves onto an element with a different tooltip, and
+//! withdraws it with [`EngineEvent::TooltipCancelled`] when the pointer
+//! leaves it or presses. The host applies its usual tooltip delay.
+//!
+//! [`EngineEvent::TooltipRequested`]: crate::EngineEvent::TooltipRequested
+//! [`EngineEvent::TooltipCancelled`]: crate::EngineEvent::TooltipCancelled
+
+use std::rc::Rc;
+
+use rustkit_dom::Node;
+
+/// The element whose tooltip shows when hovering `node`, and its text.
+pub(crate) fn tooltip_for(node: Rc<Node>) -> Option<(Rc<Node>, String)> {
+    let mut current = Some(node.clone());
+    while let Some(element) = current {
+        if let Some(title) = element.get_attribute("title") {
+            if title.trim().is_empty() {
+                return None;
+            }
+            return Some((element.clone(), title.to_string()));
+        }
+        current = element.parent();
+    }
+
+    let is_image = node.tag_name().is_some_and(|tag| tag.eq_ignore_ascii_case("img"));
+    let alt = node.get_attribute("alt").filter(|alt| is_image && !alt.trim().is_empty())?.to_string();
+    Some((node, alt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_dom::Document;
+
+    #[test]
+    fn test_nearest_title_wins() {
+        let doc = Document::parse_html(
+            r#"<div id="outer" title="Outer"><span id="inner">Text</span>
+               <p id="muted" title=""><b id="bold">No tooltip</b></p>
+               <img id="captioned" title="Caption" alt="Alt"></div>
+               <img id="photo" alt="A photo"><img id="decorative" alt=""><p id="plain">Plain</p>"#,
+        )
+        .unwrap();
+        let node = |id: &str| doc.get_element_by_id(id).unwrap();
+        let tooltip = |id: &str| tooltip_for(node(id)).map(|(element, text)| (element.id, text));
+
+        assert_eq!(tooltip("inner"), Some((node("outer").id, "Outer".to_string())));
+        assert_eq!(tooltip("bold"), None);
+        assert_eq!(tooltip("captioned"), Some((node("captioned").id, "Caption".to_string())));
+        assert_eq!(tooltip("photo"), Some((node("photo").id, "A photo".to_string())));
+        assert_eq!(tooltip("decorative"), None);
+        assert_eq!(tooltip("plain"), None);
+    }
+}
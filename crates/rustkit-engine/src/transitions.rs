@@ -0,0 +1,573 @@
+//! CSS transitions.
+//!
+//! When a relayout computes a new value for a property listed in an
+//! element's `transition-property`, the element shows the property
+//! changing from its old value to the new one over `transition-duration`,
+//! after `transition-delay`, eased by `transition-timing-function`.
+//! Opacity, colors, pixel lengths and transforms whose functions line up
+//! transition; other changes take effect at once.
+//!
+//! Each relayout writes the values the running transitions have reached
+//! into the new layout tree, and puts the computed ones back before the
+//! tree is reused. Hosts drive running transitions with
+//! [`Engine::pump_animations`], which queues a relayout of each view that
+//! has one about every [`ANIMATION_FRAME_INTERVAL`].
+//!
+//! [`Engine::pump_animations`]: crate::Engine::pump_animations
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rustkit_css::{Color, ColorF32, ComputedStyle, Length, TimingFunction, TransformList, TransformOp};
+use rustkit_layout::{BoxType, LayoutBox};
+
+/// How often running transitions and animations advance.
+pub(crate) const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A property that can transition or be animated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Property {
+    Opacity,
+    Color,
+    BackgroundColor,
+    BorderTopColor,
+    BorderRightColor,
+    BorderBottomColor,
+    BorderLeftColor,
+    Transform,
+    Width,
+    Height,
+    MarginTop,
+    MarginRight,
+    MarginBottom,
+    MarginLeft,
+    PaddingTop,
+    PaddingRight,
+    PaddingBottom,
+    PaddingLeft,
+    FontSize,
+    LetterSpacing,
+}
+
+/// Every property that can transition.
+const PROPERTIES: [Property; 20] = [
+    Property::Opacity,
+    Property::Color,
+    Property::BackgroundColor,
+    Property::BorderTopColor,
+    Property::BorderRightColor,
+    Property::BorderBottomColor,
+    Property::BorderLeftColor,
+    Property::Transform,
+    Property::Width,
+    Property::Height,
+    Property::MarginTop,
+    Property::MarginRight,
+    Property::MarginBottom,
+    Property::MarginLeft,
+    Property::PaddingTop,
+    Property::PaddingRight,
+    Property::PaddingBottom,
+    Property::PaddingLeft,
+    Property::FontSize,
+    Property::LetterSpacing,
+];
+
+impl Property {
+    /// CSS name of the property.
+    pub fn name(self) -> &'static str {
+        match self {
+            Property::Opacity => "opacity",
+            Property::Color => "color",
+            Property::BackgroundColor => "background-color",
+            Property::BorderTopColor => "border-top-color",
+            Property::BorderRightColor => "border-right-color",
+            Property::BorderBottomColor => "border-bottom-color",
+            Property::BorderLeftColor => "border-left-color",
+            Property::Transform => "transform",
+            Property::Width => "width",
+            Property::Height => "height",
+            Property::MarginTop => "margin-top",
+            Property::MarginRight => "margin-right",
+            Property::MarginBottom => "margin-bottom",
+            Property::MarginLeft => "margin-left",
+            Property::PaddingTop => "padding-top",
+            Property::PaddingRight => "padding-right",
+            Property::PaddingBottom => "padding-bottom",
+            Property::PaddingLeft => "padding-left",
+            Property::FontSize => "font-size",
+            Property::LetterSpacing => "letter-spacing",
+        }
+    }
+
+    /// The property named `name`.
+    pub fn from_name(name: &str) -> Option<Property> {
+        PROPERTIES.into_iter().find(|property| property.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Shorthands whose name in `transition-property` covers this one.
+    fn shorthands(self) -> &'static [&'static str] {
+        match self {
+            Property::BackgroundColor => &["background"],
+            Property::BorderTopColor
+            | Property::BorderRightColor
+            | Property::BorderBottomColor
+            | Property::BorderLeftColor => &["border", "border-color"],
+            Property::MarginTop | Property::MarginRight | Property::MarginBottom | Property::MarginLeft => &["margin"],
+            Property::PaddingTop | Property::PaddingRight | Property::PaddingBottom | Property::PaddingLeft => {
+                &["padding"]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Whether children take their value of this property from their parent.
+    fn inherited(self) -> bool {
+        matches!(self, Property::Color | Property::FontSize | Property::LetterSpacing)
+    }
+
+    /// Whether changes to this property transition on an element with `style`.
+    fn transitions_in(self, style: &ComputedStyle) -> bool {
+        style.transition_duration > 0.0
+            && style.transition_property.split(',').any(|name| {
+                let name = name.trim();
+                name.eq_ignore_ascii_case("all")
+                    || name.eq_ignore_ascii_case(self.name())
+                    || self.shorthands().iter().any(|shorthand| name.eq_ignore_ascii_case(shorthand))
+            })
+    }
+
+    /// The property's value in `style`.
+    pub fn get(self, style: &ComputedStyle) -> Value {
+        match self {
+            Property::Opacity => Value::Number(style.opacity),
+            Property::Color => Value::Color(style.color),
+            Property::BackgroundColor => Value::Color(style.background_color),
+            Property::BorderTopColor => Value::Color(style.border_top_color),
+            Property::BorderRightColor => Value::Color(style.border_right_color),
+            Property::BorderBottomColor => Value::Color(style.border_bottom_color),
+            Property::BorderLeftColor => Value::Color(style.border_left_color),
+            Property::Transform => Value::Transform(style.transform.clone()),
+            Property::Width => Value::Length(style.width.clone()),
+            Property::Height => Value::Length(style.height.clone()),
+            Property::MarginTop => Value::Length(style.margin_top.clone()),
+            Property::MarginRight => Value::Length(style.margin_right.clone()),
+            Property::MarginBottom => Value::Length(style.margin_bottom.clone()),
+            Property::MarginLeft => Value::Length(style.margin_left.clone()),
+            Property::PaddingTop => Value::Length(style.padding_top.clone()),
+            Property::PaddingRight => Value::Length(style.padding_right.clone()),
+            Property::PaddingBottom => Value::Length(style.padding_bottom.clone()),
+            Property::PaddingLeft => Value::Length(style.padding_left.clone()),
+            Property::FontSize => Value::Length(style.font_size.clone()),
+            Property::LetterSpacing => Value::Length(style.letter_spacing.clone()),
+        }
+    }
+
+    /// Set the property in `style` to `value`.
+    pub fn set(self, style: &mut ComputedStyle, value: &Value) {
+        match (self, value.clone()) {
+            (Property::Opacity, Value::Number(opacity)) => style.opacity = opacity,
+            (Property::Color, Value::Color(color)) => style.color = color,
+            (Property::BackgroundColor, Value::Color(color)) => style.background_color = color,
+            (Property::BorderTopColor, Value::Color(color)) => style.border_top_color = color,
+            (Property::BorderRightColor, Value::Color(color)) => style.border_right_color = color,
+            (Property::BorderBottomColor, Value::Color(color)) => style.border_bottom_color = color,
+            (Property::BorderLeftColor, Value::Color(color)) => style.border_left_color = color,
+            (Property::Transform, Value::Transform(transform)) => style.transform = transform,
+            (Property::Width, Value::Length(length)) => style.width = length,
+            (Property::Height, Value::Length(length)) => style.height = length,
+            (Property::MarginTop, Value::Length(length)) => style.margin_top = length,
+            (Property::MarginRight, Value::Length(length)) => style.margin_right = length,
+            (Property::MarginBottom, Value::Length(length)) => style.margin_bottom = length,
+            (Property::MarginLeft, Value::Length(length)) => style.margin_left = length,
+            (Property::PaddingTop, Value::Length(length)) => style.padding_top = length,
+            (Property::PaddingRight, Value::Length(length)) => style.padding_right = length,
+            (Property::PaddingBottom, Value::Length(length)) => style.padding_bottom = length,
+            (Property::PaddingLeft, Value::Length(length)) => style.padding_left = length,
+            (Property::FontSize, Value::Length(length)) => style.font_size = length,
+            (Property::LetterSpacing, Value::Length(length)) => style.letter_spacing = length,
+            _ => {}
+        }
+    }
+
+    /// Set the property of `layout_box` to `value`, and of the descendants
+    /// that inherited `inherited` from it when the property is inherited.
+    pub fn set_in_tree(self, layout_box: &mut LayoutBox, value: &Value, inherited: &Value) {
+        self.set(&mut layout_box.style, value);
+        if !self.inherited() {
+            return;
+        }
+        for child in &mut layout_box.children {
+            if self.get(&child.style) == *inherited {
+                self.set_in_tree(child, value, inherited);
+            }
+        }
+    }
+}
+
+/// A value of a transitionable property.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Number(f32),
+    Color(Color),
+    Length(Length),
+    Transform(TransformList),
+}
+
+impl Value {
+    /// The value `t` of the way from this one to `to`, or `None` when the
+    /// two can't be interpolated.
+    pub fn interpolate(&self, to: &Value, t: f32) -> Option<Value> {
+        match (self, to) {
+            (Value::Number(from), Value::Number(to)) => Some(Value::Number(lerp(*from, *to, t))),
+            (Value::Color(from), Value::Color(to)) => Some(Value::Color(
+                ColorF32::from_color(*from).lerp(&ColorF32::from_color(*to), t).to_color(),
+            )),
+            (Value::Length(from), Value::Length(to)) => interpolate_length(from, to, t).map(Value::Length),
+            (Value::Transform(from), Value::Transform(to)) => interpolate_transform(from, to, t).map(Value::Transform),
+            _ => None,
+        }
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+/// Pixels of an absolute length.
+fn px(length: &Length) -> Option<f32> {
+    match length {
+        Length::Px(px) => Some(*px),
+        Length::Zero => Some(0.0),
+        _ => None,
+    }
+}
+
+fn interpolate_length(from: &Length, to: &Length, t: f32) -> Option<Length> {
+    Some(Length::Px(lerp(px(from)?, px(to)?, t)))
+}
+
+/// The transform function of `op`'s kind that does nothing.
+fn identity_op(op: &TransformOp) -> TransformOp {
+    match op {
+        TransformOp::Translate(..) => TransformOp::Translate(Length::Zero, Length::Zero),
+        TransformOp::TranslateX(_) => TransformOp::TranslateX(Length::Zero),
+        TransformOp::TranslateY(_) => TransformOp::TranslateY(Length::Zero),
+        TransformOp::Scale(..) => TransformOp::Scale(1.0, 1.0),
+        TransformOp::ScaleX(_) => TransformOp::ScaleX(1.0),
+        TransformOp::ScaleY(_) => TransformOp::ScaleY(1.0),
+        TransformOp::Rotate(_) => TransformOp::Rotate(0.0),
+        TransformOp::SkewX(_) => TransformOp::SkewX(0.0),
+        TransformOp::SkewY(_) => TransformOp::SkewY(0.0),
+        TransformOp::Skew(..) => TransformOp::Skew(0.0, 0.0),
+        TransformOp::Matrix(..) => TransformOp::Matrix(1.0, 0.0, 0.0, 1.0, 0.0, 0.0),
+    }
+}
+
+/// Transforms interpolate function by function when their lists line up,
+/// `none` standing for a list of identity functions.
+fn interpolate_transform(from: &TransformList, to: &TransformList, t: f32) -> Option<TransformList> {
+    let from_ops: Vec<TransformOp> = if from.is_identity() {
+        to.ops.iter().map(identity_op).collect()
+    } else {
+        from.ops.clone()
+    };
+    let to_ops: Vec<TransformOp> = if to.is_identity() {
+        from.ops.iter().map(identity_op).collect()
+    } else {
+        to.ops.clone()
+    };
+    if from_ops.len() != to_ops.len() {
+        return None;
+    }
+    let ops = from_ops
+        .iter()
+        .zip(&to_ops)
+        .map(|(from, to)| interpolate_op(from, to, t))
+        .collect::<Option<Vec<_>>>()?;
+    Some(TransformList { ops })
+}
+
+fn interpolate_op(from: &TransformOp, to: &TransformOp, t: f32) -> Option<TransformOp> {
+    use TransformOp::*;
+    Some(match (from, to) {
+        (Translate(x1, y1), Translate(x2, y2)) => {
+            Translate(interpolate_length(x1, x2, t)?, interpolate_length(y1, y2, t)?)
+        }
+        (TranslateX(x1), TranslateX(x2)) => TranslateX(interpolate_length(x1, x2, t)?),
+        (TranslateY(y1), TranslateY(y2)) => TranslateY(interpolate_length(y1, y2, t)?),
+        (Scale(x1, y1), Scale(x2, y2)) => Scale(lerp(*x1, *x2, t), lerp(*y1, *y2, t)),
+        (ScaleX(x1), ScaleX(x2)) => ScaleX(lerp(*x1, *x2, t)),
+        (ScaleY(y1), ScaleY(y2)) => ScaleY(lerp(*y1, *y2, t)),
+        (Rotate(a1), Rotate(a2)) => Rotate(lerp(*a1, *a2, t)),
+        (SkewX(a1), SkewX(a2)) => SkewX(lerp(*a1, *a2, t)),
+        (SkewY(a1), SkewY(a2)) => SkewY(lerp(*a1, *a2, t)),
+        (Skew(x1, y1), Skew(x2, y2)) => Skew(lerp(*x1, *x2, t), lerp(*y1, *y2, t)),
+        (Matrix(a1, b1, c1, d1, e1, f1), Matrix(a2, b2, c2, d2, e2, f2)) => Matrix(
+            lerp(*a1, *a2, t),
+            lerp(*b1, *b2, t),
+            lerp(*c1, *c2, t),
+            lerp(*d1, *d2, t),
+            lerp(*e1, *e2, t),
+            lerp(*f1, *f2, t),
+        ),
+        _ => return None,
+    })
+}
+
+/// A property of an element changing over time.
+#[derive(Debug, Clone)]
+struct Transition {
+    from: Value,
+    /// The computed value.
+    to: Value,
+    /// When the value starts to move, after the delay.
+    start: Instant,
+    duration: Duration,
+    timing: TimingFunction,
+    /// Value last written into the layout tree.
+    shown: Value,
+}
+
+impl Transition {
+    /// The value at `now`.
+    fn value_at(&self, now: Instant) -> Value {
+        if now < self.start {
+            return self.from.clone();
+        }
+        let t = now.duration_since(self.start).as_secs_f32() / self.duration.as_secs_f32();
+        if t >= 1.0 {
+            return self.to.clone();
+        }
+        self.from
+            .interpolate(&self.to, self.timing.progress(t))
+            .unwrap_or_else(|| self.to.clone())
+    }
+
+    fn finished(&self, now: Instant) -> bool {
+        now >= self.start + self.duration
+    }
+}
+
+/// Transitions of a view's elements.
+#[derive(Debug, Default)]
+pub(crate) struct Transitions {
+    /// Computed value of every transitionable property of each element at
+    /// the last relayout, in [`PROPERTIES`] order, by DOM node ID.
+    computed: HashMap<usize, Vec<Value>>,
+    /// Running transitions, by DOM node ID and property.
+    running: HashMap<(usize, Property), Transition>,
+    /// When the running transitions last advanced.
+    last_frame: Option<Instant>,
+}
+
+impl Transitions {
+    /// When the running transitions next advance, if any are running.
+    pub fn next_frame(&self) -> Option<Instant> {
+        let next = self.last_frame? + ANIMATION_FRAME_INTERVAL;
+        let first_start = self.running.values().map(|transition| transition.start).min()?;
+        Some(next.max(first_start))
+    }
+
+    /// Put the computed values back in place of the ones transitions wrote
+    /// into `root`, the last relayout's tree, so the parts of it the next
+    /// relayout reuses read as freshly computed.
+    pub fn restore(&self, root: &mut LayoutBox) {
+        if self.running.is_empty() {
+            return;
+        }
+        for_each_element(root, &mut |element_id, layout_box| {
+            for property in PROPERTIES {
+                if let Some(transition) = self.running.get(&(element_id, property)) {
+                    if property.get(&layout_box.style) == transition.shown {
+                        property.set_in_tree(layout_box, &transition.to, &transition.shown);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Start, retarget or cancel transitions for the computed values in
+    /// `root`, a freshly styled tree, then write the values the running
+    /// ones have reached at `now` into it.
+    pub fn update(&mut self, root: &mut LayoutBox, now: Instant) {
+        let mut computed = HashMap::new();
+        let previous = &self.computed;
+        let running = &mut self.running;
+        for_each_element(root, &mut |element_id, layout_box| {
+            if computed.contains_key(&element_id) {
+                return;
+            }
+            let values: Vec<Value> = PROPERTIES.iter().map(|property| property.get(&layout_box.style)).collect();
+            // Elements seen for the first time start out at their values
+            if let Some(old) = previous.get(&element_id) {
+                for (property, (old, new)) in PROPERTIES.into_iter().zip(old.iter().zip(&values)) {
+                    let key = (element_id, property);
+                    if !property.transitions_in(&layout_box.style) {
+                        running.remove(&key);
+                    } else if old != new {
+                        retarget(running, key, old, new, &layout_box.style, now);
+                    }
+                }
+            }
+            computed.insert(element_id, values);
+        });
+        running.retain(|(element_id, _), _| computed.contains_key(element_id));
+        self.computed = computed;
+
+        if !running.is_empty() {
+            for_each_element(root, &mut |element_id, layout_box| {
+                for property in PROPERTIES {
+                    if let Some(transition) = running.get_mut(&(element_id, property)) {
+                        let value = transition.value_at(now);
+                        property.set_in_tree(layout_box, &value, &transition.to);
+                        transition.shown = value;
+                    }
+                }
+            });
+        }
+        running.retain(|_, transition| !transition.finished(now));
+        self.last_frame = Some(now);
+    }
+}
+
+/// Start the transition of `key` from `old` towards `new`, carrying on
+/// from where a transition already running got to.
+fn retarget(
+    running: &mut HashMap<(usize, Property), Transition>,
+    key: (usize, Property),
+    old: &Value,
+    new: &Value,
+    style: &ComputedStyle,
+    now: Instant,
+) {
+    let from = running.get(&key).map_or_else(|| old.clone(), |transition| transition.value_at(now));
+    if from == *new || from.interpolate(new, 0.0).is_none() {
+        running.remove(&key);
+        return;
+    }
+    let delay = Duration::from_secs_f32(style.transition_delay.abs());
+    let start = if style.transition_delay >= 0.0 {
+        now + delay
+    } else {
+        now.checked_sub(delay).unwrap_or(now)
+    };
+    running.insert(
+        key,
+        Transition {
+            shown: from.clone(),
+            from,
+            to: new.clone(),
+            start,
+            duration: Duration::from_secs_f32(style.transition_duration),
+            timing: style.transition_timing_function.clone(),
+        },
+    );
+}
+
+/// Call `f` with each box generated for an element in `layout_box`,
+/// parents before children.
+pub(crate) fn for_each_element(layout_box: &mut LayoutBox, f: &mut impl FnMut(usize, &mut LayoutBox)) {
+    if let Some(element_id) = layout_box.element_id {
+        if !matches!(layout_box.box_type, BoxType::Text(_)) {
+            f(element_id, layout_box);
+        }
+    }
+    for child in &mut layout_box.children {
+        for_each_element(child, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A box for element 1 with a text child.
+    fn element(style: ComputedStyle) -> LayoutBox {
+        let mut text = LayoutBox::new(BoxType::Text("hi".to_string()), style.clone());
+        text.set_element_id(2);
+        let mut layout_box = LayoutBox::new(BoxType::Block, style);
+        layout_box.set_element_id(1);
+        layout_box.children.push(text);
+        layout_box
+    }
+
+    fn transitioning(property: &str) -> ComputedStyle {
+        let mut style = ComputedStyle::new();
+        style.transition_property = property.to_string();
+        style.transition_duration = 1.0;
+        style.transition_timing_function = TimingFunction::Linear;
+        style
+    }
+
+    #[test]
+    fn test_opacity_transitions_over_duration() {
+        let start = Instant::now();
+        let mut transitions = Transitions::default();
+        let mut tree = element(transitioning("opacity"));
+        transitions.update(&mut tree, start);
+        assert_eq!(transitions.next_frame(), None);
+
+        let mut style = transitioning("opacity");
+        style.opacity = 0.0;
+        let mut tree = element(style);
+        transitions.update(&mut tree, start);
+                assert_eq!(tree.style.opacity, 1.0);
+        assert_eq!(transitions.next_frame(), Some(start + ANIMATION_FRAME_INTERVAL));
+
+        // The kept tree is put back to its computed values, then advanced
+        let half = start + Duration::from_millis(500);
+        transitions.restore(&mut tree);
+        assert_eq!(tree.style.opacity, 0.0);
+        transitions.update(&mut tree, half);
+        assert!((tree.style.opacity - 0.5).abs() < 1e-3);
+
+        transitions.restore(&mut tree);
+        transitions.update(&mut tree, start + Duration::from_secs(1));
+        assert_eq!(tree.style.opacity, 0.0);
+        assert_eq!(transitions.next_frame(), None);
+    }
+
+    #[test]
+    fn test_transitioned_values() {
+        let start = Instant::now();
+        let half = start + Duration::from_millis(500);
+        let mut transitions = Transitions::default();
+        let mut before = transitioning("color, width, transform");
+        before.color = Color::BLACK;
+        before.width = Length::Px(100.0);
+        before.height = Length::Px(10.0);
+        transitions.update(&mut element(before.clone()), start);
+
+        let mut after = before.clone();
+        after.color = Color::WHITE;
+        after.width = Length::Px(200.0);
+        after.height = Length::Px(20.0);
+        after.transform = TransformList {
+            ops: vec![TransformOp::Rotate(90.0)],
+        };
+        transitions.update(&mut element(after.clone()), start);
+        let mut tree = element(after);
+        transitions.update(&mut tree, half);
+
+        // Text inherits the color of its element
+        assert_eq!(tree.style.color, Color::from_rgb(128, 128, 128));
+        assert_eq!(tree.children[0].style.color, Color::from_rgb(128, 128, 128));
+        assert_eq!(tree.style.width, Length::Px(150.0));
+        assert_eq!(tree.style.transform.ops, vec![TransformOp::Rotate(45.0)]);
+        // Height isn't listed, so it changed at once
+        assert_eq!(tree.style.height, Length::Px(20.0));
+
+        // Retargeting carries on from where the transition got to, and
+        // lengths in other units can't be interpolated
+        let mut back = before.clone();
+        back.width = Length::Percent(50.0);
+        let mut tree = element(back);
+        transitions.update(&mut tree, half);
+        assert_eq!(tree.style.width, Length::Percent(50.0));
+        assert_eq!(tree.style.color, Color::from_rgb(128, 128, 128));
+        transitions.restore(&mut tree);
+        transitions.update(&mut tree, half + Duration::from_millis(500));
+        assert!(tree.style.color.r < 128);
+    }
+}
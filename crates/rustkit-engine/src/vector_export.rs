@@ -0,0 +1,796 @@
+//! Vector export of display lists.
+//!
+//! [`Engine::export_vector`](crate::Engine::export_vector) writes a view's
+//! display list as SVG, for captures that stay sharp at any size in
+//! documentation and paint output that diffs as text. Commands map onto the
+//! nearest SVG elements, one per line; what SVG has no equivalent for, like
+//! backdrop filters, is left out.
+
+use std::fmt::Write;
+
+use rustkit_css::{Color, ColorF32, ColorStop, Gradient, GradientDirection, RadialShape, RadialSize};
+use rustkit_layout::{BackgroundSize, BorderRadius, DisplayCommand, Rect};
+use rustkit_renderer::LayerProperties;
+
+/// The renderer places text by the top of its box, with the baseline this
+/// many font sizes below it.
+pub(crate) const ASCENT: f32 = 0.8;
+
+/// Degrees per wedge when drawing conic gradients, which SVG lacks.
+const CONIC_STEP: f32 = 2.0;
+
+/// The SVG for `commands`, showing `viewport` of the page. `layers` are the
+/// properties of the compositor layers the commands refer to.
+pub(crate) fn to_svg(commands: &[DisplayCommand], viewport: Rect, layers: &[LayerProperties]) -> String {
+    let mut svg = SvgWriter::default();
+    for command in commands {
+        svg.command(command, layers);
+    }
+    svg.finish(viewport)
+}
+
+#[derive(Default)]
+struct SvgWriter {
+    defs: String,
+    body: String,
+    /// Groups opened by push commands and not yet closed.
+    depth: usize,
+    next_id: usize,
+}
+
+impl SvgWriter {
+    fn command(&mut self, command: &DisplayCommand, layers: &[LayerProperties]) {
+        match command {
+            DisplayCommand::SolidColor(color, rect) | DisplayCommand::FillRect { rect, color } => {
+                self.shape(*rect, BorderRadius::default(), &fill(*color));
+            }
+            DisplayCommand::RoundedRect { color, rect, radius } => self.shape(*rect, *radius, &fill(*color)),
+            DisplayCommand::Border { color, rect, top, right, bottom, left } => {
+                let sides = [
+                    Rect::new(rect.x, rect.y, rect.width, *top),
+                    Rect::new(rect.x + rect.width - right, rect.y + top, *right, rect.height - top - bottom),
+                    Rect::new(rect.x, rect.y + rect.height - bottom, rect.width, *bottom),
+                    Rect::new(rect.x, rect.y + top, *left, rect.height - top - bottom),
+                ];
+                for side in sides.into_iter().filter(|side| side.width > 0.0 && side.height > 0.0) {
+                    self.shape(side, BorderRadius::default(), &fill(*color));
+                }
+            }
+            DisplayCommand::Text { text, x, y, color, font_size, font_family, font_weight, font_style } => {
+                let font = font(*font_size, font_family, *font_weight, *font_style);
+                self.text(text, *x, *y, *font_size, &format!("{font} {}", fill(*color)));
+            }
+            DisplayCommand::TextRun { segments, color, font_size, font_family, font_weight, font_style } => {
+                let font = font(*font_size, font_family, *font_weight, *font_style);
+                self.line(&format!("<text {font} {}>", fill(*color)));
+                for segment in segments {
+                    self.line(&format!(
+                        "  <tspan x=\"{}\" y=\"{}\">{}</tspan>",
+                        num(segment.x),
+                        num(segment.y + font_size * ASCENT),
+                        escape(&segment.text)
+                    ));
+                }
+                self.line("</text>");
+            }
+            // Drawn solid whatever the style, as the renderer does
+            DisplayCommand::TextDecoration { x, y, width, thickness, color, style: _ } => {
+                self.shape(Rect::new(*x, *y, *width, *thickness), BorderRadius::default(), &fill(*color));
+            }
+            DisplayCommand::Image { url, src_rect: _, dest_rect, object_fit: _, opacity } => {
+                let opacity = if *opacity < 1.0 { format!(" opacity=\"{}\"", num(*opacity)) } else { String::new() };
+                self.line(&format!(
+                    "<image {} href=\"{}\" preserveAspectRatio=\"none\"{opacity}/>",
+                    rect_attrs(*dest_rect),
+                    escape(url)
+                ));
+            }
+            DisplayCommand::BackgroundImage { url, rect, size, position, offset, repeat: _ } => {
+                // Without the image's natural size, `auto` sizes and tiling
+                // can't be worked out; such images cover the box instead
+                let (image, aspect) = match size {
+                    BackgroundSize::Contain => (*rect, "meet"),
+                    BackgroundSize::Explicit { width: Some(width), height: Some(height) } => {
+                        let x = rect.x + (rect.width - width) * position.0 + offset.0;
+                        let y = rect.y + (rect.height - height) * position.1 + offset.1;
+                        (Rect::new(x, y, *width, *height), "none")
+                    }
+                    _ => (*rect, "slice"),
+                };
+                let align = match aspect {
+                    "none" => "none".to_string(),
+                    aspect => format!("{}{} {aspect}", aspect_align("x", position.0), aspect_align("Y", position.1)),
+                };
+                let clip = self.clip_path(*rect, BorderRadius::default());
+                self.line(&format!(
+                    "<image {} href=\"{}\" preserveAspectRatio=\"{align}\" clip-path=\"{clip}\"/>",
+                    rect_attrs(image),
+                    escape(url)
+                ));
+            }
+            DisplayCommand::BoxShadow { offset_x, offset_y, blur_radius, spread_radius, color, rect, inset } => {
+                self.box_shadow(*rect, (*offset_x, *offset_y), *blur_radius, *spread_radius, *color, *inset);
+            }
+            // SVG can't filter what's behind an element
+            DisplayCommand::BackdropFilter { .. } => {}
+            DisplayCommand::LinearGradient { rect, direction, stops, repeating, border_radius } => {
+                let paint = self.linear_gradient(*rect, *direction, stops, *repeating);
+                self.shape(*rect, *border_radius, &format!("fill=\"{paint}\""));
+            }
+            DisplayCommand::RadialGradient { rect, shape, size, center, stops, repeating, border_radius } => {
+                let paint = self.radial_gradient(*rect, *shape, *size, *center, stops, *repeating);
+                self.shape(*rect, *border_radius, &format!("fill=\"{paint}\""));
+            }
+            DisplayCommand::ConicGradient { rect, from_angle, center, stops, repeating, border_radius } => {
+                self.conic_gradient(*rect, *from_angle, *center, stops, *repeating, *border_radius);
+            }
+            DisplayCommand::TextInput {
+                rect,
+                value,
+                placeholder,
+                font_size,
+                text_color,
+                placeholder_color,
+                background_color,
+                border_color,
+                border_width,
+                focused: _,
+                caret_position: _,
+            } => {
+                self.bordered(*rect, 0.0, *background_color, *border_color, *border_width);
+                let (text, color) = if value.is_empty() { (placeholder, placeholder_color) } else { (value, text_color) };
+                let top = rect.y + (rect.height + font_size) / 2.0 - font_size * 0.2;
+                let font = font(*font_size, "sans-serif", 400, 0);
+                self.text(text, rect.x + 6.0, top, *font_size, &format!("{font} {}", fill(*color)));
+            }
+            DisplayCommand::Button {
+                rect,
+                label,
+                font_size,
+                text_color,
+                background_color,
+                border_color,
+                border_width,
+                border_radius,
+                pressed: _,
+                focused: _,
+            } => {
+                self.bordered(*rect, *border_radius, *background_color, *border_color, *border_width);
+                let top = rect.y + (rect.height + font_size) / 2.0 - font_size * 0.2;
+                let font = font(*font_size, "sans-serif", 400, 0);
+                let attrs = format!("{font} {} text-anchor=\"middle\"", fill(*text_color));
+                self.text(label, rect.x + rect.width / 2.0, top, *font_size, &attrs);
+            }
+            DisplayCommand::FocusRing { rect, color, width, offset } => {
+                let grow = offset + width / 2.0;
+                let ring = Rect::new(rect.x - grow, rect.y - grow, rect.width + grow * 2.0, rect.height + grow * 2.0);
+                self.shape(ring, BorderRadius::default(), &format!("fill=\"none\" {}", stroke(*color, *width)));
+            }
+            DisplayCommand::Caret { x, y, height, color } => {
+                self.shape(Rect::new(*x, *y, 2.0, *height), BorderRadius::default(), &fill(*color));
+            }
+            DisplayCommand::PushClip(rect) => {
+                let clip = self.clip_path(*rect, BorderRadius::default());
+                self.open(&format!("clip-path=\"{clip}\""));
+            }
+            DisplayCommand::PushRoundedClip { rect, radius } => {
+                let clip = self.clip_path(*rect, *radius);
+                self.open(&format!("clip-path=\"{clip}\""));
+            }
+            DisplayCommand::PushStackingContext { z_index, rect: _ } => {
+                self.open(&format!("data-z-index=\"{z_index}\""));
+            }
+            DisplayCommand::PushTransform { matrix, origin } => {
+                self.open(&transform(rustkit_layout::transform::around_origin(*matrix, *origin)));
+            }
+            DisplayCommand::PushLayer { id, rect } => {
+                let properties = layers.get(*id).copied().unwrap_or_default();
+                let center = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+                let mut attrs = transform(rustkit_layout::transform::around_origin(properties.transform, center));
+                if properties.opacity < 1.0 {
+                    let _ = write!(attrs, " opacity=\"{}\"", num(properties.opacity));
+                }
+                self.open(&attrs);
+            }
+            DisplayCommand::PushOpacity(opacity) => self.open(&format!("opacity=\"{}\"", num(*opacity))),
+            DisplayCommand::PopClip
+            | DisplayCommand::PopRoundedClip
+            | DisplayCommand::PopStackingContext
+            | DisplayCommand::PopTransform
+            | DisplayCommand::PopLayer
+            | DisplayCommand::PopOpacity => self.close(),
+            DisplayCommand::GradientText {
+                text,
+                x,
+                y,
+                font_size,
+                font_family,
+                font_weight,
+                font_style,
+                gradient,
+                rect,
+            } => {
+                let paint = match gradient {
+                    Gradient::Linear(linear) => {
+                        self.linear_gradient(*rect, linear.direction, &linear.stops, linear.repeating)
+                    }
+                    Gradient::Radial(radial) => self.radial_gradient(
+                        *rect,
+                        radial.shape,
+                        radial.size,
+                        radial.center,
+                        &radial.stops,
+                        radial.repeating,
+                    ),
+                    // Text can't be filled with wedges; use the average color
+                    Gradient::Conic(conic) => {
+                        let stops = rustkit_css::resolve_color_stops(&conic.stops, |p| p.to_normalized(360.0));
+                        let count = stops.len().max(1) as f32;
+                        let sum = stops.iter().fold([0.0; 4], |sum, (_, c)| {
+                            [sum[0] + c.r, sum[1] + c.g, sum[2] + c.b, sum[3] + c.a]
+                        });
+                        color_f32(ColorF32 { r: sum[0] / count, g: sum[1] / count, b: sum[2] / count, a: sum[3] / count })
+                    }
+                };
+                let font = font(*font_size, font_family, *font_weight, *font_style);
+                self.text(text, *x, *y, *font_size, &format!("{font} fill=\"{paint}\""));
+            }
+            DisplayCommand::StrokeRect { rect, color, width } => {
+                self.shape(*rect, BorderRadius::default(), &format!("fill=\"none\" {}", stroke(*color, *width)));
+            }
+            DisplayCommand::FillCircle { cx, cy, radius, color } => {
+                self.line(&format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>", num(*cx), num(*cy), num(*radius), fill(*color)));
+            }
+            DisplayCommand::StrokeCircle { cx, cy, radius, color, width } => {
+                self.line(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" {}/>",
+                    num(*cx),
+                    num(*cy),
+                    num(*radius),
+                    stroke(*color, *width)
+                ));
+            }
+            DisplayCommand::FillEllipse { rect, color } => {
+                self.line(&format!(
+                    "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {}/>",
+                    num(rect.x + rect.width / 2.0),
+                    num(rect.y + rect.height / 2.0),
+                    num(rect.width / 2.0),
+                    num(rect.height / 2.0),
+                    fill(*color)
+                ));
+            }
+            DisplayCommand::Line { x1, y1, x2, y2, color, width } => {
+                self.line(&format!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+                    num(*x1),
+                    num(*y1),
+                    num(*x2),
+                    num(*y2),
+                    stroke(*color, *width)
+                ));
+            }
+            DisplayCommand::Polyline { points, color, width } => {
+                self.line(&format!("<polyline points=\"{}\" fill=\"none\" {}/>", points_attr(points), stroke(*color, *width)));
+            }
+            DisplayCommand::FillPolygon { points, color } => {
+                self.line(&format!("<polygon points=\"{}\" {}/>", points_attr(points), fill(*color)));
+            }
+            DisplayCommand::StrokePolygon { points, color, width } => {
+                self.line(&format!("<polygon points=\"{}\" fill=\"none\" {}/>", points_attr(points), stroke(*color, *width)));
+            }
+        }
+    }
+
+    fn finish(mut self, viewport: Rect) -> String {
+        while self.depth > 0 {
+            self.close();
+        }
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n",
+            num(viewport.width),
+            num(viewport.height),
+            num(viewport.x),
+            num(viewport.y),
+            num(viewport.width),
+            num(viewport.height)
+        );
+        if !self.defs.is_empty() {
+            svg.push_str("  <defs>\n");
+            svg.push_str(&self.defs);
+            svg.push_str("  </defs>\n");
+        }
+        svg.push_str(&self.body);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Append an element to the body, indented by the groups it's in.
+    fn line(&mut self, element: &str) {
+        for _ in 0..=self.depth {
+            self.body.push_str("  ");
+        }
+        self.body.push_str(element);
+        self.body.push('\n');
+    }
+
+    fn def(&mut self, element: &str) {
+        self.defs.push_str("    ");
+        self.defs.push_str(element);
+        self.defs.push('\n');
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id)
+    }
+
+    fn open(&mut self, attrs: &str) {
+        self.line(&format!("<g {attrs}>"));
+        self.depth += 1;
+    }
+
+    /// Close the innermost group. Unbalanced pops are ignored.
+    fn close(&mut self) {
+        if self.depth > 0 {
+            self.depth -= 1;
+            self.line("</g>");
+        }
+    }
+
+    /// A rectangle, rounded by `radius`.
+    fn shape(&mut self, rect: Rect, radius: BorderRadius, attrs: &str) {
+        let element = shape_element(rect, radius, attrs);
+        self.line(&element);
+    }
+
+    /// Text whose box's top is at `top`.
+    fn text(&mut self, text: &str, x: f32, top: f32, font_size: f32, attrs: &str) {
+        self.line(&format!(
+            "<text x=\"{}\" y=\"{}\" {attrs}>{}</text>",
+            num(x),
+            num(top + font_size * ASCENT),
+            escape(text)
+        ));
+    }
+
+    /// A box with a background and a border drawn inside its edge.
+    fn bordered(&mut self, rect: Rect, radius: f32, background: Color, border: Color, border_width: f32) {
+        self.shape(rect, BorderRadius::uniform(radius), &fill(background));
+        if border_width > 0.0 {
+            let inset = border_width / 2.0;
+            let edge = Rect::new(rect.x + inset, rect.y + inset, rect.width - border_width, rect.height - border_width);
+            let radius = BorderRadius::uniform((radius - inset).max(0.0));
+            self.shape(edge, radius, &format!("fill=\"none\" {}", stroke(border, border_width)));
+        }
+    }
+
+    /// Define a clip path and return the reference to it.
+    fn clip_path(&mut self, rect: Rect, radius: BorderRadius) -> String {
+        let id = self.id("clip");
+        let shape = shape_element(rect, radius, "");
+        self.def(&format!("<clipPath id=\"{id}\">{shape}</clipPath>"));
+        format!("url(#{id})")
+    }
+
+    fn box_shadow(&mut self, rect: Rect, offset: (f32, f32), blur: f32, spread: f32, color: Color, inset: bool) {
+        let filter = if blur > 0.0 {
+            let id = self.id("blur");
+            self.def(&format!(
+                "<filter id=\"{id}\" x=\"-50%\" y=\"-50%\" width=\"200%\" height=\"200%\"><feGaussianBlur stdDeviation=\"{}\"/></filter>",
+                num(blur / 2.0)
+            ));
+            format!(" filter=\"url(#{id})\"")
+        } else {
+            String::new()
+        };
+
+        if inset {
+            // Everything around the shrunken box, seen through the box
+            let hole = Rect::new(
+                rect.x + offset.0 + spread,
+                rect.y + offset.1 + spread,
+                rect.width - spread * 2.0,
+                rect.height - spread * 2.0,
+            );
+            let margin = blur + spread.abs() + offset.0.abs().max(offset.1.abs());
+            let outer = Rect::new(rect.x - margin, rect.y - margin, rect.width + margin * 2.0, rect.height + margin * 2.0);
+            let clip = self.clip_path(rect, BorderRadius::default());
+            self.open(&format!("clip-path=\"{clip}\""));
+            self.line(&format!(
+                "<path d=\"{} {}\" fill-rule=\"evenodd\" {}{filter}/>",
+                rect_path(outer),
+                rect_path(hole),
+                fill(color)
+            ));
+            self.close();
+        } else {
+            let shadow = Rect::new(
+                rect.x + offset.0 - spread,
+                rect.y + offset.1 - spread,
+                rect.width + spread * 2.0,
+                rect.height + spread * 2.0,
+            );
+            self.shape(shadow, BorderRadius::default(), &format!("{}{filter}", fill(color)));
+        }
+    }
+
+    /// Define a linear gradient over `rect` and return the paint for it.
+    fn linear_gradient(&mut self, rect: Rect, direction: GradientDirection, stops: &[ColorStop], repeating: bool) -> String {
+        let angle = direction.to_degrees().to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let half_length = (rect.width / 2.0 * sin.abs() + rect.height / 2.0 * cos.abs()).max(0.001);
+        let resolved = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(half_length * 2.0));
+        let (resolved, scale) = repeat_stops(resolved, repeating);
+
+        // The gradient line runs through the center, pointing at `angle`
+        let (cx, cy) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+        let (x1, y1) = (cx - sin * half_length, cy + cos * half_length);
+        let length = half_length * 2.0 * scale;
+        let (x2, y2) = (x1 + sin * length, y1 - cos * length);
+
+        let id = self.id("gradient");
+        let spread = if repeating { " spreadMethod=\"repeat\"" } else { "" };
+        self.def(&format!(
+            "<linearGradient id=\"{id}\" gradientUnits=\"userSpaceOnUse\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"{spread}>",
+            num(x1),
+            num(y1),
+            num(x2),
+            num(y2)
+        ));
+        self.gradient_stops(&resolved);
+        self.def("</linearGradient>");
+        format!("url(#{id})")
+    }
+
+    /// Define a radial gradient over `rect` and return the paint for it.
+    fn radial_gradient(
+        &mut self,
+        rect: Rect,
+        shape: RadialShape,
+        size: RadialSize,
+        center: (f32, f32),
+        stops: &[ColorStop],
+        repeating: bool,
+    ) -> String {
+        let (cx, cy) = (rect.x + rect.width * center.0, rect.y + rect.height * center.1);
+        let (rx, ry) = radial_radii(rect, shape, size, center);
+        let resolved = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(rx.max(ry)));
+        let (resolved, scale) = repeat_stops(resolved, repeating);
+
+        // Ellipses are circles of radius `rx`, squashed vertically
+        let rx = rx.max(0.001);
+        let squash = if ry == rx {
+            String::new()
+        } else {
+            format!(
+                " gradientTransform=\"{}\"",
+                matrix_attr(rustkit_layout::transform::around_origin([1.0, 0.0, 0.0, ry / rx, 0.0, 0.0], (cx, cy)))
+            )
+        };
+
+        let id = self.id("gradient");
+        let spread = if repeating { " spreadMethod=\"repeat\"" } else { "" };
+        self.def(&format!(
+            "<radialGradient id=\"{id}\" gradientUnits=\"userSpaceOnUse\" cx=\"{}\" cy=\"{}\" r=\"{}\"{squash}{spread}>",
+            num(cx),
+            num(cy),
+            num(rx * scale)
+        ));
+        self.gradient_stops(&resolved);
+        self.def("</radialGradient>");
+        format!("url(#{id})")
+    }
+
+    fn gradient_stops(&mut self, stops: &[(f32, ColorF32)]) {
+        for (offset, color) in stops {
+            let opacity = if color.a < 1.0 { format!(" stop-opacity=\"{}\"", num(color.a)) } else { String::new() };
+            self.def(&format!(
+                "  <stop offset=\"{}\" stop-color=\"{}\"{opacity}/>",
+                num(offset.clamp(0.0, 1.0)),
+                color_f32(ColorF32 { a: 1.0, ..*color })
+            ));
+        }
+    }
+
+    /// SVG has no conic gradients, so draw one as thin wedges around its
+    /// center, clipped to the box.
+    fn conic_gradient(
+        &mut self,
+        rect: Rect,
+        from_angle: f32,
+        center: (f32, f32),
+        stops: &[ColorStop],
+        repeating: bool,
+        radius: BorderRadius,
+    ) {
+        let resolved = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(360.0));
+        if resolved.is_empty() {
+            return;
+        }
+        let (resolved, _) = repeat_stops(resolved, repeating);
+        let (cx, cy) = (rect.x + rect.width * center.0, rect.y + rect.height * center.1);
+        let reach = rect.width.hypot(rect.height);
+
+        let clip = self.clip_path(rect, radius);
+        self.open(&format!("clip-path=\"{clip}\""));
+        let steps = (360.0 / CONIC_STEP) as usize;
+        for step in 0..steps {
+            let t = (step as f32 + 0.5) / steps as f32;
+            let color = color_at(&resolved, if repeating { t.fract() } else { t });
+            // A hair wider than the step, so no seams show between wedges
+            let start = (from_angle + step as f32 * CONIC_STEP - 90.0).to_radians();
+            let end = (from_angle + (step + 1) as f32 * CONIC_STEP - 90.0 + 0.5).to_radians();
+            self.line(&format!(
+                "<path d=\"M{},{} L{},{} L{},{} Z\" fill=\"{}\"{}/>",
+                num(cx),
+                num(cy),
+                num(cx + reach * start.cos()),
+                num(cy + reach * start.sin()),
+                num(cx + reach * end.cos()),
+                num(cy + reach * end.sin()),
+                color_f32(ColorF32 { a: 1.0, ..color }),
+                if color.a < 1.0 { format!(" fill-opacity=\"{}\"", num(color.a)) } else { String::new() }
+            ));
+        }
+        self.close();
+    }
+}
+
+/// A rectangle element, or a path if its corners are rounded unevenly.
+fn shape_element(rect: Rect, radius: BorderRadius, attrs: &str) -> String {
+    let attrs = if attrs.is_empty() { String::new() } else { format!(" {attrs}") };
+    let max = rect.width.min(rect.height).max(0.0) / 2.0;
+    let [tl, tr, br, bl] =
+        [radius.top_left, radius.top_right, radius.bottom_right, radius.bottom_left].map(|r| r.clamp(0.0, max));
+
+    if tl == tr && tr == br && br == bl {
+        let rounded = if tl > 0.0 { format!(" rx=\"{}\"", num(tl)) } else { String::new() };
+        return format!("<rect {}{rounded}{attrs}/>", rect_attrs(rect));
+    }
+
+    let (x, y, right, bottom) = (rect.x, rect.y, rect.x + rect.width, rect.y + rect.height);
+    format!(
+        "<path d=\"M{},{} H{} A{r1},{r1} 0 0 1 {},{} V{} A{r2},{r2} 0 0 1 {},{} H{} A{r3},{r3} 0 0 1 {},{} V{} A{r4},{r4} 0 0 1 {},{} Z\"{attrs}/>",
+        num(x + tl),
+        num(y),
+        num(right - tr),
+        num(right),
+        num(y + tr),
+        num(bottom - br),
+        num(right - br),
+        num(bottom),
+        num(x + bl),
+        num(x),
+        num(bottom - bl),
+        num(y + tl),
+        num(x + tl),
+        num(y),
+        r1 = num(tr),
+        r2 = num(br),
+        r3 = num(bl),
+        r4 = num(tl),
+    )
+}
+
+fn rect_attrs(rect: Rect) -> String {
+    format!(
+        "x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+        num(rect.x),
+        num(rect.y),
+        num(rect.width.max(0.0)),
+        num(rect.height.max(0.0))
+    )
+}
+
+fn rect_path(rect: Rect) -> String {
+    format!(
+        "M{},{} h{} v{} h{} Z",
+        num(rect.x),
+        num(rect.y),
+        num(rect.width),
+        num(rect.height),
+        num(-rect.width)
+    )
+}
+
+fn transform(matrix: [f32; 6]) -> String {
+    format!("transform=\"{}\"", matrix_attr(matrix))
+}
+
+fn matrix_attr(m: [f32; 6]) -> String {
+    format!("matrix({} {} {} {} {} {})", num(m[0]), num(m[1]), num(m[2]), num(m[3]), num(m[4]), num(m[5]))
+}
+
+fn points_attr(points: &[(f32, f32)]) -> String {
+    points.iter().map(|(x, y)| format!("{},{}", num(*x), num(*y))).collect::<Vec<_>>().join(" ")
+}
+
+fn font(size: f32, family: &str, weight: u16, style: u8) -> String {
+    let mut attrs = format!("font-size=\"{}\"", num(size));
+    if !family.is_empty() {
+        let _ = write!(attrs, " font-family=\"{}\"", escape(family));
+    }
+    if weight != 400 {
+        let _ = write!(attrs, " font-weight=\"{weight}\"");
+    }
+    if style != 0 {
+        attrs.push_str(" font-style=\"italic\"");
+    }
+    attrs
+}
+
+fn fill(color: Color) -> String {
+    let mut attrs = format!("fill=\"{}\"", hex(color));
+    if color.a < 1.0 {
+        let _ = write!(attrs, " fill-opacity=\"{}\"", num(color.a));
+    }
+    attrs
+}
+
+fn stroke(color: Color, width: f32) -> String {
+    let mut attrs = format!("stroke=\"{}\" stroke-width=\"{}\"", hex(color), num(width));
+    if color.a < 1.0 {
+        let _ = write!(attrs, " stroke-opacity=\"{}\"", num(color.a));
+    }
+    attrs
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn color_f32(color: ColorF32) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    hex(Color::new(channel(color.r), channel(color.g), channel(color.b), color.a))
+}
+
+/// `preserveAspectRatio` alignment along one axis, e.g. `xMid`.
+fn aspect_align(axis: &str, position: f32) -> String {
+    let place = if position <= 0.25 {
+        "Min"
+    } else if position >= 0.75 {
+        "Max"
+    } else {
+        "Mid"
+    };
+    format!("{axis}{place}")
+}
+
+/// For a repeating gradient, stretch the stops over the whole 0-1 range,
+/// returning them with how much of the gradient line one repetition covers.
+pub(crate) fn repeat_stops(stops: Vec<(f32, ColorF32)>, repeating: bool) -> (Vec<(f32, ColorF32)>, f32) {
+    let period = stops.last().map_or(1.0, |(offset, _)| *offset);
+    if !repeating || period <= 0.0 || period >= 1.0 {
+        return (stops, 1.0);
+    }
+    (stops.into_iter().map(|(offset, color)| (offset / period, color)).collect(), period)
+}
+
+/// The color `t` of the way along resolved stops.
+pub(crate) fn color_at(stops: &[(f32, ColorF32)], t: f32) -> ColorF32 {
+    let Some(next) = stops.iter().position(|(offset, _)| *offset >= t) else {
+        return stops.last().map_or(ColorF32::TRANSPARENT, |(_, color)| *color);
+    };
+    if next == 0 {
+        return stops[0].1;
+    }
+    let ((from, from_color), (to, to_color)) = (stops[next - 1], stops[next]);
+    if to <= from {
+        return to_color;
+    }
+    from_color.lerp(&to_color, (t - from) / (to - from))
+}
+
+/// The radii of a radial gradient's ending shape, as the renderer sizes it.
+pub(crate) fn radial_radii(rect: Rect, shape: RadialShape, size: RadialSize, center: (f32, f32)) -> (f32, f32) {
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+        .map(|(x, y): (f32, f32)| ((x - center.0).abs() * rect.width).hypot((y - center.1).abs() * rect.height));
+    let aspect = rect.width / rect.height.max(1.0);
+    let sides = |pick: fn(f32, f32) -> f32| {
+        let dx = pick(center.0, 1.0 - center.0) * rect.width;
+        let dy = pick(center.1, 1.0 - center.1) * rect.height;
+        match shape {
+            RadialShape::Circle => (pick(dx, dy), pick(dx, dy)),
+            RadialShape::Ellipse => (dx, dy),
+        }
+    };
+    let corner = |distance: f32| match shape {
+        RadialShape::Circle => (distance, distance),
+        RadialShape::Ellipse => (distance, distance / aspect),
+    };
+    match size {
+        RadialSize::ClosestSide => sides(f32::min),
+        RadialSize::FarthestSide => sides(f32::max),
+        RadialSize::ClosestCorner => corner(corners.into_iter().fold(f32::INFINITY, f32::min)),
+        RadialSize::FarthestCorner => corner(corners.into_iter().fold(0.0, f32::max)),
+        RadialSize::Explicit(rx, ry) => (rx, ry),
+    }
+}
+
+/// A number as short as it can be written to a hundredth.
+pub(crate) fn num(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    let text = format!("{:.2}", if rounded == 0.0 { 0.0 } else { rounded });
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_list_to_svg() {
+        let red = Color::from_rgb(255, 0, 0);
+        let commands = vec![
+            DisplayCommand::SolidColor(Color::WHITE, Rect::new(0.0, 0.0, 200.0, 100.0)),
+            DisplayCommand::PushClip(Rect::new(10.0, 10.0, 50.0, 50.0)),
+            DisplayCommand::RoundedRect {
+                color: Color::new(255, 0, 0, 0.5),
+                rect: Rect::new(10.0, 10.0, 40.0, 20.0),
+                radius: BorderRadius::uniform(4.0),
+            },
+            DisplayCommand::PopClip,
+            DisplayCommand::Text {
+                text: "a < b & c".into(),
+                x: 10.0,
+                y: 40.0,
+                color: red,
+                font_size: 10.0,
+                font_family: "Helvetica".into(),
+                font_weight: 700,
+                font_style: 0,
+            },
+            DisplayCommand::LinearGradient {
+                rect: Rect::new(0.0, 60.0, 100.0, 40.0),
+                direction: GradientDirection::ToRight,
+                stops: vec![ColorStop::new(red, Some(0.0)), ColorStop::new(Color::BLACK, Some(1.0))],
+                repeating: false,
+                border_radius: BorderRadius { top_left: 8.0, ..Default::default() },
+            },
+            // Left open; closed at the end
+            DisplayCommand::PushOpacity(0.5),
+        ];
+        let svg = to_svg(&commands, Rect::new(0.0, 0.0, 200.0, 100.0), &[]);
+
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"100\" viewBox=\"0 0 200 100\">"
+        ));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"200\" height=\"100\" fill=\"#ffffff\"/>"));
+        assert!(svg.contains("<clipPath id=\"clip1\"><rect x=\"10\" y=\"10\" width=\"50\" height=\"50\"/></clipPath>"));
+        assert!(svg.contains("<rect x=\"10\" y=\"10\" width=\"40\" height=\"20\" rx=\"4\" fill=\"#ff0000\" fill-opacity=\"0.5\"/>"));
+        // Baseline below the top of the text's box, and markup escaped
+        assert!(svg.contains(
+            "<text x=\"10\" y=\"48\" font-size=\"10\" font-family=\"Helvetica\" font-weight=\"700\" fill=\"#ff0000\">a &lt; b &amp; c</text>"
+        ));
+        // Left to right across the box, with only one corner rounded
+        assert!(svg.contains("x1=\"0\" y1=\"80\" x2=\"100\" y2=\"80\""));
+        assert!(svg.contains("<stop offset=\"1\" stop-color=\"#000000\"/>"));
+        assert!(svg.contains("<path d=\"M8,60 H100 A0,0 0 0 1 100,60"));
+        assert!(svg.contains("fill=\"url(#gradient2)\""));
+        assert_eq!(svg.matches("<g ").count(), svg.matches("</g>").count());
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_svg_numbers() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(0.125), "0.13");
+        assert_eq!(num(-0.001), "0");
+        assert_eq!(num(12.5), "12.5");
+    }
+}
@@ -0,0 +1,238 @@
+//! Golden-image visual regression suite.
+//!
+//! Each fixture in `tests/golden/` is rendered in a headless view and
+//! compared against the committed `<name>.png` next to it. Pixels are
+//! compared perceptually (YIQ color distance, as in pixelmatch), so
+//! differences below a fixture's `threshold` are ignored; the test fails
+//! when more than `max_diff_pixels` pixels differ. Fixtures and their
+//! tolerances are listed in `tests/golden/manifest.json`.
+//!
+//! ```bash
+//! # Run the suite
+//! cargo test -p rustkit-engine --features headless --test golden
+//!
+//! # Re-capture the goldens after an intended rendering change
+//! UPDATE_GOLDENS=1 cargo test -p rustkit-engine --features headless --test golden
+//! ```
+//!
+//! On failure the actual image and a diff (differing pixels in red over a
+//! faded copy of the golden) are written to the test's target temp dir.
+//! The suite skips itself when no GPU adapter is available.
+
+#![cfg(feature = "headless")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rustkit_codecs::{decode_png, encode_png, RgbaImage};
+use rustkit_engine::{EngineBuilder, EngineViewId};
+use rustkit_viewhost::Bounds;
+
+/// Set to re-capture goldens instead of comparing against them.
+const UPDATE_ENV: &str = "UPDATE_GOLDENS";
+
+/// Largest possible YIQ delta between two colors.
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+/// A manifest entry.
+#[derive(Debug)]
+struct Fixture {
+    name: String,
+    width: u32,
+    height: u32,
+    /// Perceptual distance (0-1) below which pixels count as equal.
+    threshold: f32,
+    /// Number of differing pixels tolerated.
+    max_diff_pixels: usize,
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn load_manifest() -> Vec<Fixture> {
+    let text = fs::read_to_string(golden_dir().join("manifest.json")).expect("read manifest.json");
+    let manifest: serde_json::Value = serde_json::from_str(&text).expect("parse manifest.json");
+    manifest["fixtures"]
+        .as_array()
+        .expect("manifest has a fixtures array")
+        .iter()
+        .map(|entry| Fixture {
+            name: entry["name"].as_str().expect("fixture name").to_string(),
+            width: entry["width"].as_u64().unwrap_or(400) as u32,
+            height: entry["height"].as_u64().unwrap_or(300) as u32,
+            threshold: entry["threshold"].as_f64().unwrap_or(0.1) as f32,
+            max_diff_pixels: entry["max_diff_pixels"].as_u64().unwrap_or(0) as usize,
+        })
+        .collect()
+}
+
+/// Parse a binary PPM as written by `Engine::capture_frame`.
+fn read_ppm(bytes: &[u8]) -> RgbaImage {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while fields.len() < 4 {
+        while bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let start = pos;
+        while !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        fields.push(std::str::from_utf8(&bytes[start..pos]).unwrap().to_string());
+    }
+    assert_eq!(fields[0], "P6", "not a binary PPM");
+    let width: u32 = fields[1].parse().unwrap();
+    let height: u32 = fields[2].parse().unwrap();
+    // A single whitespace byte separates the header from the pixels
+    let rgb = &bytes[pos + 1..];
+
+    let rgba = rgb.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect();
+    RgbaImage::from_rgba8(width, height, rgba).unwrap()
+}
+
+/// Render a fixture's HTML and capture the frame.
+fn render(engine: &mut rustkit_engine::Engine, view: EngineViewId, fixture: &Fixture) -> RgbaImage {
+    let html = fs::read_to_string(golden_dir().join(format!("{}.html", fixture.name)))
+        .unwrap_or_else(|e| panic!("read {}.html: {e}", fixture.name));
+    engine
+        .resize_view(view, bounds(fixture))
+        .unwrap_or_else(|e| panic!("{}: resize failed: {e}", fixture.name));
+    engine
+        .load_html(view, &html)
+        .unwrap_or_else(|e| panic!("{}: load failed: {e}", fixture.name));
+
+    let path = std::env::temp_dir().join(format!("rustkit-golden-{}-{}.ppm", std::process::id(), fixture.name));
+    engine
+        .capture_frame(view, path.to_str().unwrap())
+        .unwrap_or_else(|e| panic!("{}: capture failed: {e}", fixture.name));
+    let bytes = fs::read(&path).unwrap();
+    let _ = fs::remove_file(&path);
+    read_ppm(&bytes)
+}
+
+fn bounds(fixture: &Fixture) -> Bounds {
+    Bounds {
+        x: 0,
+        y: 0,
+        width: fixture.width,
+        height: fixture.height,
+    }
+}
+
+/// Perceptual distance between two RGB colors, as a YIQ delta.
+fn yiq_delta(a: &[u8], b: &[u8]) -> f32 {
+    let yiq = |p: &[u8]| {
+        let (r, g, b) = (p[0] as f32, p[1] as f32, p[2] as f32);
+        (
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+            r * 0.595_978 - g * 0.274_176_1 - b * 0.321_801_9,
+            r * 0.211_470_2 - g * 0.522_617_1 + b * 0.311_146_9,
+        )
+    };
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    0.5053 * (y1 - y2).powi(2) + 0.299 * (i1 - i2).powi(2) + 0.1957 * (q1 - q2).powi(2)
+}
+
+/// Compare two images, returning the number of differing pixels and a
+/// diff image. Images of different sizes differ everywhere.
+fn compare(actual: &RgbaImage, golden: &RgbaImage, threshold: f32) -> (usize, RgbaImage) {
+    let mut diff = RgbaImage::new(golden.width(), golden.height());
+    if (actual.width(), actual.height()) != (golden.width(), golden.height()) {
+        let pixels = (actual.width() as usize * actual.height() as usize).max(golden.data().len() / 4);
+        return (pixels, diff);
+    }
+
+    let max_delta = MAX_YIQ_DELTA * threshold * threshold;
+    let mut differing = 0;
+    for ((a, g), out) in actual
+        .data()
+        .chunks_exact(4)
+        .zip(golden.data().chunks_exact(4))
+        .zip(diff.data_mut().chunks_exact_mut(4))
+    {
+        if yiq_delta(a, g) > max_delta {
+            differing += 1;
+            out.copy_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (g[0] as f32 * 0.299 + g[1] as f32 * 0.587 + g[2] as f32 * 0.114) as u8;
+            let faded = 255 - (255 - luma) / 4;
+            out.copy_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+    (differing, diff)
+}
+
+#[test]
+fn test_golden_images() {
+    let mut engine = match EngineBuilder::new().javascript_enabled(false).build() {
+        Ok(engine) => engine,
+        Err(_) => {
+            eprintln!("Skipping golden suite: GPU not available");
+            return;
+        }
+    };
+    let fixtures = load_manifest();
+    let first = fixtures.first().expect("manifest lists fixtures");
+    let view = engine.create_headless_view(bounds(first)).expect("create headless view");
+
+    let update = std::env::var_os(UPDATE_ENV).is_some_and(|v| !v.is_empty() && v != "0");
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    let mut failures = Vec::new();
+
+    for fixture in &fixtures {
+        let actual = render(&mut engine, view, fixture);
+        let golden_path = golden_dir().join(format!("{}.png", fixture.name));
+
+        if update {
+            fs::write(&golden_path, encode_png(&actual).unwrap()).unwrap();
+            eprintln!("Updated {}", golden_path.display());
+            continue;
+        }
+
+        let golden = match fs::read(&golden_path) {
+            Ok(bytes) => decode_png(&bytes).unwrap(),
+            Err(_) => {
+                failures.push(format!("{}: no golden (run with {UPDATE_ENV}=1)", fixture.name));
+                continue;
+            }
+        };
+
+        let (differing, diff) = compare(&actual, &golden, fixture.threshold);
+        if differing > fixture.max_diff_pixels {
+            fs::create_dir_all(&out_dir).unwrap();
+            let actual_path = out_dir.join(format!("{}.actual.png", fixture.name));
+            let diff_path = out_dir.join(format!("{}.diff.png", fixture.name));
+            fs::write(&actual_path, encode_png(&actual).unwrap()).unwrap();
+            fs::write(&diff_path, encode_png(&diff).unwrap()).unwrap();
+            failures.push(format!(
+                "{}: {differing} pixels differ (max {}); see {}",
+                fixture.name,
+                fixture.max_diff_pixels,
+                diff_path.display()
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "golden mismatches:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_compare_tolerance() {
+    let solid = |rgb: [u8; 3]| {
+        let data = [rgb[0], rgb[1], rgb[2], 255].repeat(4);
+        RgbaImage::from_rgba8(2, 2, data).unwrap()
+    };
+    let golden = solid([200, 40, 40]);
+
+    assert_eq!(compare(&solid([200, 40, 40]), &golden, 0.0).0, 0);
+    // A slight shade change passes the default threshold but not a zero one
+    assert_eq!(compare(&solid([203, 42, 40]), &golden, 0.1).0, 0);
+    assert_eq!(compare(&solid([203, 42, 40]), &golden, 0.0).0, 4);
+    assert_eq!(compare(&solid([40, 40, 200]), &golden, 0.1).0, 4);
+
+    let (differing, diff) = compare(&RgbaImage::new(1, 1), &golden, 0.1);
+    assert_eq!(differing, 4);
+    assert_eq!((diff.width(), diff.height()), (2, 2));
+}
@@ -1103,8 +1103,10 @@ impl<S: TreeSink> TreeBuilder<S> {
     fn handle_in_table(&mut self, token: Token) -> ParseResult<()> {
         match &token {
             Token::Character(ch) if ch.is_whitespace() => {
-                // Switch to InTableText mode to accumulate whitespace
-                self.original_mode = Some(InsertionMode::InTable);
+                // Switch to InTableText mode to accumulate whitespace; table
+                // body and row modes delegate here, so return to whichever
+                // mode was active rather than always InTable.
+                self.original_mode = Some(self.mode);
                 self.mode = InsertionMode::InTableText;
                 self.pending_table_chars.push(*ch);
             }
@@ -2336,5 +2338,18 @@ mod tests {
         // Should recover gracefully
         assert!(!result.events.is_empty());
     }
+
+    #[test]
+    fn test_whitespace_between_rows_keeps_single_tbody() {
+        let html = "<table>\n  <tr><td>1</td></tr>\n  <tr><td>2</td></tr>\n</table>";
+        let tokens = tokenize(html).unwrap();
+        let sink = TestSink::new();
+        let result = build_tree(tokens, sink).unwrap();
+
+        let tbodies = result.events.iter().filter(|e| *e == "start:tbody").count();
+        let rows = result.events.iter().filter(|e| *e == "start:tr").count();
+        assert_eq!(tbodies, 1);
+        assert_eq!(rows, 2);
+    }
 }
 
@@ -6,6 +6,7 @@
 //! eliminating the need for reqwest and its transitive dependencies.
 
 use std::io::{self, Write};
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -25,6 +26,9 @@ pub enum HttpError {
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
 
+    #[error("DNS lookup failed: {0}")]
+    DnsFailed(String),
+
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
 
@@ -234,10 +238,7 @@ impl Client {
         headers: &HeaderMap,
         body: &Option<Bytes>,
     ) -> Result<RawResponse, HttpError> {
-        let addr = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(&addr)
-            .await
-            .map_err(|e| HttpError::ConnectionFailed(e.to_string()))?;
+        let stream = connect(host, port).await?;
 
         let tls_stream = self
             .tls_connector
@@ -259,10 +260,7 @@ impl Client {
         headers: &HeaderMap,
         body: &Option<Bytes>,
     ) -> Result<RawResponse, HttpError> {
-        let addr = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(&addr)
-            .await
-            .map_err(|e| HttpError::ConnectionFailed(e.to_string()))?;
+        let stream = connect(host, port).await?;
 
         self.send_request(stream, host, method, url, headers, body)
             .await
@@ -295,7 +293,10 @@ impl Client {
         let mut request = Vec::new();
         writeln!(request, "{} {} HTTP/1.1\r", method, path)?;
         writeln!(request, "Host: {}\r", host)?;
-        writeln!(request, "User-Agent: {}\r", self.config.user_agent)?;
+        // Per-request User-Agent headers take precedence over the client default
+        if !headers.contains_key("user-agent") {
+            writeln!(request, "User-Agent: {}\r", self.config.user_agent)?;
+        }
         writeln!(request, "Accept: */*\r")?;
         writeln!(request, "Connection: close\r")?;
 
@@ -434,6 +435,29 @@ impl Default for ClientBuilder {
     }
 }
 
+/// Resolve `host` and connect to the first address that accepts, so a
+/// failed lookup reports as [`HttpError::DnsFailed`] rather than a
+/// connection failure.
+async fn connect(host: &str, port: u16) -> Result<TcpStream, HttpError> {
+    let addrs = resolved_addrs(host, tokio::net::lookup_host((host, port)).await)?;
+    TcpStream::connect(addrs.as_slice())
+        .await
+        .map_err(|e| HttpError::ConnectionFailed(e.to_string()))
+}
+
+/// The addresses a lookup of `host` found, or [`HttpError::DnsFailed`] if
+/// it failed or found none.
+fn resolved_addrs(
+    host: &str,
+    lookup: io::Result<impl Iterator<Item = SocketAddr>>,
+) -> Result<Vec<SocketAddr>, HttpError> {
+    let addrs: Vec<_> = lookup.map_err(|e| HttpError::DnsFailed(format!("{host}: {e}")))?.collect();
+    if addrs.is_empty() {
+        return Err(HttpError::DnsFailed(format!("{host}: no addresses")));
+    }
+    Ok(addrs)
+}
+
 /// Parse HTTP status line.
 fn parse_status_line(line: &str) -> Result<(Version, StatusCode), HttpError> {
     let parts: Vec<&str> = line.split_whitespace().collect();
@@ -569,10 +593,7 @@ impl Client {
         port: u16,
         url: &Url,
     ) -> Result<StreamingResponse, HttpError> {
-        let addr = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(&addr)
-            .await
-            .map_err(|e| HttpError::ConnectionFailed(e.to_string()))?;
+        let stream = connect(host, port).await?;
 
         let tls_stream = self
             .tls_connector
@@ -589,10 +610,7 @@ impl Client {
         port: u16,
         url: &Url,
     ) -> Result<StreamingResponse, HttpError> {
-        let addr = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(&addr)
-            .await
-            .map_err(|e| HttpError::ConnectionFailed(e.to_string()))?;
+        let stream = connect(host, port).await?;
 
         self.send_streaming_request(stream, host, url).await
     }
@@ -766,6 +784,19 @@ mod tests {
         assert_eq!(response.text().unwrap(), "Hello");
     }
 
+    #[test]
+    fn test_failed_lookup_is_dns_failure() {
+        let failed = io::Error::new(io::ErrorKind::NotFound, "no such host");
+        let result = resolved_addrs("unresolvable.invalid", Err::<std::iter::Empty<SocketAddr>, _>(failed));
+        assert!(matches!(result, Err(HttpError::DnsFailed(_))), "{result:?}");
+
+        let result = resolved_addrs("empty.invalid", Ok(std::iter::empty()));
+        assert!(matches!(result, Err(HttpError::DnsFailed(_))), "{result:?}");
+
+        let addr = SocketAddr::from(([127, 0, 0, 1], 80));
+        assert_eq!(resolved_addrs("localhost", Ok(std::iter::once(addr))).unwrap(), [addr]);
+    }
+
     #[test]
     fn test_default_config() {
         let config = ClientConfig2::default();
@@ -0,0 +1,196 @@
+//! Image decode worker pool
+//!
+//! Decoding a large image takes long enough to drop frames, so images are
+//! decoded on a few worker threads instead of the thread that asked for
+//! them. Decodes of images in view run before those of images out of view,
+//! each in the order they were queued.
+
+use std::collections::BinaryHeap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+
+use tracing::warn;
+
+/// Which decodes run first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DecodePriority {
+    /// The image is out of view, or not laid out yet.
+    Offscreen,
+    /// The image is in view.
+    Visible,
+}
+
+/// A queued decode.
+struct Job {
+    priority: DecodePriority,
+    /// Order the job was queued in.
+    sequence: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    /// Higher priority first, then earlier queued first.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: BinaryHeap<Job>,
+    next_sequence: u64,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+/// Worker threads running decode jobs by priority.
+///
+/// Threads start with the first job. Dropping the pool drops the jobs
+/// still queued; running ones finish.
+pub struct DecodePool {
+    shared: Arc<Shared>,
+    threads: usize,
+    started: OnceLock<()>,
+}
+
+impl DecodePool {
+    /// Create a pool of `threads` workers (at least one).
+    pub fn new(threads: usize) -> Self {
+        Self {
+            shared: Arc::default(),
+            threads: threads.max(1),
+            started: OnceLock::new(),
+        }
+    }
+
+    /// Create a pool sized for the machine: a worker per core, up to four.
+    pub fn with_default_threads() -> Self {
+        let cores = thread::available_parallelism().map_or(2, |cores| cores.get());
+        Self::new(cores.min(4))
+    }
+
+    /// Queue `job` to run on a worker.
+    pub fn spawn(&self, priority: DecodePriority, job: impl FnOnce() + Send + 'static) {
+        self.started.get_or_init(|| self.start_workers());
+        let mut queue = self.shared.queue.lock().unwrap();
+        let sequence = queue.next_sequence;
+        queue.next_sequence += 1;
+        queue.jobs.push(Job {
+            priority,
+            sequence,
+            run: Box::new(job),
+        });
+        drop(queue);
+        self.shared.available.notify_one();
+    }
+
+    /// Number of jobs waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.shared.queue.lock().unwrap().jobs.len()
+    }
+
+    fn start_workers(&self) {
+        for index in 0..self.threads {
+            let shared = self.shared.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("rustkit-image-decode-{index}"))
+                .spawn(move || run_worker(&shared));
+            if let Err(e) = spawned {
+                warn!("Failed to start image decode thread: {}", e);
+            }
+        }
+    }
+}
+
+impl Default for DecodePool {
+    fn default() -> Self {
+        Self::with_default_threads()
+    }
+}
+
+impl Drop for DecodePool {
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.closed = true;
+        queue.jobs.clear();
+        drop(queue);
+        self.shared.available.notify_all();
+    }
+}
+
+/// Run jobs until the pool is dropped.
+fn run_worker(shared: &Shared) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.closed {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop() {
+                    break job;
+                }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+        // A decoder panicking on a bad image mustn't take the worker down
+        if catch_unwind(AssertUnwindSafe(job.run)).is_err() {
+            warn!("Image decode job panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_visible_decodes_run_first() {
+        let pool = DecodePool::new(1);
+        let (tx, rx) = mpsc::channel();
+
+        // Hold the only worker until everything is queued
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        pool.spawn(DecodePriority::Offscreen, move || {
+            let _ = release_rx.recv();
+        });
+        for (name, priority) in [
+            ("offscreen 1", DecodePriority::Offscreen),
+            ("visible 1", DecodePriority::Visible),
+            ("offscreen 2", DecodePriority::Offscreen),
+            ("visible 2", DecodePriority::Visible),
+        ] {
+            let tx = tx.clone();
+            pool.spawn(priority, move || tx.send(name).unwrap());
+        }
+        pool.spawn(DecodePriority::Visible, || panic!("bad image"));
+        release_tx.send(()).unwrap();
+
+        let order: Vec<_> = (0..4).map(|_| rx.recv().unwrap()).collect();
+        assert_eq!(order, ["visible 1", "visible 2", "offscreen 1", "offscreen 2"]);
+        assert_eq!(pool.queued(), 0);
+    }
+}
@@ -4,14 +4,15 @@
 //!
 //! This crate handles:
 //! - Async image fetching from URLs
+//! - Decoding on worker threads, images in view first
 //! - Decoding of PNG, JPEG, GIF, WebP, BMP, and ICO formats
 //! - Animated GIF support
 //! - Memory and disk caching
 //! - GPU texture management
 //! - Lazy loading support
 
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 use rustkit_codecs::{Decoded, ImageFormat, RgbaImage};
@@ -22,10 +23,12 @@ use url::Url;
 
 pub mod cache;
 pub mod decode;
+pub mod decode_pool;
 pub mod loader;
 
 pub use cache::*;
 pub use decode::*;
+pub use decode_pool::*;
 pub use loader::*;
 
 /// Errors that can occur during image operations
@@ -80,6 +83,9 @@ pub struct LoadedImage {
     /// Content type from HTTP response
     pub content_type: Option<String>,
 
+    /// Size of the HTTP response body, 0 for data URLs
+    pub encoded_size: usize,
+
     /// Whether this image is complete (loaded successfully)
     pub complete: bool,
 }
@@ -96,6 +102,7 @@ impl LoadedImage {
             data: ImageData::Static(image),
             decoded_at: Instant::now(),
             content_type: None,
+            encoded_size: 0,
             complete: true,
         }
     }
@@ -118,6 +125,7 @@ impl LoadedImage {
             }),
             decoded_at: Instant::now(),
             content_type: None,
+            encoded_size: 0,
             complete: true,
         }
     }
@@ -135,6 +143,21 @@ impl LoadedImage {
         matches!(self.data, ImageData::Animated(_))
     }
 
+    /// Whether the image was decoded smaller than its natural size
+    pub fn is_downscaled(&self) -> bool {
+        let frame = self.current_frame(Duration::ZERO);
+        frame.width() < self.natural_width || frame.height() < self.natural_height
+    }
+
+    /// Whether the decoded image has the pixels to draw at `width`x`height`
+    /// device pixels without being scaled up, or all the pixels it has
+    pub fn covers(&self, width: u32, height: u32) -> bool {
+        let (needed_width, needed_height) =
+            rustkit_codecs::scaled_size((self.natural_width, self.natural_height), (width, height));
+        let frame = self.current_frame(Duration::ZERO);
+        frame.width() >= needed_width && frame.height() >= needed_height
+    }
+
     /// Get the aspect ratio
     pub fn aspect_ratio(&self) -> f64 {
         if self.natural_height == 0 {
@@ -279,6 +302,15 @@ impl ImageRequest {
     }
 }
 
+/// An image loaded or decoded again in the background
+pub struct DecodedImage {
+    /// URL of the image
+    pub url: Url,
+
+    /// The image, or why it couldn't be loaded
+    pub result: Result<Arc<LoadedImage>, String>,
+}
+
 /// The main image manager that handles loading and caching
 pub struct ImageManager {
     /// Memory cache for decoded images
@@ -291,6 +323,21 @@ pub struct ImageManager {
     #[allow(clippy::type_complexity)]
     pending: Arc<RwLock<HashMap<Url, Vec<oneshot::Sender<ImageResult<Arc<LoadedImage>>>>>>>,
 
+    /// Encoded bytes of images decoded below their natural size, kept to
+    /// decode them again larger
+    sources: Arc<RwLock<HashMap<Url, Arc<[u8]>>>>,
+
+    /// Worker threads decoding images
+    decoder: DecodePool,
+
+    /// Images loading in the background, reported to `take_decoded` when
+    /// they finish
+    reported: Arc<Mutex<HashSet<Url>>>,
+
+    /// Images loaded in the background, for `take_decoded`
+    decoded_tx: mpsc::UnboundedSender<DecodedImage>,
+    decoded_rx: Mutex<mpsc::UnboundedReceiver<DecodedImage>>,
+
     /// Channel for sending load requests
     request_tx: mpsc::Sender<ImageRequest>,
 
@@ -306,6 +353,7 @@ impl ImageManager {
     /// Create a new image manager
     pub fn new() -> Self {
         let (request_tx, _request_rx) = mpsc::channel::<ImageRequest>(100);
+        let (decoded_tx, decoded_rx) = mpsc::unbounded_channel();
 
         Self {
             cache: Arc::new(RwLock::new(ImageCache::new(100))),
@@ -314,6 +362,11 @@ impl ImageManager {
                 .build()
                 .expect("Failed to create HTTP client"),
             pending: Arc::new(RwLock::new(HashMap::new())),
+            sources: Arc::new(RwLock::new(HashMap::new())),
+            decoder: DecodePool::default(),
+            reported: Arc::new(Mutex::new(HashSet::new())),
+            decoded_tx,
+            decoded_rx: Mutex::new(decoded_rx),
             request_tx,
             max_dimensions: (16384, 16384),
             max_cache_bytes: 256 * 1024 * 1024, // 256MB
@@ -322,82 +375,195 @@ impl ImageManager {
 
     /// Load an image from a URL
     pub async fn load(&self, url: Url) -> ImageResult<Arc<LoadedImage>> {
-        // Check cache first
-        if let Some(cached) = self.cache.read().unwrap().get(&url) {
-            debug!("Image cache hit: {}", url);
-            return Ok(cached);
+        self.load_decoded(url, None).await
+    }
+
+    /// Load an image to draw at `width`x`height` device pixels, decoding
+    /// it no larger than that needs. Asking for a larger size than the
+    /// image was decoded at decodes it again.
+    pub async fn load_to_size(&self, url: Url, width: u32, height: u32) -> ImageResult<Arc<LoadedImage>> {
+        if let Some(source) = self.source_to_redecode(&url, width, height) {
+            if self.claim_load(&url) {
+                self.queue_decode(url.clone(), source, Some((width, height)), DecodePriority::Visible);
+            }
         }
+        self.load_decoded(url, Some((width, height))).await
+    }
 
-        // Check if already loading
-        let already_loading = {
-            let pending = self.pending.read().unwrap();
-            pending.contains_key(&url)
-        };
+    /// Load an image without waiting for it to decode, no larger than
+    /// drawing it at `size` device pixels needs if given. An image cached
+    /// too small for `size` is decoded again. Returns whether the image is
+    /// loading; [`take_decoded`](Self::take_decoded) reports it once it's
+    /// cached or has failed.
+    ///
+    /// Only fetching the image is awaited; it decodes on worker threads,
+    /// after the images queued with a higher `priority`.
+    pub async fn load_in_background(&self, url: Url, size: Option<(u32, u32)>, priority: DecodePriority) -> bool {
+        if self.is_cached(&url) {
+            return size.is_some_and(|(width, height)| self.redecode_in_background(&url, width, height, priority));
+        }
+        self.reported.lock().unwrap().insert(url.clone());
+        if self.claim_load(&url) {
+            debug!("Starting image load: {}", url);
+            self.fetch_and_decode(url, size, priority).await;
+        }
+        true
+    }
 
-        if already_loading {
-            debug!("Image already loading: {}", url);
-            // Add ourselves to the waiting list
-            let (tx, rx) = oneshot::channel();
-            self.pending.write().unwrap().entry(url.clone()).or_default().push(tx);
-            return rx.await.map_err(|_| ImageError::FetchError("Load cancelled".into()))?;
+    /// Decode a cached image again on the worker threads if it was decoded
+    /// smaller than drawing it at `width`x`height` device pixels needs. The
+    /// larger one replaces it once decoded. Returns whether the image is
+    /// loading; [`take_decoded`](Self::take_decoded) reports it once it's
+    /// done.
+    pub fn redecode_in_background(&self, url: &Url, width: u32, height: u32, priority: DecodePriority) -> bool {
+        if self.is_loading(url) {
+            self.reported.lock().unwrap().insert(url.clone());
+            return true;
+        }
+        let Some(source) = self.source_to_redecode(url, width, height) else {
+            return false;
+        };
+        self.reported.lock().unwrap().insert(url.clone());
+        if self.claim_load(url) {
+            self.queue_decode(url.clone(), source, Some((width, height)), priority);
         }
+        true
+    }
 
-        // Start loading
-        debug!("Starting image load: {}", url);
-        self.pending.write().unwrap().insert(url.clone(), vec![]);
+    /// Take the images loaded or decoded again in the background that
+    /// finished since the last call.
+    pub fn take_decoded(&self) -> Vec<DecodedImage> {
+        let mut decoded_rx = self.decoded_rx.lock().unwrap();
+        let mut decoded = Vec::new();
+        while let Ok(image) = decoded_rx.try_recv() {
+            decoded.push(image);
+        }
+        decoded
+    }
 
-        let result = self.fetch_and_decode(url.clone()).await;
+    /// The encoded bytes of a cached image decoded smaller than drawing it
+    /// at `width`x`height` device pixels needs
+    fn source_to_redecode(&self, url: &Url, width: u32, height: u32) -> Option<Arc<[u8]>> {
+        let cached = self.get_cached(url)?;
+        if cached.covers(width, height) {
+            return None;
+        }
+        self.sources.read().unwrap().get(url).cloned()
+    }
 
-        // Notify waiters and cache result
-        let waiters = self.pending.write().unwrap().remove(&url).unwrap_or_default();
-        
-        match &result {
-            Ok(image) => {
-                self.cache.write().unwrap().insert(url.clone(), image.clone());
-                for waiter in waiters {
-                    let _ = waiter.send(Ok(image.clone()));
-                }
-            }
-            Err(e) => {
-                let err_msg = e.to_string();
-                for waiter in waiters {
-                    let _ = waiter.send(Err(ImageError::FetchError(err_msg.clone())));
-                }
+    /// Load an image, decoding it for drawing at `target` size if given,
+    /// or wait for the load or decode of it in progress
+    async fn load_decoded(&self, url: Url, target: Option<(u32, u32)>) -> ImageResult<Arc<LoadedImage>> {
+        let (tx, rx) = oneshot::channel();
+        let claimed = {
+            let mut pending = self.pending.write().unwrap();
+            if let Some(waiters) = pending.get_mut(&url) {
+                debug!("Image already loading: {}", url);
+                waiters.push(tx);
+                false
+            } else if let Some(cached) = self.cache.read().unwrap().get(&url) {
+                debug!("Image cache hit: {}", url);
+                return Ok(cached);
+            } else {
+                pending.insert(url.clone(), vec![tx]);
+                true
             }
+        };
+
+        if claimed {
+            debug!("Starting image load: {}", url);
+            self.fetch_and_decode(url, target, DecodePriority::Visible).await;
         }
+        rx.await.map_err(|_| ImageError::FetchError("Load cancelled".into()))?
+    }
 
-        result
+    /// Take on loading an image, unless it's already loading. Whoever
+    /// takes it on must finish it, so that its waiters hear back.
+    fn claim_load(&self, url: &Url) -> bool {
+        let mut pending = self.pending.write().unwrap();
+        if pending.contains_key(url) {
+            return false;
+        }
+        pending.insert(url.clone(), vec![]);
+        true
     }
 
-    /// Fetch and decode an image
-    async fn fetch_and_decode(&self, url: Url) -> ImageResult<Arc<LoadedImage>> {
-        // Handle data URLs
+    /// Fetch a claimed image and queue it for decoding
+    async fn fetch_and_decode(&self, url: Url, target: Option<(u32, u32)>, priority: DecodePriority) {
+        // Inline images are part of the page, so they're already here and
+        // small
         if url.scheme() == "data" {
-            return self.decode_data_url(&url);
+            let result = self.decode_data_url(&url);
+            self.sink().finish(&url, None, result);
+            return;
         }
 
         // Fetch the image using rustkit-http
-        let response = self.client.get(url.as_str()).await?;
-
-        if !response.is_success() {
-            return Err(ImageError::FetchError(format!(
-                "HTTP {} for {}",
-                response.status,
-                url
-            )));
-        }
+        let response = match self.client.get(url.as_str()).await {
+            Ok(response) if response.is_success() => response,
+            Ok(response) => {
+                let error = ImageError::FetchError(format!("HTTP {} for {}", response.status, url));
+                self.sink().finish(&url, None, Err(error));
+                return;
+            }
+            Err(e) => {
+                self.sink().finish(&url, None, Err(e.into()));
+                return;
+            }
+        };
 
         let content_type = response.content_type().map(|s| s.to_string());
+        self.queue_decode_as(url, Arc::from(response.body.as_ref()), content_type, target, priority);
+    }
 
-        // Decode the image
-        let mut loaded = self.decode_bytes(&url, &response.body)?;
-        loaded.content_type = content_type;
+    /// Queue a claimed image, cached with the source it's decoded from
+    /// again, to decode on the worker threads
+    fn queue_decode(&self, url: Url, source: Arc<[u8]>, target: Option<(u32, u32)>, priority: DecodePriority) {
+        debug!("Decoding {} again for {:?}", url, target);
+        let content_type = self.get_cached(&url).and_then(|cached| cached.content_type.clone());
+        self.queue_decode_as(url, source, content_type, target, priority);
+    }
 
-        Ok(Arc::new(loaded))
+    /// Queue a claimed image to decode on the worker threads
+    fn queue_decode_as(
+        &self,
+        url: Url,
+        source: Arc<[u8]>,
+        content_type: Option<String>,
+        target: Option<(u32, u32)>,
+        priority: DecodePriority,
+    ) {
+        let sink = self.sink();
+        let max_dimensions = self.max_dimensions;
+        self.decoder.spawn(priority, move || {
+            let result = Self::decode_bytes(&url, &source, target, max_dimensions).map(|mut loaded| {
+                loaded.content_type = content_type;
+                loaded.encoded_size = source.len();
+                Arc::new(loaded)
+            });
+            sink.finish(&url, Some(source), result);
+        });
+    }
+
+    /// Where this manager's finished loads go
+    fn sink(&self) -> LoadSink {
+        LoadSink {
+            cache: self.cache.clone(),
+            pending: self.pending.clone(),
+            sources: self.sources.clone(),
+            reported: self.reported.clone(),
+            decoded_tx: self.decoded_tx.clone(),
+        }
     }
 
-    /// Decode image from bytes
-    fn decode_bytes(&self, url: &Url, bytes: &[u8]) -> ImageResult<LoadedImage> {
+    /// Decode image from bytes, no larger than drawing it at `target` size
+    /// needs if given
+    fn decode_bytes(
+        url: &Url,
+        bytes: &[u8],
+        target: Option<(u32, u32)>,
+        max_dimensions: (u32, u32),
+    ) -> ImageResult<LoadedImage> {
         // Guess format from bytes
         let format = rustkit_codecs::detect_format(bytes)
             .unwrap_or(ImageFormat::Unknown);
@@ -408,12 +574,25 @@ impl ImageManager {
 
         // Handle animated GIFs specially
         if format == ImageFormat::Gif {
-            return self.decode_gif(url, bytes);
+            return Self::decode_gif(url, bytes, max_dimensions);
         }
 
-        // Decode static image
-        let decoded = rustkit_codecs::decode_any(bytes)
+        // Check dimensions before decoding. JPEGs scale while they decode,
+        // so only their decoded size has to fit
+        let natural = rustkit_codecs::dimensions(bytes)
             .map_err(|e| ImageError::DecodeError(e.to_string()))?;
+        let decoded_size = target.map_or(natural, |target| rustkit_codecs::scaled_size(natural, target));
+        let (width, height) = if format == ImageFormat::Jpeg { decoded_size } else { natural };
+        if width > max_dimensions.0 || height > max_dimensions.1 {
+            return Err(ImageError::TooLarge { width, height });
+        }
+
+        // Decode static image
+        let decoded = match target {
+            Some(target) => rustkit_codecs::decode_scaled(bytes, target),
+            None => rustkit_codecs::decode_any(bytes),
+        }
+        .map_err(|e| ImageError::DecodeError(e.to_string()))?;
         let img = match decoded {
             Decoded::Static(img) => img,
             Decoded::Animated(frames) => {
@@ -426,24 +605,20 @@ impl ImageManager {
             }
         };
 
-        // Check dimensions
-        let (width, height) = (img.width(), img.height());
-        if width > self.max_dimensions.0 || height > self.max_dimensions.1 {
-            return Err(ImageError::TooLarge { width, height });
-        }
-
-        Ok(LoadedImage::new(url.clone(), img))
+        let mut loaded = LoadedImage::new(url.clone(), img);
+        (loaded.natural_width, loaded.natural_height) = natural;
+        Ok(loaded)
     }
 
     /// Decode an animated GIF
-    fn decode_gif(&self, url: &Url, bytes: &[u8]) -> ImageResult<LoadedImage> {
+    fn decode_gif(url: &Url, bytes: &[u8], max_dimensions: (u32, u32)) -> ImageResult<LoadedImage> {
         let decoded_frames = rustkit_codecs::decode_gif(bytes)
             .map_err(|e| ImageError::DecodeError(e.to_string()))?;
 
         let mut frames = Vec::with_capacity(decoded_frames.len());
         for f in decoded_frames {
             // Check dimensions
-            if f.image.width() > self.max_dimensions.0 || f.image.height() > self.max_dimensions.1 {
+            if f.image.width() > max_dimensions.0 || f.image.height() > max_dimensions.1 {
                 return Err(ImageError::TooLarge {
                     width: f.image.width(),
                     height: f.image.height(),
@@ -469,6 +644,7 @@ impl ImageManager {
                 data: ImageData::Static(frame.image),
                 decoded_at: Instant::now(),
                 content_type: Some("image/gif".into()),
+                encoded_size: 0,
                 complete: true,
             });
         }
@@ -509,7 +685,7 @@ impl ImageManager {
             return self.rasterize_svg(url, &svg_text);
         }
 
-        let loaded = self.decode_bytes(url, &bytes)?;
+        let loaded = Self::decode_bytes(url, &bytes, None, self.max_dimensions)?;
         Ok(Arc::new(loaded))
     }
 
@@ -588,6 +764,7 @@ impl ImageManager {
     /// Clear the cache
     pub fn clear_cache(&self) {
         self.cache.write().unwrap().clear();
+        self.sources.write().unwrap().clear();
     }
 
     /// Get cache statistics
@@ -647,6 +824,63 @@ impl Default for ImageManager {
     }
 }
 
+/// Where loads finish, on whichever thread they finish on: the cache, and
+/// whoever waits for them
+struct LoadSink {
+    cache: Arc<RwLock<ImageCache>>,
+    #[allow(clippy::type_complexity)]
+    pending: Arc<RwLock<HashMap<Url, Vec<oneshot::Sender<ImageResult<Arc<LoadedImage>>>>>>>,
+    sources: Arc<RwLock<HashMap<Url, Arc<[u8]>>>>,
+    reported: Arc<Mutex<HashSet<Url>>>,
+    decoded_tx: mpsc::UnboundedSender<DecodedImage>,
+}
+
+impl LoadSink {
+    /// Cache a finished load of `url`, decoded from `source`, and hand it
+    /// to whoever waits for it. An image that fails to decode again larger
+    /// keeps its cached decode.
+    fn finish(&self, url: &Url, source: Option<Arc<[u8]>>, result: ImageResult<Arc<LoadedImage>>) {
+        let result = match result {
+            Ok(image) => {
+                self.cache.write().unwrap().insert(url.clone(), image.clone());
+                if let Some(source) = source {
+                    self.keep_source(url, source, &image);
+                }
+                Ok(image)
+            }
+            Err(e) => match self.cache.read().unwrap().get(url) {
+                Some(cached) => {
+                    debug!("Failed to decode {} again: {}", url, e);
+                    Ok(cached)
+                }
+                None => Err(e.to_string()),
+            },
+        };
+
+        let waiters = self.pending.write().unwrap().remove(url).unwrap_or_default();
+        for waiter in waiters {
+            let _ = waiter.send(result.clone().map_err(ImageError::FetchError));
+        }
+        if self.reported.lock().unwrap().remove(url) {
+            let _ = self.decoded_tx.send(DecodedImage { url: url.clone(), result });
+        }
+    }
+
+    /// Keep the bytes of an image decoded below its natural size, to decode
+    /// it again larger, or drop them once it's decoded in full
+    fn keep_source(&self, url: &Url, source: Arc<[u8]>, image: &LoadedImage) {
+        let mut sources = self.sources.write().unwrap();
+        if image.is_downscaled() {
+            sources.insert(url.clone(), source);
+        } else {
+            sources.remove(url);
+        }
+        // Bytes of images evicted from the cache aren't needed any more
+        let cache = self.cache.read().unwrap();
+        sources.retain(|kept, _| kept == url || cache.contains(kept));
+    }
+}
+
 /// CSS object-fit values
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ObjectFit {
@@ -1065,5 +1299,38 @@ mod tests {
         assert!((rect.width - 100.0).abs() < 0.001);
         assert!((rect.height - 50.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_decode_to_size() {
+        let manager = ImageManager::new();
+        let url = Url::parse("https://example.com/photo.png").unwrap();
+        let bytes: Arc<[u8]> = rustkit_codecs::encode_png(&RgbaImage::new(400, 200)).unwrap().into();
+
+        let image = ImageManager::decode_bytes(&url, &bytes, Some((100, 100)), manager.max_dimensions).unwrap();
+        assert_eq!((image.natural_width, image.natural_height), (400, 200));
+        assert_eq!(image.current_frame(Duration::ZERO).width(), 200);
+        assert!(image.is_downscaled());
+        assert!(image.covers(100, 100) && !image.covers(300, 150));
+        manager.sink().finish(&url, Some(bytes), Ok(Arc::new(image)));
+        assert!(manager.sources.read().unwrap().contains_key(&url));
+
+        // Drawing larger decodes it again on a worker, in full here
+        assert!(!manager.redecode_in_background(&url, 150, 50, DecodePriority::Visible));
+        assert!(manager.redecode_in_background(&url, 800, 800, DecodePriority::Visible));
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let decoded = loop {
+            if let Some(decoded) = manager.take_decoded().pop() {
+                break decoded;
+            }
+            assert!(Instant::now() < deadline, "Decode didn't finish");
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(decoded.url, url);
+        let larger = decoded.result.unwrap();
+        assert!(!larger.is_downscaled());
+        assert!(Arc::ptr_eq(&manager.get_cached(&url).unwrap(), &larger));
+        assert!(manager.sources.read().unwrap().is_empty());
+        assert!(!manager.is_loading(&url));
+    }
 }
 
@@ -16,6 +16,10 @@ use std::time::Duration;
 use thiserror::Error;
 use tracing::{debug, info, trace};
 
+pub mod timers;
+
+pub use timers::{TimerQueue, MIN_NESTED_DELAY};
+
 /// Errors that can occur in JS operations.
 #[derive(Error, Debug)]
 pub enum JsError {
@@ -190,6 +194,8 @@ impl JsRuntime {
             use boa_engine::Source;
 
             let result = self.context.eval(Source::from_bytes(source));
+            // Settle the promises the script resolved
+            self.context.run_jobs();
 
             match result {
                 Ok(value) => {
@@ -0,0 +1,170 @@
+//! Timer scheduling for `setTimeout` and `setInterval`.
+//!
+//! A [`TimerQueue`] tracks when each of a page's timers is next due, by
+//! the id the page knows it by; the callbacks stay in the page's JS. The
+//! caller passes the current time in, so timers fire as whatever clock it
+//! drives them with advances.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Nesting level past which timers wait at least [`MIN_NESTED_DELAY`].
+const MAX_UNCLAMPED_NESTING: u32 = 5;
+
+/// Shortest delay of timers set from deeply nested timer callbacks, and
+/// of intervals that have repeated a few times, as in browsers.
+pub const MIN_NESTED_DELAY: Duration = Duration::from_millis(4);
+
+struct Timer {
+    due: Instant,
+    interval: Option<Duration>,
+    nesting: u32,
+    /// Order the timer was scheduled in, which breaks ties between timers
+    /// due at the same time.
+    seq: u64,
+}
+
+/// A page's pending timers.
+#[derive(Default)]
+pub struct TimerQueue {
+    timers: HashMap<u64, Timer>,
+    next_seq: u64,
+}
+
+impl TimerQueue {
+    /// Create an empty timer queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule timer `id` to fire `delay` after `now`, and then every
+    /// `delay` if it `repeat`s. `nesting` is 1 for a timer set outside of
+    /// timer callbacks, and one more than the firing timer's inside one.
+    pub fn schedule(&mut self, id: u64, delay: Duration, repeat: bool, nesting: u32, now: Instant) {
+        let delay = clamp_delay(delay, nesting);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.timers.insert(
+            id,
+            Timer {
+                due: now + delay,
+                interval: repeat.then_some(delay),
+                nesting,
+                seq,
+            },
+        );
+    }
+
+    /// Cancel timer `id`. Returns whether it was pending.
+    pub fn cancel(&mut self, id: u64) -> bool {
+        self.timers.remove(&id).is_some()
+    }
+
+    /// Take the timers due at `now`, in the order they fell due, with the
+    /// nesting level each fires at. Intervals are scheduled again from
+    /// `now`; other timers are done.
+    pub fn take_due(&mut self, now: Instant) -> Vec<(u64, u32)> {
+        let mut due: Vec<_> = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.due <= now)
+            .map(|(&id, timer)| (timer.due, timer.seq, id))
+            .collect();
+        due.sort();
+
+        let mut fired = Vec::with_capacity(due.len());
+        for (_, _, id) in due {
+            let timer = self.timers.get_mut(&id).unwrap();
+            fired.push((id, timer.nesting));
+            match timer.interval {
+                Some(interval) => {
+                    timer.nesting += 1;
+                    let interval = clamp_delay(interval, timer.nesting);
+                    timer.interval = Some(interval);
+                    timer.due = now + interval;
+                    timer.seq = self.next_seq;
+                    self.next_seq += 1;
+                }
+                None => {
+                    self.timers.remove(&id);
+                }
+            }
+        }
+        fired
+    }
+
+    /// When the next timer is due, if any is pending.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.timers.values().map(|timer| timer.due).min()
+    }
+
+    /// Number of pending timers.
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// Whether no timers are pending.
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Cancel every timer.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+}
+
+fn clamp_delay(delay: Duration, nesting: u32) -> Duration {
+    if nesting > MAX_UNCLAMPED_NESTING {
+        delay.max(MIN_NESTED_DELAY)
+    } else {
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_queue_order_and_intervals() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut timers = TimerQueue::new();
+        timers.schedule(1, ms(20), false, 1, start);
+        timers.schedule(2, ms(10), true, 1, start);
+        timers.schedule(3, ms(10), false, 1, start);
+        assert_eq!(timers.next_due(), Some(start + ms(10)));
+
+        assert!(timers.take_due(start + ms(5)).is_empty());
+        assert_eq!(timers.take_due(start + ms(10)), [(2, 1), (3, 1)]);
+        assert_eq!(timers.len(), 2);
+
+        // The interval is due again 10ms after it fired, with the timeout
+        assert_eq!(timers.take_due(start + ms(20)), [(1, 1), (2, 2)]);
+        assert!(timers.cancel(2));
+        assert!(!timers.cancel(2));
+        assert!(timers.is_empty());
+        assert_eq!(timers.next_due(), None);
+    }
+
+    #[test]
+    fn test_timer_queue_clamps_nested_timers() {
+        let start = Instant::now();
+        let mut timers = TimerQueue::new();
+        timers.schedule(1, Duration::ZERO, false, MAX_UNCLAMPED_NESTING, start);
+        timers.schedule(2, Duration::ZERO, false, MAX_UNCLAMPED_NESTING + 1, start);
+        assert_eq!(timers.take_due(start), [(1, MAX_UNCLAMPED_NESTING)]);
+        assert_eq!(timers.next_due(), Some(start + MIN_NESTED_DELAY));
+
+        // A zero interval slows to the minimum once it's repeated enough
+        timers.clear();
+        timers.schedule(3, Duration::ZERO, true, 1, start);
+        for _ in 0..MAX_UNCLAMPED_NESTING - 1 {
+            assert_eq!(timers.take_due(start).len(), 1);
+        }
+        assert_eq!(timers.take_due(start).len(), 1);
+        assert!(timers.take_due(start).is_empty());
+        assert_eq!(timers.next_due(), Some(start + MIN_NESTED_DELAY));
+    }
+}
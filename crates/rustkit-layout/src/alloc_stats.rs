@@ -0,0 +1,138 @@
+//! Heap allocation counting.
+//!
+//! [`CountingAllocator`] wraps a global allocator and counts allocations and
+//! bytes per thread. A host opts in by installing it:
+//!
+//! ```rust,ignore
+//! #[global_allocator]
+//! static ALLOCATOR: rustkit_layout::CountingAllocator = rustkit_layout::CountingAllocator::system();
+//! ```
+//!
+//! and the engine takes an [`AllocationSnapshot`] around each layout pass.
+//! Without it installed, every count reads zero.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Allocations and bytes allocated on this thread.
+    static COUNTS: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+/// Global allocator wrapper counting allocations per thread.
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl CountingAllocator<System> {
+    /// Count allocations made through the system allocator.
+    pub const fn system() -> Self {
+        Self { inner: System }
+    }
+
+    /// Whether a counting allocator is the active global allocator.
+    pub fn is_installed() -> bool {
+        INSTALLED.load(Ordering::Relaxed)
+    }
+}
+
+impl<A> CountingAllocator<A> {
+    /// Count allocations made through `inner`.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    fn record(size: usize) {
+        if !INSTALLED.load(Ordering::Relaxed) {
+            INSTALLED.store(true, Ordering::Relaxed);
+        }
+        // Unavailable while the thread is being torn down
+        let _ = COUNTS.try_with(|counts| {
+            let (allocations, bytes) = counts.get();
+            counts.set((allocations + 1, bytes + size as u64));
+        });
+    }
+}
+
+// SAFETY: every call is forwarded unchanged to `inner`; counting touches
+// only a thread-local `Cell` and never allocates.
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::record(new_size);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+}
+
+/// Allocations counted over some span of work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Number of allocations (reallocations count as one each).
+    pub allocations: u64,
+    /// Bytes requested.
+    pub bytes: u64,
+}
+
+/// The current thread's allocation counts at some point in time.
+#[derive(Debug, Clone, Copy)]
+pub struct AllocationSnapshot {
+    allocations: u64,
+    bytes: u64,
+}
+
+impl AllocationSnapshot {
+    /// Take a snapshot of the current thread's counts.
+    pub fn now() -> Self {
+        let (allocations, bytes) = COUNTS.try_with(Cell::get).unwrap_or_default();
+        Self { allocations, bytes }
+    }
+
+    /// Allocations made on this thread since the snapshot was taken.
+    pub fn elapsed(&self) -> AllocationStats {
+        let now = Self::now();
+        AllocationStats {
+            allocations: now.allocations - self.allocations,
+            bytes: now.bytes - self.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator::system();
+
+    #[test]
+    fn test_counts_allocations_on_this_thread() {
+        let snapshot = AllocationSnapshot::now();
+        let buffer: Vec<u8> = Vec::with_capacity(1000);
+        let stats = snapshot.elapsed();
+        drop(buffer);
+
+        assert!(CountingAllocator::is_installed());
+        assert_eq!(stats, AllocationStats { allocations: 1, bytes: 1000 });
+
+        // Other threads' allocations aren't counted here
+        let snapshot = AllocationSnapshot::now();
+        std::thread::spawn(|| vec![0u8; 4096]).join().unwrap();
+        assert!(snapshot.elapsed().bytes < 4096);
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-pass storage for layout box construction.
+//!
+//! Every layout pass builds a fresh box tree, and each box's `children`
+//! vector is its own heap allocation that grows as children are pushed.
+//! [`LayoutArena`] keeps those vectors from one pass to the next: the
+//! previous tree is [reclaimed](LayoutArena::reclaim) before the next one is
+//! built, and [`LayoutArena::alloc`] hands its emptied vectors, capacity
+//! intact, to the new boxes. A steady-state relayout of an unchanged page
+//! then builds its tree without growing any child list.
+//!
+//! When only parts of the document changed, the previous tree is torn down
+//! with [`LayoutArena::reclaim_keeping`] instead, which keeps the subtrees
+//! of unchanged elements whole. The next build takes them back with
+//! [`LayoutArena::take_kept`] rather than styling and building them again.
+
+use std::collections::HashMap;
+
+use rustkit_css::ComputedStyle;
+
+use crate::{BoxType, LayoutBox};
+
+/// Cap on pooled vectors, so one huge page doesn't pin memory forever.
+const MAX_POOLED: usize = 16 * 1024;
+
+/// Counters for the current layout pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Boxes allocated.
+    pub boxes: usize,
+    /// Boxes whose child list came from the pool.
+    pub reused: usize,
+    /// Boxes reclaimed from the previous tree.
+    pub reclaimed: usize,
+    /// Subtrees kept whole from the previous tree.
+    pub kept: usize,
+}
+
+/// What [`LayoutArena::reclaim_keeping`] does with an element's box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtreeReuse {
+    /// Keep the box and everything under it.
+    Keep,
+    /// Reclaim the box, deciding for its descendants in turn.
+    Descend,
+    /// Reclaim the box and everything under it.
+    Rebuild,
+}
+
+/// Pool of child vectors recycled between layout passes.
+#[derive(Debug, Default)]
+pub struct LayoutArena {
+    pool: Vec<Vec<LayoutBox>>,
+    /// Subtrees kept from the previous tree, by element ID.
+    kept: HashMap<usize, LayoutBox>,
+    stats: ArenaStats,
+}
+
+impl LayoutArena {
+    /// Create an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new pass, resetting the counters. Subtrees the last pass
+    /// kept but didn't take back are reclaimed.
+    pub fn begin_pass(&mut self) {
+        let unused: Vec<LayoutBox> = self.kept.drain().map(|(_, layout_box)| layout_box).collect();
+        for layout_box in unused {
+            self.reclaim(layout_box);
+        }
+        self.stats = ArenaStats::default();
+    }
+
+    /// Tear down a finished tree, keeping its child vectors for reuse.
+    pub fn reclaim(&mut self, root: LayoutBox) {
+        let mut pending = vec![root];
+        while let Some(mut layout_box) = pending.pop() {
+            self.stats.reclaimed += 1;
+            let mut children = std::mem::take(&mut layout_box.children);
+            pending.append(&mut children);
+            if children.capacity() > 0 && self.pool.len() < MAX_POOLED {
+                self.pool.push(children);
+            }
+        }
+    }
+
+    /// Tear down a finished tree like [`LayoutArena::reclaim`], except for
+    /// the subtrees `reuse` says to keep, decided top down by element ID.
+    /// Boxes without an element are decided along with their parent.
+    pub fn reclaim_keeping(&mut self, root: LayoutBox, reuse: impl Fn(usize) -> SubtreeReuse) {
+        let mut pending = vec![(root, true)];
+        while let Some((mut layout_box, may_keep)) = pending.pop() {
+            let decision = match layout_box.element_id {
+                Some(element_id) if may_keep => reuse(element_id),
+                _ if may_keep => SubtreeReuse::Descend,
+                _ => SubtreeReuse::Rebuild,
+            };
+            if decision == SubtreeReuse::Keep {
+                let element_id = layout_box.element_id.expect("only element boxes are kept");
+                self.kept.entry(element_id).or_insert(layout_box);
+                continue;
+            }
+
+            self.stats.reclaimed += 1;
+            let mut children = std::mem::take(&mut layout_box.children);
+            let descend = decision == SubtreeReuse::Descend;
+            pending.extend(children.drain(..).map(|child| (child, descend)));
+            if children.capacity() > 0 && self.pool.len() < MAX_POOLED {
+                self.pool.push(children);
+            }
+        }
+    }
+
+    /// Take back the kept subtree of an element, if there is one.
+    pub fn take_kept(&mut self, element_id: usize) -> Option<LayoutBox> {
+        let layout_box = self.kept.remove(&element_id)?;
+        self.stats.kept += 1;
+        Some(layout_box)
+    }
+
+    /// Create a box, giving it a recycled child vector when one is pooled.
+    pub fn alloc(&mut self, box_type: BoxType, style: ComputedStyle) -> LayoutBox {
+        let mut layout_box = LayoutBox::new(box_type, style);
+        self.stats.boxes += 1;
+        if let Some(children) = self.pool.pop() {
+            layout_box.children = children;
+            self.stats.reused += 1;
+        }
+        layout_box
+    }
+
+    /// Counters for the current pass.
+    pub fn stats(&self) -> ArenaStats {
+        self.stats
+    }
+
+    /// Number of pooled child vectors.
+    pub fn pooled(&self) -> usize {
+        self.pool.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(arena: &mut LayoutArena) -> LayoutBox {
+        let mut root = arena.alloc(BoxType::Block, ComputedStyle::new());
+        for _ in 0..3 {
+            let mut child = arena.alloc(BoxType::Block, ComputedStyle::new());
+            child
+                .children
+                .push(arena.alloc(BoxType::Text("x".to_string()), ComputedStyle::new()));
+            root.children.push(child);
+        }
+        root
+    }
+
+    #[test]
+    fn test_reuses_child_vectors_across_passes() {
+        let mut arena = LayoutArena::new();
+        arena.begin_pass();
+        let tree = build(&mut arena);
+        assert_eq!(arena.stats(), ArenaStats { boxes: 7, reused: 0, reclaimed: 0, kept: 0 });
+
+        arena.begin_pass();
+        arena.reclaim(tree);
+        // The root and the three blocks had children
+        assert_eq!(arena.pooled(), 4);
+        let tree = build(&mut arena);
+        assert_eq!(arena.stats(), ArenaStats { boxes: 7, reused: 4, reclaimed: 7, kept: 0 });
+        // Recycled vectors come back empty
+        assert_eq!(tree.children.len(), 3);
+        assert!(tree.children.iter().all(|child| child.children.len() == 1));
+    }
+
+    #[test]
+    fn test_kept_subtree_lays_out_like_a_fresh_one() {
+        let text = |s: &str| LayoutBox::new(BoxType::Text(s.to_string()), ComputedStyle::new());
+        let element = |id: usize, children: Vec<LayoutBox>| {
+            let mut layout_box = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+            layout_box.set_element_id(id);
+            layout_box.children = children;
+            layout_box
+        };
+        let tree = || {
+            let list = element(2, vec![element(3, vec![text("one two three four five six")]), element(4, vec![text("x")])]);
+            element(1, vec![list, element(5, vec![text("changed")])])
+        };
+        let width = |width: f32| {
+            let mut containing_block = crate::Dimensions::default();
+            containing_block.content.width = width;
+            containing_block
+        };
+
+        let mut arena = LayoutArena::new();
+        let mut previous = tree();
+        previous.layout(&width(400.0));
+        arena.begin_pass();
+        arena.reclaim_keeping(previous, |id| match id {
+            1 => SubtreeReuse::Descend,
+            5 => SubtreeReuse::Rebuild,
+            _ => SubtreeReuse::Keep,
+        });
+        assert!(arena.take_kept(3).is_none(), "only the topmost kept box is kept");
+        let list = arena.take_kept(2).unwrap();
+        assert_eq!(arena.stats().kept, 1);
+        assert_eq!(arena.stats().reclaimed, 3);
+
+        // A kept box lays out at a new width as if it were just built
+        let mut rebuilt = element(1, vec![list, element(5, vec![text("changed")])]);
+        rebuilt.layout(&width(120.0));
+        let mut fresh = tree();
+        fresh.layout(&width(120.0));
+        fn rects(layout_box: &LayoutBox, out: &mut Vec<crate::Rect>) {
+            out.push(layout_box.dimensions.margin_box());
+            layout_box.children.iter().for_each(|child| rects(child, out));
+        }
+        let (mut kept_rects, mut fresh_rects) = (Vec::new(), Vec::new());
+        rects(&rebuilt, &mut kept_rects);
+        rects(&fresh, &mut fresh_rects);
+        assert_eq!(kept_rects, fresh_rects);
+    }
+}
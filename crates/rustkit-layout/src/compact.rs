@@ -0,0 +1,332 @@
+//! Display list compaction.
+//!
+//! [`DisplayList::build`] emits commands box by box, so a page with many
+//! same-colored rows or a paragraph split into inline boxes produces a long
+//! run of tiny commands. [`DisplayList::compact`] rewrites the list in paint
+//! order without changing what it draws:
+//!
+//! - adjacent solid rects of one color that together form a rectangle are
+//!   merged, as are opaque ones that overlap;
+//! - consecutive text commands with the same font and color are batched
+//!   into one [`DisplayCommand::TextRun`];
+//! - invisible rects and clip, rounded clip, stacking context, transform,
+//!   layer and opacity pushes with nothing between them and their pop are
+//!   dropped.
+//!
+//! Only neighbouring commands are combined, so clip and transform state is
+//! the same for everything that gets merged.
+
+use rustkit_css::Color;
+
+use crate::{DisplayCommand, DisplayList, Rect};
+
+/// Tolerance for edges to count as touching.
+const EPSILON: f32 = 0.01;
+
+/// One piece of text in a [`DisplayCommand::TextRun`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRunSegment {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// What a compaction pass did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// Commands before compaction.
+    pub before: usize,
+    /// Commands after compaction.
+    pub after: usize,
+    /// Solid rects merged into a neighbour.
+    pub merged_rects: usize,
+    /// Text commands folded into a text run.
+    pub batched_text: usize,
+    /// Commands removed because they draw nothing.
+    pub dropped: usize,
+}
+
+/// The font and color shared by a text run.
+struct RunFont<'a> {
+    color: Color,
+    font_size: f32,
+    font_family: &'a str,
+    font_weight: u16,
+    font_style: u8,
+}
+
+impl DisplayList {
+    /// Merge and batch commands in place.
+    pub fn compact(&mut self) -> CompactionStats {
+        let mut stats = CompactionStats {
+            before: self.commands.len(),
+            ..Default::default()
+        };
+        let mut out: Vec<DisplayCommand> = Vec::with_capacity(self.commands.len());
+
+        for command in self.commands.drain(..) {
+            match command {
+                DisplayCommand::SolidColor(color, rect) => {
+                    if color.a <= 0.0 || rect.width <= 0.0 || rect.height <= 0.0 {
+                        stats.dropped += 1;
+                        continue;
+                    }
+                    if let Some(DisplayCommand::SolidColor(last_color, last_rect)) = out.last_mut() {
+                        if *last_color == color {
+                            if let Some(merged) = merge_rects(*last_rect, rect, color.a >= 1.0) {
+                                *last_rect = merged;
+                                stats.merged_rects += 1;
+                                continue;
+                            }
+                        }
+                    }
+                    out.push(DisplayCommand::SolidColor(color, rect));
+                }
+                DisplayCommand::Text {
+                    text,
+                    x,
+                    y,
+                    color,
+                    font_size,
+                    font_family,
+                    font_weight,
+                    font_style,
+                } => {
+                    let font = RunFont {
+                        color,
+                        font_size,
+                        font_family: &font_family,
+                        font_weight,
+                        font_style,
+                    };
+                    if let Some(segments) = out.last_mut().and_then(|last| text_run(last, &font)) {
+                        segments.push(TextRunSegment { text, x, y });
+                        stats.batched_text += 1;
+                        continue;
+                    }
+                    out.push(DisplayCommand::Text {
+                        text,
+                        x,
+                        y,
+                        color,
+                        font_size,
+                        font_family,
+                        font_weight,
+                        font_style,
+                    });
+                }
+                DisplayCommand::PopClip if matches!(out.last(), Some(DisplayCommand::PushClip(_))) => {
+                    out.pop();
+                    stats.dropped += 2;
+                }
+                DisplayCommand::PopRoundedClip
+                    if matches!(out.last(), Some(DisplayCommand::PushRoundedClip { .. })) =>
+                {
+                    out.pop();
+                    stats.dropped += 2;
+                }
+                DisplayCommand::PopStackingContext
+                    if matches!(out.last(), Some(DisplayCommand::PushStackingContext { .. })) =>
+                {
+                    out.pop();
+                    stats.dropped += 2;
+                }
+                DisplayCommand::PopTransform if matches!(out.last(), Some(DisplayCommand::PushTransform { .. })) => {
+                    out.pop();
+                    stats.dropped += 2;
+                }
+                DisplayCommand::PopLayer if matches!(out.last(), Some(DisplayCommand::PushLayer { .. })) => {
+                    out.pop();
+                    stats.dropped += 2;
+                }
+                DisplayCommand::PopOpacity if matches!(out.last(), Some(DisplayCommand::PushOpacity(_))) => {
+                    out.pop();
+                    stats.dropped += 2;
+                }
+                other => out.push(other),
+            }
+        }
+
+        self.commands = out;
+        stats.after = self.commands.len();
+        stats
+    }
+}
+
+/// The segments of `last` if text in `font` can be appended to it, turning
+/// a lone text command into a run first.
+fn text_run<'a>(last: &'a mut DisplayCommand, font: &RunFont) -> Option<&'a mut Vec<TextRunSegment>> {
+    if let DisplayCommand::Text {
+        text,
+        x,
+        y,
+        color,
+        font_size,
+        font_family,
+        font_weight,
+        font_style,
+    } = last
+    {
+        if !font.matches(*color, *font_size, font_family, *font_weight, *font_style) {
+            return None;
+        }
+        *last = DisplayCommand::TextRun {
+            segments: vec![TextRunSegment {
+                text: std::mem::take(text),
+                x: *x,
+                y: *y,
+            }],
+            color: *color,
+            font_size: *font_size,
+            font_family: std::mem::take(font_family),
+            font_weight: *font_weight,
+            font_style: *font_style,
+        };
+    }
+
+    match last {
+        DisplayCommand::TextRun {
+            segments,
+            color,
+            font_size,
+            font_family,
+            font_weight,
+            font_style,
+        } if font.matches(*color, *font_size, font_family, *font_weight, *font_style) => Some(segments),
+        _ => None,
+    }
+}
+
+impl RunFont<'_> {
+    fn matches(&self, color: Color, font_size: f32, font_family: &str, font_weight: u16, font_style: u8) -> bool {
+        self.color == color
+            && self.font_size == font_size
+            && self.font_family == font_family
+            && self.font_weight == font_weight
+            && self.font_style == font_style
+    }
+}
+
+/// The single rect covering `a` and `b`, if painting it looks the same as
+/// painting both. Overlap only qualifies for opaque colors, where painting
+/// a pixel twice changes nothing.
+fn merge_rects(a: Rect, b: Rect, opaque: bool) -> Option<Rect> {
+    if opaque && covers(a, b) {
+        return Some(a);
+    }
+    if opaque && covers(b, a) {
+        return Some(b);
+    }
+    if close(a.x, b.x) && close(a.width, b.width) {
+        join(a.y, a.bottom(), b.y, b.bottom(), opaque).map(|(y, height)| Rect::new(a.x, y, a.width, height))
+    } else if close(a.y, b.y) && close(a.height, b.height) {
+        join(a.x, a.right(), b.x, b.right(), opaque).map(|(x, width)| Rect::new(x, a.y, width, a.height))
+    } else {
+        None
+    }
+}
+
+/// Union of two spans that touch, or that overlap when `overlap` is allowed.
+fn join(a_start: f32, a_end: f32, b_start: f32, b_end: f32, overlap: bool) -> Option<(f32, f32)> {
+    let touching = close(a_end, b_start) || close(b_end, a_start);
+    let overlapping = a_start < b_end && b_start < a_end;
+    if !(touching || (overlap && overlapping)) {
+        return None;
+    }
+    let start = a_start.min(b_start);
+    Some((start, a_end.max(b_end) - start))
+}
+
+fn covers(outer: Rect, inner: Rect) -> bool {
+    outer.x <= inner.x + EPSILON
+        && outer.y <= inner.y + EPSILON
+        && outer.right() + EPSILON >= inner.right()
+        && outer.bottom() + EPSILON >= inner.bottom()
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str, x: f32, color: Color) -> DisplayCommand {
+        DisplayCommand::Text {
+            text: text.to_string(),
+            x,
+            y: 10.0,
+            color,
+            font_size: 16.0,
+            font_family: "sans-serif".to_string(),
+            font_weight: 400,
+            font_style: 0,
+        }
+    }
+
+    #[test]
+    fn test_merges_adjacent_rects() {
+        let red = Color::new(255, 0, 0, 1.0);
+        let translucent = Color::new(0, 0, 255, 0.5);
+        let mut list = DisplayList {
+            commands: vec![
+                // Three stacked rows of one table column
+                DisplayCommand::SolidColor(red, Rect::new(0.0, 0.0, 100.0, 20.0)),
+                DisplayCommand::SolidColor(red, Rect::new(0.0, 20.0, 100.0, 20.0)),
+                DisplayCommand::SolidColor(red, Rect::new(0.0, 40.0, 100.0, 20.0)),
+                DisplayCommand::SolidColor(Color::TRANSPARENT, Rect::new(0.0, 0.0, 50.0, 50.0)),
+                // Overlapping translucent rects must both be painted
+                DisplayCommand::SolidColor(translucent, Rect::new(0.0, 0.0, 50.0, 50.0)),
+                DisplayCommand::SolidColor(translucent, Rect::new(25.0, 0.0, 50.0, 50.0)),
+            ],
+            ..Default::default()
+        };
+
+        let stats = list.compact();
+        assert_eq!(stats.merged_rects, 2);
+        assert_eq!(stats.dropped, 1);
+        assert_eq!((stats.before, stats.after), (6, 3));
+        assert!(matches!(
+            list.commands[0],
+            DisplayCommand::SolidColor(c, r) if c == red && r == Rect::new(0.0, 0.0, 100.0, 60.0)
+        ));
+    }
+
+    #[test]
+    fn test_batches_text_and_drops_empty_groups() {
+        let black = Color::new(0, 0, 0, 1.0);
+        let gray = Color::new(128, 128, 128, 1.0);
+        let mut list = DisplayList {
+            commands: vec![
+                text("Hello", 0.0, black),
+                DisplayCommand::PushClip(Rect::new(0.0, 0.0, 10.0, 10.0)),
+                DisplayCommand::PopClip,
+                text("big", 40.0, black),
+                text("world", 70.0, black),
+                text("(muted)", 110.0, gray),
+                DisplayCommand::PushTransform {
+                    matrix: [1.0, 0.0, 0.0, 1.0, 5.0, 0.0],
+                    origin: (0.0, 0.0),
+                },
+                DisplayCommand::PopTransform,
+            ],
+            ..Default::default()
+        };
+
+        let stats = list.compact();
+        assert_eq!(stats.batched_text, 2);
+        assert_eq!(stats.dropped, 4);
+        assert_eq!(list.commands.len(), 2);
+        match &list.commands[0] {
+            DisplayCommand::TextRun { segments, color, .. } => {
+                assert_eq!(*color, black);
+                let texts: Vec<_> = segments.iter().map(|s| s.text.as_str()).collect();
+                assert_eq!(texts, ["Hello", "big", "world"]);
+                assert_eq!(segments[2].x, 70.0);
+            }
+            other => panic!("expected a text run, got {other:?}"),
+        }
+        assert!(matches!(&list.commands[1], DisplayCommand::Text { text, .. } if text == "(muted)"));
+    }
+}
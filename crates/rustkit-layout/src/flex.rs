@@ -14,10 +14,15 @@
 //! 8. Cross axis alignment (align-items, align-self)
 //! 9. Multi-line alignment (align-content)
 //! 10. Handle reverse directions
+//!
+//! Flex base sizes come from the items' max-content sizes, and items don't
+//! shrink below their min-content size unless `min-width`/`min-height` say
+//! otherwise (the automatic minimum size, §4.5). Auto margins absorb free
+//! space on both axes before `justify-content` and `align-self` apply (§8.1).
 
-use crate::{Dimensions, EdgeSizes, LayoutBox, Rect};
+use crate::{is_scroll_container, BoxType, Dimensions, EdgeSizes, IntrinsicSizingMode, LayoutBox, Rect};
 use rustkit_css::{
-    AlignContent, AlignItems, AlignSelf, FlexBasis, FlexWrap, JustifyContent, Length,
+    AlignContent, AlignItems, AlignSelf, BoxSizing, FlexBasis, FlexWrap, JustifyContent, Length,
 };
 use tracing::trace;
 
@@ -101,25 +106,65 @@ pub struct FlexItem<'a> {
     /// Outer margin on cross axis end.
     pub cross_margin_end: f32,
 
+    /// Whether the main axis start margin is `auto`.
+    pub main_margin_start_auto: bool,
+
+    /// Whether the main axis end margin is `auto`.
+    pub main_margin_end_auto: bool,
+
+    /// Whether the cross axis start margin is `auto`.
+    pub cross_margin_start_auto: bool,
+
+    /// Whether the cross axis end margin is `auto`.
+    pub cross_margin_end_auto: bool,
+
+    /// Padding plus border along the main axis.
+    pub main_padding_border: f32,
+
+    /// Padding plus border along the cross axis.
+    pub cross_padding_border: f32,
+
     /// Whether the item has an explicit cross size (not auto).
     /// If true, stretch should not apply per CSS spec.
     pub has_explicit_cross_size: bool,
 }
 
 impl<'a> FlexItem<'a> {
-    /// Get outer main size (target + margins).
+    /// Get outer main size (target + padding, border and margins).
     pub fn outer_main_size(&self) -> f32 {
-        self.target_main_size + self.main_margin_start + self.main_margin_end
+        self.target_main_size + self.main_edges()
     }
 
     /// Get outer hypothetical main size.
     pub fn outer_hypothetical_main_size(&self) -> f32 {
-        self.hypothetical_main_size + self.main_margin_start + self.main_margin_end
+        self.hypothetical_main_size + self.main_edges()
     }
 
     /// Get outer cross size.
     pub fn outer_cross_size(&self) -> f32 {
-        self.cross_size + self.cross_margin_start + self.cross_margin_end
+        self.cross_size + self.cross_padding_border + self.cross_margin_start + self.cross_margin_end
+    }
+
+    /// Padding, border and margins along the main axis.
+    fn main_edges(&self) -> f32 {
+        self.main_padding_border + self.main_margin_start + self.main_margin_end
+    }
+
+    /// The item's cross axis alignment, resolving `align-self: auto`.
+    fn alignment(&self, align_items: AlignItems) -> AlignItems {
+        match self.align_self {
+            AlignSelf::Auto => align_items,
+            AlignSelf::FlexStart => AlignItems::FlexStart,
+            AlignSelf::FlexEnd => AlignItems::FlexEnd,
+            AlignSelf::Center => AlignItems::Center,
+            AlignSelf::Baseline => AlignItems::Baseline,
+            AlignSelf::Stretch => AlignItems::Stretch,
+        }
+    }
+
+    /// Whether either cross axis margin is `auto`.
+    fn has_auto_cross_margin(&self) -> bool {
+        self.cross_margin_start_auto || self.cross_margin_end_auto
     }
 }
 
@@ -194,10 +239,11 @@ pub fn layout_flex_container(
 
     // Check if the flex container has a definite cross size
     // For row direction, cross axis is vertical (height)
-    // For column direction, cross axis is horizontal (width)
+    // For column direction, cross axis is horizontal (width), which block
+    // layout has always resolved by now
     let has_definite_cross_size = match cross_axis {
-        Axis::Vertical => !matches!(container.style.height, Length::Auto),
-        Axis::Horizontal => !matches!(container.style.width, Length::Auto),
+        Axis::Vertical => !container.style.height.is_content_sized(),
+        Axis::Horizontal => true,
     };
 
     // Get gap values
@@ -242,7 +288,7 @@ pub fn layout_flex_container(
     // 5. Calculate cross sizes for each line
     // Pass has_definite_cross_size so stretch behavior is correct for auto-height containers
     for line in &mut lines {
-        calculate_cross_sizes(line, container_cross_size, style.align_items, has_definite_cross_size);
+        calculate_cross_sizes(line, cross_axis, container_cross_size, style.align_items, has_definite_cross_size);
     }
 
     // 6. Calculate line cross sizes and positions
@@ -263,7 +309,7 @@ pub fn layout_flex_container(
         distribute_main_axis(line, container_main_size, main_gap, style.justify_content, direction.is_reverse());
     }
 
-    // 9. Cross axis alignment (align-items, align-self)
+    // 9. Cross axis alignment (auto margins, align-items, align-self)
     for line in &mut lines {
         align_cross_axis(line, style.align_items);
     }
@@ -273,65 +319,46 @@ pub fn layout_flex_container(
     let container_origin = (containing_block.content.x, containing_block.content.y);
     apply_positions(&mut lines, main_axis, direction.is_reverse(), wrap == FlexWrap::WrapReverse, container_origin);
     
-    // 11. Recursively layout children of flex items (important for nested flex containers)
-    // After flex positioning, each item's dimensions are set, so we can use them as containing blocks
+    // 11. Lay out the contents of each item inside its final box
+    // (important for nested flex containers and for text that rewraps at the flexed width)
     for line in &mut lines {
         for item in &mut line.items {
-            // If this flex item has children and is a container (flex or block), lay them out
-            if !item.layout_box.children.is_empty() {
-                if item.layout_box.style.display.is_flex() {
-                    // Nested flex container: recursively apply flex layout
-                    let child_containing = item.layout_box.dimensions.clone();
-                    layout_flex_container(item.layout_box, &child_containing);
-                } else {
-                    // Block container: lay out children normally
-                    for child in &mut item.layout_box.children {
-                        let cb = item.layout_box.dimensions.clone();
-                        child.layout(&cb);
-                    }
-                }
+            let layout_box = &mut *item.layout_box;
+            if layout_box.children.is_empty() {
+                continue;
             }
-        }
-    }
 
-    // 11b. Recompute cross sizes now that children are laid out
-    // This fixes the chicken-and-egg problem where we need children heights
-    // before we can determine item cross sizes
-    for line in &mut lines {
-        for item in &mut line.items {
-            // Only recompute if cross_size is still using fallback (line_height or similar)
-            // and we have children with actual heights
-            if !item.layout_box.children.is_empty() {
-                let children_height: f32 = item.layout_box.children
-                    .iter()
-                    .map(|c| c.dimensions.margin_box().height)
-                    .sum();
-
-                if children_height > 0.0 && children_height > item.cross_size {
-                    // Update cross size based on actual children heights
-                    item.cross_size = children_height.max(item.min_cross_size).min(item.max_cross_size);
-
-                    // Also update the layout box content height
-                    match cross_axis {
-                        Axis::Vertical => {
-                            if item.layout_box.dimensions.content.height < children_height {
-                                item.layout_box.dimensions.content.height = children_height;
-                            }
-                        }
-                        Axis::Horizontal => {
-                            if item.layout_box.dimensions.content.width < children_height {
-                                item.layout_box.dimensions.content.width = children_height;
-                            }
-                        }
-                    }
+            let content_height = if layout_box.style.display.is_flex() {
+                // Nested flex container: recursively apply flex layout
+                let child_containing = layout_box.dimensions.clone();
+                layout_flex_container(layout_box, &child_containing);
+                layout_box.dimensions.content.height
+            } else {
+                // Block (or grid) container: flow children in the item's content
+                // box, keeping the size flex layout gave the item itself
+                let flexed_height = layout_box.dimensions.content.height;
+                layout_box.layout_block_children();
+                let content_height = std::mem::replace(&mut layout_box.dimensions.content.height, flexed_height);
+                if layout_box.style.display.is_grid() {
+                    let (width, height) = (layout_box.dimensions.content.width, flexed_height);
+                    crate::grid::layout_grid_container(layout_box, width, height);
                 }
+                content_height
+            };
+
+            // 11b. Content that turned out taller than the item's cross size grows
+            // it, since cross sizes were estimated before the item's width was known
+            let content_sized = !item.has_explicit_cross_size || item.layout_box.style.height.is_intrinsic();
+            if cross_axis == Axis::Vertical && content_sized && content_height > item.cross_size {
+                item.cross_size = content_height.max(item.min_cross_size).min(item.max_cross_size);
+                item.layout_box.dimensions.content.height = item.cross_size;
             }
         }
 
         // Recompute line cross size based on updated item cross sizes
         line.cross_size = line.items
             .iter()
-            .map(|i| i.cross_size + i.cross_margin_start + i.cross_margin_end)
+            .map(|i| i.outer_cross_size())
             .fold(0.0, f32::max);
     }
 
@@ -343,7 +370,7 @@ pub fn layout_flex_container(
                 // Main axis is horizontal (width), cross axis is vertical (height)
                 let max_main: f32 = lines.iter()
                     .flat_map(|l| l.items.iter())
-                    .map(|item| item.main_position + item.target_main_size)
+                    .map(|item| item.main_position + item.target_main_size + item.main_padding_border + item.main_margin_end)
                     .fold(0.0f32, f32::max);
                 let total_cross: f32 = lines.iter().map(|l| l.cross_size).sum::<f32>()
                     + cross_gap * (lines.len().saturating_sub(1)) as f32;
@@ -353,7 +380,7 @@ pub fn layout_flex_container(
                 // Main axis is vertical (height), cross axis is horizontal (width)
                 let max_main: f32 = lines.iter()
                     .flat_map(|l| l.items.iter())
-                    .map(|item| item.main_position + item.target_main_size)
+                    .map(|item| item.main_position + item.target_main_size + item.main_padding_border + item.main_margin_end)
                     .fold(0.0f32, f32::max);
                 let total_cross: f32 = lines.iter().map(|l| l.cross_size).sum::<f32>()
                     + cross_gap * (lines.len().saturating_sub(1)) as f32;
@@ -366,14 +393,14 @@ pub fn layout_flex_container(
             Axis::Horizontal => {
                 // For row direction, update height from cross size
                 if container.dimensions.content.height == 0.0 || 
-                   matches!(container.style.height, rustkit_css::Length::Auto) {
+                   container.style.height.is_content_sized() {
                     container.dimensions.content.height = total_cross;
                 }
             }
             Axis::Vertical => {
                 // For column direction, update height from main size
                 if container.dimensions.content.height == 0.0 ||
-                   matches!(container.style.height, rustkit_css::Length::Auto) {
+                   container.style.height.is_content_sized() {
                     container.dimensions.content.height = total_main;
                 }
             }
@@ -389,87 +416,93 @@ fn create_flex_item<'a>(
     container_cross: f32,
 ) -> FlexItem<'a> {
     // Extract all values from style first to avoid borrow conflicts
-    let order = layout_box.style.order;
-    let flex_grow = layout_box.style.flex_grow;
-    let flex_shrink = layout_box.style.flex_shrink;
-    let flex_basis_value = layout_box.style.flex_basis;
-    let align_self = layout_box.style.align_self;
-
-    // Get margins
-    let (main_margin_start, main_margin_end, cross_margin_start, cross_margin_end) = match main_axis {
-        Axis::Horizontal => (
-            resolve_length(&layout_box.style.margin_left, container_main),
-            resolve_length(&layout_box.style.margin_right, container_main),
-            resolve_length(&layout_box.style.margin_top, container_cross),
-            resolve_length(&layout_box.style.margin_bottom, container_cross),
-        ),
-        Axis::Vertical => (
-            resolve_length(&layout_box.style.margin_top, container_main),
-            resolve_length(&layout_box.style.margin_bottom, container_main),
-            resolve_length(&layout_box.style.margin_left, container_cross),
-            resolve_length(&layout_box.style.margin_right, container_cross),
-        ),
+    let style = &layout_box.style;
+    let order = style.order;
+    let flex_grow = style.flex_grow;
+    let flex_shrink = style.flex_shrink;
+    let flex_basis_value = style.flex_basis;
+    let align_self = style.align_self;
+
+    // Main/cross views of the box's sizing properties
+    let (main_margins, cross_margins) = match main_axis {
+        Axis::Horizontal => ((&style.margin_left, &style.margin_right), (&style.margin_top, &style.margin_bottom)),
+        Axis::Vertical => ((&style.margin_top, &style.margin_bottom), (&style.margin_left, &style.margin_right)),
+    };
+    let (main_size, min_main_size, max_main_size, cross_size, min_cross_size, max_cross_size) = match main_axis {
+        Axis::Horizontal => (&style.width, &style.min_width, &style.max_width, &style.height, &style.min_height, &style.max_height),
+        Axis::Vertical => (&style.height, &style.min_height, &style.max_height, &style.width, &style.min_width, &style.max_width),
     };
 
-    // Calculate flex basis
+    // Get margins (auto margins resolve to zero until free space is distributed)
+    let main_margin_start = resolve_length(main_margins.0, container_main);
+    let main_margin_end = resolve_length(main_margins.1, container_main);
+    let cross_margin_start = resolve_length(cross_margins.0, container_cross);
+    let cross_margin_end = resolve_length(cross_margins.1, container_cross);
+
+    // Padding and border were resolved when the item was laid out as a block
+    let d = &layout_box.dimensions;
+    let horizontal_padding_border = d.padding.horizontal() + d.border.horizontal();
+    let vertical_padding_border = d.padding.vertical() + d.border.vertical();
+    let (main_padding_border, cross_padding_border) = match main_axis {
+        Axis::Horizontal => (horizontal_padding_border, vertical_padding_border),
+        Axis::Vertical => (vertical_padding_border, horizontal_padding_border),
+    };
+
+    // Sizes below are content-box sizes; border-box lengths include padding and border
+    let border_box = style.box_sizing == BoxSizing::BorderBox;
+    let content_size = |size: f32, padding_border: f32| {
+        if border_box { (size - padding_border).max(0.0) } else { size }
+    };
+
+    // Calculate flex base size (§9.2.3)
+    let available_main = (container_main - main_padding_border - main_margin_start - main_margin_end).max(0.0);
+    let specified_main = match main_size {
+        Length::Auto => None,
+        size if size.is_intrinsic() => keyword_main_size(layout_box, main_axis, size, available_main),
+        size => Some(content_size(resolve_length(size, container_main), main_padding_border)),
+    };
     let flex_basis = match flex_basis_value {
-        FlexBasis::Auto => {
-            // Use main size property, or intrinsic size for replaced elements
-            let explicit_size = match main_axis {
-                Axis::Horizontal => resolve_length(&layout_box.style.width, container_main),
-                Axis::Vertical => resolve_length(&layout_box.style.height, container_main),
-            };
-            
-            // If explicit size is 0 (auto), check for intrinsic sizing
-            if explicit_size == 0.0 {
-                // Get intrinsic size for replaced elements (form controls, images)
-                get_intrinsic_main_size(&layout_box.box_type, main_axis, &layout_box.style)
-            } else {
-                explicit_size
-            }
-        }
-        FlexBasis::Content => {
-            // Use content size - for replaced elements, use intrinsic size
-            get_intrinsic_main_size(&layout_box.box_type, main_axis, &layout_box.style)
-        }
-        FlexBasis::Length(len) => len,
-        FlexBasis::Percent(pct) => pct / 100.0 * container_main,
+        // Use the main size property, or the max-content size when that's auto
+        FlexBasis::Auto => specified_main
+            .unwrap_or_else(|| content_main_size(layout_box, main_axis, IntrinsicSizingMode::MaxContent)),
+        FlexBasis::Content => content_main_size(layout_box, main_axis, IntrinsicSizingMode::MaxContent),
+        FlexBasis::Length(len) => content_size(len, main_padding_border),
+        FlexBasis::Percent(pct) => content_size(pct / 100.0 * container_main, main_padding_border),
+        FlexBasis::MinContent => content_main_size(layout_box, main_axis, IntrinsicSizingMode::MinContent),
+        FlexBasis::MaxContent => content_main_size(layout_box, main_axis, IntrinsicSizingMode::MaxContent),
+        FlexBasis::FitContent => keyword_main_size(layout_box, main_axis, &Length::FitContent, available_main)
+            .unwrap_or_else(|| content_main_size(layout_box, main_axis, IntrinsicSizingMode::MaxContent)),
     };
 
     // Get min/max constraints from CSS
-    let (css_min_main, max_main, css_min_cross, max_cross) = match main_axis {
-        Axis::Horizontal => (
-            resolve_length(&layout_box.style.min_width, container_main),
-            resolve_max_length(&layout_box.style.max_width, container_main),
-            resolve_length(&layout_box.style.min_height, container_cross),
-            resolve_max_length(&layout_box.style.max_height, container_cross),
-        ),
-        Axis::Vertical => (
-            resolve_length(&layout_box.style.min_height, container_main),
-            resolve_max_length(&layout_box.style.max_height, container_main),
-            resolve_length(&layout_box.style.min_width, container_cross),
-            resolve_max_length(&layout_box.style.max_width, container_cross),
-        ),
+    let max_main = keyword_main_size(layout_box, main_axis, max_main_size, available_main)
+        .unwrap_or_else(|| content_size(resolve_max_length(max_main_size, container_main), main_padding_border));
+    let min_main = match min_main_size {
+        // Automatic minimum size (§4.5): don't shrink below the content's
+        // min-content size, or the specified size if that's smaller
+        Length::Auto if !is_scroll_container(style.overflow_x, style.overflow_y) => {
+            let content_min = content_main_size(layout_box, main_axis, IntrinsicSizingMode::MinContent);
+            specified_main.map_or(content_min, |size| content_min.min(size)).min(max_main)
+        }
+        size => keyword_main_size(layout_box, main_axis, size, available_main)
+            .unwrap_or_else(|| content_size(resolve_length(size, container_main), main_padding_border)),
     };
+    let css_min_cross = content_size(resolve_length(min_cross_size, container_cross), cross_padding_border);
+    let max_cross = content_size(resolve_max_length(max_cross_size, container_cross), cross_padding_border);
     
     // For replaced elements (form controls, images), use intrinsic size as minimum
     // This ensures flex items have proper sizing even without explicit min-width/height
-    let intrinsic_cross = get_intrinsic_cross_size(&layout_box.box_type, main_axis, &layout_box.style);
-    let min_main = css_min_main;
+    let intrinsic_cross = get_intrinsic_cross_size(&layout_box.box_type, main_axis, style);
     let min_cross = if css_min_cross > 0.0 { css_min_cross } else { intrinsic_cross };
 
-    // Hypothetical main size (clamped)
-    let hypothetical_main_size = flex_basis.max(min_main).min(max_main);
+    // Hypothetical main size (clamped, with min winning over max)
+    let hypothetical_main_size = flex_basis.min(max_main).max(min_main);
 
     // Check if the cross size is explicitly set (not auto)
     // Per CSS spec, items with explicit cross size should NOT be stretched
-    let has_explicit_cross_size = match main_axis {
-        Axis::Horizontal => !matches!(layout_box.style.height, rustkit_css::Length::Auto),
-        Axis::Vertical => !matches!(layout_box.style.width, rustkit_css::Length::Auto),
-    };
+    let has_explicit_cross_size = !matches!(cross_size, Length::Auto);
 
     FlexItem {
-        layout_box,
         order,
         flex_grow,
         flex_shrink,
@@ -489,7 +522,48 @@ fn create_flex_item<'a>(
         main_margin_end,
         cross_margin_start,
         cross_margin_end,
+        main_margin_start_auto: matches!(main_margins.0, Length::Auto),
+        main_margin_end_auto: matches!(main_margins.1, Length::Auto),
+        cross_margin_start_auto: matches!(cross_margins.0, Length::Auto),
+        cross_margin_end_auto: matches!(cross_margins.1, Length::Auto),
+        main_padding_border,
+        cross_padding_border,
         has_explicit_cross_size,
+        layout_box,
+    }
+}
+
+/// Content-based main size of an item, for flex base sizes and automatic
+/// minimum sizes.
+fn content_main_size(layout_box: &LayoutBox, main_axis: Axis, mode: IntrinsicSizingMode) -> f32 {
+    match (&layout_box.box_type, main_axis) {
+        (BoxType::Image { .. } | BoxType::FormControl(_), _) => {
+            get_intrinsic_main_size(&layout_box.box_type, main_axis, &layout_box.style)
+        }
+        (_, Axis::Horizontal) => layout_box.intrinsic_inline_size(mode),
+        // Heights come from the block layout pass at the item's cross size,
+        // where content can't get any shorter
+        (_, Axis::Vertical) => layout_box.dimensions.content.height,
+    }
+}
+
+/// Main size for an intrinsic sizing keyword, or None when it doesn't apply.
+///
+/// Heights are always content-sized here, so only horizontal main axes have
+/// keyword sizes; fit-content clamps max-content to the available space.
+fn keyword_main_size(layout_box: &LayoutBox, main_axis: Axis, size: &Length, available: f32) -> Option<f32> {
+    if main_axis == Axis::Vertical {
+        return None;
+    }
+    match size {
+        Length::MinContent => Some(content_main_size(layout_box, main_axis, IntrinsicSizingMode::MinContent)),
+        Length::MaxContent => Some(content_main_size(layout_box, main_axis, IntrinsicSizingMode::MaxContent)),
+        Length::FitContent => {
+            let min = content_main_size(layout_box, main_axis, IntrinsicSizingMode::MinContent);
+            let max = content_main_size(layout_box, main_axis, IntrinsicSizingMode::MaxContent);
+            Some(max.min(available).max(min))
+        }
+        _ => None,
     }
 }
 
@@ -539,92 +613,111 @@ fn collect_flex_lines<'a>(
     lines
 }
 
-/// Resolve flexible lengths (grow/shrink) for a line.
+/// Resolve flexible lengths (grow/shrink) for a line (§9.7).
 fn resolve_flexible_lengths(line: &mut FlexLine, container_main: f32, main_gap: f32) {
     if line.items.is_empty() {
         return;
     }
 
-    // Calculate used space
     let total_gaps = main_gap * (line.items.len().saturating_sub(1)) as f32;
-    let used_space: f32 = line.items.iter().map(|i| i.hypothetical_main_size + i.main_margin_start + i.main_margin_end).sum();
-    let free_space = container_main - used_space - total_gaps;
 
-    if free_space.abs() < 0.01 {
-        // No adjustment needed
-        return;
-    }
+    // 1. Grow if the hypothetical sizes leave space over, shrink otherwise
+    let used_space: f32 = line.items.iter().map(|i| i.outer_hypothetical_main_size()).sum();
+    let growing = used_space + total_gaps < container_main;
 
-    // Reset frozen state
+    // 2. Size inflexible items: no flex factor, or already past their base
+    //    size in the direction we'd flex them
     for item in &mut line.items {
-        item.frozen = false;
         item.target_main_size = item.hypothetical_main_size;
+        let factor = if growing { item.flex_grow } else { item.flex_shrink };
+        item.frozen = factor <= 0.0
+            || (growing && item.flex_basis > item.hypothetical_main_size)
+            || (!growing && item.flex_basis < item.hypothetical_main_size);
     }
 
-    if free_space > 0.0 {
-        // Grow items
-        grow_items(line, free_space);
-    } else {
-        // Shrink items
-        shrink_items(line, -free_space);
-    }
-}
-
-/// Grow items to fill free space.
-fn grow_items(line: &mut FlexLine, free_space: f32) {
-    let total_grow: f32 = line.items.iter().filter(|i| !i.frozen).map(|i| i.flex_grow).sum();
-
-    if total_grow <= 0.0 {
-        return;
-    }
-
-    let space_per_grow = free_space / total_grow;
+    // 3. Free space, counting frozen items at their target and the rest at their base size
+    let free_space = |line: &FlexLine| {
+        let used: f32 = line
+            .items
+            .iter()
+            .map(|i| i.main_edges() + if i.frozen { i.target_main_size } else { i.flex_basis })
+            .sum();
+        container_main - total_gaps - used
+    };
+    let initial_free_space = free_space(line);
 
-    for item in &mut line.items {
-        if item.frozen {
-            continue;
+    // 4. Distribute, freezing items that hit min/max, until everything is frozen
+    while line.items.iter().any(|i| !i.frozen) {
+        let mut remaining = free_space(line);
+        let factor_sum: f32 = line
+            .items
+            .iter()
+            .filter(|i| !i.frozen)
+            .map(|i| if growing { i.flex_grow } else { i.flex_shrink })
+            .sum();
+        // Flex factors summing below 1 only take that fraction of the space
+        if factor_sum < 1.0 && (initial_free_space * factor_sum).abs() < remaining.abs() {
+            remaining = initial_free_space * factor_sum;
         }
 
-        let grow = item.flex_grow * space_per_grow;
-        let new_size = item.target_main_size + grow;
-
-        if new_size > item.max_main_size {
-            item.target_main_size = item.max_main_size;
-            item.frozen = true;
+        if growing {
+            grow_items(line, remaining, factor_sum);
         } else {
-            item.target_main_size = new_size;
+            shrink_items(line, remaining);
+        }
+
+        // Clamp to min/max, freezing the items whose violations win
+        let mut total_violation = 0.0;
+        let mut violations = vec![0.0_f32; line.items.len()];
+        for (item, violation) in line.items.iter_mut().zip(&mut violations) {
+            if item.frozen {
+                continue;
+            }
+            let clamped = item.target_main_size.min(item.max_main_size).max(item.min_main_size);
+            *violation = clamped - item.target_main_size;
+            total_violation += *violation;
+            item.target_main_size = clamped;
+        }
+        for (item, violation) in line.items.iter_mut().zip(violations) {
+            if item.frozen {
+                continue;
+            }
+            item.frozen = if total_violation > 0.0 {
+                violation > 0.0
+            } else if total_violation < 0.0 {
+                violation < 0.0
+            } else {
+                true
+            };
         }
     }
 }
 
-/// Shrink items to remove overflow.
-fn shrink_items(line: &mut FlexLine, overflow: f32) {
-    let total_shrink_scaled: f32 = line.items
+/// Grow unfrozen items from their base size by their share of `free_space`.
+fn grow_items(line: &mut FlexLine, free_space: f32, total_grow: f32) {
+    for item in line.items.iter_mut().filter(|i| !i.frozen) {
+        let share = if total_grow > 0.0 { item.flex_grow / total_grow } else { 0.0 };
+        item.target_main_size = item.flex_basis + free_space * share;
+    }
+}
+
+/// Shrink unfrozen items from their base size, in proportion to their
+/// scaled shrink factor, to absorb (negative) `free_space`.
+fn shrink_items(line: &mut FlexLine, free_space: f32) {
+    let total_shrink_scaled: f32 = line
+        .items
         .iter()
         .filter(|i| !i.frozen)
         .map(|i| i.flex_shrink * i.flex_basis)
         .sum();
 
-    if total_shrink_scaled <= 0.0 {
-        return;
-    }
-
-    for item in &mut line.items {
-        if item.frozen {
-            continue;
-        }
-
-        let shrink_scaled = item.flex_shrink * item.flex_basis;
-        let shrink_ratio = shrink_scaled / total_shrink_scaled;
-        let shrink = overflow * shrink_ratio;
-        let new_size = (item.target_main_size - shrink).max(item.min_main_size);
-
-        if new_size <= item.min_main_size {
-            item.target_main_size = item.min_main_size;
-            item.frozen = true;
+    for item in line.items.iter_mut().filter(|i| !i.frozen) {
+        let ratio = if total_shrink_scaled > 0.0 {
+            item.flex_shrink * item.flex_basis / total_shrink_scaled
         } else {
-            item.target_main_size = new_size;
-        }
+            0.0
+        };
+        item.target_main_size = item.flex_basis + free_space * ratio;
     }
 }
 
@@ -634,14 +727,27 @@ fn shrink_items(line: &mut FlexLine, overflow: f32) {
 /// has a definite (non-auto) cross size. This affects stretch behavior:
 /// - With definite cross size: stretch items to fill the container
 /// - With auto cross size: stretch items to match the tallest item in the line
-fn calculate_cross_sizes(line: &mut FlexLine, container_cross: f32, align_items: AlignItems, has_definite_cross_size: bool) {
+fn calculate_cross_sizes(
+    line: &mut FlexLine,
+    cross_axis: Axis,
+    container_cross: f32,
+    align_items: AlignItems,
+    has_definite_cross_size: bool,
+) {
     // PASS 1: Calculate content-based cross sizes for ALL items (ignore stretch for now)
     // This determines the "natural" height of each item
     let mut content_cross_sizes: Vec<f32> = Vec::with_capacity(line.items.len());
     
     for item in &mut line.items {
-        // Compute the content-based cross size (hypothetical cross size)
-        let content_cross_size = get_content_cross_size(item.layout_box);
+        // Compute the content-based cross size (hypothetical cross size); an
+        // aspect-ratio derives it from the flexed main size instead
+        let ratio_cross_size = if item.has_explicit_cross_size {
+            None
+        } else {
+            item.layout_box.aspect_ratio_transfer(item.target_main_size, cross_axis == Axis::Vertical)
+        };
+        let content_cross_size =
+            ratio_cross_size.unwrap_or_else(|| get_content_cross_size(item.layout_box, cross_axis));
         
         // Apply min/max constraints to content size
         let constrained_size = content_cross_size.max(item.min_cross_size).min(item.max_cross_size);
@@ -652,35 +758,26 @@ fn calculate_cross_sizes(line: &mut FlexLine, container_cross: f32, align_items:
     }
     
     // Compute the line cross size based on content sizes (largest item outer cross size)
-    let line_cross_size = line.items.iter().enumerate()
-        .map(|(i, item)| content_cross_sizes[i] + item.cross_margin_start + item.cross_margin_end)
+    let line_cross_size = line.items.iter()
+        .map(|item| item.outer_cross_size())
         .fold(0.0, f32::max);
     
     // PASS 2: Apply stretch behavior based on container sizing
     for (i, item) in line.items.iter_mut().enumerate() {
-        let align = if item.align_self == AlignSelf::Auto {
-            align_items
-        } else {
-            match item.align_self {
-                AlignSelf::Auto => align_items,
-                AlignSelf::FlexStart => AlignItems::FlexStart,
-                AlignSelf::FlexEnd => AlignItems::FlexEnd,
-                AlignSelf::Center => AlignItems::Center,
-                AlignSelf::Baseline => AlignItems::Baseline,
-                AlignSelf::Stretch => AlignItems::Stretch,
-            }
-        };
-
         // Per CSS spec: stretch only applies if cross size is "auto"
-        // Items with explicit height/width should NOT be stretched
-        if align == AlignItems::Stretch && !item.has_explicit_cross_size {
+        // Items with explicit height/width, or auto cross margins, should NOT be stretched
+        if item.alignment(align_items) == AlignItems::Stretch
+            && !item.has_explicit_cross_size
+            && !item.has_auto_cross_margin()
+        {
             // Determine the stretch target based on container cross size
+            let edges = item.cross_padding_border + item.cross_margin_start + item.cross_margin_end;
             let stretch_target = if has_definite_cross_size {
                 // Container has definite height - stretch to fill container
-                container_cross - item.cross_margin_start - item.cross_margin_end
+                container_cross - edges
             } else {
                 // Container has auto height - stretch to match tallest item in line
-                line_cross_size - item.cross_margin_start - item.cross_margin_end
+                line_cross_size - edges
             };
             
             // Stretch, but never below content size
@@ -694,13 +791,23 @@ fn calculate_cross_sizes(line: &mut FlexLine, container_cross: f32, align_items:
     // Set line cross size (largest item outer cross size after stretch)
     line.cross_size = line.items
         .iter()
-        .map(|i| i.cross_size + i.cross_margin_start + i.cross_margin_end)
+        .map(|i| i.outer_cross_size())
         .fold(0.0, f32::max);
 }
 
 /// Get the content-based cross size for a layout box.
 /// This computes the hypothetical cross size based on content, intrinsic sizing, or children.
-fn get_content_cross_size(layout_box: &LayoutBox) -> f32 {
+fn get_content_cross_size(layout_box: &LayoutBox, cross_axis: Axis) -> f32 {
+    // Column items are as wide as their content
+    if cross_axis == Axis::Horizontal {
+        return match layout_box.box_type {
+            BoxType::Image { .. } | BoxType::FormControl(_) => {
+                get_intrinsic_cross_size(&layout_box.box_type, Axis::Vertical, &layout_box.style)
+            }
+            _ => layout_box.intrinsic_inline_size(IntrinsicSizingMode::MaxContent),
+        };
+    }
+
     // If the box already has a computed height from layout, use it
     if layout_box.dimensions.content.height > 0.0 {
         return layout_box.dimensions.content.height;
@@ -738,6 +845,8 @@ fn get_content_cross_size(layout_box: &LayoutBox) -> f32 {
             FormControlType::Button { .. } => font_size * 1.5 + 12.0,
             FormControlType::Checkbox { .. } | FormControlType::Radio { .. } => font_size * 1.2,
             FormControlType::Select { .. } => font_size * 1.5 + 8.0,
+            FormControlType::Range { .. } => font_size * 1.2,
+            FormControlType::ColorSwatch { .. } => font_size * 1.5 + 4.0,
         };
     }
     
@@ -841,6 +950,27 @@ fn distribute_main_axis(
     let total_gaps = main_gap * (line.items.len().saturating_sub(1)) as f32;
     let free_space = (container_main - total_item_size - total_gaps).max(0.0);
 
+    // Auto margins take all positive free space, leaving none for justify-content
+    let auto_margins = line
+        .items
+        .iter()
+        .map(|i| i.main_margin_start_auto as usize + i.main_margin_end_auto as usize)
+        .sum::<usize>();
+    let free_space = if auto_margins > 0 {
+        let share = free_space / auto_margins as f32;
+        for item in &mut line.items {
+            if item.main_margin_start_auto {
+                item.main_margin_start += share;
+            }
+            if item.main_margin_end_auto {
+                item.main_margin_end += share;
+            }
+        }
+        0.0
+    } else {
+        free_space
+    };
+
     let (initial_offset, spacing) = match justify_content {
         JustifyContent::FlexStart => (0.0, main_gap),
         JustifyContent::FlexEnd => (free_space, main_gap),
@@ -883,29 +1013,22 @@ fn distribute_main_axis(
 /// Align items on cross axis within line.
 fn align_cross_axis(line: &mut FlexLine, align_items: AlignItems) {
     for item in &mut line.items {
-        let align = if item.align_self == AlignSelf::Auto {
-            align_items
-        } else {
-            match item.align_self {
-                AlignSelf::Auto => align_items,
-                AlignSelf::FlexStart => AlignItems::FlexStart,
-                AlignSelf::FlexEnd => AlignItems::FlexEnd,
-                AlignSelf::Center => AlignItems::Center,
-                AlignSelf::Baseline => AlignItems::Baseline,
-                AlignSelf::Stretch => AlignItems::Stretch,
-            }
-        };
-
-        let outer_cross = item.cross_size + item.cross_margin_start + item.cross_margin_end;
-        let free_space = (line.cross_size - outer_cross).max(0.0);
-
-        item.cross_position = match align {
-            AlignItems::FlexStart => item.cross_margin_start,
-            AlignItems::FlexEnd => free_space + item.cross_margin_start,
-            AlignItems::Center => free_space / 2.0 + item.cross_margin_start,
-            AlignItems::Baseline => item.cross_margin_start, // Simplified
-            AlignItems::Stretch => item.cross_margin_start,
+        let free_space = (line.cross_size - item.outer_cross_size()).max(0.0);
+
+        // Auto margins take the free space and override align-self
+        let offset = match (item.cross_margin_start_auto, item.cross_margin_end_auto) {
+            (true, true) => free_space / 2.0,
+            (true, false) => free_space,
+            (false, true) => 0.0,
+            (false, false) => match item.alignment(align_items) {
+                AlignItems::FlexStart => 0.0,
+                AlignItems::FlexEnd => free_space,
+                AlignItems::Center => free_space / 2.0,
+                AlignItems::Baseline => 0.0, // Simplified
+                AlignItems::Stretch => 0.0,
+            },
         };
+        item.cross_position = offset + item.cross_margin_start;
     }
 }
 
@@ -952,8 +1075,10 @@ fn apply_positions(
                 ),
             };
 
-            let abs_x = origin_x + rel_x;
-            let abs_y = origin_y + rel_y;
+            // Positions are of the border box; the content box sits inside
+            let d = &item.layout_box.dimensions;
+            let abs_x = origin_x + rel_x + d.padding.left + d.border.left;
+            let abs_y = origin_y + rel_y + d.padding.top + d.border.top;
             
             trace!(
                 ?rel_x,
@@ -1034,6 +1159,18 @@ fn get_intrinsic_main_size(box_type: &crate::BoxType, main_axis: Axis, style: &r
                         Axis::Vertical => font_size * 1.5 + 8.0,
                     }
                 }
+                FormControlType::Range { .. } => {
+                    match main_axis {
+                        Axis::Horizontal => font_size * 8.0,
+                        Axis::Vertical => font_size * 1.2,
+                    }
+                }
+                FormControlType::ColorSwatch { .. } => {
+                    match main_axis {
+                        Axis::Horizontal => font_size * 3.0,
+                        Axis::Vertical => font_size * 1.5 + 4.0,
+                    }
+                }
             }
         }
         crate::BoxType::Image { natural_width, natural_height, .. } => {
@@ -1096,6 +1233,18 @@ fn get_intrinsic_cross_size(box_type: &crate::BoxType, main_axis: Axis, style: &
                         Axis::Vertical => font_size * 1.5 + 8.0,
                     }
                 }
+                FormControlType::Range { .. } => {
+                    match cross_axis {
+                        Axis::Horizontal => font_size * 8.0,
+                        Axis::Vertical => font_size * 1.2,
+                    }
+                }
+                FormControlType::ColorSwatch { .. } => {
+                    match cross_axis {
+                        Axis::Horizontal => font_size * 3.0,
+                        Axis::Vertical => font_size * 1.5 + 4.0,
+                    }
+                }
             }
         }
         crate::BoxType::Image { natural_width, natural_height, .. } => {
@@ -1133,7 +1282,8 @@ fn resolve_length(length: &Length, container_size: f32) -> f32 {
 /// Resolve a max Length (returns f32::INFINITY for Auto).
 fn resolve_max_length(length: &Length, container_size: f32) -> f32 {
     match length {
-        Length::Auto => f32::INFINITY,
+        // Keywords the caller didn't resolve leave the size to the content
+        length if length.is_content_sized() => f32::INFINITY,
         _ => resolve_length(length, container_size),
     }
 }
@@ -1375,4 +1525,197 @@ mod tests {
             child2_height
         );
     }
+
+    fn row_container(width: f32, height: f32, children: Vec<LayoutBox>) -> (LayoutBox, Dimensions) {
+        let mut style = ComputedStyle::new();
+        style.display = rustkit_css::Display::Flex;
+        let mut container = LayoutBox::new(BoxType::Block, style);
+        container.children = children;
+        let containing = Dimensions {
+            content: Rect::new(0.0, 0.0, width, height),
+            ..Default::default()
+        };
+        (container, containing)
+    }
+
+    fn sized_block(width: f32, height: f32) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.width = Length::Px(width);
+        style.height = Length::Px(height);
+        LayoutBox::new(BoxType::Block, style)
+    }
+
+    fn text_block(text: &str, min_width: Length) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.min_width = min_width;
+        let mut item = LayoutBox::new(BoxType::Block, style);
+        item.children.push(LayoutBox::new(BoxType::Text(text.to_string()), ComputedStyle::new()));
+        item
+    }
+
+    #[test]
+    fn test_flex_intrinsic_basis_and_width() {
+        let mut min_basis = text_block("several short words", Length::Px(0.0));
+        min_basis.style.flex_basis = rustkit_css::FlexBasis::MinContent;
+        let mut fit_width = text_block("several short words", Length::Px(0.0));
+        fit_width.style.width = Length::FitContent;
+        let min = min_basis.intrinsic_inline_size(IntrinsicSizingMode::MinContent);
+        let max = fit_width.intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+        let (mut container, containing) = row_container(1000.0, 100.0, vec![min_basis, fit_width]);
+
+        layout_flex_container(&mut container, &containing);
+
+        assert!((container.children[0].dimensions.content.width - min).abs() < 0.01);
+        assert!((container.children[1].dimensions.content.width - max).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_flex_items_with_aspect_ratio() {
+        // Row item with a fixed height takes its width from the ratio
+        let mut from_height = sized_block(0.0, 50.0);
+        from_height.style.width = Length::Auto;
+        from_height.style.aspect_ratio = Some(2.0);
+        // Row item with a fixed width takes its height from the ratio
+        let mut from_width = sized_block(120.0, 0.0);
+        from_width.style.height = Length::Auto;
+        from_width.style.aspect_ratio = Some(2.0);
+        from_width.style.flex_shrink = 0.0;
+        let (mut container, containing) = row_container(400.0, 200.0, vec![from_height, from_width]);
+        container.style.align_items = AlignItems::FlexStart;
+
+        layout_flex_container(&mut container, &containing);
+
+        assert_eq!(container.children[0].dimensions.content.width, 100.0);
+        assert_eq!(container.children[1].dimensions.content.height, 60.0);
+    }
+
+    #[test]
+    fn test_flex_base_size_is_max_content() {
+        let (mut container, containing) = row_container(
+            1000.0,
+            100.0,
+            vec![text_block("a", Length::Auto), text_block("a much longer label", Length::Auto)],
+        );
+        let expected: Vec<f32> = container
+            .children
+            .iter()
+            .map(|c| c.intrinsic_inline_size(IntrinsicSizingMode::MaxContent))
+            .collect();
+
+        layout_flex_container(&mut container, &containing);
+
+        let short = &container.children[0].dimensions.content;
+        let long = &container.children[1].dimensions.content;
+        assert!((short.width - expected[0]).abs() < 0.01);
+        assert!((long.width - expected[1]).abs() < 0.01);
+        assert!(long.width > short.width);
+        assert!((long.x - short.right()).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_automatic_minimum_size_stops_at_min_content() {
+        let word = "Supercalifragilisticexpialidocious";
+        let (mut container, containing) = row_container(50.0, 100.0, vec![text_block(word, Length::Auto)]);
+        let min_content = container.children[0].intrinsic_inline_size(IntrinsicSizingMode::MinContent);
+        assert!(min_content > 50.0);
+
+        layout_flex_container(&mut container, &containing);
+        assert!((container.children[0].dimensions.content.width - min_content).abs() < 0.01);
+
+        // An explicit min-width: 0 lets the item shrink into the container
+        let (mut container, containing) = row_container(50.0, 100.0, vec![text_block(word, Length::Zero)]);
+        layout_flex_container(&mut container, &containing);
+        assert!((container.children[0].dimensions.content.width - 50.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_shrink_is_weighted_by_base_size() {
+        let mut first = text_block("", Length::Zero);
+        first.style.flex_basis = rustkit_css::FlexBasis::Length(200.0);
+        let mut second = text_block("", Length::Zero);
+        second.style.flex_basis = rustkit_css::FlexBasis::Length(400.0);
+        let (mut container, containing) = row_container(300.0, 100.0, vec![first, second]);
+
+        layout_flex_container(&mut container, &containing);
+
+        // 300px of overflow split 1:2
+        assert!((container.children[0].dimensions.content.width - 100.0).abs() < 0.01);
+        assert!((container.children[1].dimensions.content.width - 200.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_max_size_violation_redistributes_free_space() {
+        let mut first = text_block("", Length::Zero);
+        first.style.flex_basis = rustkit_css::FlexBasis::Length(0.0);
+        first.style.flex_grow = 1.0;
+        first.style.max_width = Length::Px(50.0);
+        let mut second = text_block("", Length::Zero);
+        second.style.flex_basis = rustkit_css::FlexBasis::Length(0.0);
+        second.style.flex_grow = 1.0;
+        let (mut container, containing) = row_container(400.0, 100.0, vec![first, second]);
+
+        layout_flex_container(&mut container, &containing);
+
+        assert!((container.children[0].dimensions.content.width - 50.0).abs() < 0.01);
+        assert!((container.children[1].dimensions.content.width - 350.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_auto_margin_pushes_item_to_end() {
+        let mut pushed = sized_block(100.0, 50.0);
+        pushed.style.margin_left = Length::Auto;
+        let (mut container, containing) = row_container(400.0, 100.0, vec![sized_block(100.0, 50.0), pushed]);
+        container.style.justify_content = JustifyContent::Center;
+
+        layout_flex_container(&mut container, &containing);
+
+        // Auto margins win over justify-content
+        assert_eq!(container.children[0].dimensions.content.x, 0.0);
+        assert_eq!(container.children[1].dimensions.content.x, 300.0);
+        assert_eq!(container.children[1].dimensions.margin.left, 200.0);
+    }
+
+    #[test]
+    fn test_auto_margins_center_on_both_axes() {
+        let mut centered = sized_block(100.0, 50.0);
+        centered.style.margin_left = Length::Auto;
+        centered.style.margin_right = Length::Auto;
+        centered.style.margin_top = Length::Auto;
+        centered.style.margin_bottom = Length::Auto;
+        let (mut container, containing) = row_container(400.0, 200.0, vec![centered]);
+        container.style.height = Length::Px(200.0);
+
+        layout_flex_container(&mut container, &containing);
+
+        let content = &container.children[0].dimensions.content;
+        assert_eq!((content.x, content.y), (150.0, 75.0));
+    }
+
+    #[test]
+    fn test_auto_cross_margin_prevents_stretch() {
+        let mut item = text_block("label", Length::Auto);
+        item.style.margin_top = Length::Auto;
+        let (mut container, containing) = row_container(400.0, 200.0, vec![item]);
+        container.style.height = Length::Px(200.0);
+
+        layout_flex_container(&mut container, &containing);
+
+        let content = &container.children[0].dimensions.content;
+        assert!(content.height < 200.0);
+        assert!((content.bottom() - 200.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_padding_and_border_are_part_of_outer_size() {
+        let mut padded = sized_block(100.0, 50.0);
+        padded.dimensions.padding = EdgeSizes { left: 10.0, right: 10.0, top: 0.0, bottom: 0.0 };
+        padded.dimensions.border = EdgeSizes { left: 2.0, right: 2.0, top: 0.0, bottom: 0.0 };
+        let (mut container, containing) = row_container(400.0, 100.0, vec![padded, sized_block(100.0, 50.0)]);
+
+        layout_flex_container(&mut container, &containing);
+
+        assert_eq!(container.children[0].dimensions.content.x, 12.0);
+        assert_eq!(container.children[0].dimensions.content.width, 100.0);
+        assert_eq!(container.children[1].dimensions.content.x, 124.0);
+    }
 }
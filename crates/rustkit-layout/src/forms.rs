@@ -1,8 +1,12 @@
 //! # Form Element Layout
 //!
 //! Layout and rendering support for form elements (input, textarea, button, select).
+//!
+//! Controls are painted with a [`FormTheme`]. The built-in themes follow the
+//! macOS light and dark appearance; hosts can swap in the system accent
+//! color or a theme of their own.
 
-use crate::{ComputedStyle, DisplayCommand, Rect};
+use crate::{BorderRadius, ComputedStyle, DisplayCommand, Rect};
 use rustkit_css::Color;
 
 /// Input element visual state.
@@ -15,6 +19,141 @@ pub enum InputState {
     Disabled,
 }
 
+/// Light or dark appearance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Colors and metrics form controls are painted with.
+///
+/// Author styles still win: a control with its own background, border or
+/// text color uses it instead of the theme's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormTheme {
+    /// Appearance the theme was made for.
+    pub color_scheme: ColorScheme,
+    /// Accent color of checked boxes and radios and of focus rings.
+    pub accent: Color,
+    /// Background of text fields, select boxes, checkboxes and radios.
+    pub field_background: Color,
+    /// Border of text fields, select boxes, checkboxes and radios.
+    pub field_border: Color,
+    /// Background of push buttons.
+    pub button_background: Color,
+    /// Border of push buttons.
+    pub button_border: Color,
+    /// Text color.
+    pub text: Color,
+    /// Placeholder text color.
+    pub placeholder: Color,
+    /// Background of disabled controls.
+    pub disabled_background: Color,
+    /// Text and border color of disabled controls.
+    pub disabled_text: Color,
+    /// Color of the mark in a checked control, drawn over the accent.
+    pub checkmark: Color,
+    /// Focus ring color.
+    pub focus_ring: Color,
+    /// Focus ring width in pixels.
+    pub focus_ring_width: f32,
+    /// Corner radius of buttons and fields.
+    pub corner_radius: f32,
+}
+
+impl FormTheme {
+    /// macOS light appearance with the default blue accent.
+    pub fn light() -> Self {
+        let accent = Color::from_rgb(0, 122, 255);
+        Self {
+            color_scheme: ColorScheme::Light,
+            accent,
+            field_background: Color::WHITE,
+            field_border: Color::from_rgb(191, 191, 191),
+            button_background: Color::WHITE,
+            button_border: Color::from_rgb(197, 197, 197),
+            text: Color::new(0, 0, 0, 0.85),
+            placeholder: Color::new(0, 0, 0, 0.25),
+            disabled_background: Color::from_rgb(246, 246, 246),
+            disabled_text: Color::new(0, 0, 0, 0.25),
+            checkmark: Color::WHITE,
+            focus_ring: focus_ring_color(accent),
+            focus_ring_width: 3.0,
+            corner_radius: 5.0,
+        }
+    }
+
+    /// macOS dark appearance with the default blue accent.
+    pub fn dark() -> Self {
+        let accent = Color::from_rgb(10, 132, 255);
+        Self {
+            color_scheme: ColorScheme::Dark,
+            accent,
+            field_background: Color::from_rgb(30, 30, 30),
+            field_border: Color::from_rgb(85, 85, 85),
+            button_background: Color::from_rgb(101, 101, 101),
+            button_border: Color::from_rgb(101, 101, 101),
+            text: Color::new(255, 255, 255, 0.85),
+            placeholder: Color::new(255, 255, 255, 0.25),
+            disabled_background: Color::from_rgb(50, 50, 50),
+            disabled_text: Color::new(255, 255, 255, 0.25),
+            checkmark: Color::WHITE,
+            focus_ring: focus_ring_color(accent),
+            focus_ring_width: 3.0,
+            corner_radius: 5.0,
+        }
+    }
+
+    /// The built-in theme for `color_scheme`.
+    pub fn for_scheme(color_scheme: ColorScheme) -> Self {
+        match color_scheme {
+            ColorScheme::Light => Self::light(),
+            ColorScheme::Dark => Self::dark(),
+        }
+    }
+
+    /// This theme with another accent color, e.g. the one picked in the
+    /// system settings. The focus ring follows the accent.
+    pub fn with_accent(mut self, accent: Color) -> Self {
+        self.accent = accent;
+        self.focus_ring = focus_ring_color(accent);
+        self
+    }
+
+    /// The author's background or border color if it is visible, else the
+    /// theme's.
+    pub(crate) fn pick(author: Color, theme: Color) -> Color {
+        if author.a > 0.0 {
+            author
+        } else {
+            theme
+        }
+    }
+
+    /// The text color of a control. Controls don't take the page's text
+    /// color, so the initial (black) color counts as unstyled.
+    pub(crate) fn text_color(&self, author: Color) -> Color {
+        if author == Color::BLACK || author.a <= 0.0 {
+            self.text
+        } else {
+            author
+        }
+    }
+}
+
+impl Default for FormTheme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+/// Focus rings are the accent color, half transparent.
+fn focus_ring_color(accent: Color) -> Color {
+    Color::new(accent.r, accent.g, accent.b, 0.5)
+}
+
 /// Caret rendering information.
 #[derive(Debug, Clone)]
 pub struct CaretInfo {
@@ -45,6 +184,18 @@ impl Default for CaretInfo {
     }
 }
 
+/// The focused text field and its caret, for [`DisplayList::build_with_caret`].
+///
+/// [`DisplayList::build_with_caret`]: crate::DisplayList::build_with_caret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextCaret {
+    /// DOM node ID of the field.
+    pub element_id: usize,
+    /// Caret position in characters from the start of the value, or `None`
+    /// while the caret is blinked off.
+    pub position: Option<usize>,
+}
+
 /// Selection rendering information.
 #[derive(Debug, Clone)]
 pub struct SelectionInfo {
@@ -161,22 +312,21 @@ pub fn render_input(
     placeholder: &str,
     style: &ComputedStyle,
     is_password: bool,
+    theme: &FormTheme,
 ) -> Vec<DisplayCommand> {
     let mut commands = Vec::new();
 
     // 1. Background
     let bg_color = match layout.state {
-        InputState::Disabled => Color::from_rgb(240, 240, 240), // Grayed out
-        _ => style.background_color,
+        InputState::Disabled => theme.disabled_background,
+        _ => FormTheme::pick(style.background_color, theme.field_background),
     };
     commands.push(DisplayCommand::SolidColor(bg_color, layout.border_box));
 
     // 2. Border
     let border_color = match layout.state {
-        InputState::Focused => Color::from_rgb(51, 144, 255), // Focus blue
-        InputState::Hover => Color::from_rgb(100, 100, 100),
-        InputState::Disabled => Color::from_rgb(200, 200, 200),
-        _ => style.border_top_color,
+        InputState::Disabled => theme.disabled_text,
+        _ => FormTheme::pick(style.border_top_color, theme.field_border),
     };
 
     commands.push(DisplayCommand::Border {
@@ -201,30 +351,17 @@ pub fn render_input(
         _ => 14.0,
     };
 
+    let value_color = if layout.state == InputState::Disabled {
+        theme.disabled_text
+    } else {
+        theme.text_color(style.color)
+    };
     let (text_to_render, text_color) = if layout.show_placeholder && value.is_empty() {
-        (
-            placeholder.to_string(),
-            Color::from_rgb(150, 150, 150), // Placeholder gray
-        )
+        (placeholder.to_string(), theme.placeholder)
     } else if is_password && !value.is_empty() {
-        let dots = "●".repeat(value.chars().count());
-        (
-            dots,
-            if layout.state == InputState::Disabled {
-                Color::from_rgb(128, 128, 128)
-            } else {
-                style.color
-            },
-        )
+        ("●".repeat(value.chars().count()), value_color)
     } else {
-        (
-            value.to_string(),
-            if layout.state == InputState::Disabled {
-                Color::from_rgb(128, 128, 128)
-            } else {
-                style.color
-            },
-        )
+        (value.to_string(), value_color)
     };
 
     if !text_to_render.is_empty() {
@@ -254,6 +391,11 @@ pub fn render_input(
         }
     }
 
+    // 6. Focus ring
+    if layout.state == InputState::Focused {
+        commands.push(focus_ring(layout.border_box, theme));
+    }
+
     commands
 }
 
@@ -263,21 +405,22 @@ pub fn render_button(
     label: &str,
     style: &ComputedStyle,
     state: InputState,
+    theme: &FormTheme,
 ) -> Vec<DisplayCommand> {
     let mut commands = Vec::new();
 
     // Background with state-based coloring
+    let background = FormTheme::pick(style.background_color, theme.button_background);
     let bg_color = match state {
-        InputState::Hover => lighten_color(&style.background_color, 0.1),
-        InputState::Focused => style.background_color,
-        InputState::Disabled => Color::from_rgb(200, 200, 200),
-        _ => style.background_color,
+        InputState::Hover => lighten_color(&background, 0.1),
+        InputState::Disabled => theme.disabled_background,
+        _ => background,
     };
     commands.push(DisplayCommand::SolidColor(bg_color, border_box));
 
     // Border
     commands.push(DisplayCommand::Border {
-        color: style.border_top_color,
+        color: FormTheme::pick(style.border_top_color, theme.button_border),
         rect: border_box,
         top: 1.0,
         right: 1.0,
@@ -296,9 +439,9 @@ pub fn render_button(
     let text_y = border_box.y + (border_box.height + font_size) / 2.0;
 
     let text_color = if state == InputState::Disabled {
-        Color::from_rgb(128, 128, 128)
+        theme.disabled_text
     } else {
-        style.color
+        theme.text_color(style.color)
     };
 
     commands.push(DisplayCommand::Text {
@@ -316,6 +459,10 @@ pub fn render_button(
         },
     });
 
+    if state == InputState::Focused {
+        commands.push(focus_ring(border_box, theme));
+    }
+
     commands
 }
 
@@ -325,6 +472,7 @@ pub fn render_checkbox(
     checked: bool,
     indeterminate: bool,
     state: InputState,
+    theme: &FormTheme,
 ) -> Vec<DisplayCommand> {
     let mut commands = Vec::new();
 
@@ -332,17 +480,18 @@ pub fn render_checkbox(
     let box_size = border_box.height.min(16.0);
     let checkbox_rect = Rect::new(border_box.x, border_box.y, box_size, box_size);
 
-    let bg_color = if checked && state != InputState::Disabled {
-        Color::from_rgb(51, 144, 255) // Blue when checked
-    } else {
-        Color::from_rgb(255, 255, 255)
+    let marked = checked || indeterminate;
+    let bg_color = match state {
+        InputState::Disabled => theme.disabled_background,
+        _ if marked => theme.accent,
+        _ => theme.field_background,
     };
     commands.push(DisplayCommand::SolidColor(bg_color, checkbox_rect));
 
     let border_color = match state {
-        InputState::Focused => Color::from_rgb(51, 144, 255),
-        InputState::Disabled => Color::from_rgb(200, 200, 200),
-        _ => Color::from_rgb(150, 150, 150),
+        InputState::Disabled => theme.disabled_text,
+        _ if marked => theme.accent,
+        _ => theme.field_border,
     };
     commands.push(DisplayCommand::Border {
         color: border_color,
@@ -354,8 +503,12 @@ pub fn render_checkbox(
     });
 
     // Checkmark or indeterminate line
-    if checked || indeterminate {
-        let check_color = Color::from_rgb(255, 255, 255);
+    if marked {
+        let check_color = if state == InputState::Disabled {
+            theme.disabled_text
+        } else {
+            theme.checkmark
+        };
         if indeterminate {
             // Horizontal line
             let line_rect = Rect::new(
@@ -390,55 +543,196 @@ pub fn render_checkbox(
         }
     }
 
+    if state == InputState::Focused {
+        commands.push(focus_ring(checkbox_rect, theme));
+    }
+
     commands
 }
 
 /// Generate display commands for a radio button.
-pub fn render_radio(border_box: Rect, checked: bool, state: InputState) -> Vec<DisplayCommand> {
+pub fn render_radio(border_box: Rect, checked: bool, state: InputState, theme: &FormTheme) -> Vec<DisplayCommand> {
     let mut commands = Vec::new();
 
-    // For now, render as a circle approximation (square with note about needing circle support)
     let box_size = border_box.height.min(16.0);
     let radio_rect = Rect::new(border_box.x, border_box.y, box_size, box_size);
+    let center = (radio_rect.x + box_size / 2.0, radio_rect.y + box_size / 2.0);
 
-    // Outer circle (rendered as square for now)
-    let bg_color = Color::from_rgb(255, 255, 255);
-    commands.push(DisplayCommand::SolidColor(bg_color, radio_rect));
-
-    let border_color = match state {
-        InputState::Focused => Color::from_rgb(51, 144, 255),
-        InputState::Disabled => Color::from_rgb(200, 200, 200),
-        _ => Color::from_rgb(150, 150, 150),
+    // Checked radios fill with the accent, like macOS
+    let (bg_color, border_color) = match state {
+        InputState::Disabled => (theme.disabled_background, theme.disabled_text),
+        _ if checked => (theme.accent, theme.accent),
+        _ => (theme.field_background, theme.field_border),
     };
-    commands.push(DisplayCommand::Border {
-        color: border_color,
+    commands.push(DisplayCommand::FillEllipse {
         rect: radio_rect,
-        top: 1.0,
-        right: 1.0,
-        bottom: 1.0,
-        left: 1.0,
+        color: bg_color,
+    });
+    commands.push(DisplayCommand::StrokeCircle {
+        cx: center.0,
+        cy: center.1,
+        radius: box_size / 2.0 - 0.5,
+        color: border_color,
+        width: 1.0,
     });
 
     // Inner dot when checked
     if checked {
         let dot_color = if state == InputState::Disabled {
-            Color::from_rgb(150, 150, 150)
+            theme.disabled_text
         } else {
-            Color::from_rgb(51, 144, 255)
+            theme.checkmark
         };
-        let dot_size = box_size * 0.5;
-        let dot_rect = Rect::new(
-            radio_rect.x + (box_size - dot_size) / 2.0,
-            radio_rect.y + (box_size - dot_size) / 2.0,
-            dot_size,
-            dot_size,
-        );
-        commands.push(DisplayCommand::SolidColor(dot_color, dot_rect));
+        commands.push(DisplayCommand::FillCircle {
+            cx: center.0,
+            cy: center.1,
+            radius: box_size * 0.2,
+            color: dot_color,
+        });
+    }
+
+    if state == InputState::Focused {
+        commands.push(focus_ring(radio_rect, theme));
+    }
+
+    commands
+}
+
+/// Height of a range slider's track.
+const RANGE_TRACK_HEIGHT: f32 = 4.0;
+
+/// Radius of a range slider's thumb in a control `height` tall.
+fn range_thumb_radius(height: f32) -> f32 {
+    height.min(20.0) / 2.0
+}
+
+/// Where along a range slider a point at `x` falls, from 0 at the left end
+/// of the thumb's travel to 1 at the right.
+pub fn range_fraction_at(border_box: Rect, x: f32) -> f32 {
+    let radius = range_thumb_radius(border_box.height);
+    let travel = border_box.width - 2.0 * radius;
+    if travel <= 0.0 {
+        return 0.0;
+    }
+    ((x - border_box.x - radius) / travel).clamp(0.0, 1.0)
+}
+
+/// Generate display commands for a range slider: a rounded track, filled
+/// with the accent up to the thumb.
+pub fn render_range(border_box: Rect, fraction: f32, state: InputState, theme: &FormTheme) -> Vec<DisplayCommand> {
+    let mut commands = Vec::new();
+
+    let radius = range_thumb_radius(border_box.height);
+    let center_y = border_box.y + border_box.height / 2.0;
+    let thumb_x = border_box.x + radius + fraction.clamp(0.0, 1.0) * (border_box.width - 2.0 * radius).max(0.0);
+
+    let track = Rect::new(
+        border_box.x,
+        center_y - RANGE_TRACK_HEIGHT / 2.0,
+        border_box.width,
+        RANGE_TRACK_HEIGHT,
+    );
+    let track_radius = BorderRadius::uniform(RANGE_TRACK_HEIGHT / 2.0);
+    let (track_color, fill_color) = match state {
+        InputState::Disabled => (theme.disabled_background, theme.disabled_text),
+        _ => (theme.field_border, theme.accent),
+    };
+    commands.push(DisplayCommand::RoundedRect {
+        color: track_color,
+        rect: track,
+        radius: track_radius,
+    });
+    commands.push(DisplayCommand::RoundedRect {
+        color: fill_color,
+        rect: Rect::new(track.x, track.y, thumb_x - track.x, track.height),
+        radius: track_radius,
+    });
+
+    let thumb_color = match state {
+        InputState::Disabled => theme.disabled_background,
+        _ => theme.button_background,
+    };
+    commands.push(DisplayCommand::FillCircle {
+        cx: thumb_x,
+        cy: center_y,
+        radius: radius - 0.5,
+        color: thumb_color,
+    });
+    commands.push(DisplayCommand::StrokeCircle {
+        cx: thumb_x,
+        cy: center_y,
+        radius: radius - 0.5,
+        color: theme.button_border,
+        width: 1.0,
+    });
+
+    if state == InputState::Focused {
+        let thumb = Rect::new(thumb_x - radius, center_y - radius, 2.0 * radius, 2.0 * radius);
+        commands.push(focus_ring(thumb, theme));
     }
 
     commands
 }
 
+/// Generate display commands for a color input: a push button holding a
+/// swatch of `color`.
+pub fn render_color_swatch(border_box: Rect, color: Color, state: InputState, theme: &FormTheme) -> Vec<DisplayCommand> {
+    let mut commands = Vec::new();
+
+    let (background, border) = match state {
+        InputState::Disabled => (theme.disabled_background, theme.disabled_text),
+        _ => (theme.button_background, theme.button_border),
+    };
+    // A 1px border as a rounded rect behind the inset background
+    commands.push(DisplayCommand::RoundedRect {
+        color: border,
+        rect: border_box,
+        radius: BorderRadius::uniform(theme.corner_radius),
+    });
+    commands.push(DisplayCommand::RoundedRect {
+        color: background,
+        rect: inset(border_box, 1.0),
+        radius: BorderRadius::uniform((theme.corner_radius - 1.0).max(0.0)),
+    });
+
+    let swatch = inset(border_box, 4.0);
+    commands.push(DisplayCommand::SolidColor(color, swatch));
+    commands.push(DisplayCommand::Border {
+        color: theme.field_border,
+        rect: swatch,
+        top: 1.0,
+        right: 1.0,
+        bottom: 1.0,
+        left: 1.0,
+    });
+
+    if state == InputState::Focused {
+        commands.push(focus_ring(border_box, theme));
+    }
+
+    commands
+}
+
+/// `rect` shrunk by `amount` on every side.
+fn inset(rect: Rect, amount: f32) -> Rect {
+    Rect::new(
+        rect.x + amount,
+        rect.y + amount,
+        (rect.width - 2.0 * amount).max(0.0),
+        (rect.height - 2.0 * amount).max(0.0),
+    )
+}
+
+/// A focus ring just outside `rect`.
+fn focus_ring(rect: Rect, theme: &FormTheme) -> DisplayCommand {
+    DisplayCommand::FocusRing {
+        rect,
+        color: theme.focus_ring,
+        width: theme.focus_ring_width,
+        offset: theme.focus_ring_width,
+    }
+}
+
 /// Lighten a color by a factor (0.0 - 1.0).
 fn lighten_color(color: &Color, factor: f32) -> Color {
     let factor = factor.clamp(0.0, 1.0);
@@ -484,7 +778,7 @@ mod tests {
         };
 
         let style = ComputedStyle::new();
-        let commands = render_input(&layout, "Hello", "placeholder", &style, false);
+        let commands = render_input(&layout, "Hello", "placeholder", &style, false, &FormTheme::default());
 
         assert!(!commands.is_empty());
     }
@@ -493,7 +787,7 @@ mod tests {
     fn test_render_button() {
         let rect = Rect::new(0.0, 0.0, 100.0, 30.0);
         let style = ComputedStyle::new();
-        let commands = render_button(rect, "Click me", &style, InputState::Normal);
+        let commands = render_button(rect, "Click me", &style, InputState::Normal, &FormTheme::default());
 
         assert!(!commands.is_empty());
     }
@@ -502,15 +796,56 @@ mod tests {
     fn test_render_checkbox() {
         let rect = Rect::new(0.0, 0.0, 16.0, 16.0);
 
-        let unchecked = render_checkbox(rect, false, false, InputState::Normal);
-        let checked = render_checkbox(rect, true, false, InputState::Normal);
-        let indeterminate = render_checkbox(rect, false, true, InputState::Normal);
+        let theme = FormTheme::default();
+        let unchecked = render_checkbox(rect, false, false, InputState::Normal, &theme);
+        let checked = render_checkbox(rect, true, false, InputState::Normal, &theme);
+        let indeterminate = render_checkbox(rect, false, true, InputState::Normal, &theme);
 
         // Checked should have more commands (for checkmark)
         assert!(checked.len() > unchecked.len());
         assert!(indeterminate.len() > unchecked.len());
     }
 
+    #[test]
+    fn test_theme_accent_and_focus_ring() {
+        let rect = Rect::new(0.0, 0.0, 16.0, 16.0);
+        let purple = Color::from_rgb(149, 61, 150);
+        let theme = FormTheme::dark().with_accent(purple);
+        assert_eq!(theme.color_scheme, ColorScheme::Dark);
+        assert_eq!(theme.focus_ring, Color::new(149, 61, 150, 0.5));
+
+        let commands = render_radio(rect, true, InputState::Focused, &theme);
+        assert!(matches!(commands[0], DisplayCommand::FillEllipse { color, .. } if color == purple));
+        assert!(matches!(
+            commands.last(),
+            Some(DisplayCommand::FocusRing { color, width: 3.0, .. }) if *color == theme.focus_ring
+        ));
+
+        // Controls keep author colors but not the page's initial text color
+        let mut style = ComputedStyle::new();
+        style.background_color = Color::from_rgb(255, 255, 0);
+        let commands = render_button(rect, "OK", &style, InputState::Normal, &theme);
+        assert!(matches!(commands[0], DisplayCommand::SolidColor(color, _) if color == style.background_color));
+        assert!(matches!(&commands[2], DisplayCommand::Text { color, .. } if *color == theme.text));
+    }
+
+    #[test]
+    fn test_range_thumb_tracks_fraction() {
+        let theme = FormTheme::default();
+        let rect = Rect::new(10.0, 0.0, 120.0, 20.0);
+        let commands = render_range(rect, 0.5, InputState::Focused, &theme);
+
+        // Track, accent fill up to the thumb, thumb, focus ring
+        assert!(matches!(commands[1], DisplayCommand::RoundedRect { color, rect, .. }
+            if color == theme.accent && rect.width == 60.0));
+        assert!(matches!(commands[2], DisplayCommand::FillCircle { cx, .. } if cx == 70.0));
+        assert!(matches!(commands.last(), Some(DisplayCommand::FocusRing { .. })));
+
+        assert_eq!(range_fraction_at(rect, 70.0), 0.5);
+        assert_eq!(range_fraction_at(rect, 0.0), 0.0);
+        assert_eq!(range_fraction_at(rect, 500.0), 1.0);
+    }
+
     #[test]
     fn test_lighten_color() {
         let color = Color::from_rgb(100, 100, 100);
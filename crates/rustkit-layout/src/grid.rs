@@ -24,7 +24,7 @@ use rustkit_css::{
 };
 use tracing::{debug, trace};
 
-use crate::{LayoutBox, Rect};
+use crate::{BoxType, IntrinsicSizingMode, LayoutBox, Rect};
 
 // ==================== Grid Container ====================
 
@@ -52,6 +52,9 @@ pub struct GridTrack {
     pub fit_content_limit: Option<f32>,
     /// Whether this track is from auto-fit (should collapse if empty).
     pub is_auto_fit: bool,
+    /// Largest max-content contribution of the items in this track; the
+    /// size a content-sized track grows to before free space is shared.
+    pub max_content_contribution: f32,
     /// Final computed size.
     pub size: f32,
     /// Position (offset from container start).
@@ -88,7 +91,8 @@ impl GridTrack {
             TrackSize::MaxContent => (false, true),
             TrackSize::MinMax(min, max) => {
                 let min_is_min = matches!(min.as_ref(), TrackSize::MinContent);
-                let max_is_max = matches!(max.as_ref(), TrackSize::MaxContent);
+                // An `auto` maximum behaves as max-content
+                let max_is_max = matches!(max.as_ref(), TrackSize::MaxContent | TrackSize::Auto);
                 (min_is_min, max_is_max)
             }
             TrackSize::FitContent(_) => (true, false), // fit-content uses min-content as minimum
@@ -153,6 +157,7 @@ impl GridTrack {
             is_max_content,
             fit_content_limit,
             is_auto_fit: false,
+            max_content_contribution: 0.0,
             size: base_size,
             position: 0.0,
             line_names: Vec::new(),
@@ -249,6 +254,14 @@ impl<'a> GridItem<'a> {
             _ => 0.0,
         };
 
+        // A fixed width sets the height through aspect-ratio
+        if let Length::Px(_) = style.width {
+            let width = self.layout_box.intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+            if let Some(height) = self.layout_box.aspect_ratio_transfer(width, true) {
+                return min_height.max(height + self.layout_box.vertical_padding_border());
+            }
+        }
+
         // For auto height, estimate based on content
         // This is a simplified calculation - a full implementation would
         // do a layout pass to determine content height
@@ -401,6 +414,28 @@ impl<'a> GridItem<'a> {
         min_width
     }
 
+    /// Get the item's min-content and max-content contributions to column sizing.
+    ///
+    /// A definite width contributes itself to both. Otherwise the content's
+    /// intrinsic widths plus padding and border are used, never less than
+    /// min-width.
+    pub fn get_width_contributions(&self, container_width: f32) -> (f32, f32) {
+        let explicit = self.get_width_contribution(container_width);
+        let definite = match self.layout_box.style.width {
+            Length::Px(_) => true,
+            Length::Percent(_) => container_width > 0.0,
+            _ => false,
+        };
+        if definite {
+            return (explicit, explicit);
+        }
+
+        let edges = self.layout_box.horizontal_padding_border();
+        let min = self.layout_box.intrinsic_inline_size(IntrinsicSizingMode::MinContent) + edges;
+        let max = self.layout_box.intrinsic_inline_size(IntrinsicSizingMode::MaxContent) + edges;
+        (min.max(explicit), max.max(explicit))
+    }
+
     /// Set explicit placement from style.
     pub fn set_placement(&mut self, placement: &GridPlacement) {
         // Start with both dimensions needing auto-placement
@@ -612,6 +647,10 @@ pub struct GridLayout {
     pub row_auto_repeat: Option<AutoRepeatPattern>,
     /// Template areas for named area placement.
     pub template_areas: Option<GridTemplateAreas>,
+    /// Names of the line after the last explicit column.
+    pub final_column_line_names: Vec<String>,
+    /// Names of the line after the last explicit row.
+    pub final_row_line_names: Vec<String>,
 }
 
 impl GridLayout {
@@ -672,6 +711,8 @@ impl GridLayout {
             column_auto_repeat,
             row_auto_repeat,
             template_areas: None,
+            final_column_line_names: template_columns.final_line_names.clone(),
+            final_row_line_names: template_rows.final_line_names.clone(),
         }
     }
 
@@ -723,14 +764,18 @@ impl GridLayout {
     /// Expand auto-fill/auto-fit patterns now that we have container size.
     ///
     /// Per CSS Grid spec:
-    /// - Calculate how many repetitions fit in the available space
+    /// - Calculate how many repetitions fit in the available space, after the
+    ///   definite sizes of the other explicit tracks and all gaps
     /// - Insert the repeated tracks at the stored insert position
     /// - For auto-fit, empty tracks will be collapsed to 0 during sizing
+    ///
+    /// A size of 0 is treated as indefinite and yields a single repetition.
     pub fn expand_auto_repeats(&mut self, container_width: f32, container_height: f32) {
         // Expand column auto-repeat
         if let Some(pattern) = self.column_auto_repeat.take() {
-            let available = container_width - (self.columns.len().saturating_sub(1)) as f32 * self.column_gap;
-            let new_tracks = Self::calculate_auto_repeat_tracks(&pattern, available, self.column_gap);
+            let available = Self::space_for_auto_repeat(&self.columns, container_width, self.column_gap);
+            let new_tracks =
+                Self::calculate_auto_repeat_tracks(&pattern, available, self.column_gap, container_width);
 
             // Insert at the stored position
             let insert_at = pattern.insert_position.min(self.columns.len());
@@ -742,8 +787,8 @@ impl GridLayout {
 
         // Expand row auto-repeat
         if let Some(pattern) = self.row_auto_repeat.take() {
-            let available = container_height - (self.rows.len().saturating_sub(1)) as f32 * self.row_gap;
-            let new_tracks = Self::calculate_auto_repeat_tracks(&pattern, available, self.row_gap);
+            let available = Self::space_for_auto_repeat(&self.rows, container_height, self.row_gap);
+            let new_tracks = Self::calculate_auto_repeat_tracks(&pattern, available, self.row_gap, container_height);
 
             // Insert at the stored position
             let insert_at = pattern.insert_position.min(self.rows.len());
@@ -754,13 +799,30 @@ impl GridLayout {
         }
     }
 
+    /// Space left for the repeated tracks once the other explicit tracks and
+    /// the gaps separating them from the repetitions are accounted for.
+    fn space_for_auto_repeat(other_tracks: &[GridTrack], container_size: f32, gap: f32) -> f32 {
+        let fixed: f32 = other_tracks
+            .iter()
+            .map(|t| match t.percent {
+                Some(pct) if t.max_percent.is_none() => container_size * pct / 100.0,
+                _ if t.is_flexible || t.growth_limit == f32::INFINITY => t.base_size,
+                _ => t.growth_limit,
+            })
+            .sum();
+        container_size - fixed - other_tracks.len() as f32 * gap
+    }
+
     /// Calculate how many tracks to create for auto-fill/auto-fit.
     ///
+    /// `available_space` is the room for the repetitions themselves;
+    /// percentages in the pattern resolve against `percent_basis`.
     /// Returns a Vec of GridTrack to insert.
     fn calculate_auto_repeat_tracks(
         pattern: &AutoRepeatPattern,
         available_space: f32,
         gap: f32,
+        percent_basis: f32,
     ) -> Vec<GridTrack> {
         if pattern.tracks.is_empty() {
             return Vec::new();
@@ -770,54 +832,31 @@ impl GridLayout {
         let pattern_fixed_size: f32 = pattern
             .tracks
             .iter()
-            .map(|def| Self::get_track_definite_size(&def.size))
+            .map(|def| Self::get_track_definite_size(&def.size, percent_basis))
             .sum();
 
-        // Include gaps between tracks in one repetition
-        let pattern_gaps = if pattern.tracks.len() > 1 {
-            (pattern.tracks.len() - 1) as f32 * gap
+        // Each repetition brings one gap per track; the last gap of the grid is
+        // not needed, so it is credited back to the available space.
+        let per_repetition = pattern_fixed_size + pattern.tracks.len() as f32 * gap;
+
+        // If pattern has no definite size (all fr units), or the space is
+        // indefinite, create exactly 1 repetition
+        let repetitions = if pattern_fixed_size <= 0.0 || available_space <= 0.0 {
+            1
         } else {
-            0.0
+            // Per spec, at least 1 repetition
+            (((available_space + gap) / per_repetition).floor() as usize).max(1)
         };
 
-        let single_repetition_size = pattern_fixed_size + pattern_gaps;
-
-        // If pattern has no definite size (all fr units), create exactly 1 repetition
-        if single_repetition_size <= 0.0 {
-            let tracks: Vec<GridTrack> = pattern
-                .tracks
-                .iter()
-                .map(|def| {
-                    let mut track = GridTrack::new(&def.size);
-                    track.line_names = def.line_names.clone();
-                    track
-                })
-                .collect();
-            return tracks;
-        }
-
-        // Calculate how many repetitions fit
-        // Account for gaps between repetitions
-        let mut repetitions = 1u32;
-        let mut total_size = single_repetition_size;
-
-        while total_size + gap + single_repetition_size <= available_space {
-            repetitions += 1;
-            total_size += gap + single_repetition_size;
-        }
-
-        // Per spec, at least 1 repetition
-        repetitions = repetitions.max(1);
-
         trace!(
             "auto-repeat: {} repetitions fit in {}px (pattern size: {}px)",
             repetitions,
             available_space,
-            single_repetition_size
+            pattern_fixed_size
         );
 
         // Create the tracks
-        let mut result = Vec::with_capacity(repetitions as usize * pattern.tracks.len());
+        let mut result = Vec::with_capacity(repetitions * pattern.tracks.len());
         for _ in 0..repetitions {
             for def in &pattern.tracks {
                 let mut track = GridTrack::new(&def.size);
@@ -834,28 +873,27 @@ impl GridLayout {
     }
 
     /// Get the definite (fixed) size of a track for auto-repeat calculations.
-    /// Returns 0 for flexible tracks (fr) since they don't contribute to fixed size.
-    fn get_track_definite_size(size: &TrackSize) -> f32 {
+    ///
+    /// Uses the max sizing function when it is definite, otherwise the min.
+    /// Returns 0 for flexible and intrinsic tracks since they don't contribute
+    /// a fixed size.
+    fn get_track_definite_size(size: &TrackSize, percent_basis: f32) -> f32 {
         match size {
             TrackSize::Px(px) => *px,
+            TrackSize::Percent(pct) => percent_basis.max(0.0) * pct / 100.0,
             TrackSize::MinMax(min, max) => {
-                // Use the definite bound
-                let min_size = Self::get_track_definite_size(min);
-                let max_size = Self::get_track_definite_size(max);
-                // If max is definite, use it; otherwise use min
+                let min_size = Self::get_track_definite_size(min, percent_basis);
+                let max_size = Self::get_track_definite_size(max, percent_basis);
+                // If max is definite, use it (never below the min); otherwise use min
                 if max_size > 0.0 {
-                    max_size
+                    max_size.max(min_size)
                 } else {
                     min_size
                 }
             }
             TrackSize::FitContent(max) => *max,
             // Flexible and intrinsic sizes are not definite
-            TrackSize::Fr(_)
-            | TrackSize::Percent(_)
-            | TrackSize::MinContent
-            | TrackSize::MaxContent
-            | TrackSize::Auto => 0.0,
+            TrackSize::Fr(_) | TrackSize::MinContent | TrackSize::MaxContent | TrackSize::Auto => 0.0,
         }
     }
 
@@ -929,11 +967,8 @@ impl GridLayout {
     /// - "area-name-end" → column_end of the named area
     pub fn find_column_line_by_name(&self, name: &str) -> Option<i32> {
         // First check explicit line names on tracks
-        for (track_idx, track) in self.columns.iter().enumerate() {
-            // Line before track at index `track_idx` is line number `track_idx + 1` (1-based)
-            if track.line_names.iter().any(|n| n == name) {
-                return Some((track_idx + 1) as i32);
-            }
+        if let Some(line) = self.find_explicit_column_line_by_name(name) {
+            return Some(line);
         }
 
         // Check implicit line names from template-areas
@@ -966,10 +1001,8 @@ impl GridLayout {
     /// - "area-name-end" → row_end of the named area
     pub fn find_row_line_by_name(&self, name: &str) -> Option<i32> {
         // First check explicit line names on tracks
-        for (track_idx, track) in self.rows.iter().enumerate() {
-            if track.line_names.iter().any(|n| n == name) {
-                return Some((track_idx + 1) as i32);
-            }
+        if let Some(line) = self.find_explicit_row_line_by_name(name) {
+            return Some(line);
         }
 
         // Check implicit line names from template-areas
@@ -1083,6 +1116,9 @@ impl GridLayout {
                 return Some((track_idx + 1) as i32);
             }
         }
+        if self.final_column_line_names.iter().any(|n| n == name) {
+            return Some(self.explicit_columns as i32 + 1);
+        }
         None
     }
 
@@ -1093,6 +1129,9 @@ impl GridLayout {
                 return Some((track_idx + 1) as i32);
             }
         }
+        if self.final_row_line_names.iter().any(|n| n == name) {
+            return Some(self.explicit_rows as i32 + 1);
+        }
         None
     }
 
@@ -1348,8 +1387,18 @@ pub fn layout_grid_container(
     // Set template areas for named area placement
     grid.set_template_areas(style.grid_template_areas.clone());
 
-    // Expand auto-fill/auto-fit patterns now that we have container size
-    grid.expand_auto_repeats(container_width, container_height);
+    // Expand auto-fill/auto-fit patterns now that we have container size.
+    // An auto-height container has no definite row space to repeat into.
+    let has_definite_height = !style.height.is_content_sized();
+    let repeat_height = if has_definite_height { container_height } else { 0.0 };
+    grid.expand_auto_repeats(container_width, repeat_height);
+
+    // Rows and columns named in grid-template-areas exist even when the
+    // templates don't size them; they get the auto track size
+    if let Some(areas) = &style.grid_template_areas {
+        let area_columns = areas.rows.first().map_or(0, |row| row.len());
+        grid.ensure_tracks(area_columns, areas.rows.len(), &style.grid_auto_columns, &style.grid_auto_rows);
+    }
 
     // Ensure at least one column and row
     if grid.columns.is_empty() {
@@ -1580,25 +1629,31 @@ pub fn layout_grid_container(
         col_start: usize,
         col_span: usize,
         height_contribution: f32,
-        width_contribution: f32,
+        min_width_contribution: f32,
+        max_width_contribution: f32,
+        width_is_definite: bool,
     }
 
     // For auto-height containers, use 0.0 for height contribution calculation.
     // This prevents percentage heights from resolving against the incorrect block-flow
     // computed height (which stacks children vertically). Items with percentage heights
     // will contribute based on their intrinsic content height instead.
-    let has_definite_height = !matches!(style.height, Length::Auto);
     let height_for_contributions = if has_definite_height { container_height } else { 0.0 };
 
     let item_sizings: Vec<ItemSizing> = items
         .iter()
-        .map(|item| ItemSizing {
-            row_start: (item.row_start - 1).max(0) as usize,
-            row_span: item.row_span.max(1) as usize,
-            col_start: (item.column_start - 1).max(0) as usize,
-            col_span: item.column_span.max(1) as usize,
-            height_contribution: item.get_height_contribution(height_for_contributions),
-            width_contribution: item.get_width_contribution(container_width),
+        .map(|item| {
+            let (min_width_contribution, max_width_contribution) = item.get_width_contributions(container_width);
+            ItemSizing {
+                row_start: (item.row_start - 1).max(0) as usize,
+                row_span: item.row_span.max(1) as usize,
+                col_start: (item.column_start - 1).max(0) as usize,
+                col_span: item.column_span.max(1) as usize,
+                height_contribution: item.get_height_contribution(height_for_contributions),
+                min_width_contribution,
+                max_width_contribution,
+                width_is_definite: matches!(item.layout_box.style.width, Length::Px(_) | Length::Percent(_)),
+            }
         })
         .collect();
 
@@ -1656,41 +1711,61 @@ pub fn layout_grid_container(
     // Process columns by span count
     for span in 1..=max_col_span {
         for sizing in item_sizings.iter().filter(|s| s.col_span == span) {
-            if sizing.width_contribution > 0.0 {
-                let start = sizing.col_start;
-                let end = (start + span).min(grid.columns.len());
-
-                // Calculate current space provided by spanned tracks
-                let current_space: f32 = (start..end)
-                    .map(|i| grid.columns[i].base_size)
-                    .sum();
+            let start = sizing.col_start;
+            let end = (start + span).min(grid.columns.len());
+
+            // Tracks that size to content (intrinsic or flexible)
+            let growable: Vec<usize> = (start..end)
+                .filter(|&i| {
+                    let track = &grid.columns[i];
+                    track.is_min_content || track.is_max_content || track.is_flexible
+                        || track.growth_limit > track.base_size
+                })
+                .collect();
 
-                // Calculate extra space needed
-                let extra_needed = sizing.width_contribution - current_space;
+            if growable.is_empty() {
+                // All tracks are fixed: only an explicit width stretches them
+                let current_space: f32 = (start..end).map(|i| grid.columns[i].base_size).sum();
+                let extra_needed = sizing.min_width_contribution - current_space;
+                if sizing.width_is_definite && extra_needed > 0.0 {
+                    let per_track = extra_needed / span as f32;
+                    for i in start..end {
+                        grid.columns[i].base_size += per_track;
+                    }
+                }
+                continue;
+            }
 
-                if extra_needed > 0.0 {
-                    // Find tracks that can grow (intrinsic or flexible)
-                    let growable: Vec<usize> = (start..end)
-                        .filter(|&i| {
-                            let track = &grid.columns[i];
-                            track.is_min_content || track.is_max_content || track.is_flexible
-                                || track.growth_limit > track.base_size
-                        })
-                        .collect();
+            // Base sizes take the min-content contribution; a plain max-content
+            // track (no fixed minimum) takes the max-content contribution
+            let track = &grid.columns[start];
+            let max_content_minimum =
+                span == 1 && track.is_max_content && !track.is_min_content && track.base_size == 0.0;
+            let contribution = if max_content_minimum {
+                sizing.max_width_contribution
+            } else {
+                sizing.min_width_contribution
+            };
+            let current_space: f32 = (start..end).map(|i| grid.columns[i].base_size).sum();
+            let extra_needed = contribution - current_space;
+            if extra_needed > 0.0 {
+                // Distribute extra space equally among growable tracks
+                let per_track = extra_needed / growable.len() as f32;
+                for &i in &growable {
+                    grid.columns[i].base_size += per_track;
+                }
+            }
 
-                    if !growable.is_empty() {
-                        // Distribute extra space equally among growable tracks
-                        let per_track = extra_needed / growable.len() as f32;
-                        for i in growable {
-                            grid.columns[i].base_size += per_track;
-                        }
-                    } else {
-                        // All tracks are fixed, distribute equally anyway
-                        let per_track = extra_needed / span as f32;
-                        for i in start..end {
-                            grid.columns[i].base_size += per_track;
-                        }
-                    }
+            // Record how far the tracks would grow to fit the content unwrapped
+            let current_max: f32 = (start..end)
+                .map(|i| grid.columns[i].base_size.max(grid.columns[i].max_content_contribution))
+                .sum();
+            let extra_max = sizing.max_width_contribution - current_max;
+            if extra_max > 0.0 {
+                let per_track = extra_max / growable.len() as f32;
+                for &i in &growable {
+                    let track = &mut grid.columns[i];
+                    track.max_content_contribution = track.base_size.max(track.max_content_contribution) + per_track;
                 }
             }
         }
@@ -1812,11 +1887,27 @@ pub fn layout_grid_container(
                 child,
             );
 
+            // An aspect-ratio sets an auto height from the item's width
+            let ratio_height = if child.style.height.is_content_sized() {
+                let border_box = child.style.box_sizing == BoxSizing::BorderBox;
+                let width = if border_box {
+                    border_box_width - child.horizontal_padding_border()
+                } else {
+                    border_box_width
+                };
+                child
+                    .aspect_ratio_transfer(width.max(0.0), true)
+                    .map(|height| if border_box { height + child.vertical_padding_border() } else { height })
+            } else {
+                None
+            };
+
             let (y, border_box_height) = apply_align_self(
                 &child.style.align_self,
                 &style.align_items,
                 rect.y,
                 rect.height,
+                ratio_height,
                 child,
             );
 
@@ -1925,6 +2016,15 @@ pub fn layout_grid_container(
                     grandchild.dimensions.content.width = grid_item_width - margin_left - border_left - padding_left
                         - grandchild.dimensions.margin.right - grandchild.dimensions.border.right - grandchild.dimensions.padding.right;
 
+                    // Block containers flow their children again at the new
+                    // position and width, which also gives their auto height
+                    let is_block_container = matches!(grandchild.box_type, BoxType::Block | BoxType::AnonymousBlock)
+                        && !grandchild.style.display.is_flex()
+                        && !grandchild.style.display.is_grid();
+                    if is_block_container && !grandchild.children.is_empty() {
+                        grandchild.layout_block_children();
+                    }
+
                     // Calculate height for percentage resolution
                     // DEBUG: Uncomment to trace Phase 9 percentage height issues
                     // debug!("Phase 9: grandchild style.height={:?}, grid_item_height={}, existing_height={}",
@@ -1959,7 +2059,6 @@ pub fn layout_grid_container(
                         } else if grandchild.style.display.is_grid() {
                             layout_grid_container(grandchild, grandchild.dimensions.content.width, grandchild.dimensions.content.height);
                         }
-                        // For block, children were already laid out - we just fixed the container
                     }
 
                     // Update y for next sibling
@@ -2050,86 +2149,86 @@ fn size_grid_tracks(tracks: &mut [GridTrack], container_size: f32, gap: f32) {
             // max-content track: can grow to fit content
             // base_size has min contribution, allow growth
             track.size = track.base_size;
-            // growth_limit stays at INFINITY or is set based on max-content
+            // The max sizing function is intrinsic, so growth is bounded only by content.
             // For pure max-content, we want to expand to fill
-            if track.growth_limit == 0.0 {
-                track.growth_limit = f32::INFINITY;
-            }
+            track.growth_limit = f32::INFINITY;
         }
         // Handle fit-content(length): clamp growth_limit to the specified length
         // fit-content behaves like minmax(min-content, min(max-content, length))
         if let Some(limit) = track.fit_content_limit {
             // Base size is already set from min-content contribution
             track.size = track.base_size;
-            // Cap growth at the specified limit
-            track.growth_limit = limit.min(track.growth_limit);
+            // Growth is capped at the smaller of the max-content contribution and the limit
+            track.growth_limit = limit.min(track.base_size.max(track.max_content_contribution));
             // But growth_limit should be at least base_size
             track.growth_limit = track.growth_limit.max(track.base_size);
         }
     }
 
-    // Step 3: Distribute remaining space to flexible tracks
-    let fixed_size: f32 = tracks.iter().filter(|t| !t.is_flexible).map(|t| t.size).sum();
-    let flex_space = (available_space - fixed_size).max(0.0);
-
-    let total_flex: f32 = tracks.iter().filter(|t| t.is_flexible).map(|t| t.flex_factor).sum();
-
-    if total_flex > 0.0 {
-        let flex_unit = flex_space / total_flex;
-        for track in tracks.iter_mut().filter(|t| t.is_flexible) {
-            track.size = (track.flex_factor * flex_unit).max(track.base_size);
-            // Respect growth limit
-            if track.growth_limit < f32::INFINITY {
-                track.size = track.size.min(track.growth_limit);
-            }
-        }
+    // A growth limit below the base size is raised to it (CSS Grid §12.4)
+    for track in tracks.iter_mut().filter(|t| !t.is_flexible) {
+        track.growth_limit = track.growth_limit.max(track.base_size);
     }
 
-    // Step 4: Distribute remaining space to auto tracks if any space left
-    // Per spec, this is the "maximize tracks" step - grow tracks to their growth_limit
-    let mut used_space: f32 = tracks.iter().map(|t| t.size).sum();
-    let mut remaining = (available_space - used_space).max(0.0);
-
-    // Iteratively distribute space, respecting growth_limit
+    // Step 3: Maximize tracks (§12.6). Free space is shared equally between the
+    // tracks that haven't reached their growth limit, freezing each as it does.
+    // Tracks with a max-content maximum first grow only as far as their content.
+    // Growth is bounded by the space to fill, so it terminates in at most one
+    // round per track.
+    let maximize_limit = |t: &GridTrack| {
+        if t.growth_limit < f32::INFINITY {
+            t.growth_limit
+        } else if t.is_max_content {
+            t.max_content_contribution.max(t.base_size)
+        } else {
+            t.size
+        }
+    };
+    let mut remaining = (available_space - tracks.iter().map(|t| t.size).sum::<f32>()).max(0.0);
     while remaining > 0.01 {
-        // Find tracks that can still grow (not yet at growth_limit)
-        let growable: Vec<(usize, f32)> = tracks
+        let growable: Vec<usize> = tracks
             .iter()
             .enumerate()
-            .filter(|(_, t)| !t.is_flexible && t.growth_limit > t.size && t.growth_limit < f32::INFINITY)
-            .map(|(i, t)| (i, t.growth_limit - t.size)) // (index, room_to_grow)
+            .filter(|(_, t)| !t.is_flexible && maximize_limit(t) > t.size)
+            .map(|(i, _)| i)
             .collect();
 
         if growable.is_empty() {
             break;
         }
 
-        // Calculate how much each track can receive
-        let total_room: f32 = growable.iter().map(|(_, room)| room).sum();
-
-        if total_room <= 0.0 {
-            break;
-        }
-
-        // Distribute proportionally, but don't exceed room_to_grow
-        let to_distribute = remaining.min(total_room);
-        for (i, room) in &growable {
-            let share = (room / total_room) * to_distribute;
-            tracks[*i].size += share.min(*room);
+        let share = remaining / growable.len() as f32;
+        for i in growable {
+            let grow = share.min(maximize_limit(&tracks[i]) - tracks[i].size);
+            tracks[i].size += grow;
+            remaining -= grow;
         }
-
-        // Recalculate remaining space
-        used_space = tracks.iter().map(|t| t.size).sum();
-        remaining = (available_space - used_space).max(0.0);
     }
 
-    // If there's still remaining space and we have tracks with infinite growth_limit,
-    // distribute to them equally
-    if remaining > 0.01 {
+    // Step 4: Expand flexible tracks (§12.7)
+    let has_flexible = tracks.iter().any(|t| t.is_flexible);
+    if has_flexible {
+        let fr_size = if container_size > 0.0 {
+            find_fr_size(tracks, available_space)
+        } else {
+            // Indefinite free space: the fr is sized so no flexible track
+            // shrinks below its base size
+            tracks
+                .iter()
+                .filter(|t| t.is_flexible)
+                .map(|t| if t.flex_factor > 1.0 { t.base_size / t.flex_factor } else { t.base_size })
+                .fold(0.0, f32::max)
+        };
+        for track in tracks.iter_mut().filter(|t| t.is_flexible) {
+            track.size = (track.flex_factor * fr_size).max(track.base_size);
+        }
+    } else if remaining > 0.01 {
+        // Step 4b: Stretch auto tracks. Without flexible tracks, whatever is left
+        // goes equally to the tracks whose growth is unbounded.
         let infinite_tracks: Vec<usize> = tracks
             .iter()
             .enumerate()
-            .filter(|(_, t)| !t.is_flexible && t.growth_limit == f32::INFINITY)
+            .filter(|(_, t)| t.growth_limit == f32::INFINITY)
             .map(|(i, _)| i)
             .collect();
 
@@ -2156,6 +2255,39 @@ fn size_grid_tracks(tracks: &mut [GridTrack], container_size: f32, gap: f32) {
     }
 }
 
+/// Find the size of an fr (CSS Grid §12.7.1).
+///
+/// Flexible tracks whose base size exceeds their share are treated as
+/// inflexible and the share is recomputed without them. A flex sum below 1
+/// only takes that fraction of the leftover space.
+fn find_fr_size(tracks: &[GridTrack], space_to_fill: f32) -> f32 {
+    let mut inflexible = vec![false; tracks.len()];
+    loop {
+        let mut leftover = space_to_fill;
+        let mut flex_sum = 0.0;
+        for (track, &frozen) in tracks.iter().zip(&inflexible) {
+            if track.is_flexible && !frozen {
+                flex_sum += track.flex_factor;
+            } else {
+                leftover -= if track.is_flexible { track.base_size } else { track.size };
+            }
+        }
+
+        let fr_size = leftover.max(0.0) / flex_sum.max(1.0);
+
+        let mut restart = false;
+        for (track, frozen) in tracks.iter().zip(inflexible.iter_mut()) {
+            if track.is_flexible && !*frozen && track.flex_factor * fr_size < track.base_size {
+                *frozen = true;
+                restart = true;
+            }
+        }
+        if !restart {
+            return fr_size;
+        }
+    }
+}
+
 /// Stretch auto tracks when align-content is stretch.
 /// Per CSS Grid Level 1, Section 11.5.1: When align-content is stretch,
 /// the free space is distributed to auto tracks proportionally.
@@ -2347,10 +2479,14 @@ fn apply_justify_self(
 
     // Check if width is explicitly set (not auto)
     let has_explicit_width = !matches!(child.style.width, Length::Auto);
+    let edges = child.horizontal_padding_border();
     let child_width = match child.style.width {
         Length::Auto => cell_width,
         Length::Px(w) => w,
         Length::Percent(p) => cell_width * p / 100.0,
+        Length::MinContent => child.intrinsic_inline_size(IntrinsicSizingMode::MinContent) + edges,
+        Length::MaxContent => child.intrinsic_inline_size(IntrinsicSizingMode::MaxContent) + edges,
+        Length::FitContent => child.fit_content_width((cell_width - edges).max(0.0)) + edges,
         _ => cell_width,
     };
 
@@ -2375,6 +2511,7 @@ fn apply_align_self(
     items_align: &AlignItems,
     cell_y: f32,
     cell_height: f32,
+    ratio_height: Option<f32>,
     child: &LayoutBox,
 ) -> (f32, f32) {
     let align = match self_align {
@@ -2388,14 +2525,16 @@ fn apply_align_self(
         other => *other,
     };
 
-    // Check if height is explicitly set (not auto)
-    let has_explicit_height = !matches!(child.style.height, Length::Auto);
-    let child_height = match child.style.height {
+    // Check if height is explicitly set (not auto). Only an explicit
+    // align-self: stretch overrides a height from aspect-ratio
+    let has_explicit_height = !matches!(child.style.height, Length::Auto)
+        || (ratio_height.is_some() && *self_align != AlignSelf::Stretch);
+    let child_height = ratio_height.unwrap_or(match child.style.height {
         Length::Auto => cell_height,
         Length::Px(h) => h,
         Length::Percent(p) => cell_height * p / 100.0,
         _ => cell_height,
-    };
+    });
 
     match align {
         AlignSelf::FlexStart | AlignSelf::Auto => (cell_y, child_height),
@@ -2681,9 +2820,10 @@ mod tests {
         size_grid_tracks(&mut tracks, 400.0, 0.0);
 
         // First track has min=100px, max=200px
-        // It should start at 100px, and fr gets remaining 300px
-        assert_eq!(tracks[0].size, 100.0); // Gets base size
-        assert_eq!(tracks[1].size, 300.0); // Remaining goes to fr
+        // Tracks are maximized before fr tracks expand, so it reaches 200px
+        // and fr gets the remaining 200px
+        assert_eq!(tracks[0].size, 200.0);
+        assert_eq!(tracks[1].size, 200.0); // Remaining goes to fr
     }
 
     #[test]
@@ -2804,7 +2944,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0, 500.0);
 
         assert_eq!(tracks.len(), 5);
         for track in &tracks {
@@ -2823,7 +2963,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 20.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 20.0, 500.0);
 
         assert_eq!(tracks.len(), 4);
     }
@@ -2841,7 +2981,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0, 500.0);
 
         assert_eq!(tracks.len(), 5);
         // Each track should be flexible
@@ -2864,7 +3004,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0, 500.0);
 
         // 3 repetitions * 2 tracks = 6 tracks
         assert_eq!(tracks.len(), 6);
@@ -2883,7 +3023,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 100.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 100.0, 0.0, 100.0);
 
         // At least 1 repetition per spec
         assert_eq!(tracks.len(), 1);
@@ -2899,7 +3039,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0, 500.0);
 
         assert_eq!(tracks.len(), 5);
         for track in &tracks {
@@ -2916,7 +3056,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0, 500.0);
 
         // With no definite size, we get exactly 1 repetition
         assert_eq!(tracks.len(), 1);
@@ -2964,7 +3104,7 @@ mod tests {
             insert_position: 0,
         };
 
-        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0);
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 500.0, 0.0, 500.0);
 
         // Should have 5 tracks, all marked as auto-fit
         assert_eq!(tracks.len(), 5);
@@ -3004,6 +3144,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Mark only the first and third columns as occupied
@@ -3130,6 +3272,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Find lines by name
@@ -3166,6 +3310,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Test resolve_column_line with named line
@@ -3225,6 +3371,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         let style = ComputedStyle::new();
@@ -3277,6 +3425,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         let style = ComputedStyle::new();
@@ -3331,6 +3481,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: Some(areas),
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Test area lookup
@@ -3373,6 +3525,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: Some(areas),
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         let style = ComputedStyle::new();
@@ -3425,6 +3579,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: Some(areas),
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Test implicit line names
@@ -3465,6 +3621,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: Some(areas),
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         let style = ComputedStyle::new();
@@ -3514,6 +3672,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Simulate occupied grid:
@@ -3546,6 +3706,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         let grid_dense = GridLayout {
@@ -3564,6 +3726,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Occupied: col 0 taken, col 1 free, col 2 cursor position
@@ -3604,6 +3768,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Occupied: row 0 taken, row 1 free
@@ -3637,6 +3803,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Test resolving SpanName for column end position
@@ -3692,6 +3860,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: Some(areas),
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Test "span header" at column end - should resolve to header's column_end (4)
@@ -3753,6 +3923,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: Some(areas),
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Verify sidebar area bounds
@@ -3872,6 +4044,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Initial grid: 2 columns, 1 row
@@ -3920,6 +4094,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Item 1: grid-column: 1 / 3; grid-row: 1 / 2; (spans both columns, row 1)
@@ -3990,6 +4166,8 @@ mod tests {
             column_auto_repeat: None,
             row_auto_repeat: None,
             template_areas: None,
+            final_column_line_names: Vec::new(),
+            final_row_line_names: Vec::new(),
         };
 
         // Test resolving -1 (last line) for columns
@@ -4221,17 +4399,17 @@ mod tests {
         // Child height: 30
 
         // align-self: flex-start
-        let (y, h) = apply_align_self(&AlignSelf::FlexStart, &AlignItems::Stretch, 20.0, 100.0, &layout_box);
+        let (y, h) = apply_align_self(&AlignSelf::FlexStart, &AlignItems::Stretch, 20.0, 100.0, None, &layout_box);
         assert_eq!(y, 20.0, "align-self: flex-start should position at cell start");
         assert_eq!(h, 30.0);
 
         // align-self: flex-end
-        let (y, h) = apply_align_self(&AlignSelf::FlexEnd, &AlignItems::Stretch, 20.0, 100.0, &layout_box);
+        let (y, h) = apply_align_self(&AlignSelf::FlexEnd, &AlignItems::Stretch, 20.0, 100.0, None, &layout_box);
         assert_eq!(y, 90.0, "align-self: flex-end should position at cell end - height (20 + 100 - 30)");
         assert_eq!(h, 30.0);
 
         // align-self: center
-        let (y, h) = apply_align_self(&AlignSelf::Center, &AlignItems::Stretch, 20.0, 100.0, &layout_box);
+        let (y, h) = apply_align_self(&AlignSelf::Center, &AlignItems::Stretch, 20.0, 100.0, None, &layout_box);
         assert_eq!(y, 55.0, "align-self: center should center (20 + (100-30)/2)");
         assert_eq!(h, 30.0);
     }
@@ -4244,7 +4422,7 @@ mod tests {
         let layout_box = LayoutBox::new(BoxType::Block, style);
 
         // With auto height, stretch should use cell height
-        let (y, h) = apply_align_self(&AlignSelf::Stretch, &AlignItems::Stretch, 20.0, 100.0, &layout_box);
+        let (y, h) = apply_align_self(&AlignSelf::Stretch, &AlignItems::Stretch, 20.0, 100.0, None, &layout_box);
         assert_eq!(y, 20.0);
         assert_eq!(h, 100.0, "Stretch with auto height should fill cell");
 
@@ -4253,7 +4431,7 @@ mod tests {
         style2.height = Length::Px(30.0);
         let layout_box2 = LayoutBox::new(BoxType::Block, style2);
 
-        let (y, h) = apply_align_self(&AlignSelf::Stretch, &AlignItems::Stretch, 20.0, 100.0, &layout_box2);
+        let (y, h) = apply_align_self(&AlignSelf::Stretch, &AlignItems::Stretch, 20.0, 100.0, None, &layout_box2);
         assert_eq!(y, 20.0);
         assert_eq!(h, 30.0, "Stretch with explicit height should respect height");
     }
@@ -4557,4 +4735,187 @@ mod tests {
         assert_eq!(item.column_span, 1);
         assert_eq!(item.row_span, 1);
     }
+
+    #[test]
+    fn test_minmax_track_maximized_before_fr() {
+        // minmax(100px, 300px) 1fr in 1000px: the minmax track grows to its
+        // limit first, the fr track takes what is left
+        let mut tracks = vec![
+            GridTrack::new(&TrackSize::minmax(TrackSize::Px(100.0), TrackSize::Px(300.0))),
+            GridTrack::new(&TrackSize::Fr(1.0)),
+        ];
+
+        size_grid_tracks(&mut tracks, 1000.0, 0.0);
+
+        assert_eq!(tracks[0].size, 300.0);
+        assert_eq!(tracks[1].size, 700.0);
+    }
+
+    #[test]
+    fn test_fr_track_with_large_base_is_treated_as_inflexible() {
+        // 1fr 1fr in 300px where the first track's content needs 250px:
+        // it keeps 250px and the other track gets the remaining 50px
+        let mut tracks = vec![GridTrack::new(&TrackSize::Fr(1.0)), GridTrack::new(&TrackSize::Fr(1.0))];
+        tracks[0].base_size = 250.0;
+
+        size_grid_tracks(&mut tracks, 300.0, 0.0);
+
+        assert_eq!(tracks[0].size, 250.0);
+        assert_eq!(tracks[1].size, 50.0);
+    }
+
+    #[test]
+    fn test_fr_sum_below_one_leaves_space() {
+        let mut tracks = vec![GridTrack::new(&TrackSize::Fr(0.25)), GridTrack::new(&TrackSize::Fr(0.25))];
+
+        size_grid_tracks(&mut tracks, 400.0, 0.0);
+
+        assert_eq!(tracks[0].size, 100.0);
+        assert_eq!(tracks[1].size, 100.0);
+    }
+
+    #[test]
+    fn test_fr_in_indefinite_space_keeps_ratio() {
+        // Auto-height rows: 1fr 2fr with 30px content each size the fr from
+        // the content, so the 2fr row is twice as tall
+        let mut tracks = vec![GridTrack::new(&TrackSize::Fr(1.0)), GridTrack::new(&TrackSize::Fr(2.0))];
+        tracks[0].base_size = 30.0;
+        tracks[1].base_size = 30.0;
+
+        size_grid_tracks(&mut tracks, 0.0, 0.0);
+
+        assert_eq!(tracks[0].size, 30.0);
+        assert_eq!(tracks[1].size, 60.0);
+    }
+
+    #[test]
+    fn test_auto_fill_accounts_for_other_tracks() {
+        // 200px repeat(auto-fill, 100px) with 10px gaps in 650px:
+        // 200 + 10 + 4 × (100 + 10) = 650 → four repetitions
+        let mut template = GridTemplate::from_sizes(vec![TrackSize::Px(200.0)]);
+        template.repeats.push((1, TrackRepeat::AutoFill(vec![TrackDefinition::simple(TrackSize::Px(100.0))])));
+        let mut grid = GridLayout::new(
+            &template,
+            &GridTemplate::none(),
+            &TrackSize::Auto,
+            &TrackSize::Auto,
+            10.0,
+            0.0,
+            GridAutoFlow::Row,
+        );
+
+        grid.expand_auto_repeats(650.0, 0.0);
+
+        assert_eq!(grid.column_count(), 5);
+        assert_eq!(grid.columns[0].base_size, 200.0);
+        assert_eq!(grid.explicit_columns, 5);
+    }
+
+    #[test]
+    fn test_auto_fill_percentage_pattern() {
+        let pattern = AutoRepeatPattern {
+            tracks: vec![TrackDefinition::simple(TrackSize::Percent(25.0))],
+            is_auto_fit: false,
+            insert_position: 0,
+        };
+
+        let tracks = GridLayout::calculate_auto_repeat_tracks(&pattern, 800.0, 0.0, 800.0);
+
+        assert_eq!(tracks.len(), 4);
+    }
+
+    #[test]
+    fn test_final_line_name_resolves_past_last_track() {
+        let mut template = GridTemplate::from_sizes(vec![TrackSize::Fr(1.0), TrackSize::Fr(1.0)]);
+        template.tracks[0].line_names = vec!["full-start".to_string()];
+        template.final_line_names = vec!["full-end".to_string()];
+        let grid = GridLayout::new(
+            &template,
+            &GridTemplate::none(),
+            &TrackSize::Auto,
+            &TrackSize::Auto,
+            0.0,
+            0.0,
+            GridAutoFlow::Row,
+        );
+
+        assert_eq!(grid.find_column_line_by_name("full-start"), Some(1));
+        assert_eq!(grid.find_column_line_by_name("full-end"), Some(3));
+    }
+
+    #[test]
+    fn test_max_content_column_beside_fr() {
+        let mut style = ComputedStyle::new();
+        style.display = Display::Grid;
+        style.grid_template_columns = GridTemplate::from_sizes(vec![TrackSize::MaxContent, TrackSize::Fr(1.0)]);
+        let mut container = LayoutBox::new(BoxType::Block, style);
+        for text in ["a fairly long label", "x"] {
+            let mut item = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+            item.children.push(LayoutBox::new(BoxType::Text(text.to_string()), ComputedStyle::new()));
+            container.children.push(item);
+        }
+        let label = container.children[0].intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+
+        layout_grid_container(&mut container, 500.0, 0.0);
+
+        let first = container.children[0].dimensions.content;
+        let second = container.children[1].dimensions.content;
+        assert!((first.width - label).abs() < 0.01);
+        assert!((second.x - label).abs() < 0.01);
+        assert!((second.width - (500.0 - label)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_aspect_ratio_item_height_from_column() {
+        let mut style = ComputedStyle::new();
+        style.display = Display::Grid;
+        style.grid_template_columns = GridTemplate::from_sizes(vec![TrackSize::Px(200.0)]);
+        style.grid_template_rows = GridTemplate::from_sizes(vec![TrackSize::Px(300.0)]);
+        style.height = Length::Px(300.0);
+        let mut container = LayoutBox::new(BoxType::Block, style);
+        let mut item_style = ComputedStyle::new();
+        item_style.aspect_ratio = Some(4.0);
+        container.children.push(LayoutBox::new(BoxType::Block, item_style.clone()));
+        // An explicit stretch still fills the row
+        item_style.align_self = AlignSelf::Stretch;
+        container.children.push(LayoutBox::new(BoxType::Block, item_style));
+        container.children[1].style.grid_row_start = GridLine::Number(1);
+        container.children[1].style.grid_column_start = GridLine::Number(1);
+
+        layout_grid_container(&mut container, 200.0, 300.0);
+
+        assert_eq!(container.children[0].dimensions.content.height, 50.0);
+        assert_eq!(container.children[1].dimensions.content.height, 300.0);
+    }
+
+    #[test]
+    fn test_layout_places_items_in_named_areas() {
+        let mut style = ComputedStyle::new();
+        style.display = Display::Grid;
+        style.grid_template_columns = GridTemplate::from_sizes(vec![TrackSize::Px(100.0), TrackSize::Fr(1.0)]);
+        style.grid_template_rows = GridTemplate::from_sizes(vec![TrackSize::Px(50.0), TrackSize::Px(200.0)]);
+        style.grid_template_areas = GridTemplateAreas::parse("\"head head\" \"nav main\"");
+        style.height = Length::Px(250.0);
+        let mut container = LayoutBox::new(BoxType::Block, style);
+
+        for area in ["main", "head", "nav"] {
+            let mut child_style = ComputedStyle::new();
+            let name = GridLine::Name(area.to_string());
+            child_style.grid_row_start = name.clone();
+            child_style.grid_row_end = name.clone();
+            child_style.grid_column_start = name.clone();
+            child_style.grid_column_end = name;
+            container.children.push(LayoutBox::new(BoxType::Block, child_style));
+        }
+
+        layout_grid_container(&mut container, 500.0, 250.0);
+
+        let rects: Vec<Rect> = container.children.iter().map(|c| c.dimensions.content).collect();
+        // main: column 2, row 2
+        assert_eq!((rects[0].x, rects[0].y, rects[0].width, rects[0].height), (100.0, 50.0, 400.0, 200.0));
+        // head spans both columns of row 1
+        assert_eq!((rects[1].x, rects[1].y, rects[1].width, rects[1].height), (0.0, 0.0, 500.0, 50.0));
+        // nav: column 1, row 2
+        assert_eq!((rects[2].x, rects[2].y, rects[2].width, rects[2].height), (0.0, 50.0, 100.0, 200.0));
+    }
 }
@@ -47,6 +47,7 @@ pub fn render_image(
 }
 
 /// Generate display commands for a background image
+#[allow(clippy::too_many_arguments)]
 pub fn render_background_image(
     url: &str,
     container: Rect,
@@ -54,106 +55,106 @@ pub fn render_background_image(
     image_height: f32,
     size: &BackgroundSize,
     position: (f32, f32),
+    offset: (f32, f32),
     repeat: BackgroundRepeat,
 ) -> Vec<DisplayCommand> {
-    let mut commands = Vec::new();
-
-    // Calculate the size of the background image
-    let (bg_width, bg_height) = size.compute_size(container, image_width, image_height);
-
-    if bg_width == 0.0 || bg_height == 0.0 {
-        return commands;
-    }
-
-    // Calculate the starting position
-    let start_x = container.x + (container.width - bg_width) * position.0;
-    let start_y = container.y + (container.height - bg_height) * position.1;
-
-    // Determine tiling
-    let (tile_x, tile_y) = match repeat {
-        BackgroundRepeat::Repeat => (true, true),
-        BackgroundRepeat::RepeatX => (true, false),
-        BackgroundRepeat::RepeatY => (false, true),
-        BackgroundRepeat::NoRepeat => (false, false),
-        BackgroundRepeat::Space => (true, true), // TODO: proper spacing
-        BackgroundRepeat::Round => (true, true), // TODO: proper rounding
-    };
-
-    // Generate tile positions
-    if !tile_x && !tile_y {
-        // Single image
-        commands.push(DisplayCommand::BackgroundImage {
+    background_tiles(container, image_width, image_height, size, position, offset, repeat)
+        .into_iter()
+        .map(|rect| DisplayCommand::BackgroundImage {
             url: url.to_string(),
-            rect: Rect {
-                x: start_x,
-                y: start_y,
-                width: bg_width,
-                height: bg_height,
-            },
+            rect,
             size: size.clone(),
             position,
+            offset,
             repeat,
-        });
-    } else {
-        // Tiled images
-        let x_start = if tile_x {
-            // Find the leftmost position that's visible
-            let tiles_left = ((start_x - container.x) / bg_width).ceil() as i32;
-            start_x - (tiles_left as f32 * bg_width)
-        } else {
-            start_x
-        };
+        })
+        .collect()
+}
 
-        let y_start = if tile_y {
-            let tiles_up = ((start_y - container.y) / bg_height).ceil() as i32;
-            start_y - (tiles_up as f32 * bg_height)
-        } else {
-            start_y
-        };
+/// Where the tiles of a background image go within `container`.
+///
+/// The first tile sits `position` of the way across the space left over
+/// by the image, shifted by `offset` pixels, and repeats from there.
+/// `Space` instead spreads as many whole tiles as fit evenly from edge to
+/// edge, and `Round` scales the tile so a whole number fit. Only tiles
+/// that overlap the container are returned.
+pub fn background_tiles(
+    container: Rect,
+    image_width: f32,
+    image_height: f32,
+    size: &BackgroundSize,
+    position: (f32, f32),
+    offset: (f32, f32),
+    repeat: BackgroundRepeat,
+) -> Vec<Rect> {
+    let (mut width, mut height) = size.compute_size(container, image_width, image_height);
+    if width <= 0.0 || height <= 0.0 {
+        return Vec::new();
+    }
 
-        let mut y = y_start;
-        while y < container.y + container.height {
-            let mut x = x_start;
-            while x < container.x + container.width {
-                // Clip to container bounds
-                let tile_rect = Rect {
-                    x,
-                    y,
-                    width: bg_width,
-                    height: bg_height,
-                };
-
-                // Only emit if visible
-                if tile_rect.x + tile_rect.width > container.x
-                    && tile_rect.y + tile_rect.height > container.y
-                    && tile_rect.x < container.x + container.width
-                    && tile_rect.y < container.y + container.height
-                {
-                    commands.push(DisplayCommand::BackgroundImage {
-                        url: url.to_string(),
-                        rect: tile_rect,
-                        size: size.clone(),
-                        position,
-                        repeat,
-                    });
-                }
-
-                if tile_x {
-                    x += bg_width;
-                } else {
-                    break;
-                }
-            }
+    let (repeat_x, repeat_y) = match repeat {
+        BackgroundRepeat::Repeat | BackgroundRepeat::Round => (AxisRepeat::Repeat, AxisRepeat::Repeat),
+        BackgroundRepeat::Space => (AxisRepeat::Space, AxisRepeat::Space),
+        BackgroundRepeat::RepeatX => (AxisRepeat::Repeat, AxisRepeat::Once),
+        BackgroundRepeat::RepeatY => (AxisRepeat::Once, AxisRepeat::Repeat),
+        BackgroundRepeat::NoRepeat => (AxisRepeat::Once, AxisRepeat::Once),
+    };
+    if repeat == BackgroundRepeat::Round {
+        width = container.width / (container.width / width).round().max(1.0);
+        height = container.height / (container.height / height).round().max(1.0);
+    }
 
-            if tile_y {
-                y += bg_height;
-            } else {
-                break;
+    let xs = axis_tiles(container.x, container.width, width, position.0, offset.0, repeat_x);
+    let ys = axis_tiles(container.y, container.height, height, position.1, offset.1, repeat_y);
+
+    let mut tiles = Vec::with_capacity(xs.len() * ys.len());
+    for &y in &ys {
+        for &x in &xs {
+            let tile = Rect { x, y, width, height };
+            if tile.right() > container.x
+                && tile.bottom() > container.y
+                && tile.x < container.right()
+                && tile.y < container.bottom()
+            {
+                tiles.push(tile);
             }
         }
     }
+    tiles
+}
 
-    commands
+/// How a background image repeats along one axis.
+#[derive(Clone, Copy)]
+enum AxisRepeat {
+    Once,
+    Repeat,
+    Space,
+}
+
+/// Start coordinates of the tiles along one axis of a background.
+fn axis_tiles(start: f32, extent: f32, tile: f32, position: f32, offset: f32, repeat: AxisRepeat) -> Vec<f32> {
+    let first = start + (extent - tile) * position + offset;
+    match repeat {
+        AxisRepeat::Once => vec![first],
+        AxisRepeat::Space => {
+            let count = (extent / tile).floor();
+            if count < 2.0 {
+                return vec![first];
+            }
+            let gap = (extent - count * tile) / (count - 1.0);
+            (0..count as usize).map(|i| start + i as f32 * (tile + gap)).collect()
+        }
+        AxisRepeat::Repeat => {
+            // Step back to the first tile that reaches into the container.
+            let mut x = first - ((first - start) / tile).ceil() * tile;
+            let mut positions = Vec::new();
+            while x < start + extent {
+                positions.push(x);
+                x += tile;
+            }
+            positions
+        }
+    }
 }
 
 /// Generate display command for a broken image placeholder
@@ -329,6 +330,7 @@ mod tests {
             100.0,
             &BackgroundSize::Auto,
             (0.5, 0.5),
+            (0.0, 0.0),
             BackgroundRepeat::NoRepeat,
         );
 
@@ -345,6 +347,7 @@ mod tests {
             50.0,
             &BackgroundSize::Auto,
             (0.0, 0.0),
+            (0.0, 0.0),
             BackgroundRepeat::Repeat,
         );
 
@@ -352,6 +355,40 @@ mod tests {
         assert!(commands.len() >= 16);
     }
 
+    #[test]
+    fn test_background_tiles() {
+        let container = Rect { x: 10.0, y: 0.0, width: 100.0, height: 100.0 };
+        let tiles = |size: &BackgroundSize, position, offset, repeat| {
+            background_tiles(container, 30.0, 30.0, size, position, offset, repeat)
+        };
+
+        // A pixel offset moves a single tile and percentages place it in the free space.
+        let single = tiles(&BackgroundSize::Auto, (1.0, 0.0), (-5.0, 4.0), BackgroundRepeat::NoRepeat);
+        assert_eq!(single, vec![Rect { x: 75.0, y: 4.0, width: 30.0, height: 30.0 }]);
+
+        // Repeating covers the container starting from a tile cut by its left edge.
+        let row = tiles(&BackgroundSize::Auto, (0.0, 0.0), (5.0, 0.0), BackgroundRepeat::RepeatX);
+        let xs: Vec<f32> = row.iter().map(|tile| tile.x).collect();
+        assert_eq!(xs, vec![-15.0, 15.0, 45.0, 75.0, 105.0]);
+
+        // Space fits three whole tiles with equal gaps touching both edges.
+        let spaced = tiles(&BackgroundSize::Auto, (0.5, 0.5), (0.0, 0.0), BackgroundRepeat::Space);
+        assert_eq!(spaced.len(), 9);
+        assert_eq!(spaced[0].x, 10.0);
+        assert_eq!(spaced[2].right(), 110.0);
+
+        // Round scales tiles so a whole number fit.
+        let rounded = tiles(&BackgroundSize::Auto, (0.0, 0.0), (0.0, 0.0), BackgroundRepeat::Round);
+        assert_eq!(rounded.len(), 9);
+        assert!((rounded[0].width - 100.0 / 3.0).abs() < 0.001);
+
+        // Negative explicit sizes are percentages of the container.
+        let half = BackgroundSize::Explicit { width: Some(-50.0), height: None };
+        let halves = tiles(&half, (0.0, 0.0), (0.0, 0.0), BackgroundRepeat::NoRepeat);
+        assert_eq!(halves[0].width, 50.0);
+        assert_eq!(halves[0].height, 50.0);
+    }
+
     #[test]
     fn test_calculate_intrinsic_size() {
         // Both explicit
@@ -0,0 +1,418 @@
+//! Compositor layer promotion.
+//!
+//! Boxes whose transform or opacity is expected to change every frame, and
+//! boxes that move relative to the page on scroll, are given their own
+//! compositor layer. The display list brackets their content in
+//! [`DisplayCommand::PushLayer`]/[`DisplayCommand::PopLayer`], and the
+//! renderer applies per-layer transform and opacity at composite time, so
+//! updating them needs neither relayout nor a new display list.
+//!
+//! Layers of fixed and sticky boxes are moved against the scroll offset at
+//! composite time too (see [`CompositedLayer::scroll_translation`]), so
+//! they stay in view without repainting. Demoted fixed and sticky boxes
+//! scroll with the page.
+//!
+//! Each layer costs a backing store the size of its border box. A
+//! [`LayerBudget`] caps the number of layers and their total memory;
+//! candidates that don't fit are demoted and paint into their parent as
+//! usual.
+//!
+//! [`DisplayCommand::PushLayer`]: crate::DisplayCommand::PushLayer
+//! [`DisplayCommand::PopLayer`]: crate::DisplayCommand::PopLayer
+
+use rustkit_css::ComputedStyle;
+
+use crate::{LayoutBox, Position, Rect, StickyOffsets};
+
+/// Bytes per pixel of a layer's backing store.
+const BYTES_PER_PIXEL: u64 = 4;
+
+/// Why a box was considered for its own layer, in priority order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PromotionReason {
+    /// A running animation or a transition on `transform`.
+    AnimatedTransform,
+    /// A transition on `opacity`.
+    AnimatedOpacity,
+    /// `will-change: transform`.
+    WillChangeTransform,
+    /// `will-change: opacity`.
+    WillChangeOpacity,
+    /// `position: fixed`.
+    FixedPosition,
+    /// `position: sticky`.
+    StickyPosition,
+}
+
+/// Limits on compositor layers per view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerBudget {
+    /// Maximum number of layers.
+    pub max_layers: usize,
+    /// Maximum total backing store size in bytes.
+    pub max_memory: u64,
+}
+
+impl Default for LayerBudget {
+    fn default() -> Self {
+        Self {
+            max_layers: 256,
+            max_memory: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// A box promoted (or considered for promotion) to a compositor layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositedLayer {
+    /// ID of the DOM node the box was generated for.
+    pub element_id: usize,
+    /// Border box of the layer's root.
+    pub bounds: Rect,
+    /// Why the box was considered.
+    pub reason: PromotionReason,
+    /// Estimated backing store size in bytes.
+    pub memory: u64,
+    /// How the layer moves as the page scrolls.
+    pub anchor: ScrollAnchor,
+}
+
+impl CompositedLayer {
+    /// How far the layer is moved from where it was laid out while the
+    /// page shows `viewport`, in layout coordinates.
+    pub fn scroll_translation(&self, viewport: Rect) -> (f32, f32) {
+        match self.anchor {
+            ScrollAnchor::Page => (0.0, 0.0),
+            ScrollAnchor::Viewport => (viewport.x, viewport.y),
+            ScrollAnchor::Sticky { offsets, container } => (
+                sticky_shift(
+                    (self.bounds.x, self.bounds.width),
+                    (offsets.left, offsets.right),
+                    (viewport.x, viewport.width),
+                    (container.x, container.width),
+                ),
+                sticky_shift(
+                    (self.bounds.y, self.bounds.height),
+                    (offsets.top, offsets.bottom),
+                    (viewport.y, viewport.height),
+                    (container.y, container.height),
+                ),
+            ),
+        }
+    }
+}
+
+/// How a layer moves as the page scrolls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollAnchor {
+    /// With the page.
+    Page,
+    /// Not at all: `position: fixed` boxes stay where they are in the
+    /// viewport.
+    Viewport,
+    /// With the page until an edge would come closer to the viewport's
+    /// than its `position: sticky` offset, then not, as long as the box
+    /// stays inside `container`, its parent's content box.
+    Sticky { offsets: StickyOffsets, container: Rect },
+}
+
+/// How far a sticky box spanning `start` and `size` along one axis moves
+/// to keep its `near` and `far` offsets from the ends of the `viewport`
+/// span without leaving the `container` span. The near offset wins when
+/// both can't be kept.
+fn sticky_shift(
+    (start, size): (f32, f32),
+    (near, far): (Option<f32>, Option<f32>),
+    (viewport_start, viewport_size): (f32, f32),
+    (container_start, container_size): (f32, f32),
+) -> f32 {
+    let end = start + size;
+    let mut shift = 0.0_f32;
+    if let Some(far) = far {
+        let overflow = end - (viewport_start + viewport_size - far);
+        shift = -overflow.clamp(0.0, (start - container_start).max(0.0));
+    }
+    if let Some(near) = near {
+        let room = (container_start + container_size - end).max(0.0);
+        shift = shift.max((viewport_start + near - start).min(room));
+    }
+    shift
+}
+
+/// The layers of one layout tree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerTree {
+    /// Promoted layers; a box's `layer` indexes this list.
+    pub layers: Vec<CompositedLayer>,
+    /// Candidates left out to stay within the budget.
+    pub demoted: Vec<CompositedLayer>,
+}
+
+impl LayerTree {
+    /// Total backing store size of the promoted layers.
+    pub fn memory(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.memory).sum()
+    }
+
+    /// Index of the layer promoted for a DOM node, if any.
+    pub fn layer_for_element(&self, element_id: usize) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.element_id == element_id)
+    }
+}
+
+/// Why `layout_box` should get its own layer, if it should.
+pub fn promotion_reason(layout_box: &LayoutBox) -> Option<PromotionReason> {
+    let style = &layout_box.style;
+    if let Some(reason) = animation_reason(style) {
+        return Some(reason);
+    }
+    if style.will_change.transform {
+        return Some(PromotionReason::WillChangeTransform);
+    }
+    if style.will_change.opacity {
+        return Some(PromotionReason::WillChangeOpacity);
+    }
+    match layout_box.position {
+        Position::Fixed => Some(PromotionReason::FixedPosition),
+        Position::Sticky => Some(PromotionReason::StickyPosition),
+        _ => None,
+    }
+}
+
+/// Animations are run by the compositor only for transform and opacity.
+/// Which properties a keyframe animation touches isn't known here, so any
+/// running animation counts as a transform animation.
+fn animation_reason(style: &ComputedStyle) -> Option<PromotionReason> {
+    if !style.animation_name.is_empty() && style.animation_name != "none" && style.animation_duration > 0.0 {
+        return Some(PromotionReason::AnimatedTransform);
+    }
+    if style.transition_duration <= 0.0 {
+        return None;
+    }
+    let mut opacity = false;
+    for property in style.transition_property.split(',') {
+        match property.trim() {
+            "transform" | "translate" | "rotate" | "scale" | "all" => {
+                return Some(PromotionReason::AnimatedTransform)
+            }
+            "opacity" => opacity = true,
+            _ => {}
+        }
+    }
+    opacity.then_some(PromotionReason::AnimatedOpacity)
+}
+
+/// Pick the boxes of `root` that get their own layer and record each one's
+/// index in its `layer` field.
+///
+/// Candidates are admitted by [`PromotionReason`] priority, then document
+/// order, until the budget runs out. Boxes not generated for a DOM node are
+/// never promoted, since layer properties are addressed by node.
+pub fn promote_layers(root: &mut LayoutBox, budget: &LayerBudget) -> LayerTree {
+    let mut candidates = Vec::new();
+    collect_candidates(root, root.dimensions.content, false, &mut candidates);
+    // Stable, so document order breaks ties
+    candidates.sort_by_key(|layer: &CompositedLayer| layer.reason);
+
+    let mut tree = LayerTree::default();
+    let mut memory = 0;
+    for candidate in candidates {
+        if tree.layers.len() < budget.max_layers && memory + candidate.memory <= budget.max_memory {
+            memory += candidate.memory;
+            tree.layers.push(candidate);
+        } else {
+            tree.demoted.push(candidate);
+        }
+    }
+
+    assign_layers(root, &tree);
+    tree
+}
+
+/// Collect the candidates of `layout_box`'s subtree, `container` being its
+/// parent's content box. Boxes inside a fixed or sticky box move with it,
+/// so they're anchored to the page (their ancestor's layer).
+fn collect_candidates(
+    layout_box: &LayoutBox,
+    container: Rect,
+    in_scroll_anchored: bool,
+    candidates: &mut Vec<CompositedLayer>,
+) {
+    let anchor = match (layout_box.position, &layout_box.sticky_state) {
+        _ if in_scroll_anchored => ScrollAnchor::Page,
+        (Position::Fixed, _) => ScrollAnchor::Viewport,
+        (Position::Sticky, Some(sticky)) => ScrollAnchor::Sticky {
+            offsets: sticky.offsets,
+            container,
+        },
+        _ => ScrollAnchor::Page,
+    };
+    if let (Some(element_id), Some(reason)) = (layout_box.element_id, promotion_reason(layout_box)) {
+        let bounds = layout_box.dimensions.border_box();
+        let pixels = bounds.width.max(0.0).ceil() as u64 * bounds.height.max(0.0).ceil() as u64;
+        candidates.push(CompositedLayer {
+            element_id,
+            bounds,
+            reason,
+            memory: pixels * BYTES_PER_PIXEL,
+            anchor,
+        });
+    }
+    let in_scroll_anchored = in_scroll_anchored || anchor != ScrollAnchor::Page;
+    for child in &layout_box.children {
+        collect_candidates(child, layout_box.dimensions.content, in_scroll_anchored, candidates);
+    }
+}
+
+fn assign_layers(layout_box: &mut LayoutBox, tree: &LayerTree) {
+    layout_box.layer = match (layout_box.element_id, promotion_reason(layout_box)) {
+        (Some(element_id), Some(_)) => tree.layer_for_element(element_id),
+        _ => None,
+    };
+    for child in &mut layout_box.children {
+        assign_layers(child, tree);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoxType, DisplayCommand, DisplayList};
+    use rustkit_css::WillChange;
+
+    fn block(element_id: usize, width: f32, height: f32) -> LayoutBox {
+        let mut layout_box = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        layout_box.element_id = Some(element_id);
+        layout_box.dimensions.content = Rect::new(0.0, 0.0, width, height);
+        layout_box
+    }
+
+    #[test]
+    fn test_promotes_by_priority_within_budget() {
+        let mut root = block(1, 800.0, 600.0);
+
+        let mut fixed = block(2, 100.0, 100.0);
+        fixed.position = Position::Fixed;
+        root.children.push(fixed);
+
+        let mut hinted = block(3, 100.0, 100.0);
+        hinted.style.will_change = WillChange::parse("opacity");
+        root.children.push(hinted);
+
+        let mut animated = block(4, 100.0, 100.0);
+        animated.style.transition_property = "opacity, transform".to_string();
+        animated.style.transition_duration = 0.3;
+        root.children.push(animated);
+
+        // Too big for the memory budget on its own
+        let mut huge = block(5, 4000.0, 4000.0);
+        huge.style.will_change = WillChange::parse("transform");
+        root.children.push(huge);
+
+        let budget = LayerBudget {
+            max_layers: 2,
+            max_memory: 1024 * 1024,
+        };
+        let tree = promote_layers(&mut root, &budget);
+
+        let promoted: Vec<_> = tree.layers.iter().map(|layer| (layer.element_id, layer.reason)).collect();
+        assert_eq!(
+            promoted,
+            [(4, PromotionReason::AnimatedTransform), (3, PromotionReason::WillChangeOpacity)]
+        );
+        let demoted: Vec<_> = tree.demoted.iter().map(|layer| layer.element_id).collect();
+        assert_eq!(demoted, [5, 2]);
+        assert_eq!(tree.memory(), 2 * 100 * 100 * 4);
+
+        assert_eq!(root.layer, None);
+        assert_eq!(root.children[0].layer, None);
+        assert_eq!(root.children[1].layer, Some(1));
+        assert_eq!(root.children[2].layer, Some(0));
+        assert_eq!(root.children[3].layer, None);
+    }
+
+    #[test]
+    fn test_fixed_and_sticky_layers_move_against_the_scroll() {
+        let mut root = block(1, 800.0, 3000.0);
+
+        let mut fixed = block(2, 800.0, 50.0);
+        fixed.position = Position::Fixed;
+        let mut inside_fixed = block(3, 10.0, 10.0);
+        inside_fixed.position = Position::Sticky;
+        inside_fixed.sticky_state = Some(crate::StickyState::new(Rect::new(0.0, 0.0, 10.0, 10.0), Default::default()));
+        fixed.children.push(inside_fixed);
+        root.children.push(fixed);
+
+        // A header stuck to the top of a 1000px tall section at y=500
+        let mut section = block(4, 800.0, 1000.0);
+        section.dimensions.content.y = 500.0;
+        let mut header = block(5, 800.0, 40.0);
+        header.dimensions.content.y = 600.0;
+        header.position = Position::Sticky;
+        let offsets = StickyOffsets { top: Some(10.0), ..Default::default() };
+        header.sticky_state = Some(crate::StickyState::new(header.dimensions.border_box(), offsets));
+        section.children.push(header);
+        root.children.push(section);
+
+        let tree = promote_layers(&mut root, &LayerBudget::default());
+        let layer = |element_id| &tree.layers[tree.layer_for_element(element_id).unwrap()];
+        let viewport = |scroll_y| Rect::new(0.0, scroll_y, 800.0, 600.0);
+
+        assert_eq!(layer(2).scroll_translation(viewport(700.0)), (0.0, 700.0));
+        assert_eq!(layer(3).anchor, ScrollAnchor::Page);
+        assert_eq!(
+            layer(5).anchor,
+            ScrollAnchor::Sticky {
+                offsets,
+                container: Rect::new(0.0, 500.0, 800.0, 1000.0),
+            }
+        );
+        // In the flow until scrolled past, then 10px below the top, until
+        // the section's bottom pushes it up
+        assert_eq!(layer(5).scroll_translation(viewport(0.0)), (0.0, 0.0));
+        assert_eq!(layer(5).scroll_translation(viewport(590.0)), (0.0, 0.0));
+        assert_eq!(layer(5).scroll_translation(viewport(890.0)), (0.0, 300.0));
+        assert_eq!(layer(5).scroll_translation(viewport(2000.0)), (0.0, 860.0));
+    }
+
+    #[test]
+    fn test_sticky_bottom_and_left() {
+        let layer = CompositedLayer {
+            element_id: 1,
+            bounds: Rect::new(100.0, 900.0, 50.0, 40.0),
+            reason: PromotionReason::StickyPosition,
+            memory: 0,
+            anchor: ScrollAnchor::Sticky {
+                offsets: StickyOffsets { bottom: Some(0.0), left: Some(5.0), ..Default::default() },
+                container: Rect::new(0.0, 200.0, 2000.0, 1000.0),
+            },
+        };
+        // Held at the viewport's bottom, but not above the container's top
+        assert_eq!(layer.scroll_translation(Rect::new(0.0, 0.0, 800.0, 600.0)), (0.0, -340.0));
+        assert_eq!(layer.scroll_translation(Rect::new(0.0, 500.0, 800.0, 600.0)), (0.0, 0.0));
+        assert_eq!(layer.scroll_translation(Rect::new(0.0, -800.0, 800.0, 600.0)), (0.0, -700.0));
+        // Held 5px from the viewport's left edge when scrolled right
+        assert_eq!(layer.scroll_translation(Rect::new(300.0, 500.0, 800.0, 600.0)), (205.0, 0.0));
+    }
+
+    #[test]
+    fn test_layers_bracket_their_content() {
+        let mut root = block(1, 800.0, 600.0);
+        let mut child = block(2, 100.0, 50.0);
+        child.style.will_change = WillChange::parse("transform");
+        child.style.background_color = rustkit_css::Color::new(255, 0, 0, 1.0);
+        root.children.push(child);
+
+        promote_layers(&mut root, &LayerBudget::default());
+        let list = DisplayList::build(&root);
+
+        let push = list
+            .commands
+            .iter()
+            .position(|cmd| matches!(cmd, DisplayCommand::PushLayer { id: 0, .. }))
+            .expect("layer pushed");
+        assert!(matches!(list.commands[push - 1], DisplayCommand::PushStackingContext { .. }));
+        assert!(matches!(list.commands[push + 1], DisplayCommand::SolidColor(..)));
+        assert!(matches!(list.commands[push + 2], DisplayCommand::PopLayer));
+    }
+}
@@ -14,19 +14,34 @@
 //! 7. **Stacking contexts**: Z-index based paint ordering
 //! 8. **Text rendering**: Font fallback, decorations, line height
 
+pub mod alloc_stats;
+pub mod arena;
+pub mod compact;
 pub mod flex;
 pub mod forms;
 pub mod grid;
 pub mod images;
 pub mod intrinsic_cache;
+pub mod layers;
+pub mod line_box;
 pub mod margin_collapse;
+pub mod multicol;
+pub mod pagination;
 pub mod scroll;
 pub mod text;
-
+pub mod top_layer;
+pub mod transform;
+pub mod validate;
+pub mod zoom;
+
+pub use alloc_stats::{AllocationSnapshot, AllocationStats, CountingAllocator};
+pub use arena::{ArenaStats, LayoutArena, SubtreeReuse};
+pub use compact::{CompactionStats, TextRunSegment};
 pub use grid::{layout_grid_container, GridItem, GridLayout, GridTrack};
 pub use forms::{
-    calculate_caret_position, calculate_selection_rects, render_button, render_checkbox,
-    render_input, render_radio, CaretInfo, InputLayout, InputState, SelectionInfo,
+    calculate_caret_position, calculate_selection_rects, range_fraction_at, render_button, render_checkbox,
+    render_color_swatch, render_input, render_radio, render_range, CaretInfo, ColorScheme, FormTheme,
+    InputLayout, InputState, SelectionInfo, TextCaret,
 };
 pub use flex::{layout_flex_container, Axis, FlexItem, FlexLine};
 pub use scroll::{
@@ -35,22 +50,27 @@ pub use scroll::{
     StickyState, WheelDeltaMode,
 };
 pub use images::{
-    calculate_intrinsic_size, calculate_placeholder_size, render_background_image,
+    background_tiles, calculate_intrinsic_size, calculate_placeholder_size, render_background_image,
     render_broken_image, render_image, ImageLayoutInfo,
 };
 pub use text::{
-    apply_text_transform, collapse_whitespace, FontCache, FontDisplay, FontFaceRule,
+    apply_bidi_reordering, apply_text_transform, collapse_whitespace, FontCache, FontDisplay, FontFaceRule,
     FontFamilyChain, FontLoader, LineHeight, PositionedGlyph, ShapedRun, TextDecoration, TextError,
     TextMetrics, TextShaper,
 };
 pub use intrinsic_cache::IntrinsicSizingMode;
+pub use layers::{promote_layers, CompositedLayer, LayerBudget, LayerTree, PromotionReason, ScrollAnchor};
+pub use top_layer::TopLayer;
+pub use line_box::{align_line, baseline_offset, LineStrut};
+pub use multicol::{is_multicol_container, layout_multicol_container, ColumnLayout};
+pub use pagination::page_breaks;
+pub use validate::{debug_assert_layout, validate_layout, LayoutViolation, ViolationKind};
 pub use margin_collapse::{
     collapse_margins, establishes_bfc, is_margin_collapsible_through,
     should_collapse_with_first_child, should_collapse_with_last_child, CollapsibleMargin,
 };
 
-use rustkit_css::{BoxSizing, Color, ComputedStyle, Length, TextAlign};
-use std::cmp::Ordering;
+use rustkit_css::{BoxSizing, Color, ComputedStyle, Direction, Length, ListStylePosition, Overflow, TextAlign};
 use thiserror::Error;
 
 /// Errors that can occur in layout.
@@ -491,7 +511,7 @@ impl MarginCollapseContext {
 }
 
 /// A 2D rectangle.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -644,11 +664,21 @@ pub enum FormControlType {
         checked: bool,
         name: String,
     },
-    /// Select dropdown (placeholder for future).
+    /// Select dropdown.
     Select {
         options: Vec<String>,
         selected_index: Option<usize>,
     },
+    /// Range slider (`<input type=range>`).
+    Range {
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+    /// Color swatch (`<input type=color>`).
+    ColorSwatch {
+        color: Color,
+    },
 }
 
 /// Stacking context for z-index ordering.
@@ -692,9 +722,33 @@ pub struct LayoutBox {
     pub viewport: (f32, f32),
     /// Sticky positioning state (for position: sticky elements).
     pub sticky_state: Option<StickyState>,
-    /// Optional element ID for intrinsic sizing cache.
-    /// When set, enables caching of min-content/max-content calculations.
+    /// ID of the DOM node this box was generated for, if any.
+    /// Keys the intrinsic sizing cache and maps DOM ranges onto boxes.
     pub element_id: Option<usize>,
+    /// Used column boxes, for multi-column containers.
+    pub column_layout: Option<ColumnLayout>,
+    /// Whether this box is a list item's `::marker`.
+    pub is_marker: bool,
+    /// Index of the compositor layer this box was promoted to, set by
+    /// [`promote_layers`].
+    pub layer: Option<usize>,
+    /// The part this box plays in the top layer, if it's in it.
+    pub top_layer: Option<TopLayer>,
+    /// Highlighted characters of a text box, set by
+    /// [`LayoutBox::highlight_range`].
+    pub text_highlight: Option<TextHighlight>,
+}
+
+/// A line of inline-level children still being filled during block layout.
+struct OpenLine {
+    /// Index of the first child on the line.
+    start: usize,
+    /// Top of the line relative to the parent's content box.
+    top: f32,
+    /// Advance width of the boxes placed so far.
+    width: f32,
+    /// Whether anything other than floats is on the line.
+    has_content: bool,
 }
 
 impl LayoutBox {
@@ -715,6 +769,11 @@ impl LayoutBox {
             viewport: (0.0, 0.0),
             sticky_state: None,
             element_id: None,
+            column_layout: None,
+            is_marker: false,
+            layer: None,
+            top_layer: None,
+            text_highlight: None,
         }
     }
 
@@ -762,6 +821,14 @@ impl LayoutBox {
         self.element_id
     }
 
+    /// The first box in this subtree generated for a DOM node.
+    pub fn find_element(&self, element_id: usize) -> Option<&LayoutBox> {
+        if self.element_id == Some(element_id) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find_element(element_id))
+    }
+
     /// Set position offsets.
     pub fn set_offsets(
         &mut self,
@@ -965,7 +1032,6 @@ impl LayoutBox {
         // Use the containing block's width for child layout, not our own (which might be 0)
         let available_width = containing_block.content.width;
         let mut cursor_x = 0.0;
-        let mut max_height = 0.0f32;
         
         for child in &mut self.children {
             let mut cb = self.dimensions.clone();
@@ -976,8 +1042,15 @@ impl LayoutBox {
             child.layout(&cb);
             
             cursor_x += child.dimensions.margin_box().width;
-            max_height = max_height.max(child.dimensions.margin_box().height);
         }
+
+        // Children share a single line, aligned on this box's baseline
+        let max_height = if self.children.is_empty() {
+            0.0
+        } else {
+            let strut = line_box::LineStrut::for_style(&self.style);
+            line_box::align_line(&mut self.children, &strut, self.dimensions.content.y)
+        };
         
         // Set content dimensions:
         // 1. Use explicit CSS width if specified
@@ -1016,47 +1089,16 @@ impl LayoutBox {
 
     /// Layout a text box.
     fn layout_text(&mut self, text: String, containing_block: &Dimensions) {
-        // Get font size
-        let font_size = match self.style.font_size {
-            Length::Px(px) => px,
-            _ => 16.0,
-        };
-
-        // Get letter-spacing and word-spacing in pixels
-        // CSS "normal" keyword (Auto/Zero) maps to 0.0 via the wildcard
-        let letter_spacing = match self.style.letter_spacing {
-            Length::Px(px) => px,
-            Length::Em(em) => em * font_size,
-            Length::Rem(rem) => rem * 16.0, // Root font size assumed 16px
-            _ => 0.0,
-        };
-        let word_spacing = match self.style.word_spacing {
-            Length::Px(px) => px,
-            Length::Em(em) => em * font_size,
-            Length::Rem(rem) => rem * 16.0,
-            _ => 0.0,
-        };
-
         // Use proper text measurement for width with spacing
-        let metrics = measure_text_with_spacing(
-            &text,
-            &self.style.font_family,
-            font_size,
-            self.style.font_weight,
-            self.style.font_style,
-            letter_spacing,
-            word_spacing,
-        );
-        let text_width = metrics.width;
+        let text_width = self.measure_text_width(&text);
 
         // Calculate text-align offset
         let container_width = containing_block.content.width;
         let text_align_offset = if container_width > text_width {
-            match self.style.text_align {
-                TextAlign::Left => 0.0,
+            match self.style.text_align.resolve(self.style.direction) {
                 TextAlign::Right => container_width - text_width,
                 TextAlign::Center => (container_width - text_width) / 2.0,
-                TextAlign::Justify => 0.0, // Single text run doesn't justify
+                _ => 0.0, // Left, and a single text run doesn't justify
             }
         } else {
             0.0
@@ -1090,6 +1132,15 @@ impl LayoutBox {
             _ => None,
         };
         
+        // A specified aspect-ratio replaces the image's natural one
+        let (natural_width, natural_height) = match self.style.aspect_ratio.filter(|r| r.is_finite() && *r > 0.0) {
+            Some(ratio) if natural_width > 0.0 => (natural_width, natural_width / ratio),
+            Some(ratio) if natural_height > 0.0 => (natural_height * ratio, natural_height),
+            // Without a natural size, fit the ratio to the default object width
+            Some(ratio) => (300.0, 300.0 / ratio),
+            None => (natural_width, natural_height),
+        };
+
         // Determine final dimensions using intrinsic size calculation
         let (width, height) = crate::images::calculate_intrinsic_size(
             if natural_width > 0.0 { Some(natural_width) } else { None },
@@ -1138,6 +1189,8 @@ impl LayoutBox {
                 // Dropdown: similar to text input but with arrow space
                 (font_size * 10.0, font_size * 1.5 + 8.0)
             }
+            FormControlType::Range { .. } => (font_size * 8.0, font_size * 1.2),
+            FormControlType::ColorSwatch { .. } => (font_size * 3.0, font_size * 1.5 + 4.0),
         };
         
         // Override with explicit CSS dimensions if specified, but always fall back to intrinsic
@@ -1171,6 +1224,232 @@ impl LayoutBox {
         self.dimensions.content.height = height;
     }
 
+    /// Width of `text` in this box's font, including letter- and word-spacing.
+    fn measure_text_width(&self, text: &str) -> f32 {
+        let font_size = match self.style.font_size {
+            Length::Px(px) => px,
+            _ => 16.0,
+        };
+
+        // Get letter-spacing and word-spacing in pixels
+        // CSS "normal" keyword (Auto/Zero) maps to 0.0 via the wildcard
+        let letter_spacing = match self.style.letter_spacing {
+            Length::Px(px) => px,
+            Length::Em(em) => em * font_size,
+            Length::Rem(rem) => rem * 16.0, // Root font size assumed 16px
+            _ => 0.0,
+        };
+        let word_spacing = match self.style.word_spacing {
+            Length::Px(px) => px,
+            Length::Em(em) => em * font_size,
+            Length::Rem(rem) => rem * 16.0,
+            _ => 0.0,
+        };
+
+        measure_text_with_spacing(
+            text,
+            &self.style.font_family,
+            font_size,
+            self.style.font_weight,
+            self.style.font_style,
+            letter_spacing,
+            word_spacing,
+        )
+        .width
+    }
+
+    /// Intrinsic inline size (width) of this box's content box.
+    ///
+    /// Min-content is the narrowest the box can get without overflowing, i.e.
+    /// its widest word or unbreakable child. Max-content is the width it takes
+    /// when nothing wraps. Results are memoized in the intrinsic sizing cache
+    /// for boxes that have an element ID.
+    pub fn intrinsic_inline_size(&self, mode: IntrinsicSizingMode) -> f32 {
+        let style_ptr = &self.style as *const ComputedStyle as usize;
+        let element_id = self.element_id.unwrap_or(0);
+        if let Some(cached) = intrinsic_cache::lookup_inline(element_id, style_ptr, mode) {
+            return cached;
+        }
+
+        let size = self.compute_intrinsic_inline_size(mode);
+        intrinsic_cache::store_inline(element_id, style_ptr, mode, size);
+        size
+    }
+
+    fn compute_intrinsic_inline_size(&self, mode: IntrinsicSizingMode) -> f32 {
+        let mode = match self.style.width {
+            Length::MinContent => IntrinsicSizingMode::MinContent,
+            Length::MaxContent => IntrinsicSizingMode::MaxContent,
+            _ => mode,
+        };
+
+        // A fixed width is the same at any size
+        if let Length::Px(px) = self.style.width {
+            if !matches!(self.box_type, BoxType::Text(_)) {
+                return if self.style.box_sizing == BoxSizing::BorderBox {
+                    (px - self.horizontal_padding_border()).max(0.0)
+                } else {
+                    px
+                };
+            }
+        }
+
+        // A fixed height sets the width through aspect-ratio
+        if !matches!(self.box_type, BoxType::Text(_)) {
+            if let Some(width) = self.fixed_content_height().and_then(|h| self.aspect_ratio_transfer(h, false)) {
+                return width;
+            }
+        }
+
+        self.content_intrinsic_inline_size(mode)
+    }
+
+    /// Intrinsic inline size measured from the box's content alone, ignoring
+    /// its own width property.
+    fn content_intrinsic_inline_size(&self, mode: IntrinsicSizingMode) -> f32 {
+        match &self.box_type {
+            BoxType::Text(text) => match mode {
+                IntrinsicSizingMode::MaxContent => self.measure_text_width(text),
+                IntrinsicSizingMode::MinContent => text
+                    .split_whitespace()
+                    .map(|word| self.measure_text_width(word))
+                    .fold(0.0, f32::max),
+            },
+            BoxType::Image { natural_width, .. } => *natural_width,
+            BoxType::FormControl(_) => {
+                // Controls size themselves from their font and type
+                let mut probe = LayoutBox::new(self.box_type.clone(), self.style.clone());
+                probe.layout(&Dimensions::default());
+                probe.dimensions.content.width
+            }
+            BoxType::Inline | BoxType::Block | BoxType::AnonymousBlock => {
+                // Inline-level children add up along a line; blocks each start their own
+                let mut widest = 0.0_f32;
+                let mut line = 0.0_f32;
+                for child in &self.children {
+                    if matches!(child.position, Position::Absolute | Position::Fixed) || child.is_outside_marker() {
+                        continue;
+                    }
+                    let outer = child.intrinsic_inline_size(mode) + child.horizontal_edges();
+                    if child.is_inline_level() && mode == IntrinsicSizingMode::MaxContent {
+                        line += outer;
+                    } else {
+                        widest = widest.max(line).max(outer);
+                        line = 0.0;
+                    }
+                }
+                widest.max(line)
+            }
+        }
+    }
+
+    /// Shrink-to-fit content width: max-content, but no wider than the
+    /// available space unless min-content needs more.
+    pub fn fit_content_width(&self, available: f32) -> f32 {
+        let min = self.intrinsic_inline_size(IntrinsicSizingMode::MinContent);
+        let max = self.intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+        max.min(available).max(min)
+    }
+
+    /// Content width for an intrinsic sizing keyword, or None for other lengths.
+    fn intrinsic_keyword_width(&self, length: &Length, available: f32) -> Option<f32> {
+        let min = || self.content_intrinsic_inline_size(IntrinsicSizingMode::MinContent);
+        let max = || self.content_intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+        match length {
+            Length::MinContent => Some(min()),
+            Length::MaxContent => Some(max()),
+            Length::FitContent => Some(max().min(available).max(min())),
+            _ => None,
+        }
+    }
+
+    /// Horizontal padding and border from style, ignoring percentages.
+    fn horizontal_padding_border(&self) -> f32 {
+        let style = &self.style;
+        [
+            &style.padding_left,
+            &style.padding_right,
+            &style.border_left_width,
+            &style.border_right_width,
+        ]
+        .into_iter()
+        .map(|length| self.intrinsic_edge_px(length))
+        .sum()
+    }
+
+    /// Vertical padding and border from style, ignoring percentages.
+    fn vertical_padding_border(&self) -> f32 {
+        let style = &self.style;
+        [
+            &style.padding_top,
+            &style.padding_bottom,
+            &style.border_top_width,
+            &style.border_bottom_width,
+        ]
+        .into_iter()
+        .map(|length| self.intrinsic_edge_px(length))
+        .sum()
+    }
+
+    /// Content size on the other axis implied by `aspect-ratio`, from a content
+    /// width (`to_height`) or content height. Under `box-sizing: border-box`
+    /// the ratio applies to the border box.
+    fn aspect_ratio_transfer(&self, size: f32, to_height: bool) -> Option<f32> {
+        let ratio = self.style.aspect_ratio.filter(|ratio| ratio.is_finite() && *ratio > 0.0)?;
+        let (from_edges, to_edges) = match (self.style.box_sizing, to_height) {
+            (BoxSizing::BorderBox, true) => (self.horizontal_padding_border(), self.vertical_padding_border()),
+            (BoxSizing::BorderBox, false) => (self.vertical_padding_border(), self.horizontal_padding_border()),
+            _ => (0.0, 0.0),
+        };
+        let outer = size + from_edges;
+        let other = if to_height { outer / ratio } else { outer * ratio };
+        Some((other - to_edges).max(0.0))
+    }
+
+    /// Content height from a height that doesn't depend on the containing
+    /// block, clamped by min/max-height. None for auto, percentage and
+    /// intrinsic heights.
+    fn fixed_content_height(&self) -> Option<f32> {
+        let edges = if self.style.box_sizing == BoxSizing::BorderBox {
+            self.vertical_padding_border()
+        } else {
+            0.0
+        };
+        let resolve = |length: &Length| {
+            (!length.is_content_sized() && !matches!(length, Length::Percent(_)))
+                .then(|| (self.length_to_px(length, 0.0) - edges).max(0.0))
+        };
+        let height = resolve(&self.style.height)?;
+        let min = resolve(&self.style.min_height).unwrap_or(0.0);
+        let max = match self.style.max_height {
+            // Like max-width, a zero max-height is treated as unset
+            Length::Zero => f32::INFINITY,
+            ref length => resolve(length).unwrap_or(f32::INFINITY),
+        };
+        Some(height.min(max).max(min))
+    }
+
+    /// Horizontal margin, padding and border from style, ignoring percentages
+    /// and auto margins.
+    fn horizontal_edges(&self) -> f32 {
+        self.horizontal_padding_border()
+            + self.intrinsic_edge_px(&self.style.margin_left)
+            + self.intrinsic_edge_px(&self.style.margin_right)
+    }
+
+    /// Resolve an edge length while measuring; percentages of a size that
+    /// isn't known yet count as zero.
+    fn intrinsic_edge_px(&self, length: &Length) -> f32 {
+        let font_size = match self.style.font_size {
+            Length::Px(px) => px,
+            _ => 16.0,
+        };
+        match length {
+            Length::Percent(_) | Length::Auto => 0.0,
+            length => length.to_px(font_size, 16.0, 0.0),
+        }
+    }
+
     /// Get line height for text layout.
     fn get_line_height(&self) -> f32 {
         let font_size = match self.style.font_size {
@@ -1248,7 +1527,11 @@ impl LayoutBox {
         self.calculate_block_position(containing_block);
 
         // Layout children
-        self.layout_block_children();
+        if is_multicol_container(&self.style) {
+            multicol::layout_multicol_container(self);
+        } else {
+            self.layout_block_children();
+        }
 
         // Height depends on children - use definite_height for percentage resolution
         self.calculate_block_height(definite_height);
@@ -1272,10 +1555,7 @@ impl LayoutBox {
         let collapsed_margin = margin_context.resolve();
 
         // Position the box with collapsed margin
-        self.dimensions.content.x = containing_block.content.x
-            + self.dimensions.margin.left
-            + self.dimensions.border.left
-            + self.dimensions.padding.left;
+        self.dimensions.content.x = self.block_content_x(containing_block);
 
         self.dimensions.content.y = containing_block.content.y
             + containing_block.content.height
@@ -1314,6 +1594,9 @@ impl LayoutBox {
                 self.dimensions.content.width,
                 self.dimensions.content.height,
             );
+        } else if is_multicol_container(&self.style) {
+            // Columns are a new formatting context, so margins don't collapse into them
+            multicol::layout_multicol_container(self);
         } else {
             // Normal block layout
             if blocks_collapse {
@@ -1583,12 +1866,22 @@ impl LayoutBox {
         let total_margin_border_padding =
             margin_left + margin_right + border_left + border_right + padding_left + padding_right;
 
+        // Space left for the content box; intrinsic keywords resolve against it
+        let available = (containing_block.content.width - total_margin_border_padding).max(0.0);
+
         // Calculate content width
         let content_width = match style.width {
-            Length::Auto => {
+            // A fixed height sets the width through aspect-ratio
+            Length::Auto => match self.fixed_content_height().and_then(|h| self.aspect_ratio_transfer(h, false)) {
+                Some(width) => width,
+                // Inline-blocks shrink to fit their content
+                None if style.display.is_inline_block() => self.fit_content_width(available),
                 // Fill available space
-                (containing_block.content.width - total_margin_border_padding).max(0.0)
-            }
+                None => available,
+            },
+            Length::MinContent | Length::MaxContent | Length::FitContent => self
+                .intrinsic_keyword_width(&style.width, available)
+                .unwrap_or(available),
             _ => {
                 let specified_width = self.length_to_px(&style.width, containing_block.content.width);
                 // With box-sizing: border-box, the specified width includes padding and border
@@ -1601,17 +1894,24 @@ impl LayoutBox {
         };
 
         // Apply min-width constraint (also respects box-sizing)
-        let min_width_raw = self.length_to_px(&style.min_width, containing_block.content.width);
-        let min_width = if style.box_sizing == BoxSizing::BorderBox && min_width_raw > 0.0 {
-            (min_width_raw - padding_left - padding_right - border_left - border_right).max(0.0)
+        let min_width = if let Some(width) = self.intrinsic_keyword_width(&style.min_width, available) {
+            width
         } else {
-            min_width_raw
+            let min_width_raw = self.length_to_px(&style.min_width, containing_block.content.width);
+            if style.box_sizing == BoxSizing::BorderBox && min_width_raw > 0.0 {
+                (min_width_raw - padding_left - padding_right - border_left - border_right).max(0.0)
+            } else {
+                min_width_raw
+            }
         };
         let content_width = content_width.max(min_width);
 
         // Apply max-width constraint (also respects box-sizing)
         let max_width = match style.max_width {
             Length::Auto | Length::Zero => f32::INFINITY,
+            Length::MinContent | Length::MaxContent | Length::FitContent => self
+                .intrinsic_keyword_width(&style.max_width, available)
+                .unwrap_or(f32::INFINITY),
             _ => {
                 let max_width_raw = self.length_to_px(&style.max_width, containing_block.content.width);
                 if style.box_sizing == BoxSizing::BorderBox {
@@ -1632,6 +1932,21 @@ impl LayoutBox {
         self.dimensions.padding.right = padding_right;
     }
 
+    /// Horizontal content position of a block box in its containing block.
+    ///
+    /// Blocks sit against the start edge: in RTL the left margin is the one that
+    /// absorbs any leftover space, so the box hugs the right edge instead. The
+    /// box's own (inherited) direction stands in for the containing block's.
+    /// Inline-blocks are placed by their line instead.
+    fn block_content_x(&self, containing_block: &Dimensions) -> f32 {
+        let d = &self.dimensions;
+        if self.style.direction.is_rtl() && !self.style.display.is_inline_block() {
+            containing_block.content.right() - d.margin.right - d.border.right - d.padding.right - d.content.width
+        } else {
+            containing_block.content.x + d.margin.left + d.border.left + d.padding.left
+        }
+    }
+
     /// Calculate block position.
     fn calculate_block_position(&mut self, containing_block: &Dimensions) {
         let style = &self.style;
@@ -1650,10 +1965,7 @@ impl LayoutBox {
             self.length_to_px(&style.padding_bottom, containing_block.content.width);
 
         // Position below the containing block's content
-        self.dimensions.content.x = containing_block.content.x
-            + self.dimensions.margin.left
-            + self.dimensions.border.left
-            + self.dimensions.padding.left;
+        self.dimensions.content.x = self.block_content_x(containing_block);
 
         self.dimensions.content.y = containing_block.content.y
             + containing_block.content.height
@@ -1673,92 +1985,35 @@ impl LayoutBox {
     /// Layout block children.
     fn layout_block_children(&mut self) {
         let mut cursor_y = 0.0;
-        let mut cursor_x = 0.0;
-        let mut line_height = 0.0_f32;
-        let container_width = self.dimensions.content.width;
-        let text_align = self.style.text_align;
+        let mut line: Option<OpenLine> = None;
 
-        // Track lines for text-align adjustment after layout: (start_index, end_index, line_width)
-        let mut lines: Vec<(usize, usize, f32)> = Vec::new();
-        let mut line_start_index: Option<usize> = None;
-        let mut line_width = 0.0_f32;
+        for i in 0..self.children.len() {
+            let mut cb = self.dimensions.clone();
+            let child = &mut self.children[i];
 
-        for (i, child) in self.children.iter_mut().enumerate() {
             // Skip absolutely/fixed positioned children for flow layout
             if child.position == Position::Absolute || child.position == Position::Fixed {
-                let mut cb = self.dimensions.clone();
                 cb.content.height = cursor_y;
                 child.layout(&cb);
                 continue;
             }
+            if child.is_outside_marker() {
+                continue;
+            }
 
-            // Check if child is inline-block
-            let is_inline_block = child.style.display.is_inline_block();
-
-            if is_inline_block {
-                // Layout inline-block child to get its dimensions first
-                let mut cb = self.dimensions.clone();
-                cb.content.x = self.dimensions.content.x + cursor_x;
-                cb.content.y = self.dimensions.content.y + cursor_y;
+            if child.is_inline_level() {
+                // Lay out to get dimensions first, then place on the line
+                let line_top = line.as_ref().map_or(cursor_y, |l| l.top);
+                cb.content.x = self.dimensions.content.x + line.as_ref().map_or(0.0, |l| l.width);
+                cb.content.y = self.dimensions.content.y + line_top;
                 child.layout(&cb);
-
-                let child_width = child.dimensions.margin_box().width;
-                let child_height = child.dimensions.margin_box().height;
-
-                // Check if child fits on current line
-                if cursor_x > 0.0 && cursor_x + child_width > container_width {
-                    // Record completed line for text-align
-                    if let Some(start) = line_start_index {
-                        lines.push((start, i, line_width));
-                    }
-
-                    // Wrap to next line
-                    cursor_y += line_height;
-                    cursor_x = 0.0;
-                    line_height = 0.0;
-                    line_start_index = Some(i);
-                    line_width = 0.0;
-
-                    // Re-layout at new position
-                    cb.content.x = self.dimensions.content.x;
-                    cb.content.y = self.dimensions.content.y + cursor_y;
-                    child.layout(&cb);
-                }
-
-                // Track line start
-                if line_start_index.is_none() {
-                    line_start_index = Some(i);
-                }
-
-                // Position the child
-                child.dimensions.content.x = self.dimensions.content.x + cursor_x + child.dimensions.margin.left;
-                child.dimensions.content.y = self.dimensions.content.y + cursor_y + child.dimensions.margin.top;
-
-                // Advance cursor
-                cursor_x += child_width;
-                line_width += child_width;
-                line_height = line_height.max(child_height);
-
-                if child.float != Float::None {
-                    // Floated elements don't affect cursor
-                    cursor_x -= child_width;
-                    line_width -= child_width;
-                }
+                self.place_on_line(i, &mut line, &mut cursor_y);
             } else {
                 // Regular block layout
-                // First, finish any inline-block line
-                if cursor_x > 0.0 {
-                    if let Some(start) = line_start_index {
-                        lines.push((start, i, line_width));
-                    }
-                    cursor_y += line_height;
-                    cursor_x = 0.0;
-                    line_height = 0.0;
-                    line_start_index = None;
-                    line_width = 0.0;
-                }
+                // First, finish any open line
+                self.finish_line(&mut line, i, &mut cursor_y);
 
-                let mut cb = self.dimensions.clone();
+                let child = &mut self.children[i];
                 cb.content.height = cursor_y;
                 child.layout(&cb);
 
@@ -1768,42 +2023,214 @@ impl LayoutBox {
             }
         }
 
-        // Record any remaining inline-block line
-        if cursor_x > 0.0 {
-            if let Some(start) = line_start_index {
-                lines.push((start, self.children.len(), line_width));
+        self.finish_line(&mut line, self.children.len(), &mut cursor_y);
+        self.layout_outside_markers();
+
+        self.dimensions.content.height = cursor_y;
+    }
+
+    /// Whether this is a `::marker` hung outside its list item's content.
+    fn is_outside_marker(&self) -> bool {
+        self.is_marker && self.style.list_style_position == ListStylePosition::Outside
+    }
+
+    /// Place outside markers against the start edge of the content box,
+    /// level with the first line.
+    fn layout_outside_markers(&mut self) {
+        let content = self.dimensions.content;
+        let rtl = self.style.direction.is_rtl();
+        for marker in self.children.iter_mut().filter(|c| c.is_outside_marker()) {
+            let mut cb = self.dimensions.clone();
+            cb.content.height = 0.0;
+            marker.layout(&cb);
+            let margin_box = marker.dimensions.margin_box();
+            let x = if rtl { content.right() } else { content.x - margin_box.width };
+            marker.translate(x - margin_box.x, content.y - margin_box.y);
+        }
+    }
+
+    /// Whether this box is laid out on a line rather than stacked as a block.
+    fn is_inline_level(&self) -> bool {
+        match self.box_type {
+            BoxType::Text(_) => true,
+            BoxType::Inline | BoxType::Image { .. } | BoxType::FormControl(_) => {
+                self.style.display.is_inline_level()
             }
-            cursor_y += line_height;
+            BoxType::Block | BoxType::AnonymousBlock => self.style.display.is_inline_block(),
+        }
+    }
+
+    /// Whether this box creates a stacking context.
+    pub fn creates_stacking_context(&self) -> bool {
+        !self.style.transform.is_identity()
+            || self.style.opacity < 1.0
+            || self.layer.is_some()
+            || self.top_layer.is_some()
+            || self.stacking_context.as_ref().is_some_and(|ctx| ctx.creates_context)
+    }
+
+    /// Whether this box clips its content to its padding box (`overflow`
+    /// other than `visible` on either axis). `overflow` only applies to
+    /// block containers.
+    fn clips_overflow(&self) -> bool {
+        matches!(self.box_type, BoxType::Block | BoxType::AnonymousBlock)
+            && (self.style.overflow_x != Overflow::Visible || self.style.overflow_y != Overflow::Visible)
+    }
+
+    /// Corner radii of this box's border box, in pixels.
+    pub fn border_radius(&self) -> BorderRadius {
+        let s = &self.style;
+        let width = self.dimensions.border_box().width;
+        let font_size = match s.font_size {
+            Length::Px(px) => px,
+            _ => 16.0,
+        };
+        let root_font_size = 16.0; // TODO: Pass actual root font size
+        BorderRadius {
+            top_left: s.border_top_left_radius.to_px(font_size, root_font_size, width),
+            top_right: s.border_top_right_radius.to_px(font_size, root_font_size, width),
+            bottom_right: s.border_bottom_right_radius.to_px(font_size, root_font_size, width),
+            bottom_left: s.border_bottom_left_radius.to_px(font_size, root_font_size, width),
+        }
+    }
+
+    /// The region content is clipped to when this box clips overflow: its
+    /// padding box, with the outer border radii reduced by the border
+    /// widths meeting at each corner.
+    fn overflow_clip(&self) -> (Rect, BorderRadius) {
+        let border = &self.dimensions.border;
+        let outer = self.border_radius();
+        let inner = |radius: f32, a: f32, b: f32| (radius - a.max(b)).max(0.0);
+        let radius = BorderRadius {
+            top_left: inner(outer.top_left, border.left, border.top),
+            top_right: inner(outer.top_right, border.right, border.top),
+            bottom_right: inner(outer.bottom_right, border.right, border.bottom),
+            bottom_left: inner(outer.bottom_left, border.left, border.bottom),
+        };
+        (self.dimensions.padding_box(), radius)
+    }
+
+    /// The z-index this box is painted at; `z-index` only applies to
+    /// positioned boxes.
+    fn stacking_z_index(&self) -> i32 {
+        if self.position != Position::Static {
+            self.z_index
+        } else {
+            0
         }
+    }
 
-        // Apply text-align to all recorded lines
-        for (start, end, width) in lines {
-            Self::apply_text_align_offset(&mut self.children[start..end], width, container_width, text_align);
+    /// Whether this box's background is painted with the in-flow blocks,
+    /// before floats and inline content.
+    fn is_block_level_for_paint(&self) -> bool {
+        match self.box_type {
+            BoxType::Text(_) | BoxType::Inline => false,
+            BoxType::Block | BoxType::AnonymousBlock => !self.style.display.is_inline_block(),
+            BoxType::Image { .. } | BoxType::FormControl(_) => !self.style.display.is_inline_level(),
         }
+    }
 
-        self.dimensions.content.height = cursor_y;
+    /// Whether this box is an inline block, painted atomically with the
+    /// inline content around it.
+    fn is_atomic_inline(&self) -> bool {
+        matches!(self.box_type, BoxType::Block | BoxType::AnonymousBlock) && self.style.display.is_inline_block()
+    }
+
+    /// Move the just laid out child `index` to the end of the open line,
+    /// wrapping to a new line first if it doesn't fit.
+    fn place_on_line(&mut self, index: usize, line: &mut Option<OpenLine>, cursor_y: &mut f32) {
+        let container_width = self.dimensions.content.width;
+        let child = &self.children[index];
+        let margin_box = child.dimensions.margin_box();
+        let floated = child.float != Float::None;
+
+        let wraps = line
+            .as_ref()
+            .is_some_and(|l| !floated && l.has_content && l.width + margin_box.width > container_width);
+        if wraps {
+            self.finish_line(line, index, cursor_y);
+        }
+
+        let open = line.get_or_insert(OpenLine { start: index, top: *cursor_y, width: 0.0, has_content: false });
+        let dx = self.dimensions.content.x + open.width - margin_box.x;
+        let dy = self.dimensions.content.y + open.top - margin_box.y;
+        self.children[index].translate(dx, dy);
+
+        // Floated elements don't advance the line
+        if !floated {
+            open.width += margin_box.width;
+            open.has_content = true;
+        }
+    }
+
+    /// Close the open line: align its boxes vertically (`vertical-align`) and
+    /// horizontally (`text-align`), then advance `cursor_y` past it.
+    fn finish_line(&mut self, line: &mut Option<OpenLine>, end: usize, cursor_y: &mut f32) {
+        let Some(open) = line.take() else {
+            return;
+        };
+        // A line holding only floats takes up no space
+        if !open.has_content {
+            return;
+        }
+
+        let strut = line_box::LineStrut::for_style(&self.style);
+        let container_width = self.dimensions.content.width;
+        let items = &mut self.children[open.start..end];
+        let height = line_box::align_line(items, &strut, self.dimensions.content.y + open.top);
+        Self::apply_text_align_offset(items, open.width, container_width, self.style.text_align, self.style.direction);
+
+        *cursor_y = open.top + height;
     }
 
     /// Apply text-align offset to inline children on a line.
+    ///
+    /// Children are laid out left to right; in RTL the line is mirrored so the
+    /// first child ends up at the right.
     fn apply_text_align_offset(
         children: &mut [LayoutBox],
         line_width: f32,
         container_width: f32,
         text_align: TextAlign,
+        direction: Direction,
     ) {
-        let offset = match text_align {
-            TextAlign::Left => 0.0,
+        let offset = match text_align.resolve(direction) {
             TextAlign::Right => (container_width - line_width).max(0.0),
             TextAlign::Center => ((container_width - line_width) / 2.0).max(0.0),
-            TextAlign::Justify => 0.0, // Justify would need gap distribution (complex)
+            _ => 0.0, // Left; justify would need gap distribution (complex)
         };
 
-        if offset > 0.0 {
-            for child in children {
-                if child.style.display.is_inline_block() {
-                    child.dimensions.content.x += offset;
-                }
+        let on_line = |c: &LayoutBox| {
+            c.is_inline_level() && !matches!(c.position, Position::Absolute | Position::Fixed)
+        };
+        let line_start = children
+            .iter()
+            .filter(|c| on_line(c) && c.float == Float::None)
+            .map(|c| c.dimensions.margin_box().x)
+            .fold(f32::INFINITY, f32::min);
+
+        for child in children {
+            if !on_line(child) {
+                continue;
+            }
+            let mut dx = offset;
+            if direction.is_rtl() && child.float == Float::None {
+                let margin_box = child.dimensions.margin_box();
+                let start = margin_box.x - line_start;
+                dx += line_width - margin_box.width - 2.0 * start;
             }
+            if dx != 0.0 {
+                child.translate(dx, 0.0);
+            }
+        }
+    }
+
+    /// Move this box and everything laid out inside it.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        self.dimensions.content.x += dx;
+        self.dimensions.content.y += dy;
+        for child in &mut self.children {
+            child.translate(dx, dy);
         }
     }
 
@@ -1814,92 +2241,36 @@ impl LayoutBox {
         float_context: &mut FloatContext,
     ) {
         let mut cursor_y = 0.0;
-        let mut cursor_x = 0.0;
-        let mut line_height = 0.0_f32;
-        let container_width = self.dimensions.content.width;
-        let text_align = self.style.text_align;
+        let mut line: Option<OpenLine> = None;
 
-        // Track lines for text-align adjustment after layout: (start_index, end_index, line_width)
-        let mut lines: Vec<(usize, usize, f32)> = Vec::new();
-        let mut line_start_index: Option<usize> = None;
-        let mut line_width = 0.0_f32;
+        for i in 0..self.children.len() {
+            let mut cb = self.dimensions.clone();
+            let child = &mut self.children[i];
 
-        for (i, child) in self.children.iter_mut().enumerate() {
             // Skip absolutely/fixed positioned children for flow layout
             if child.position == Position::Absolute || child.position == Position::Fixed {
-                let mut cb = self.dimensions.clone();
                 cb.content.height = cursor_y;
                 child.layout_with_collapse(&cb, margin_context, float_context);
                 continue;
             }
+            if child.is_outside_marker() {
+                continue;
+            }
 
-            // Check if child is inline-block
-            let is_inline_block = child.style.display.is_inline_block();
-
-            if is_inline_block {
-                // Inline-block elements don't participate in margin collapse
-                // Layout to get dimensions first
-                let mut cb = self.dimensions.clone();
-                cb.content.x = self.dimensions.content.x + cursor_x;
-                cb.content.y = self.dimensions.content.y + cursor_y;
+            if child.is_inline_level() {
+                // Inline-level boxes don't participate in margin collapse
+                // Layout to get dimensions first, then place on the line
+                let line_top = line.as_ref().map_or(cursor_y, |l| l.top);
+                cb.content.x = self.dimensions.content.x + line.as_ref().map_or(0.0, |l| l.width);
+                cb.content.y = self.dimensions.content.y + line_top;
                 child.layout_with_collapse(&cb, margin_context, float_context);
-
-                let child_width = child.dimensions.margin_box().width;
-                let child_height = child.dimensions.margin_box().height;
-
-                // Check if child fits on current line
-                if cursor_x > 0.0 && cursor_x + child_width > container_width {
-                    // Record completed line for text-align
-                    if let Some(start) = line_start_index {
-                        lines.push((start, i, line_width));
-                    }
-
-                    // Wrap to next line
-                    cursor_y += line_height;
-                    cursor_x = 0.0;
-                    line_height = 0.0;
-                    line_start_index = Some(i);
-                    line_width = 0.0;
-
-                    // Re-layout at new position
-                    cb.content.x = self.dimensions.content.x;
-                    cb.content.y = self.dimensions.content.y + cursor_y;
-                    child.layout_with_collapse(&cb, margin_context, float_context);
-                }
-
-                // Track line start
-                if line_start_index.is_none() {
-                    line_start_index = Some(i);
-                }
-
-                // Position the child
-                child.dimensions.content.x = self.dimensions.content.x + cursor_x + child.dimensions.margin.left;
-                child.dimensions.content.y = self.dimensions.content.y + cursor_y + child.dimensions.margin.top;
-
-                // Advance cursor
-                cursor_x += child_width;
-                line_width += child_width;
-                line_height = line_height.max(child_height);
-
-                if child.float != Float::None {
-                    cursor_x -= child_width;
-                    line_width -= child_width;
-                }
+                self.place_on_line(i, &mut line, &mut cursor_y);
             } else {
                 // Regular block layout with margin collapse
-                // First, finish any inline-block line
-                if cursor_x > 0.0 {
-                    if let Some(start) = line_start_index {
-                        lines.push((start, i, line_width));
-                    }
-                    cursor_y += line_height;
-                    cursor_x = 0.0;
-                    line_height = 0.0;
-                    line_start_index = None;
-                    line_width = 0.0;
-                }
+                // First, finish any open line
+                self.finish_line(&mut line, i, &mut cursor_y);
 
-                let mut cb = self.dimensions.clone();
+                let child = &mut self.children[i];
                 cb.content.height = cursor_y;
                 child.layout_with_collapse(&cb, margin_context, float_context);
 
@@ -1909,18 +2280,8 @@ impl LayoutBox {
             }
         }
 
-        // Record any remaining inline-block line
-        if cursor_x > 0.0 {
-            if let Some(start) = line_start_index {
-                lines.push((start, self.children.len(), line_width));
-            }
-            cursor_y += line_height;
-        }
-
-        // Apply text-align to all recorded lines
-        for (start, end, width) in lines {
-            Self::apply_text_align_offset(&mut self.children[start..end], width, container_width, text_align);
-        }
+        self.finish_line(&mut line, self.children.len(), &mut cursor_y);
+        self.layout_outside_markers();
 
         self.dimensions.content.height = cursor_y;
     }
@@ -1996,12 +2357,17 @@ impl LayoutBox {
                 };
             }
             _ => {
-                // Auto or Zero - content.height was set by layout_block_children
-                // But if aspect-ratio is set and we have a width, calculate height from it
-                if let Some(ratio) = self.style.aspect_ratio {
-                    if self.dimensions.content.width > 0.0 && ratio > 0.0 {
-                        self.dimensions.content.height = self.dimensions.content.width / ratio;
-                    }
+                // Auto or Zero - content.height was set by layout_block_children. With an
+                // aspect-ratio the width sets the height instead, though content
+                // still stretches a box that doesn't scroll
+                if let Some(height) = self.aspect_ratio_transfer(self.dimensions.content.width, true) {
+                    let content_height = self.dimensions.content.height;
+                    self.dimensions.content.height =
+                        if is_scroll_container(self.style.overflow_x, self.style.overflow_y) {
+                            height
+                        } else {
+                            height.max(content_height)
+                        };
                 }
             }
         }
@@ -2097,12 +2463,29 @@ impl LayoutBox {
 
     /// Perform hit testing at the given point.
     /// Returns the hit test result with information about the element at the point.
+    ///
+    /// Top layer children are tested first, and outside this box's bounds.
     pub fn hit_test(&self, x: f32, y: f32) -> Option<HitTestResult> {
+        for layer in self.children.iter().rev().filter(|child| child.top_layer.is_some()) {
+            if let Some(mut result) = layer.hit_test_internal(x, y, 1) {
+                result.ancestors.push(HitTestAncestor {
+                    box_type: self.box_type.clone(),
+                    element_id: self.element_id,
+                    border_box: self.dimensions.border_box(),
+                    content_box: self.dimensions.content,
+                    z_index: self.z_index,
+                    position: self.position,
+                });
+                return Some(result);
+            }
+        }
         self.hit_test_internal(x, y, 0)
     }
 
     /// Internal hit test that tracks depth.
     fn hit_test_internal(&self, x: f32, y: f32, depth: u32) -> Option<HitTestResult> {
+        let (x, y) = self.untransform_point(x, y)?;
+
         // Get the border box for this element
         let border_box = self.dimensions.border_box();
 
@@ -2118,6 +2501,7 @@ impl LayoutBox {
                 // Found a hit in a child - add ourselves to the path
                 result.ancestors.push(HitTestAncestor {
                     box_type: self.box_type.clone(),
+                    element_id: self.element_id,
                     border_box: self.dimensions.border_box(),
                     content_box: self.dimensions.content,
                     z_index: self.z_index,
@@ -2130,6 +2514,7 @@ impl LayoutBox {
         // No child was hit, so we are the target
         Some(HitTestResult {
             box_type: self.box_type.clone(),
+            element_id: self.element_id,
             border_box,
             content_box: self.dimensions.content,
             padding_box: self.dimensions.padding_box(),
@@ -2143,6 +2528,16 @@ impl LayoutBox {
         })
     }
 
+    /// Map a point into this box's untransformed coordinates, undoing its
+    /// `transform`. `None` if the transform is degenerate, so nothing in
+    /// the box can be hit.
+    fn untransform_point(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        match self.transform_matrix() {
+            Some(matrix) => transform::invert(matrix).map(|inverse| transform::apply(inverse, x, y)),
+            None => Some((x, y)),
+        }
+    }
+
     /// Check if a point is within the border box.
     pub fn contains_point(&self, x: f32, y: f32) -> bool {
         self.dimensions.border_box().contains(x, y)
@@ -2157,6 +2552,9 @@ impl LayoutBox {
 
     /// Internal hit test that collects all results.
     fn hit_test_all_internal(&self, x: f32, y: f32, depth: u32, results: &mut Vec<HitTestResult>) {
+        let Some((x, y)) = self.untransform_point(x, y) else {
+            return;
+        };
         let border_box = self.dimensions.border_box();
 
         if !border_box.contains(x, y) {
@@ -2166,6 +2564,7 @@ impl LayoutBox {
         // Add this element
         results.push(HitTestResult {
             box_type: self.box_type.clone(),
+            element_id: self.element_id,
             border_box,
             content_box: self.dimensions.content,
             padding_box: self.dimensions.padding_box(),
@@ -2183,6 +2582,103 @@ impl LayoutBox {
             child.hit_test_all_internal(x, y, depth + 1, results);
         }
     }
+
+    /// Rects of the boxes a DOM range covers (`Range.getClientRects()`), in
+    /// layout coordinates.
+    ///
+    /// `select` says how much of the box with a given element ID the range
+    /// covers. A wholly selected box contributes its border box and hides
+    /// its descendants; a partly selected text box contributes the span of
+    /// its selected characters.
+    pub fn range_client_rects(&self, select: &dyn Fn(usize) -> Option<RangeSelection>) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        self.collect_range_rects(select, &mut rects);
+        rects
+    }
+
+    fn collect_range_rects(&self, select: &dyn Fn(usize) -> Option<RangeSelection>, rects: &mut Vec<Rect>) {
+        match self.element_id.and_then(select) {
+            Some(RangeSelection::Whole) => rects.push(self.dimensions.border_box()),
+            Some(RangeSelection::Text { start, end }) => {
+                if let BoxType::Text(text) = &self.box_type {
+                    rects.push(self.text_span_rect(text, start, end));
+                }
+            }
+            None => {
+                for child in &self.children {
+                    child.collect_range_rects(select, rects);
+                }
+            }
+        }
+    }
+
+    /// The rect characters `start..end` of this text box's `text` cover.
+    fn text_span_rect(&self, text: &str, start: usize, end: usize) -> Rect {
+        let prefix: String = text.chars().take(start).collect();
+        let selected: String = text.chars().skip(start).take(end.saturating_sub(start)).collect();
+        let content = self.dimensions.content;
+        Rect::new(
+            content.x + self.measure_text_width(&prefix),
+            content.y,
+            self.measure_text_width(&selected),
+            content.height,
+        )
+    }
+
+    /// Highlight the text a DOM range covers in `color`, painted behind the
+    /// glyphs, as for a URL text fragment.
+    ///
+    /// `select` is as for [`LayoutBox::range_client_rects`]; a wholly
+    /// selected box highlights all the text inside it.
+    pub fn highlight_range(&mut self, select: &dyn Fn(usize) -> Option<RangeSelection>, color: Color) {
+        match self.element_id.and_then(select) {
+            Some(RangeSelection::Whole) => self.highlight_all_text(color),
+            Some(RangeSelection::Text { start, end }) => {
+                if matches!(self.box_type, BoxType::Text(_)) {
+                    self.text_highlight = Some(TextHighlight { start, end, color });
+                }
+            }
+            None => {
+                for child in &mut self.children {
+                    child.highlight_range(select, color);
+                }
+            }
+        }
+    }
+
+    /// Remove the text highlights in this tree.
+    pub fn clear_text_highlights(&mut self) {
+        self.text_highlight = None;
+        for child in &mut self.children {
+            child.clear_text_highlights();
+        }
+    }
+
+    fn highlight_all_text(&mut self, color: Color) {
+        if let BoxType::Text(text) = &self.box_type {
+            self.text_highlight = Some(TextHighlight { start: 0, end: text.chars().count(), color });
+        }
+        for child in &mut self.children {
+            child.highlight_all_text(color);
+        }
+    }
+}
+
+/// Characters `start..end` of a text box, painted over `color`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextHighlight {
+    pub start: usize,
+    pub end: usize,
+    pub color: Color,
+}
+
+/// How much of a layout box a DOM range covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeSelection {
+    /// The whole box.
+    Whole,
+    /// Characters `start..end` of a text box.
+    Text { start: usize, end: usize },
 }
 
 /// Result of a hit test operation.
@@ -2190,6 +2686,8 @@ impl LayoutBox {
 pub struct HitTestResult {
     /// The type of the hit box.
     pub box_type: BoxType,
+    /// ID of the DOM node the hit box was generated for.
+    pub element_id: Option<usize>,
     /// The border box of the hit element.
     pub border_box: Rect,
     /// The content box of the hit element.
@@ -2213,6 +2711,12 @@ pub struct HitTestResult {
 }
 
 impl HitTestResult {
+    /// The DOM node of the innermost box on the hit path that has one.
+    pub fn node_id(&self) -> Option<usize> {
+        self.element_id
+            .or_else(|| self.ancestors.iter().find_map(|ancestor| ancestor.element_id))
+    }
+
     /// Check if the hit was in the content area.
     pub fn is_in_content(&self) -> bool {
         self.content_box.contains(
@@ -2241,6 +2745,8 @@ impl HitTestResult {
 pub struct HitTestAncestor {
     /// Box type.
     pub box_type: BoxType,
+    /// DOM node ID.
+    pub element_id: Option<usize>,
     /// Border box.
     pub border_box: Rect,
     /// Content box.
@@ -2252,7 +2758,7 @@ pub struct HitTestAncestor {
 }
 
 /// Border radius values for each corner.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct BorderRadius {
     pub top_left: f32,
     pub top_right: f32,
@@ -2279,7 +2785,7 @@ impl BorderRadius {
 }
 
 /// A paint command for rendering.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DisplayCommand {
     /// Fill a rectangle with a solid color.
     SolidColor(Color, Rect),
@@ -2309,6 +2815,15 @@ pub enum DisplayCommand {
         font_weight: u16,
         font_style: u8,
     },
+    /// Draw several pieces of text sharing a font and color.
+    TextRun {
+        segments: Vec<TextRunSegment>,
+        color: Color,
+        font_size: f32,
+        font_family: String,
+        font_weight: u16,
+        font_style: u8,
+    },
     /// Draw text decoration line (underline, strikethrough, overline).
     TextDecoration {
         x: f32,
@@ -2341,6 +2856,8 @@ pub enum DisplayCommand {
         size: BackgroundSize,
         /// Background position (0-1 range)
         position: (f32, f32),
+        /// Offset in pixels added to `position`
+        offset: (f32, f32),
         /// Background repeat
         repeat: BackgroundRepeat,
     },
@@ -2442,6 +2959,11 @@ pub enum DisplayCommand {
     PushClip(Rect),
     /// Pop clip rect.
     PopClip,
+    /// Push a rounded clip rect (overflow clipping of a box with
+    /// `border-radius`). Edges are antialiased.
+    PushRoundedClip { rect: Rect, radius: BorderRadius },
+    /// Pop a rounded clip rect.
+    PopRoundedClip,
     /// Start stacking context.
     PushStackingContext { z_index: i32, rect: Rect },
     /// End stacking context.
@@ -2458,6 +2980,17 @@ pub enum DisplayCommand {
     },
     /// Pop a transform matrix.
     PopTransform,
+    /// Start the content of compositor layer `id`, whose root's border box
+    /// is `rect`. The renderer applies the layer's transform (around the
+    /// center of `rect`) and opacity to everything up to the matching pop.
+    PushLayer { id: usize, rect: Rect },
+    /// End a compositor layer.
+    PopLayer,
+    /// Start a group of commands composited together with the given
+    /// opacity (CSS `opacity` on an element and its descendants).
+    PushOpacity(f32),
+    /// End an opacity group.
+    PopOpacity,
 
     /// Draw text with a gradient fill (for background-clip: text effect).
     GradientText {
@@ -2671,10 +3204,16 @@ impl BackgroundSize {
             BackgroundSize::Auto => (image_width, image_height),
 
             BackgroundSize::Explicit { width, height } => {
+                // Negative lengths are percentages of the container.
+                let resolve = |length: f32, extent: f32| {
+                    if length < 0.0 { extent * -length / 100.0 } else { length }
+                };
+                let width = width.map(|w| resolve(w, container.width));
+                let height = height.map(|h| resolve(h, container.height));
                 match (width, height) {
-                    (Some(w), Some(h)) => (*w, *h),
-                    (Some(w), None) => (*w, *w / image_aspect),
-                    (None, Some(h)) => (*h * image_aspect, *h),
+                    (Some(w), Some(h)) => (w, h),
+                    (Some(w), None) => (w, w / image_aspect),
+                    (None, Some(h)) => (h * image_aspect, h),
                     (None, None) => (image_width, image_height),
                 }
             }
@@ -2738,22 +3277,68 @@ fn parse_length(value: &str) -> Option<f32> {
     }
 }
 
-/// A paint item with z-index for sorting.
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-struct PaintItem {
-    z_index: i32,
-    layer: u32, // For stable sort within same z-index
-    commands: Vec<DisplayCommand>,
+/// The boxes a stacking context paints in its own phases, in tree order
+/// (CSS 2.1 Appendix E).
+#[derive(Default)]
+struct StackingLayers<'a> {
+    /// Child stacking contexts with negative z-index.
+    negative: Vec<&'a LayoutBox>,
+    /// Positioned descendants with z-index `auto` or 0, and other child
+    /// stacking contexts (transform, opacity, compositor layers).
+    zero: Vec<&'a LayoutBox>,
+    /// Child stacking contexts with positive z-index.
+    positive: Vec<&'a LayoutBox>,
 }
 
-#[allow(dead_code)]
-impl PaintItem {
-    fn new(z_index: i32, layer: u32) -> Self {
-        Self {
-            z_index,
-            layer,
-            commands: Vec::new(),
+impl<'a> StackingLayers<'a> {
+    /// Collect the positioned and stacking context descendants of `layout_box`
+    /// that belong to the stacking context being painted.
+    fn collect(&mut self, layout_box: &'a LayoutBox) {
+        for child in &layout_box.children {
+            // The top layer is painted after the root stacking context
+            if child.style.opacity <= 0.0 || child.top_layer.is_some() {
+                continue;
+            }
+            if child.creates_stacking_context() {
+                match child.stacking_z_index() {
+                    z if z < 0 => self.negative.push(child),
+                    0 => self.zero.push(child),
+                    _ => self.positive.push(child),
+                }
+            } else {
+                if child.position != Position::Static {
+                    self.zero.push(child);
+                }
+                // Positioned descendants of z-index `auto` boxes, floats and
+                // inline blocks still belong to this context, unless a box
+                // clips them
+                if !child.clips_overflow() {
+                    self.collect(child);
+                }
+            }
+        }
+        // Stable, so tree order breaks ties
+        self.negative.sort_by_key(|b| b.stacking_z_index());
+        self.positive.sort_by_key(|b| b.stacking_z_index());
+    }
+}
+
+/// Collect the in-flow descendants of `layout_box` painted in its flow, in
+/// tree order, and its floats. Floats, inline blocks and boxes that clip
+/// overflow are collected but not descended into, since they paint their
+/// own content.
+fn collect_flow<'a>(layout_box: &'a LayoutBox, flow: &mut Vec<&'a LayoutBox>, floats: &mut Vec<&'a LayoutBox>) {
+    for child in &layout_box.children {
+        if child.style.opacity <= 0.0 || child.position != Position::Static || child.creates_stacking_context() {
+            continue;
+        }
+        if child.float != Float::None {
+            floats.push(child);
+        } else if child.is_atomic_inline() || child.clips_overflow() {
+            flow.push(child);
+        } else {
+            flow.push(child);
+            collect_flow(child, flow, floats);
         }
     }
 }
@@ -2762,6 +3347,10 @@ impl PaintItem {
 #[derive(Debug, Default, Clone)]
 pub struct DisplayList {
     pub commands: Vec<DisplayCommand>,
+    /// Theme form controls are painted with.
+    form_theme: FormTheme,
+    /// The focused text field, drawn with a focus ring and caret.
+    caret: Option<TextCaret>,
 }
 
 impl DisplayList {
@@ -2769,13 +3358,32 @@ impl DisplayList {
     pub fn new() -> Self {
         Self {
             commands: Vec::new(),
+            form_theme: FormTheme::default(),
+            caret: None,
         }
     }
 
     /// Build display list from a layout box with proper stacking order.
     pub fn build(root: &LayoutBox) -> Self {
-        let mut list = DisplayList::new();
-        list.render_stacking_context(root, 0, &mut 0);
+        Self::build_themed(root, &FormTheme::default())
+    }
+
+    /// Build display list from a layout box, painting form controls with
+    /// `theme`.
+    pub fn build_themed(root: &LayoutBox, theme: &FormTheme) -> Self {
+        Self::build_with_caret(root, theme, None)
+    }
+
+    /// Build display list from a layout box, painting form controls with
+    /// `theme` and the text field `caret` is in as focused.
+    pub fn build_with_caret(root: &LayoutBox, theme: &FormTheme, caret: Option<TextCaret>) -> Self {
+        let mut list = DisplayList {
+            form_theme: *theme,
+            caret,
+            ..DisplayList::new()
+        };
+        list.render_stacking_context(root);
+        list.render_top_layer(root);
         list
     }
 
@@ -2795,119 +3403,173 @@ impl DisplayList {
 
         // Build the display list
         let mut list = DisplayList::new();
-        list.render_stacking_context(root, 0, &mut 0);
+        list.render_stacking_context(root);
+        list.render_top_layer(root);
         list
     }
 
-    /// Render a stacking context with proper z-ordering.
-    fn render_stacking_context(&mut self, layout_box: &LayoutBox, parent_z: i32, layer: &mut u32) {
-        let z_index = if layout_box.position != Position::Static {
-            layout_box.z_index
-        } else {
-            parent_z
-        };
+    /// Render a stacking context in CSS 2.1 Appendix E paint order.
+    fn render_stacking_context(&mut self, layout_box: &LayoutBox) {
+        // Fully transparent subtrees paint nothing
+        if layout_box.style.opacity <= 0.0 {
+            return;
+        }
 
-        // Check if this creates a new stacking context
-        let creates_context = layout_box
-            .stacking_context
-            .as_ref()
-            .map(|ctx| ctx.creates_context)
-            .unwrap_or(false);
+        // Check if this box has a transform
+        let has_transform = !layout_box.style.transform.is_identity();
+        let has_opacity = layout_box.style.opacity < 1.0;
+        let creates_context = layout_box.creates_stacking_context();
 
         if creates_context {
             self.commands.push(DisplayCommand::PushStackingContext {
-                z_index,
+                z_index: layout_box.stacking_z_index(),
                 rect: layout_box.dimensions.border_box(),
             });
         }
 
-        // Check if this box has a transform
-        let has_transform = !layout_box.style.transform.is_identity();
         if has_transform {
-            let border_box = layout_box.dimensions.border_box();
-            // Compute transform matrix
-            let matrix = layout_box.style.transform.to_matrix(border_box.width, border_box.height);
-            // Compute origin in absolute coordinates
-            let origin_x = border_box.x + layout_box.style.transform_origin.x.to_px(16.0, 16.0, border_box.width);
-            let origin_y = border_box.y + layout_box.style.transform_origin.y.to_px(16.0, 16.0, border_box.height);
-            self.commands.push(DisplayCommand::PushTransform {
-                matrix,
-                origin: (origin_x, origin_y),
+            let (matrix, origin) = layout_box.transform_parts();
+            self.commands.push(DisplayCommand::PushTransform { matrix, origin });
+        }
+
+        if let Some(id) = layout_box.layer {
+            self.commands.push(DisplayCommand::PushLayer {
+                id,
+                rect: layout_box.dimensions.border_box(),
             });
         }
 
-        // Render this box
-        self.render_box_content(layout_box);
+        if has_opacity {
+            self.commands.push(DisplayCommand::PushOpacity(layout_box.style.opacity));
+        }
 
-        // Collect children grouped by paint order
-        let mut negative_z: Vec<(&LayoutBox, u32)> = Vec::new();
-        let mut normal_flow: Vec<(&LayoutBox, u32)> = Vec::new();
-        let mut positive_z: Vec<(&LayoutBox, u32)> = Vec::new();
+        // 1. Background and borders of the root
+        self.render_box_decorations(layout_box);
 
-        for child in &layout_box.children {
-            *layer += 1;
-            let child_layer = *layer;
+        // 2-7. Everything else, clipped by the root's overflow
+        self.render_contents(layout_box);
 
-            if child.position != Position::Static {
-                if child.z_index < 0 {
-                    negative_z.push((child, child_layer));
-                } else {
-                    positive_z.push((child, child_layer));
-                }
-            } else if child.float != Float::None {
-                // Floats paint between normal flow and positioned
-                positive_z.push((child, child_layer));
-            } else {
-                normal_flow.push((child, child_layer));
-            }
+        if has_opacity {
+            self.commands.push(DisplayCommand::PopOpacity);
         }
 
-        // Sort by z-index, then by layer for stability
-        negative_z.sort_by(|a, b| {
-            let z_cmp = a.0.z_index.cmp(&b.0.z_index);
-            if z_cmp == Ordering::Equal {
-                a.1.cmp(&b.1)
-            } else {
-                z_cmp
-            }
-        });
-        positive_z.sort_by(|a, b| {
-            let z_cmp = a.0.z_index.cmp(&b.0.z_index);
-            if z_cmp == Ordering::Equal {
-                a.1.cmp(&b.1)
+        if layout_box.layer.is_some() {
+            self.commands.push(DisplayCommand::PopLayer);
+        }
+
+        // Pop transform if we pushed one
+        if has_transform {
+            self.commands.push(DisplayCommand::PopTransform);
+        }
+
+        if creates_context {
+            self.commands.push(DisplayCommand::PopStackingContext);
+        }
+    }
+
+    /// Paint the content of `layout_box` on top of its decorations, clipped
+    /// to its padding box if it clips overflow.
+    ///
+    /// A box that clips overflow paints its positioned descendants itself,
+    /// so they are clipped with the rest of its content.
+    fn render_contents(&mut self, layout_box: &LayoutBox) {
+        let clip = layout_box.clips_overflow().then(|| layout_box.overflow_clip());
+        match clip {
+            Some((rect, radius)) if radius.is_zero() => self.commands.push(DisplayCommand::PushClip(rect)),
+            Some((rect, radius)) => self.commands.push(DisplayCommand::PushRoundedClip { rect, radius }),
+            None => {}
+        }
+
+        let mut layers = StackingLayers::default();
+        layers.collect(layout_box);
+
+        // 2. Stacking contexts with negative z-index
+        for child in &layers.negative {
+            self.render_stacking_context(child);
+        }
+
+        // 3-5. In-flow blocks, floats and inline content
+        self.render_flow(layout_box);
+
+        // 6. Positioned descendants with z-index auto or 0, in tree order
+        for child in &layers.zero {
+            if child.creates_stacking_context() {
+                self.render_stacking_context(child);
             } else {
-                z_cmp
+                // Painted like a stacking context whose positioned
+                // descendants were collected above
+                self.render_atomically(child);
             }
-        });
+        }
 
-        // Render in correct order:
-        // 1. Negative z-index positioned descendants
-        for (child, _) in negative_z {
-            self.render_stacking_context(child, z_index, layer);
+        // 7. Stacking contexts with positive z-index
+        for child in &layers.positive {
+            self.render_stacking_context(child);
         }
 
-        // 2. Normal flow block children
-        for (child, _) in &normal_flow {
-            self.render_stacking_context(child, z_index, layer);
+        match clip {
+            Some((_, radius)) if radius.is_zero() => self.commands.push(DisplayCommand::PopClip),
+            Some(_) => self.commands.push(DisplayCommand::PopRoundedClip),
+            None => {}
         }
+    }
 
-        // 3. Floats and positive/zero z-index positioned descendants
-        for (child, _) in positive_z {
-            self.render_stacking_context(child, z_index, layer);
+    /// Paint a float, inline block or positioned box that doesn't create a
+    /// stacking context: its decorations, then its content.
+    fn render_atomically(&mut self, layout_box: &LayoutBox) {
+        self.render_box_decorations(layout_box);
+        if layout_box.clips_overflow() {
+            self.render_contents(layout_box);
+        } else {
+            self.render_flow(layout_box);
         }
+    }
 
-        // Pop transform if we pushed one
-        if has_transform {
-            self.commands.push(DisplayCommand::PopTransform);
+    /// Paint the in-flow content of `root` after its own decorations:
+    /// block backgrounds, then floats, then inline content and replaced
+    /// content (Appendix E steps 4-7). Floats and inline blocks are painted
+    /// atomically; positioned descendants and stacking contexts are left
+    /// to the enclosing stacking context.
+    fn render_flow(&mut self, root: &LayoutBox) {
+        let mut flow = Vec::new();
+        let mut floats = Vec::new();
+        collect_flow(root, &mut flow, &mut floats);
+
+        for layout_box in &flow {
+            if layout_box.is_block_level_for_paint() {
+                self.render_box_decorations(layout_box);
+            }
         }
 
-        if creates_context {
-            self.commands.push(DisplayCommand::PopStackingContext);
+        for float in floats {
+            self.render_atomically(float);
+        }
+
+        self.render_box_foreground(root);
+        for layout_box in flow {
+            if layout_box.is_block_level_for_paint() {
+                if layout_box.clips_overflow() {
+                    // Its content wasn't collected into this flow
+                    self.render_contents(layout_box);
+                } else {
+                    self.render_box_foreground(layout_box);
+                }
+            } else if layout_box.is_atomic_inline() {
+                self.render_atomically(layout_box);
+            } else {
+                self.render_box_content(layout_box);
+            }
         }
     }
 
     /// Render a layout box's own content (shadows, background, borders, text, images).
     fn render_box_content(&mut self, layout_box: &LayoutBox) {
+        self.render_box_decorations(layout_box);
+        self.render_box_foreground(layout_box);
+    }
+
+    /// Render a box's shadows, background, borders and column rules.
+    fn render_box_decorations(&mut self, layout_box: &LayoutBox) {
         // Box shadows (outer) are drawn first, behind the element
         self.render_box_shadows(layout_box);
         // Then background
@@ -2916,9 +3578,13 @@ impl DisplayList {
         self.render_inset_shadows(layout_box);
         // Then borders
         self.render_borders(layout_box);
-        // Then text
+        // Then rules between columns
+        self.render_column_rules(layout_box);
+    }
+
+    /// Render a box's text and replaced content.
+    fn render_box_foreground(&mut self, layout_box: &LayoutBox) {
         self.render_text(layout_box);
-        // Then images (replaced content)
         self.render_replaced_content(layout_box);
     }
 
@@ -2998,12 +3664,7 @@ impl DisplayList {
         let root_font_size = 16.0; // TODO: Pass actual root font size
 
         // Calculate border radius once (used for both solid color and gradient clipping)
-        let radius = BorderRadius {
-            top_left: s.border_top_left_radius.to_px(font_size, root_font_size, border_rect.width),
-            top_right: s.border_top_right_radius.to_px(font_size, root_font_size, border_rect.width),
-            bottom_right: s.border_bottom_right_radius.to_px(font_size, root_font_size, border_rect.width),
-            bottom_left: s.border_bottom_left_radius.to_px(font_size, root_font_size, border_rect.width),
-        };
+        let radius = layout_box.border_radius();
 
         // Calculate the clipped rect based on background-clip property
         let clip_rect = match s.background_clip {
@@ -3195,11 +3856,10 @@ impl DisplayList {
                 }
             }
             rustkit_css::BackgroundImage::Url(url) => {
-                // For URL backgrounds, emit a BackgroundImage command
-                // The actual image dimensions would come from the image cache
-                // For now, use container size as fallback
+                // The renderer tiles the image over the container once it
+                // knows the image's size from the texture cache
                 let size = self.convert_background_size(&layer.size);
-                let position = self.convert_background_position(&layer.position);
+                let (position, offset) = self.convert_background_position(&layer.position);
                 let repeat = self.convert_background_repeat(layer.repeat);
 
                 self.commands.push(DisplayCommand::BackgroundImage {
@@ -3207,6 +3867,7 @@ impl DisplayList {
                     rect: container,
                     size,
                     position,
+                    offset,
                     repeat,
                 });
             }
@@ -3301,17 +3962,16 @@ impl DisplayList {
         }
     }
 
-    /// Convert rustkit_css::BackgroundPosition to (f32, f32) tuple.
-    fn convert_background_position(&self, pos: &rustkit_css::BackgroundPosition) -> (f32, f32) {
-        let x = match &pos.x {
-            rustkit_css::BackgroundPositionValue::Percent(p) => *p,
-            rustkit_css::BackgroundPositionValue::Px(_) => 0.0, // Will be handled in rendering
+    /// Convert rustkit_css::BackgroundPosition to a fraction of the free
+    /// space and an offset in pixels.
+    fn convert_background_position(&self, pos: &rustkit_css::BackgroundPosition) -> ((f32, f32), (f32, f32)) {
+        let split = |value: &rustkit_css::BackgroundPositionValue| match value {
+            rustkit_css::BackgroundPositionValue::Percent(p) => (*p, 0.0),
+            rustkit_css::BackgroundPositionValue::Px(px) => (0.0, *px),
         };
-        let y = match &pos.y {
-            rustkit_css::BackgroundPositionValue::Percent(p) => *p,
-            rustkit_css::BackgroundPositionValue::Px(_) => 0.0,
-        };
-        (x, y)
+        let (x, offset_x) = split(&pos.x);
+        let (y, offset_y) = split(&pos.y);
+        ((x, y), (offset_x, offset_y))
     }
 
     /// Convert rustkit_css::BackgroundRepeat to layout BackgroundRepeat.
@@ -3381,14 +4041,45 @@ impl DisplayList {
         }
     }
 
+    /// Render `column-rule` lines centered in the gaps between column boxes.
+    fn render_column_rules(&mut self, layout_box: &LayoutBox) {
+        let Some(columns) = layout_box.column_layout else {
+            return;
+        };
+        let style = &layout_box.style;
+        let rule_width = style.column_rule_width.to_px(16.0, 16.0, 0.0);
+        if rule_width <= 0.0 || columns.count < 2 {
+            return;
+        }
+        let color = style.column_rule_color.unwrap_or(style.color);
+        let content = layout_box.dimensions.content;
+        for index in 1..columns.count {
+            let (previous, next) = (columns.column_rect(content, index - 1), columns.column_rect(content, index));
+            // Columns may run either way; the rule sits between the nearer edges
+            let gap_start = previous.x.min(next.x) + columns.width;
+            let gap_end = previous.x.max(next.x);
+            let center = (gap_start + gap_end) / 2.0;
+            let rect = Rect::new(center - rule_width / 2.0, content.y, rule_width, columns.height);
+            self.commands.push(DisplayCommand::SolidColor(color, rect));
+        }
+    }
+
     /// Render text with decorations.
     fn render_text(&mut self, layout_box: &LayoutBox) {
         if let BoxType::Text(ref raw_text) = layout_box.box_type {
             let style = &layout_box.style;
 
+            if let Some(highlight) = layout_box.text_highlight {
+                let rect = layout_box.text_span_rect(raw_text, highlight.start, highlight.end);
+                self.commands.push(DisplayCommand::SolidColor(highlight.color, rect));
+            }
+
             // Apply text-transform (uppercase, lowercase, capitalize)
             let text = apply_text_transform(raw_text, style.text_transform);
 
+            // Glyphs are drawn left to right, so hand them over in visual order
+            let text = apply_bidi_reordering(&text, style.direction, style.unicode_bidi);
+
             let font_size = match &style.font_size {
                 Length::Px(px) => *px,
                 _ => 16.0,
@@ -3595,7 +4286,8 @@ impl DisplayList {
                     *natural_height,
                     object_fit,
                     (pos_x, pos_y),
-                    layout_box.style.opacity,
+                    // Element opacity is applied by the enclosing opacity group
+                    1.0,
                 );
                 
                 self.commands.push(cmd);
@@ -3607,7 +4299,7 @@ impl DisplayList {
         }
     }
     
-    /// Render a form control.
+    /// Render a form control with the list's [`FormTheme`].
     fn render_form_control(&mut self, layout_box: &LayoutBox, control: &FormControlType) {
         let dims = &layout_box.dimensions;
         let rect = Rect {
@@ -3622,24 +4314,28 @@ impl DisplayList {
             _ => 16.0,
         };
         
-        let text_color = layout_box.style.color;
+        let theme = self.form_theme;
+        let text_color = theme.text_color(layout_box.style.color);
         let bg_color = layout_box.style.background_color;
         let border_color = layout_box.style.border_top_color;
         
         match control {
             FormControlType::TextInput { value, placeholder, .. } => {
+                let caret = self
+                    .caret
+                    .filter(|caret| layout_box.element_id().is_some_and(|id| id == caret.element_id));
                 self.commands.push(DisplayCommand::TextInput {
                     rect,
                     value: value.clone(),
                     placeholder: placeholder.clone(),
                     font_size,
                     text_color,
-                    placeholder_color: Color::new(160, 160, 160, 1.0),
-                    background_color: if bg_color.a > 0.0 { bg_color } else { Color::WHITE },
-                    border_color: if border_color.a > 0.0 { border_color } else { Color::new(200, 200, 200, 1.0) },
+                    placeholder_color: theme.placeholder,
+                    background_color: FormTheme::pick(bg_color, theme.field_background),
+                    border_color: FormTheme::pick(border_color, theme.field_border),
                     border_width: 1.0,
-                    focused: false, // TODO: track focus state
-                    caret_position: None,
+                    focused: caret.is_some(),
+                    caret_position: caret.and_then(|caret| caret.position),
                 });
             }
             FormControlType::TextArea { value, placeholder, .. } => {
@@ -3649,9 +4345,9 @@ impl DisplayList {
                     placeholder: placeholder.clone(),
                     font_size,
                     text_color,
-                    placeholder_color: Color::new(160, 160, 160, 1.0),
-                    background_color: if bg_color.a > 0.0 { bg_color } else { Color::WHITE },
-                    border_color: if border_color.a > 0.0 { border_color } else { Color::new(200, 200, 200, 1.0) },
+                    placeholder_color: theme.placeholder,
+                    background_color: FormTheme::pick(bg_color, theme.field_background),
+                    border_color: FormTheme::pick(border_color, theme.field_border),
                     border_width: 1.0,
                     focused: false,
                     caret_position: None,
@@ -3662,64 +4358,21 @@ impl DisplayList {
                     rect,
                     label: label.clone(),
                     font_size,
-                    text_color: if text_color.a > 0.0 { text_color } else { Color::BLACK },
-                    background_color: if bg_color.a > 0.0 { bg_color } else { Color::new(239, 239, 239, 1.0) },
-                    border_color: if border_color.a > 0.0 { border_color } else { Color::new(180, 180, 180, 1.0) },
+                    text_color,
+                    background_color: FormTheme::pick(bg_color, theme.button_background),
+                    border_color: FormTheme::pick(border_color, theme.button_border),
                     border_width: 1.0,
-                    border_radius: 4.0,
+                    border_radius: theme.corner_radius,
                     pressed: false,
                     focused: false,
                 });
             }
             FormControlType::Checkbox { checked } => {
-                // Draw checkbox as a small rect with optional checkmark
-                let check_color = if *checked { text_color } else { Color::TRANSPARENT };
-                self.commands.push(DisplayCommand::SolidColor(
-                    if bg_color.a > 0.0 { bg_color } else { Color::WHITE },
-                    rect,
-                ));
-                self.commands.push(DisplayCommand::Border {
-                    color: Color::new(150, 150, 150, 1.0),
-                    rect,
-                    top: 1.0,
-                    right: 1.0,
-                    bottom: 1.0,
-                    left: 1.0,
-                });
-                if *checked {
-                    // Draw a simple checkmark using lines (simplified)
-                    let inner = Rect {
-                        x: rect.x + 3.0,
-                        y: rect.y + 3.0,
-                        width: rect.width - 6.0,
-                        height: rect.height - 6.0,
-                    };
-                    self.commands.push(DisplayCommand::SolidColor(check_color, inner));
-                }
+                self.commands
+                    .extend(forms::render_checkbox(rect, *checked, false, InputState::Normal, &theme));
             }
             FormControlType::Radio { checked, .. } => {
-                // Draw radio as a circle (using ellipse)
-                self.commands.push(DisplayCommand::FillEllipse {
-                    rect,
-                    color: if bg_color.a > 0.0 { bg_color } else { Color::WHITE },
-                });
-                // Outer ring
-                self.commands.push(DisplayCommand::StrokeCircle {
-                    cx: rect.x + rect.width / 2.0,
-                    cy: rect.y + rect.height / 2.0,
-                    radius: rect.width / 2.0 - 1.0,
-                    color: Color::new(150, 150, 150, 1.0),
-                    width: 1.0,
-                });
-                if *checked {
-                    // Inner dot
-                    self.commands.push(DisplayCommand::FillCircle {
-                        cx: rect.x + rect.width / 2.0,
-                        cy: rect.y + rect.height / 2.0,
-                        radius: rect.width / 4.0,
-                        color: text_color,
-                    });
-                }
+                self.commands.extend(forms::render_radio(rect, *checked, InputState::Normal, &theme));
             }
             FormControlType::Select { options, selected_index } => {
                 // Draw as a text input with dropdown arrow
@@ -3734,14 +4387,90 @@ impl DisplayList {
                     placeholder: String::new(),
                     font_size,
                     text_color,
-                    placeholder_color: Color::new(160, 160, 160, 1.0),
-                    background_color: if bg_color.a > 0.0 { bg_color } else { Color::WHITE },
-                    border_color: if border_color.a > 0.0 { border_color } else { Color::new(200, 200, 200, 1.0) },
+                    placeholder_color: theme.placeholder,
+                    background_color: FormTheme::pick(bg_color, theme.field_background),
+                    border_color: FormTheme::pick(border_color, theme.field_border),
                     border_width: 1.0,
                     focused: false,
                     caret_position: None,
                 });
             }
+            FormControlType::Range { value, min, max } => {
+                let fraction = if max > min { (value - min) / (max - min) } else { 0.0 };
+                self.commands
+                    .extend(forms::render_range(rect, fraction, InputState::Normal, &theme));
+            }
+            FormControlType::ColorSwatch { color } => {
+                self.commands
+                    .extend(forms::render_color_swatch(rect, *color, InputState::Normal, &theme));
+            }
+        }
+    }
+}
+
+/// A font to measure text in, as CSS would describe it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontDescriptor {
+    /// A `font-family` value, e.g. `"Helvetica Neue", sans-serif`.
+    pub family: String,
+    /// Size in pixels.
+    pub size: f32,
+    pub weight: rustkit_css::FontWeight,
+    pub style: rustkit_css::FontStyle,
+    /// Extra space after each character, in pixels.
+    pub letter_spacing: f32,
+    /// Extra space after each space, in pixels.
+    pub word_spacing: f32,
+}
+
+impl FontDescriptor {
+    pub fn new(family: impl Into<String>, size: f32) -> Self {
+        Self {
+            family: family.into(),
+            size,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_weight(mut self, weight: rustkit_css::FontWeight) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_style(mut self, style: rustkit_css::FontStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_letter_spacing(mut self, letter_spacing: f32) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// Measure `text` in this font, the same way layout measures a box's
+    /// text.
+    pub fn measure(&self, text: &str) -> TextMetrics {
+        measure_text_with_spacing(
+            text,
+            &self.family,
+            self.size,
+            self.weight,
+            self.style,
+            self.letter_spacing,
+            self.word_spacing,
+        )
+    }
+}
+
+impl Default for FontDescriptor {
+    fn default() -> Self {
+        Self {
+            family: "sans-serif".into(),
+            size: 16.0,
+            weight: rustkit_css::FontWeight::NORMAL,
+            style: rustkit_css::FontStyle::Normal,
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
         }
     }
 }
@@ -3876,6 +4605,24 @@ mod tests {
         assert_eq!(mb.height, 82.0);
     }
 
+    #[test]
+    fn test_font_descriptor_measures_like_layout() {
+        let mut style = ComputedStyle::new();
+        style.font_family = "Helvetica, sans-serif".into();
+        style.font_size = Length::Px(13.0);
+        style.font_weight = rustkit_css::FontWeight::BOLD;
+        style.letter_spacing = Length::Px(1.0);
+        let text = LayoutBox::new(BoxType::Text("New Tab".to_string()), style);
+
+        let font = FontDescriptor::new("Helvetica, sans-serif", 13.0)
+            .with_weight(rustkit_css::FontWeight::BOLD)
+            .with_letter_spacing(1.0);
+        let metrics = font.measure("New Tab");
+        assert_eq!(metrics.width, text.measure_text_width("New Tab"));
+        assert!(metrics.width > FontDescriptor::new("Helvetica, sans-serif", 13.0).measure("New Tab").width);
+        assert!(metrics.ascent > 0.0);
+    }
+
     #[test]
     fn test_layout_box_creation() {
         let style = ComputedStyle::new();
@@ -4144,6 +4891,241 @@ mod tests {
         assert_eq!(layout_box.offsets.bottom, None);
     }
 
+    #[test]
+    fn test_intrinsic_inline_sizes() {
+        let text = LayoutBox::new(BoxType::Text("hello wide world".to_string()), ComputedStyle::new());
+        let min = text.intrinsic_inline_size(IntrinsicSizingMode::MinContent);
+        let max = text.intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+        assert!(min > 0.0 && min < max);
+
+        // Inline-level children add up on a line; a block child starts a new one
+        let mut parent = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        parent.children.push(LayoutBox::new(BoxType::Text("hello".to_string()), ComputedStyle::new()));
+        parent.children.push(LayoutBox::new(BoxType::Text("hello".to_string()), ComputedStyle::new()));
+        let mut fixed_style = ComputedStyle::new();
+        fixed_style.width = Length::Px(20.0);
+        fixed_style.padding_left = Length::Px(5.0);
+        parent.children.push(LayoutBox::new(BoxType::Block, fixed_style));
+
+        let word = parent.children[0].intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+        assert_eq!(parent.intrinsic_inline_size(IntrinsicSizingMode::MaxContent), (word * 2.0).max(25.0));
+        assert_eq!(parent.intrinsic_inline_size(IntrinsicSizingMode::MinContent), word.max(25.0));
+    }
+
+    #[test]
+    fn test_intrinsic_width_keywords() {
+        let text_box = |width: Length, display: rustkit_css::Display| {
+            let mut style = ComputedStyle::new();
+            style.width = width;
+            style.display = display;
+            style.padding_left = Length::Px(4.0);
+            let mut layout_box = LayoutBox::new(BoxType::Block, style);
+            layout_box
+                .children
+                .push(LayoutBox::new(BoxType::Text("hello wide world".to_string()), ComputedStyle::new()));
+            layout_box
+        };
+        let laid_out = |mut layout_box: LayoutBox, available: f32| {
+            let mut containing_block = Dimensions::default();
+            containing_block.content.width = available;
+            layout_box.layout(&containing_block);
+            layout_box.dimensions.content
+        };
+
+        let probe = text_box(Length::Auto, rustkit_css::Display::Block);
+        let min = probe.intrinsic_inline_size(IntrinsicSizingMode::MinContent);
+        let max = probe.intrinsic_inline_size(IntrinsicSizingMode::MaxContent);
+        let one_line = laid_out(probe, 10_000.0).height;
+
+        let block = rustkit_css::Display::Block;
+        assert_eq!(laid_out(text_box(Length::MinContent, block), 500.0).width, min);
+        assert_eq!(laid_out(text_box(Length::MaxContent, block), 10.0).width, max);
+
+        // fit-content takes max-content when it fits, and stays on one line
+        let fit = laid_out(text_box(Length::FitContent, block), 500.0);
+        assert_eq!(fit.width, max);
+        assert_eq!(fit.height, one_line);
+        // ...and clamps to the space left after padding otherwise
+        let between = (min + max) / 2.0;
+        assert_eq!(laid_out(text_box(Length::FitContent, block), between + 4.0).width, between);
+        assert_eq!(laid_out(text_box(Length::FitContent, block), 1.0).width, min);
+
+        // Auto-width inline-blocks shrink to fit
+        let inline_block = laid_out(text_box(Length::Auto, rustkit_css::Display::InlineBlock), 500.0);
+        assert_eq!(inline_block.width, max);
+        assert_eq!(inline_block.height, one_line);
+
+        // Keywords also work as min/max constraints
+        let mut capped = text_box(Length::Auto, block);
+        capped.style.max_width = Length::MinContent;
+        assert_eq!(laid_out(capped, 500.0).width, min);
+        let mut floored = text_box(Length::Px(1.0), block);
+        floored.style.min_width = Length::MaxContent;
+        assert_eq!(laid_out(floored, 500.0).width, max);
+    }
+
+    #[test]
+    fn test_list_item_markers() {
+        let list_item = |position: ListStylePosition| {
+            let mut style = ComputedStyle::new();
+            style.display = rustkit_css::Display::ListItem;
+            style.list_style_position = position;
+            let mut marker_style = style.clone();
+            marker_style.display = rustkit_css::Display::Inline;
+            let mut marker = LayoutBox::new(BoxType::Inline, marker_style.clone());
+            marker.is_marker = true;
+            marker.children.push(LayoutBox::new(BoxType::Text("1. ".to_string()), marker_style));
+            let mut item = LayoutBox::new(BoxType::Block, style);
+            item.children.push(marker);
+            item.children.push(LayoutBox::new(BoxType::Text("first".to_string()), ComputedStyle::new()));
+            let mut containing_block = Dimensions::default();
+            containing_block.content.x = 40.0;
+            containing_block.content.width = 200.0;
+            item.layout(&containing_block);
+            item
+        };
+
+        // Outside markers hang before the content without moving it
+        let outside = list_item(ListStylePosition::Outside);
+        let marker = outside.children[0].dimensions.margin_box();
+        assert_eq!(marker.right(), 40.0);
+        assert_eq!(marker.y, outside.dimensions.content.y);
+        assert_eq!(outside.children[1].dimensions.content.x, 40.0);
+        assert_eq!(
+            outside.intrinsic_inline_size(IntrinsicSizingMode::MaxContent),
+            outside.children[1].dimensions.content.width
+        );
+
+        // Inside markers start the first line
+        let inside = list_item(ListStylePosition::Inside);
+        let marker = inside.children[0].dimensions.margin_box();
+        assert_eq!(marker.x, 40.0);
+        assert_eq!(inside.children[1].dimensions.content.x, marker.right());
+    }
+
+    #[test]
+    fn test_range_client_rects() {
+        let mut text = LayoutBox::new(BoxType::Text("hello world".to_string()), ComputedStyle::new());
+        text.set_element_id(3);
+        let mut block = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        block.set_element_id(2);
+        block.children.push(text);
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        root.children.push(block);
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = 400.0;
+        root.layout(&containing_block);
+
+        // A wholly selected element stands in for its descendants
+        let rects = root.range_client_rects(&|id| (id == 2 || id == 3).then_some(RangeSelection::Whole));
+        let border_box = root.children[0].dimensions.border_box();
+        assert_eq!(rects.len(), 1);
+        assert_eq!(
+            (rects[0].x, rects[0].y, rects[0].width, rects[0].height),
+            (border_box.x, border_box.y, border_box.width, border_box.height)
+        );
+
+        // Part of a text box covers just its selected characters
+        let text = &root.children[0].children[0];
+        let rects = root.range_client_rects(&|id| (id == 3).then_some(RangeSelection::Text { start: 6, end: 11 }));
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].x, text.dimensions.content.x + text.measure_text_width("hello "));
+        assert_eq!(rects[0].width, text.measure_text_width("world"));
+        assert_eq!(rects[0].height, text.dimensions.content.height);
+
+        // Highlighting paints the same span behind the glyphs
+        let yellow = Color::new(255, 255, 0, 1.0);
+        root.highlight_range(&|id| (id == 3).then_some(RangeSelection::Text { start: 6, end: 11 }), yellow);
+        let list = DisplayList::build(&root);
+        let highlight = list
+            .commands
+            .iter()
+            .position(|command| matches!(command, DisplayCommand::SolidColor(color, rect) if *color == yellow && *rect == rects[0]));
+        let glyphs = list.commands.iter().position(|command| matches!(command, DisplayCommand::Text { .. }));
+        assert!(highlight.unwrap() < glyphs.unwrap());
+    }
+
+    #[test]
+    fn test_aspect_ratio_sizes() {
+        let ratio_box = |width: Length, height: Length| {
+            let mut style = ComputedStyle::new();
+            style.width = width;
+            style.height = height;
+            style.aspect_ratio = Some(2.0);
+            LayoutBox::new(BoxType::Block, style)
+        };
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = 400.0;
+
+        // Auto height follows the width
+        let mut layout_box = ratio_box(Length::Px(200.0), Length::Auto);
+        layout_box.layout(&containing_block);
+        assert_eq!(layout_box.dimensions.content.height, 100.0);
+
+        // Auto width follows a fixed height, clamped by max-height first
+        let mut layout_box = ratio_box(Length::Auto, Length::Px(50.0));
+        layout_box.layout(&containing_block);
+        assert_eq!(layout_box.dimensions.content.width, 100.0);
+        let mut layout_box = ratio_box(Length::Auto, Length::Px(50.0));
+        layout_box.style.max_height = Length::Px(40.0);
+        layout_box.layout(&containing_block);
+        assert_eq!(layout_box.dimensions.content.width, 80.0);
+
+        // Border-box sizing applies the ratio to the border box
+        let mut layout_box = ratio_box(Length::Px(200.0), Length::Auto);
+        layout_box.style.box_sizing = BoxSizing::BorderBox;
+        layout_box.style.padding_left = Length::Px(10.0);
+        layout_box.style.padding_right = Length::Px(10.0);
+        layout_box.style.padding_top = Length::Px(10.0);
+        layout_box.style.padding_bottom = Length::Px(10.0);
+        layout_box.layout(&containing_block);
+        assert_eq!(layout_box.dimensions.content.height, 80.0);
+
+        // Replaced elements use the specified ratio over their natural one
+        let mut style = ComputedStyle::new();
+        style.width = Length::Px(120.0);
+        style.aspect_ratio = Some(2.0);
+        let mut image = LayoutBox::new(
+            BoxType::Image { url: "a.png".to_string(), natural_width: 100.0, natural_height: 100.0 },
+            style,
+        );
+        image.layout(&containing_block);
+        assert_eq!((image.dimensions.content.width, image.dimensions.content.height), (120.0, 60.0));
+    }
+
+    #[test]
+    fn test_rtl_block_and_inline_block_placement() {
+        let mut rtl = ComputedStyle::new();
+        rtl.direction = Direction::Rtl;
+
+        let mut block_style = rtl.clone();
+        block_style.width = Length::Px(100.0);
+        block_style.height = Length::Px(10.0);
+
+        let mut inline_block_style = rtl.clone();
+        inline_block_style.display = rustkit_css::Display::InlineBlock;
+        inline_block_style.height = Length::Px(10.0);
+        let mut first_style = inline_block_style.clone();
+        first_style.width = Length::Px(50.0);
+        let mut second_style = inline_block_style;
+        second_style.width = Length::Px(60.0);
+
+        let mut parent = LayoutBox::new(BoxType::Block, rtl);
+        parent.children.push(LayoutBox::new(BoxType::Block, block_style));
+        parent.children.push(LayoutBox::new(BoxType::Block, first_style));
+        parent.children.push(LayoutBox::new(BoxType::Block, second_style));
+
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = 300.0;
+        parent.layout(&containing_block);
+
+        // A narrow block hugs the right edge
+        assert_eq!(parent.children[0].dimensions.content.x, 200.0);
+        // Inline-blocks run right to left: the first one is rightmost
+        assert_eq!(parent.children[1].dimensions.content.x, 250.0);
+        assert_eq!(parent.children[2].dimensions.content.x, 190.0);
+    }
+
     #[test]
     fn test_z_index_stacking() {
         let style = ComputedStyle::new();
@@ -4169,6 +5151,100 @@ mod tests {
         assert!(!display_list.commands.is_empty());
     }
 
+    #[test]
+    fn test_display_list_draws_caret_in_focused_field() {
+        let mut parent = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        parent.dimensions.content = Rect::new(0.0, 0.0, 200.0, 100.0);
+        for (element_id, y) in [(7, 0.0), (8, 40.0)] {
+            let control = FormControlType::TextInput {
+                value: "abc".to_string(),
+                placeholder: String::new(),
+                input_type: "text".to_string(),
+            };
+            let mut field = LayoutBox::new(BoxType::FormControl(control), ComputedStyle::new());
+            field.set_element_id(element_id);
+            field.dimensions.content = Rect::new(0.0, y, 150.0, 30.0);
+            parent.children.push(field);
+        }
+
+        let fields = |caret| {
+            DisplayList::build_with_caret(&parent, &FormTheme::default(), caret)
+                .commands
+                .into_iter()
+                .filter_map(|cmd| match cmd {
+                    DisplayCommand::TextInput { focused, caret_position, .. } => Some((focused, caret_position)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fields(None), [(false, None), (false, None)]);
+        let caret = TextCaret { element_id: 8, position: Some(2) };
+        assert_eq!(fields(Some(caret)), [(false, None), (true, Some(2))]);
+        // Blinked off, the field keeps its focus ring
+        let caret = TextCaret { element_id: 8, position: None };
+        assert_eq!(fields(Some(caret)), [(false, None), (true, None)]);
+    }
+
+    #[test]
+    fn test_transformed_box_paints_and_hits_transformed() {
+        let mut parent = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        parent.dimensions.content = Rect::new(0.0, 0.0, 200.0, 200.0);
+
+        // A 100x20 bar rotated a quarter turn about its center (50, 10)
+        let mut style = ComputedStyle::new();
+        style.transform = rustkit_css::TransformList {
+            ops: vec![rustkit_css::TransformOp::Rotate(90.0)],
+        };
+        let mut child = LayoutBox::new(BoxType::Block, style);
+        child.dimensions.content = Rect::new(0.0, 0.0, 100.0, 20.0);
+        parent.children.push(child);
+
+        let display_list = DisplayList::build(&parent);
+        let push = display_list
+            .commands
+            .iter()
+            .position(|cmd| matches!(cmd, DisplayCommand::PushTransform { .. }))
+            .expect("transform pushed");
+        assert!(matches!(display_list.commands[push - 1], DisplayCommand::PushStackingContext { .. }));
+        if let DisplayCommand::PushTransform { origin, .. } = display_list.commands[push] {
+            assert_eq!(origin, (50.0, 10.0));
+        }
+
+        // Now vertical: covers x 40..60, y -40..60
+        let hit = parent.hit_test(50.0, 50.0).unwrap();
+        assert_eq!(hit.depth, 1);
+        assert!((hit.local_x - 90.0).abs() < 1e-3 && (hit.local_y - 10.0).abs() < 1e-3);
+        // Where the bar was before rotating
+        assert_eq!(parent.hit_test(90.0, 10.0).unwrap().depth, 0);
+        assert_eq!(parent.hit_test_all(50.0, 50.0).len(), 2);
+    }
+
+    #[test]
+    fn test_opacity_groups_subtree() {
+        let red = Color::new(255, 0, 0, 1.0);
+        let mut parent = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        parent.style.opacity = 0.5;
+        parent.dimensions.content = Rect::new(0.0, 0.0, 100.0, 100.0);
+        for y in [0.0, 30.0] {
+            let mut child = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+            child.style.background_color = red;
+            child.dimensions.content = Rect::new(0.0, y, 100.0, 50.0);
+            parent.children.push(child);
+        }
+
+        // One group around both children, whose colors keep full alpha
+        let display_list = DisplayList::build(&parent);
+        let commands = &display_list.commands;
+        assert!(matches!(commands[0], DisplayCommand::PushStackingContext { .. }));
+        assert!(matches!(commands[1], DisplayCommand::PushOpacity(opacity) if opacity == 0.5));
+        assert!(matches!(commands[2], DisplayCommand::SolidColor(color, _) if color == red));
+        assert!(matches!(commands[3], DisplayCommand::SolidColor(color, _) if color == red));
+        assert!(matches!(commands[4], DisplayCommand::PopOpacity));
+
+        parent.style.opacity = 0.0;
+        assert!(DisplayList::build(&parent).commands.is_empty());
+    }
+
     #[test]
     fn test_display_list_with_positioned() {
         let style = ComputedStyle::new();
@@ -4184,6 +5260,130 @@ mod tests {
         assert!(!display_list.commands.is_empty());
     }
 
+    /// A 10x10 box filled with a color whose red channel is `id`.
+    fn painted_box(id: u8, position: Position, z_index: Option<i32>) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.background_color = Color::new(id, 0, 0, 1.0);
+        let mut layout_box = LayoutBox::with_position(BoxType::Block, style, position);
+        if let Some(z_index) = z_index {
+            layout_box.set_z_index(z_index);
+        }
+        layout_box.dimensions.content = Rect::new(0.0, 0.0, 10.0, 10.0);
+        layout_box
+    }
+
+    /// Ids of the boxes filled by a display list, in paint order.
+    fn painted_ids(display_list: &DisplayList) -> Vec<u8> {
+        display_list
+            .commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DisplayCommand::SolidColor(color, _) => Some(color.r),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stacking_context_paint_order() {
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+
+        // In-flow block with a negative z-index descendant, which paints in
+        // the root context beneath it
+        let mut a = painted_box(1, Position::Static, None);
+        a.children.push(painted_box(2, Position::Absolute, Some(-1)));
+        root.children.push(a);
+
+        // z-index 2 context: its negative descendant stays inside it
+        let mut b = painted_box(3, Position::Relative, Some(2));
+        b.children.push(painted_box(4, Position::Absolute, Some(-5)));
+        root.children.push(b);
+
+        // z-index 1 context: its z-index 10 descendant can't rise above b
+        let mut c = painted_box(5, Position::Absolute, Some(1));
+        c.children.push(painted_box(6, Position::Absolute, Some(10)));
+        root.children.push(c);
+
+        // z-index auto: its z-index 3 descendant joins the root context
+        let mut d = painted_box(7, Position::Relative, None);
+        d.children.push(painted_box(8, Position::Absolute, Some(3)));
+        root.children.push(d);
+
+        // Floats paint after in-flow blocks that follow them
+        let mut e = painted_box(9, Position::Static, None);
+        e.float = Float::Left;
+        root.children.insert(0, e);
+
+        let display_list = DisplayList::build(&root);
+        assert_eq!(painted_ids(&display_list), [2, 1, 9, 7, 5, 6, 3, 4, 8]);
+    }
+
+    #[test]
+    fn test_nested_stacking_contexts_paint_atomically() {
+        // A transformed box is a z-index 0 context: it paints with the
+        // positioned boxes in tree order, and its descendants stay inside
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        let mut transformed = painted_box(1, Position::Static, None);
+        transformed.style.transform = rustkit_css::TransformList {
+            ops: vec![rustkit_css::TransformOp::Rotate(10.0)],
+        };
+        transformed.children.push(painted_box(2, Position::Absolute, Some(-1)));
+        transformed.children.push(painted_box(3, Position::Static, None));
+        root.children.push(transformed);
+        root.children.push(painted_box(4, Position::Relative, None));
+        root.children.push(painted_box(5, Position::Static, None));
+
+        let display_list = DisplayList::build(&root);
+        assert_eq!(painted_ids(&display_list), [5, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_overflow_clips_content_to_rounded_padding_box() {
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        let mut clipper = painted_box(1, Position::Static, None);
+        clipper.style.overflow_x = Overflow::Hidden;
+        clipper.style.overflow_y = Overflow::Hidden;
+        clipper.style.border_top_left_radius = Length::Px(10.0);
+        clipper.style.border_top_right_radius = Length::Px(10.0);
+        clipper.style.border_bottom_right_radius = Length::Px(2.0);
+        clipper.dimensions.content = Rect::new(20.0, 20.0, 100.0, 50.0);
+        clipper.dimensions.padding = EdgeSizes { top: 5.0, right: 5.0, bottom: 5.0, left: 5.0 };
+        clipper.dimensions.border = EdgeSizes { top: 4.0, right: 4.0, bottom: 4.0, left: 4.0 };
+        // Positioned descendants are clipped along with the flow
+        clipper.children.push(painted_box(2, Position::Static, None));
+        clipper.children.push(painted_box(3, Position::Absolute, None));
+        root.children.push(clipper);
+        root.children.push(painted_box(4, Position::Relative, None));
+
+        let display_list = DisplayList::build(&root);
+        // Black borders of the clipper, then its content, then the sibling
+        assert_eq!(painted_ids(&display_list), [0, 0, 0, 0, 2, 3, 4]);
+
+        let commands = &display_list.commands;
+        let push = commands
+            .iter()
+            .position(|cmd| matches!(cmd, DisplayCommand::PushRoundedClip { .. }))
+            .expect("rounded clip pushed");
+        let DisplayCommand::PushRoundedClip { rect, radius } = &commands[push] else {
+            unreachable!()
+        };
+        assert_eq!(*rect, Rect::new(15.0, 15.0, 110.0, 60.0));
+        assert_eq!(
+            (radius.top_left, radius.top_right, radius.bottom_right, radius.bottom_left),
+            (6.0, 6.0, 0.0, 0.0)
+        );
+        assert!(matches!(commands[push - 5], DisplayCommand::RoundedRect { .. }));
+        assert!(matches!(commands[push + 3], DisplayCommand::PopRoundedClip));
+
+        // Square corners clip with a plain rect
+        root.children[0].style.border_top_left_radius = Length::Px(0.0);
+        root.children[0].style.border_top_right_radius = Length::Px(0.0);
+        root.children[0].style.border_bottom_right_radius = Length::Px(0.0);
+        let display_list = DisplayList::build(&root);
+        assert!(display_list.commands.iter().any(|cmd| matches!(cmd, DisplayCommand::PushClip(_))));
+        assert!(!display_list.commands.iter().any(|cmd| matches!(cmd, DisplayCommand::PushRoundedClip { .. })));
+    }
+
     #[test]
     fn test_paint_order() {
         let style = ComputedStyle::new();
@@ -0,0 +1,287 @@
+//! Line box metrics and `vertical-align` per CSS 2.1 §10.8.
+//!
+//! Inline-level boxes that share a line are positioned horizontally by the
+//! block that owns the line. This module does the vertical half: it works out
+//! each box's baseline, aligns the boxes against the parent's strut, and
+//! reports how tall the resulting line box is.
+//!
+//! Baselines come from real font metrics (ascent/descent from the text
+//! shaper), so an icon aligned to `middle` or `baseline` lines up with the
+//! glyphs next to it rather than with the top of the line.
+
+use crate::{measure_text_advanced, BoxType, Float, LayoutBox, Position};
+use rustkit_css::{ComputedStyle, Length, Overflow, VerticalAlign};
+
+/// The zero-width "strut" every line starts with: the parent's font metrics
+/// and line-height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStrut {
+    /// Font size in pixels.
+    pub font_size: f32,
+    /// Distance from the baseline to the top of the em box.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the em box.
+    pub descent: f32,
+    /// Half of the space line-height adds around the glyphs.
+    pub half_leading: f32,
+}
+
+impl LineStrut {
+    /// Build the strut for a box's own font.
+    pub fn for_style(style: &ComputedStyle) -> Self {
+        let font_size = font_size_px(style);
+        let metrics = measure_text_advanced("x", &style.font_family, font_size, style.font_weight, style.font_style);
+        Self::new(font_size, metrics.ascent, metrics.descent, style.line_height.to_px(font_size))
+    }
+
+    /// Build a strut from explicit metrics.
+    pub fn new(font_size: f32, ascent: f32, descent: f32, line_height: f32) -> Self {
+        Self {
+            font_size,
+            ascent,
+            descent,
+            half_leading: (line_height - ascent - descent) / 2.0,
+        }
+    }
+
+    /// Space the strut needs above the baseline.
+    pub fn above(&self) -> f32 {
+        self.half_leading + self.ascent
+    }
+
+    /// Space the strut needs below the baseline.
+    pub fn below(&self) -> f32 {
+        self.half_leading + self.descent
+    }
+
+    /// Approximate x-height, used by `vertical-align: middle`.
+    pub fn x_height(&self) -> f32 {
+        self.font_size * 0.5
+    }
+}
+
+/// Distance from the top of a box's margin box to its baseline.
+///
+/// - Text uses its font's ascent plus half-leading; inline boxes use the
+///   baseline of the line formed by their children.
+/// - Inline-blocks use the baseline of their last line, or their bottom
+///   margin edge when they have no lines or clip their overflow.
+/// - Replaced elements (images) sit on their bottom margin edge.
+pub fn baseline_offset(layout_box: &LayoutBox) -> f32 {
+    let d = &layout_box.dimensions;
+    let margin_box = d.margin_box();
+    match &layout_box.box_type {
+        BoxType::Text(text) => {
+            let strut = text_strut(text, &layout_box.style, d.content.height);
+            d.content.y - margin_box.y + strut.above()
+        }
+        BoxType::Inline => {
+            // An inline box's children sit on its own line, whose baseline is its baseline
+            let strut = LineStrut::for_style(&layout_box.style);
+            d.content.y - margin_box.y + measure_line(&layout_box.children, &strut).above
+        }
+        BoxType::Image { .. } => margin_box.height,
+        BoxType::FormControl(_) => {
+            // Controls put their label text on the baseline, centered in the box
+            let strut = LineStrut::for_style(&layout_box.style);
+            let text_height = strut.ascent + strut.descent;
+            d.content.y - margin_box.y + (d.content.height - text_height) / 2.0 + strut.ascent
+        }
+        BoxType::Block | BoxType::AnonymousBlock => {
+            let clips = layout_box.style.overflow_x != Overflow::Visible
+                || layout_box.style.overflow_y != Overflow::Visible;
+            match last_line_baseline(layout_box) {
+                Some(y) if !clips => y - margin_box.y,
+                _ => margin_box.height,
+            }
+        }
+    }
+}
+
+/// Absolute y of the baseline of the last line inside a box, if it has one.
+fn last_line_baseline(layout_box: &LayoutBox) -> Option<f32> {
+    layout_box.children.iter().rev().filter(|c| in_flow(c)).find_map(|child| match &child.box_type {
+        BoxType::Text(_) | BoxType::Inline | BoxType::Image { .. } | BoxType::FormControl(_) => {
+            Some(child.dimensions.margin_box().y + baseline_offset(child))
+        }
+        BoxType::Block | BoxType::AnonymousBlock => last_line_baseline(child),
+    })
+}
+
+/// Vertically align the inline-level boxes of one line.
+///
+/// `line_top` is the absolute y of the top of the line box. Each box is moved
+/// (with its descendants) to its aligned position, and the height of the line
+/// box is returned. Floats and absolutely positioned boxes are left alone.
+pub fn align_line(items: &mut [LayoutBox], strut: &LineStrut, line_top: f32) -> f32 {
+    let metrics = measure_line(items, strut);
+    let baseline = line_top + metrics.above;
+
+    for (item, offset) in items.iter_mut().zip(metrics.offsets) {
+        if !in_flow(item) {
+            continue;
+        }
+        let margin_box = item.dimensions.margin_box();
+        let target = match (item.style.vertical_align, offset) {
+            (_, Some(top)) => baseline + top,
+            (VerticalAlign::Bottom, None) => line_top + metrics.height - margin_box.height,
+            _ => line_top,
+        };
+        item.translate(0.0, target - margin_box.y);
+    }
+
+    metrics.height
+}
+
+/// Where the boxes of a line go, relative to its baseline.
+struct LineMetrics {
+    /// Top of each box relative to the baseline; `None` for boxes aligned to
+    /// the line's top or bottom and for boxes that aren't on the line.
+    offsets: Vec<Option<f32>>,
+    /// Distance from the top of the line box to the baseline.
+    above: f32,
+    /// Height of the line box.
+    height: f32,
+}
+
+fn measure_line(items: &[LayoutBox], strut: &LineStrut) -> LineMetrics {
+    // Extents relative to the baseline, with y growing downwards
+    let mut above = strut.above();
+    let mut below = strut.below();
+
+    let mut offsets = Vec::with_capacity(items.len());
+    for item in items {
+        if !in_flow(item) {
+            offsets.push(None);
+            continue;
+        }
+        let height = item.dimensions.margin_box().height;
+        let top = match item.style.vertical_align {
+            VerticalAlign::Top | VerticalAlign::Bottom => {
+                offsets.push(None);
+                continue;
+            }
+            VerticalAlign::Middle => -(strut.x_height() / 2.0) - height / 2.0,
+            VerticalAlign::TextTop => -strut.ascent,
+            VerticalAlign::TextBottom => strut.descent - height,
+            VerticalAlign::Baseline => -baseline_offset(item),
+            VerticalAlign::Sub => -baseline_offset(item) + strut.font_size / 5.0,
+            VerticalAlign::Super => -baseline_offset(item) - strut.font_size / 3.0,
+            VerticalAlign::Length(px) => -baseline_offset(item) - px,
+        };
+        above = above.max(-top);
+        below = below.max(top + height);
+        offsets.push(Some(top));
+    }
+
+    // Boxes aligned to the line's top or bottom can only grow it further
+    let mut height = above + below;
+    for item in items.iter().filter(|i| in_flow(i)) {
+        let item_height = item.dimensions.margin_box().height;
+        match item.style.vertical_align {
+            VerticalAlign::Top if item_height > height => height = item_height,
+            VerticalAlign::Bottom if item_height > height => {
+                above += item_height - height;
+                height = item_height;
+            }
+            _ => {}
+        }
+    }
+
+    LineMetrics { offsets, above, height }
+}
+
+/// Whether a box takes part in line layout.
+fn in_flow(layout_box: &LayoutBox) -> bool {
+    layout_box.float == Float::None && !matches!(layout_box.position, Position::Absolute | Position::Fixed)
+}
+
+/// Strut for a text box, using the metrics of the text itself.
+fn text_strut(text: &str, style: &ComputedStyle, line_height: f32) -> LineStrut {
+    let font_size = font_size_px(style);
+    let metrics = measure_text_advanced(text, &style.font_family, font_size, style.font_weight, style.font_style);
+    LineStrut::new(font_size, metrics.ascent, metrics.descent, line_height)
+}
+
+fn font_size_px(style: &ComputedStyle) -> f32 {
+    match style.font_size {
+        Length::Px(px) => px,
+        _ => 16.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rect;
+
+    fn replaced(height: f32, vertical_align: VerticalAlign) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.vertical_align = vertical_align;
+        let mut layout_box = LayoutBox::new(
+            BoxType::Image { url: String::new(), natural_width: height, natural_height: height },
+            style,
+        );
+        layout_box.dimensions.content = Rect::new(0.0, 0.0, height, height);
+        layout_box
+    }
+
+    #[test]
+    fn test_strut_extents() {
+        let strut = LineStrut::new(16.0, 12.0, 4.0, 20.0);
+        assert_eq!(strut.half_leading, 2.0);
+        assert_eq!(strut.above(), 14.0);
+        assert_eq!(strut.below(), 6.0);
+    }
+
+    #[test]
+    fn test_baseline_aligned_image_sits_on_baseline() {
+        let strut = LineStrut::new(16.0, 12.0, 4.0, 20.0);
+        let mut items = vec![replaced(30.0, VerticalAlign::Baseline)];
+        let height = align_line(&mut items, &strut, 100.0);
+
+        // The image rises 30px above the baseline; the strut still hangs below it
+        assert_eq!(height, 36.0);
+        assert_eq!(items[0].dimensions.content.y, 100.0);
+    }
+
+    #[test]
+    fn test_middle_centers_on_x_height() {
+        let strut = LineStrut::new(16.0, 12.0, 4.0, 20.0);
+        let mut items = vec![replaced(10.0, VerticalAlign::Middle)];
+        let height = align_line(&mut items, &strut, 0.0);
+
+        // Baseline at 14; the middle of the image sits 4px (half the x-height) above it
+        assert_eq!(height, 20.0);
+        assert_eq!(items[0].dimensions.content.y, 14.0 - 4.0 - 5.0);
+    }
+
+    #[test]
+    fn test_top_bottom_and_length() {
+        let strut = LineStrut::new(16.0, 12.0, 4.0, 20.0);
+        let mut items = vec![
+            replaced(10.0, VerticalAlign::Top),
+            replaced(10.0, VerticalAlign::Bottom),
+            replaced(10.0, VerticalAlign::Length(2.0)),
+        ];
+        let height = align_line(&mut items, &strut, 0.0);
+
+        assert_eq!(height, 20.0);
+        assert_eq!(items[0].dimensions.content.y, 0.0);
+        assert_eq!(items[1].dimensions.content.y, 10.0);
+        // Raised 2px: bottom edge 2px above the baseline at 14
+        assert_eq!(items[2].dimensions.content.y, 14.0 - 2.0 - 10.0);
+    }
+
+    #[test]
+    fn test_tall_bottom_box_grows_line_upwards() {
+        let strut = LineStrut::new(16.0, 12.0, 4.0, 20.0);
+        let mut items = vec![replaced(10.0, VerticalAlign::Baseline), replaced(40.0, VerticalAlign::Bottom)];
+        let height = align_line(&mut items, &strut, 0.0);
+
+        assert_eq!(height, 40.0);
+        assert_eq!(items[1].dimensions.content.y, 0.0);
+        // The baseline moved down by the extra 20px
+        assert_eq!(items[0].dimensions.content.y, 34.0 - 10.0);
+    }
+}
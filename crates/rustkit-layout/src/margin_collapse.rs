@@ -155,6 +155,11 @@ pub fn establishes_bfc(style: &ComputedStyle, float: Float) -> bool {
         return true;
     }
 
+    // So do multi-column containers
+    if crate::multicol::is_multicol_container(style) {
+        return true;
+    }
+
     false
 }
 
@@ -0,0 +1,385 @@
+//! Multi-column layout for RustKit.
+//!
+//! Implements the CSS Multi-column Layout Module Level 1:
+//! https://www.w3.org/TR/css-multicol-1/
+//!
+//! A multi-column container first lays its content out as a single column at
+//! the used column width, then fragments that column into column boxes placed
+//! side by side:
+//! 1. Resolve the used column count and width (§3.4)
+//! 2. Lay out the content in one column of that width
+//! 3. Cut the flow into fragments: lines and unbreakable boxes
+//! 4. Pick the column height (balanced, or the container's own height)
+//! 5. Move each fragment into its column box
+//!
+//! Breaks happen between lines and between block boxes. Boxes with
+//! `break-inside: avoid` and monolithic content (replaced elements, flex and
+//! grid containers, scroll containers) always move as a whole. A block that is
+//! broken keeps its box in its first column, cut off at the column's end.
+
+use crate::{is_scroll_container, BoxType, Float, FloatContext, LayoutBox, MarginCollapseContext, Position, Rect};
+use rustkit_css::{ColumnFill, ComputedStyle, Length};
+
+/// Slack when comparing fragment edges, to absorb float rounding.
+const EPSILON: f32 = 0.01;
+
+/// Used geometry of a multi-column container's column boxes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnLayout {
+    /// Number of column boxes that hold content, including overflow columns.
+    pub count: usize,
+    /// Width of each column box.
+    pub width: f32,
+    /// Gap between adjacent column boxes.
+    pub gap: f32,
+    /// Height of the column boxes.
+    pub height: f32,
+    /// Whether columns run right to left.
+    pub rtl: bool,
+}
+
+impl ColumnLayout {
+    /// Rect of column box `index` within the container's content box.
+    pub fn column_rect(&self, content: Rect, index: usize) -> Rect {
+        let offset = index as f32 * (self.width + self.gap);
+        let x = if self.rtl {
+            content.right() - self.width - offset
+        } else {
+            content.x + offset
+        };
+        Rect::new(x, content.y, self.width, self.height)
+    }
+}
+
+/// Whether a box with this style is a multi-column container.
+pub fn is_multicol_container(style: &ComputedStyle) -> bool {
+    (style.column_count.is_some() || style.column_width.is_some())
+        && !style.display.is_flex()
+        && !style.display.is_grid()
+}
+
+/// Resolve the used column count and width from `column-count` and
+/// `column-width` (§3.4). Returns None when both are `auto`.
+pub fn resolve_columns(count: Option<u32>, width: Option<f32>, available: f32, gap: f32) -> Option<(usize, f32)> {
+    let available = available.max(0.0);
+    // As many columns of at least the specified width as fit, and at least one
+    let fitting = width.map(|width| (((available + gap) / (width.max(1.0) + gap)).floor() as usize).max(1));
+    let count = match (count, fitting) {
+        (None, None) => return None,
+        (Some(count), None) => count as usize,
+        (None, Some(fitting)) => fitting,
+        (Some(count), Some(fitting)) => (count as usize).min(fitting),
+    }
+    .max(1);
+    let width = ((available + gap) / count as f32 - gap).max(0.0);
+    Some((count, width))
+}
+
+/// Lay out a multi-column container's children into column boxes.
+///
+/// Expects the container's width and position to be resolved; sets its
+/// content height from the columns when the height is auto.
+pub fn layout_multicol_container(container: &mut LayoutBox) {
+    let style = &container.style;
+    let available = container.dimensions.content.width;
+    let font_size = match style.font_size {
+        Length::Px(px) => px,
+        _ => 16.0,
+    };
+    // `normal` is 1em between columns
+    let gap = match style.column_gap {
+        Length::Auto => font_size,
+        ref gap => gap.to_px(font_size, 16.0, available),
+    }
+    .max(0.0);
+    let column_width = style.column_width.as_ref().map(|width| width.to_px(font_size, 16.0, available));
+    let Some((count, width)) = resolve_columns(style.column_count, column_width, available, gap) else {
+        container.layout_block_children();
+        return;
+    };
+    let fill = style.column_fill;
+    let rtl = style.direction.is_rtl();
+    let fixed_height = container.fixed_content_height();
+
+    // Lay the content out as one column; columns are their own formatting context
+    container.dimensions.content.width = width;
+    container.layout_block_children_with_collapse(&mut MarginCollapseContext::new(), &mut FloatContext::new());
+    container.dimensions.content.width = available;
+
+    let origin_y = container.dimensions.content.y;
+    let mut fragments = Vec::new();
+    let mut broken = Vec::new();
+    collect_fragments(container, &mut Vec::new(), origin_y, &mut fragments, &mut broken);
+
+    let column_height = match fixed_height {
+        Some(height) if fill == ColumnFill::Auto => height,
+        Some(height) => balanced_height(&fragments, count).min(height),
+        None => balanced_height(&fragments, count),
+    };
+    let placement = place_fragments(&fragments, column_height);
+
+    let column_dx = |column: usize| {
+        let offset = column as f32 * (width + gap);
+        if rtl {
+            available - width - offset
+        } else {
+            offset
+        }
+    };
+    for (fragment, &(column, top)) in fragments.iter().zip(&placement) {
+        for path in &fragment.boxes {
+            box_at_mut(container, path).translate(column_dx(column), -top);
+        }
+    }
+
+    // A broken block moves with its first fragment and ends with that column
+    for path in &broken {
+        let mut spanned = fragments
+            .iter()
+            .zip(&placement)
+            .filter(|(fragment, _)| fragment.boxes.iter().any(|b| b.starts_with(path)))
+            .map(|(_, &placed)| placed);
+        let Some((first_column, top)) = spanned.next() else {
+            continue;
+        };
+        let crosses_columns = spanned.any(|(column, _)| column != first_column);
+        let node = box_at_mut(container, path);
+        node.dimensions.content.x += column_dx(first_column);
+        node.dimensions.content.y -= top;
+        if crosses_columns {
+            let d = &node.dimensions;
+            let column_bottom = origin_y + column_height;
+            node.dimensions.content.height =
+                (column_bottom - d.content.y - d.padding.bottom - d.border.bottom).max(0.0);
+        }
+    }
+
+    let used_height = fragments
+        .iter()
+        .zip(&placement)
+        .map(|(fragment, &(_, top))| fragment.bottom - top)
+        .fold(0.0, f32::max);
+    container.dimensions.content.height = used_height;
+    container.column_layout = Some(ColumnLayout {
+        count: placement.last().map_or(0, |&(column, _)| column + 1),
+        width,
+        gap,
+        height: fixed_height.unwrap_or(used_height),
+        rtl,
+    });
+}
+
+/// A piece of the single-column flow that goes into one column as a whole.
+struct Fragment {
+    /// Paths (child indices from the container) of the boxes in the piece.
+    boxes: Vec<Vec<usize>>,
+    /// Top edge in the single-column flow, relative to the container's content box.
+    top: f32,
+    /// Bottom edge in the single-column flow, relative to the container's content box.
+    bottom: f32,
+    /// Whether the piece is a line of inline-level boxes.
+    line: bool,
+}
+
+/// Whether the flow may break between a box's children.
+fn is_breakable(layout_box: &LayoutBox) -> bool {
+    matches!(layout_box.box_type, BoxType::Block | BoxType::AnonymousBlock)
+        && !layout_box.children.is_empty()
+        && !layout_box.is_inline_level()
+        && layout_box.float == Float::None
+        && !layout_box.style.break_inside.avoids_column_break()
+        && !layout_box.style.display.is_flex()
+        && !layout_box.style.display.is_grid()
+        && !is_multicol_container(&layout_box.style)
+        && !is_scroll_container(layout_box.style.overflow_x, layout_box.style.overflow_y)
+}
+
+/// Cut `parent`'s in-flow content into fragments, in flow order, descending
+/// into breakable blocks. Paths of the blocks descended into go to `broken`.
+fn collect_fragments(
+    parent: &LayoutBox,
+    path: &mut Vec<usize>,
+    origin_y: f32,
+    fragments: &mut Vec<Fragment>,
+    broken: &mut Vec<Vec<usize>>,
+) {
+    for (index, child) in parent.children.iter().enumerate() {
+        if matches!(child.position, Position::Absolute | Position::Fixed) {
+            continue;
+        }
+        path.push(index);
+
+        if is_breakable(child) {
+            broken.push(path.clone());
+            collect_fragments(child, path, origin_y, fragments, broken);
+        } else {
+            let margin_box = child.dimensions.margin_box();
+            let (top, bottom) = (margin_box.y - origin_y, margin_box.bottom() - origin_y);
+            let inline = child.is_inline_level();
+
+            // Inline-level siblings that overlap vertically share a line
+            let same_line = fragments.last_mut().filter(|last| {
+                inline
+                    && last.line
+                    && top < last.bottom - EPSILON
+                    && last.boxes.last().is_some_and(|b| b[..b.len() - 1] == path[..path.len() - 1])
+            });
+            match same_line {
+                Some(line) => {
+                    line.boxes.push(path.clone());
+                    line.top = line.top.min(top);
+                    line.bottom = line.bottom.max(bottom);
+                }
+                None => fragments.push(Fragment { boxes: vec![path.clone()], top, bottom, line: inline }),
+            }
+        }
+
+        path.pop();
+    }
+}
+
+/// Column index and the flow offset where that column starts, for each
+/// fragment, when columns are `height` tall. A fragment that doesn't fit in
+/// the rest of a column starts the next one, unless it is the column's first.
+fn place_fragments(fragments: &[Fragment], height: f32) -> Vec<(usize, f32)> {
+    let mut column = 0;
+    let mut column_top = 0.0;
+    let mut column_empty = true;
+    fragments
+        .iter()
+        .map(|fragment| {
+            if !column_empty && fragment.bottom - column_top > height + EPSILON {
+                column += 1;
+                column_top = fragment.top;
+            }
+            column_empty = false;
+            (column, column_top)
+        })
+        .collect()
+}
+
+/// Smallest column height that fits all fragments in `count` columns.
+fn balanced_height(fragments: &[Fragment], count: usize) -> f32 {
+    let total = fragments.iter().map(|f| f.bottom).fold(0.0, f32::max);
+    let tallest = fragments.iter().map(|f| f.bottom - f.top).fold(0.0, f32::max);
+    let fits = |height: f32| {
+        place_fragments(fragments, height)
+            .last()
+            .is_none_or(|&(column, _)| column < count)
+    };
+
+    let mut low = (total / count as f32).max(tallest);
+    if fits(low) {
+        return low;
+    }
+    let mut high = total;
+    while high - low > 0.5 {
+        let mid = (low + high) / 2.0;
+        if fits(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    high
+}
+
+/// The descendant of `root` at `path`.
+fn box_at_mut<'a>(root: &'a mut LayoutBox, path: &[usize]) -> &'a mut LayoutBox {
+    path.iter().fold(root, |node, &index| &mut node.children[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dimensions;
+
+    fn block(height: f32) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.height = Length::Px(height);
+        LayoutBox::new(BoxType::Block, style)
+    }
+
+    fn multicol(count: u32, children: Vec<LayoutBox>) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.column_count = Some(count);
+        style.column_gap = Length::Px(20.0);
+        let mut container = LayoutBox::new(BoxType::Block, style);
+        container.children = children;
+        container
+    }
+
+    fn lay_out(container: &mut LayoutBox, width: f32) {
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = width;
+        container.layout(&containing_block);
+    }
+
+    #[test]
+    fn test_resolve_columns() {
+        // Count only: the width is shared out
+        assert_eq!(resolve_columns(Some(3), None, 320.0, 10.0), Some((3, 100.0)));
+        // Width only: as many as fit, then widened to fill
+        assert_eq!(resolve_columns(None, Some(100.0), 350.0, 10.0), Some((3, 110.0)));
+        assert_eq!(resolve_columns(None, Some(500.0), 330.0, 10.0), Some((1, 330.0)));
+        // Both: the count is a maximum
+        assert_eq!(resolve_columns(Some(2), Some(100.0), 330.0, 10.0), Some((2, 160.0)));
+        assert_eq!(resolve_columns(None, None, 330.0, 10.0), None);
+    }
+
+    #[test]
+    fn test_columns_balance_blocks() {
+        let mut container = multicol(2, (0..4).map(|_| block(50.0)).collect());
+        lay_out(&mut container, 420.0);
+
+        let columns = container.column_layout.unwrap();
+        assert_eq!((columns.count, columns.width, columns.height), (2, 200.0, 100.0));
+        assert_eq!(container.dimensions.content.height, 100.0);
+        let positions: Vec<(f32, f32)> =
+            container.children.iter().map(|c| (c.dimensions.content.x, c.dimensions.content.y)).collect();
+        assert_eq!(positions, [(0.0, 0.0), (0.0, 50.0), (220.0, 0.0), (220.0, 50.0)]);
+        assert!(container.children.iter().all(|c| c.dimensions.content.width == 200.0));
+    }
+
+    #[test]
+    fn test_break_inside_avoid_keeps_block_whole() {
+        let make = |avoid: bool| {
+            let mut wrapper = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+            wrapper.children = vec![block(40.0), block(40.0)];
+            if avoid {
+                wrapper.style.break_inside = rustkit_css::BreakInside::Avoid;
+            }
+            multicol(2, vec![block(20.0), wrapper])
+        };
+
+        // The wrapper may break between its children...
+        let mut container = make(false);
+        lay_out(&mut container, 420.0);
+        let wrapper = &container.children[1];
+        assert_eq!(wrapper.children[0].dimensions.content.x, 0.0);
+        assert_eq!(wrapper.children[1].dimensions.content.x, 220.0);
+        assert_eq!(container.dimensions.content.height, 60.0);
+
+        // ...unless it avoids breaks, when it moves to the next column whole
+        let mut container = make(true);
+        lay_out(&mut container, 420.0);
+        let wrapper = &container.children[1];
+        assert_eq!(wrapper.dimensions.content.x, 220.0);
+        assert_eq!(wrapper.children[0].dimensions.content.y, 0.0);
+        assert_eq!(wrapper.children[1].dimensions.content.y, 40.0);
+        assert_eq!(container.dimensions.content.height, 80.0);
+    }
+
+    #[test]
+    fn test_fixed_height_overflows_into_extra_columns() {
+        let mut container = multicol(2, (0..3).map(|_| block(50.0)).collect());
+        container.style.height = Length::Px(50.0);
+        container.style.column_fill = ColumnFill::Auto;
+        lay_out(&mut container, 420.0);
+
+        let columns = container.column_layout.unwrap();
+        assert_eq!((columns.count, columns.height), (3, 50.0));
+        assert_eq!(container.children[2].dimensions.content.x, 440.0);
+        assert_eq!(container.dimensions.content.height, 50.0);
+    }
+}
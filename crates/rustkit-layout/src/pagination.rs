@@ -0,0 +1,213 @@
+//! Pagination for printing.
+//!
+//! A document is printed by laying it out as one long page at the width of
+//! the paper's content area, then cutting that flow into pages:
+//! 1. Cut the flow into pieces: lines and unbreakable boxes
+//! 2. Note where `break-before` and `break-after` force or avoid a break
+//! 3. Fill each page with the pieces that fit, breaking before the first
+//!    that doesn't, or at an earlier piece if a break there is avoided
+//!
+//! As in multi-column layout, breaks never happen inside a line, a box
+//! with `break-inside: avoid` or monolithic content. Pieces taller than a
+//! page are sliced where the page ends.
+
+use crate::{is_multicol_container, is_scroll_container, BoxType, Float, LayoutBox, Position};
+use rustkit_css::BreakBetween;
+
+/// Slack when comparing piece edges, to absorb float rounding.
+const EPSILON: f32 = 0.01;
+
+/// What may happen at a piece's top edge, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Break {
+    Auto,
+    Avoid,
+    Force,
+}
+
+impl From<BreakBetween> for Break {
+    fn from(value: BreakBetween) -> Self {
+        if value.forces_page_break() {
+            Break::Force
+        } else if value.avoids_page_break() {
+            Break::Avoid
+        } else {
+            Break::Auto
+        }
+    }
+}
+
+/// A piece of the flow that goes onto one page as a whole.
+struct Piece {
+    top: f32,
+    bottom: f32,
+    /// Whether the piece is a line of inline-level boxes.
+    line: bool,
+    /// What may happen between the piece and the one before it.
+    before: Break,
+}
+
+/// Where each page starts in a laid-out tree, for pages `page_height`
+/// tall. The first page starts at the top of the flow; each page ends
+/// where the next starts, at most `page_height` below its own start.
+pub fn page_breaks(root: &LayoutBox, page_height: f32) -> Vec<f32> {
+    let page_height = page_height.max(1.0);
+    let mut pieces = Vec::new();
+    collect_pieces(root, &mut pieces, &mut Break::Auto);
+
+    let flow = root.dimensions.margin_box();
+    let mut starts = vec![flow.y];
+    // Start a page at `y`, with blank pages for any gap too tall for one
+    let start_page = |starts: &mut Vec<f32>, y: f32| {
+        while y - starts[starts.len() - 1] > page_height + EPSILON {
+            let next = starts[starts.len() - 1] + page_height;
+            starts.push(next);
+        }
+        starts.push(y);
+    };
+
+    // The latest piece on the current page the page may end before
+    let mut last_break: Option<f32> = None;
+    for piece in &pieces {
+        let page_top = starts[starts.len() - 1];
+        let on_page = piece.top > page_top + EPSILON;
+        if piece.before == Break::Force && on_page {
+            start_page(&mut starts, piece.top);
+            last_break = None;
+        } else if piece.bottom > page_top + page_height + EPSILON {
+            let here = (piece.before == Break::Auto && on_page).then_some(piece.top);
+            if let Some(y) = here.or(last_break) {
+                start_page(&mut starts, y);
+            }
+            last_break = None;
+            // What still doesn't fit is sliced
+            while piece.bottom > starts[starts.len() - 1] + page_height + EPSILON {
+                let next = starts[starts.len() - 1] + page_height;
+                starts.push(next);
+            }
+        } else if piece.before == Break::Auto && on_page {
+            last_break = Some(piece.top);
+        }
+    }
+
+    let end = pieces.iter().map(|piece| piece.bottom).fold(flow.bottom(), f32::max);
+    while end > starts[starts.len() - 1] + page_height + EPSILON {
+        let next = starts[starts.len() - 1] + page_height;
+        starts.push(next);
+    }
+    starts
+}
+
+/// Whether pages may break between a box's children.
+fn is_breakable(layout_box: &LayoutBox) -> bool {
+    matches!(layout_box.box_type, BoxType::Block | BoxType::AnonymousBlock)
+        && !layout_box.children.is_empty()
+        && !layout_box.is_inline_level()
+        && layout_box.float == Float::None
+        && !layout_box.style.break_inside.avoids_page_break()
+        && !layout_box.style.display.is_flex()
+        && !layout_box.style.display.is_grid()
+        && !is_multicol_container(&layout_box.style)
+        && !is_scroll_container(layout_box.style.overflow_x, layout_box.style.overflow_y)
+}
+
+/// Cut `parent`'s in-flow content into pieces, in flow order, descending
+/// into breakable blocks. `pending` is what the next piece's top allows,
+/// from the `break-before` and `break-after` of the boxes around it.
+fn collect_pieces(parent: &LayoutBox, pieces: &mut Vec<Piece>, pending: &mut Break) {
+    for child in &parent.children {
+        if matches!(child.position, Position::Absolute | Position::Fixed) {
+            continue;
+        }
+        *pending = (*pending).max(child.style.break_before.into());
+
+        if is_breakable(child) {
+            collect_pieces(child, pieces, pending);
+        } else {
+            let margin_box = child.dimensions.margin_box();
+            let (top, bottom) = (margin_box.y, margin_box.bottom());
+            let inline = child.is_inline_level();
+
+            // Inline-level siblings that overlap vertically share a line
+            let same_line = pieces
+                .last_mut()
+                .filter(|last| inline && last.line && top < last.bottom - EPSILON);
+            match same_line {
+                Some(line) => {
+                    line.top = line.top.min(top);
+                    line.bottom = line.bottom.max(bottom);
+                }
+                None => {
+                    pieces.push(Piece { top, bottom, line: inline, before: *pending });
+                    *pending = Break::Auto;
+                }
+            }
+        }
+
+        *pending = (*pending).max(child.style.break_after.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dimensions;
+    use rustkit_css::{BreakInside, ComputedStyle, Length};
+
+    fn block(height: f32) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.height = Length::Px(height);
+        LayoutBox::new(BoxType::Block, style)
+    }
+
+    fn document(children: Vec<LayoutBox>) -> LayoutBox {
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        root.children = children;
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = 400.0;
+        root.layout(&containing_block);
+        root
+    }
+
+    #[test]
+    fn test_blocks_fill_pages() {
+        let root = document((0..5).map(|_| block(50.0)).collect());
+        assert_eq!(page_breaks(&root, 120.0), [0.0, 100.0, 200.0]);
+    }
+
+    #[test]
+    fn test_forced_and_avoided_breaks() {
+        let mut second = block(50.0);
+        second.style.break_before = BreakBetween::Page;
+        let root = document(vec![block(20.0), second, block(20.0)]);
+        assert_eq!(page_breaks(&root, 500.0), [0.0, 20.0]);
+
+        // Avoiding the break before the third block moves the second along
+        let mut third = block(50.0);
+        third.style.break_before = BreakBetween::Avoid;
+        let root = document(vec![block(50.0), block(50.0), third]);
+        assert_eq!(page_breaks(&root, 120.0), [0.0, 50.0]);
+    }
+
+    #[test]
+    fn test_break_inside_avoid_keeps_block_on_one_page() {
+        let make = |avoid: bool| {
+            let mut wrapper = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+            wrapper.children = vec![block(40.0), block(40.0)];
+            if avoid {
+                wrapper.style.break_inside = BreakInside::AvoidPage;
+            }
+            document(vec![block(50.0), wrapper])
+        };
+        assert_eq!(page_breaks(&make(false), 100.0), [0.0, 90.0]);
+        assert_eq!(page_breaks(&make(true), 100.0), [0.0, 50.0]);
+    }
+
+    #[test]
+    fn test_tall_content_is_sliced() {
+        let mut tall = block(250.0);
+        tall.style.break_inside = BreakInside::Avoid;
+        let root = document(vec![block(30.0), tall]);
+        assert_eq!(page_breaks(&root, 100.0), [0.0, 30.0, 130.0, 230.0]);
+    }
+}
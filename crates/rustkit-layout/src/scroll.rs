@@ -9,7 +9,7 @@
 //! - Position: sticky handling
 
 use crate::{DisplayCommand, Rect};
-use rustkit_css::{Color, Overflow, ScrollbarGutter, ScrollbarWidth};
+use rustkit_css::{Color, Direction, Overflow, ScrollbarGutter, ScrollbarWidth};
 use std::time::{Duration, Instant};
 
 /// Scroll state for a scroll container.
@@ -58,6 +58,10 @@ pub struct ScrollState {
 
     /// Momentum scrolling state.
     pub momentum: Option<ScrollMomentum>,
+
+    /// Inline direction of the container. RTL containers start scrolled to the
+    /// right edge and put the vertical scrollbar on the left.
+    pub direction: Direction,
 }
 
 impl ScrollState {
@@ -80,33 +84,70 @@ impl ScrollState {
             animation_start: None,
             animation_duration: Duration::from_millis(300),
             momentum: None,
+            direction: Direction::Ltr,
         }
     }
 
+    /// Set the inline direction, moving to the scroll origin for RTL.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+        self.scroll_x = if direction.is_rtl() { self.scroll_width } else { 0.0 };
+    }
+
     /// Update content size.
     pub fn set_content_size(&mut self, width: f32, height: f32) {
+        let old_scroll_width = self.scroll_width;
         self.content_width = width;
         self.content_height = height;
         self.scroll_width = (width - self.viewport_width).max(0.0);
         self.scroll_height = (height - self.viewport_height).max(0.0);
-
-        // Clamp current scroll position
-        self.scroll_x = self.scroll_x.clamp(0.0, self.scroll_width);
-        self.scroll_y = self.scroll_y.clamp(0.0, self.scroll_height);
+        self.clamp_after_resize(old_scroll_width);
     }
 
     /// Update viewport size.
     pub fn set_viewport_size(&mut self, width: f32, height: f32) {
+        let old_scroll_width = self.scroll_width;
         self.viewport_width = width;
         self.viewport_height = height;
         self.scroll_width = (self.content_width - width).max(0.0);
         self.scroll_height = (self.content_height - height).max(0.0);
+        self.clamp_after_resize(old_scroll_width);
+    }
+
+    /// Clamp the scroll position after the scrollable area changed size.
+    fn clamp_after_resize(&mut self, old_scroll_width: f32) {
+        // RTL content grows to the left, so keep the distance from the right edge
+        if self.direction.is_rtl() {
+            self.scroll_x += self.scroll_width - old_scroll_width;
+        }
 
         // Clamp current scroll position
         self.scroll_x = self.scroll_x.clamp(0.0, self.scroll_width);
         self.scroll_y = self.scroll_y.clamp(0.0, self.scroll_height);
     }
 
+    /// Get `scrollLeft` as scripts see it.
+    ///
+    /// For RTL containers the origin is the right edge, so the value runs from
+    /// 0 down to `-scroll_width` as the content is scrolled left.
+    pub fn scroll_left(&self) -> f32 {
+        if self.direction.is_rtl() {
+            self.scroll_x - self.scroll_width
+        } else {
+            self.scroll_x
+        }
+    }
+
+    /// Set `scrollLeft` as scripts see it (see [`ScrollState::scroll_left`]).
+    pub fn set_scroll_left(&mut self, scroll_left: f32) {
+        let x = if self.direction.is_rtl() {
+            scroll_left + self.scroll_width
+        } else {
+            scroll_left
+        };
+        self.scroll_to(x, self.scroll_y);
+    }
+
     /// Scroll to a position (instant).
     pub fn scroll_to(&mut self, x: f32, y: f32) {
         self.scroll_x = x.clamp(0.0, self.scroll_width);
@@ -436,10 +477,17 @@ pub fn render_scrollbars(
     let show_vertical = overflow_y.is_scrollable() && scroll_state.can_scroll_y();
     let show_horizontal = overflow_x.is_scrollable() && scroll_state.can_scroll_x();
 
+    // The vertical scrollbar sits on the inline-end side: left in RTL
+    let vertical_x = if scroll_state.direction.is_rtl() {
+        container_rect.x
+    } else {
+        container_rect.x + container_rect.width - scrollbar_width
+    };
+
     // Vertical scrollbar
     if show_vertical {
         let track_rect = Rect {
-            x: container_rect.x + container_rect.width - scrollbar_width,
+            x: vertical_x,
             y: container_rect.y,
             width: scrollbar_width,
             height: container_rect.height - if show_horizontal { scrollbar_width } else { 0.0 },
@@ -460,7 +508,11 @@ pub fn render_scrollbars(
     // Horizontal scrollbar
     if show_horizontal {
         let track_rect = Rect {
-            x: container_rect.x,
+            x: if show_vertical && scroll_state.direction.is_rtl() {
+                container_rect.x + scrollbar_width
+            } else {
+                container_rect.x
+            },
             y: container_rect.y + container_rect.height - scrollbar_width,
             width: container_rect.width - if show_vertical { scrollbar_width } else { 0.0 },
             height: scrollbar_width,
@@ -481,7 +533,7 @@ pub fn render_scrollbars(
     // Corner piece if both scrollbars are shown
     if show_vertical && show_horizontal {
         let corner_rect = Rect {
-            x: container_rect.x + container_rect.width - scrollbar_width,
+            x: vertical_x,
             y: container_rect.y + container_rect.height - scrollbar_width,
             width: scrollbar_width,
             height: scrollbar_width,
@@ -615,7 +667,7 @@ pub struct StickyState {
 }
 
 /// Sticky offsets.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct StickyOffsets {
     pub top: Option<f32>,
     pub right: Option<f32>,
@@ -738,6 +790,54 @@ mod tests {
         assert!(scrollbar.thumb_rect.height < 600.0);
     }
 
+    #[test]
+    fn test_rtl_scroll_origin() {
+        let mut state = ScrollState::new(800.0, 600.0);
+        state.set_content_size(1200.0, 600.0);
+        state.set_direction(Direction::Rtl);
+
+        // Starts at the right edge, which scripts see as scrollLeft 0
+        assert_eq!(state.scroll_x, 400.0);
+        assert_eq!(state.scroll_left(), 0.0);
+
+        state.set_scroll_left(-100.0);
+        assert_eq!(state.scroll_x, 300.0);
+        assert_eq!(state.scroll_left(), -100.0);
+
+        // Growing content extends to the left without moving the view
+        state.set_content_size(1400.0, 600.0);
+        assert_eq!(state.scroll_left(), -100.0);
+    }
+
+    #[test]
+    fn test_rtl_scrollbars_on_left() {
+        let mut state = ScrollState::new(100.0, 100.0);
+        state.set_content_size(200.0, 200.0);
+        state.set_direction(Direction::Rtl);
+
+        let container = Rect { x: 10.0, y: 0.0, width: 100.0, height: 100.0 };
+        let commands = render_scrollbars(
+            &state,
+            container,
+            ScrollbarWidth::Auto,
+            ScrollbarGutter::Auto,
+            None,
+            Overflow::Scroll,
+            Overflow::Scroll,
+        );
+        let rects: Vec<Rect> = commands
+            .iter()
+            .filter_map(|c| match c {
+                DisplayCommand::SolidColor(_, rect) => Some(*rect),
+                _ => None,
+            })
+            .collect();
+
+        // Vertical track on the left, horizontal track starting after it
+        assert_eq!(rects[0].x, 10.0);
+        assert!(rects.iter().any(|r| r.height == 12.0 && r.x == 22.0 && r.width == 88.0));
+    }
+
     #[test]
     fn test_overflow_is_scrollable() {
         assert!(Overflow::Scroll.is_scrollable());
@@ -16,10 +16,10 @@
 
 use rustkit_css::{
     Color, Direction as CssDirection, FontStretch, FontStyle, FontWeight, Length,
-    TextDecorationLine, TextDecorationStyle, TextTransform, WhiteSpace,
+    TextDecorationLine, TextDecorationStyle, TextTransform, UnicodeBidi, WhiteSpace,
     WordBreak as CssWordBreak,
 };
-use rustkit_text::bidi::{BidiInfo, Direction as BidiDirection};
+use rustkit_text::bidi::{override_visual_text, BidiInfo, Direction as BidiDirection};
 use rustkit_text::line_break::{LineBreaker, WordBreak as LineBreakWordBreak, OverflowWrap};
 use std::collections::HashMap;
 use std::sync::RwLock;
@@ -559,6 +559,23 @@ pub fn apply_text_transform(text: &str, transform: TextTransform) -> String {
     }
 }
 
+/// Reorder a run of text into visual order for painting.
+///
+/// The paragraph direction comes from `direction` unless `unicode-bidi` is
+/// `plaintext`, in which case the first strong character decides. Override
+/// values skip the bidi algorithm and force every character into `direction`.
+pub fn apply_bidi_reordering(text: &str, direction: CssDirection, unicode_bidi: UnicodeBidi) -> String {
+    let base = TextDirection::from_css(direction).to_bidi();
+    if unicode_bidi.is_override() {
+        return override_visual_text(text, base);
+    }
+    let base = match unicode_bidi {
+        UnicodeBidi::Plaintext => None,
+        _ => Some(base),
+    };
+    BidiInfo::with_base_direction(text, base).visual_text()
+}
+
 /// Collapse whitespace according to white-space property.
 pub fn collapse_whitespace(text: &str, white_space: WhiteSpace) -> String {
     match white_space {
@@ -1799,6 +1816,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_apply_bidi_reordering() {
+        let hebrew = "\u{05D0}\u{05D1}";
+        let mixed = "abc \u{05D0}\u{05D1}";
+
+        assert_eq!(apply_bidi_reordering("abc", CssDirection::Ltr, UnicodeBidi::Normal), "abc");
+        assert_eq!(
+            apply_bidi_reordering(hebrew, CssDirection::Ltr, UnicodeBidi::Normal),
+            "\u{05D1}\u{05D0}"
+        );
+        // In an RTL paragraph the Latin word is drawn to the left of the Hebrew
+        assert_eq!(
+            apply_bidi_reordering(mixed, CssDirection::Rtl, UnicodeBidi::Normal),
+            "\u{05D1}\u{05D0} abc"
+        );
+        // plaintext ignores `direction` and takes the first strong character
+        assert_eq!(
+            apply_bidi_reordering(mixed, CssDirection::Rtl, UnicodeBidi::Plaintext),
+            "abc \u{05D1}\u{05D0}"
+        );
+        assert_eq!(apply_bidi_reordering("abc", CssDirection::Rtl, UnicodeBidi::BidiOverride), "cba");
+        assert_eq!(
+            apply_bidi_reordering(hebrew, CssDirection::Ltr, UnicodeBidi::IsolateOverride),
+            hebrew
+        );
+    }
+
     #[test]
     fn test_collapse_whitespace() {
         assert_eq!(
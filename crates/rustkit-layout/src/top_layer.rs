@@ -0,0 +1,207 @@
+//! The top layer.
+//!
+//! Modal dialogs and open popovers render above every stacking context,
+//! modal dialogs over a `::backdrop` that covers the viewport. The engine
+//! builds their boxes in place, marked [`TopLayer::Element`], with any
+//! backdrop box marked [`TopLayer::Backdrop`] as a child.
+//! [`LayoutBox::hoist_top_layer`] then moves them to the end of the root's
+//! children, so they are laid out against the initial containing block
+//! instead of their parent, and after layout [`LayoutBox::place_top_layer`]
+//! stretches the backdrops over the viewport and places the elements:
+//! next to their anchor element if they have one, else centered.
+//!
+//! The display list paints the top layer after the root stacking context,
+//! and hit testing tries it first.
+
+use std::collections::HashMap;
+
+use crate::{DisplayList, LayoutBox, Position, Rect};
+
+/// The part a box plays in the top layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopLayer {
+    /// The `::backdrop` under a top layer element.
+    Backdrop,
+    /// An element in the top layer.
+    Element,
+}
+
+impl LayoutBox {
+    /// Move the top layer boxes in this tree to the end of this box's
+    /// children, each after its backdrop.
+    ///
+    /// `order` lists the element IDs of the top layer, bottommost first.
+    pub fn hoist_top_layer(&mut self, order: &[usize]) {
+        let mut hoisted = Vec::new();
+        take_top_layer(self, &mut hoisted);
+        hoisted.sort_by_key(|element| {
+            element
+                .element_id
+                .and_then(|id| order.iter().position(|&top| top == id))
+                .unwrap_or(usize::MAX)
+        });
+
+        for mut element in hoisted {
+            element.position = Position::Fixed;
+            if let Some(index) = element.children.iter().position(|child| child.top_layer == Some(TopLayer::Backdrop)) {
+                let mut backdrop = element.children.remove(index);
+                backdrop.position = Position::Fixed;
+                self.children.push(backdrop);
+            }
+            self.children.push(element);
+        }
+    }
+
+    /// Place the hoisted top layer children of this box in `viewport`.
+    ///
+    /// Backdrops cover the viewport. Elements with an anchor in `anchors`
+    /// (element ID to anchor element ID) go below the anchor's border box,
+    /// or above it when only that fits; others are centered in the
+    /// viewport, or placed at its top when they are taller.
+    pub fn place_top_layer(&mut self, viewport: Rect, anchors: &HashMap<usize, usize>) {
+        let anchor_rects: Vec<Option<Rect>> = self
+            .children
+            .iter()
+            .map(|child| {
+                let anchor = anchors.get(&child.element_id?)?;
+                Some(self.find_element(*anchor)?.dimensions.border_box())
+            })
+            .collect();
+
+        for (child, anchor) in self.children.iter_mut().zip(anchor_rects) {
+            match child.top_layer {
+                Some(TopLayer::Backdrop) => child.dimensions.content = viewport,
+                Some(TopLayer::Element) => {
+                    let border_box = child.dimensions.border_box();
+                    let (x, y) = match anchor {
+                        Some(anchor) => anchored_position(anchor, border_box, viewport),
+                        None => (
+                            viewport.x + ((viewport.width - border_box.width) / 2.0).max(0.0),
+                            viewport.y + ((viewport.height - border_box.height) / 2.0).max(0.0),
+                        ),
+                    };
+                    child.translate(x - border_box.x, y - border_box.y);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Where a box of `size` anchored to `anchor` goes: below it and aligned
+/// with its left edge, flipped above when it only fits there, and shifted
+/// left to stay inside the viewport.
+fn anchored_position(anchor: Rect, size: Rect, viewport: Rect) -> (f32, f32) {
+    let fits_below = anchor.bottom() + size.height <= viewport.bottom();
+    let fits_above = anchor.y - size.height >= viewport.y;
+    let y = if !fits_below && fits_above {
+        anchor.y - size.height
+    } else {
+        anchor.bottom()
+    };
+    let x = anchor.x.min(viewport.right() - size.width).max(viewport.x);
+    (x, y)
+}
+
+/// Remove the top layer elements under `layout_box`, including ones nested
+/// in other top layer elements, into `hoisted`.
+fn take_top_layer(layout_box: &mut LayoutBox, hoisted: &mut Vec<LayoutBox>) {
+    let mut index = 0;
+    while index < layout_box.children.len() {
+        if layout_box.children[index].top_layer == Some(TopLayer::Element) {
+            let mut element = layout_box.children.remove(index);
+            take_top_layer(&mut element, hoisted);
+            hoisted.push(element);
+        } else {
+            take_top_layer(&mut layout_box.children[index], hoisted);
+            index += 1;
+        }
+    }
+}
+
+impl DisplayList {
+    /// Paint the hoisted top layer children of `root` over everything else.
+    pub(crate) fn render_top_layer(&mut self, root: &LayoutBox) {
+        for layer in root.children.iter().filter(|child| child.top_layer.is_some()) {
+            self.render_stacking_context(layer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoxType, DisplayCommand, Dimensions};
+    use rustkit_css::{Color, ComputedStyle};
+
+    fn block(element_id: Option<usize>, color: Color) -> LayoutBox {
+        let mut style = ComputedStyle::new();
+        style.background_color = color;
+        style.height = rustkit_css::Length::Px(50.0);
+        let mut layout_box = LayoutBox::new(BoxType::Block, style);
+        layout_box.element_id = element_id;
+        layout_box
+    }
+
+    #[test]
+    fn test_top_layer_paints_last_and_centers() {
+        let red = Color::new(255, 0, 0, 1.0);
+        let blue = Color::new(0, 0, 255, 1.0);
+        let dim = Color::new(0, 0, 0, 0.1);
+
+        let mut root = block(None, Color::WHITE);
+        let mut body = block(Some(2), Color::WHITE);
+        let mut dialog = block(Some(3), red);
+        dialog.style.width = rustkit_css::Length::Px(100.0);
+        dialog.top_layer = Some(TopLayer::Element);
+        let mut backdrop = block(None, dim);
+        backdrop.top_layer = Some(TopLayer::Backdrop);
+        dialog.children.push(backdrop);
+        body.children.push(dialog);
+        // A positive z-index context still paints under the top layer
+        let mut raised = LayoutBox::with_position(BoxType::Block, block(Some(4), blue).style, Position::Relative);
+        raised.set_z_index(10);
+        body.children.push(raised);
+        root.children.push(body);
+
+        root.hoist_top_layer(&[3]);
+        assert_eq!(root.children.len(), 3);
+        assert_eq!(root.children[1].top_layer, Some(TopLayer::Backdrop));
+        assert_eq!(root.children[2].element_id, Some(3));
+
+        let mut viewport = Dimensions::default();
+        viewport.content.width = 400.0;
+        root.layout(&viewport);
+        root.place_top_layer(Rect::new(0.0, 0.0, 400.0, 300.0), &HashMap::new());
+        assert_eq!(root.children[1].dimensions.content, Rect::new(0.0, 0.0, 400.0, 300.0));
+        assert_eq!(root.children[2].dimensions.border_box(), Rect::new(150.0, 125.0, 100.0, 50.0));
+
+        let list = DisplayList::build(&root);
+        let colors: Vec<_> = list
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                DisplayCommand::SolidColor(color, _) if *color != Color::WHITE => Some(*color),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(colors, [blue, dim, red]);
+
+        let hit = root.hit_test(200.0, 150.0).unwrap();
+        assert_eq!(hit.node_id(), Some(3));
+        // The backdrop covers the rest of the viewport, below the document
+        let hit = root.hit_test(10.0, 280.0).unwrap();
+        assert_eq!(hit.node_id(), None);
+    }
+
+    #[test]
+    fn test_anchored_position_flips_and_shifts() {
+        let viewport = Rect::new(0.0, 0.0, 400.0, 300.0);
+        let size = Rect::new(0.0, 0.0, 100.0, 80.0);
+        assert_eq!(anchored_position(Rect::new(20.0, 10.0, 60.0, 20.0), size, viewport), (20.0, 30.0));
+        // No room below: flip above
+        assert_eq!(anchored_position(Rect::new(20.0, 250.0, 60.0, 20.0), size, viewport), (20.0, 170.0));
+        // Too close to the right edge
+        assert_eq!(anchored_position(Rect::new(350.0, 10.0, 40.0, 20.0), size, viewport), (300.0, 30.0));
+    }
+}
@@ -0,0 +1,149 @@
+//! 2D affine transform math.
+//!
+//! Matrices are `[a, b, c, d, e, f]`, as produced by
+//! `TransformList::to_matrix`, representing:
+//!
+//! ```text
+//! | a c e |
+//! | b d f |
+//! | 0 0 1 |
+//! ```
+//!
+//! Points are column vectors, so `multiply(a, b)` applies `b` first.
+
+use crate::{LayoutBox, Rect};
+
+/// The identity transform.
+pub const IDENTITY: [f32; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Compose two transforms: the result applies `b`, then `a`.
+pub fn multiply(a: [f32; 6], b: [f32; 6]) -> [f32; 6] {
+    [
+        a[0] * b[0] + a[2] * b[1],
+        a[1] * b[0] + a[3] * b[1],
+        a[0] * b[2] + a[2] * b[3],
+        a[1] * b[2] + a[3] * b[3],
+        a[0] * b[4] + a[2] * b[5] + a[4],
+        a[1] * b[4] + a[3] * b[5] + a[5],
+    ]
+}
+
+/// Apply `matrix` around `origin` (CSS `transform-origin`).
+pub fn around_origin(matrix: [f32; 6], origin: (f32, f32)) -> [f32; 6] {
+    let to_origin = [1.0, 0.0, 0.0, 1.0, origin.0, origin.1];
+    let from_origin = [1.0, 0.0, 0.0, 1.0, -origin.0, -origin.1];
+    multiply(multiply(to_origin, matrix), from_origin)
+}
+
+/// Transform a point.
+pub fn apply(m: [f32; 6], x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+/// The inverse transform, or `None` if `m` collapses the plane (e.g.
+/// `scale(0)`).
+pub fn invert(m: [f32; 6]) -> Option<[f32; 6]> {
+    let det = m[0] * m[3] - m[1] * m[2];
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let [a, b, c, d, e, f] = m;
+    Some([
+        d / det,
+        -b / det,
+        -c / det,
+        a / det,
+        (c * f - d * e) / det,
+        (b * e - a * f) / det,
+    ])
+}
+
+/// Whether `m` keeps rectangles axis-aligned (no rotation or skew).
+pub fn is_axis_aligned(m: [f32; 6]) -> bool {
+    m[1] == 0.0 && m[2] == 0.0
+}
+
+/// Bounding box of a rect after transforming it.
+pub fn map_rect_bounds(m: [f32; 6], rect: Rect) -> Rect {
+    let corners = [
+        apply(m, rect.x, rect.y),
+        apply(m, rect.right(), rect.y),
+        apply(m, rect.right(), rect.bottom()),
+        apply(m, rect.x, rect.bottom()),
+    ];
+    let (mut min_x, mut min_y) = corners[0];
+    let (mut max_x, mut max_y) = corners[0];
+    for &(x, y) in &corners[1..] {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    Rect::new(min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+impl LayoutBox {
+    /// This box's `transform` with `transform-origin` applied, or `None`
+    /// if it has none.
+    pub fn transform_matrix(&self) -> Option<[f32; 6]> {
+        if self.style.transform.is_identity() {
+            return None;
+        }
+        let (matrix, origin) = self.transform_parts();
+        Some(around_origin(matrix, origin))
+    }
+
+    /// The transform matrix and its origin in layout coordinates, as
+    /// pushed onto the display list.
+    pub(crate) fn transform_parts(&self) -> ([f32; 6], (f32, f32)) {
+        let border_box = self.dimensions.border_box();
+        let matrix = self.style.transform.to_matrix(border_box.width, border_box.height);
+        let origin_x = border_box.x + self.style.transform_origin.x.to_px(16.0, 16.0, border_box.width);
+        let origin_y = border_box.y + self.style.transform_origin.y.to_px(16.0, 16.0, border_box.height);
+        (matrix, (origin_x, origin_y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-3 && (actual.1 - expected.1).abs() < 1e-3,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn test_rotation_around_origin() {
+        // rotate(90deg) around the center of a 100x100 box at (0, 0)
+        let rotate = [0.0, 1.0, -1.0, 0.0, 0.0, 0.0];
+        let m = around_origin(rotate, (50.0, 50.0));
+        assert_point(apply(m, 50.0, 50.0), (50.0, 50.0));
+        assert_point(apply(m, 0.0, 0.0), (100.0, 0.0));
+        assert_point(apply(m, 100.0, 0.0), (100.0, 100.0));
+    }
+
+    #[test]
+    fn test_invert_round_trips() {
+        let skew = [1.0, 0.0, 0.5, 1.0, 0.0, 0.0];
+        let m = multiply(around_origin(skew, (10.0, 20.0)), [2.0, 0.0, 0.0, 3.0, 5.0, -7.0]);
+        let inverse = invert(m).unwrap();
+        let (x, y) = apply(m, 12.0, 34.0);
+        assert_point(apply(inverse, x, y), (12.0, 34.0));
+        assert_point(apply(multiply(m, inverse), 3.0, 4.0), (3.0, 4.0));
+
+        assert!(invert([0.0, 0.0, 0.0, 0.0, 1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn test_map_rect_bounds() {
+        let rotate = [0.0, 1.0, -1.0, 0.0, 0.0, 0.0];
+        let bounds = map_rect_bounds(rotate, Rect::new(0.0, 0.0, 40.0, 10.0));
+        assert_point((bounds.x, bounds.y), (-10.0, 0.0));
+        assert_point((bounds.width, bounds.height), (10.0, 40.0));
+        assert!(is_axis_aligned([2.0, 0.0, 0.0, 3.0, 1.0, 1.0]));
+        assert!(!is_axis_aligned(rotate));
+    }
+}
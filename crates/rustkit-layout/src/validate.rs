@@ -0,0 +1,341 @@
+//! Layout invariant checks.
+//!
+//! [`validate_layout`] walks a laid-out tree and reports boxes that break
+//! invariants every correct layout keeps:
+//!
+//! - All dimensions are finite, and content sizes, padding and borders are
+//!   non-negative (margins may be negative).
+//! - In-flow block children of a block container stay within the parent's
+//!   margin box, unless they overflow it by construction (a wider fixed
+//!   width, negative margins), the parent has a fixed height or a flex or
+//!   grid container sized it, or the parent's `overflow` isn't visible.
+//! - In-flow block siblings progress downwards in document order.
+//!
+//! Out-of-flow boxes (positioned, floated) and the children of flex, grid
+//! and multi-column containers are placed by other rules and only get the
+//! size checks. [`debug_assert_layout`] runs the checks in debug builds.
+
+use std::fmt;
+
+use rustkit_css::{Display, Length, Overflow};
+
+use crate::{is_multicol_container, BoxType, Float, LayoutBox, Position, Rect};
+
+/// Slack for float rounding, in pixels.
+const EPSILON: f32 = 0.5;
+
+/// An invariant broken by a laid-out box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutViolation {
+    /// Child indices from the root to the offending box.
+    pub path: Vec<usize>,
+    /// The broken invariant.
+    pub kind: ViolationKind,
+}
+
+/// The invariants [`validate_layout`] checks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    /// A dimension is NaN or infinite.
+    NonFinite,
+    /// A content size, padding or border is negative.
+    NegativeSize { field: &'static str, value: f32 },
+    /// An in-flow child's border box extends outside its parent's margin box.
+    EscapesParent { child: Rect, parent: Rect },
+    /// An in-flow block starts above the previous in-flow block.
+    OutOfOrder { top: f32, previous_top: f32 },
+}
+
+impl fmt::Display for LayoutViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path: Vec<String> = self.path.iter().map(usize::to_string).collect();
+        write!(f, "box /{}: ", path.join("/"))?;
+        match &self.kind {
+            ViolationKind::NonFinite => write!(f, "non-finite dimensions"),
+            ViolationKind::NegativeSize { field, value } => write!(f, "negative {field} ({value})"),
+            ViolationKind::EscapesParent { child, parent } => write!(
+                f,
+                "border box ({}, {}, {}x{}) escapes parent margin box ({}, {}, {}x{})",
+                child.x, child.y, child.width, child.height, parent.x, parent.y, parent.width, parent.height
+            ),
+            ViolationKind::OutOfOrder { top, previous_top } => {
+                write!(f, "starts at y={top}, above previous sibling at y={previous_top}")
+            }
+        }
+    }
+}
+
+/// Check a laid-out tree against the layout invariants, returning every
+/// violation found.
+pub fn validate_layout(root: &LayoutBox) -> Vec<LayoutViolation> {
+    let mut violations = Vec::new();
+    let mut path = Vec::new();
+    validate_box(root, false, &mut path, &mut violations);
+    violations
+}
+
+/// Panic if a laid-out tree breaks a layout invariant. Does nothing in
+/// release builds.
+pub fn debug_assert_layout(root: &LayoutBox) {
+    if cfg!(debug_assertions) {
+        let violations = validate_layout(root);
+        if !violations.is_empty() {
+            let report: Vec<String> = violations.iter().map(ToString::to_string).collect();
+            panic!("layout invariants violated:\n{}", report.join("\n"));
+        }
+    }
+}
+
+/// Check `layout_box` and its subtree. `container_sized` says a flex or
+/// grid container set the box's size, which its content may overflow.
+fn validate_box(
+    layout_box: &LayoutBox,
+    container_sized: bool,
+    path: &mut Vec<usize>,
+    violations: &mut Vec<LayoutViolation>,
+) {
+    let mut report = |path: &[usize], kind| {
+        violations.push(LayoutViolation {
+            path: path.to_vec(),
+            kind,
+        })
+    };
+
+    let d = &layout_box.dimensions;
+    let edges = [d.padding, d.border, d.margin];
+    let finite = [d.content.x, d.content.y, d.content.width, d.content.height]
+        .into_iter()
+        .chain(edges.iter().flat_map(|e| [e.top, e.right, e.bottom, e.left]))
+        .all(f32::is_finite);
+    if !finite {
+        report(path, ViolationKind::NonFinite);
+    } else {
+        let sizes = [
+            ("width", d.content.width),
+            ("height", d.content.height),
+            ("padding-top", d.padding.top),
+            ("padding-right", d.padding.right),
+            ("padding-bottom", d.padding.bottom),
+            ("padding-left", d.padding.left),
+            ("border-top", d.border.top),
+            ("border-right", d.border.right),
+            ("border-bottom", d.border.bottom),
+            ("border-left", d.border.left),
+        ];
+        for (field, value) in sizes {
+            if value < -EPSILON {
+                report(path, ViolationKind::NegativeSize { field, value });
+            }
+        }
+    }
+
+    if finite && is_block_flow(layout_box) {
+        let parent = d.margin_box();
+        let auto_height = matches!(layout_box.style.height, Length::Auto);
+        // Content may overflow a size the box didn't get from it
+        let may_overflow = container_sized
+            || layout_box.style.overflow_x != Overflow::Visible
+            || layout_box.style.overflow_y != Overflow::Visible;
+        let mut previous_top: Option<f32> = None;
+
+        for (index, child) in layout_box.children.iter().enumerate() {
+            if !is_in_flow_block(child) {
+                continue;
+            }
+            path.push(index);
+            let cd = &child.dimensions;
+            let border_box = cd.border_box();
+            let pulled_up = cd.margin.top < 0.0;
+
+            // A wider child overflows to the end side by design
+            let fits = cd.margin_box().width <= d.content.width + EPSILON;
+            let escapes_horizontally = !may_overflow
+                && fits
+                && cd.margin.left >= 0.0
+                && cd.margin.right >= 0.0
+                && (border_box.x < parent.x - EPSILON || border_box.right() > parent.right() + EPSILON);
+            let escapes_vertically = !may_overflow
+                && ((!pulled_up && border_box.y < parent.y - EPSILON)
+                    || (auto_height && cd.margin.bottom >= 0.0 && border_box.bottom() > parent.bottom() + EPSILON));
+            if escapes_horizontally || escapes_vertically {
+                report(
+                    path,
+                    ViolationKind::EscapesParent {
+                        child: border_box,
+                        parent,
+                    },
+                );
+            }
+
+            if let Some(previous) = previous_top {
+                if !pulled_up && border_box.y < previous - EPSILON {
+                    report(
+                        path,
+                        ViolationKind::OutOfOrder {
+                            top: border_box.y,
+                            previous_top: previous,
+                        },
+                    );
+                }
+            }
+            previous_top = Some(border_box.y);
+            path.pop();
+        }
+    }
+
+    let sizes_children = layout_box.style.display.is_flex() || layout_box.style.display.is_grid();
+    for (index, child) in layout_box.children.iter().enumerate() {
+        path.push(index);
+        validate_box(child, sizes_children, path, violations);
+        path.pop();
+    }
+}
+
+/// Whether a box lays its children out in normal block flow.
+fn is_block_flow(layout_box: &LayoutBox) -> bool {
+    matches!(layout_box.box_type, BoxType::Block | BoxType::AnonymousBlock)
+        && matches!(
+            layout_box.style.display,
+            Display::Block | Display::ListItem | Display::InlineBlock
+        )
+        && layout_box.column_layout.is_none()
+        && !is_multicol_container(&layout_box.style)
+}
+
+/// Whether a box is an in-flow, block-level box.
+fn is_in_flow_block(layout_box: &LayoutBox) -> bool {
+    matches!(layout_box.box_type, BoxType::Block | BoxType::AnonymousBlock)
+        && !layout_box.style.display.is_inline_level()
+        && layout_box.position == Position::Static
+        && layout_box.float == Float::None
+        && !layout_box.is_marker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dimensions;
+    use proptest::prelude::*;
+    use rustkit_css::ComputedStyle;
+
+    fn lay_out(mut root: LayoutBox, width: f32) -> LayoutBox {
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = width;
+        root.layout(&containing_block);
+        root
+    }
+
+    #[test]
+    fn test_reports_violations() {
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        root.children.push(LayoutBox::new(BoxType::Block, ComputedStyle::new()));
+        root.children.push(LayoutBox::new(BoxType::Block, ComputedStyle::new()));
+        let mut root = lay_out(root, 100.0);
+        assert_eq!(validate_layout(&root), vec![]);
+
+        root.children[1].dimensions.content.y = -50.0;
+        root.children[1].dimensions.content.width = -1.0;
+        root.children[0].dimensions.padding.left = f32::NAN;
+        let kinds: Vec<_> = validate_layout(&root).into_iter().map(|v| (v.path, v.kind)).collect();
+        assert!(matches!(kinds[0], (ref p, ViolationKind::EscapesParent { .. }) if p == &[1]));
+        assert!(matches!(kinds[1], (ref p, ViolationKind::OutOfOrder { .. }) if p == &[1]));
+        assert_eq!(kinds[2], (vec![0], ViolationKind::NonFinite));
+        assert_eq!(
+            kinds[3],
+            (vec![1], ViolationKind::NegativeSize { field: "width", value: -1.0 })
+        );
+    }
+
+    #[test]
+    fn test_overflow_is_allowed_where_content_may_overflow() {
+        let mut item = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        item.children.push(LayoutBox::new(BoxType::Block, ComputedStyle::new()));
+        let mut root = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+        root.children.push(item);
+        let mut root = lay_out(root, 100.0);
+        root.children[0].children[0].dimensions.content.x = -50.0;
+        assert_eq!(validate_layout(&root).len(), 1);
+
+        // Clipped or scrolled
+        root.children[0].style.overflow_x = Overflow::Hidden;
+        assert_eq!(validate_layout(&root), vec![]);
+
+        // Sized by a grid or flex container
+        root.children[0].style.overflow_x = Overflow::Visible;
+        root.style.display = Display::Grid;
+        assert_eq!(validate_layout(&root), vec![]);
+        root.style.display = Display::Flex;
+        assert_eq!(validate_layout(&root), vec![]);
+    }
+
+    fn length() -> impl Strategy<Value = Length> {
+        prop_oneof![
+            Just(Length::Auto),
+            Just(Length::Zero),
+            (0.0f32..300.0).prop_map(Length::Px),
+            (0.0f32..100.0).prop_map(Length::Percent),
+        ]
+    }
+
+    fn edge() -> impl Strategy<Value = Length> {
+        prop_oneof![Just(Length::Zero), (0.0f32..30.0).prop_map(Length::Px)]
+    }
+
+    fn style() -> impl Strategy<Value = ComputedStyle> {
+        (
+            (length(), length(), length()),
+            prop::array::uniform4(edge()),
+            prop::array::uniform4(edge()),
+            prop::array::uniform4(edge()),
+            prop_oneof![Just(Display::Block), Just(Display::InlineBlock), Just(Display::Flex)],
+        )
+            .prop_map(|((width, height, max_width), margin, padding, border, display)| {
+                let mut style = ComputedStyle::new();
+                style.width = width;
+                style.height = height;
+                style.max_width = max_width;
+                [style.margin_top, style.margin_right, style.margin_bottom, style.margin_left] = margin;
+                [style.padding_top, style.padding_right, style.padding_bottom, style.padding_left] = padding;
+                [
+                    style.border_top_width,
+                    style.border_right_width,
+                    style.border_bottom_width,
+                    style.border_left_width,
+                ] = border;
+                style.display = display;
+                style
+            })
+    }
+
+    /// Random trees of blocks, inline-blocks, flex containers and text.
+    fn box_tree() -> impl Strategy<Value = LayoutBox> {
+        let text = "[a-z]{1,8}( [a-z]{1,8}){0,6}"
+            .prop_map(|text| LayoutBox::new(BoxType::Text(text), ComputedStyle::new()));
+        let leaf = prop_oneof![
+            text,
+            style().prop_map(|style| LayoutBox::new(BoxType::Block, style)),
+        ];
+        leaf.prop_recursive(4, 48, 5, |inner| {
+            (style(), prop::collection::vec(inner, 0..5)).prop_map(|(style, children)| {
+                let mut layout_box = LayoutBox::new(BoxType::Block, style);
+                layout_box.children = children;
+                layout_box
+            })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_layout_keeps_invariants(root in box_tree(), width in 0.0f32..1200.0) {
+            let mut root_box = LayoutBox::new(BoxType::Block, ComputedStyle::new());
+            root_box.children.push(root);
+            let root_box = lay_out(root_box, width);
+            let violations = validate_layout(&root_box);
+            prop_assert!(
+                violations.is_empty(),
+                "{}",
+                violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+            );
+        }
+    }
+}
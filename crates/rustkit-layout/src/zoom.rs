@@ -0,0 +1,331 @@
+//! Display list zoom.
+//!
+//! Page zoom lays a document out in a viewport `1 / factor` the size of the
+//! view and then paints it `factor` times larger. [`DisplayList::scale`]
+//! does the painting half: it multiplies every position, size, font size,
+//! radius and stroke width in the list, so text is rasterized at its zoomed
+//! size rather than stretched, and stays sharp.
+//!
+//! Images drawn at their natural size (`object-fit: none` and
+//! `scale-down`, and `background-size: auto`) keep that size, since the
+//! list doesn't know it.
+
+use rustkit_css::{ColorStop, Gradient, RadialSize, StopPosition};
+
+use crate::{BackgroundSize, BorderRadius, DisplayCommand, DisplayList, Rect};
+
+impl DisplayList {
+    /// Scale everything the list draws by `factor` about the origin.
+    pub fn scale(&mut self, factor: f32) {
+        if factor == 1.0 {
+            return;
+        }
+        for command in &mut self.commands {
+            scale_command(command, factor);
+        }
+    }
+}
+
+fn scale_command(command: &mut DisplayCommand, k: f32) {
+    match command {
+        DisplayCommand::SolidColor(_, rect)
+        | DisplayCommand::PushClip(rect)
+        | DisplayCommand::PushStackingContext { rect, .. }
+        | DisplayCommand::PushLayer { rect, .. }
+        | DisplayCommand::FillRect { rect, .. }
+        | DisplayCommand::FillEllipse { rect, .. } => scale_rect(rect, k),
+        DisplayCommand::RoundedRect { rect, radius, .. }
+        | DisplayCommand::PushRoundedClip { rect, radius }
+        | DisplayCommand::BackdropFilter {
+            rect,
+            border_radius: radius,
+            ..
+        } => {
+            scale_rect(rect, k);
+            scale_radius(radius, k);
+        }
+        DisplayCommand::Border {
+            rect,
+            top,
+            right,
+            bottom,
+            left,
+            ..
+        } => {
+            scale_rect(rect, k);
+            for width in [top, right, bottom, left] {
+                *width *= k;
+            }
+        }
+        DisplayCommand::Text { x, y, font_size, .. } => {
+            *x *= k;
+            *y *= k;
+            *font_size *= k;
+        }
+        DisplayCommand::TextRun {
+            segments, font_size, ..
+        } => {
+            for segment in segments {
+                segment.x *= k;
+                segment.y *= k;
+            }
+            *font_size *= k;
+        }
+        DisplayCommand::TextDecoration {
+            x, y, width, thickness, ..
+        } => {
+            *x *= k;
+            *y *= k;
+            *width *= k;
+            *thickness *= k;
+        }
+        DisplayCommand::Image { dest_rect, .. } => scale_rect(dest_rect, k),
+        DisplayCommand::BackgroundImage { rect, size, offset, .. } => {
+            scale_rect(rect, k);
+            offset.0 *= k;
+            offset.1 *= k;
+            if let BackgroundSize::Explicit { width, height } = size {
+                // Percentages (negative) follow the rect on their own.
+                for length in [width, height].into_iter().flatten().filter(|length| **length > 0.0) {
+                    *length *= k;
+                }
+            }
+        }
+        DisplayCommand::BoxShadow {
+            offset_x,
+            offset_y,
+            blur_radius,
+            spread_radius,
+            rect,
+            ..
+        } => {
+            *offset_x *= k;
+            *offset_y *= k;
+            *blur_radius *= k;
+            *spread_radius *= k;
+            scale_rect(rect, k);
+        }
+        DisplayCommand::LinearGradient {
+            rect,
+            stops,
+            border_radius,
+            ..
+        } => {
+            scale_rect(rect, k);
+            scale_stops(stops, k);
+            scale_radius(border_radius, k);
+        }
+        DisplayCommand::RadialGradient {
+            rect,
+            size,
+            stops,
+            border_radius,
+            ..
+        } => {
+            scale_rect(rect, k);
+            if let RadialSize::Explicit(rx, ry) = size {
+                *rx *= k;
+                *ry *= k;
+            }
+            scale_stops(stops, k);
+            scale_radius(border_radius, k);
+        }
+        // Conic stops are angles
+        DisplayCommand::ConicGradient {
+            rect, border_radius, ..
+        } => {
+            scale_rect(rect, k);
+            scale_radius(border_radius, k);
+        }
+        DisplayCommand::TextInput {
+            rect,
+            font_size,
+            border_width,
+            ..
+        } => {
+            scale_rect(rect, k);
+            *font_size *= k;
+            *border_width *= k;
+        }
+        DisplayCommand::Button {
+            rect,
+            font_size,
+            border_width,
+            border_radius,
+            ..
+        } => {
+            scale_rect(rect, k);
+            *font_size *= k;
+            *border_width *= k;
+            *border_radius *= k;
+        }
+        DisplayCommand::FocusRing {
+            rect, width, offset, ..
+        } => {
+            scale_rect(rect, k);
+            *width *= k;
+            *offset *= k;
+        }
+        DisplayCommand::Caret { x, y, height, .. } => {
+            *x *= k;
+            *y *= k;
+            *height *= k;
+        }
+        DisplayCommand::PushTransform { matrix, origin } => {
+            // The linear part is unitless; only the translation is a length
+            matrix[4] *= k;
+            matrix[5] *= k;
+            origin.0 *= k;
+            origin.1 *= k;
+        }
+        DisplayCommand::GradientText {
+            x,
+            y,
+            font_size,
+            gradient,
+            rect,
+            ..
+        } => {
+            *x *= k;
+            *y *= k;
+            *font_size *= k;
+            scale_rect(rect, k);
+            match gradient {
+                Gradient::Linear(linear) => scale_stops(&mut linear.stops, k),
+                Gradient::Radial(radial) => {
+                    if let RadialSize::Explicit(rx, ry) = &mut radial.size {
+                        *rx *= k;
+                        *ry *= k;
+                    }
+                    scale_stops(&mut radial.stops, k);
+                }
+                Gradient::Conic(_) => {}
+            }
+        }
+        DisplayCommand::StrokeRect { rect, width, .. } => {
+            scale_rect(rect, k);
+            *width *= k;
+        }
+        DisplayCommand::FillCircle { cx, cy, radius, .. } => {
+            *cx *= k;
+            *cy *= k;
+            *radius *= k;
+        }
+        DisplayCommand::StrokeCircle {
+            cx, cy, radius, width, ..
+        } => {
+            *cx *= k;
+            *cy *= k;
+            *radius *= k;
+            *width *= k;
+        }
+        DisplayCommand::Line {
+            x1, y1, x2, y2, width, ..
+        } => {
+            *x1 *= k;
+            *y1 *= k;
+            *x2 *= k;
+            *y2 *= k;
+            *width *= k;
+        }
+        DisplayCommand::Polyline { points, width, .. } | DisplayCommand::StrokePolygon { points, width, .. } => {
+            scale_points(points, k);
+            *width *= k;
+        }
+        DisplayCommand::FillPolygon { points, .. } => scale_points(points, k),
+        DisplayCommand::PopClip
+        | DisplayCommand::PopRoundedClip
+        | DisplayCommand::PopStackingContext
+        | DisplayCommand::PopTransform
+        | DisplayCommand::PopLayer
+        | DisplayCommand::PushOpacity(_)
+        | DisplayCommand::PopOpacity => {}
+    }
+}
+
+fn scale_rect(rect: &mut Rect, k: f32) {
+    rect.x *= k;
+    rect.y *= k;
+    rect.width *= k;
+    rect.height *= k;
+}
+
+fn scale_radius(radius: &mut BorderRadius, k: f32) {
+    radius.top_left *= k;
+    radius.top_right *= k;
+    radius.bottom_right *= k;
+    radius.bottom_left *= k;
+}
+
+fn scale_stops(stops: &mut [ColorStop], k: f32) {
+    for stop in stops {
+        for position in [&mut stop.position, &mut stop.hint].into_iter().flatten() {
+            if let StopPosition::Pixels(px) = position {
+                *px *= k;
+            }
+        }
+    }
+}
+
+fn scale_points(points: &mut [(f32, f32)], k: f32) {
+    for (x, y) in points {
+        *x *= k;
+        *y *= k;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextRunSegment;
+    use rustkit_css::Color;
+
+    #[test]
+    fn test_scale_display_list() {
+        let black = Color::new(0, 0, 0, 1.0);
+        let mut list = DisplayList {
+            commands: vec![
+                DisplayCommand::SolidColor(black, Rect::new(10.0, 20.0, 100.0, 50.0)),
+                DisplayCommand::PushTransform {
+                    matrix: [0.5, 0.0, 0.0, 0.5, 4.0, 6.0],
+                    origin: (30.0, 40.0),
+                },
+                DisplayCommand::TextRun {
+                    segments: vec![TextRunSegment {
+                        text: "zoom".to_string(),
+                        x: 10.0,
+                        y: 30.0,
+                    }],
+                    color: black,
+                    font_size: 16.0,
+                    font_family: "sans-serif".to_string(),
+                    font_weight: 400,
+                    font_style: 0,
+                },
+                DisplayCommand::PopTransform,
+            ],
+            ..DisplayList::new()
+        };
+        list.scale(1.5);
+
+        assert!(matches!(
+            list.commands[0],
+            DisplayCommand::SolidColor(_, rect) if rect == Rect::new(15.0, 30.0, 150.0, 75.0)
+        ));
+        assert!(matches!(
+            list.commands[1],
+            DisplayCommand::PushTransform {
+                matrix: [0.5, 0.0, 0.0, 0.5, 6.0, 9.0],
+                origin: (45.0, 60.0),
+            }
+        ));
+        let DisplayCommand::TextRun {
+            segments, font_size, ..
+        } = &list.commands[2]
+        else {
+            panic!("expected a text run");
+        };
+        assert_eq!((segments[0].x, segments[0].y, *font_size), (15.0, 45.0, 24.0));
+        assert!(matches!(list.commands[3], DisplayCommand::PopTransform));
+    }
+}
@@ -25,8 +25,11 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc;
+use tracing::debug;
 use url::Url;
 
+pub use rustkit_core::AutoplayPolicy;
+
 // ==================== Errors ====================
 
 /// Errors that can occur in media operations.
@@ -216,6 +219,8 @@ pub enum MediaEvent {
     RateChange(f64),
     /// Volume changed.
     VolumeChange { volume: f64, muted: bool },
+    /// Autoplay was blocked by the autoplay policy.
+    AutoplayBlocked,
 }
 
 // ==================== HTMLMediaElement ====================
@@ -277,6 +282,9 @@ pub struct HTMLMediaElement {
     /// Autoplay attribute.
     pub autoplay: bool,
     
+    /// Policy consulted before autoplaying.
+    pub autoplay_policy: AutoplayPolicy,
+    
     /// Loop attribute.
     pub loop_: bool,
     
@@ -326,6 +334,7 @@ impl HTMLMediaElement {
             playback_rate: 1.0,
             ended: false,
             autoplay: false,
+            autoplay_policy: AutoplayPolicy::default(),
             loop_: false,
             controls: false,
             volume: 1.0,
@@ -518,7 +527,12 @@ impl HTMLMediaElement {
         let _ = self.event_tx.send(MediaEvent::CanPlayThrough);
         
         if self.autoplay {
-            let _ = self.play();
+            if self.autoplay_policy.allows(self.muted) {
+                let _ = self.play();
+            } else {
+                debug!(id = ?self.id, policy = ?self.autoplay_policy, "Autoplay blocked");
+                let _ = self.event_tx.send(MediaEvent::AutoplayBlocked);
+            }
         }
     }
 
@@ -836,6 +850,26 @@ mod tests {
         assert_eq!(element.current_time, 100.0);
     }
 
+    #[test]
+    fn test_autoplay_policy() {
+        let ready = |policy: AutoplayPolicy, muted: bool| {
+            let (mut element, mut rx) = HTMLMediaElement::new();
+            element.network_state = NetworkState::Loading;
+            element.autoplay = true;
+            element.autoplay_policy = policy;
+            element.muted = muted;
+            element.set_ready();
+            let blocked = std::iter::from_fn(|| rx.try_recv().ok())
+                .any(|event| matches!(event, MediaEvent::AutoplayBlocked));
+            (!element.paused, blocked)
+        };
+
+        assert_eq!(ready(AutoplayPolicy::Allow, false), (true, false));
+        assert_eq!(ready(AutoplayPolicy::AllowMuted, true), (true, false));
+        assert_eq!(ready(AutoplayPolicy::AllowMuted, false), (false, true));
+        assert_eq!(ready(AutoplayPolicy::Block, true), (false, true));
+    }
+
     #[test]
     fn test_can_play_type() {
         let (element, _rx) = HTMLMediaElement::new();
@@ -0,0 +1,719 @@
+//! HTTP cookies for RustKit.
+//!
+//! A [`CookieJar`] keeps the cookies servers set with `Set-Cookie` and
+//! picks the ones each request carries, following RFC 6265: a cookie is
+//! scoped to the host that set it, or with `Domain` to a domain and its
+//! subdomains, and to a path; `Secure` cookies only travel over HTTPS and
+//! `HttpOnly` ones are hidden from scripts.
+//!
+//! Opened with a path, the jar keeps its persistent cookies, those with an
+//! expiry, in a journal file: every change is appended as a line of JSON,
+//! and the file is rewritten with just the live cookies when it's opened
+//! and whenever stale lines pile up. Session cookies stay in memory.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{NetError, SameSite};
+
+/// Journal lines allowed beyond the live cookies before a rewrite.
+const JOURNAL_SLACK: usize = 256;
+
+/// Longest a cookie lives: later `Max-Age` and `Expires` are clamped to
+/// it, as RFC 6265bis has browsers do.
+const MAX_COOKIE_AGE: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// A stored cookie.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Domain the cookie is scoped to, lowercase and without a leading dot.
+    pub domain: String,
+    /// Whether only `domain` itself gets the cookie, not its subdomains:
+    /// true when the server didn't set `Domain`.
+    pub host_only: bool,
+    pub path: String,
+    /// When the cookie expires; `None` for a session cookie.
+    pub expires: Option<SystemTime>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    /// When the cookie was first set.
+    pub created: SystemTime,
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` header received from `url`. Returns `None` if
+    /// it's malformed or `url` may not set it.
+    pub fn parse(header: &str, url: &Url, now: SystemTime) -> Option<Cookie> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: SameSite::default(),
+            created: now,
+        };
+        let mut max_age = None;
+        let mut expires = None;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    // A public suffix, such as `com` or `co.uk`, would reach
+                    // every site under it
+                    let public_suffix = psl::suffix_str(&domain) == Some(domain.as_str());
+                    if !domain_matches(&host, &domain) || (domain != host && public_suffix) {
+                        return None;
+                    }
+                    cookie.host_only = domain == host;
+                    cookie.domain = domain;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "max-age" => max_age = value.parse::<i64>().ok(),
+                "expires" => expires = parse_cookie_date(value),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => cookie.same_site = value.parse().unwrap_or_default(),
+                _ => {}
+            }
+        }
+
+        // Max-Age wins over Expires; zero or less expires the cookie now
+        let latest = now + MAX_COOKIE_AGE;
+        cookie.expires = match max_age {
+            Some(seconds) if seconds <= 0 => Some(UNIX_EPOCH),
+            Some(seconds) => Some(now + Duration::from_secs(seconds as u64).min(MAX_COOKIE_AGE)),
+            None => expires.map(|expires| expires.min(latest)),
+        };
+        if cookie.secure && url.scheme() != "https" {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    /// Whether the cookie has expired at `now`.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether a request to `url` carries the cookie.
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        domain_ok && path_matches(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+
+    /// Whether two cookies are the same one, which a newer one replaces.
+    fn same_as(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// Which cookies [`CookieJar::clear`] removes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieFilter {
+    /// Only cookies for this domain and its subdomains.
+    pub domain: Option<String>,
+    /// Only cookies set at or after this time.
+    pub since: Option<SystemTime>,
+}
+
+impl CookieFilter {
+    /// Every cookie.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Cookies for `domain` and its subdomains.
+    pub fn domain(domain: impl Into<String>) -> Self {
+        Self {
+            domain: Some(domain.into()),
+            since: None,
+        }
+    }
+
+    /// Cookies set at or after `since`.
+    pub fn since(since: SystemTime) -> Self {
+        Self {
+            domain: None,
+            since: Some(since),
+        }
+    }
+
+    /// Whether the filter selects `cookie`.
+    pub fn matches(&self, cookie: &Cookie) -> bool {
+        let domain_ok = self.domain.as_deref().is_none_or(|domain| {
+            domain_matches(&cookie.domain, &domain.trim_start_matches('.').to_ascii_lowercase())
+        });
+        domain_ok && self.since.is_none_or(|since| cookie.created >= since)
+    }
+}
+
+/// A cookie store, in memory or kept on disk.
+#[derive(Default)]
+pub struct CookieJar {
+    cookies: RwLock<Vec<Cookie>>,
+    journal: Option<Mutex<Journal>>,
+}
+
+impl CookieJar {
+    /// Create an empty jar that only keeps cookies in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the jar kept at `path`, creating it if there's none.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, NetError> {
+        let path = path.into();
+        let now = SystemTime::now();
+        let mut cookies: Vec<Cookie> = Vec::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    match serde_json::from_str::<JournalRecord>(&line?) {
+                        Ok(JournalRecord::Set(cookie)) => {
+                            let Some(cookie) = cookie.into_cookie() else {
+                                warn!(path = %path.display(), "Skipping cookie with a bad time");
+                                continue;
+                            };
+                            cookies.retain(|existing| !existing.same_as(&cookie));
+                            cookies.push(cookie);
+                        }
+                        Ok(JournalRecord::Remove { name, domain, path }) => {
+                            cookies.retain(|c| !(c.name == name && c.domain == domain && c.path == path));
+                        }
+                        // A line cut short by a crash
+                        Err(e) => warn!(path = %path.display(), error = %e, "Skipping bad cookie journal line"),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let journal = Journal::rewrite(&path, &cookies)?;
+        debug!(path = %path.display(), count = cookies.len(), "Cookie jar opened");
+        Ok(Self {
+            cookies: RwLock::new(cookies),
+            journal: Some(Mutex::new(journal)),
+        })
+    }
+
+    /// Store the cookies a response from `url` sets.
+    pub fn set_from_headers(&self, url: &Url, headers: &HeaderMap) {
+        let now = SystemTime::now();
+        for header in headers.get_all(http::header::SET_COOKIE) {
+            let Some(cookie) = header.to_str().ok().and_then(|header| Cookie::parse(header, url, now)) else {
+                continue;
+            };
+            self.store(cookie, false);
+        }
+    }
+
+    /// Store a cookie a script at `url` sets through `document.cookie`.
+    /// Scripts can't set `HttpOnly` cookies or replace them.
+    pub fn set_from_script(&self, url: &Url, cookie: &str) {
+        if let Some(cookie) = Cookie::parse(cookie, url, SystemTime::now()) {
+            if !cookie.http_only {
+                self.store(cookie, true);
+            }
+        }
+    }
+
+    /// The `Cookie` header for a request to `url`, if it carries any
+    /// cookies.
+    pub fn cookie_header(&self, url: &Url) -> Option<String> {
+        let header = self.serialize(url, true);
+        (!header.is_empty()).then_some(header)
+    }
+
+    /// What `document.cookie` reads for a document at `url`: its cookies
+    /// other than `HttpOnly` ones.
+    pub fn script_cookies(&self, url: &Url) -> String {
+        self.serialize(url, false)
+    }
+
+    /// All live cookies.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        self.cookies
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove the cookies `filter` selects. Returns how many were removed.
+    pub fn clear(&self, filter: &CookieFilter) -> usize {
+        let mut cookies = self.cookies.write().unwrap();
+        let (removed, kept): (Vec<_>, Vec<_>) = cookies.drain(..).partition(|cookie| filter.matches(cookie));
+        *cookies = kept;
+        if removed.iter().any(|cookie| cookie.expires.is_some()) {
+            self.journal(&cookies, |journal| {
+                removed
+                    .iter()
+                    .filter(|cookie| cookie.expires.is_some())
+                    .try_for_each(|cookie| journal.append(&JournalRecord::remove(cookie)))
+            });
+        }
+        debug!(?filter, removed = removed.len(), "Cookies cleared");
+        removed.len()
+    }
+
+    /// Add `cookie`, replacing the one it updates, or remove that one if
+    /// `cookie` has expired.
+    fn store(&self, mut cookie: Cookie, from_script: bool) {
+        let now = SystemTime::now();
+        let mut cookies = self.cookies.write().unwrap();
+        let existing = cookies.iter().position(|existing| existing.same_as(&cookie));
+        if let Some(index) = existing {
+            if from_script && cookies[index].http_only {
+                return;
+            }
+            let old = cookies.remove(index);
+            cookie.created = old.created;
+            if old.expires.is_some() && (cookie.expires.is_none() || cookie.is_expired(now)) {
+                self.journal(&cookies, |journal| journal.append(&JournalRecord::remove(&old)));
+            }
+        }
+        if cookie.is_expired(now) {
+            return;
+        }
+        if cookie.expires.is_some() {
+            self.journal(&cookies, |journal| journal.append(&JournalRecord::Set(PersistedCookie::from(&cookie))));
+        }
+        cookies.push(cookie);
+    }
+
+    /// Write to the journal, if the jar has one, rewriting it with `live`
+    /// once it has grown enough.
+    fn journal(&self, live: &[Cookie], write: impl FnOnce(&mut Journal) -> std::io::Result<()>) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let mut journal = journal.lock().unwrap();
+        let result = write(&mut journal).and_then(|()| {
+            if journal.records > live.len() + JOURNAL_SLACK {
+                *journal = Journal::rewrite(&journal.path, live)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            warn!(path = %journal.path.display(), error = %e, "Failed to write cookie journal");
+        }
+    }
+
+    /// `name=value` pairs of the cookies for `url`, longest path first.
+    fn serialize(&self, url: &Url, include_http_only: bool) -> String {
+        let now = SystemTime::now();
+        let cookies = self.cookies.read().unwrap();
+        let mut matching: Vec<&Cookie> = cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .filter(|cookie| include_http_only || !cookie.http_only)
+            .collect();
+        matching.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.created.cmp(&b.created)));
+        matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// The append-only file a jar keeps its persistent cookies in.
+struct Journal {
+    path: PathBuf,
+    file: BufWriter<File>,
+    /// Lines in the file.
+    records: usize,
+}
+
+impl Journal {
+    /// Replace the file at `path` with one holding the persistent cookies
+    /// among `cookies`, and open it for appending.
+    fn rewrite(path: &Path, cookies: &[Cookie]) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension("tmp");
+        let mut records = 0;
+        {
+            let mut file = BufWriter::new(File::create(&temp)?);
+            for cookie in cookies.iter().filter(|cookie| cookie.expires.is_some()) {
+                serde_json::to_writer(&mut file, &JournalRecord::Set(PersistedCookie::from(cookie)))?;
+                file.write_all(b"\n")?;
+                records += 1;
+            }
+            file.flush()?;
+        }
+        fs::rename(&temp, path)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            records,
+        })
+    }
+
+    /// Append a record and flush it to the file.
+    fn append(&mut self, record: &JournalRecord) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.file, record)?;
+        self.file.write_all(b"\n")?;
+        self.file.flush()?;
+        self.records += 1;
+        Ok(())
+    }
+}
+
+/// A line of the cookie journal.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum JournalRecord {
+    Set(PersistedCookie),
+    Remove { name: String, domain: String, path: String },
+}
+
+impl JournalRecord {
+    fn remove(cookie: &Cookie) -> Self {
+        JournalRecord::Remove {
+            name: cookie.name.clone(),
+            domain: cookie.domain.clone(),
+            path: cookie.path.clone(),
+        }
+    }
+}
+
+/// A cookie as the journal stores it, with times in seconds since the
+/// Unix epoch.
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedCookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    expires: u64,
+    secure: bool,
+    http_only: bool,
+    same_site: String,
+    created: u64,
+}
+
+impl From<&Cookie> for PersistedCookie {
+    fn from(cookie: &Cookie) -> Self {
+        let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        Self {
+            name: cookie.name.clone(),
+            value: cookie.value.clone(),
+            domain: cookie.domain.clone(),
+            host_only: cookie.host_only,
+            path: cookie.path.clone(),
+            expires: cookie.expires.map_or(0, seconds),
+            secure: cookie.secure,
+            http_only: cookie.http_only,
+            same_site: match cookie.same_site {
+                SameSite::Strict => "strict",
+                SameSite::Lax => "lax",
+                SameSite::None => "none",
+            }
+            .to_string(),
+            created: seconds(cookie.created),
+        }
+    }
+}
+
+impl PersistedCookie {
+    /// The cookie, or `None` if its times are out of range, as in a
+    /// corrupt journal.
+    fn into_cookie(self) -> Option<Cookie> {
+        let time = |seconds: u64| UNIX_EPOCH.checked_add(Duration::from_secs(seconds));
+        Some(Cookie {
+            name: self.name,
+            value: self.value,
+            domain: self.domain,
+            host_only: self.host_only,
+            path: self.path,
+            expires: Some(time(self.expires)?),
+            secure: self.secure,
+            http_only: self.http_only,
+            same_site: self.same_site.parse().unwrap_or_default(),
+            created: time(self.created)?,
+        })
+    }
+}
+
+/// Whether `host` is `domain` or one of its subdomains.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+/// Whether a request path falls under a cookie path.
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The path a cookie without `Path` gets: the directory of the URL's path.
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// Parse a cookie `Expires` date, as RFC 6265 reads them, in UTC.
+fn parse_cookie_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    let tokens = value
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+        if time.is_none() && token.contains(':') {
+            let fields: Vec<u32> = token.split(':').filter_map(|field| field.parse().ok()).collect();
+            if let [hours, minutes, seconds] = fields[..] {
+                time = Some((hours, minutes, seconds));
+                continue;
+            }
+        }
+        if day.is_none() && (1..=2).contains(&digits) {
+            day = token[..digits].parse::<u32>().ok();
+        } else if month.is_none() && token.len() >= 3 {
+            if let Some(index) = MONTHS.iter().position(|m| token[..3].eq_ignore_ascii_case(m)) {
+                month = Some(index as u32 + 1);
+                continue;
+            }
+            if year.is_none() && (2..=4).contains(&digits) {
+                year = token[..digits].parse::<i64>().ok();
+            }
+        } else if year.is_none() && (2..=4).contains(&digits) {
+            year = token[..digits].parse::<i64>().ok();
+        }
+    }
+
+    let (hours, minutes, seconds) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    // Two-digit years: 70-99 are 19xx, 00-69 are 20xx
+    year += match year {
+        70..=99 => 1900,
+        0..=69 => 2000,
+        _ => 0,
+    };
+    if year < 1601 || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    // Days since the epoch of a proleptic Gregorian date
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y.rem_euclid(400);
+    let day_of_year = (153 * m as i64 + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + (hours * 3600 + minutes * 60 + seconds) as i64;
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        Some(UNIX_EPOCH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn set(jar: &CookieJar, from: &str, cookies: &[&str]) {
+        let mut headers = HeaderMap::new();
+        for cookie in cookies {
+            headers.append(http::header::SET_COOKIE, HeaderValue::from_str(cookie).unwrap());
+        }
+        jar.set_from_headers(&url(from), &headers);
+    }
+
+    #[test]
+    fn test_parse_cookie_date() {
+        let expected = UNIX_EPOCH + Duration::from_secs(1_445_412_480);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), Some(expected));
+        assert_eq!(parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"), Some(expected));
+        assert_eq!(parse_cookie_date("Wed Oct 21 07:28:00 2015"), Some(expected));
+        assert_eq!(parse_cookie_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_domain_and_path_matching() {
+        let jar = CookieJar::new();
+        set(
+            &jar,
+            "https://www.example.com/app/page",
+            &[
+                "session=1",
+                "shared=2; Domain=.example.com; Path=/",
+                "docs=3; Path=/docs",
+                "evil=4; Domain=other.com",
+                "tld=5; Domain=com",
+            ],
+        );
+        set(
+            &jar,
+            "https://shop.example.co.uk/",
+            &["suffix=6; Domain=co.uk", "site=7; Domain=example.co.uk"],
+        );
+
+        assert_eq!(
+            jar.cookie_header(&url("https://www.example.com/app/settings")).as_deref(),
+            Some("session=1; shared=2")
+        );
+        assert_eq!(jar.cookie_header(&url("https://api.example.com/")).as_deref(), Some("shared=2"));
+        assert_eq!(
+            jar.cookie_header(&url("https://www.example.com/docs/intro")).as_deref(),
+            Some("docs=3; shared=2")
+        );
+        assert_eq!(jar.cookie_header(&url("https://www.example.com/docsearch")).as_deref(), Some("shared=2"));
+        assert_eq!(jar.cookie_header(&url("https://other.com/")), None);
+        assert_eq!(jar.cookie_header(&url("https://other.co.uk/")), None);
+        assert_eq!(jar.cookie_header(&url("https://www.example.co.uk/")).as_deref(), Some("site=7"));
+        assert_eq!(jar.cookies().len(), 4);
+    }
+
+    #[test]
+    fn test_secure_http_only_and_expiry() {
+        let jar = CookieJar::new();
+        set(&jar, "http://example.com/", &["insecure=1; Secure"]);
+        set(
+            &jar,
+            "https://example.com/",
+            &["token=1; Secure; HttpOnly", "theme=dark", "gone=1; Max-Age=0"],
+        );
+
+        assert_eq!(jar.cookie_header(&url("https://example.com/")).as_deref(), Some("token=1; theme=dark"));
+        assert_eq!(jar.cookie_header(&url("http://example.com/")).as_deref(), Some("theme=dark"));
+        assert_eq!(jar.script_cookies(&url("https://example.com/")), "theme=dark");
+
+        // Scripts can neither set nor replace HttpOnly cookies
+        jar.set_from_script(&url("https://example.com/"), "token=stolen");
+        jar.set_from_script(&url("https://example.com/"), "sneaky=1; HttpOnly");
+        jar.set_from_script(&url("https://example.com/"), "theme=light");
+        assert_eq!(jar.cookie_header(&url("https://example.com/")).as_deref(), Some("token=1; theme=light"));
+
+        // Setting a cookie again with an expiry in the past deletes it
+        set(&jar, "https://example.com/", &["theme=; Expires=Thu, 01 Jan 1970 00:00:00 GMT"]);
+        assert_eq!(jar.cookie_header(&url("https://example.com/")).as_deref(), Some("token=1"));
+
+        // Far-off expiries are clamped
+        set(
+            &jar,
+            "https://example.com/",
+            &["forever=1; Max-Age=9223372036854775807", "later=1; Expires=Fri, 31 Dec 9999 23:59:59 GMT"],
+        );
+        let latest = SystemTime::now() + MAX_COOKIE_AGE;
+        for name in ["forever", "later"] {
+            let cookie = jar.cookies().into_iter().find(|cookie| cookie.name == name).unwrap();
+            assert!(cookie.expires.unwrap() <= latest);
+        }
+    }
+
+    #[test]
+    fn test_clear_with_filter() {
+        let jar = CookieJar::new();
+        set(&jar, "https://a.example.com/", &["a=1"]);
+        set(&jar, "https://example.org/", &["b=1"]);
+        set(&jar, "https://c.example.org/", &["c=1"]);
+
+        assert_eq!(jar.clear(&CookieFilter::domain("example.org")), 2);
+        assert_eq!(jar.cookies().len(), 1);
+        assert_eq!(jar.clear(&CookieFilter::since(SystemTime::now() + Duration::from_secs(60))), 0);
+        assert_eq!(jar.clear(&CookieFilter::all()), 1);
+        assert!(jar.cookies().is_empty());
+    }
+
+    #[test]
+    fn test_persistent_cookies_survive_reopening() {
+        let dir = std::env::temp_dir().join(format!("rustkit-cookies-{}", std::process::id()));
+        let path = dir.join("cookies.jsonl");
+        let _ = fs::remove_file(&path);
+
+        {
+            let jar = CookieJar::open(&path).unwrap();
+            set(
+                &jar,
+                "https://example.com/",
+                &["session=1", "kept=1; Max-Age=3600", "replaced=old; Max-Age=3600", "dropped=1; Max-Age=3600"],
+            );
+            set(&jar, "https://example.com/", &["replaced=new; Max-Age=3600", "dropped=; Max-Age=0"]);
+        }
+
+        let jar = CookieJar::open(&path).unwrap();
+        assert_eq!(
+            jar.cookie_header(&url("https://example.com/")).as_deref(),
+            Some("kept=1; replaced=new")
+        );
+        // Opening compacts the journal to the live cookies
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        // Out-of-range times in a corrupt journal drop the cookie
+        let corrupt = PersistedCookie {
+            name: "corrupt".to_string(),
+            expires: u64::MAX,
+            ..PersistedCookie::from(&jar.cookies()[0])
+        };
+        let mut journal = fs::read_to_string(&path).unwrap();
+        journal.push_str(&serde_json::to_string(&JournalRecord::Set(corrupt)).unwrap());
+        fs::write(&path, journal).unwrap();
+        let jar = CookieJar::open(&path).unwrap();
+        assert_eq!(jar.cookies().len(), 2);
+
+        jar.clear(&CookieFilter::all());
+        drop(jar);
+        assert!(CookieJar::open(&path).unwrap().cookies().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -3,12 +3,13 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use rustkit_http::Client as HttpClient;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, trace};
 
 use crate::{NetError, Request};
@@ -134,6 +135,8 @@ impl Download {
 pub struct DownloadManager {
     downloads: RwLock<HashMap<DownloadId, Download>>,
     event_tx: RwLock<Option<mpsc::UnboundedSender<DownloadEvent>>>,
+    /// Streaming tasks of started downloads.
+    tasks: Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl DownloadManager {
@@ -142,6 +145,7 @@ impl DownloadManager {
         Self {
             downloads: RwLock::new(HashMap::new()),
             event_tx: RwLock::new(None),
+            tasks: Mutex::new(Vec::new()),
         }
     }
 
@@ -198,7 +202,7 @@ impl DownloadManager {
 
         // For downloads, we use the streaming API
         let url_str = request.url.to_string();
-        tokio::spawn(async move {
+        let task = tokio::spawn(async move {
             let result = Self::download_file_streaming(
                 id,
                 &url_str,
@@ -234,6 +238,10 @@ impl DownloadManager {
             }
         });
 
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task);
+
         Ok(id)
     }
 
@@ -356,6 +364,37 @@ impl DownloadManager {
             .collect()
     }
 
+    /// Cancel all in-progress downloads and wait for their tasks to end.
+    ///
+    /// Cancelled downloads emit `DownloadEvent::Cancelled` as usual.
+    pub async fn shutdown(&self) {
+        let in_progress: Vec<DownloadId> = self
+            .downloads
+            .read()
+            .await
+            .iter()
+            .filter(|(_, d)| d.state == DownloadState::InProgress)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in in_progress {
+            let _ = self.cancel(id).await;
+        }
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        debug!(count = tasks.len(), "Waiting for download tasks");
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+
+    /// Stop all download tasks immediately, without waiting or emitting
+    /// events. For teardown paths that can't await `shutdown()`.
+    pub fn abort_all(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+
     /// Remove completed/failed/cancelled downloads.
     pub async fn cleanup(&self) {
         let mut downloads = self.downloads.write().await;
@@ -414,4 +453,22 @@ mod tests {
         let list = manager.list().await;
         assert!(list.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_download_manager_shutdown_joins_tasks() {
+        let manager = DownloadManager::new();
+        let client = HttpClient::new().unwrap();
+        let request = Request::get(url::Url::parse("http://127.0.0.1:9/file.bin").unwrap());
+        let destination = std::env::temp_dir().join("rustkit-download-shutdown-test.bin");
+        manager.start(request, destination, &client).await.unwrap();
+        assert_eq!(manager.tasks.lock().unwrap().len(), 1);
+
+        manager.shutdown().await;
+        assert!(manager.tasks.lock().unwrap().is_empty());
+        assert!(manager
+            .list()
+            .await
+            .iter()
+            .all(|(_, state, _)| *state != DownloadState::InProgress));
+    }
 }
@@ -245,6 +245,8 @@ mod tests {
             timeout: None,
             credentials: Default::default(),
             referrer: None,
+            mode: Default::default(),
+            origin: None,
         }
     }
 
@@ -0,0 +1,153 @@
+//! Keepalive requests for RustKit.
+//!
+//! `navigator.sendBeacon()` and `fetch(..., { keepalive: true })` send
+//! small requests, typically analytics, that must go out even though the
+//! page making them is going away. The loader owns them rather than the
+//! page: they're spawned detached from any view, and their bodies share a
+//! quota of [`MAX_KEEPALIVE_BYTES`] in flight, as in browsers.
+//!
+//! Requests made outside of an async runtime wait until
+//! [`ResourceLoader::flush_keepalive`] sends them.
+//!
+//! [`ResourceLoader::flush_keepalive`]: crate::ResourceLoader::flush_keepalive
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::{NetError, Request, ResourceLoader, Response};
+
+/// Most body bytes keepalive requests may have in flight at once.
+pub const MAX_KEEPALIVE_BYTES: usize = 64 * 1024;
+
+/// Keepalive requests a loader hasn't finished.
+#[derive(Default)]
+pub(crate) struct KeepaliveQueue {
+    /// Body bytes of the requests not yet finished.
+    bytes_in_flight: Mutex<usize>,
+    /// Requests waiting for an async runtime to send them on.
+    pending: Mutex<VecDeque<Request>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl KeepaliveQueue {
+    /// Count a request's body against the quota, if it fits.
+    fn reserve(&self, bytes: usize) -> Result<(), NetError> {
+        let mut in_flight = self.bytes_in_flight.lock().unwrap();
+        if *in_flight + bytes > MAX_KEEPALIVE_BYTES {
+            return Err(NetError::QuotaExceeded);
+        }
+        *in_flight += bytes;
+        Ok(())
+    }
+
+    fn release(&self, bytes: usize) {
+        *self.bytes_in_flight.lock().unwrap() -= bytes;
+    }
+}
+
+impl ResourceLoader {
+    /// Send a keepalive request, which goes out even if the page that made
+    /// it is destroyed. Fails if its body doesn't fit in what remains of
+    /// the quota; the response is discarded.
+    pub fn send_keepalive(self: &Arc<Self>, request: Request) -> Result<(), NetError> {
+        let bytes = body_len(&request);
+        self.keepalive.reserve(bytes)?;
+        debug!(url = %request.url, bytes, "Keepalive request queued");
+        if tokio::runtime::Handle::try_current().is_ok() {
+            self.spawn_keepalive(request);
+        } else {
+            self.keepalive.pending.lock().unwrap().push_back(request);
+        }
+        Ok(())
+    }
+
+    /// Fetch a keepalive request and return its response, for
+    /// `fetch(..., { keepalive: true })`. Its body counts against the quota
+    /// while it's in flight.
+    pub async fn fetch_keepalive(&self, request: Request) -> Result<Response, NetError> {
+        let bytes = body_len(&request);
+        self.keepalive.reserve(bytes)?;
+        let result = self.fetch(request).await;
+        self.keepalive.release(bytes);
+        result
+    }
+
+    /// Send any keepalive requests still waiting and wait for all of them
+    /// to finish.
+    pub async fn flush_keepalive(self: &Arc<Self>) {
+        let pending = std::mem::take(&mut *self.keepalive.pending.lock().unwrap());
+        for request in pending {
+            self.spawn_keepalive(request);
+        }
+        let tasks = std::mem::take(&mut *self.keepalive.tasks.lock().unwrap());
+        debug!(count = tasks.len(), "Waiting for keepalive requests");
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+
+    /// Body bytes of the keepalive requests not yet finished.
+    pub fn keepalive_bytes_in_flight(&self) -> usize {
+        *self.keepalive.bytes_in_flight.lock().unwrap()
+    }
+
+    fn spawn_keepalive(self: &Arc<Self>, request: Request) {
+        let loader = Arc::clone(self);
+        let task = tokio::spawn(async move {
+            let bytes = body_len(&request);
+            let url = request.url.clone();
+            match loader.fetch(request).await {
+                Ok(response) => debug!(url = %url, status = %response.status, "Keepalive request sent"),
+                Err(e) => warn!(url = %url, error = %e, "Keepalive request failed"),
+            }
+            loader.keepalive.release(bytes);
+        });
+        let mut tasks = self.keepalive.tasks.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task);
+    }
+}
+
+fn body_len(request: &Request) -> usize {
+    request.body.as_ref().map_or(0, |body| body.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LoaderConfig;
+    use bytes::Bytes;
+    use url::Url;
+
+    #[tokio::test]
+    async fn test_keepalive_quota_and_flush() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/collect"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let loader = Arc::new(ResourceLoader::new(LoaderConfig::default()).unwrap());
+        let url = Url::parse(&format!("{}/collect", server.uri())).unwrap();
+        let beacon = |bytes: usize| Request::post(url.clone(), Bytes::from(vec![b'x'; bytes]));
+
+        loader.send_keepalive(beacon(40 * 1024)).unwrap();
+        assert!(matches!(
+            loader.send_keepalive(beacon(30 * 1024)),
+            Err(NetError::QuotaExceeded)
+        ));
+        loader.send_keepalive(beacon(1024)).unwrap();
+
+        loader.flush_keepalive().await;
+        assert_eq!(loader.keepalive_bytes_in_flight(), 0);
+        server.verify().await;
+    }
+}
@@ -25,13 +25,20 @@ use tracing::{debug, info, trace, warn};
 use url::Url;
 
 pub mod cache;
+pub mod cookies;
 pub mod download;
 pub mod intercept;
+pub mod keepalive;
+pub mod replay;
 pub mod security;
 
 pub use cache::{CacheConfig, CacheKey, CacheStats, CachedResponse, MemoryCache, parse_cache_control};
+pub use cookies::{Cookie, CookieFilter, CookieJar};
 pub use download::{Download, DownloadEvent, DownloadId, DownloadManager, DownloadState};
 pub use intercept::{InterceptAction, InterceptHandler, RequestInterceptor};
+pub use keepalive::MAX_KEEPALIVE_BYTES;
+pub use replay::NetworkReplay;
+pub use rustkit_http::HttpError;
 pub use security::{
     check_mixed_content, ContentSecurityPolicy, CookieAttributes, CorsChecker, CorsResult,
     CspDirective, CspSource, HashAlgorithm, MixedContentResult, MixedContentType, Origin,
@@ -56,11 +63,17 @@ pub enum NetError {
     #[error("Request blocked")]
     Blocked,
 
+    #[error("Keepalive quota exceeded")]
+    QuotaExceeded,
+
+    #[error("Cross-origin request refused: {0}")]
+    CorsError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
     #[error("HTTP error: {0}")]
-    HttpError(#[from] rustkit_http::HttpError),
+    HttpError(#[from] HttpError),
 }
 
 /// Unique identifier for a request.
@@ -95,6 +108,11 @@ pub struct Request {
     pub timeout: Option<Duration>,
     pub credentials: CredentialsMode,
     pub referrer: Option<Url>,
+    /// How the request may reach other origins than `origin`'s.
+    pub mode: RequestMode,
+    /// Origin of the page that made the request; `None` for requests the
+    /// engine makes itself, which no origin checks apply to.
+    pub origin: Option<Origin>,
 }
 
 impl Request {
@@ -109,6 +127,8 @@ impl Request {
             timeout: Some(Duration::from_secs(30)),
             credentials: CredentialsMode::SameOrigin,
             referrer: None,
+            mode: RequestMode::NoCors,
+            origin: None,
         }
     }
 
@@ -123,6 +143,25 @@ impl Request {
             timeout: Some(Duration::from_secs(30)),
             credentials: CredentialsMode::SameOrigin,
             referrer: None,
+            mode: RequestMode::NoCors,
+            origin: None,
+        }
+    }
+
+    /// Whether the request goes to another origin than the page that made
+    /// it.
+    pub fn is_cross_origin(&self) -> bool {
+        self.origin
+            .as_ref()
+            .is_some_and(|origin| !origin.same_origin(&Origin::from_url(&self.url)))
+    }
+
+    /// Whether the request carries cookies, by its credentials mode.
+    fn sends_credentials(&self) -> bool {
+        match self.credentials {
+            CredentialsMode::Omit => false,
+            CredentialsMode::SameOrigin => !self.is_cross_origin(),
+            CredentialsMode::Include => true,
         }
     }
 
@@ -143,6 +182,14 @@ impl Request {
         self.referrer = Some(referrer);
         self
     }
+
+    /// Override the loader's user agent for this request.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        if let Ok(value) = HeaderValue::try_from(user_agent) {
+            self.headers.insert(http::header::USER_AGENT, value);
+        }
+        self
+    }
 }
 
 /// Credentials mode for requests.
@@ -157,6 +204,18 @@ pub enum CredentialsMode {
     Include,
 }
 
+/// Request mode: how a page's request may reach other origins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequestMode {
+    /// Only the page's own origin; other origins fail.
+    SameOrigin,
+    /// Other origins must allow it with CORS headers.
+    Cors,
+    /// Other origins are reached, but the page can't read their responses.
+    #[default]
+    NoCors,
+}
+
 /// Redirect handling mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum RedirectMode {
@@ -312,6 +371,16 @@ pub struct Response {
     pub headers: HeaderMap,
     pub content_type: Option<Mime>,
     pub content_length: Option<u64>,
+    /// Redirects followed on the way to `url`.
+    pub redirects: RedirectChain,
+    /// Body bytes received over the network, before any
+    /// `Content-Encoding` was undone, including those of the redirects
+    /// followed; 0 for a response served from the cache.
+    pub encoded_size: u64,
+    /// Body bytes after decoding.
+    pub decoded_size: u64,
+    /// Whether the response was served from the HTTP cache.
+    pub from_cache: bool,
     body: ResponseBody,
 }
 
@@ -395,10 +464,14 @@ pub struct LoaderConfig {
     pub accept_language: String,
     /// Default timeout.
     pub default_timeout: Duration,
-    /// Maximum redirects.
+    /// Maximum redirects followed for one fetch.
     pub max_redirects: usize,
     /// Enable cookies.
     pub cookies_enabled: bool,
+    /// File persistent cookies are kept in; `None` keeps them in memory.
+    pub cookie_store_path: Option<PathBuf>,
+    /// Record responses to disk or replay them from there (see [`replay`]).
+    pub replay: Option<NetworkReplay>,
 }
 
 impl Default for LoaderConfig {
@@ -409,10 +482,54 @@ impl Default for LoaderConfig {
             default_timeout: Duration::from_secs(30),
             max_redirects: 10,
             cookies_enabled: true,
+            cookie_store_path: None,
+            replay: None,
         }
     }
 }
 
+/// Check that a response from another origin lets `origin` read it.
+fn check_cors(origin: &Origin, headers: &HeaderMap, with_credentials: bool) -> Result<(), NetError> {
+    let header = |name: HeaderName| headers.get(name).and_then(|value| value.to_str().ok());
+    match CorsChecker::new().check_response(
+        &origin.serialize(),
+        header(http::header::ACCESS_CONTROL_ALLOW_ORIGIN),
+        header(http::header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+        with_credentials,
+    ) {
+        CorsResult::Allowed => Ok(()),
+        CorsResult::Denied(reason) => Err(NetError::CorsError(reason)),
+        CorsResult::PreflightRequired => Err(NetError::CorsError("Preflight required".into())),
+    }
+}
+
+/// Undo a response body's `Content-Encoding`. Bodies in other encodings
+/// are passed through as received.
+fn decode_body(headers: &HeaderMap, body: Bytes) -> Result<Bytes, NetError> {
+    use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+    use std::io::Read;
+
+    let encoding = headers
+        .get(http::header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase());
+    if body.is_empty() {
+        return Ok(body);
+    }
+    let mut decoded = Vec::new();
+    let result = match encoding.as_deref() {
+        Some("gzip" | "x-gzip") => GzDecoder::new(&body[..]).read_to_end(&mut decoded),
+        // Some servers send raw deflate data rather than the zlib format
+        Some("deflate") => ZlibDecoder::new(&body[..]).read_to_end(&mut decoded).or_else(|_| {
+            decoded.clear();
+            DeflateDecoder::new(&body[..]).read_to_end(&mut decoded)
+        }),
+        _ => return Ok(body),
+    };
+    result.map_err(|e| NetError::RequestFailed(format!("Couldn't decode the body: {e}")))?;
+    Ok(decoded.into())
+}
+
 /// Resource loader for fetching URLs.
 pub struct ResourceLoader {
     client: HttpClient,
@@ -420,6 +537,8 @@ pub struct ResourceLoader {
     interceptor: Option<Arc<RwLock<RequestInterceptor>>>,
     download_manager: Arc<DownloadManager>,
     cache: Arc<MemoryCache>,
+    cookies: Arc<CookieJar>,
+    keepalive: keepalive::KeepaliveQueue,
 }
 
 impl ResourceLoader {
@@ -436,11 +555,17 @@ impl ResourceLoader {
         let client = HttpClient::builder()
             .user_agent(&config.user_agent)
             .timeout(config.default_timeout)
-            .redirect(true, config.max_redirects)
+            // Followed by fetch(), which records each one
+            .redirect(false, config.max_redirects)
             .cookie_store(config.cookies_enabled)
             .build()
             .map_err(|e| NetError::RequestFailed(e.to_string()))?;
 
+        let cookies = match &config.cookie_store_path {
+            Some(path) if config.cookies_enabled => CookieJar::open(path)?,
+            _ => CookieJar::new(),
+        };
+
         if interceptor.is_some() {
             info!("ResourceLoader initialized with request interceptor and cache");
         } else {
@@ -453,6 +578,8 @@ impl ResourceLoader {
             interceptor: interceptor.map(|i| Arc::new(RwLock::new(i))),
             download_manager: Arc::new(DownloadManager::new()),
             cache: Arc::new(MemoryCache::new()),
+            cookies: Arc::new(cookies),
+            keepalive: keepalive::KeepaliveQueue::default(),
         })
     }
     
@@ -461,6 +588,11 @@ impl ResourceLoader {
         &self.cache
     }
     
+    /// Get the cookie jar.
+    pub fn cookies(&self) -> &Arc<CookieJar> {
+        &self.cookies
+    }
+
     /// Get cache statistics.
     pub fn cache_stats(&self) -> CacheStats {
         self.cache.stats()
@@ -481,8 +613,145 @@ impl ResourceLoader {
         &self.client
     }
 
-    /// Fetch a URL.
+    /// Fetch a URL, following up to `max_redirects` redirects. The response
+    /// lists the redirects it took in [`Response::redirects`].
+    ///
+    /// Every request the redirects lead to is checked against the
+    /// request's origin and mode, and once one has gone to another origin,
+    /// those after it count as cross-origin too.
     pub async fn fetch(&self, request: Request) -> Result<Response, NetError> {
+        self.fetch_following_redirects(request).await
+    }
+
+    /// Fetch a URL without following redirects, if the request's origin
+    /// may reach it by the request's mode: a cross-origin `same-origin`
+    /// request fails, and a cross-origin `cors` one is preflighted if it
+    /// isn't simple, then fails unless the response allows the origin.
+    async fn fetch_checked(&self, request: &Request, cross_origin: bool) -> Result<Response, NetError> {
+        let origin = match &request.origin {
+            Some(origin) if cross_origin => origin.clone(),
+            _ => return self.fetch_once(request.clone()).await,
+        };
+        match request.mode {
+            RequestMode::SameOrigin => {
+                return Err(NetError::CorsError(format!("{} isn't same-origin", request.url)));
+            }
+            RequestMode::NoCors => return self.fetch_once(request.clone()).await,
+            RequestMode::Cors => {}
+        }
+
+        self.preflight(request, &origin).await?;
+        let mut request = request.clone();
+        if let Ok(value) = HeaderValue::try_from(origin.serialize()) {
+            request.headers.insert(http::header::ORIGIN, value);
+        }
+        let with_credentials = request.credentials == CredentialsMode::Include;
+        let response = self.fetch_once(request).await?;
+        check_cors(&origin, &response.headers, with_credentials)?;
+        Ok(response)
+    }
+
+    /// Ask another origin whether a CORS request that isn't simple may be
+    /// made, with an `OPTIONS` preflight request.
+    async fn preflight(&self, request: &Request, origin: &Origin) -> Result<(), NetError> {
+        let headers: Vec<(&str, &str)> = request
+            .headers
+            .iter()
+            .filter_map(|(name, value)| value.to_str().ok().map(|value| (name.as_str(), value)))
+            .collect();
+        if CorsChecker::is_simple_request(request.method.as_str(), &headers) {
+            return Ok(());
+        }
+        debug!(url = %request.url, method = %request.method, "Sending CORS preflight");
+
+        let mut preflight = Request::get(request.url.clone());
+        preflight.method = Method::OPTIONS;
+        preflight.credentials = CredentialsMode::Omit;
+        preflight.referrer = request.referrer.clone();
+        let names: Vec<&str> = headers.iter().map(|(name, _)| *name).collect();
+        for (name, value) in [
+            (http::header::ORIGIN, origin.serialize()),
+            (http::header::ACCESS_CONTROL_REQUEST_METHOD, request.method.to_string()),
+            (http::header::ACCESS_CONTROL_REQUEST_HEADERS, names.join(",")),
+        ] {
+            if let Ok(value) = HeaderValue::try_from(value) {
+                preflight.headers.insert(name, value);
+            }
+        }
+
+        let response = self.fetch_once(preflight).await?;
+        if !response.status.is_success() {
+            return Err(NetError::CorsError(format!("Preflight answered {}", response.status)));
+        }
+        check_cors(origin, &response.headers, request.credentials == CredentialsMode::Include)?;
+
+        let header = |name: HeaderName| response.headers.get(name).and_then(|value| value.to_str().ok());
+        let mut checker = CorsChecker::new();
+        checker.parse_preflight_response(
+            header(http::header::ACCESS_CONTROL_ALLOW_METHODS),
+            header(http::header::ACCESS_CONTROL_ALLOW_HEADERS),
+            header(http::header::ACCESS_CONTROL_MAX_AGE),
+        );
+        if !checker.is_method_allowed(request.method.as_str()) {
+            return Err(NetError::CorsError(format!("Method {} not allowed", request.method)));
+        }
+        if let Some(name) = names.iter().find(|name| !checker.is_header_allowed(name)) {
+            return Err(NetError::CorsError(format!("Header {name} not allowed")));
+        }
+        Ok(())
+    }
+
+    /// Fetch a request, following redirects, checking each request against
+    /// the request's origin.
+    async fn fetch_following_redirects(&self, mut request: Request) -> Result<Response, NetError> {
+        let mut redirects = RedirectChain::with_max(self.config.max_redirects);
+        let mut encoded_size = 0;
+        let mut cross_origin = false;
+        loop {
+            cross_origin |= request.is_cross_origin();
+            let mut response = self.fetch_checked(&request, cross_origin).await?;
+            let Some(redirect_type) = RedirectType::from_status(response.status) else {
+                response.redirects = redirects;
+                response.encoded_size += encoded_size;
+                return Ok(response);
+            };
+            let Some(mut to_url) = response
+                .headers
+                .get(http::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url.join(location).ok())
+            else {
+                // A redirect with nowhere to go is the response
+                response.redirects = redirects;
+                response.encoded_size += encoded_size;
+                return Ok(response);
+            };
+            encoded_size += response.encoded_size;
+            // The fragment carries over unless the new location has its own
+            if to_url.fragment().is_none() {
+                to_url.set_fragment(request.url.fragment());
+            }
+
+            let method_changed = !redirect_type.preserves_method()
+                && request.method != Method::GET
+                && request.method != Method::HEAD;
+            debug!(from = %response.url, to = %to_url, status = %response.status, "Following redirect");
+            redirects.add(RedirectInfo {
+                from_url: request.url.clone(),
+                to_url: to_url.clone(),
+                redirect_type,
+                method_changed,
+            })?;
+            request.url = to_url;
+            if method_changed {
+                request.method = Method::GET;
+                request.body = None;
+            }
+        }
+    }
+
+    /// Fetch a URL without following redirects.
+    async fn fetch_once(&self, request: Request) -> Result<Response, NetError> {
         debug!(url = %request.url, method = %request.method, "Fetching resource");
 
         // Apply interception
@@ -498,10 +767,10 @@ impl ResourceLoader {
                     debug!(url = %request.url, new_url = %new_url, "Request redirected");
                     let mut new_request = request.clone();
                     new_request.url = new_url;
-                    return Box::pin(self.fetch(new_request)).await;
+                    return Box::pin(self.fetch_once(new_request)).await;
                 }
                 InterceptAction::Modify(modified) => {
-                    return Box::pin(self.fetch(*modified)).await;
+                    return Box::pin(self.fetch_once(*modified)).await;
                 }
             }
         }
@@ -525,6 +794,10 @@ impl ResourceLoader {
                     headers: cached.headers,
                     content_type,
                     content_length: Some(cached.body.len() as u64),
+                    redirects: RedirectChain::default(),
+                    encoded_size: 0,
+                    decoded_size: cached.body.len() as u64,
+                    from_cache: true,
                     body: ResponseBody::Full(cached.body),
                 });
             }
@@ -536,9 +809,16 @@ impl ResourceLoader {
         // Build headers for rustkit-http request
         let mut headers = request.headers.clone();
 
-        // Add Accept-Language
-        if let Ok(val) = HeaderValue::try_from(&self.config.accept_language) {
-            headers.insert(HeaderName::from_static("accept-language"), val);
+        // Offer the encodings decode_body() undoes
+        if !headers.contains_key(http::header::ACCEPT_ENCODING) {
+            headers.insert(http::header::ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate"));
+        }
+
+        // Add Accept-Language, unless the request asks for its own
+        if !headers.contains_key(http::header::ACCEPT_LANGUAGE) {
+            if let Ok(val) = HeaderValue::try_from(&self.config.accept_language) {
+                headers.insert(HeaderName::from_static("accept-language"), val);
+            }
         }
 
         // Add referrer
@@ -548,19 +828,40 @@ impl ResourceLoader {
             }
         }
 
-        // Execute request using rustkit-http
-        let http_response = self
-            .client
-            .request(
-                request.method.clone(),
-                request.url.as_str(),
-                headers,
-                request.body.clone(),
-            )
-            .await?;
+        // Add cookies
+        let send_cookies = self.config.cookies_enabled && request.sends_credentials();
+        if send_cookies {
+            if let Some(val) = self.cookies.cookie_header(&request.url).and_then(|c| HeaderValue::try_from(c).ok()) {
+                headers.insert(http::header::COOKIE, val);
+            }
+        }
+
+        // Execute request using rustkit-http, or answer it from a recording
+        let http_response = match &self.config.replay {
+            Some(NetworkReplay::Replay(dir)) => replay::replay(dir, &request).await?,
+            mode => {
+                let http_response = self
+                    .client
+                    .request(
+                        request.method.clone(),
+                        request.url.as_str(),
+                        headers,
+                        request.body.clone(),
+                    )
+                    .await?;
+                if let Some(NetworkReplay::Record(dir)) = mode {
+                    replay::record(dir, &request, &http_response).await;
+                }
+                http_response
+            }
+        };
 
         let url = http_response.url.clone();
 
+        if send_cookies {
+            self.cookies.set_from_headers(&url, &http_response.headers);
+        }
+
         // Parse content type
         let content_type = http_response
             .content_type()
@@ -569,12 +870,16 @@ impl ResourceLoader {
         // Get content length
         let content_length = http_response.content_length();
 
+        let encoded_size = http_response.body.len() as u64;
+        let body = decode_body(&http_response.headers, http_response.body)?;
+
         trace!(
             url = %url,
             status = %http_response.status,
             content_type = ?content_type,
             content_length = ?content_length,
-            body_len = http_response.body.len(),
+            encoded_size,
+            decoded_size = body.len(),
             "Response received"
         );
         
@@ -591,10 +896,10 @@ impl ResourceLoader {
                     let cached = CachedResponse {
                         status: http_response.status,
                         headers: http_response.headers.clone(),
-                        body: http_response.body.clone(),
+                        body: body.clone(),
                         cached_at: Instant::now(),
                         expires_at: Instant::now() + ttl,
-                        size: http_response.body.len(),
+                        size: body.len(),
                     };
                     self.cache.put(key, cached);
                 }
@@ -608,7 +913,11 @@ impl ResourceLoader {
             headers: http_response.headers,
             content_type,
             content_length,
-            body: ResponseBody::Full(http_response.body),
+            redirects: RedirectChain::default(),
+            encoded_size,
+            decoded_size: body.len() as u64,
+            from_cache: false,
+            body: ResponseBody::Full(body),
         })
     }
 
@@ -727,4 +1036,231 @@ mod tests {
         assert_eq!(config.user_agent, "RustKit/1.0");
         assert!(config.cookies_enabled);
     }
+
+    #[tokio::test]
+    async fn test_fetch_follows_redirects() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let redirect = |status: u16, location: &str| {
+            ResponseTemplate::new(status).insert_header("location", location)
+        };
+        Mock::given(path("/old")).respond_with(redirect(301, "/moved")).mount(&server).await;
+        Mock::given(path("/moved")).respond_with(redirect(307, "new")).mount(&server).await;
+        Mock::given(path("/new")).respond_with(ResponseTemplate::new(200).set_body_string("done")).mount(&server).await;
+        Mock::given(path("/loop")).respond_with(redirect(302, "/loop")).mount(&server).await;
+
+        let loader = ResourceLoader::new(LoaderConfig::default()).unwrap();
+        let url = |p: &str| Url::parse(&format!("{}{p}", server.uri())).unwrap();
+
+        let response = loader.fetch(Request::get(url("/old#top"))).await.unwrap();
+        assert!(response.ok());
+        assert_eq!(response.url, url("/new#top"));
+        let hops: Vec<_> = response
+            .redirects
+            .redirects
+            .iter()
+            .map(|r| (r.from_url.path(), r.to_url.path(), r.redirect_type.status_code()))
+            .collect();
+        assert_eq!(hops, [("/old", "/moved", 301), ("/moved", "/new", 307)]);
+        assert_eq!(response.text().await.unwrap(), "done");
+
+        assert!(loader.fetch(Request::get(url("/loop"))).await.is_err());
+
+        // Past the maximum, the fetch fails
+        let config = LoaderConfig { max_redirects: 1, ..Default::default() };
+        let loader = ResourceLoader::new(config).unwrap();
+        assert!(loader.fetch(Request::get(url("/old"))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_decodes_and_measures_bodies() {
+        use flate2::write::{GzEncoder, ZlibEncoder};
+        use flate2::Compression;
+        use std::io::Write;
+        use wiremock::matchers::{header_exists, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let text = "compressible ".repeat(100);
+        let gzip = {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let zlib = {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(path("/gzip"))
+            .and(header_exists("accept-encoding"))
+            .respond_with(ResponseTemplate::new(200).insert_header("content-encoding", "gzip").set_body_bytes(gzip.clone()))
+            .mount(&server)
+            .await;
+        Mock::given(path("/deflate"))
+            .respond_with(ResponseTemplate::new(200).insert_header("content-encoding", "deflate").set_body_bytes(zlib))
+            .mount(&server)
+            .await;
+        Mock::given(path("/old"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/plain").set_body_string("moved"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/plain")).respond_with(ResponseTemplate::new(200).set_body_string("plain")).mount(&server).await;
+
+        let loader = ResourceLoader::new(LoaderConfig::default()).unwrap();
+        let url = |p: &str| Url::parse(&format!("{}{p}", server.uri())).unwrap();
+
+        let response = loader.fetch(Request::get(url("/gzip"))).await.unwrap();
+        assert_eq!((response.encoded_size, response.decoded_size), (gzip.len() as u64, text.len() as u64));
+        assert!(response.encoded_size < response.decoded_size);
+        assert_eq!(response.text().await.unwrap(), text);
+
+        let response = loader.fetch(Request::get(url("/deflate"))).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), text);
+
+        // Redirects count towards the bytes received
+        let response = loader.fetch(Request::get(url("/old"))).await.unwrap();
+        assert_eq!((response.encoded_size, response.decoded_size), (10, 5));
+
+        // Nothing is received for a response served from the cache
+        let response = loader.fetch(Request::get(url("/gzip"))).await.unwrap();
+        assert_eq!((response.encoded_size, response.decoded_size), (0, text.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sends_and_stores_cookies() {
+        use wiremock::matchers::{header, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/login"))
+            .respond_with(ResponseTemplate::new(200).insert_header("set-cookie", "sid=abc; Path=/; HttpOnly"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/account"))
+            .and(header("cookie", "sid=abc"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(path("/account")).respond_with(ResponseTemplate::new(401)).mount(&server).await;
+
+        let loader = ResourceLoader::new(LoaderConfig::default()).unwrap();
+        let url = |p: &str| Url::parse(&format!("{}{p}", server.uri())).unwrap();
+
+        loader.fetch(Request::get(url("/login"))).await.unwrap();
+        assert_eq!(loader.fetch(Request::get(url("/account"))).await.unwrap().status, 200);
+
+        // Distinct queries keep the responses out of the cache
+        let mut anonymous = Request::get(url("/account?anonymous"));
+        anonymous.credentials = CredentialsMode::Omit;
+        assert_eq!(loader.fetch(anonymous).await.unwrap().status, 401);
+
+        assert_eq!(loader.cookies().clear(&CookieFilter::all()), 1);
+        assert_eq!(loader.fetch(Request::get(url("/account?cleared"))).await.unwrap().status, 401);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_enforces_origin_checks() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/private")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        Mock::given(path("/public"))
+            .respond_with(ResponseTemplate::new(200).insert_header("access-control-allow-origin", "*"))
+            .mount(&server)
+            .await;
+        Mock::given(method("OPTIONS"))
+            .and(path("/api"))
+            .and(header("access-control-request-method", "PUT"))
+            .respond_with(
+                ResponseTemplate::new(204)
+                    .insert_header("access-control-allow-origin", "https://app.example")
+                    .insert_header("access-control-allow-methods", "GET, PUT")
+                    .insert_header("access-control-allow-headers", "x-token"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/api"))
+            .and(header("origin", "https://app.example"))
+            .respond_with(ResponseTemplate::new(200).insert_header("access-control-allow-origin", "https://app.example"))
+            .mount(&server)
+            .await;
+
+        let loader = ResourceLoader::new(LoaderConfig::default()).unwrap();
+        let page_origin = Origin::from_url(&Url::parse("https://app.example/").unwrap());
+        let request = |p: &str, mode: RequestMode| {
+            let mut request = Request::get(Url::parse(&format!("{}{p}", server.uri())).unwrap());
+            request.origin = Some(page_origin.clone());
+            request.mode = mode;
+            request
+        };
+
+        assert!(matches!(
+            loader.fetch(request("/private", RequestMode::Cors)).await,
+            Err(NetError::CorsError(_))
+        ));
+        assert!(matches!(
+            loader.fetch(request("/public", RequestMode::SameOrigin)).await,
+            Err(NetError::CorsError(_))
+        ));
+        assert!(loader.fetch(request("/public", RequestMode::Cors)).await.unwrap().ok());
+        assert!(loader.fetch(request("/private", RequestMode::NoCors)).await.unwrap().ok());
+
+        // A request that isn't simple goes out once the preflight allows it
+        let mut put = request("/api", RequestMode::Cors);
+        put.method = Method::PUT;
+        put.headers.insert("x-token", HeaderValue::from_static("secret"));
+        assert!(loader.fetch(put.clone()).await.unwrap().ok());
+        put.headers.insert("x-other", HeaderValue::from_static("1"));
+        assert!(matches!(loader.fetch(put).await, Err(NetError::CorsError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_checked_against_origin() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let page = MockServer::start().await;
+        let other = MockServer::start().await;
+        Mock::given(path("/private")).respond_with(ResponseTemplate::new(200)).mount(&other).await;
+        Mock::given(path("/public"))
+            .respond_with(ResponseTemplate::new(200).insert_header("access-control-allow-origin", "*"))
+            .mount(&other)
+            .await;
+        for to in ["private", "public"] {
+            Mock::given(path(format!("/to-{to}")))
+                .respond_with(ResponseTemplate::new(302).insert_header("location", format!("{}/{to}", other.uri())))
+                .mount(&page)
+                .await;
+        }
+
+        let loader = ResourceLoader::new(LoaderConfig::default()).unwrap();
+        let page_url = Url::parse(&page.uri()).unwrap();
+        let request = |p: &str, mode: RequestMode| {
+            let mut request = Request::get(page_url.join(p).unwrap());
+            request.origin = Some(Origin::from_url(&page_url));
+            request.mode = mode;
+            request
+        };
+
+        // Same-origin at first, but not once redirected
+        assert!(matches!(
+            loader.fetch(request("/to-public", RequestMode::SameOrigin)).await,
+            Err(NetError::CorsError(_))
+        ));
+        assert!(matches!(
+            loader.fetch(request("/to-private", RequestMode::Cors)).await,
+            Err(NetError::CorsError(_))
+        ));
+        let response = loader.fetch(request("/to-public", RequestMode::Cors)).await.unwrap();
+        assert_eq!(response.url.as_str(), format!("{}/public", other.uri()));
+        assert!(response.ok());
+        // Left for the caller to make opaque
+        assert!(loader.fetch(request("/to-private", RequestMode::NoCors)).await.unwrap().ok());
+    }
 }
@@ -0,0 +1,159 @@
+//! Network record and replay for RustKit.
+//!
+//! For reproducible page loads, a loader can record the responses it gets
+//! from the network to a directory and later replay them from there
+//! instead of going to the network. Bodies are kept as they arrived, before
+//! any `Content-Encoding` was undone, so replayed responses go through the
+//! same decoding and are byte-exact.
+//!
+//! Requests are told apart by method, URL without its fragment, and body.
+//! Each recording is a `<key>.json` file of the response's URL, status and
+//! headers next to a `<key>.body` file of its body; recording the same
+//! request again replaces it. In replay mode, requests without a recording
+//! fail rather than reaching the network.
+
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{NetError, Request};
+
+/// Whether a loader records responses or replays them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkReplay {
+    /// Fetch from the network and save each response in this directory.
+    Record(PathBuf),
+    /// Answer requests from the responses saved in this directory.
+    Replay(PathBuf),
+}
+
+/// A response as saved next to its body.
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// The name a request's recording is saved under: an FNV-1a hash of its
+/// method, URL and body, which stays the same across runs and builds.
+fn recording_key(request: &Request) -> String {
+    let mut url = request.url.clone();
+    url.set_fragment(None);
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let parts: [&[u8]; 3] = [
+        request.method.as_str().as_bytes(),
+        url.as_str().as_bytes(),
+        request.body.as_deref().unwrap_or_default(),
+    ];
+    for part in parts {
+        for byte in part.iter().chain(&[0]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// Save the response to `request`, with its body as received.
+pub(crate) async fn record(dir: &Path, request: &Request, response: &rustkit_http::Response) {
+    let key = recording_key(request);
+    let recording = Recording {
+        url: response.url.to_string(),
+        status: response.status.as_u16(),
+        headers: response
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect(),
+    };
+    let result = async {
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(dir.join(format!("{key}.body")), &response.body).await?;
+        let json = serde_json::to_vec_pretty(&recording).map_err(std::io::Error::other)?;
+        tokio::fs::write(dir.join(format!("{key}.json")), json).await
+    }
+    .await;
+    match result {
+        Ok(()) => debug!(url = %request.url, key, "Response recorded"),
+        Err(e) => warn!(url = %request.url, error = %e, "Failed to record response"),
+    }
+}
+
+/// The recorded response to `request`. Fails if there is none.
+pub(crate) async fn replay(dir: &Path, request: &Request) -> Result<rustkit_http::Response, NetError> {
+    let key = recording_key(request);
+    let json = match tokio::fs::read(dir.join(format!("{key}.json"))).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(NetError::RequestFailed(format!("No recorded response for {} {}", request.method, request.url)));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let recording: Recording =
+        serde_json::from_slice(&json).map_err(|e| NetError::RequestFailed(format!("Invalid recording {key}: {e}")))?;
+    let body = tokio::fs::read(dir.join(format!("{key}.body"))).await?;
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in recording.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            headers.append(name, value);
+        }
+    }
+    debug!(url = %request.url, key, "Replaying recorded response");
+    Ok(rustkit_http::Response {
+        status: StatusCode::from_u16(recording.status)
+            .map_err(|e| NetError::RequestFailed(format!("Invalid recording {key}: {e}")))?,
+        version: Version::HTTP_11,
+        headers,
+        body: Bytes::from(body),
+        url: Url::parse(&recording.url).map_err(|e| NetError::InvalidUrl(e.to_string()))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recorded_responses_replay_byte_exact() {
+        let dir = std::env::temp_dir().join(format!("rustkit-replay-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let url = Url::parse("https://example.com/data#section").unwrap();
+        let request = Request::get(url.clone());
+
+        assert!(matches!(replay(&dir, &request).await, Err(NetError::RequestFailed(_))));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.append(http::header::SET_COOKIE, HeaderValue::from_static("a=1"));
+        headers.append(http::header::SET_COOKIE, HeaderValue::from_static("b=2"));
+        let body = Bytes::from_static(&[0x1f, 0x8b, 0x00, 0xff, 0x80]);
+        let response = rustkit_http::Response {
+            status: StatusCode::CREATED,
+            version: Version::HTTP_11,
+            headers: headers.clone(),
+            body: body.clone(),
+            url: url.clone(),
+        };
+        record(&dir, &request, &response).await;
+
+        // The fragment doesn't tell requests apart
+        let mut other_fragment = url.clone();
+        other_fragment.set_fragment(Some("other"));
+        let replayed = replay(&dir, &Request::get(other_fragment)).await.unwrap();
+        assert_eq!(replayed.status, StatusCode::CREATED);
+        assert_eq!(replayed.headers, headers);
+        assert_eq!(replayed.body, body);
+        assert_eq!(replayed.url, url);
+
+        // The method and body do
+        let post = Request::post(url.clone(), Bytes::from_static(b"x"));
+        assert!(replay(&dir, &post).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
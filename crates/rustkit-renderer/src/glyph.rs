@@ -7,6 +7,63 @@ use hashbrown::HashMap;
 #[cfg(windows)]
 use rustkit_text::{FontCollection as RkFontCollection, FontStretch as RkFontStretch, FontStyle as RkFontStyle, FontWeight as RkFontWeight};
 
+/// Largest font size drawn with subpixel antialiasing. Bigger text gains
+/// little from it, and its 3x oversampled bitmap would outgrow the atlas.
+pub const MAX_SUBPIXEL_FONT_SIZE: f32 = 64.0;
+
+/// FIR filter spreading each subpixel sample over its neighbours to keep
+/// color fringes down (FreeType's default LCD filter). Weights sum to 256.
+const LCD_FILTER: [u32; 5] = [8, 77, 86, 77, 8];
+
+/// How glyph edges are antialiased.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextAntialiasing {
+    /// One coverage value per pixel.
+    #[default]
+    Grayscale,
+    /// One coverage value per color channel, for horizontal RGB-striped
+    /// LCD panels. Falls back to grayscale where it can't be blended
+    /// correctly: transparent targets, transformed text and large text.
+    Subpixel,
+}
+
+/// How glyphs are fitted to the pixel grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextHinting {
+    /// Glyphs keep their fractional positions.
+    #[default]
+    None,
+    /// Baselines snap to whole pixels.
+    Slight,
+    /// Baselines, pen positions and advances snap to whole pixels.
+    Full,
+}
+
+impl TextHinting {
+    /// Snap a horizontal position or advance.
+    pub fn snap_x(self, x: f32) -> f32 {
+        match self {
+            TextHinting::Full => x.round(),
+            TextHinting::None | TextHinting::Slight => x,
+        }
+    }
+
+    /// Snap a vertical position.
+    pub fn snap_y(self, y: f32) -> f32 {
+        match self {
+            TextHinting::None => y,
+            TextHinting::Slight | TextHinting::Full => y.round(),
+        }
+    }
+}
+
+/// Text rendering quality options.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextRenderingOptions {
+    pub antialiasing: TextAntialiasing,
+    pub hinting: TextHinting,
+}
+
 /// Key for identifying a specific glyph.
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct GlyphKey {
@@ -15,6 +72,8 @@ pub struct GlyphKey {
     pub font_size: u32, // Fixed-point (size * 10)
     pub font_weight: u16,
     pub font_style: u8, // 0 = normal, 1 = italic
+    /// Rasterized with per-channel (LCD) coverage.
+    pub subpixel: bool,
 }
 
 /// Cached glyph entry.
@@ -29,6 +88,9 @@ pub struct GlyphEntry {
 }
 
 /// Glyph atlas for caching rasterized glyphs.
+///
+/// Texels hold coverage per channel: grayscale glyphs repeat one value in
+/// all four, subpixel glyphs store red, green and blue coverage.
 pub struct GlyphCache {
     atlas: wgpu::Texture,
     _atlas_view: wgpu::TextureView,
@@ -63,13 +125,13 @@ impl GlyphCache {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R8Unorm,
+            format: wgpu::TextureFormat::Rgba8Unorm,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
 
         // Initialize with transparent
-        let empty_data = vec![0u8; (atlas_size * atlas_size) as usize];
+        let empty_data = vec![0u8; (atlas_size * atlas_size * 4) as usize];
         queue.write_texture(
             wgpu::TexelCopyTextureInfo {
                 texture: &atlas,
@@ -80,7 +142,7 @@ impl GlyphCache {
             &empty_data,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(atlas_size),
+                bytes_per_row: Some(atlas_size * 4),
                 rows_per_image: Some(atlas_size),
             },
             wgpu::Extent3d {
@@ -139,6 +201,13 @@ impl GlyphCache {
         &self.bind_group
     }
 
+    /// GPU memory the atlas holds.
+    pub fn memory_usage(&self) -> crate::GpuMemoryUsage {
+        let mut usage = crate::GpuMemoryUsage::default();
+        usage.add_texture(&self.atlas);
+        usage
+    }
+
     /// Get or rasterize a glyph.
     pub fn get_or_rasterize(
         &mut self,
@@ -154,82 +223,29 @@ impl GlyphCache {
         self.rasterize_glyph_fallback(queue, key)
     }
 
-    /// Rasterize a glyph using platform-specific text rendering.
+    /// Rasterize a glyph and upload it to the atlas.
     fn rasterize_glyph_fallback(
         &mut self,
         queue: &wgpu::Queue,
         key: &GlyphKey,
     ) -> Option<GlyphEntry> {
         let font_size = key.font_size as f32 / 10.0;
-        
-        // Use platform-specific glyph rasterization
-        #[cfg(target_os = "macos")]
-        let raster_result = {
-            let italic = key.font_style == 1;
-            // Map font families for parity testing
-            let family = if key.font_family.is_empty() {
-                "Helvetica"
-            } else {
-                // Map ParityTest to Noto Sans for consistent cross-platform rendering
-                match key.font_family.as_str() {
-                    "ParityTest" | "'ParityTest'" => "Noto Sans",
-                    "Noto Sans" | "'Noto Sans'" => "Noto Sans",
-                    other => other
-                }
-            };
-            let rasterizer = rustkit_text::macos::GlyphRasterizer::with_style(
-                family,
-                font_size,
-                key.font_weight,
-                italic,
-            );
-            rasterizer.rasterize_char(key.codepoint)
-        };
-        
-        #[cfg(windows)]
-        let raster_result = {
-            // Windows fallback - use simple placeholder
-            let (glyph_width, glyph_height) = estimate_glyph_size(key.codepoint, font_size);
-            let glyph_width = glyph_width.max(1).min(256);
-            let glyph_height = glyph_height.max(1).min(256);
-            
-            let mut bitmap = vec![0u8; (glyph_width * glyph_height) as usize];
-            if key.codepoint.is_ascii_graphic() || key.codepoint.is_alphabetic() {
-                for y in 0..glyph_height {
-                    for x in 0..glyph_width {
-                        let idx = (y * glyph_width + x) as usize;
-                        let border = x == 0 || x == glyph_width - 1 || y == 0 || y == glyph_height - 1;
-                        bitmap[idx] = if border { 255 } else { 200 };
-                    }
-                }
-            }
-            Some((bitmap, glyph_width, glyph_height, glyph_width as f32, 0.0f32, font_size * 0.8))
-        };
-        
-        #[cfg(not(any(target_os = "macos", windows)))]
-        let raster_result: Option<(Vec<u8>, u32, u32, f32, f32, f32)> = {
-            // Fallback for other platforms
-            let (glyph_width, glyph_height) = estimate_glyph_size(key.codepoint, font_size);
-            let glyph_width = glyph_width.max(1).min(256);
-            let glyph_height = glyph_height.max(1).min(256);
-            
-            let mut bitmap = vec![0u8; (glyph_width * glyph_height) as usize];
-            if key.codepoint.is_ascii_graphic() || key.codepoint.is_alphabetic() {
-                for y in 0..glyph_height {
-                    for x in 0..glyph_width {
-                        let idx = (y * glyph_width + x) as usize;
-                        let border = x == 0 || x == glyph_width - 1 || y == 0 || y == glyph_height - 1;
-                        bitmap[idx] = if border { 255 } else { 200 };
-                    }
-                }
-            }
-            Some((bitmap, glyph_width, glyph_height, glyph_width as f32, 0.0f32, font_size * 0.8))
+
+        // Subpixel glyphs are rasterized at 3x and filtered down, so each
+        // color channel gets its own coverage sample
+        let (bitmap, glyph_width, glyph_height, advance, bearing_x, bearing_y) = if key.subpixel {
+            let (bitmap, width, height, advance, bearing_x, bearing_y) = rasterize_bitmap(key, font_size * 3.0)?;
+            let (rgba, width, height) = lcd_filter(&bitmap, width, height);
+            // The filter adds a pixel of spill on the left
+            (rgba, width, height, advance / 3.0, bearing_x / 3.0 - 1.0, bearing_y / 3.0)
+        } else {
+            let (bitmap, width, height, advance, bearing_x, bearing_y) = rasterize_bitmap(key, font_size)?;
+            let rgba = bitmap.iter().flat_map(|&coverage| [coverage; 4]).collect();
+            (rgba, width, height, advance, bearing_x, bearing_y)
         };
         
-        let (bitmap, glyph_width, glyph_height, advance, bearing_x, bearing_y) = raster_result?;
-        
-        let glyph_width = glyph_width.max(1).min(256);
-        let glyph_height = glyph_height.max(1).min(256);
+        let glyph_width = glyph_width.clamp(1, 256);
+        let glyph_height = glyph_height.clamp(1, 256);
 
         // Allocate space in the atlas
         let (atlas_x, atlas_y) = self.allocate_space(glyph_width + 2, glyph_height + 2)?;
@@ -249,7 +265,7 @@ impl GlyphCache {
             &bitmap,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(glyph_width),
+                bytes_per_row: Some(glyph_width * 4),
                 rows_per_image: Some(glyph_height),
             },
             wgpu::Extent3d {
@@ -337,6 +353,119 @@ impl GlyphCache {
     }
 }
 
+/// Rasterize a glyph's coverage bitmap using platform-specific text
+/// rendering. Returns the bitmap, its size, the advance and the bearings.
+fn rasterize_bitmap(key: &GlyphKey, font_size: f32) -> Option<(Vec<u8>, u32, u32, f32, f32, f32)> {
+    // Use platform-specific glyph rasterization
+    #[cfg(target_os = "macos")]
+    let raster_result = {
+        let italic = key.font_style == 1;
+        // Map font families for parity testing
+        let family = if key.font_family.is_empty() {
+            "Helvetica"
+        } else {
+            // Map ParityTest to Noto Sans for consistent cross-platform rendering
+            match key.font_family.as_str() {
+                "ParityTest" | "'ParityTest'" => "Noto Sans",
+                "Noto Sans" | "'Noto Sans'" => "Noto Sans",
+                other => other
+            }
+        };
+        let rasterizer = rustkit_text::macos::GlyphRasterizer::with_style(
+            family,
+            font_size,
+            key.font_weight,
+            italic,
+        );
+        rasterizer.rasterize_char(key.codepoint)
+    };
+    
+    #[cfg(windows)]
+    let raster_result = {
+        // Windows fallback - use simple placeholder
+        let (glyph_width, glyph_height) = estimate_glyph_size(key.codepoint, font_size);
+        let glyph_width = glyph_width.clamp(1, 256);
+        let glyph_height = glyph_height.clamp(1, 256);
+        
+        let mut bitmap = vec![0u8; (glyph_width * glyph_height) as usize];
+        if key.codepoint.is_ascii_graphic() || key.codepoint.is_alphabetic() {
+            for y in 0..glyph_height {
+                for x in 0..glyph_width {
+                    let idx = (y * glyph_width + x) as usize;
+                    let border = x == 0 || x == glyph_width - 1 || y == 0 || y == glyph_height - 1;
+                    bitmap[idx] = if border { 255 } else { 200 };
+                }
+            }
+        }
+        Some((bitmap, glyph_width, glyph_height, glyph_width as f32, 0.0f32, font_size * 0.8))
+    };
+    
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let raster_result: Option<(Vec<u8>, u32, u32, f32, f32, f32)> = {
+        // Fallback for other platforms
+        let (glyph_width, glyph_height) = estimate_glyph_size(key.codepoint, font_size);
+        let glyph_width = glyph_width.clamp(1, 256);
+        let glyph_height = glyph_height.clamp(1, 256);
+        
+        let mut bitmap = vec![0u8; (glyph_width * glyph_height) as usize];
+        if key.codepoint.is_ascii_graphic() || key.codepoint.is_alphabetic() {
+            for y in 0..glyph_height {
+                for x in 0..glyph_width {
+                    let idx = (y * glyph_width + x) as usize;
+                    let border = x == 0 || x == glyph_width - 1 || y == 0 || y == glyph_height - 1;
+                    bitmap[idx] = if border { 255 } else { 200 };
+                }
+            }
+        }
+        Some((bitmap, glyph_width, glyph_height, glyph_width as f32, 0.0f32, font_size * 0.8))
+    };
+
+    raster_result
+}
+
+/// Turn a coverage bitmap rasterized at 3x size into per-channel coverage
+/// at 1x: rows are averaged in threes and each channel takes the filtered
+/// subpixel sample under it. Returns RGBA texels (alpha is the strongest
+/// channel) with one pixel of filter spill on either side.
+fn lcd_filter(bitmap: &[u8], width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+    let (width, height) = (width as usize, height as usize);
+    let out_width = width.div_ceil(3) + 2;
+    let out_height = height.div_ceil(3);
+
+    let mut samples = vec![0u32; width * out_height];
+    for (y, row) in bitmap.chunks_exact(width).take(height).enumerate() {
+        for (x, &coverage) in row.iter().enumerate() {
+            samples[(y / 3) * width + x] += coverage as u32;
+        }
+    }
+    let sample = |row: usize, s: isize| -> u32 {
+        if s < 0 || s as usize >= width {
+            0
+        } else {
+            samples[row * width + s as usize] / 3
+        }
+    };
+
+    let mut rgba = Vec::with_capacity(out_width * out_height * 4);
+    for row in 0..out_height {
+        for x in 0..out_width {
+            let mut texel = [0u8; 4];
+            for (channel, value) in texel.iter_mut().take(3).enumerate() {
+                let center = (x as isize - 1) * 3 + channel as isize;
+                let sum: u32 = LCD_FILTER
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| weight * sample(row, center + k as isize - 2))
+                    .sum();
+                *value = (sum / 256).min(255) as u8;
+            }
+            texel[3] = texel[0].max(texel[1]).max(texel[2]);
+            rgba.extend_from_slice(&texel);
+        }
+    }
+    (rgba, out_width as u32, out_height as u32)
+}
+
 /// Estimate glyph size based on character and font size.
 #[allow(dead_code)]
 fn estimate_glyph_size(ch: char, font_size: f32) -> (u32, u32) {
@@ -367,6 +496,7 @@ mod tests {
             font_size: 160,
             font_weight: 400,
             font_style: 0,
+            subpixel: false,
         };
 
         let key2 = GlyphKey {
@@ -375,6 +505,7 @@ mod tests {
             font_size: 160,
             font_weight: 400,
             font_style: 0,
+            subpixel: false,
         };
 
         assert_eq!(key1, key2);
@@ -388,6 +519,7 @@ mod tests {
             font_size: 160,
             font_weight: 400,
             font_style: 0,
+            subpixel: false,
         };
 
         let key2 = GlyphKey {
@@ -396,6 +528,7 @@ mod tests {
             font_size: 160,
             font_weight: 400,
             font_style: 0,
+            subpixel: false,
         };
 
         assert_ne!(key1, key2);
@@ -411,4 +544,34 @@ mod tests {
         let (wide_w, _) = estimate_glyph_size('M', 16.0);
         assert!(narrow_w < wide_w);
     }
+
+    #[test]
+    fn test_lcd_filter_spreads_coverage_across_channels() {
+        // One lit column of subpixels, three rows tall
+        let width = 6;
+        let mut bitmap = vec![0u8; width * 3];
+        for row in 0..3 {
+            bitmap[row * width + 3] = 255;
+        }
+
+        let (rgba, out_width, out_height) = lcd_filter(&bitmap, width as u32, 3);
+        assert_eq!((out_width, out_height), (4, 1));
+        assert_eq!(rgba.len(), 16);
+
+        // Subpixel 3 is the red channel of pixel 2 (after the spill pixel)
+        let pixel = &rgba[8..12];
+        assert!(pixel[0] > pixel[1] && pixel[1] > pixel[2], "{pixel:?}");
+        assert_eq!(pixel[3], pixel[0]);
+        // The filter leaks into the blue channel of the pixel before
+        assert!(rgba[4 + 2] > 0 && rgba[4 + 1] > 0);
+        assert_eq!(&rgba[0..4], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_hinting_snaps_positions() {
+        assert_eq!(TextHinting::None.snap_y(10.4), 10.4);
+        assert_eq!(TextHinting::Slight.snap_y(10.4), 10.0);
+        assert_eq!(TextHinting::Slight.snap_x(10.6), 10.6);
+        assert_eq!(TextHinting::Full.snap_x(10.6), 11.0);
+    }
 }
@@ -43,20 +43,24 @@
 use bytemuck::{Pod, Zeroable};
 use hashbrown::HashMap;
 use rustkit_css::Color;
-use rustkit_layout::{BackgroundRepeat, BackgroundSize, DisplayCommand, Rect};
+use rustkit_layout::{background_tiles, transform, BackgroundRepeat, BackgroundSize, DisplayCommand, Rect};
 use std::sync::Arc;
 use thiserror::Error;
 use wgpu::util::DeviceExt;
 
 pub mod dither;
 mod glyph;
+mod memory;
 mod pipeline;
 pub mod screenshot;
 mod shaders;
+mod tiles;
 
 pub use glyph::*;
+pub use memory::{texture_bytes, GpuMemoryUsage};
 pub use pipeline::*;
 pub use screenshot::*;
+pub use tiles::{commands_from, commands_within, tiles_covering, TileCache, TileCoord, TILE_SIZE};
 
 // ==================== Errors ====================
 
@@ -281,6 +285,15 @@ impl TextureCache {
     pub fn remove(&mut self, key: &str) {
         self.textures.remove(key);
     }
+
+    /// GPU memory the cached textures hold.
+    pub fn memory_usage(&self) -> GpuMemoryUsage {
+        let mut usage = GpuMemoryUsage::default();
+        for cached in self.textures.values() {
+            usage.add_texture(&cached.texture);
+        }
+        usage
+    }
 }
 
 // ==================== Renderer ====================
@@ -300,6 +313,10 @@ pub struct Renderer {
     blit_pipeline: wgpu::RenderPipeline,
     // Blit pipeline for Rgba8Unorm targets (for blitting to filter textures)
     blit_pipeline_rgba: wgpu::RenderPipeline,
+    // Pipeline compositing opacity groups onto their parent target
+    composite_pipeline: wgpu::RenderPipeline,
+    // Pipeline compositing rounded clip groups through their mask
+    rounded_clip_pipeline: pipeline::RoundedClipPipeline,
 
     // Backdrop filter pipelines (compute shaders for blur + color filters)
     backdrop_filter_pipelines: pipeline::BackdropFilterPipelines,
@@ -307,6 +324,15 @@ pub struct Renderer {
     // GPU gradient pipeline
     gradient_pipeline: pipeline::GradientPipeline,
 
+    // Subpixel (LCD) text pipelines
+    subpixel_text_pipelines: pipeline::SubpixelTextPipelines,
+
+    // Text rendering quality
+    text_rendering: TextRenderingOptions,
+    /// Whether the target is composited over other content, where subpixel
+    /// text would fringe.
+    transparent_target: bool,
+
     /// Enable GPU gradient rendering (controlled by RUSTKIT_GPU_GRADIENTS env var)
     /// When disabled, uses cell-by-cell rendering for gradients
     gpu_gradients_enabled: bool,
@@ -321,6 +347,9 @@ pub struct Renderer {
     color_indices: Vec<u32>,
     texture_vertices: Vec<TextureVertex>,
     texture_indices: Vec<u32>,
+    // Glyphs with per-channel coverage, drawn with the subpixel text pipelines
+    subpixel_text_vertices: Vec<TextureVertex>,
+    subpixel_text_indices: Vec<u32>,
 
     // GPU gradient queues for batched rendering
     gradient_queue: Vec<QueuedLinearGradient>,
@@ -333,6 +362,8 @@ pub struct Renderer {
     /// Stack of 2D transform matrices and their origins.
     /// Each entry is (matrix [a,b,c,d,e,f], origin (x,y)).
     transform_stack: Vec<([f32; 6], (f32, f32))>,
+    /// Composite-time transform and opacity per layer, indexed by layer id.
+    layer_properties: Vec<LayerProperties>,
 
     // Caches
     texture_cache: TextureCache,
@@ -350,8 +381,18 @@ pub struct Renderer {
     // Sampler for drawing filtered textures back to screen
     filter_sampler: wgpu::Sampler,
 
+    // Offscreen targets for opacity groups, one per nesting depth, sized
+    // to the viewport
+    opacity_group_textures: Vec<(wgpu::Texture, wgpu::TextureView)>,
+    opacity_group_size: (u32, u32),
+    /// Number of opacity groups currently being drawn into.
+    opacity_group_depth: usize,
+
     // Surface format for creating compatible textures
     surface_format: wgpu::TextureFormat,
+
+    /// Vertex and index buffers uploaded since they were last taken.
+    buffer_uploads: memory::BufferUploads,
 }
 
 /// A stacking context for z-ordering.
@@ -361,6 +402,33 @@ pub struct StackingContext {
     pub rect: Rect,
 }
 
+/// Transform and opacity applied to a compositor layer when it is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerProperties {
+    /// 2D matrix applied around the center of the layer's bounds.
+    pub transform: [f32; 6],
+    /// Opacity the layer is composited with, as a group.
+    pub opacity: f32,
+}
+
+impl Default for LayerProperties {
+    fn default() -> Self {
+        Self {
+            transform: transform::IDENTITY,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// An offscreen group being drawn by `execute_with_opacity_groups`.
+#[derive(Debug, Clone, Copy)]
+struct OpacityGroup {
+    /// Opacity the group is composited with.
+    opacity: f32,
+    /// Rounded clip masking the group, in target pixels.
+    mask: Option<pipeline::RoundedClipParams>,
+}
+
 /// A queued linear gradient to be rendered with the GPU shader.
 /// Enable GPU gradients via RUSTKIT_GPU_GRADIENTS=1 environment variable.
 #[derive(Debug, Clone)]
@@ -505,6 +573,22 @@ impl Renderer {
             &texture_bind_group_layout,
         );
 
+        // Create pipeline for compositing opacity groups
+        let composite_pipeline = pipeline::create_composite_pipeline(
+            &device,
+            surface_format,
+            &uniform_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+
+        // Create pipeline for compositing rounded clip groups
+        let rounded_clip_pipeline = pipeline::create_rounded_clip_pipeline(
+            &device,
+            surface_format,
+            &uniform_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+
         // Create backdrop filter pipelines (compute shaders for blur + color filters)
         let backdrop_filter_pipelines = pipeline::create_backdrop_filter_pipelines(&device);
 
@@ -515,6 +599,13 @@ impl Renderer {
             &uniform_bind_group_layout,
         );
 
+        let subpixel_text_pipelines = pipeline::create_subpixel_text_pipelines(
+            &device,
+            surface_format,
+            &uniform_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+
         // Create caches
         let texture_cache = TextureCache::new(&device, texture_bind_group_layout.clone());
         let glyph_cache = GlyphCache::new(&device, &queue, texture_bind_group_layout.clone())?;
@@ -541,8 +632,13 @@ impl Renderer {
             _texture_pipeline_rgba: texture_pipeline_rgba,
             blit_pipeline,
             blit_pipeline_rgba,
+            composite_pipeline,
+            rounded_clip_pipeline,
             backdrop_filter_pipelines,
             gradient_pipeline,
+            subpixel_text_pipelines,
+            text_rendering: TextRenderingOptions::default(),
+            transparent_target: false,
             gpu_gradients_enabled,
             uniform_buffer,
             uniform_bind_group,
@@ -551,12 +647,15 @@ impl Renderer {
             color_indices: Vec::with_capacity(8192),
             texture_vertices: Vec::with_capacity(4096),
             texture_indices: Vec::with_capacity(8192),
+            subpixel_text_vertices: Vec::new(),
+            subpixel_text_indices: Vec::new(),
             gradient_queue: Vec::with_capacity(64),
             radial_gradient_queue: Vec::with_capacity(16),
             conic_gradient_queue: Vec::with_capacity(16),
             clip_stack: Vec::new(),
             stacking_contexts: Vec::new(),
             transform_stack: Vec::new(),
+            layer_properties: Vec::new(),
             texture_cache,
             glyph_cache,
             texture_bind_group_layout,
@@ -564,7 +663,11 @@ impl Renderer {
             intermediate_view: None,
             intermediate_size: (0, 0),
             filter_sampler,
+            opacity_group_textures: Vec::new(),
+            opacity_group_size: (0, 0),
+            opacity_group_depth: 0,
             surface_format,
+            buffer_uploads: memory::BufferUploads::default(),
         })
     }
 
@@ -642,10 +745,87 @@ impl Renderer {
         self.intermediate_view.as_ref().unwrap()
     }
 
+    /// Set text antialiasing and hinting.
+    pub fn set_text_rendering(&mut self, options: TextRenderingOptions) {
+        self.text_rendering = options;
+    }
+
+    /// Current text antialiasing and hinting.
+    pub fn text_rendering(&self) -> TextRenderingOptions {
+        self.text_rendering
+    }
+
+    /// Mark the target as transparent (composited over other content).
+    /// Text in transparent targets is always antialiased in grayscale.
+    pub fn set_transparent_target(&mut self, transparent: bool) {
+        self.transparent_target = transparent;
+    }
+
+    /// Set the composite-time properties of compositor layers, indexed by
+    /// the layer ids in [`DisplayCommand::PushLayer`]. Layers without an
+    /// entry are drawn untransformed and opaque.
+    pub fn set_layer_properties(&mut self, properties: &[LayerProperties]) {
+        self.layer_properties.clear();
+        self.layer_properties.extend_from_slice(properties);
+    }
+
+    /// Whether any vertices are waiting to be flushed.
+    fn has_batches(&self) -> bool {
+        !self.color_vertices.is_empty()
+            || !self.texture_vertices.is_empty()
+            || !self.subpixel_text_vertices.is_empty()
+    }
+
+    /// Antialiasing for text drawn now. Subpixel coverage is only right
+    /// when channels line up with the panel's subpixels over an opaque
+    /// backdrop, so transformed, oversized, transparent-target and
+    /// opacity group text falls back to grayscale.
+    fn text_antialiasing(&self, font_size: f32) -> TextAntialiasing {
+        let [a, b, c, d, _, _] = self.current_transform();
+        let axis_aligned = a == 1.0 && b == 0.0 && c == 0.0 && d == 1.0;
+        let opaque_backdrop = !self.transparent_target && self.opacity_group_depth == 0;
+        match self.text_rendering.antialiasing {
+            TextAntialiasing::Subpixel
+                if axis_aligned && opaque_backdrop && font_size <= MAX_SUBPIXEL_FONT_SIZE =>
+            {
+                TextAntialiasing::Subpixel
+            }
+            _ => TextAntialiasing::Grayscale,
+        }
+    }
+
+    /// Draw batched subpixel glyphs: darken by coverage, then add color.
+    fn draw_subpixel_text(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        if self.subpixel_text_vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
+            label: Some("Subpixel Text Vertex Buffer"),
+            contents: bytemuck::cast_slice(&self.subpixel_text_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
+            label: Some("Subpixel Text Index Buffer"),
+            contents: bytemuck::cast_slice(&self.subpixel_text_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, self.glyph_cache.bind_group(), &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for pipeline in [&self.subpixel_text_pipelines.mask, &self.subpixel_text_pipelines.color] {
+            render_pass.set_pipeline(pipeline);
+            render_pass.draw_indexed(0..self.subpixel_text_indices.len() as u32, 0, 0..1);
+        }
+    }
+
     /// Flush current batched vertices to the target without clearing.
     /// Used for incremental rendering when backdrop filters are present.
     fn flush_batches_to(&mut self, target: &wgpu::TextureView, clear: bool) {
-        if self.color_vertices.is_empty() && self.texture_vertices.is_empty() {
+        if !self.has_batches() {
             return;
         }
 
@@ -677,13 +857,13 @@ impl Renderer {
 
             // Draw solid colors
             if !self.color_vertices.is_empty() {
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Color Vertex Buffer"),
                     contents: bytemuck::cast_slice(&self.color_vertices),
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Color Index Buffer"),
                     contents: bytemuck::cast_slice(&self.color_indices),
                     usage: wgpu::BufferUsages::INDEX,
@@ -698,13 +878,13 @@ impl Renderer {
 
             // Draw textured quads
             if !self.texture_vertices.is_empty() {
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Texture Vertex Buffer"),
                     contents: bytemuck::cast_slice(&self.texture_vertices),
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Texture Index Buffer"),
                     contents: bytemuck::cast_slice(&self.texture_indices),
                     usage: wgpu::BufferUsages::INDEX,
@@ -717,6 +897,8 @@ impl Renderer {
                 render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
                 render_pass.draw_indexed(0..self.texture_indices.len() as u32, 0, 0..1);
             }
+
+            self.draw_subpixel_text(&mut render_pass);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -726,12 +908,14 @@ impl Renderer {
         self.color_indices.clear();
         self.texture_vertices.clear();
         self.texture_indices.clear();
+        self.subpixel_text_vertices.clear();
+        self.subpixel_text_indices.clear();
     }
 
     /// Flush batched vertices before rendering a GPU gradient.
     /// This ensures correct z-order: batched content renders before the gradient.
     fn flush_batches_for_gradient(&mut self, target: &wgpu::TextureView, clear: bool) {
-        if self.color_vertices.is_empty() && self.texture_vertices.is_empty() {
+        if !self.has_batches() {
             // Nothing to flush, but if this is the first call we still need to clear
             if clear {
                 let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -786,13 +970,13 @@ impl Renderer {
 
             // Draw solid colors
             if !self.color_vertices.is_empty() {
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Color Vertex Buffer"),
                     contents: bytemuck::cast_slice(&self.color_vertices),
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Color Index Buffer"),
                     contents: bytemuck::cast_slice(&self.color_indices),
                     usage: wgpu::BufferUsages::INDEX,
@@ -807,13 +991,13 @@ impl Renderer {
 
             // Draw textured quads
             if !self.texture_vertices.is_empty() {
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Texture Vertex Buffer"),
                     contents: bytemuck::cast_slice(&self.texture_vertices),
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Texture Index Buffer"),
                     contents: bytemuck::cast_slice(&self.texture_indices),
                     usage: wgpu::BufferUsages::INDEX,
@@ -826,6 +1010,8 @@ impl Renderer {
                 render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
                 render_pass.draw_indexed(0..self.texture_indices.len() as u32, 0, 0..1);
             }
+
+            self.draw_subpixel_text(&mut render_pass);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -835,6 +1021,8 @@ impl Renderer {
         self.color_indices.clear();
         self.texture_vertices.clear();
         self.texture_indices.clear();
+        self.subpixel_text_vertices.clear();
+        self.subpixel_text_indices.clear();
     }
 
     /// Draw a textured quad from a filtered texture to the render target immediately.
@@ -884,13 +1072,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Filtered Quad Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Filtered Quad Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -1078,6 +1266,8 @@ impl Renderer {
         self.color_indices.clear();
         self.texture_vertices.clear();
         self.texture_indices.clear();
+        self.subpixel_text_vertices.clear();
+        self.subpixel_text_indices.clear();
         self.gradient_queue.clear();
         self.radial_gradient_queue.clear();
         self.conic_gradient_queue.clear();
@@ -1101,7 +1291,16 @@ impl Renderer {
             )
         });
 
-        if has_blur_filters {
+        // Check if there are any subtrees to composite with group opacity or
+        // a rounded clip
+        let has_opacity_groups = commands.iter().any(|cmd| {
+            self.group_opacity(cmd).is_some() || matches!(cmd, DisplayCommand::PushRoundedClip { .. })
+        });
+
+        if has_opacity_groups {
+            // Use opacity group path - render groups offscreen and composite them
+            self.execute_with_opacity_groups(commands, target)?;
+        } else if has_blur_filters {
             // Use GPU blur path - render to intermediate texture with GPU blur processing
             self.execute_with_gpu_blur(commands, target)?;
         } else if has_gpu_gradients {
@@ -1118,6 +1317,257 @@ impl Renderer {
         Ok(())
     }
 
+    /// The opacity of the group `cmd` starts, if it starts one.
+    fn group_opacity(&self, cmd: &DisplayCommand) -> Option<f32> {
+        let opacity = match cmd {
+            DisplayCommand::PushOpacity(opacity) => *opacity,
+            DisplayCommand::PushLayer { id, .. } => self.layer_properties.get(*id)?.opacity,
+            _ => return None,
+        };
+        (opacity < 1.0).then_some(opacity.max(0.0))
+    }
+
+    /// Execute commands, drawing each opacity group into an offscreen
+    /// target and compositing it onto its parent with the group's opacity,
+    /// so overlapping content within a group doesn't show through. Content
+    /// under a rounded clip is grouped the same way and composited through
+    /// the clip's antialiased mask.
+    ///
+    /// Gradients are drawn as batched quads and blur backdrop filters take
+    /// the approximate path here, since both GPU paths draw straight to
+    /// the final target.
+    fn execute_with_opacity_groups(
+        &mut self,
+        commands: &[DisplayCommand],
+        target: &wgpu::TextureView,
+    ) -> Result<(), RendererError> {
+        let gpu_gradients_enabled = std::mem::replace(&mut self.gpu_gradients_enabled, false);
+        self.clear_target(target, wgpu::Color::WHITE);
+
+        // Open groups, and for each open opacity, layer or rounded clip
+        // push whether it opened a group
+        let mut groups: Vec<OpacityGroup> = Vec::new();
+        let mut scopes: Vec<bool> = Vec::new();
+
+        for cmd in commands {
+            let opened = match cmd {
+                DisplayCommand::PushRoundedClip { rect, radius } => Some(OpacityGroup {
+                    opacity: 1.0,
+                    mask: Some(self.rounded_clip_mask(*rect, *radius)),
+                }),
+                cmd => self.group_opacity(cmd).map(|opacity| OpacityGroup { opacity, mask: None }),
+            };
+            let closes = match cmd {
+                DisplayCommand::PopOpacity | DisplayCommand::PopLayer | DisplayCommand::PopRoundedClip => {
+                    scopes.pop().unwrap_or(false)
+                }
+                _ => false,
+            };
+
+            if closes {
+                if let Some(group) = groups.pop() {
+                    self.close_opacity_group(groups.len(), group, target);
+                }
+            }
+
+            // Layer transforms apply to the group's content
+            self.process_command(cmd);
+
+            if matches!(
+                cmd,
+                DisplayCommand::PushOpacity(_) | DisplayCommand::PushLayer { .. } | DisplayCommand::PushRoundedClip { .. }
+            ) {
+                scopes.push(opened.is_some());
+            }
+            if let Some(group) = opened {
+                let parent = self.opacity_group_parent(groups.len(), target);
+                self.flush_batches_to(&parent, false);
+                let view = self.opacity_group_view(groups.len());
+                self.clear_target(&view, wgpu::Color::TRANSPARENT);
+                groups.push(group);
+                self.opacity_group_depth = groups.len();
+            }
+        }
+
+        // Close groups left open by an unbalanced list
+        while let Some(group) = groups.pop() {
+            self.close_opacity_group(groups.len(), group, target);
+        }
+        self.flush_batches_to(target, false);
+
+        self.gpu_gradients_enabled = gpu_gradients_enabled;
+        Ok(())
+    }
+
+    /// Flush the group at `depth` and composite it onto its parent.
+    fn close_opacity_group(&mut self, depth: usize, group: OpacityGroup, target: &wgpu::TextureView) {
+        let view = self.opacity_group_view(depth);
+        self.flush_batches_to(&view, false);
+        let parent = self.opacity_group_parent(depth, target);
+        self.composite_opacity_group(&view, &parent, group);
+        self.opacity_group_depth = depth;
+    }
+
+    /// A rounded clip in target pixels. Under rotation or skew the bounding
+    /// box of the clip is used, like for rect clips.
+    fn rounded_clip_mask(&self, rect: Rect, radius: rustkit_layout::BorderRadius) -> pipeline::RoundedClipParams {
+        let m = self.current_transform();
+        let bounds = transform::map_rect_bounds(m, rect);
+        let scale = (m[0] * m[3] - m[1] * m[2]).abs().sqrt();
+        pipeline::RoundedClipParams {
+            rect: [bounds.x, bounds.y, bounds.width, bounds.height],
+            radii: [
+                radius.top_left * scale,
+                radius.top_right * scale,
+                radius.bottom_right * scale,
+                radius.bottom_left * scale,
+            ],
+        }
+    }
+
+    /// The target the group at `depth` composites onto.
+    fn opacity_group_parent(&mut self, depth: usize, target: &wgpu::TextureView) -> wgpu::TextureView {
+        match depth {
+            0 => target.clone(),
+            depth => self.opacity_group_view(depth - 1),
+        }
+    }
+
+    /// Offscreen target for the opacity group at `depth`, created (or
+    /// recreated after a resize) as needed.
+    fn opacity_group_view(&mut self, depth: usize) -> wgpu::TextureView {
+        let (width, height) = self.viewport_size;
+        if self.opacity_group_size != (width, height) {
+            self.opacity_group_textures.clear();
+            self.opacity_group_size = (width, height);
+        }
+        while self.opacity_group_textures.len() <= depth {
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Opacity Group Texture"),
+                size: wgpu::Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.surface_format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.opacity_group_textures.push((texture, view));
+        }
+        self.opacity_group_textures[depth].1.clone()
+    }
+
+    /// Clear a target to `color`.
+    fn clear_target(&self, target: &wgpu::TextureView, color: wgpu::Color) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Clear Encoder"),
+        });
+        {
+            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Clear Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Draw a group's target over its parent with its opacity, through its
+    /// mask if it has one.
+    fn composite_opacity_group(&self, view: &wgpu::TextureView, parent: &wgpu::TextureView, group: OpacityGroup) {
+        let (vw, vh) = self.viewport_size;
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Opacity Group Bind Group"),
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.filter_sampler),
+                },
+            ],
+        });
+
+        if let Some(mask) = group.mask {
+            self.queue
+                .write_buffer(&self.rounded_clip_pipeline.uniform_buffer, 0, bytemuck::cast_slice(&[mask]));
+        }
+
+        let color = [group.opacity; 4];
+        let vertices = [
+            TextureVertex { position: [0.0, 0.0], tex_coords: [0.0, 0.0], color },
+            TextureVertex { position: [vw as f32, 0.0], tex_coords: [1.0, 0.0], color },
+            TextureVertex { position: [vw as f32, vh as f32], tex_coords: [1.0, 1.0], color },
+            TextureVertex { position: [0.0, vh as f32], tex_coords: [0.0, 1.0], color },
+        ];
+        let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
+            label: Some("Opacity Group Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
+            label: Some("Opacity Group Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Opacity Group Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Opacity Group Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: parent,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if group.mask.is_some() {
+                render_pass.set_pipeline(&self.rounded_clip_pipeline.pipeline);
+                render_pass.set_bind_group(2, &self.rounded_clip_pipeline.bind_group, &[]);
+            } else {
+                render_pass.set_pipeline(&self.composite_pipeline);
+            }
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            render_pass.set_bind_group(1, &bind_group, &[]);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..6, 0, 0..1);
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
     /// Execute commands with GPU blur support for backdrop filters.
     fn execute_with_gpu_blur(
         &mut self,
@@ -1143,8 +1593,9 @@ impl Renderer {
                     self.flush_batches_to(&intermediate_view, is_first_flush);
                     is_first_flush = false;
 
-                    // Apply GPU blur
-                    self.apply_gpu_blur(&intermediate_view, *rect, *radius);
+                    // Apply GPU blur where the filter lands in the target
+                    let rect = transform::map_rect_bounds(self.current_transform(), *rect);
+                    self.apply_gpu_blur(&intermediate_view, rect, *radius);
 
                     continue;
                 }
@@ -1155,7 +1606,7 @@ impl Renderer {
         }
 
         // Flush remaining batches to intermediate
-        if !self.color_vertices.is_empty() || !self.texture_vertices.is_empty() {
+        if self.has_batches() {
             self.flush_batches_to(&intermediate_view, is_first_flush);
         }
 
@@ -1184,7 +1635,9 @@ impl Renderer {
                 DisplayCommand::ConicGradient { .. }
             );
 
-            if is_gpu_gradient {
+            // The gradient shader draws axis-aligned rects, so transformed
+            // gradients take the batched path, whose quads are transformed
+            if is_gpu_gradient && self.current_transform() == transform::IDENTITY {
                 // Flush batched content FIRST (before gradient)
                 // This ensures children render before their parent's gradient
                 self.flush_batches_for_gradient(target, is_first_flush);
@@ -1199,7 +1652,7 @@ impl Renderer {
         }
 
         // Flush any remaining batched content
-        if !self.color_vertices.is_empty() || !self.texture_vertices.is_empty() {
+        if self.has_batches() {
             self.flush_batches_for_gradient(target, is_first_flush);
         }
 
@@ -1267,29 +1720,19 @@ impl Renderer {
         };
 
         // Normalize stops
-        let normalized_stops: Vec<(f32, rustkit_css::ColorF32)> = stops.iter().enumerate()
-            .map(|(i, stop)| {
-                let pos = match &stop.position {
-                    Some(p) => {
-                        if has_pixel_positions && repeating {
-                            match p {
-                                rustkit_css::StopPosition::Pixels(px) => *px / repeat_length_pixels,
-                                rustkit_css::StopPosition::Percent(pct) => *pct,
-                            }
-                        } else {
-                            match p {
-                                rustkit_css::StopPosition::Percent(pct) => *pct,
-                                rustkit_css::StopPosition::Pixels(px) => *px / (gradient_half_length * 2.0),
-                            }
-                        }
-                    }
-                    None => {
-                        if stops.len() == 1 { 0.5 } else { i as f32 / (stops.len() - 1) as f32 }
-                    }
-                };
-                (pos, rustkit_css::ColorF32::from_color(stop.color))
-            })
-            .collect();
+        let normalized_stops = rustkit_css::resolve_color_stops(stops, |p| {
+            if has_pixel_positions && repeating {
+                match p {
+                    rustkit_css::StopPosition::Pixels(px) => *px / repeat_length_pixels,
+                    rustkit_css::StopPosition::Percent(pct) => *pct,
+                }
+            } else {
+                match p {
+                    rustkit_css::StopPosition::Percent(pct) => *pct,
+                    rustkit_css::StopPosition::Pixels(px) => *px / (gradient_half_length * 2.0),
+                }
+            }
+        });
 
         // Render using GPU
         self.render_linear_gradient_gpu_with_clear(
@@ -1342,29 +1785,19 @@ impl Renderer {
         };
 
         // Normalize stops
-        let normalized_stops: Vec<(f32, rustkit_css::ColorF32)> = stops.iter().enumerate()
-            .map(|(i, stop)| {
-                let pos = match &stop.position {
-                    Some(p) => {
-                        if has_pixel_positions && repeating {
-                            match p {
-                                rustkit_css::StopPosition::Pixels(px) => *px / repeat_length_pixels,
-                                rustkit_css::StopPosition::Percent(pct) => *pct,
-                            }
-                        } else {
-                            match p {
-                                rustkit_css::StopPosition::Percent(pct) => *pct,
-                                rustkit_css::StopPosition::Pixels(px) => *px / rx.max(ry).max(0.001),
-                            }
-                        }
-                    }
-                    None => {
-                        if stops.len() == 1 { 0.5 } else { i as f32 / (stops.len() - 1) as f32 }
-                    }
-                };
-                (pos, rustkit_css::ColorF32::from_color(stop.color))
-            })
-            .collect();
+        let normalized_stops = rustkit_css::resolve_color_stops(stops, |p| {
+            if has_pixel_positions && repeating {
+                match p {
+                    rustkit_css::StopPosition::Pixels(px) => *px / repeat_length_pixels,
+                    rustkit_css::StopPosition::Percent(pct) => *pct,
+                }
+            } else {
+                match p {
+                    rustkit_css::StopPosition::Percent(pct) => *pct,
+                    rustkit_css::StopPosition::Pixels(px) => *px / rx.max(ry).max(0.001),
+                }
+            }
+        });
 
         // Render using GPU
         self.render_radial_gradient_gpu(
@@ -1394,20 +1827,10 @@ impl Renderer {
         }
 
         // Conic gradients use angular positions (0-360 degrees or 0-1 normalized)
-        let normalized_stops: Vec<(f32, rustkit_css::ColorF32)> = stops.iter().enumerate()
-            .map(|(i, stop)| {
-                let pos = match &stop.position {
-                    Some(p) => match p {
-                        rustkit_css::StopPosition::Percent(pct) => *pct,
-                        rustkit_css::StopPosition::Pixels(deg) => *deg / 360.0, // Degrees to 0-1
-                    },
-                    None => {
-                        if stops.len() == 1 { 0.5 } else { i as f32 / (stops.len() - 1) as f32 }
-                    }
-                };
-                (pos, rustkit_css::ColorF32::from_color(stop.color))
-            })
-            .collect();
+        let normalized_stops = rustkit_css::resolve_color_stops(stops, |p| match p {
+            rustkit_css::StopPosition::Percent(pct) => *pct,
+            rustkit_css::StopPosition::Pixels(deg) => *deg / 360.0, // Degrees to 0-1
+        });
 
         // Convert from_angle to radians
         let from_rad = from_angle.to_radians();
@@ -1536,13 +1959,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Blit Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Blit Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -1644,13 +2067,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Copy Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Copy Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -1738,6 +2161,28 @@ impl Renderer {
                 );
             }
 
+            DisplayCommand::TextRun {
+                segments,
+                color,
+                font_size,
+                font_family,
+                font_weight,
+                font_style,
+            } => {
+                for segment in segments {
+                    self.draw_text(
+                        &segment.text,
+                        segment.x,
+                        segment.y,
+                        *color,
+                        *font_size,
+                        font_family,
+                        *font_weight,
+                        *font_style,
+                    );
+                }
+            }
+
             DisplayCommand::TextDecoration {
                 x,
                 y,
@@ -1768,9 +2213,10 @@ impl Renderer {
                 rect,
                 size,
                 position,
+                offset,
                 repeat,
             } => {
-                self.draw_background_image(url, *rect, size, *position, repeat);
+                self.draw_background_image(url, *rect, size, *position, *offset, *repeat);
             }
 
             DisplayCommand::BoxShadow {
@@ -1879,6 +2325,16 @@ impl Renderer {
                 self.pop_clip();
             }
 
+            // The corners are masked when execute_with_opacity_groups
+            // composites the clipped content
+            DisplayCommand::PushRoundedClip { rect, .. } => {
+                self.push_clip(*rect);
+            }
+
+            DisplayCommand::PopRoundedClip => {
+                self.pop_clip();
+            }
+
             DisplayCommand::PushStackingContext { z_index, rect } => {
                 self.stacking_contexts.push(StackingContext {
                     z_index: *z_index,
@@ -2010,6 +2466,19 @@ impl Renderer {
                 self.pop_transform();
             }
 
+            DisplayCommand::PushLayer { id, rect } => {
+                let properties = self.layer_properties.get(*id).copied().unwrap_or_default();
+                let center = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+                self.push_transform(properties.transform, center);
+            }
+
+            DisplayCommand::PopLayer => {
+                self.pop_transform();
+            }
+
+            // Composited as groups by execute_with_opacity_groups
+            DisplayCommand::PushOpacity(_) | DisplayCommand::PopOpacity => {}
+
             DisplayCommand::GradientText {
                 text,
                 x,
@@ -2654,13 +3123,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Gradient Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Gradient Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -2801,13 +3270,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Gradient Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Gradient Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -2937,13 +3406,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Radial Gradient Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Radial Gradient Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -3067,13 +3536,13 @@ impl Renderer {
         ];
         let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
 
-        let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Conic Gradient Vertex Buffer"),
             contents: bytemuck::cast_slice(&vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
             label: Some("Conic Gradient Index Buffer"),
             contents: bytemuck::cast_slice(&indices),
             usage: wgpu::BufferUsages::INDEX,
@@ -3157,35 +3626,21 @@ impl Renderer {
 
         // For pixel-based repeating gradients, normalize stops to the repeat length (not full gradient)
         // For percentage-based gradients, normalize to 0-1
-        let mut normalized_stops: Vec<(f32, rustkit_css::ColorF32)> = Vec::with_capacity(stops.len());
-        for (i, stop) in stops.iter().enumerate() {
-            let pos = match &stop.position {
-                Some(p) => {
-                    if has_pixel_positions && repeating {
-                        // For pixel-based repeating gradients, normalize to repeat length
-                        match p {
-                            rustkit_css::StopPosition::Pixels(px) => *px / repeat_length_pixels,
-                            rustkit_css::StopPosition::Percent(pct) => *pct,
-                        }
-                    } else {
-                        // For non-repeating or percentage-based, normalize to 0-1 using gradient line
-                        match p {
-                            rustkit_css::StopPosition::Percent(pct) => *pct,
-                            rustkit_css::StopPosition::Pixels(px) => *px / (gradient_half_length * 2.0),
-                        }
-                    }
+        let normalized_stops = rustkit_css::resolve_color_stops(stops, |p| {
+            if has_pixel_positions && repeating {
+                // For pixel-based repeating gradients, normalize to repeat length
+                match p {
+                    rustkit_css::StopPosition::Pixels(px) => *px / repeat_length_pixels,
+                    rustkit_css::StopPosition::Percent(pct) => *pct,
                 }
-                None => {
-                    // Auto-position: distribute evenly
-                    if stops.len() == 1 {
-                        0.5
-                    } else {
-                        i as f32 / (stops.len() - 1) as f32
-                    }
+            } else {
+                // For non-repeating or percentage-based, normalize to 0-1 using gradient line
+                match p {
+                    rustkit_css::StopPosition::Percent(pct) => *pct,
+                    rustkit_css::StopPosition::Pixels(px) => *px / (gradient_half_length * 2.0),
                 }
-            };
-            normalized_stops.push((pos, rustkit_css::ColorF32::from_color(stop.color)));
-        }
+            }
+        });
 
         // For repeating gradients, the repeat length is 1.0 (since we normalized stops to it)
         // For non-repeating, use the last stop position
@@ -3430,17 +3885,7 @@ impl Renderer {
 
         // Normalize color stops using high-precision colors
         // For pixel positions, convert to normalized using the radial gradient length
-        let mut normalized_stops: Vec<(f32, rustkit_css::ColorF32)> = Vec::with_capacity(stops.len());
-        for (i, stop) in stops.iter().enumerate() {
-            let pos = match &stop.position {
-                Some(p) => p.to_normalized(radial_gradient_length),
-                None => {
-                    // Auto-position: distribute evenly
-                    if stops.len() == 1 { 0.5 } else { i as f32 / (stops.len() - 1) as f32 }
-                }
-            };
-            normalized_stops.push((pos, rustkit_css::ColorF32::from_color(stop.color)));
-        }
+        let normalized_stops = rustkit_css::resolve_color_stops(stops, |p| p.to_normalized(radial_gradient_length));
 
         // For repeating gradients, calculate repeat length
         let repeat_length = if repeating && !normalized_stops.is_empty() {
@@ -3554,25 +3999,11 @@ impl Renderer {
         let from_rad = (from_angle - 90.0).to_radians();
 
         // Normalize color stops using high-precision colors
-        // For conic gradients, positions are typically percentages (0-1) of the full sweep
-        // Pixel positions are treated as percentages for conic gradients
-        let mut normalized_stops: Vec<(f32, rustkit_css::ColorF32)> = Vec::with_capacity(stops.len());
-        for (i, stop) in stops.iter().enumerate() {
-            let pos = match &stop.position {
-                Some(p) => {
-                    // For conic gradients, use raw value as percentage
-                    // (pixel positions don't make sense for conic, treat them as normalized)
-                    match p {
-                        rustkit_css::StopPosition::Percent(pct) => *pct,
-                        rustkit_css::StopPosition::Pixels(px) => *px / 360.0, // Treat as degrees
-                    }
-                }
-                None => {
-                    if stops.len() == 1 { 0.5 } else { i as f32 / (stops.len() - 1) as f32 }
-                }
-            };
-            normalized_stops.push((pos, rustkit_css::ColorF32::from_color(stop.color)));
-        }
+        // Conic positions are fractions of the full sweep; pixel positions carry degrees
+        let normalized_stops = rustkit_css::resolve_color_stops(stops, |p| match p {
+            rustkit_css::StopPosition::Percent(pct) => *pct,
+            rustkit_css::StopPosition::Pixels(px) => *px / 360.0, // Treat as degrees
+        });
 
         // For repeating gradients, get the repeat length from the last stop
         let repeat_length = if repeating && !normalized_stops.is_empty() {
@@ -4010,6 +4441,8 @@ impl Renderer {
 
         // Get atlas size before the loop to avoid borrow issues
         let atlas_size = self.glyph_cache.atlas_size() as f32;
+        let subpixel = self.text_antialiasing(font_size) == TextAntialiasing::Subpixel;
+        let hinting = self.text_rendering.hinting;
 
         for ch in text.chars() {
             let key = GlyphKey {
@@ -4018,12 +4451,13 @@ impl Renderer {
                 font_size: (font_size * 10.0) as u32,
                 font_weight,
                 font_style,
+                subpixel,
             };
 
             // Clone the entry to avoid borrow issues
             if let Some(entry) = self.glyph_cache.get_or_rasterize(&self.device, &self.queue, &key) {
-                let glyph_x = cursor_x + entry.offset[0];
-                let glyph_y = y + entry.offset[1];
+                let glyph_x = hinting.snap_x(cursor_x + entry.offset[0]);
+                let glyph_y = hinting.snap_y(y + entry.offset[1]);
                 let glyph_w = (entry.tex_coords[2] - entry.tex_coords[0]) * atlas_size;
                 let glyph_h = (entry.tex_coords[3] - entry.tex_coords[1]) * atlas_size;
 
@@ -4033,9 +4467,14 @@ impl Renderer {
                 let (x2, y2) = self.transform_point(glyph_x + glyph_w, glyph_y + glyph_h);
                 let (x3, y3) = self.transform_point(glyph_x, glyph_y + glyph_h);
 
-                let base = self.texture_vertices.len() as u32;
+                let (vertices, indices) = if subpixel {
+                    (&mut self.subpixel_text_vertices, &mut self.subpixel_text_indices)
+                } else {
+                    (&mut self.texture_vertices, &mut self.texture_indices)
+                };
+                let base = vertices.len() as u32;
 
-                self.texture_vertices.extend_from_slice(&[
+                vertices.extend_from_slice(&[
                     TextureVertex {
                         position: [x0, y0],
                         tex_coords: [entry.tex_coords[0], entry.tex_coords[1]],
@@ -4058,12 +4497,12 @@ impl Renderer {
                     },
                 ]);
 
-                self.texture_indices.extend_from_slice(&[
+                indices.extend_from_slice(&[
                     base, base + 1, base + 2,
                     base, base + 2, base + 3,
                 ]);
 
-                cursor_x += entry.advance;
+                cursor_x += hinting.snap_x(entry.advance);
             } else {
                 // Fallback: advance by estimated width
                 cursor_x += font_size * 0.6;
@@ -4120,7 +4559,8 @@ impl Renderer {
         container: Rect,
         size: &BackgroundSize,
         position: (f32, f32),
-        repeat: &BackgroundRepeat,
+        offset: (f32, f32),
+        repeat: BackgroundRepeat,
     ) {
         // Get the texture to retrieve image dimensions
         let (image_width, image_height) = if let Some(cached) = self.texture_cache.get(url) {
@@ -4134,86 +4574,9 @@ impl Renderer {
             return;
         }
 
-        // Calculate the background image size based on size property
-        let (bg_width, bg_height) = size.compute_size(container, image_width, image_height);
-
-        if bg_width == 0.0 || bg_height == 0.0 {
-            return;
-        }
-
-        // Calculate the starting position based on position property
-        let start_x = container.x + (container.width - bg_width) * position.0;
-        let start_y = container.y + (container.height - bg_height) * position.1;
-
-        // Determine tiling based on repeat
-        let (tile_x, tile_y) = match repeat {
-            BackgroundRepeat::Repeat => (true, true),
-            BackgroundRepeat::RepeatX => (true, false),
-            BackgroundRepeat::RepeatY => (false, true),
-            BackgroundRepeat::NoRepeat => (false, false),
-            BackgroundRepeat::Space => (true, true), // TODO: proper spacing
-            BackgroundRepeat::Round => (true, true), // TODO: proper rounding
-        };
-
-        // Generate tile positions
-        if !tile_x && !tile_y {
-            // Single image - draw at the calculated position
-            self.draw_background_image_tile(url, Rect {
-                x: start_x,
-                y: start_y,
-                width: bg_width,
-                height: bg_height,
-            }, container);
-        } else {
-            // Tiled images
-            let x_start = if tile_x {
-                // Find the leftmost position that's visible
-                let tiles_left = ((start_x - container.x) / bg_width).ceil() as i32;
-                start_x - (tiles_left as f32 * bg_width)
-            } else {
-                start_x
-            };
-
-            let y_start = if tile_y {
-                let tiles_up = ((start_y - container.y) / bg_height).ceil() as i32;
-                start_y - (tiles_up as f32 * bg_height)
-            } else {
-                start_y
-            };
-
-            let mut y = y_start;
-            while y < container.y + container.height {
-                let mut x = x_start;
-                while x < container.x + container.width {
-                    let tile_rect = Rect {
-                        x,
-                        y,
-                        width: bg_width,
-                        height: bg_height,
-                    };
-
-                    // Only draw if visible within container
-                    if tile_rect.x + tile_rect.width > container.x
-                        && tile_rect.y + tile_rect.height > container.y
-                        && tile_rect.x < container.x + container.width
-                        && tile_rect.y < container.y + container.height
-                    {
-                        self.draw_background_image_tile(url, tile_rect, container);
-                    }
-
-                    if tile_x {
-                        x += bg_width;
-                    } else {
-                        break;
-                    }
-                }
-
-                if tile_y {
-                    y += bg_height;
-                } else {
-                    break;
-                }
-            }
+        let tiles = background_tiles(container, image_width, image_height, size, position, offset, repeat);
+        for tile in tiles {
+            self.draw_background_image_tile(url, tile, container);
         }
     }
 
@@ -4327,6 +4690,9 @@ impl Renderer {
 
     /// Push a clipping rectangle.
     fn push_clip(&mut self, rect: Rect) {
+        // Clips are kept in target coordinates, so they stay put when the
+        // content inside them is transformed
+        let rect = transform::map_rect_bounds(self.current_transform(), rect);
         let clip = if let Some(current) = self.clip_stack.last() {
             if let Some(intersected) = current.intersect(&rect) {
                 intersected
@@ -4344,9 +4710,19 @@ impl Renderer {
         self.clip_stack.pop();
     }
 
-    /// Get the current clip rectangle.
+    /// Get the current clip rectangle in the coordinates of content drawn
+    /// now. Under rotation or skew the clip isn't a rectangle there; its
+    /// bounding box is used, clipping less than it should.
     fn current_clip(&self) -> Option<Rect> {
-        self.clip_stack.last().copied()
+        let clip = *self.clip_stack.last()?;
+        let m = self.current_transform();
+        if m == transform::IDENTITY {
+            return Some(clip);
+        }
+        Some(match transform::invert(m) {
+            Some(inverse) => transform::map_rect_bounds(inverse, clip),
+            None => Rect::zero(),
+        })
     }
 
     /// Push a 2D transform matrix onto the stack.
@@ -4362,38 +4738,17 @@ impl Renderer {
     /// Get the current combined transform matrix.
     /// Returns identity matrix [1, 0, 0, 1, 0, 0] if no transforms are active.
     fn current_transform(&self) -> [f32; 6] {
-        if self.transform_stack.is_empty() {
-            return [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]; // Identity
-        }
-
-        // Compose all transforms on the stack
-        let mut result = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
-        for (matrix, origin) in &self.transform_stack {
-            // Apply origin offset: translate(-origin) * matrix * translate(origin)
-            // First, translate to origin
-            let t1 = [1.0, 0.0, 0.0, 1.0, -origin.0, -origin.1];
-            // Then the transform
-            let m = *matrix;
-            // Then translate back
-            let t2 = [1.0, 0.0, 0.0, 1.0, origin.0, origin.1];
-
-            // Compose: result = result * t1 * m * t2
-            let temp1 = multiply_matrices_2d(result, t1);
-            let temp2 = multiply_matrices_2d(temp1, m);
-            result = multiply_matrices_2d(temp2, t2);
-        }
-        result
+        // Compose all transforms on the stack; inner ones apply first
+        self.transform_stack
+            .iter()
+            .fold(transform::IDENTITY, |result, (matrix, origin)| {
+                transform::multiply(result, transform::around_origin(*matrix, *origin))
+            })
     }
 
     /// Apply the current transform to a point.
     fn transform_point(&self, x: f32, y: f32) -> (f32, f32) {
-        let m = self.current_transform();
-        // [a, b, c, d, e, f] where:
-        // x' = a*x + c*y + e
-        // y' = b*x + d*y + f
-        let x_prime = m[0] * x + m[2] * y + m[4];
-        let y_prime = m[1] * x + m[3] * y + m[5];
-        (x_prime, y_prime)
+        transform::apply(self.current_transform(), x, y)
     }
 
     /// Flush all batched vertices to the target.
@@ -4479,13 +4834,13 @@ impl Renderer {
 
             // Draw solid colors
             if !self.color_vertices.is_empty() {
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Color Vertex Buffer"),
                     contents: bytemuck::cast_slice(&self.color_vertices),
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Color Index Buffer"),
                     contents: bytemuck::cast_slice(&self.color_indices),
                     usage: wgpu::BufferUsages::INDEX,
@@ -4500,13 +4855,13 @@ impl Renderer {
 
             // Draw textured quads (images and glyphs)
             if !self.texture_vertices.is_empty() {
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Texture Vertex Buffer"),
                     contents: bytemuck::cast_slice(&self.texture_vertices),
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
                     label: Some("Texture Index Buffer"),
                     contents: bytemuck::cast_slice(&self.texture_indices),
                     usage: wgpu::BufferUsages::INDEX,
@@ -4519,6 +4874,8 @@ impl Renderer {
                 render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
                 render_pass.draw_indexed(0..self.texture_indices.len() as u32, 0, 0..1);
             }
+
+            self.draw_subpixel_text(&mut render_pass);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -4538,6 +4895,37 @@ impl Renderer {
     pub fn glyph_cache(&mut self) -> &mut GlyphCache {
         &mut self.glyph_cache
     }
+
+    /// GPU memory the renderer holds between frames: its image and glyph
+    /// caches, offscreen targets and uniform buffers. Raster tiles are
+    /// held by each view's [`TileCache`] and counted there.
+    pub fn memory_usage(&self) -> GpuMemoryUsage {
+        let mut usage = self.texture_cache.memory_usage();
+        usage += self.glyph_cache.memory_usage();
+        if let Some(texture) = &self.intermediate_texture {
+            usage.add_texture(texture);
+        }
+        for (texture, _) in &self.opacity_group_textures {
+            usage.add_texture(texture);
+        }
+        for buffer in [
+            &self.uniform_buffer,
+            &self.rounded_clip_pipeline.uniform_buffer,
+            &self.backdrop_filter_pipelines.uniform_buffer,
+            &self.gradient_pipeline.uniform_buffer,
+            &self.gradient_pipeline.stops_buffer,
+        ] {
+            usage.add_buffer(buffer);
+        }
+        usage
+    }
+
+    /// The vertex and index buffers draws have uploaded since the last
+    /// call. They're dropped once their frame is submitted, so this is
+    /// what a frame streams to the GPU rather than what stays there.
+    pub fn take_buffer_uploads(&self) -> GpuMemoryUsage {
+        self.buffer_uploads.take()
+    }
 }
 
 // ==================== Rect Extension ====================
@@ -4561,24 +4949,6 @@ impl RectExt for Rect {
     }
 }
 
-// ==================== Transform Helpers ====================
-
-/// Multiply two 2D affine matrices.
-/// Matrix format: [a, b, c, d, e, f] representing:
-/// | a c e |
-/// | b d f |
-/// | 0 0 1 |
-fn multiply_matrices_2d(a: [f32; 6], b: [f32; 6]) -> [f32; 6] {
-    [
-        a[0] * b[0] + a[2] * b[1],
-        a[1] * b[0] + a[3] * b[1],
-        a[0] * b[2] + a[2] * b[3],
-        a[1] * b[2] + a[3] * b[3],
-        a[0] * b[4] + a[2] * b[5] + a[4],
-        a[1] * b[4] + a[3] * b[5] + a[5],
-    ]
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
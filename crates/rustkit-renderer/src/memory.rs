@@ -0,0 +1,121 @@
+//! GPU memory accounting.
+//!
+//! wgpu doesn't say how much memory its resources take, so it's worked out
+//! from what was asked for: a texture takes the size of its texel blocks
+//! for every mip level, layer and sample, and a buffer its size. Drivers
+//! pad and align allocations, so the GPU holds somewhat more than counted.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use wgpu::util::DeviceExt;
+
+/// GPU memory held by a set of textures and buffers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GpuMemoryUsage {
+    /// Number of textures.
+    pub textures: usize,
+    /// Bytes the textures take.
+    pub texture_bytes: u64,
+    /// Number of buffers.
+    pub buffers: usize,
+    /// Bytes the buffers take.
+    pub buffer_bytes: u64,
+}
+
+impl GpuMemoryUsage {
+    /// Bytes of textures and buffers together.
+    pub fn total_bytes(&self) -> u64 {
+        self.texture_bytes + self.buffer_bytes
+    }
+
+    /// Count a texture.
+    pub fn add_texture(&mut self, texture: &wgpu::Texture) {
+        self.textures += 1;
+        self.texture_bytes += texture_memory(texture);
+    }
+
+    /// Count a buffer.
+    pub fn add_buffer(&mut self, buffer: &wgpu::Buffer) {
+        self.buffers += 1;
+        self.buffer_bytes += buffer.size();
+    }
+}
+
+impl std::ops::AddAssign for GpuMemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.textures += other.textures;
+        self.texture_bytes += other.texture_bytes;
+        self.buffers += other.buffers;
+        self.buffer_bytes += other.buffer_bytes;
+    }
+}
+
+/// Bytes one layer of a 2D texture of `format` takes at `width` by
+/// `height`, without mip levels or multisampling.
+pub fn texture_bytes(format: wgpu::TextureFormat, width: u32, height: u32) -> u64 {
+    let (block_width, block_height) = format.block_dimensions();
+    // Combined depth-stencil formats have no single block size
+    let block_size = format.block_copy_size(None).unwrap_or(4);
+    u64::from(width.div_ceil(block_width)) * u64::from(height.div_ceil(block_height)) * u64::from(block_size)
+}
+
+/// Bytes a texture takes, over its mip levels, layers and samples.
+fn texture_memory(texture: &wgpu::Texture) -> u64 {
+    let size = texture.size();
+    let levels: u64 = (0..texture.mip_level_count())
+        .map(|level| {
+            let level_size = size.mip_level_size(level, texture.dimension());
+            texture_bytes(texture.format(), level_size.width, level_size.height)
+                * u64::from(level_size.depth_or_array_layers)
+        })
+        .sum();
+    levels * u64::from(texture.sample_count())
+}
+
+/// Creates the vertex and index buffers draws upload, counting them until
+/// they're taken.
+#[derive(Debug, Default)]
+pub(crate) struct BufferUploads {
+    buffers: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+impl BufferUploads {
+    /// Create a buffer holding `descriptor`'s contents.
+    pub(crate) fn create(&self, device: &wgpu::Device, descriptor: &wgpu::util::BufferInitDescriptor<'_>) -> wgpu::Buffer {
+        self.buffers.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(descriptor.contents.len() as u64, Ordering::Relaxed);
+        device.create_buffer_init(descriptor)
+    }
+
+    /// The buffers created since the last call.
+    pub(crate) fn take(&self) -> GpuMemoryUsage {
+        GpuMemoryUsage {
+            buffers: self.buffers.swap(0, Ordering::Relaxed),
+            buffer_bytes: self.bytes.swap(0, Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_bytes() {
+        assert_eq!(texture_bytes(wgpu::TextureFormat::Bgra8Unorm, 800, 600), 800 * 600 * 4);
+        assert_eq!(texture_bytes(wgpu::TextureFormat::R8Unorm, 2048, 2048), 2048 * 2048);
+        assert_eq!(texture_bytes(wgpu::TextureFormat::Rgba16Float, 10, 10), 10 * 10 * 8);
+        // 4x4 blocks of 8 bytes, partial blocks rounded up
+        assert_eq!(texture_bytes(wgpu::TextureFormat::Bc1RgbaUnorm, 10, 10), 3 * 3 * 8);
+    }
+
+    #[test]
+    fn test_usage_adds_up() {
+        let mut usage = GpuMemoryUsage { textures: 1, texture_bytes: 100, ..Default::default() };
+        usage += GpuMemoryUsage { buffers: 2, buffer_bytes: 50, ..Default::default() };
+        assert_eq!(usage, GpuMemoryUsage { textures: 1, texture_bytes: 100, buffers: 2, buffer_bytes: 50 });
+        assert_eq!(usage.total_bytes(), 150);
+    }
+}
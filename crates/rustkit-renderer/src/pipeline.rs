@@ -356,6 +356,101 @@ pub fn create_texture_pipeline(
     })
 }
 
+/// The two passes of subpixel text rendering (see `shaders/subpixel_text.wgsl`).
+pub struct SubpixelTextPipelines {
+    /// Darkens the target by per-channel glyph coverage.
+    pub mask: wgpu::RenderPipeline,
+    /// Adds the text color weighted by per-channel glyph coverage.
+    pub color: wgpu::RenderPipeline,
+}
+
+/// Create the subpixel text pipelines.
+pub fn create_subpixel_text_pipelines(
+    device: &wgpu::Device,
+    surface_format: wgpu::TextureFormat,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+) -> SubpixelTextPipelines {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Subpixel Text Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shaders/subpixel_text.wgsl").into()),
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Subpixel Text Pipeline Layout"),
+        bind_group_layouts: &[uniform_bind_group_layout, texture_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    // Target alpha is left alone by both passes
+    let keep_alpha = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+
+    let create = |label: &str, entry_point: &str, color: wgpu::BlendComponent| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[TextureVertex::LAYOUT],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(entry_point),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: Some(wgpu::BlendState { color, alpha: keep_alpha }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    };
+
+    SubpixelTextPipelines {
+        mask: create(
+            "Subpixel Text Mask Pipeline",
+            "fs_mask",
+            wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                operation: wgpu::BlendOperation::Add,
+            },
+        ),
+        color: create(
+            "Subpixel Text Color Pipeline",
+            "fs_color",
+            wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        ),
+    }
+}
+
 /// Create the blit pipeline for copying RGBA textures.
 /// Unlike the texture pipeline (for glyph rendering), this properly samples all 4 channels.
 pub fn create_blit_pipeline(
@@ -363,6 +458,161 @@ pub fn create_blit_pipeline(
     surface_format: wgpu::TextureFormat,
     uniform_bind_group_layout: &wgpu::BindGroupLayout,
     texture_bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    // Use REPLACE blend (no blending) for proper texture copying
+    create_blit_pipeline_with_blend(
+        device,
+        "Blit Pipeline",
+        surface_format,
+        wgpu::BlendState::REPLACE,
+        uniform_bind_group_layout,
+        texture_bind_group_layout,
+    )
+}
+
+/// Create the pipeline compositing opacity groups onto their parent.
+///
+/// Group textures start transparent and are drawn with alpha blending, so
+/// they hold premultiplied color; the vertex color scales all four
+/// channels by the group's opacity.
+pub fn create_composite_pipeline(
+    device: &wgpu::Device,
+    surface_format: wgpu::TextureFormat,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    create_blit_pipeline_with_blend(
+        device,
+        "Composite Pipeline",
+        surface_format,
+        wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        uniform_bind_group_layout,
+        texture_bind_group_layout,
+    )
+}
+
+/// Rounded clip parameters uniform structure (must match rounded_clip.wgsl).
+/// Total size: 32 bytes (8 f32 values)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RoundedClipParams {
+    /// Clip rect in target pixels: x, y, width, height
+    pub rect: [f32; 4],
+    /// Corner radii in target pixels: top-left, top-right, bottom-right,
+    /// bottom-left
+    pub radii: [f32; 4],
+}
+
+/// Pipeline compositing a group through an antialiased rounded-rect mask.
+pub struct RoundedClipPipeline {
+    pub pipeline: wgpu::RenderPipeline,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// Create the pipeline compositing rounded clip groups onto their parent.
+/// Like the composite pipeline, but coverage outside the clip's rounded
+/// rect is masked out.
+pub fn create_rounded_clip_pipeline(
+    device: &wgpu::Device,
+    surface_format: wgpu::TextureFormat,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+) -> RoundedClipPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Rounded Clip Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shaders/rounded_clip.wgsl").into()),
+    });
+
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Rounded Clip Params Buffer"),
+        size: std::mem::size_of::<RoundedClipParams>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Rounded Clip Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Rounded Clip Bind Group"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        }],
+    });
+
+    // Group 0: viewport uniforms, group 1: group texture, group 2: clip
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Rounded Clip Pipeline Layout"),
+        bind_group_layouts: &[uniform_bind_group_layout, texture_bind_group_layout, &bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Rounded Clip Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[TextureVertex::LAYOUT],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    });
+
+    RoundedClipPipeline {
+        pipeline,
+        uniform_buffer,
+        bind_group,
+    }
+}
+
+fn create_blit_pipeline_with_blend(
+    device: &wgpu::Device,
+    label: &str,
+    surface_format: wgpu::TextureFormat,
+    blend: wgpu::BlendState,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("Blit Shader"),
@@ -376,7 +626,7 @@ pub fn create_blit_pipeline(
     });
 
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Blit Pipeline"),
+        label: Some(label),
         layout: Some(&pipeline_layout),
         vertex: wgpu::VertexState {
             module: &shader,
@@ -389,8 +639,7 @@ pub fn create_blit_pipeline(
             entry_point: Some("fs_main"),
             targets: &[Some(wgpu::ColorTargetState {
                 format: surface_format,
-                // Use REPLACE blend (no blending) for proper texture copying
-                blend: Some(wgpu::BlendState::REPLACE),
+                blend: Some(blend),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
             compilation_options: wgpu::PipelineCompilationOptions::default(),
@@ -0,0 +1,83 @@
+// Composites a group's target onto its parent through a rounded-rect mask
+// (overflow clipping of a box with border-radius). Same inputs as blit.wgsl,
+// plus the clip in group 2. Group content is premultiplied.
+
+struct Uniforms {
+    viewport_size: vec2<f32>,
+    _padding: vec2<f32>,
+};
+
+struct ClipParams {
+    // x, y, width, height in target pixels
+    rect: vec4<f32>,
+    // top-left, top-right, bottom-right, bottom-left
+    radii: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+@group(2) @binding(0)
+var<uniform> clip: ClipParams;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Convert from pixel coords to clip space (-1 to 1)
+    let x = in.position.x * 2.0 / uniforms.viewport_size.x - 1.0;
+    let y = 1.0 - in.position.y * 2.0 / uniforms.viewport_size.y;
+
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    out.tex_coords = in.tex_coords;
+    out.color = in.color;
+
+    return out;
+}
+
+// Signed distance from p to the rounded rect (negative inside)
+fn rounded_rect_distance(p: vec2<f32>) -> f32 {
+    let half_size = clip.rect.zw * 0.5;
+    let center = clip.rect.xy + half_size;
+    let q = p - center;
+
+    // Radius of the quadrant p is in
+    var radius = clip.radii.x;
+    if (q.x >= 0.0 && q.y < 0.0) {
+        radius = clip.radii.y;
+    } else if (q.x >= 0.0 && q.y >= 0.0) {
+        radius = clip.radii.z;
+    } else if (q.x < 0.0 && q.y >= 0.0) {
+        radius = clip.radii.w;
+    }
+    radius = min(radius, min(half_size.x, half_size.y));
+
+    let d = abs(q) - half_size + vec2<f32>(radius);
+    return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0) - radius;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // Antialias over one pixel across the edge
+    let coverage = 1.0 - smoothstep(-0.5, 0.5, rounded_rect_distance(in.clip_position.xy));
+    return tex_color * in.color * coverage;
+}
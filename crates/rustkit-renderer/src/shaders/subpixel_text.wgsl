@@ -0,0 +1,62 @@
+// Subpixel (LCD) text shader
+//
+// Glyph texels hold coverage per color channel, which ordinary alpha
+// blending can't apply. Text is drawn twice instead: fs_mask darkens the
+// target by each channel's coverage, then fs_color adds the text color
+// weighted the same way, giving dst * (1 - coverage) + color * coverage.
+
+struct Uniforms {
+    viewport_size: vec2<f32>,
+    _padding: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(1) @binding(0)
+var t_glyphs: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s_glyphs: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let x = in.position.x * 2.0 / uniforms.viewport_size.x - 1.0;
+    let y = 1.0 - in.position.y * 2.0 / uniforms.viewport_size.y;
+
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    out.tex_coords = in.tex_coords;
+    out.color = in.color;
+
+    return out;
+}
+
+fn coverage(in: VertexOutput) -> vec3<f32> {
+    return textureSample(t_glyphs, s_glyphs, in.tex_coords).rgb * in.color.a;
+}
+
+// Blended with (Zero, OneMinusSrc): dst *= 1 - coverage
+@fragment
+fn fs_mask(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(coverage(in), 0.0);
+}
+
+// Blended with (One, One): dst += color * coverage
+@fragment
+fn fs_color(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color.rgb * coverage(in), 0.0);
+}
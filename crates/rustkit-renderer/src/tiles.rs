@@ -0,0 +1,622 @@
+//! Raster tiles
+//!
+//! A document many viewports long is drawn in [`TILE_SIZE`] square tiles
+//! kept on the GPU, rather than executing its whole display list every
+//! frame. Scrolling composites the tiles already drawn and only rasterizes
+//! those coming into view, and a new display list redraws only the tiles
+//! its changes touch. Tiles drifting more than a viewport out of view are
+//! dropped.
+//!
+//! Changes are found by comparing the new list with the one the tiles were
+//! drawn from, command by command. Lists of different lengths, changes
+//! under a transform and changes to opacity or layer properties redraw
+//! every tile, as do changes to text rendering options.
+
+use hashbrown::HashMap;
+use rustkit_layout::{DisplayCommand, Rect};
+
+use crate::{GpuMemoryUsage, LayerProperties, RectExt, Renderer, RendererError, TextRenderingOptions, TextureVertex};
+
+/// Width and height of a tile, in target pixels.
+pub const TILE_SIZE: u32 = 512;
+
+/// A tile's place in the grid, in tiles from the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileCoord {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl TileCoord {
+    /// The part of the page the tile draws.
+    pub fn rect(self) -> Rect {
+        let size = TILE_SIZE as f32;
+        Rect::new(self.col as f32 * size, self.row as f32 * size, size, size)
+    }
+}
+
+/// The tiles overlapping `rect`, row by row.
+pub fn tiles_covering(rect: Rect) -> impl Iterator<Item = TileCoord> {
+    let size = TILE_SIZE as f32;
+    let (first_col, first_row) = ((rect.x / size).floor() as i32, (rect.y / size).floor() as i32);
+    let (end_col, end_row) = ((rect.right() / size).ceil() as i32, (rect.bottom() / size).ceil() as i32);
+    (first_row..end_row).flat_map(move |row| (first_col..end_col).map(move |col| TileCoord { col, row }))
+}
+
+/// A rasterized tile.
+struct Tile {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// The tiles of one view, and what they were drawn from.
+#[derive(Default)]
+pub struct TileCache {
+    tiles: HashMap<TileCoord, Tile>,
+    /// The display list the tiles were drawn from.
+    commands: Vec<DisplayCommand>,
+    layer_properties: Vec<LayerProperties>,
+    text_rendering: TextRenderingOptions,
+    /// Size of each image drawn, as uploaded when the tiles were drawn, or
+    /// `None` for images that weren't uploaded yet.
+    images: HashMap<String, Option<(u32, u32)>>,
+    /// Tiles the last frame rasterized.
+    rasterized: usize,
+}
+
+impl TileCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of tiles held.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Whether no tiles are held.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Number of tiles the last [`Renderer::execute_tiled`] rasterized.
+    pub fn rasterized(&self) -> usize {
+        self.rasterized
+    }
+
+    /// GPU memory the tiles hold.
+    pub fn memory_usage(&self) -> GpuMemoryUsage {
+        let mut usage = GpuMemoryUsage::default();
+        for tile in self.tiles.values() {
+            usage.add_texture(&tile.texture);
+        }
+        usage
+    }
+
+    /// Drop every tile.
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.commands.clear();
+        self.images.clear();
+    }
+
+    /// Drop the tiles `commands` draws differently than the list they were
+    /// drawn from, with `image_size` giving the size each image is uploaded
+    /// at now.
+    fn invalidate(
+        &mut self,
+        commands: &[DisplayCommand],
+        layer_properties: &[LayerProperties],
+        text_rendering: TextRenderingOptions,
+        image_size: impl Fn(&str) -> Option<(u32, u32)>,
+    ) {
+        let dirty = if layer_properties == self.layer_properties.as_slice() && text_rendering == self.text_rendering {
+            dirty_rects(&self.commands, commands)
+        } else {
+            None
+        };
+        let mut dirty = match dirty {
+            Some(dirty) if dirty.is_empty() => dirty,
+            dirty => {
+                self.commands = commands.to_vec();
+                self.layer_properties = layer_properties.to_vec();
+                self.text_rendering = text_rendering;
+                match dirty {
+                    Some(dirty) => dirty,
+                    None => {
+                        self.tiles.clear();
+                        Vec::new()
+                    }
+                }
+            }
+        };
+
+        // Images uploaded, or uploaded again at another size, since the
+        // tiles were drawn
+        let mut images = HashMap::new();
+        for command in commands {
+            let url = match command {
+                DisplayCommand::Image { url, .. } | DisplayCommand::BackgroundImage { url, .. } => url,
+                _ => continue,
+            };
+            let size = *images.entry(url.clone()).or_insert_with(|| image_size(url));
+            if self.images.get(url) != Some(&size) {
+                dirty.extend(command_bounds(command));
+            }
+        }
+        self.images = images;
+
+        if !dirty.is_empty() {
+            self.tiles
+                .retain(|coord, _| dirty.iter().all(|rect| rect.intersect(&coord.rect()).is_none()));
+        }
+    }
+}
+
+/// Whether `command` starts or ends a group of commands.
+fn is_group_boundary(command: &DisplayCommand) -> bool {
+    matches!(
+        command,
+        DisplayCommand::PushClip(_)
+            | DisplayCommand::PopClip
+            | DisplayCommand::PushRoundedClip { .. }
+            | DisplayCommand::PopRoundedClip
+            | DisplayCommand::PushStackingContext { .. }
+            | DisplayCommand::PopStackingContext
+            | DisplayCommand::PushTransform { .. }
+            | DisplayCommand::PopTransform
+            | DisplayCommand::PushLayer { .. }
+            | DisplayCommand::PopLayer
+            | DisplayCommand::PushOpacity(_)
+            | DisplayCommand::PopOpacity
+    )
+}
+
+/// How many transforms deep the commands after `command` are, given the
+/// depth before it.
+fn transform_depth(command: &DisplayCommand, depth: usize) -> usize {
+    match command {
+        DisplayCommand::PushTransform { .. } | DisplayCommand::PushLayer { .. } => depth + 1,
+        DisplayCommand::PopTransform | DisplayCommand::PopLayer => depth.saturating_sub(1),
+        _ => depth,
+    }
+}
+
+/// A rect containing everything `command` draws or clips, before any
+/// transform. `None` for commands changing everything up to the end of
+/// their group, whose extent isn't known.
+fn command_bounds(command: &DisplayCommand) -> Option<Rect> {
+    fn inflate(rect: Rect, by: f32) -> Rect {
+        Rect::new(rect.x - by, rect.y - by, rect.width + by * 2.0, rect.height + by * 2.0)
+    }
+    // Glyphs are never wider than 1em, and sit within 1em of the baseline
+    fn text(text: &str, x: f32, y: f32, font_size: f32) -> Rect {
+        let width = (text.chars().count() + 2) as f32 * font_size;
+        Rect::new(x - font_size, y - font_size * 2.0, width, font_size * 4.0)
+    }
+    fn points(points: &[(f32, f32)], width: f32) -> Rect {
+        let (mut min, mut max) = ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN));
+        for &(x, y) in points {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        if points.is_empty() {
+            return Rect::zero();
+        }
+        inflate(Rect::new(min.0, min.1, max.0 - min.0, max.1 - min.1), width)
+    }
+
+    Some(match command {
+        DisplayCommand::SolidColor(_, rect)
+        | DisplayCommand::RoundedRect { rect, .. }
+        | DisplayCommand::Border { rect, .. }
+        | DisplayCommand::BackgroundImage { rect, .. }
+        | DisplayCommand::BackdropFilter { rect, .. }
+        | DisplayCommand::LinearGradient { rect, .. }
+        | DisplayCommand::RadialGradient { rect, .. }
+        | DisplayCommand::ConicGradient { rect, .. }
+        | DisplayCommand::TextInput { rect, .. }
+        | DisplayCommand::Button { rect, .. }
+        | DisplayCommand::PushClip(rect)
+        | DisplayCommand::PushRoundedClip { rect, .. }
+        | DisplayCommand::PushStackingContext { rect, .. }
+        | DisplayCommand::FillRect { rect, .. }
+        | DisplayCommand::FillEllipse { rect, .. } => *rect,
+        DisplayCommand::Image { dest_rect, .. } => *dest_rect,
+        DisplayCommand::Text {
+            text: content,
+            x,
+            y,
+            font_size,
+            ..
+        } => text(content, *x, *y, *font_size),
+        DisplayCommand::TextRun {
+            segments, font_size, ..
+        } => segments
+            .iter()
+            .map(|segment| text(&segment.text, segment.x, segment.y, *font_size))
+            .reduce(|a, b| {
+                let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+                Rect::new(x, y, a.right().max(b.right()) - x, a.bottom().max(b.bottom()) - y)
+            })
+            .unwrap_or_else(Rect::zero),
+        DisplayCommand::GradientText {
+            text: content,
+            x,
+            y,
+            font_size,
+            rect,
+            ..
+        } => {
+            let glyphs = text(content, *x, *y, *font_size);
+            let (x, y) = (glyphs.x.min(rect.x), glyphs.y.min(rect.y));
+            Rect::new(x, y, glyphs.right().max(rect.right()) - x, glyphs.bottom().max(rect.bottom()) - y)
+        }
+        DisplayCommand::TextDecoration {
+            x,
+            y,
+            width,
+            thickness,
+            ..
+        } => inflate(Rect::new(*x, *y, *width, *thickness), *thickness * 2.0),
+        DisplayCommand::BoxShadow {
+            offset_x,
+            offset_y,
+            blur_radius,
+            spread_radius,
+            rect,
+            ..
+        } => inflate(
+            *rect,
+            offset_x.abs().max(offset_y.abs()) + spread_radius.abs() + blur_radius.abs() * 3.0,
+        ),
+        DisplayCommand::FocusRing { rect, width, offset, .. } => inflate(*rect, offset.max(0.0) + width),
+        DisplayCommand::Caret { x, y, height, .. } => Rect::new(x - 2.0, *y, 4.0, *height),
+        DisplayCommand::StrokeRect { rect, width, .. } => inflate(*rect, *width),
+        DisplayCommand::FillCircle { cx, cy, radius, .. } => {
+            Rect::new(cx - radius, cy - radius, radius * 2.0, radius * 2.0)
+        }
+        DisplayCommand::StrokeCircle {
+            cx, cy, radius, width, ..
+        } => inflate(Rect::new(cx - radius, cy - radius, radius * 2.0, radius * 2.0), *width),
+        DisplayCommand::Line {
+            x1, y1, x2, y2, width, ..
+        } => points(&[(*x1, *y1), (*x2, *y2)], *width),
+        DisplayCommand::Polyline { points: list, width, .. }
+        | DisplayCommand::StrokePolygon { points: list, width, .. } => points(list, *width),
+        DisplayCommand::FillPolygon { points: list, .. } => points(list, 0.0),
+        DisplayCommand::PopClip
+        | DisplayCommand::PopRoundedClip
+        | DisplayCommand::PopStackingContext
+        | DisplayCommand::PopTransform
+        | DisplayCommand::PopLayer
+        | DisplayCommand::PopOpacity => Rect::zero(),
+        DisplayCommand::PushTransform { .. } | DisplayCommand::PushLayer { .. } | DisplayCommand::PushOpacity(_) => {
+            return None
+        }
+    })
+}
+
+/// The rects `new` draws differently than `old`, or `None` if that's
+/// everywhere or unknown.
+fn dirty_rects(old: &[DisplayCommand], new: &[DisplayCommand]) -> Option<Vec<Rect>> {
+    if old.len() != new.len() {
+        return None;
+    }
+    let mut dirty = Vec::new();
+    let mut depth = 0;
+    for (before, after) in old.iter().zip(new) {
+        if before != after {
+            let same_kind = std::mem::discriminant(before) == std::mem::discriminant(after);
+            if depth > 0 || (!same_kind && (is_group_boundary(before) || is_group_boundary(after))) {
+                return None;
+            }
+            dirty.push(command_bounds(before)?);
+            dirty.push(command_bounds(after)?);
+        }
+        depth = transform_depth(after, depth);
+    }
+    Some(dirty)
+}
+
+/// The commands drawing into `tile`, moved so that the tile's corner is
+/// at the origin.
+fn tile_commands(commands: &[DisplayCommand], tile: Rect) -> Vec<DisplayCommand> {
+    commands_from(commands, (tile.x, tile.y), &[tile])
+}
+
+/// The commands drawing into any of `rects`, moved so that `origin` is at
+/// the target's top-left corner, as when drawing the page scrolled to it.
+pub fn commands_from(commands: &[DisplayCommand], origin: (f32, f32), rects: &[Rect]) -> Vec<DisplayCommand> {
+    let mut culled = vec![DisplayCommand::PushTransform {
+        matrix: [1.0, 0.0, 0.0, 1.0, -origin.0, -origin.1],
+        origin: (0.0, 0.0),
+    }];
+    culled.extend(commands_within(commands, rects));
+    culled.push(DisplayCommand::PopTransform);
+    culled
+}
+
+/// The commands drawing into any of `rects`. Commands under a transform
+/// are all kept, since they may be drawn anywhere.
+pub fn commands_within(commands: &[DisplayCommand], rects: &[Rect]) -> Vec<DisplayCommand> {
+    let mut depth = 0;
+    commands
+        .iter()
+        .filter(|command| {
+            depth = transform_depth(command, depth);
+            depth > 0
+                || is_group_boundary(command)
+                || command_bounds(command)
+                    .is_none_or(|bounds| rects.iter().any(|rect| bounds.intersect(rect).is_some()))
+        })
+        .cloned()
+        .collect()
+}
+
+impl Renderer {
+    /// Execute a display list through `tiles`, drawing the part of the page
+    /// at `scroll` to the target. Only tiles coming into view, or touched
+    /// by changes since the last call, are rasterized, and when other views
+    /// cover part of the target, only those overlapping the `visible` rects
+    /// of it.
+    pub fn execute_tiled(
+        &mut self,
+        tiles: &mut TileCache,
+        commands: &[DisplayCommand],
+        target: &wgpu::TextureView,
+        scroll: (f32, f32),
+        visible: Option<&[Rect]>,
+    ) -> Result<(), RendererError> {
+        let (width, height) = self.viewport_size;
+        // Whole pixels, so tiles are drawn texel for pixel
+        let viewport = Rect::new(scroll.0.round(), scroll.1.round(), width as f32, height as f32);
+
+        let texture_cache = &self.texture_cache;
+        tiles.invalidate(commands, &self.layer_properties, self.text_rendering, |url| {
+            texture_cache.get(url).map(|texture| (texture.width, texture.height))
+        });
+        let kept = Rect::new(
+            viewport.x - viewport.width,
+            viewport.y - viewport.height,
+            viewport.width * 3.0,
+            viewport.height * 3.0,
+        );
+        tiles.tiles.retain(|coord, _| coord.rect().intersect(&kept).is_some());
+
+        let seen = |coord: &TileCoord| {
+            visible.is_none_or(|rects| {
+                rects.iter().any(|rect| {
+                    let rect = Rect::new(rect.x + viewport.x, rect.y + viewport.y, rect.width, rect.height);
+                    rect.intersect(&coord.rect()).is_some()
+                })
+            })
+        };
+        let missing: Vec<TileCoord> = tiles_covering(viewport)
+            .filter(|coord| !tiles.tiles.contains_key(coord) && seen(coord))
+            .collect();
+        tiles.rasterized = 0;
+        if !missing.is_empty() {
+            self.set_viewport_size(TILE_SIZE, TILE_SIZE);
+            let mut result = Ok(());
+            for coord in missing {
+                let (texture, view) = self.create_tile_texture();
+                result = self.execute(&tile_commands(commands, coord.rect()), &view);
+                if result.is_err() {
+                    break;
+                }
+                let bind_group = self.texture_bind_group(&view);
+                tiles.tiles.insert(coord, Tile { texture, bind_group });
+                tiles.rasterized += 1;
+            }
+            self.set_viewport_size(width, height);
+            result?;
+        }
+
+        self.composite_tiles(tiles, viewport, target);
+        Ok(())
+    }
+
+    /// A texture to draw a tile into.
+    fn create_tile_texture(&self) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Tile Texture"),
+            size: wgpu::Extent3d {
+                width: TILE_SIZE,
+                height: TILE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    /// A bind group sampling `view`.
+    fn texture_bind_group(&self, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Tile Bind Group"),
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.filter_sampler),
+                },
+            ],
+        })
+    }
+
+    /// Draw the tiles in `viewport` to the target, over white.
+    fn composite_tiles(&self, tiles: &TileCache, viewport: Rect, target: &wgpu::TextureView) {
+        let visible: Vec<(TileCoord, &Tile)> = tiles_covering(viewport)
+            .filter_map(|coord| Some((coord, tiles.tiles.get(&coord)?)))
+            .collect();
+        let mut vertices = Vec::with_capacity(visible.len() * 4);
+        let mut indices: Vec<u32> = Vec::with_capacity(visible.len() * 6);
+        for (coord, _) in &visible {
+            let rect = coord.rect();
+            let (x, y) = (rect.x - viewport.x, rect.y - viewport.y);
+            let color = [1.0; 4];
+            let base = vertices.len() as u32;
+            vertices.extend_from_slice(&[
+                TextureVertex { position: [x, y], tex_coords: [0.0, 0.0], color },
+                TextureVertex { position: [x + rect.width, y], tex_coords: [1.0, 0.0], color },
+                TextureVertex { position: [x + rect.width, y + rect.height], tex_coords: [1.0, 1.0], color },
+                TextureVertex { position: [x, y + rect.height], tex_coords: [0.0, 1.0], color },
+            ]);
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Tile Composite Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Tile Composite Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if !visible.is_empty() {
+                let vertex_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
+                    label: Some("Tile Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+                let index_buffer = self.buffer_uploads.create(&self.device, &wgpu::util::BufferInitDescriptor {
+                    label: Some("Tile Index Buffer"),
+                    contents: bytemuck::cast_slice(&indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                });
+
+                render_pass.set_pipeline(&self.composite_pipeline);
+                render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                for (i, (_, tile)) in visible.iter().enumerate() {
+                    let first = i as u32 * 6;
+                    render_pass.set_bind_group(1, &tile.bind_group, &[]);
+                    render_pass.draw_indexed(first..first + 6, 0, 0..1);
+                }
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustkit_css::Color;
+
+    fn fill(rect: Rect) -> DisplayCommand {
+        DisplayCommand::SolidColor(Color::new(0, 0, 0, 1.0), rect)
+    }
+
+    #[test]
+    fn test_tiles_covering() {
+        let tiles: Vec<_> = tiles_covering(Rect::new(100.0, 1000.0, 800.0, 600.0)).collect();
+        assert_eq!(
+            tiles,
+            [(0, 1), (1, 1), (0, 2), (1, 2), (0, 3), (1, 3)].map(|(col, row)| TileCoord { col, row })
+        );
+        assert_eq!(TileCoord { col: 1, row: 2 }.rect(), Rect::new(512.0, 1024.0, 512.0, 512.0));
+        assert_eq!(tiles_covering(Rect::new(0.0, 0.0, 512.0, 512.0)).count(), 1);
+    }
+
+    #[test]
+    fn test_dirty_rects() {
+        let old = vec![
+            fill(Rect::new(0.0, 0.0, 10.0, 10.0)),
+            DisplayCommand::PushClip(Rect::new(0.0, 0.0, 100.0, 100.0)),
+            fill(Rect::new(0.0, 2000.0, 10.0, 10.0)),
+            DisplayCommand::PopClip,
+        ];
+        assert_eq!(dirty_rects(&old, &old), Some(Vec::new()));
+
+        // A moved box dirties where it was and where it is
+        let mut new = old.clone();
+        new[2] = fill(Rect::new(0.0, 3000.0, 10.0, 10.0));
+        assert_eq!(
+            dirty_rects(&old, &new),
+            Some(vec![Rect::new(0.0, 2000.0, 10.0, 10.0), Rect::new(0.0, 3000.0, 10.0, 10.0)])
+        );
+
+        // Added commands, changed groups and changes under a transform
+        // dirty everything
+        assert_eq!(dirty_rects(&old, &old[..3]), None);
+        let mut regrouped = old.clone();
+        regrouped[1] = DisplayCommand::PushOpacity(0.5);
+        regrouped[3] = DisplayCommand::PopOpacity;
+        assert_eq!(dirty_rects(&old, &regrouped), None);
+        let transformed = |y| {
+            vec![
+                DisplayCommand::PushTransform {
+                    matrix: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                    origin: (0.0, 0.0),
+                },
+                fill(Rect::new(0.0, y, 10.0, 10.0)),
+                DisplayCommand::PopTransform,
+            ]
+        };
+        assert_eq!(dirty_rects(&transformed(0.0), &transformed(5.0)), None);
+    }
+
+    #[test]
+    fn test_tile_commands_culls_and_moves() {
+        let commands = vec![
+            fill(Rect::new(0.0, 0.0, 10.0, 10.0)),
+            DisplayCommand::PushClip(Rect::new(0.0, 0.0, 2000.0, 2000.0)),
+            fill(Rect::new(600.0, 600.0, 10.0, 10.0)),
+            DisplayCommand::PopClip,
+        ];
+        let culled = tile_commands(&commands, TileCoord { col: 1, row: 1 }.rect());
+        assert_eq!(culled.len(), 5);
+        assert!(matches!(
+            culled[0],
+            DisplayCommand::PushTransform {
+                matrix: [1.0, 0.0, 0.0, 1.0, -512.0, -512.0],
+                ..
+            }
+        ));
+        assert_eq!(culled[1..4], commands[1..4]);
+        assert!(matches!(culled[4], DisplayCommand::PopTransform));
+    }
+
+    #[test]
+    fn test_commands_within() {
+        let commands = vec![
+            fill(Rect::new(0.0, 0.0, 10.0, 10.0)),
+            fill(Rect::new(50.0, 50.0, 10.0, 10.0)),
+            DisplayCommand::PushTransform {
+                matrix: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+                origin: (0.0, 0.0),
+            },
+            fill(Rect::new(200.0, 200.0, 10.0, 10.0)),
+            DisplayCommand::PopTransform,
+        ];
+        let rects = [Rect::new(0.0, 0.0, 20.0, 20.0), Rect::new(100.0, 0.0, 20.0, 20.0)];
+        let kept = commands_within(&commands, &rects);
+        assert_eq!(kept[0], commands[0]);
+        assert_eq!(kept[1..], commands[2..]);
+    }
+}
@@ -153,21 +153,20 @@ impl BidiInfo {
         // Run the bidi algorithm
         let bidi_info = UBidiInfo::new(text, base_level);
 
-        // Get the paragraph info for the first (and typically only) paragraph
-        let para = &bidi_info.paragraphs[0];
-        let para_level = para.level;
+        // The first paragraph decides the base direction
+        let para_level = bidi_info.paragraphs[0].level;
 
         // Determine base direction from paragraph level
         let detected_direction = Direction::from_level(para_level.number());
 
-        // Get levels for each character
+        // Levels are indexed by byte offset
         let levels: Vec<Level> = bidi_info.levels.clone();
 
-        // Compute visual runs
-        let (_reordered_levels, level_runs) = bidi_info.visual_runs(para, para.range.clone());
-        let runs: Vec<BidiRun> = level_runs
-            .into_iter()
-            .map(|range| {
+        // Compute visual runs, one paragraph after another
+        let mut runs: Vec<BidiRun> = Vec::new();
+        for para in &bidi_info.paragraphs {
+            let (_reordered_levels, level_runs) = bidi_info.visual_runs(para, para.range.clone());
+            runs.extend(level_runs.into_iter().map(|range| {
                 // Get the level for this run from the first character in the range
                 let level = levels
                     .get(range.start)
@@ -180,8 +179,8 @@ impl BidiInfo {
                     direction: Direction::from_level(level),
                     level,
                 }
-            })
-            .collect();
+            }));
+        }
 
         Self {
             text: text.to_string(),
@@ -205,11 +204,7 @@ impl BidiInfo {
     ///
     /// Returns None if the offset is out of bounds.
     pub fn level_at(&self, byte_offset: usize) -> Option<u8> {
-        // Find the character index for this byte offset
-        let char_index = self.text[..byte_offset.min(self.text.len())]
-            .chars()
-            .count();
-        self.levels.get(char_index).map(|l| l.number())
+        self.levels.get(byte_offset).map(|l| l.number())
     }
 
     /// Get the direction at a byte offset.
@@ -241,6 +236,60 @@ impl BidiInfo {
     pub fn run_count(&self) -> usize {
         self.runs.len()
     }
+
+    /// Get the text in visual order.
+    ///
+    /// RTL runs are reversed and their paired punctuation mirrored, so the
+    /// result can be drawn glyph by glyph from left to right.
+    pub fn visual_text(&self) -> String {
+        if self.is_pure_ltr() {
+            return self.text.clone();
+        }
+        let mut out = String::with_capacity(self.text.len());
+        for run in &self.runs {
+            let run_text = run.text(&self.text);
+            match run.direction {
+                Direction::Ltr => out.push_str(run_text),
+                Direction::Rtl => out.extend(run_text.chars().rev().map(mirror_char)),
+            }
+        }
+        out
+    }
+}
+
+/// Get the text in visual order with every character forced to `direction`.
+///
+/// This is what `unicode-bidi: bidi-override` asks for: the bidi algorithm is
+/// bypassed and RTL text is simply drawn back to front.
+pub fn override_visual_text(text: &str, direction: Direction) -> String {
+    match direction {
+        Direction::Ltr => text.to_string(),
+        Direction::Rtl => text.chars().rev().map(mirror_char).collect(),
+    }
+}
+
+/// Get the mirrored glyph for a character displayed right-to-left.
+///
+/// Covers the paired brackets and quotation marks from the Unicode
+/// `Bidi_Mirroring_Glyph` property that show up in ordinary text.
+pub fn mirror_char(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '\u{00AB}' => '\u{00BB}', // « »
+        '\u{00BB}' => '\u{00AB}',
+        '\u{2039}' => '\u{203A}', // ‹ ›
+        '\u{203A}' => '\u{2039}',
+        '\u{2264}' => '\u{2265}', // ≤ ≥
+        '\u{2265}' => '\u{2264}',
+        _ => c,
+    }
 }
 
 /// Check if a character is a bidi format control character.
@@ -341,6 +390,39 @@ mod tests {
         assert_eq!(runs[0].text(text), "Hello");
     }
 
+    #[test]
+    fn test_level_at_uses_byte_offsets() {
+        // "a" followed by two-byte Hebrew letters
+        let text = "a \u{05E9}\u{05DC}";
+        let info = BidiInfo::with_base_direction(text, Some(Direction::Ltr));
+        assert_eq!(info.direction_at(0), Direction::Ltr);
+        assert_eq!(info.direction_at(2), Direction::Rtl);
+        assert_eq!(info.direction_at(4), Direction::Rtl);
+    }
+
+    #[test]
+    fn test_visual_text() {
+        assert_eq!(BidiInfo::new("Hello").visual_text(), "Hello");
+
+        // Hebrew "shalom" is stored first letter first but drawn right to left
+        let info = BidiInfo::new("\u{05E9}\u{05DC}\u{05D5}\u{05DD}");
+        assert_eq!(info.visual_text(), "\u{05DD}\u{05D5}\u{05DC}\u{05E9}");
+
+        // An embedded Latin word keeps its own order inside an RTL paragraph
+        let info = BidiInfo::with_base_direction("\u{05D0}\u{05D1} abc", Some(Direction::Rtl));
+        assert_eq!(info.visual_text(), "abc \u{05D1}\u{05D0}");
+
+        // Brackets in RTL runs are mirrored
+        let info = BidiInfo::with_base_direction("\u{05D0}(\u{05D1})", Some(Direction::Rtl));
+        assert_eq!(info.visual_text(), "(\u{05D1})\u{05D0}");
+    }
+
+    #[test]
+    fn test_override_visual_text() {
+        assert_eq!(override_visual_text("abc", Direction::Ltr), "abc");
+        assert_eq!(override_visual_text("a(b", Direction::Rtl), "b)a");
+    }
+
     #[test]
     fn test_arabic_text() {
         // Arabic: "marhaba" (hello)
@@ -125,6 +125,28 @@ impl Bounds {
     }
 }
 
+/// Appearance settings of the host platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SystemAppearance {
+    /// Whether the dark appearance is selected.
+    pub dark: bool,
+    /// Accent color picked in the system settings, as sRGB.
+    pub accent: Option<[u8; 3]>,
+}
+
+/// Read the platform's current appearance. Platforms without the notion
+/// report the light appearance and no accent color.
+pub fn system_appearance() -> SystemAppearance {
+    #[cfg(target_os = "macos")]
+    {
+        macos::system_appearance()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        SystemAppearance::default()
+    }
+}
+
 /// Errors that can occur in the ViewHost.
 #[derive(Error, Debug)]
 pub enum ViewHostError {
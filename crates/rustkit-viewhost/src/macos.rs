@@ -3,7 +3,7 @@
 //! This module provides the macOS-specific implementation of ViewHost,
 //! using NSView for rendering surfaces and TAO window handles.
 
-use crate::{Bounds, ViewHostError, ViewId};
+use crate::{Bounds, SystemAppearance, ViewHostError, ViewId};
 use raw_window_handle::RawWindowHandle;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
@@ -14,7 +14,7 @@ use cocoa::{
     base::{id, nil},
 };
 #[cfg(target_os = "macos")]
-use objc::{msg_send, sel, sel_impl};
+use objc::{class, msg_send, sel, sel_impl};
 
 /// macOS-specific view state
 #[cfg(target_os = "macos")]
@@ -350,6 +350,43 @@ impl MacOSViewHost {
     }
 }
 
+/// Read the dark mode setting and `NSColor.controlAccentColor`.
+#[cfg(target_os = "macos")]
+pub(crate) fn system_appearance() -> SystemAppearance {
+    use cocoa::foundation::NSString;
+    use std::ffi::CStr;
+
+    unsafe {
+        let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+        let key = NSString::alloc(nil).init_str("AppleInterfaceStyle");
+        let style: id = msg_send![defaults, stringForKey: key];
+        let _: () = msg_send![key, release];
+        let dark = style != nil && CStr::from_ptr(style.UTF8String()).to_bytes() == b"Dark";
+
+        // Available since macOS 10.14; older systems keep the theme's accent
+        let responds: bool = msg_send![class!(NSColor), respondsToSelector: sel!(controlAccentColor)];
+        let accent = if responds {
+            let color: id = msg_send![class!(NSColor), controlAccentColor];
+            let srgb: id = msg_send![class!(NSColorSpace), sRGBColorSpace];
+            let color: id = msg_send![color, colorUsingColorSpace: srgb];
+            if color != nil {
+                let r: f64 = msg_send![color, redComponent];
+                let g: f64 = msg_send![color, greenComponent];
+                let b: f64 = msg_send![color, blueComponent];
+                let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                Some([channel(r), channel(g), channel(b)])
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        debug!(dark, ?accent, "Read system appearance");
+        SystemAppearance { dark, accent }
+    }
+}
+
 #[cfg(not(target_os = "macos"))]
 pub struct MacOSViewHost;
 